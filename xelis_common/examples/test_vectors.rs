@@ -0,0 +1,169 @@
+// Generate the test vectors used by third-party SDKs to validate their serializers
+// Everything is deterministic: keys are derived from fixed seeds and ed25519 signatures don't use any randomness
+// cargo run -p xelis_common --example test_vectors > vectors.json
+
+use std::{borrow::Cow, collections::{BTreeMap, HashMap}};
+use serde_json::{json, Value};
+use xelis_common::{
+    api::{DataElement, DataValue},
+    block::{BlockHeader, BlockMiner, EXTRA_NONCE_SIZE},
+    config::{VERSION, XELIS_ASSET},
+    crypto::{
+        hash::{hash, Hash, Hashable},
+        key::{KeyPair, PrivateKey, Signature}
+    },
+    serializer::Serializer,
+    transaction::{SmartContractCall, Transaction, TransactionType, Transfer}
+};
+
+// keypair derived from the hash of its seed
+fn keypair_from_seed(seed: &str) -> KeyPair {
+    let private_key = PrivateKey::from_bytes(hash(seed.as_bytes()).as_bytes());
+    KeyPair::from_private_key(private_key)
+}
+
+fn create_tx(owner: &KeyPair, data: TransactionType, fee: u64, nonce: u64, sponsor: Option<&KeyPair>, expiration: Option<u64>, signature: Signature) -> Transaction {
+    let key = owner.get_public_key().clone();
+    let mut tx = match sponsor {
+        Some(sponsor) => Transaction::new_sponsored(key, data, fee, nonce, sponsor.get_public_key().clone(), signature),
+        None => Transaction::new(key, data, fee, nonce, signature)
+    };
+    if let Some(expiration) = expiration {
+        tx.set_expiration(expiration);
+    }
+    tx
+}
+
+// sign the transaction by its owner, then by its sponsor if any
+fn build_tx(owner: &KeyPair, data: TransactionType, fee: u64, nonce: u64, sponsor: Option<&KeyPair>, expiration: Option<u64>) -> Transaction {
+    // the signature is not part of the signing hash, so any value can be used to compute it
+    let unsigned = create_tx(owner, data, fee, nonce, sponsor, expiration, owner.sign(&[]));
+    let signature = owner.sign(unsigned.get_signing_hash().as_bytes());
+    let (_, data) = unsigned.consume();
+
+    let mut tx = create_tx(owner, data, fee, nonce, sponsor, expiration, signature);
+    if let Some(sponsor) = sponsor {
+        assert!(tx.sign_as_sponsor(sponsor));
+    }
+    assert!(tx.verify_signature());
+    tx
+}
+
+fn account_vector(keypair: &KeyPair) -> Value {
+    let key = keypair.get_public_key();
+    // only one field so its serialization doesn't depend on the map order
+    let mut fields = HashMap::new();
+    fields.insert(DataValue::String("id".to_owned()), DataElement::Value(Some(DataValue::U64(1337))));
+    let data = DataElement::Fields(fields);
+
+    json!({
+        "private_key": keypair.get_private_key().to_hex(),
+        "public_key": key.to_hex(),
+        "mainnet_address": key.to_address(true).to_string(),
+        "testnet_address": key.to_address(false).to_string(),
+        "integrated_address": {
+            "data": data,
+            "data_hex": data.to_hex(),
+            "address": key.to_address_with(true, data.clone()).to_string()
+        }
+    })
+}
+
+fn tx_vector(name: &str, tx: &Transaction) -> Value {
+    json!({
+        "name": name,
+        "hash": tx.hash(),
+        "signing_hash": tx.get_signing_hash(),
+        "sponsor_signing_hash": tx.get_sponsor().map(|_| tx.get_sponsor_signing_hash()),
+        "hex": tx.to_hex(),
+        "size": tx.size(),
+        "json": tx
+    })
+}
+
+fn block_vector(name: &str, header: &BlockHeader) -> Value {
+    let mut miner = BlockMiner::new(header.get_work_hash(), header.get_timestamp());
+    miner.nonce = header.get_nonce();
+    miner.extra_nonce = *header.get_extra_nonce();
+    miner.miner = Some(Cow::Borrowed(header.get_miner()));
+
+    json!({
+        "name": name,
+        "hash": header.hash(),
+        "hex": header.to_hex(),
+        "tips_hash": header.get_tips_hash(),
+        "txs_hash": header.get_txs_hash(),
+        "work": hex::encode(header.get_work()),
+        "work_hash": header.get_work_hash(),
+        "pow_input": miner.to_hex(),
+        "pow_hash": header.get_pow_hash(),
+        "json": header
+    })
+}
+
+fn main() {
+    let alice = keypair_from_seed("xelis test vectors alice");
+    let bob = keypair_from_seed("xelis test vectors bob");
+    let asset = hash(b"xelis test vectors asset");
+
+    let transactions = [
+        ("transfer", build_tx(&alice, TransactionType::Transfer(vec![Transfer {
+            amount: 100_000,
+            asset: XELIS_ASSET,
+            to: bob.get_public_key().clone(),
+            extra_data: None
+        }]), 1000, 0, None, None)),
+        ("multi_transfer_with_extra_data", build_tx(&alice, TransactionType::Transfer(vec![
+            Transfer {
+                amount: 1,
+                asset: XELIS_ASSET,
+                to: bob.get_public_key().clone(),
+                extra_data: Some(b"hello world".to_vec())
+            },
+            Transfer {
+                amount: 25,
+                asset: asset.clone(),
+                to: bob.get_public_key().clone(),
+                extra_data: None
+            }
+        ]), 1500, 1, None, None)),
+        ("burn", build_tx(&bob, TransactionType::Burn { asset: XELIS_ASSET, amount: 5000 }, 1000, 0, None, None)),
+        ("deploy_contract", build_tx(&alice, TransactionType::DeployContract("param value\nstore counter\n".to_owned()), 2000, 2, None, None)),
+        ("call_contract", build_tx(&bob, TransactionType::CallContract(SmartContractCall {
+            contract: hash(b"xelis test vectors contract"),
+            assets: BTreeMap::new(),
+            params: BTreeMap::from([("value".to_owned(), "42".to_owned())])
+        }), 1000, 1, None, None)),
+        ("sponsored_transfer", build_tx(&alice, TransactionType::Transfer(vec![Transfer {
+            amount: 500,
+            asset: XELIS_ASSET,
+            to: keypair_from_seed("xelis test vectors carol").get_public_key().clone(),
+            extra_data: None
+        }]), 1200, 3, Some(&bob), None)),
+        ("expiring_burn", build_tx(&bob, TransactionType::Burn { asset: XELIS_ASSET, amount: 100 }, 1000, 2, None, Some(1_000)))
+    ];
+
+    let genesis = BlockHeader::new(0, 0, 1_696_000_000_000, Vec::new(), [0u8; EXTRA_NONCE_SIZE], alice.get_public_key().clone(), Vec::new());
+    let mut header = BlockHeader::new(
+        0,
+        1,
+        1_696_000_015_000,
+        vec![genesis.hash()],
+        [7u8; EXTRA_NONCE_SIZE],
+        bob.get_public_key().clone(),
+        transactions.iter().map(|(_, tx)| tx.hash()).collect::<Vec<Hash>>()
+    );
+    header.set_nonce(42);
+
+    let vectors = json!({
+        "version": VERSION,
+        // keys in JSON are encoded as mainnet addresses
+        "network": "mainnet",
+        "accounts": [account_vector(&alice), account_vector(&bob)],
+        "transactions": transactions.iter().map(|(name, tx)| tx_vector(name, tx)).collect::<Vec<Value>>(),
+        "blocks": [block_vector("genesis", &genesis), block_vector("block_with_txs", &header)]
+    });
+
+    println!("{}", serde_json::to_string_pretty(&vectors).expect("serializable vectors"));
+}
+
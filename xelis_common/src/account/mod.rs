@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
 use crate::serializer::{Serializer, ReaderError, Reader, Writer};
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
 pub struct VersionedBalance {
     balance: u64,
     previous_topoheight: Option<u64>,
@@ -64,7 +65,7 @@ impl Serializer for VersionedBalance {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct VersionedNonce {
     nonce: u64,
     previous_topoheight: Option<u64>,
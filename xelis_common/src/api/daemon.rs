@@ -1,12 +1,14 @@
-use std::{borrow::Cow, collections::{HashSet, HashMap}, net::SocketAddr};
+use std::{borrow::Cow, collections::{HashSet, HashMap}, net::SocketAddr, sync::Arc};
 
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde_json::Value;
 
-use crate::{crypto::{hash::Hash, address::Address}, account::{VersionedBalance, VersionedNonce}, network::Network, block::Difficulty, transaction::Transaction};
+use crate::{crypto::{hash::Hash, address::Address, key::PublicKey}, account::{VersionedBalance, VersionedNonce}, network::Network, block::{BlockHeader, Difficulty, AuxCommitment, AuxCommitmentProof}, transaction::{Transaction, TransactionReceipt, TransactionType}};
 
-use super::DataHash;
+use super::{DataHash, ErrorCode, FeeEstimation};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BlockType {
     Sync,
     Side,
@@ -14,118 +16,251 @@ pub enum BlockType {
     Normal
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct BlockResponse<'a, T: Clone> {
     pub topoheight: Option<u64>,
     pub block_type: BlockType,
     pub difficulty: Difficulty,
     pub supply: Option<u64>,
+    // cumulative XELIS burned until this block
+    pub burned_supply: Option<u64>,
     pub reward: Option<u64>,
     pub cumulative_difficulty: Difficulty,
     pub total_fees: Option<u64>,
     pub total_size_in_bytes: usize,
+    // height at which an orphaned block is deleted by the node
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retained_until_height: Option<u64>,
     #[serde(flatten)]
     pub data: DataHash<'a, T>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetTopBlockParams {
     #[serde(default)]
     pub include_txs: bool
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetBlockAtTopoHeightParams {
     pub topoheight: u64,
     #[serde(default)]
     pub include_txs: bool
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetBlocksAtHeightParams {
     pub height: u64,
     #[serde(default)]
     pub include_txs: bool
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetBlockByHashParams<'a> {
     pub hash: Cow<'a, Hash>,
     #[serde(default)]
     pub include_txs: bool
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetBlockTemplateParams<'a> {
-    pub address: Cow<'a, Address>
+    pub address: Cow<'a, Address>,
+    // external chains to commit in the block, only allowed starting from the block version supporting it
+    #[serde(default)]
+    pub aux_commitment: Option<AuxCommitment>
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct GetBlockTemplateResult {
     pub template: String, // template is BlockMiner in hex format
     pub height: u64, // block height
+    // topoheight of the chain when the job was created, used by the miners to detect outdated jobs
+    #[serde(default)]
+    pub topoheight: u64,
     pub difficulty: Difficulty // difficulty required for valid block
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SubmitBlockParams {
     pub block_template: String, // hex: represent the BlockHeader (Block)
 }
 
-#[derive(Serialize, Deserialize)]
+// sent by a miner on its GetWork connection
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ReportHashrateParams {
+    // hashes per second
+    pub hashrate: f64
+}
+
+// sent by the daemon to the miners reporting their hashrate, when the difficulty changes
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct MinerDifficultyHint {
+    pub difficulty: Difficulty,
+    // last hashrate reported by the miner
+    pub hashrate: f64,
+    // average time in seconds for the miner to find a block at this difficulty
+    pub estimated_block_time: u64
+}
+
+// sent by the daemon to a miner when its block is rejected
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct BlockRejection {
+    // stable code when the cause is known
+    pub code: Option<ErrorCode>,
+    pub message: String
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MinerEntry {
+    pub address: Address,
+    pub worker: String,
+    // timestamp in seconds
+    pub connected_on: u64,
+    // last hashrate reported by the miner, if any
+    pub hashrate: Option<f64>,
+    pub blocks_accepted: usize,
+    pub blocks_rejected: usize,
+    // timestamp in seconds of the last block submitted
+    pub last_block_submitted: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetMinersResult {
+    // sum of the hashrates reported
+    pub total_hashrate: f64,
+    pub miners: Vec<MinerEntry>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetBalanceParams<'a> {
     pub address: Cow<'a, Address>,
     pub asset: Cow<'a, Hash>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetBalanceAtTopoHeightParams<'a> {
     pub address: Cow<'a, Address>,
     pub asset: Cow<'a, Hash>,
     pub topoheight: u64
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetNonceParams<'a> {
     pub address: Cow<'a, Address>,
     #[serde(default)]
     pub topoheight: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct HasNonceParams<'a> {
     pub address: Cow<'a, Address>,
     #[serde(default)]
     pub topoheight: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetAccountPendingParams<'a> {
+    pub address: Cow<'a, Address>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AccountPendingEntry<'a> {
+    pub hash: Cow<'a, Hash>,
+    pub nonce: u64,
+    pub fee: u64,
+    pub size: usize,
+    pub first_seen: u64,
+    // waiting for a previous nonce, it can't be included in a block yet
+    pub queued: bool
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetAccountPendingResult<'a> {
+    // nonce expected on chain for the next transaction
+    pub nonce: u64,
+    // maximum unconfirmed transactions accepted by the node for an account
+    pub max_pending: usize,
+    // ordered by nonce
+    pub transactions: Vec<AccountPendingEntry<'a>>
+}
+
+// state of a pending transaction in the mempool graph of its account
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolTxState {
+    // next nonce expected on chain, it can be included in the next block
+    Ready,
+    // included once the transactions with a lower nonce are included
+    Waiting,
+    // a previous nonce is missing in the mempool
+    Blocked,
+    // its nonce is already used on chain or by another pending transaction
+    Conflict
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MempoolGraphEntry<'a> {
+    #[serde(flatten)]
+    pub entry: AccountPendingEntry<'a>,
+    pub state: MempoolTxState,
+    // pending transaction with the previous nonce, it must be included first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Cow<'a, Hash>>,
+    // other pending transactions using the same nonce
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts_with: Vec<Cow<'a, Hash>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MempoolAccountGraph<'a> {
+    pub owner: Cow<'a, Address>,
+    // nonce expected on chain for the next transaction
+    pub nonce: u64,
+    // first nonce missing before some pending transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_nonce: Option<u64>,
+    // ordered by nonce
+    pub transactions: Vec<MempoolGraphEntry<'a>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetMempoolGraphResult<'a> {
+    pub accounts: Vec<MempoolAccountGraph<'a>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetNonceResult {
     pub topoheight: u64,
     #[serde(flatten)]
     pub version: VersionedNonce
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct HasNonceResult {
     pub exist: bool
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetLastBalanceResult {
     pub balance: VersionedBalance,
     pub topoheight: u64
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct GetInfoResult {
     pub height: u64,
     pub topoheight: u64,
     pub stableheight: u64,
     pub pruned_topoheight: Option<u64>,
     pub top_block_hash: Hash,
-    // Current XELIS circulating supply
+    // Current XELIS circulating supply, burned coins excluded
     pub circulating_supply: u64,
+    // Total XELIS emitted through block rewards
+    #[serde(default)]
+    pub emitted_supply: u64,
+    // Total XELIS destroyed using burn transactions
+    #[serde(default)]
+    pub burned_supply: u64,
     // Maximum supply of XELIS
     pub maximum_supply: u64,
     // Current difficulty at tips
@@ -143,18 +278,91 @@ pub struct GetInfoResult {
     pub network: Network
 }
 
-#[derive(Serialize, Deserialize)]
+// Draft of a transaction to estimate, in the same format as the wallet `build_transaction`
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct EstimateFeesParams {
+    pub owner: Address,
+    #[serde(flatten)]
+    pub tx_type: TransactionType,
+    // fee set in the transaction, the minimum fee for its size if not set
+    #[serde(default)]
+    pub fee: Option<u64>,
+    #[serde(default)]
+    pub sponsor: Option<Address>,
+    #[serde(default)]
+    pub expiration: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct EstimateFeesResult {
+    #[serde(flatten)]
+    pub estimation: FeeEstimation,
+    // minimum fee required by this node to include the transaction in its block templates
+    pub template_min_fee: u64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SubmitTransactionParams {
-    pub data: String // should be in hex format
+    pub data: String, // should be in hex format
+    // client reference (UUID) of the submission, submitting again with it returns the first transaction hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>
+}
+
+// Result of a transaction submission
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionAcceptanceStatus {
+    // added to mempool and broadcasted to peers
+    Accepted,
+    // waiting in mempool for the previous nonces of its owner
+    Queued,
+    // already in mempool or executed in a block
+    AlreadyKnown,
+    // invalid transaction, see the reason
+    Rejected
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SubmitTransactionResult {
+    pub hash: Hash,
+    pub status: TransactionAcceptanceStatus,
+    // why the transaction was rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    // stable code of the rejection reason, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WaitForConfirmationParams<'a> {
+    pub hash: Cow<'a, Hash>,
+    // how many blocks should be ordered on top of the executing block, including it
+    pub confirmations: u64,
+    // maximum time to wait in seconds
+    #[serde(default)]
+    pub timeout: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WaitForConfirmationResult {
+    // if the requested confirmations were reached before the timeout
+    pub confirmed: bool,
+    pub confirmations: u64,
+    // block in which the transaction was executed
+    pub executed_in_block: Option<Hash>,
+    // topoheight of the executing block
+    pub topoheight: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetTransactionParams<'a> {
     pub hash: Cow<'a, Hash>
 }
 
 // Direction is used for cache to knows from which context it got added
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum Direction {
     // We don't update it because it's In, we won't send back
     In,
@@ -200,7 +408,19 @@ impl Direction {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+// services advertised by a peer in its handshake
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeService {
+    // all the blocks since genesis are stored
+    Archive,
+    // any transaction can be retrieved by hash
+    TxIndex,
+    // transactions are relayed
+    Relay
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct PeerEntry<'a> {
     pub id: u64,
     pub addr: Cow<'a, SocketAddr>,
@@ -213,10 +433,22 @@ pub struct PeerEntry<'a> {
     pub pruned_topoheight: Option<u64>,
     pub peers: HashMap<SocketAddr, Direction>,
     pub cumulative_difficulty: Difficulty,
-    pub connected_on: u64
+    pub connected_on: u64,
+    // last time we received a packet from this peer
+    pub last_activity: u64,
+    // last round trip time in milliseconds measured with keep alive packets
+    pub round_trip_time: u64,
+    // persistent identity of the peer verified in its handshake, in hex
+    pub node_key: String,
+    // if its node key is configured as trusted
+    pub trusted: bool,
+    // services advertised in its handshake
+    pub services: Vec<NodeService>,
+    // blocks kept below its top if it prunes automatically
+    pub prune_depth: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct P2pStatusResult<'a> {
     pub peer_count: usize,
     pub max_peers: usize,
@@ -224,27 +456,175 @@ pub struct P2pStatusResult<'a> {
     pub our_topoheight: u64,
     pub best_topoheight: u64,
     pub median_topoheight: u64,
-    pub peer_id: u64
+    pub peer_id: u64,
+    // our node key in hex, to be configured as trusted by other nodes
+    pub node_key: String
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PacketMetricsEntry<'a> {
+    pub packet: Cow<'a, str>,
+    // packets received of this type
+    pub count: u64,
+    pub bytes: u64,
+    // packets whose handling failed
+    pub errors: u64,
+    // handling durations in microseconds
+    pub total_time: u64,
+    pub max_time: u64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PeerMetricsEntry {
+    pub addr: SocketAddr,
+    pub packets: u64,
+    pub bytes_in: usize,
+    pub bytes_out: usize,
+    // total handling duration of its packets in microseconds
+    pub handling_time: u64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetP2pMetricsResult<'a> {
+    pub packets: Vec<PacketMetricsEntry<'a>>,
+    // sorted by handling time, the most expensive peers first
+    pub peers: Vec<PeerMetricsEntry>
+}
+
+// traffic of the current UTC day or month
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct BandwidthPeriod {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    // maximum bytes (in + out) configured
+    pub cap: Option<u64>,
+    // chain sync is not served anymore until the next period
+    pub exceeded: bool
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PeerBandwidthEntry {
+    pub addr: SocketAddr,
+    pub bytes_in: usize,
+    pub bytes_out: usize
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetBandwidthResult {
+    // since the daemon started
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub daily: BandwidthPeriod,
+    pub monthly: BandwidthPeriod,
+    // connected peers, sorted by total traffic
+    pub peers: Vec<PeerBandwidthEntry>
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetSyncStatusResult {
+    // if we are downloading blocks from our peers
+    pub syncing: bool,
+    pub topoheight: u64,
+    // best topoheight announced by our peers
+    pub target_topoheight: u64,
+    // percentage of the target topoheight reached
+    pub progress: f64,
+    // average since the sync started, 0 if not syncing
+    pub blocks_per_second: f64,
+    // estimated seconds left, only known while syncing
+    pub eta: Option<u64>,
+    // peers used as sources of blocks since the sync started
+    pub peers: Vec<SocketAddr>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CrawledNodeEntry<'a> {
+    pub addr: SocketAddr,
+    // values below are known only once the node has been reached
+    pub peer_id: Option<u64>,
+    pub tag: Cow<'a, Option<String>>,
+    pub version: Cow<'a, Option<String>>,
+    pub topoheight: Option<u64>,
+    pub height: Option<u64>,
+    // if we could connect to it
+    pub reachable: bool,
+    // if it is currently one of our peers
+    pub connected: bool,
+    // last time it was announced by a peer or reached
+    pub last_seen: u64,
+    // last time we tried to reach it, 0 if never
+    pub last_probe: u64,
+    // addresses it announced to us in the peer exchange
+    pub peers: Cow<'a, HashSet<SocketAddr>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetNetworkTopologyResult<'a> {
+    // last time a crawl has been completed, 0 if none yet
+    pub last_crawl: u64,
+    // distinct nodes discovered, estimation of the network size
+    pub nodes_count: usize,
+    pub reachable_count: usize,
+    // version => nodes, only for the nodes reached
+    pub versions: HashMap<String, usize>,
+    // subnet (/16 for IPv4, /32 for IPv6) => nodes
+    // used to estimate the geographic spread of the network
+    pub subnets: HashMap<String, usize>,
+    pub nodes: Vec<CrawledNodeEntry<'a>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct ChainSplitAlert {
+    // reference node on which the chain is different
+    pub url: String,
+    // topoheight compared, stable on both nodes
+    pub topoheight: u64,
+    pub local_hash: Hash,
+    pub reference_hash: Hash,
+    // lowest topoheight at which both chains are different
+    pub fork_topoheight: u64,
+    // time in seconds at which the split has been detected
+    pub detected_at: u64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SplitMonitorNodeEntry<'a> {
+    pub url: Cow<'a, String>,
+    // last time the node has been checked, 0 if never
+    pub last_check: u64,
+    // stable topoheight compared during the last successful check
+    pub compared_topoheight: Option<u64>,
+    // set while our chain is different from the one of this node
+    pub alert: Option<Cow<'a, ChainSplitAlert>>,
+    // error of the last check if the node couldn't be compared
+    pub error: Option<Cow<'a, String>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetChainSplitStatusResult<'a> {
+    // our chain is different from at least one reference node
+    pub split_detected: bool,
+    pub nodes: Vec<SplitMonitorNodeEntry<'a>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetTopoHeightRangeParams {
     pub start_topoheight: Option<u64>,
     pub end_topoheight: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetHeightRangeParams {
     pub start_height: Option<u64>,
     pub end_height: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetTransactionsParams {
     pub tx_hashes: Vec<Hash>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct TransactionResponse<'a, T: Clone + AsRef<Transaction>> {
     // in which blocks it was included
     pub blocks: Option<HashSet<Hash>>,
@@ -260,11 +640,38 @@ pub struct TransactionResponse<'a, T: Clone + AsRef<Transaction>> {
     pub data: DataHash<'a, T>
 }
 
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TransactionReceiptResponse<'a> {
+    pub hash: Cow<'a, Hash>,
+    // block in which it was executed
+    pub executed_in_block: Cow<'a, Hash>,
+    #[serde(flatten)]
+    pub receipt: Cow<'a, TransactionReceipt>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetTransactionConfirmationsResult<'a> {
+    pub hash: Cow<'a, Hash>,
+    // if it is in mempool, waiting to be executed
+    pub in_mempool: bool,
+    // block in which it was executed
+    pub executed_in_block: Option<Hash>,
+    // topoheight of the block in which it was executed
+    pub topoheight: Option<u64>,
+    // blocks ordered in the DAG since its execution, the executing block included
+    pub confirmations: u64,
+    // the executing block is at or below the stable height, its execution can't be reverted anymore
+    pub stable: bool,
+    // estimated probability that its execution is reverted by a reorganization of the DAG
+    // 0 once stable, 1 while not executed
+    pub reorg_probability: f64
+}
+
 fn default_xelis_asset() -> Hash {
     crate::config::XELIS_ASSET
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetAccountHistoryParams {
     pub address: Address,
     #[serde(default = "default_xelis_asset")]
@@ -273,7 +680,20 @@ pub struct GetAccountHistoryParams {
     pub maximum_topoheight: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetDepositsParams {
+    // if it's an integrated address, only the deposits with its data are returned
+    pub address: Address,
+    // all assets if not set
+    pub asset: Option<Hash>,
+    pub minimum_topoheight: Option<u64>,
+    pub maximum_topoheight: Option<u64>,
+    // minimum confirmations required for a deposit to be returned
+    #[serde(default)]
+    pub confirmations: u64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")] 
 pub enum AccountHistoryType {
     Mining { reward: u64 },
@@ -283,7 +703,7 @@ pub enum AccountHistoryType {
     Incoming { amount: u64, from: Address },
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct AccountHistoryEntry {
     pub topoheight: u64,
     pub hash: Hash,
@@ -292,17 +712,17 @@ pub struct AccountHistoryEntry {
     pub block_timestamp: u128
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetAccountAssetsParams {
     pub address: Address
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetAssetParams {
     pub asset: Hash
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetAssetsParams {
     pub skip: Option<usize>,
     pub maximum: Option<usize>,
@@ -310,7 +730,7 @@ pub struct GetAssetsParams {
     pub maximum_topoheight: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetAccountsParams {
     pub skip: Option<usize>,
     pub maximum: Option<usize>,
@@ -318,14 +738,60 @@ pub struct GetAccountsParams {
     pub maximum_topoheight: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct IsTxExecutedInBlockParams<'a> {
     pub tx_hash: Cow<'a, Hash>,
     pub block_hash: Cow<'a, Hash>
 }
 
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetContractParams<'a> {
+    // hash of the TX that deployed the contract
+    pub contract: Cow<'a, Hash>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetContractResult<'a> {
+    // topoheight at which the contract was deployed
+    pub topoheight: u64,
+    pub code: Cow<'a, String>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetContractDataParams<'a> {
+    pub contract: Cow<'a, Hash>,
+    pub key: Cow<'a, String>,
+    // read the value as it was at this topoheight, latest one if not set
+    pub topoheight: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetContractDataResult {
+    // topoheight at which the value was written, None if never written
+    pub topoheight: Option<u64>,
+    pub value: u64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ResolveNameParams<'a> {
+    // with or without the `.xel` suffix
+    pub name: Cow<'a, String>,
+    // resolve the name as it was at this topoheight, latest one if not set
+    pub topoheight: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ResolveNameResult {
+    pub name: String,
+    pub owner: Address,
+    // topoheight of the first registration by this owner
+    pub registered_topoheight: u64,
+    // first topoheight at which the name is no longer resolved
+    pub expiration: u64
+}
+
 // Struct to define dev fee threshold
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema)]
 pub struct DevFeeThreshold {
     // block height to start dev fee
     pub height: u64,
@@ -333,14 +799,208 @@ pub struct DevFeeThreshold {
     pub fee_percentage: u64
 }
 
+// PoW algorithms supported by the network
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowAlgorithm {
+    Keccak256
+}
+
+impl PowAlgorithm {
+    // memory-hard algorithms need the dataset of the current epoch to compute the PoW hash
+    pub fn requires_dataset(&self) -> bool {
+        match self {
+            Self::Keccak256 => false
+        }
+    }
+}
+
+// PoW seeds of the current and next epochs for a topoheight
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct GetPowSeedResult {
+    // algorithm used by the next block
+    pub pow_algorithm: PowAlgorithm,
+    // the seed is the hash of the stable block at this topoheight
+    pub seed_topoheight: u64,
+    pub seed: Hash,
+    pub next_seed_topoheight: u64,
+    // set once the block at the next seed topoheight is stable
+    pub next_seed: Option<Hash>,
+    // first topoheight using the next seed
+    pub next_seed_activation_topoheight: u64,
+    // algorithm used by the blocks above the next seed activation
+    pub next_pow_algorithm: PowAlgorithm
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetAuxCommitmentProofParams<'a> {
+    pub block_hash: Cow<'a, Hash>,
+    // identifier of the external chain
+    pub chain_id: Cow<'a, Hash>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetAuxCommitmentProofResult {
+    pub block_hash: Hash,
+    pub height: u64,
+    // none if the block is not ordered in the DAG yet
+    pub topoheight: Option<u64>,
+    pub difficulty: Difficulty,
+    pub proof: AuxCommitmentProof
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ReplayEventsParams {
+    pub from_topoheight: u64,
+    // last topoheight to replay, current topoheight by default
+    pub to_topoheight: Option<u64>,
+    // events to replay, BlockOrdered, TransactionExecuted and BalanceChanged by default
+    #[serde(default)]
+    pub events: Option<HashSet<NotifyEvent>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WatchAddressesParams {
+    pub addresses: Vec<Address>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UnwatchAddressesParams {
+    // addresses to stop watching, all of them by default
+    #[serde(default)]
+    pub addresses: Option<Vec<Address>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ReplayEventsResult<'a> {
+    // events in the order they are notified, topoheight by topoheight
+    pub events: Vec<DaemonEvent<'a>>,
+    // topoheight to request next to continue the replay
+    pub next_topoheight: u64,
+    // current topoheight of the chain
+    pub topoheight: u64
+}
+
+// Algorithms used to compute the difficulty of the next block
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DifficultyAlgorithm {
+    // exponential adjustment based on the solve time of the best tip only
+    Legacy,
+    // linearly weighted moving average of the last solve times
+    Lwma
+}
+
+// Struct to define a hard fork and the rules activated by it
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct HardFork {
+    // block height to activate the hard fork
+    pub height: u64,
+    // block version required starting from this height
+    pub version: u8,
+    // is the block timestamp required to be greater than the median time past of its tips
+    pub median_time_past: bool,
+    // maximum block size (header + txs) in bytes
+    pub max_block_size: usize,
+    // algorithm used to verify the PoW of the block
+    pub pow_algorithm: PowAlgorithm,
+    // algorithm used to compute the difficulty of the next blocks
+    pub difficulty_algorithm: DifficultyAlgorithm,
+    // are Smart Contracts transaction types allowed
+    pub smart_contracts: bool,
+    // are names registrations allowed
+    pub name_service: bool,
+    // highest transaction version accepted
+    pub max_tx_version: u8
+}
+
 // Struct to returns the size of the blockchain on disk
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SizeOnDiskResult {
     pub size_bytes: u64,
     pub size_formatted: String
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+// how the tips of a block template are selected among the valid ones
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ArgEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum TipSelectionStrategy {
+    // the heaviest tips by cumulative difficulty
+    #[default]
+    Heaviest,
+    // the heaviest tip, then the tips with the most recent timestamp
+    Freshest,
+    // the reference tip allowing to merge the most tips, even if lighter than the heaviest one
+    Inclusive
+}
+
+// why a tip is used or not by the next block template
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TipSelection {
+    Selected,
+    // difficulty is not within 9% of the reference tip, the heaviest selected
+    LowDifficulty,
+    // the strategy already selected the maximum tips allowed
+    TipsLimit,
+    // heavier than the reference tip chosen to merge more tips
+    AboveReference
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TipInfo {
+    pub hash: Hash,
+    pub height: u64,
+    pub topoheight: Option<u64>,
+    pub difficulty: Difficulty,
+    pub cumulative_difficulty: Difficulty,
+    // difficulty accumulated since the common base of the tips, used to select the best tip
+    pub work_score: Difficulty,
+    pub selection: TipSelection
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetTipsInfoResult {
+    // common base of the tips, the DAG is ordered from it
+    pub base: Hash,
+    pub base_height: u64,
+    // tip with the highest work score, used to order the DAG
+    pub best_tip: Hash,
+    // strategy configured to select the tips of the block templates
+    pub strategy: TipSelectionStrategy,
+    // ordered by cumulative difficulty like in a block template
+    pub tips: Vec<TipInfo>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct EvaluateBlockParams<'a> {
+    pub hash: Cow<'a, Hash>,
+    // cumulative difficulty reported by another node, used if the block is unknown
+    #[serde(default)]
+    pub cumulative_difficulty: Option<Difficulty>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct EvaluateBlockResult {
+    // block is stored by the node
+    pub known: bool,
+    pub height: Option<u64>,
+    // set if the block is ordered in the local DAG
+    pub topoheight: Option<u64>,
+    pub block_type: Option<BlockType>,
+    // local one if the block is known, otherwise the one given
+    pub cumulative_difficulty: Option<Difficulty>,
+    // local tip with the highest cumulative difficulty
+    pub best_tip: Hash,
+    pub best_cumulative_difficulty: Difficulty,
+    // the chain of the block has more work than the local one
+    pub heavier: Option<bool>,
+    // a block at or below the stable height which is not ordered can't be part of the local chain anymore
+    pub stable_height: u64
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum NotifyEvent {
     // When a new block is accepted by chain
     // it contains Block struct as value
@@ -357,12 +1017,29 @@ pub enum NotifyEvent {
     // When a transaction has been included in a valid block & executed on chain
     // it contains TransactionExecutedEvent struct as value
     TransactionExecuted,
+    // When the balance of an account has changed in an ordered block
+    // it contains BalanceChangedEvent struct as value
+    BalanceChanged,
+    // When a transaction previously executed in a block is not executed anymore
+    // (DAG reorg, rewind or double spending detected during ordering)
+    // it contains TransactionOrphanedEvent struct as value
+    TransactionOrphaned,
+    // When a transaction is replaced or invalidated by another one using the same source and nonce
+    // it contains TransactionConflictedEvent struct as value
+    TransactionConflicted,
+    // When a transaction is removed from mempool because it can't be executed anymore:
+    // it (or one with a lower nonce of the same source) reached its expiration or stayed too long in mempool
+    // it contains TransactionExpiredEvent struct as value
+    TransactionExpired,
     // When a registered TX SC Call hash has been executed by chain
     // TODO: Smart Contracts
     TransactionSCResult,
     // When a new asset has been registered
     // TODO: Smart Contracts
     NewAsset,
+    // When the seed of the next PoW epoch is known or when it's activated
+    // It contains GetPowSeedResult struct as value
+    NewPowSeed,
     // When a new peer has connected to us
     // It contains PeerEntry struct as value
     PeerConnected,
@@ -379,9 +1056,12 @@ pub enum NotifyEvent {
     // and that he notified us
     // It contains PeerPeerDisconnectedEvent as value
     PeerPeerDisconnected,
+    // When the chain split monitor detects that our stable chain is different from a reference node
+    // It contains ChainSplitAlert struct as value
+    ChainSplitDetected,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct BlockOrderedEvent<'a> {
     // block hash in which this event was triggered
     pub block_hash: Cow<'a, Hash>,
@@ -390,20 +1070,60 @@ pub struct BlockOrderedEvent<'a> {
     pub topoheight: u64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct StableHeightChangedEvent {
     pub previous_stable_height: u64,
     pub new_stable_height: u64
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct TransactionExecutedEvent<'a> {
     pub block_hash: Cow<'a, Hash>,
     pub tx_hash: Cow<'a, Hash>,
     pub topoheight: u64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct BalanceChangedEvent<'a> {
+    // block hash ordered at this topoheight
+    pub block_hash: Cow<'a, Hash>,
+    pub address: Address,
+    pub asset: Cow<'a, Hash>,
+    pub topoheight: u64,
+    // new balance of the account for this asset
+    pub balance: u64,
+    pub previous_topoheight: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TransactionOrphanedEvent<'a> {
+    pub tx_hash: Cow<'a, Hash>,
+    // block in which the transaction was included or executed before being orphaned
+    pub block_hash: Option<Cow<'a, Hash>>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TransactionConflictedEvent<'a> {
+    // transaction which is not valid anymore
+    pub tx_hash: Cow<'a, Hash>,
+    // transaction that took its place, if known
+    pub conflicting_tx_hash: Option<Cow<'a, Hash>>,
+    // source of both transactions
+    pub owner: Cow<'a, PublicKey>,
+    pub nonce: u64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TransactionExpiredEvent<'a> {
+    // transaction removed from mempool
+    pub tx_hash: Cow<'a, Hash>,
+    pub owner: Cow<'a, PublicKey>,
+    pub nonce: u64,
+    // topoheight above which it can't be executed, not set if it stayed too long in mempool
+    pub expiration: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct PeerPeerListUpdatedEvent {
     // Peer ID of the peer that sent us the new peer list
     pub peer_id: u64,
@@ -411,10 +1131,95 @@ pub struct PeerPeerListUpdatedEvent {
     pub peerlist: Vec<SocketAddr>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct PeerPeerDisconnectedEvent {
     // Peer ID of the peer that sent us this notification
     pub peer_id: u64,
     // address of the peer that disconnected from him
     pub peer_addr: SocketAddr
-}
\ No newline at end of file
+}
+// Event notified by the daemon with its typed payload
+// serialized, it's the notification received by a subscribed client: {"event": "BlockOrdered", ...payload}
+// so a client can deserialize the notifications of all its subscriptions with it
+// TransactionSCResult and NewAsset are not notified yet
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "event")]
+pub enum DaemonEvent<'a> {
+    NewBlock(BlockResponse<'a, Arc<BlockHeader>>),
+    BlockOrdered(BlockOrderedEvent<'a>),
+    StableHeightChanged(StableHeightChangedEvent),
+    TransactionAddedInMempool(TransactionResponse<'a, Arc<Transaction>>),
+    TransactionExecuted(TransactionExecutedEvent<'a>),
+    BalanceChanged(BalanceChangedEvent<'a>),
+    TransactionOrphaned(TransactionOrphanedEvent<'a>),
+    TransactionConflicted(TransactionConflictedEvent<'a>),
+    TransactionExpired(TransactionExpiredEvent<'a>),
+    NewPowSeed(GetPowSeedResult),
+    PeerConnected(PeerEntry<'a>),
+    PeerDisconnected(PeerEntry<'a>),
+    PeerPeerListUpdated(PeerPeerListUpdatedEvent),
+    PeerStateUpdated(PeerEntry<'a>),
+    PeerPeerDisconnected(PeerPeerDisconnectedEvent),
+    ChainSplitDetected(ChainSplitAlert)
+}
+
+impl DaemonEvent<'_> {
+    // event to subscribe to for receiving it
+    pub fn get_event(&self) -> NotifyEvent {
+        match self {
+            Self::NewBlock(_) => NotifyEvent::NewBlock,
+            Self::BlockOrdered(_) => NotifyEvent::BlockOrdered,
+            Self::StableHeightChanged(_) => NotifyEvent::StableHeightChanged,
+            Self::TransactionAddedInMempool(_) => NotifyEvent::TransactionAddedInMempool,
+            Self::TransactionExecuted(_) => NotifyEvent::TransactionExecuted,
+            Self::BalanceChanged(_) => NotifyEvent::BalanceChanged,
+            Self::TransactionOrphaned(_) => NotifyEvent::TransactionOrphaned,
+            Self::TransactionConflicted(_) => NotifyEvent::TransactionConflicted,
+            Self::TransactionExpired(_) => NotifyEvent::TransactionExpired,
+            Self::NewPowSeed(_) => NotifyEvent::NewPowSeed,
+            Self::PeerConnected(_) => NotifyEvent::PeerConnected,
+            Self::PeerDisconnected(_) => NotifyEvent::PeerDisconnected,
+            Self::PeerPeerListUpdated(_) => NotifyEvent::PeerPeerListUpdated,
+            Self::PeerStateUpdated(_) => NotifyEvent::PeerStateUpdated,
+            Self::PeerPeerDisconnected(_) => NotifyEvent::PeerPeerDisconnected,
+            Self::ChainSplitDetected(_) => NotifyEvent::ChainSplitDetected
+        }
+    }
+
+    // payload of the event, without its name
+    pub fn to_value(&self) -> Result<Value, serde_json::Error> {
+        let mut value = serde_json::to_value(self)?;
+        if let Value::Object(map) = &mut value {
+            map.remove("event");
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+    use crate::api::EventResult;
+
+    #[test]
+    fn test_event_wire_format() {
+        let event = DaemonEvent::BlockOrdered(BlockOrderedEvent {
+            block_hash: Cow::Owned(Hash::zero()),
+            block_type: BlockType::Normal,
+            topoheight: 42
+        });
+        assert_eq!(event.get_event(), NotifyEvent::BlockOrdered);
+
+        // same notification as the one built from the event name and its payload
+        let value = event.to_value().unwrap();
+        assert!(value.get("event").is_none());
+        let notification = json!(EventResult { event: Cow::<NotifyEvent>::Owned(NotifyEvent::BlockOrdered), value });
+        assert_eq!(json!(event), notification);
+
+        match serde_json::from_value(notification).unwrap() {
+            DaemonEvent::BlockOrdered(event) => assert_eq!(event.topoheight, 42),
+            _ => panic!("Invalid event")
+        };
+    }
+}
@@ -2,14 +2,16 @@ use std::{collections::HashMap, borrow::Cow};
 use indexmap::IndexMap;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use serde_json::Value;
-use crate::{serializer::{Serializer, Reader, ReaderError, Writer}, crypto::hash::Hash};
+use crate::{serializer::{Serializer, Reader, ReaderError, Writer}, crypto::{hash::Hash, address::Address}, network::Network, transaction::{Transaction, TransactionType}, config::{FEE_PER_KB, XELIS_ASSET}, utils::calculate_tx_fee};
 
 pub mod wallet;
 pub mod daemon;
+pub mod signature;
 
 // All types availables
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Eq, PartialEq, Hash, Clone, Copy)]
 pub enum DataType {
     Bool,
     String,
@@ -52,7 +54,7 @@ impl DataType {
 }
 
 // This enum allows complex structures with multi depth if necessary
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum DataElement {
     // Value can be Optional to represent null in JSON
@@ -163,7 +165,7 @@ impl Serializer for DataElement {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Eq, PartialEq, Hash, Clone)]
 #[serde(untagged)]
 pub enum DataValue {
     // represent a null value
@@ -260,7 +262,7 @@ impl Serializer for DataValue {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryNumber {
     // >
@@ -312,7 +314,7 @@ impl QueryNumber {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryValue {
     // ==
@@ -323,6 +325,7 @@ pub enum QueryValue {
     ContainsValue(DataValue),
     // Regex pattern on DataValue only
     #[serde(with = "serde_regex")]
+    #[schemars(with = "String")]
     Pattern(Regex),
     #[serde(untagged)]
     NumberOp(QueryNumber)
@@ -341,7 +344,7 @@ impl QueryValue {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Query {
     // !
@@ -422,7 +425,7 @@ impl Query {
 }
 
 // This is used to do query in daemon (in future for Smart Contracts) and wallet
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")] 
 pub enum QueryElement {
     // Check if DataElement::Fields has key and optional check on value
@@ -460,27 +463,275 @@ impl QueryElement {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct QueryResult {
+    #[schemars(with = "HashMap<DataValue, DataElement>")]
     pub entries: IndexMap<DataValue, DataElement>,
     pub next: Option<usize>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SubscribeParams<E> {
     pub notify: E
 }
 
-#[derive(Serialize, Deserialize)]
+// Version of the events payloads (DaemonEvent and WalletEvent)
+// fields may be added to a payload, renaming or removing one requires a new version
+pub const EVENTS_VERSION: u16 = 1;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct EventResult<'a, E: Clone> {
     pub event: Cow<'a, E>,
     #[serde(flatten)]
     pub value: Value
 }
 
-#[derive(Serialize, Deserialize)]
+// Stable codes of the errors returned in the `data` field of a JSON-RPC error
+// clients can branch on them instead of parsing the messages, which may change
+// a code is never renamed or removed, new ones are added at the end
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    UnknownBlock,
+    UnknownTransaction,
+    UnknownAccount,
+    UnknownAsset,
+    UnknownContract,
+    UnknownName,
+    TransactionNotExecuted,
+    TransactionAlreadyKnown,
+    InsufficientFunds,
+    InvalidNonce,
+    // fee is below the minimum required, or too low to replace a transaction
+    FeeTooLow,
+    InvalidSignature,
+    InvalidTransaction,
+    // transaction type or version not enabled yet
+    TransactionNotAllowed,
+    // too many pending transactions for the account or in the mempool
+    MempoolLimitReached,
+    InvalidAddress,
+    Syncing,
+    // feature required by the method is disabled on the node
+    FeatureDisabled,
+    BlockAlreadyKnown,
+    // block template is no longer valid: unknown job or tips too deep in the chain
+    StaleBlock,
+    // timestamp is in the future or lower than one of its tips
+    InvalidTimestamp,
+    // extra nonce doesn't keep the part set by the daemon
+    InvalidExtraNonce,
+    InvalidProofOfWork,
+    // tips, height or version of the block header are invalid
+    InvalidBlock,
+    // block doesn't commit to the requested external chain
+    UnknownAuxChain
+}
+
+// Content of the `data` field of a JSON-RPC error
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ErrorData {
+    pub code: ErrorCode
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct DataHash<'a, T: Clone> {
     pub hash: Cow<'a, Hash>,
     #[serde(flatten)]
     pub data: Cow<'a, T>
 }
+
+// Transfer received by an address
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct DepositEntry {
+    // hash of the transaction
+    pub hash: Hash,
+    // topoheight of the block in which the transaction was executed
+    pub topoheight: u64,
+    pub from: Address,
+    pub asset: Hash,
+    pub amount: u64,
+    pub confirmations: u64
+}
+
+// Deposits sharing the same integrated data (payment ID)
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct DepositGroup {
+    // None for the transfers sent without integrated data
+    pub data: Option<DataElement>,
+    // total received for each asset
+    pub totals: HashMap<Hash, u64>,
+    pub deposits: Vec<DepositEntry>
+}
+
+impl DepositGroup {
+    // group the deposits by their integrated data, keeping the order of first appearance
+    pub fn group<I: IntoIterator<Item = (Option<DataElement>, DepositEntry)>>(deposits: I) -> Vec<Self> {
+        let mut groups: Vec<Self> = Vec::new();
+        for (data, deposit) in deposits {
+            let group = match groups.iter().position(|group| group.data == data) {
+                Some(index) => &mut groups[index],
+                None => {
+                    groups.push(Self {
+                        data,
+                        totals: HashMap::new(),
+                        deposits: Vec::new()
+                    });
+                    groups.last_mut().expect("group was just added")
+                }
+            };
+
+            let total = group.totals.entry(deposit.asset.clone()).or_insert(0);
+            *total = total.saturating_add(deposit.amount);
+            group.deposits.push(deposit);
+        }
+        groups
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ValidateAddressParams<'a> {
+    pub address: Cow<'a, String>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressKind {
+    Normal,
+    Integrated
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ValidateAddressResult {
+    // address can be decoded and is for the expected network
+    pub is_valid: bool,
+    // reason why the address is not valid
+    pub error: Option<String>,
+    // fields below are set only if the address could be decoded
+    pub network: Option<Network>,
+    pub address_type: Option<AddressKind>,
+    // same address without its integrated data
+    pub normal_address: Option<Address>,
+    pub integrated_data: Option<DataElement>,
+    // size in bytes of the integrated data once serialized
+    pub integrated_data_size: Option<usize>
+}
+
+impl ValidateAddressResult {
+    // testnet and dev addresses use the same prefix, the expected network is used to report it
+    pub fn new(address: &String, expected: Network) -> Self {
+        let address = match Address::from_string(address) {
+            Ok(address) => address,
+            Err(e) => return Self {
+                is_valid: false,
+                error: Some(e.to_string()),
+                network: None,
+                address_type: None,
+                normal_address: None,
+                integrated_data: None,
+                integrated_data_size: None
+            }
+        };
+
+        let network = match (address.is_mainnet(), expected.is_mainnet()) {
+            (true, _) => Network::Mainnet,
+            (false, false) => expected,
+            (false, true) => Network::Testnet
+        };
+
+        let error = if network != expected {
+            Some(format!("Address is for {} network, expected {}", network, expected))
+        } else {
+            None
+        };
+
+        let (data, normal_address) = address.extract_data();
+        let address_type = if data.is_some() {
+            AddressKind::Integrated
+        } else {
+            AddressKind::Normal
+        };
+
+        Self {
+            is_valid: error.is_none(),
+            error,
+            network: Some(network),
+            address_type: Some(address_type),
+            integrated_data_size: data.as_ref().map(DataElement::size),
+            integrated_data: data,
+            normal_address: Some(normal_address)
+        }
+    }
+}
+
+// Fee breakdown of a transaction, computed before signing it
+// its size doesn't depend on the amounts, so the fee is the same whatever the amounts sent (e.g. to send the max)
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct FeeEstimation {
+    // size in bytes of the signed transaction
+    pub size: usize,
+    // a started KB is fully charged
+    pub size_in_kb: u64,
+    pub fee_per_kb: u64,
+    // minimum fee for this size
+    pub min_fee: u64,
+    // fee set in the transaction
+    pub fee: u64,
+    // the sponsor if any, otherwise the owner
+    pub fee_payer: Address,
+    // debited from the owner per asset, including the fee if it is paid by the owner
+    pub total_spent: HashMap<Hash, u64>,
+    // part of the fee paid for everything but the transfers (header, signatures)
+    pub base_fee: u64,
+    // part of the fee paid by each transfer, in the same order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<OutputFee>
+}
+
+// Fee attributed to one transfer based on its share of the transaction size
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct OutputFee {
+    // serialized size of the transfer
+    pub size: usize,
+    pub fee: u64
+}
+
+impl FeeEstimation {
+    pub fn new(tx: &Transaction, mainnet: bool) -> Self {
+        let size = tx.get_signed_size();
+        let min_fee = calculate_tx_fee(size);
+        let mut total_spent: HashMap<Hash, u64> = tx.get_amounts_spent().into_iter()
+            .map(|(asset, amount)| (asset.clone(), amount))
+            .collect();
+
+        if tx.get_sponsor().is_none() {
+            let amount = total_spent.entry(XELIS_ASSET).or_insert(0);
+            *amount = amount.saturating_add(tx.get_fee());
+        }
+
+        // each transfer pays the fee in proportion of its size, rounded down
+        // the rest of the fee is attributed to the transaction itself
+        let fee = tx.get_fee();
+        let outputs: Vec<OutputFee> = match tx.get_data() {
+            TransactionType::Transfer(transfers) => transfers.iter().map(|transfer| {
+                let output_size = transfer.size();
+                let output_fee = (fee as u128 * output_size as u128 / size as u128) as u64;
+                OutputFee { size: output_size, fee: output_fee }
+            }).collect(),
+            _ => Vec::new()
+        };
+        let base_fee = fee - outputs.iter().map(|output| output.fee).sum::<u64>();
+
+        Self {
+            size,
+            size_in_kb: min_fee / FEE_PER_KB,
+            fee_per_kb: FEE_PER_KB,
+            min_fee,
+            fee,
+            fee_payer: tx.get_fee_payer().to_address(mainnet),
+            total_spent,
+            base_fee,
+            outputs
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::crypto::{hash::{Hash, HASH_SIZE, hash}, key::{KeyPair, PublicKey, Signature}};
+
+// HTTP headers set by a node signing its JSON-RPC responses
+pub const NODE_KEY_HEADER: &str = "X-Node-Key";
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+pub const SIGNATURE_TIMESTAMP_HEADER: &str = "X-Signature-Timestamp";
+
+// maximum difference in seconds accepted between the signature timestamp and the local time
+pub const MAX_SIGNATURE_AGE: u64 = 300;
+
+// prefix of the signed data so a response signature can't be reused as another signature of the node key
+const RESPONSE_DOMAIN: &[u8] = b"XELIS_RPC_RESPONSE";
+
+// Hash signed by the node for a response
+// the request is included so a signed response can't be replayed for another request
+pub fn get_response_signing_hash(timestamp: u64, request: &[u8], response: &[u8]) -> Hash {
+    let mut bytes = Vec::with_capacity(RESPONSE_DOMAIN.len() + 8 + HASH_SIZE * 2);
+    bytes.extend_from_slice(RESPONSE_DOMAIN);
+    bytes.extend_from_slice(&timestamp.to_be_bytes());
+    bytes.extend_from_slice(hash(request).as_bytes());
+    bytes.extend_from_slice(hash(response).as_bytes());
+    hash(&bytes)
+}
+
+pub fn sign_response(keypair: &KeyPair, timestamp: u64, request: &[u8], response: &[u8]) -> Signature {
+    keypair.sign(get_response_signing_hash(timestamp, request, response).as_bytes())
+}
+
+pub fn verify_response(key: &PublicKey, signature: &Signature, timestamp: u64, request: &[u8], response: &[u8]) -> bool {
+    key.verify_signature(&get_response_signing_hash(timestamp, request, response), signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::key::KeyPair;
+    use super::{sign_response, verify_response};
+
+    const REQUEST: &[u8] = br#"{"jsonrpc":"2.0","method":"get_info","id":1}"#;
+    const RESPONSE: &[u8] = br#"{"jsonrpc":"2.0","result":{"height":1},"id":1}"#;
+
+    #[test]
+    fn test_sign_verify_response() {
+        let keypair = KeyPair::new();
+        let signature = sign_response(&keypair, 1700000000, REQUEST, RESPONSE);
+        assert!(verify_response(keypair.get_public_key(), &signature, 1700000000, REQUEST, RESPONSE));
+
+        // another key
+        assert!(!verify_response(KeyPair::new().get_public_key(), &signature, 1700000000, REQUEST, RESPONSE));
+    }
+
+    #[test]
+    fn test_tampered_response() {
+        let keypair = KeyPair::new();
+        let key = keypair.get_public_key();
+        let signature = sign_response(&keypair, 1700000000, REQUEST, RESPONSE);
+
+        let tampered = br#"{"jsonrpc":"2.0","result":{"height":2},"id":1}"#;
+        assert!(!verify_response(key, &signature, 1700000000, REQUEST, tampered));
+        // replayed for another request
+        assert!(!verify_response(key, &signature, 1700000000, br#"{"jsonrpc":"2.0","method":"get_info","id":2}"#, RESPONSE));
+        assert!(!verify_response(key, &signature, 1700000001, REQUEST, RESPONSE));
+    }
+}
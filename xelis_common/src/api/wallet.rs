@@ -1,19 +1,21 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt::{self, Display, Formatter}};
 
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde_json::Value;
 
-use crate::{transaction::{TransactionType, Transaction}, crypto::{hash::Hash, address::Address}};
+use crate::{transaction::{TransactionType, Transaction}, crypto::{hash::{Hash, hash}, address::Address, key::{PublicKey, Signature}, threshold::{SigningCommitment, SignatureShare}}, serializer::{Serializer, Writer, Reader, ReaderError}, utils::format_xelis, asset::AssetWithData};
 
-use super::{DataHash, DataElement, DataValue, Query};
+use super::{DataHash, DataElement, DataValue, Query, daemon::GetInfoResult};
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub enum FeeBuilder {
     Multiplier(f64), // calculate tx fees based on its size and multiply by this value
     Value(u64) // set a direct value of how much fees you want to pay
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct BuildTransactionParams {
     #[serde(flatten)]
     pub tx_type: TransactionType,
@@ -22,6 +24,47 @@ pub struct BuildTransactionParams {
     pub broadcast: bool,
     // Returns the TX in HEX format also
     #[serde(default = "default_false_value")]
+    pub tx_as_hex: bool,
+    // Account which will pay the fee, the TX must be signed by it before being broadcasted
+    #[serde(default)]
+    pub sponsor: Option<Address>,
+    // Topoheight above which the TX can't be executed anymore
+    // once reached, another TX can be built using the same nonce
+    #[serde(default)]
+    pub expiration: Option<u64>,
+    // Client reference (UUID), the TX built first with it is returned on a retry
+    #[serde(default)]
+    pub reference: Option<String>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct EstimateFeesParams {
+    #[serde(flatten)]
+    pub tx_type: TransactionType,
+    pub fee: Option<FeeBuilder>,
+    #[serde(default)]
+    pub sponsor: Option<Address>,
+    #[serde(default)]
+    pub expiration: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SponsorTransactionParams {
+    // TX to pay the fee for, in HEX format
+    pub tx_as_hex: String,
+    pub broadcast: bool
+}
+
+// Replacement of a transaction still in mempool, used by `bump_fee` and `cancel`
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ReplaceTransactionParams {
+    // hash of the transaction to replace
+    pub hash: Hash,
+    // fee of the replacement, it must be higher than the fee of the transaction replaced
+    #[serde(default)]
+    pub fee: Option<u64>,
+    // Returns the replacement in HEX format also
+    #[serde(default)]
     pub tx_as_hex: bool
 }
 
@@ -34,7 +77,7 @@ fn default_false_value() -> bool {
     true
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ListTransactionsParams {
     pub min_topoheight: Option<u64>,
     pub max_topoheight: Option<u64>,
@@ -52,7 +95,20 @@ pub struct ListTransactionsParams {
     pub query: Option<Query>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetDepositsParams {
+    // only the deposits with this integrated data are returned
+    pub data: Option<DataElement>,
+    // all assets if not set
+    pub asset: Option<Hash>,
+    pub min_topoheight: Option<u64>,
+    pub max_topoheight: Option<u64>,
+    // minimum confirmations required for a deposit to be returned
+    #[serde(default)]
+    pub confirmations: u64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct TransactionResponse<'a> {
     #[serde(flatten)]
     pub inner: DataHash<'a, Transaction>,
@@ -60,25 +116,25 @@ pub struct TransactionResponse<'a> {
     pub tx_as_hex: Option<String>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetAssetPrecisionParams<'a> {
     pub asset: Cow<'a, Hash>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetAddressParams {
     // Data to use for creating an integrated address
     // Returned address will contains all the data provided here
     pub integrated_data: Option<DataElement>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SplitAddressParams {
     // address which must be in integrated form
     pub address: Address
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SplitAddressResult {
     // Normal address
     pub address: Address,
@@ -86,55 +142,803 @@ pub struct SplitAddressResult {
     pub integrated_data: DataElement
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct RescanParams {
     pub until_topoheight: Option<u64>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetBalanceParams {
     pub asset: Option<Hash>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetTransactionParams {
     pub hash: Hash
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct BalanceChanged<'a> {
     pub asset: Cow<'a, Hash>,
     pub balance: u64
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetCustomDataParams {
     pub tree: String,
     pub key: DataValue
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GetCustomTreeKeysParams {
     pub tree: String,
     pub query: Option<Query>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SetCustomDataParams {
     pub tree: String,
     pub key: DataValue,
     pub value: DataElement
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct QueryDBParams {
     pub tree: String,
     pub key: Option<Query>,
     pub value: Option<Query>
 }
 
+// memo is saved with a one byte length
+pub const MAX_INVOICE_MEMO_SIZE: usize = u8::MAX as usize;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CreateInvoiceParams {
+    pub amount: u64,
+    // XELIS if not set
+    pub asset: Option<Hash>,
+    // seconds before the invoice expires, one hour if not set
+    pub expires_in: Option<u64>,
+    pub memo: Option<String>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetInvoiceParams {
+    pub id: Hash
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ListInvoicesParams {
+    // all invoices if not set
+    pub state: Option<InvoiceState>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceState {
+    // waiting for its payment
+    Pending,
+    // full amount has been received before its expiration
+    Paid,
+    // not fully paid before its expiration, transfers received after are not counted
+    Expired
+}
+
+impl InvoiceState {
+    fn to_id(self) -> u8 {
+        match self {
+            Self::Pending => 0,
+            Self::Paid => 1,
+            Self::Expired => 2
+        }
+    }
+}
+
+// Payment request of the wallet, paid using the address integrating its ID
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Invoice {
+    // random, it is the payment ID integrated in the address
+    pub id: Hash,
+    pub asset: Hash,
+    pub amount: u64,
+    // total of the transfers received for this invoice
+    pub received: u64,
+    // timestamps in seconds
+    pub created_at: u64,
+    pub expiration: u64,
+    pub memo: Option<String>,
+    pub state: InvoiceState,
+    // transactions paying this invoice
+    pub transactions: Vec<Hash>
+}
+
+impl Invoice {
+    // integrated data of the transfers paying this invoice
+    pub fn get_payment_data(&self) -> DataElement {
+        DataElement::Value(Some(DataValue::Hash(self.id.clone())))
+    }
+}
+
+impl Serializer for Invoice {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_hash(&self.id);
+        writer.write_hash(&self.asset);
+        writer.write_u64(&self.amount);
+        writer.write_u64(&self.received);
+        writer.write_u64(&self.created_at);
+        writer.write_u64(&self.expiration);
+        writer.write_optional_string(&self.memo);
+        writer.write_u8(self.state.to_id());
+        writer.write_u16(self.transactions.len() as u16);
+        for tx in &self.transactions {
+            writer.write_hash(tx);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let id = reader.read_hash()?;
+        let asset = reader.read_hash()?;
+        let amount = reader.read_u64()?;
+        let received = reader.read_u64()?;
+        let created_at = reader.read_u64()?;
+        let expiration = reader.read_u64()?;
+        let memo = reader.read_optional_string()?;
+        let state = match reader.read_u8()? {
+            0 => InvoiceState::Pending,
+            1 => InvoiceState::Paid,
+            2 => InvoiceState::Expired,
+            _ => return Err(ReaderError::InvalidValue)
+        };
+        let count = reader.read_u16()?;
+        let mut transactions = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            transactions.push(reader.read_hash()?);
+        }
+
+        Ok(Self {
+            id,
+            asset,
+            amount,
+            received,
+            created_at,
+            expiration,
+            memo,
+            state,
+            transactions
+        })
+    }
+}
+
+// Invoice with the integrated address to pay it
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InvoiceEntry<'a> {
+    #[serde(flatten)]
+    pub invoice: Cow<'a, Invoice>,
+    pub address: Address
+}
+
+// url is saved with a one byte length
+pub const MAX_WEBHOOK_URL_SIZE: usize = u8::MAX as usize;
+
+// prefix of the signed data so a webhook signature can't be reused as another signature
+const WEBHOOK_DOMAIN: &[u8] = b"XELIS_WEBHOOK";
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AddWebhookParams {
+    pub url: String,
+    // confirmations before a transaction is notified, 8 if not set
+    pub confirmations: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RemoveWebhookParams {
+    pub id: Hash
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetWebhookDeliveriesParams {
+    // deliveries of all webhooks if not set
+    pub id: Option<Hash>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    // contains an InvoiceEntry as data
+    InvoicePaid,
+    // incoming transaction, contains a TransactionEntry as data
+    TransactionConfirmed
+}
+
+// URL receiving the signed callbacks of the wallet
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Webhook {
+    // random
+    pub id: Hash,
+    pub url: String,
+    pub confirmations: u64,
+    // incoming transactions are notified up to this topoheight
+    pub topoheight: u64
+}
+
+impl Serializer for Webhook {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_hash(&self.id);
+        writer.write_string(&self.url);
+        writer.write_u64(&self.confirmations);
+        writer.write_u64(&self.topoheight);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(Self {
+            id: reader.read_hash()?,
+            url: reader.read_string()?,
+            confirmations: reader.read_u64()?,
+            topoheight: reader.read_u64()?
+        })
+    }
+}
+
+// Body of a webhook callback, its signature is sent in the `X-Xelis-Signature` header
+#[derive(Serialize, Deserialize)]
+pub struct WebhookPayload<T> {
+    // unique per callback, the same is used for its retries
+    pub id: Hash,
+    pub webhook: Hash,
+    pub event: WebhookEvent,
+    pub timestamp: u64,
+    pub data: T
+}
+
+// hash signed by the wallet key for a webhook callback
+pub fn get_webhook_signing_hash(body: &[u8]) -> Hash {
+    let mut writer = Writer::new();
+    writer.write_bytes(WEBHOOK_DOMAIN);
+    writer.write_bytes(body);
+    hash(writer.as_bytes())
+}
+
+// Result of a webhook callback, kept in memory by the wallet
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct WebhookDelivery {
+    // id of the payload
+    pub id: Hash,
+    pub webhook: Hash,
+    pub url: String,
+    pub event: WebhookEvent,
+    pub created_at: u64,
+    pub attempts: u8,
+    pub delivered: bool,
+    // HTTP status of the last attempt
+    pub status: Option<u16>,
+    pub error: Option<String>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum QRCodeFormat {
+    Png,
+    Svg
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetQRCodeParams {
+    // data to integrate in the wallet address
+    pub integrated_data: Option<DataElement>,
+    // use the address, amount and asset of this invoice
+    pub invoice: Option<Hash>,
+    // requested amount in atomic units
+    pub amount: Option<u64>,
+    // XELIS if not set
+    pub asset: Option<Hash>,
+    // PNG if not set
+    pub format: Option<QRCodeFormat>,
+    // pixels per module of the PNG image
+    pub scale: Option<u8>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetQRCodeResult {
+    // address or payment URI encoded
+    pub content: String,
+    pub format: QRCodeFormat,
+    // base64 encoded
+    pub image: String
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CosignerInfo {
+    // address of the shared account
+    pub address: Address,
+    // index of the key share of this wallet
+    pub index: u8
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CosignerCommitment {
+    // id to reference the commitment when requesting the signature share
+    pub id: Hash,
+    pub commitment: SigningCommitment
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CosignTransactionParams {
+    // TX of the shared account to co-sign, in HEX format
+    pub tx_as_hex: String,
+    // commitment previously requested to this wallet, it can only be used once
+    pub commitment_id: Hash,
+    // commitments of all the signers, including the one of this wallet
+    pub commitments: Vec<SigningCommitment>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CosignTransactionResult {
+    pub share: SignatureShare
+}
 
-#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+
+// prefix of the signed data so a balance proof signature can't be reused as another signature
+const BALANCE_PROOF_DOMAIN: &[u8] = b"XELIS_BALANCE_PROOF";
+
+#[derive(Serialize, Deserialize)]
+pub struct ProvenBalance {
+    pub asset: Hash,
+    pub balance: u64,
+    // topoheight of this balance version, at or below the topoheight of the proof
+    // it can be checked using the daemon `get_balance_at_topoheight` method
+    pub topoheight: u64
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BalanceProofData {
+    pub address: Address,
+    pub topoheight: u64,
+    // block at the topoheight of the proof, to verify it against the same chain
+    pub block_hash: Hash,
+    pub block_timestamp: u128,
+    // ordered by asset
+    pub balances: Vec<ProvenBalance>
+}
+
+impl BalanceProofData {
+    pub fn get_signing_hash(&self) -> Hash {
+        let mut writer = Writer::new();
+        writer.write_bytes(BALANCE_PROOF_DOMAIN);
+        writer.write_bool(self.address.is_mainnet());
+        self.address.get_public_key().write(&mut writer);
+        writer.write_u64(&self.topoheight);
+        writer.write_hash(&self.block_hash);
+        writer.write_u128(&self.block_timestamp);
+        writer.write_u64(&(self.balances.len() as u64));
+        for balance in &self.balances {
+            writer.write_hash(&balance.asset);
+            writer.write_u64(&balance.balance);
+            writer.write_u64(&balance.topoheight);
+        }
+        hash(writer.as_bytes())
+    }
+}
+
+// Balances of a wallet at a topoheight signed by its key, used as proof of funds
+#[derive(Serialize, Deserialize)]
+pub struct BalanceProof {
+    #[serde(flatten)]
+    pub data: BalanceProofData,
+    pub signature: Signature
+}
+
+impl BalanceProof {
+    // only the signature is checked, the balances must be verified against a daemon
+    pub fn verify_signature(&self) -> bool {
+        self.data.address.get_public_key().verify_signature(&self.data.get_signing_hash(), &self.signature)
+    }
+}
+
+// prefix of the hashed data of an audit entry
+const AUDIT_DOMAIN: &[u8] = b"XELIS_WALLET_AUDIT";
+// details of an audit entry are truncated to this size
+pub const MAX_AUDIT_DETAILS_SIZE: usize = u8::MAX as usize;
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    // wallet created or opened with its password
+    Unlock,
+    // transaction, signature share or balance proof signed by the wallet key
+    Sign,
+    // transaction broadcasted to the daemon
+    Send,
+    // seed shown or storage uploaded to the backup provider
+    Export,
+    // XSWD application or request allowed
+    PermissionGrant
+}
+
+impl AuditEvent {
+    pub fn get_id(&self) -> u8 {
+        match self {
+            Self::Unlock => 0,
+            Self::Sign => 1,
+            Self::Send => 2,
+            Self::Export => 3,
+            Self::PermissionGrant => 4
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => Self::Unlock,
+            1 => Self::Sign,
+            2 => Self::Send,
+            3 => Self::Export,
+            4 => Self::PermissionGrant,
+            _ => return None
+        })
+    }
+}
+
+// Sensitive operation recorded by the wallet
+// each entry commits to the previous one so the log can't be modified without breaking the chain
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct AuditEntry {
+    // position in the log, starting at 0
+    pub index: u64,
+    pub timestamp: u64,
+    pub event: AuditEvent,
+    pub details: String,
+    // hash of the previous entry, zero for the first one
+    pub previous: Hash,
+    pub hash: Hash
+}
+
+impl AuditEntry {
+    pub fn new(index: u64, timestamp: u64, event: AuditEvent, details: String, previous: Hash) -> Self {
+        let mut entry = Self {
+            index,
+            timestamp,
+            event,
+            details,
+            previous,
+            hash: Hash::zero()
+        };
+        entry.hash = entry.compute_hash();
+        entry
+    }
+
+    pub fn compute_hash(&self) -> Hash {
+        let mut writer = Writer::new();
+        writer.write_bytes(AUDIT_DOMAIN);
+        writer.write_u64(&self.index);
+        writer.write_u64(&self.timestamp);
+        writer.write_u8(self.event.get_id());
+        writer.write_string(&self.details);
+        writer.write_hash(&self.previous);
+        hash(writer.as_bytes())
+    }
+
+    // entry is at this index, follows this hash and its own hash matches its content
+    pub fn is_valid_after(&self, index: u64, previous: &Hash) -> bool {
+        self.index == index && self.previous == *previous && self.hash == self.compute_hash()
+    }
+}
+
+impl Serializer for AuditEntry {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u64(&self.index);
+        writer.write_u64(&self.timestamp);
+        writer.write_u8(self.event.get_id());
+        writer.write_string(&self.details);
+        writer.write_hash(&self.previous);
+        writer.write_hash(&self.hash);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(Self {
+            index: reader.read_u64()?,
+            timestamp: reader.read_u64()?,
+            event: AuditEvent::from_id(reader.read_u8()?).ok_or(ReaderError::InvalidValue)?,
+            details: reader.read_string()?,
+            previous: reader.read_hash()?,
+            hash: reader.read_hash()?
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GetAuditLogParams {
+    pub min_index: Option<u64>,
+    pub max_index: Option<u64>,
+    // all events if not set
+    pub event: Option<AuditEvent>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct VerifyAuditLogResult {
+    // number of entries in the log
+    pub count: u64,
+    // hash of the last entry of the valid chain, zero if there is none
+    pub head: Hash,
+    // index of the first entry breaking the chain
+    pub invalid_index: Option<u64>
+}
+
+// transactions replaced listed at most in the history entry of their replacement
+pub const MAX_REPLACED_TRANSACTIONS: usize = 32;
+
+// named differently to not conflict with the Transfer of a Transaction
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct TransferEntry {
+    key: PublicKey,
+    asset: Hash,
+    amount: u64,
+    // raw (plain text) extra data if build by this wallet
+    extra_data: Option<DataElement>
+}
+
+impl TransferEntry {
+    pub fn new(key: PublicKey, asset: Hash, amount: u64, extra_data: Option<DataElement>) -> Self {
+        Self {
+            key,
+            asset,
+            amount,
+            extra_data
+        }
+    }
+
+    pub fn get_key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    pub fn get_asset(&self) -> &Hash {
+        &self.asset
+    }
+
+    pub fn get_amount(&self) -> u64 {
+        self.amount
+    }
+
+    pub fn get_extra_data(&self) -> &Option<DataElement> {
+        &self.extra_data
+    }
+}
+
+impl Serializer for TransferEntry {
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let key = PublicKey::read(reader)?;
+        let asset = reader.read_hash()?;
+        let amount = reader.read_u64()?;
+
+        let extra_data = Option::read(reader)?;
+
+        Ok(Self {
+            key,
+            asset,
+            amount,
+            extra_data
+        })
+    }
+
+    fn write(&self, writer: &mut Writer) {
+        self.key.write(writer);
+        writer.write_hash(&self.asset);
+        writer.write_u64(&self.amount);
+
+        self.extra_data.write(writer);
+    }
+}
+
+// TODO support SC call / SC Deploy
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub enum EntryData {
+    #[serde(rename = "coinbase")]
+    Coinbase(u64), // Coinbase is only XELIS_ASSET
+    #[serde(rename = "burn")]
+    Burn {
+        asset: Hash,
+        amount: u64
+    },
+    #[serde(rename = "incoming")]
+    Incoming(PublicKey, Vec<TransferEntry>),
+    #[serde(rename = "outgoing")]
+    Outgoing(Vec<TransferEntry>)
+}
+
+impl Serializer for EntryData {
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let id = reader.read_u8()?;
+        Ok(match id  {
+            0 => Self::Coinbase(reader.read_u64()?),
+            1 => Self::Burn {
+                asset: reader.read_hash()?,
+                amount: reader.read_u64()?
+            },
+            2 => {
+                let key = PublicKey::read(reader)?;
+                let size = reader.read_u16()? as usize;
+                let mut transfers = Vec::new();
+                for _ in 0..size {
+                    let transfer = TransferEntry::read(reader)?;
+                    transfers.push(transfer);
+                }
+                Self::Incoming(key, transfers)
+            }
+            3 => {
+                let size = reader.read_u16()? as usize;
+                let mut transfers = Vec::new();
+                for _ in 0..size {
+                    let transfer = TransferEntry::read(reader)?;
+                    transfers.push(transfer);
+                }
+                Self::Outgoing(transfers)
+            }
+            _ => return Err(ReaderError::InvalidValue)
+        }) 
+    }
+
+    fn write(&self, writer: &mut Writer) {
+        match &self {
+            Self::Coinbase(amount) => {
+                writer.write_u8(0);
+                writer.write_u64(amount);
+            },
+            Self::Burn { asset, amount } => {
+                writer.write_u8(1);
+                writer.write_hash(asset);
+                writer.write_u64(amount);
+            },
+            Self::Incoming(key, transfers) => {
+                writer.write_u8(2);
+                key.write(writer);
+                writer.write_u16(transfers.len() as u16);
+                for transfer in transfers {
+                    transfer.write(writer);
+                }
+            },
+            Self::Outgoing(transfers) => {
+                writer.write_u8(3);
+                writer.write_u16(transfers.len() as u16);
+                for transfer in transfers {
+                    transfer.write(writer);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct TransactionEntry {
+    hash: Hash,
+    topoheight: u64,
+    #[serde(skip_serializing_if="Option::is_none")]
+    fee: Option<u64>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    nonce: Option<u64>,
+    #[serde(flatten)]
+    entry: EntryData,
+    // transactions replaced by this one using the same nonce, the last replaced first
+    #[serde(skip_serializing_if="Vec::is_empty")]
+    replaced: Vec<Hash>
+}
+
+impl TransactionEntry {
+    pub fn new(hash: Hash, topoheight: u64, fee: Option<u64>, nonce: Option<u64>, entry: EntryData) -> Self {
+        Self {
+            hash,
+            topoheight,
+            fee,
+            nonce,
+            entry,
+            replaced: Vec::new()
+        }
+    }
+
+    pub fn get_hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    pub fn get_topoheight(&self) -> u64 {
+        self.topoheight
+    }
+
+    pub fn get_fee(&self) -> Option<u64> {
+        self.fee
+    }
+
+    pub fn get_nonce(&self) -> Option<u64> {
+        self.nonce
+    }
+
+    pub fn get_entry(&self) -> &EntryData {
+        &self.entry
+    }
+
+    pub fn get_mut_entry(&mut self) -> &mut EntryData {
+        &mut self.entry
+    }
+
+    pub fn get_replaced(&self) -> &Vec<Hash> {
+        &self.replaced
+    }
+
+    pub fn set_replaced(&mut self, replaced: Vec<Hash>) {
+        self.replaced = replaced;
+    }
+}
+
+impl Serializer for TransactionEntry {
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let hash = reader.read_hash()?;
+        let topoheight = reader.read_u64()?;
+        let fee = Option::read(reader)?;
+        let nonce = Option::read(reader)?;
+        let entry = EntryData::read(reader)?;
+
+        let count = reader.read_u8()? as usize;
+        if count > MAX_REPLACED_TRANSACTIONS {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        let mut replaced = Vec::with_capacity(count);
+        for _ in 0..count {
+            replaced.push(reader.read_hash()?);
+        }
+
+        Ok(Self {
+            hash,
+            topoheight,
+            fee,
+            nonce,
+            entry,
+            replaced
+        })
+    }
+
+    fn write(&self, writer: &mut Writer) {
+        writer.write_hash(&self.hash);
+        writer.write_u64(&self.topoheight);
+
+        self.fee.write(writer);
+        self.nonce.write(writer);
+        self.entry.write(writer);
+
+        writer.write_u8(self.replaced.len() as u8);
+        for hash in &self.replaced {
+            writer.write_hash(hash);
+        }
+    }
+}
+
+// TODO display values with correct decimals from asset
+impl Display for TransactionEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let entry_str = match self.get_entry() {
+            EntryData::Coinbase(reward) => format!("Coinbase {} XELIS", format_xelis(*reward)),
+            EntryData::Burn { asset, amount } => format!("Burn {} of {}", amount, asset),
+            EntryData::Incoming(sender, txs) => {
+                if txs.len() == 1 {
+                    format!("Received from {} {} {}", sender, format_xelis(txs[0].amount), txs[0].asset)
+                } else {
+                    format!("Incoming from {} {} transfers", sender, txs.len())
+                }
+            },
+            EntryData::Outgoing(txs) => {
+                if txs.len() == 1 {
+                    format!("Sent to {} {} {}", txs[0].key, format_xelis(txs[0].amount), txs[0].asset)
+                } else {
+                    format!("{} differents transfers", txs.len())
+                }
+            }
+        };
+
+        if let (Some(fee), Some(nonce)) = (self.fee, self.nonce) {
+            write!(f, "Hash {} at TopoHeight {}, Nonce {}, Fee: {}, Data: {}", self.hash, self.topoheight, nonce, format_xelis(fee), entry_str)
+        } else { // mostly coinbase
+            write!(f, "Hash {} at TopoHeight {}: {}", self.hash, self.topoheight, entry_str)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum NotifyEvent {
     // When a new block is detected by wallet
     // it contains Block struct as value
@@ -143,7 +947,7 @@ pub enum NotifyEvent {
     // and we receive a different topoheight than previous one
     NewChainInfo,
     // When a new asset is added to wallet
-    // Contains a Hash as value
+    // Contains an AssetWithData as value
     NewAsset,
     // When a new transaction is added to wallet
     // Contains TransactionEntry struct as value
@@ -151,4 +955,41 @@ pub enum NotifyEvent {
     // When a balance is changed
     // Contains a BalanceChanged as value
     BalanceChanged,
-}
\ No newline at end of file
+    // When an invoice is paid or expired
+    // Contains an InvoiceEntry as value
+    InvoiceChanged,
+}
+
+// Event notified by the wallet with its typed payload
+// serialized, it's the notification received by a subscribed client: {"event": "NewTransaction", ...payload}
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "event")]
+pub enum WalletEvent<'a> {
+    NewChainInfo(Cow<'a, GetInfoResult>),
+    NewAsset(Cow<'a, AssetWithData>),
+    NewTransaction(Cow<'a, TransactionEntry>),
+    BalanceChanged(BalanceChanged<'a>),
+    InvoiceChanged(InvoiceEntry<'a>)
+}
+
+impl WalletEvent<'_> {
+    // event to subscribe to for receiving it
+    pub fn get_event(&self) -> NotifyEvent {
+        match self {
+            Self::NewChainInfo(_) => NotifyEvent::NewChainInfo,
+            Self::NewAsset(_) => NotifyEvent::NewAsset,
+            Self::NewTransaction(_) => NotifyEvent::NewTransaction,
+            Self::BalanceChanged(_) => NotifyEvent::BalanceChanged,
+            Self::InvoiceChanged(_) => NotifyEvent::InvoiceChanged
+        }
+    }
+
+    // payload of the event, without its name
+    pub fn to_value(&self) -> Result<Value, serde_json::Error> {
+        let mut value = serde_json::to_value(self)?;
+        if let Value::Object(map) = &mut value {
+            map.remove("event");
+        }
+        Ok(value)
+    }
+}
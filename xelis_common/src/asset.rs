@@ -1,8 +1,9 @@
 use std::hash::{Hash as StdHash, Hasher};
+use schemars::JsonSchema;
 
 use crate::{serializer::{Serializer, Writer, Reader, ReaderError}, crypto::hash::Hash};
 
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
 pub struct AssetData {
     // At which topoheight this asset is registered
     topoheight: u64,
@@ -40,7 +41,7 @@ impl Serializer for AssetData {
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
 pub struct AssetWithData {
     asset: Hash,
     #[serde(flatten)]
@@ -0,0 +1,326 @@
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+
+use crate::{
+    crypto::hash::{Hash, hash, HASH_SIZE},
+    serializer::{Serializer, Writer, Reader, ReaderError}
+};
+use super::{HEADER_WORK_SIZE, BLOCK_WORK_SIZE};
+
+// first block version able to include an auxiliary commitment
+pub const AUX_COMMITMENT_VERSION: u8 = 2;
+// maximum external chains committed in a block header
+pub const MAX_AUX_CHAINS: usize = 16;
+
+// Block of an external chain merge-mined or anchored in a XELIS block
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct AuxChainEntry {
+    // unique identifier of the external chain
+    pub chain_id: Hash,
+    // hash of the block (or any data) of the external chain to commit
+    pub hash: Hash
+}
+
+impl AuxChainEntry {
+    pub fn new(chain_id: Hash, hash: Hash) -> Self {
+        Self { chain_id, hash }
+    }
+
+    // leaf of this entry in the merkle tree
+    pub fn get_leaf(&self) -> Hash {
+        let mut bytes = [0u8; HASH_SIZE * 2];
+        bytes[..HASH_SIZE].copy_from_slice(self.chain_id.as_bytes());
+        bytes[HASH_SIZE..].copy_from_slice(self.hash.as_bytes());
+        hash(&bytes)
+    }
+}
+
+impl Serializer for AuxChainEntry {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_hash(&self.chain_id);
+        writer.write_hash(&self.hash);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let chain_id = reader.read_hash()?;
+        let hash = reader.read_hash()?;
+        Ok(Self { chain_id, hash })
+    }
+}
+
+// Auxiliary commitment of a block header
+// its merkle root is included in the header work, so the PoW of the block also covers the external chains
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct AuxCommitment {
+    entries: Vec<AuxChainEntry>
+}
+
+impl AuxCommitment {
+    // entries are sorted by chain id
+    pub fn new(mut entries: Vec<AuxChainEntry>) -> Self {
+        entries.sort_by(|a, b| a.chain_id.as_bytes().cmp(b.chain_id.as_bytes()));
+        Self { entries }
+    }
+
+    pub fn get_entries(&self) -> &Vec<AuxChainEntry> {
+        &self.entries
+    }
+
+    // search the entry of a chain and its index in the tree
+    pub fn get_entry(&self, chain_id: &Hash) -> Option<(usize, &AuxChainEntry)> {
+        self.entries.iter().enumerate().find(|(_, entry)| entry.chain_id == *chain_id)
+    }
+
+    // at least one entry, no more than MAX_AUX_CHAINS and each chain only once, sorted by chain id
+    pub fn is_valid(&self) -> bool {
+        !self.entries.is_empty()
+            && self.entries.len() <= MAX_AUX_CHAINS
+            && self.entries.windows(2).all(|w| w[0].chain_id.as_bytes() < w[1].chain_id.as_bytes())
+    }
+
+    // merkle root of all the entries
+    pub fn get_root(&self) -> Hash {
+        let mut nodes: Vec<Hash> = self.entries.iter().map(AuxChainEntry::get_leaf).collect();
+        while nodes.len() > 1 {
+            nodes = nodes.chunks(2).map(|pair| match pair {
+                [left, right] => hash_pair(left, right),
+                // a node without sibling is promoted to the next level
+                [node] => node.clone(),
+                _ => unreachable!()
+            }).collect();
+        }
+
+        nodes.pop().unwrap_or_else(Hash::zero)
+    }
+
+    // siblings required to compute the root from the leaf at this index
+    pub fn get_branch(&self, mut index: usize) -> Vec<Hash> {
+        let mut branch = Vec::new();
+        let mut nodes: Vec<Hash> = self.entries.iter().map(AuxChainEntry::get_leaf).collect();
+        while nodes.len() > 1 {
+            if let Some(sibling) = nodes.get(index ^ 1) {
+                branch.push(sibling.clone());
+            }
+
+            nodes = nodes.chunks(2).map(|pair| match pair {
+                [left, right] => hash_pair(left, right),
+                [node] => node.clone(),
+                _ => unreachable!()
+            }).collect();
+            index >>= 1;
+        }
+
+        branch
+    }
+}
+
+// compute the merkle root from a leaf, its index, the leaves count and its branch
+// returns None if the branch doesn't match the tree shape
+pub fn compute_aux_root(leaf: Hash, mut index: usize, mut count: usize, branch: &[Hash]) -> Option<Hash> {
+    if index >= count {
+        return None
+    }
+
+    let mut siblings = branch.iter();
+    let mut node = leaf;
+    while count > 1 {
+        if index % 2 == 1 {
+            node = hash_pair(siblings.next()?, &node);
+        } else if index + 1 < count {
+            node = hash_pair(&node, siblings.next()?);
+        }
+
+        index >>= 1;
+        count = count.div_ceil(2);
+    }
+
+    // all the branch must be used
+    if siblings.next().is_some() {
+        return None
+    }
+
+    Some(node)
+}
+
+fn hash_pair(left: &Hash, right: &Hash) -> Hash {
+    let mut bytes = [0u8; HASH_SIZE * 2];
+    bytes[..HASH_SIZE].copy_from_slice(left.as_bytes());
+    bytes[HASH_SIZE..].copy_from_slice(right.as_bytes());
+    hash(&bytes)
+}
+
+impl Serializer for AuxCommitment {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(self.entries.len() as u8);
+        for entry in &self.entries {
+            entry.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Self::read_optional(reader)?.ok_or(ReaderError::InvalidSize)
+    }
+}
+
+impl AuxCommitment {
+    // no commitment is serialized as an entries count of 0
+    pub fn write_optional(value: Option<&Self>, writer: &mut Writer) {
+        match value {
+            Some(commitment) => commitment.write(writer),
+            None => writer.write_u8(0)
+        }
+    }
+
+    pub fn read_optional(reader: &mut Reader) -> Result<Option<Self>, ReaderError> {
+        let count = reader.read_u8()? as usize;
+        if count == 0 {
+            return Ok(None)
+        }
+
+        if count > MAX_AUX_CHAINS {
+            return Err(ReaderError::InvalidSize)
+        }
+
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            entries.push(AuxChainEntry::read(reader)?);
+        }
+
+        let commitment = Self { entries };
+        if !commitment.is_valid() {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        Ok(Some(commitment))
+    }
+}
+
+// Proof that a block of an external chain is committed in a XELIS block
+// the external chain can verify it without knowing the XELIS chain, and then check the PoW of the block work
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct AuxCommitmentProof {
+    pub entry: AuxChainEntry,
+    // index of the entry in the merkle tree
+    pub index: usize,
+    // entries count in the merkle tree
+    pub count: usize,
+    pub branch: Vec<Hash>,
+    // header work in hex, ending with the merkle root
+    pub header_work: String,
+    // block work in hex, starting with the hash of the header work
+    pub block_work: String
+}
+
+impl AuxCommitmentProof {
+    // verify the proof and returns the hash of the XELIS block committing the entry
+    pub fn verify(&self) -> Option<Hash> {
+        let header_work = hex::decode(&self.header_work).ok()?;
+        let block_work = hex::decode(&self.block_work).ok()?;
+        if header_work.len() != HEADER_WORK_SIZE + HASH_SIZE || block_work.len() != BLOCK_WORK_SIZE {
+            return None
+        }
+
+        let root = compute_aux_root(self.entry.get_leaf(), self.index, self.count, &self.branch)?;
+        if header_work[header_work.len() - HASH_SIZE..] != *root.as_bytes() {
+            return None
+        }
+
+        if block_work[..HASH_SIZE] != *hash(&header_work).as_bytes() {
+            return None
+        }
+
+        Some(hash(&block_work))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block::{BlockHeader, EXTRA_NONCE_SIZE}, crypto::{hash::Hashable, key::KeyPair}};
+    use super::*;
+
+    fn entries(count: u8) -> Vec<AuxChainEntry> {
+        (0..count).map(|i| AuxChainEntry::new(hash(&[i]), hash(&[i, i]))).collect()
+    }
+
+    #[test]
+    fn test_branches_for_all_sizes() {
+        for count in 1..=MAX_AUX_CHAINS as u8 {
+            let commitment = AuxCommitment::new(entries(count));
+            assert!(commitment.is_valid());
+            let root = commitment.get_root();
+            for (index, entry) in commitment.get_entries().iter().enumerate() {
+                let branch = commitment.get_branch(index);
+                assert_eq!(compute_aux_root(entry.get_leaf(), index, count as usize, &branch), Some(root.clone()));
+                // another index must not give the same root
+                if count > 1 {
+                    assert_ne!(compute_aux_root(entry.get_leaf(), (index + 1) % count as usize, count as usize, &branch), Some(root.clone()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_commitments() {
+        assert!(!AuxCommitment::new(Vec::new()).is_valid());
+        assert!(!AuxCommitment::new(entries(MAX_AUX_CHAINS as u8 + 1)).is_valid());
+
+        let mut duplicated = entries(2);
+        duplicated[1].chain_id = duplicated[0].chain_id.clone();
+        assert!(!AuxCommitment::new(duplicated).is_valid());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let commitment = AuxCommitment::new(entries(3));
+        let bytes = commitment.to_bytes();
+        assert_eq!(AuxCommitment::from_bytes(&bytes).unwrap(), commitment);
+
+        // entries not sorted are rejected
+        let mut writer = Writer::new();
+        writer.write_u8(2);
+        let mut unsorted = commitment.get_entries()[..2].to_vec();
+        unsorted.reverse();
+        for entry in &unsorted {
+            entry.write(&mut writer);
+        }
+        assert!(AuxCommitment::from_bytes(writer.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_header_commitment_and_proof() {
+        let miner = KeyPair::new().get_public_key().clone();
+        let mut header = BlockHeader::new(AUX_COMMITMENT_VERSION, 10, 1000, vec![Hash::zero()], [0u8; EXTRA_NONCE_SIZE], miner, Vec::new());
+        let hash_without = header.hash();
+        let commitment = AuxCommitment::new(entries(5));
+        header.set_aux_commitment(Some(commitment.clone()));
+        assert_ne!(header.hash(), hash_without);
+
+        let decoded = BlockHeader::from_bytes(&header.to_bytes()).unwrap();
+        assert_eq!(decoded.get_aux_commitment(), Some(&commitment));
+        assert_eq!(decoded.hash(), header.hash());
+
+        let (index, entry) = commitment.get_entry(&commitment.get_entries()[3].chain_id).unwrap();
+        let mut proof = AuxCommitmentProof {
+            entry: entry.clone(),
+            index,
+            count: commitment.get_entries().len(),
+            branch: commitment.get_branch(index),
+            header_work: hex::encode(header.get_work()),
+            block_work: hex::encode(header.get_serialized_header())
+        };
+        assert_eq!(proof.verify(), Some(header.hash()));
+
+        proof.entry.hash = Hash::zero();
+        assert_eq!(proof.verify(), None);
+    }
+
+    #[test]
+    fn test_commitment_ignored_before_its_version() {
+        let miner = KeyPair::new().get_public_key().clone();
+        let header = BlockHeader::new(AUX_COMMITMENT_VERSION - 1, 10, 1000, vec![Hash::zero()], [0u8; EXTRA_NONCE_SIZE], miner, Vec::new());
+        let bytes = header.to_bytes();
+        assert_eq!(BlockHeader::from_bytes(&bytes).unwrap().hash(), header.hash());
+        // no byte is added for the commitment
+        assert_eq!(bytes.len(), header.size());
+    }
+}
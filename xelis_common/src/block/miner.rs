@@ -1,9 +1,12 @@
 use std::borrow::Cow;
 
-use crate::{crypto::{hash::{Hash, Hashable, hash}, key::PublicKey}, serializer::{Serializer, Writer, Reader, ReaderError}};
+use crate::{crypto::{hash::{Hash, Hashable, hash, hash_x4, HASH_LANES}, key::PublicKey}, serializer::{Serializer, Writer, Reader, ReaderError}};
 
 use super::{EXTRA_NONCE_SIZE, BLOCK_WORK_SIZE};
 
+// position of the nonce in the serialized block miner
+const NONCE_OFFSET: usize = 48;
+
 // This structure is used by xelis-miner which allow to compute a valid block POW hash
 #[derive(Clone, Debug)]
 pub struct BlockMiner<'a> {
@@ -30,6 +33,17 @@ impl<'a> BlockMiner<'a> {
         // TODO replace with real POW algorithm
         hash(&self.to_bytes())
     }
+
+    // POW hashes for the current nonce and the next ones, computed at once
+    pub fn get_pow_hashes(&self) -> [Hash; HASH_LANES] {
+        let bytes = self.to_bytes();
+        let mut values = [[0u8; BLOCK_WORK_SIZE]; HASH_LANES];
+        for (i, value) in values.iter_mut().enumerate() {
+            value.copy_from_slice(&bytes);
+            value[NONCE_OFFSET..NONCE_OFFSET + 8].copy_from_slice(&self.nonce.wrapping_add(i as u64).to_be_bytes());
+        }
+        hash_x4([&values[0], &values[1], &values[2], &values[3]])
+    }
 }
 
 impl<'a> Serializer for BlockMiner<'a> {
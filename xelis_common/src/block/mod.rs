@@ -1,13 +1,17 @@
 mod miner;
+mod auxiliary;
 
 pub use miner::BlockMiner;
+pub use auxiliary::*;
 
+use std::sync::OnceLock;
 use serde::Deserialize;
+use schemars::JsonSchema;
 
-use crate::crypto::hash::{Hash, Hashable, hash};
+use crate::crypto::hash::{Hash, Hashable, hash, HASH_SIZE};
 use crate::crypto::key::PublicKey;
 use crate::immutable::Immutable;
-use crate::transaction::Transaction;
+use crate::transaction::{Transaction, MIN_TX_SIZE};
 use crate::serializer::{Serializer, Writer, Reader, ReaderError};
 
 pub const EXTRA_NONCE_SIZE: usize = 32;
@@ -30,6 +34,42 @@ pub fn deserialize_extra_nonce<'de, D: serde::Deserializer<'de>>(deserializer: D
     Ok(extra_nonce)
 }
 
+// the GetWork server of the daemon tags the extra nonce of the jobs with the worker name
+// so the blocks found can be attributed to a rig: magic bytes followed by the name, padded with zeros
+// the remaining bytes of the extra nonce are random, except the last ones used by the miner
+const WORKER_TAG_MAGIC: [u8; 2] = *b"xw";
+pub const WORKER_TAG_SIZE: usize = 16;
+// longer names are truncated
+pub const WORKER_TAG_MAX_NAME_SIZE: usize = WORKER_TAG_SIZE - WORKER_TAG_MAGIC.len();
+
+pub fn set_worker_tag(extra_nonce: &mut [u8; EXTRA_NONCE_SIZE], worker: &str) {
+    let mut len = worker.len().min(WORKER_TAG_MAX_NAME_SIZE);
+    while !worker.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    extra_nonce[..WORKER_TAG_MAGIC.len()].copy_from_slice(&WORKER_TAG_MAGIC);
+    let name = &mut extra_nonce[WORKER_TAG_MAGIC.len()..WORKER_TAG_SIZE];
+    name.fill(0);
+    name[..len].copy_from_slice(&worker.as_bytes()[..len]);
+}
+
+// returns the worker name if the extra nonce has been tagged
+pub fn get_worker_tag(extra_nonce: &[u8; EXTRA_NONCE_SIZE]) -> Option<&str> {
+    if extra_nonce[..WORKER_TAG_MAGIC.len()] != WORKER_TAG_MAGIC {
+        return None
+    }
+
+    let name = &extra_nonce[WORKER_TAG_MAGIC.len()..WORKER_TAG_SIZE];
+    let len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+    // zeros are only used as padding
+    if name[len..].iter().any(|b| *b != 0) {
+        return None
+    }
+
+    std::str::from_utf8(&name[..len]).ok().filter(|name| !name.is_empty())
+}
+
 // transform it as u64, its good enough until serde is able to de/serialize u128
 pub fn serialize_timestamp<S: serde::Serializer>(timestamp: &u128, s: S) -> Result<S::Ok, S::Error> {
     s.serialize_u64(*timestamp as u64)
@@ -39,23 +79,32 @@ pub fn deserialize_timestamp<'de, D: serde::Deserializer<'de>>(deserializer: D)
     Ok(u64::deserialize(deserializer)? as u128)
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
 pub struct BlockHeader {
-    pub version: u8,
-    pub tips: Vec<Hash>,
+    version: u8,
+    tips: Vec<Hash>,
     #[serde(serialize_with = "serialize_timestamp")]
     #[serde(deserialize_with = "deserialize_timestamp")]
-    pub timestamp: u128,
-    pub height: u64,
-    pub nonce: u64,
+    #[schemars(with = "u64")]
+    timestamp: u128,
+    height: u64,
+    nonce: u64,
     #[serde(serialize_with = "serialize_extra_nonce")]
     #[serde(deserialize_with = "deserialize_extra_nonce")]
-    pub extra_nonce: [u8; EXTRA_NONCE_SIZE],
-    pub miner: PublicKey,
-    pub txs_hashes: Vec<Hash>
+    #[schemars(with = "String")]
+    extra_nonce: [u8; EXTRA_NONCE_SIZE],
+    miner: PublicKey,
+    txs_hashes: Vec<Hash>,
+    // commitment of external chains, only available starting from AUX_COMMITMENT_VERSION
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aux_commitment: Option<AuxCommitment>,
+    // hash computed on first use, fields are private so it is reset by each setter
+    #[serde(skip)]
+    #[schemars(skip)]
+    hash: OnceLock<Hash>
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
 pub struct Block {
     #[serde(flatten)]
     header: Immutable<BlockHeader>,
@@ -72,7 +121,9 @@ impl BlockHeader {
             nonce: 0,
             extra_nonce,
             miner,
-            txs_hashes
+            txs_hashes,
+            aux_commitment: None,
+            hash: OnceLock::new()
         }
     }
 
@@ -82,10 +133,32 @@ impl BlockHeader {
 
     pub fn set_miner(&mut self, key: PublicKey) {
         self.miner = key;
+        self.hash.take();
     }
 
     pub fn set_extra_nonce(&mut self, values: [u8; EXTRA_NONCE_SIZE]) {
         self.extra_nonce = values;
+        self.hash.take();
+    }
+
+    pub fn set_nonce(&mut self, nonce: u64) {
+        self.nonce = nonce;
+        self.hash.take();
+    }
+
+    pub fn set_aux_commitment(&mut self, aux_commitment: Option<AuxCommitment>) {
+        self.aux_commitment = aux_commitment;
+        self.hash.take();
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: u128) {
+        self.timestamp = timestamp;
+        self.hash.take();
+    }
+
+    pub fn add_txs_hash(&mut self, hash: Hash) {
+        self.txs_hashes.push(hash);
+        self.hash.take();
     }
 
     pub fn get_height(&self) -> u64 {
@@ -122,6 +195,10 @@ impl BlockHeader {
         &self.extra_nonce
     }
 
+    pub fn get_aux_commitment(&self) -> Option<&AuxCommitment> {
+        self.aux_commitment.as_ref()
+    }
+
     pub fn get_txs_hashes(&self) -> &Vec<Hash> {
         &self.txs_hashes
     }
@@ -145,15 +222,23 @@ impl BlockHeader {
     }
 
     pub fn get_work(&self) -> Vec<u8> {
-        let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_WORK_SIZE);
+        let expected_size = if self.aux_commitment.is_some() {
+            HEADER_WORK_SIZE + HASH_SIZE
+        } else {
+            HEADER_WORK_SIZE
+        };
+        let mut bytes: Vec<u8> = Vec::with_capacity(expected_size);
 
         bytes.push(self.version); // 1
         bytes.extend(&self.height.to_be_bytes()); // 1 + 8 = 9
         bytes.extend(self.get_tips_hash().as_bytes()); // 9 + 32 = 41
         bytes.extend(self.get_txs_hash().as_bytes()); // 41 + 32 = 73
+        if let Some(aux_commitment) = &self.aux_commitment {
+            bytes.extend(aux_commitment.get_root().as_bytes()); // 73 + 32 = 105
+        }
 
-        if bytes.len() != HEADER_WORK_SIZE {
-            panic!("Error, invalid header work size, got {} but expected {}", bytes.len(), HEADER_WORK_SIZE)
+        if bytes.len() != expected_size {
+            panic!("Error, invalid header work size, got {} but expected {}", bytes.len(), expected_size)
         }
         bytes
     }
@@ -163,7 +248,8 @@ impl BlockHeader {
         hash(&self.get_work())
     }
 
-    fn get_serialized_header(&self) -> Vec<u8> {
+    // block work used to compute the block hash, it starts with the header work hash
+    pub fn get_serialized_header(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(BLOCK_WORK_SIZE);
         bytes.extend(self.get_work_hash().to_bytes());
         bytes.extend(self.timestamp.to_be_bytes());
@@ -235,6 +321,10 @@ impl Serializer for BlockHeader {
             writer.write_hash(tx); // 32
         }
         self.miner.write(writer); // 68 + 32 = 100
+
+        if self.version >= AUX_COMMITMENT_VERSION {
+            AuxCommitment::write_optional(self.aux_commitment.as_ref(), writer);
+        }
     }
 
     fn read(reader: &mut Reader) -> Result<BlockHeader, ReaderError> {
@@ -245,18 +335,26 @@ impl Serializer for BlockHeader {
         let extra_nonce: [u8; 32] = reader.read_bytes_32()?;
 
         let tips_count = reader.read_u8()?;
+        reader.check_count(tips_count as usize, HASH_SIZE)?;
         let mut tips = Vec::with_capacity(tips_count as usize);
         for _ in 0..tips_count {
             tips.push(reader.read_hash()?);
         }
 
         let txs_count = reader.read_u16()?;
+        reader.check_count(txs_count as usize, HASH_SIZE)?;
         let mut txs_hashes = Vec::with_capacity(txs_count as usize);
         for _ in 0..txs_count {
             txs_hashes.push(reader.read_hash()?);
         }
 
         let miner = PublicKey::read(reader)?;
+        let aux_commitment = if version >= AUX_COMMITMENT_VERSION {
+            AuxCommitment::read_optional(reader)?
+        } else {
+            None
+        };
+
         Ok(
             BlockHeader {
                 version,
@@ -266,7 +364,9 @@ impl Serializer for BlockHeader {
                 tips,
                 miner,
                 nonce,
-                txs_hashes
+                txs_hashes,
+                aux_commitment,
+                hash: OnceLock::new()
             }
         )
     }
@@ -276,7 +376,7 @@ impl Hashable for BlockHeader {
     // this function has the same behavior as the get_pow_hash function
     // but we use a fast algorithm here
     fn hash(&self) -> Hash {
-        hash(&self.get_serialized_header())
+        self.hash.get_or_init(|| hash(&self.get_serialized_header())).clone()
     }
 }
 
@@ -290,7 +390,8 @@ impl Serializer for Block {
 
     fn read(reader: &mut Reader) -> Result<Block, ReaderError> {
         let block = BlockHeader::read(reader)?;
-        let mut txs: Vec<Immutable<Transaction>> = Vec::new();
+        reader.check_count(block.get_txs_count(), MIN_TX_SIZE)?;
+        let mut txs: Vec<Immutable<Transaction>> = Vec::with_capacity(block.get_txs_count());
         for _ in 0..block.get_txs_count() {
             let tx = Transaction::read(reader)?;
             txs.push(Immutable::Owned(tx));     
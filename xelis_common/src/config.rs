@@ -13,4 +13,8 @@ pub const COIN_VALUE: u64 = 10u64.pow(COIN_DECIMALS as u32);
 // mainnet prefix address
 pub const PREFIX_ADDRESS: &str = "xel";
 // testnet prefix address
-pub const TESTNET_PREFIX_ADDRESS: &str = "xet";
\ No newline at end of file
+pub const TESTNET_PREFIX_ADDRESS: &str = "xet";
+
+// JSON-RPC
+// maximum requests accepted in one batch
+pub const MAX_BATCH_REQUESTS: usize = 64;
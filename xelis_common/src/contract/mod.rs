@@ -0,0 +1,318 @@
+mod vm;
+
+pub use vm::{ExecutionContext, ExecutionResult, execute};
+
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use thiserror::Error;
+use crate::serializer::{Serializer, Reader, ReaderError, Writer};
+
+// maximum size in bytes of the code deployed
+pub const MAX_CONTRACT_CODE_SIZE: usize = 16 * 1024;
+// the code size is serialized on 2 bytes in the transaction
+const _: () = assert!(MAX_CONTRACT_CODE_SIZE <= u16::MAX as usize);
+// maximum size of a storage key, a label or a param name
+pub const MAX_KEY_SIZE: usize = 64;
+// maximum values in the stack during an execution
+pub const MAX_STACK_SIZE: usize = 256;
+// gas available for each contract call
+pub const MAX_GAS_PER_CALL: u64 = 100_000;
+// atomic units of XELIS paid in the fee for each gas available to the call
+pub const FEE_PER_GAS: u64 = 1;
+// maximum events emitted by a contract call
+pub const MAX_EVENTS_PER_CALL: usize = 32;
+
+// gas cost of each kind of instruction
+pub const GAS_COST_INSTRUCTION: u64 = 1;
+pub const GAS_COST_LOAD: u64 = 10;
+pub const GAS_COST_STORE: u64 = 50;
+pub const GAS_COST_EMIT: u64 = 20;
+
+// gas available to a call is bought by the fee of its transaction
+pub fn get_gas_limit_for_fee(fee: u64) -> u64 {
+    (fee / FEE_PER_GAS).min(MAX_GAS_PER_CALL)
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ContractError {
+    #[error("Contract code is too big: {} bytes, maximum is {} bytes", _0, _1)]
+    CodeTooBig(usize, usize),
+    #[error("Contract code has no instruction")]
+    EmptyCode,
+    #[error("Unknown instruction '{}' at line {}", _1, _0)]
+    UnknownInstruction(usize, String),
+    #[error("Invalid operand for instruction at line {}", _0)]
+    InvalidOperand(usize),
+    #[error("Label '{}' is defined twice", _0)]
+    DuplicatedLabel(String),
+    #[error("Label '{}' is not defined", _0)]
+    UnknownLabel(String),
+    #[error("Out of gas, limit is {}", _0)]
+    OutOfGas(u64),
+    #[error("Stack underflow")]
+    StackUnderflow,
+    #[error("Stack overflow, maximum size is {}", MAX_STACK_SIZE)]
+    StackOverflow,
+    #[error("Arithmetic overflow")]
+    Overflow,
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Param '{}' is missing or is not a number", _0)]
+    InvalidParam(String),
+    #[error("Too many events emitted, maximum is {}", MAX_EVENTS_PER_CALL)]
+    TooManyEvents,
+    #[error("Execution reverted")]
+    Reverted
+}
+
+// Event emitted by a contract call, saved in the receipt of its transaction
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ContractEvent {
+    pub name: String,
+    pub value: u64
+}
+
+impl Serializer for ContractEvent {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_string(&self.name);
+        writer.write_u64(&self.value);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let name = reader.read_string()?;
+        if name.len() > MAX_KEY_SIZE {
+            return Err(ReaderError::InvalidSize)
+        }
+
+        Ok(Self {
+            name,
+            value: reader.read_u64()?
+        })
+    }
+}
+
+// All the instructions supported by the VM
+// Values are unsigned 64 bits integers, a comparison push 1 if true or 0 otherwise
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    // push the value on the stack
+    Push(u64),
+    Pop,
+    Dup,
+    Swap,
+    // arithmetic operations, pop two values and push the result
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    // comparisons, pop two values and push the result
+    Eq,
+    Lt,
+    Gt,
+    Not,
+    // push the value saved in contract storage, 0 if not set
+    Load(String),
+    // pop the value and save it in contract storage
+    Store(String),
+    // push the value of a call param
+    Param(String),
+    // pop the value and emit it in an event with the name
+    Emit(String),
+    // push the topoheight at which the call is executed
+    TopoHeight,
+    // jump to the instruction index
+    Jump(usize),
+    // pop the value and jump to the instruction index if its not 0
+    JumpIf(usize),
+    // pop the value and revert if its 0
+    Assert,
+    Revert,
+    Stop
+}
+
+// A contract program parsed from its code
+// Each line contains one instruction with its operand, `#` starts a comment
+// and `name:` defines a label that can be used by `jump` and `jumpif`
+#[derive(Clone, Debug)]
+pub struct Program {
+    instructions: Vec<Instruction>
+}
+
+impl Program {
+    pub fn parse(code: &str) -> Result<Self, ContractError> {
+        if code.len() > MAX_CONTRACT_CODE_SIZE {
+            return Err(ContractError::CodeTooBig(code.len(), MAX_CONTRACT_CODE_SIZE))
+        }
+
+        let lines: Vec<(usize, &str)> = code.lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(i, line)| (i + 1, line))
+            .collect();
+
+        // first pass to know the instruction index of each label
+        let mut labels = HashMap::new();
+        let mut index = 0;
+        for (_, line) in &lines {
+            if let Some(label) = line.strip_suffix(':') {
+                if labels.insert(label, index).is_some() {
+                    return Err(ContractError::DuplicatedLabel(label.to_owned()))
+                }
+            } else {
+                index += 1;
+            }
+        }
+
+        let mut instructions = Vec::with_capacity(index);
+        for (number, line) in lines {
+            if line.ends_with(':') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let name = parts.next().ok_or(ContractError::InvalidOperand(number))?;
+            let operand = parts.next();
+            if parts.next().is_some() {
+                return Err(ContractError::InvalidOperand(number))
+            }
+
+            let key = || -> Result<String, ContractError> {
+                match operand {
+                    Some(key) if key.len() <= MAX_KEY_SIZE => Ok(key.to_owned()),
+                    _ => Err(ContractError::InvalidOperand(number))
+                }
+            };
+            let label = || -> Result<usize, ContractError> {
+                let label = operand.ok_or(ContractError::InvalidOperand(number))?;
+                labels.get(label).copied().ok_or_else(|| ContractError::UnknownLabel(label.to_owned()))
+            };
+
+            let instruction = match name {
+                "push" => Instruction::Push(operand.and_then(|v| v.parse().ok()).ok_or(ContractError::InvalidOperand(number))?),
+                "load" => Instruction::Load(key()?),
+                "store" => Instruction::Store(key()?),
+                "param" => Instruction::Param(key()?),
+                "emit" => Instruction::Emit(key()?),
+                "jump" => Instruction::Jump(label()?),
+                "jumpif" => Instruction::JumpIf(label()?),
+                _ => {
+                    if operand.is_some() {
+                        return Err(ContractError::InvalidOperand(number))
+                    }
+
+                    match name {
+                        "pop" => Instruction::Pop,
+                        "dup" => Instruction::Dup,
+                        "swap" => Instruction::Swap,
+                        "add" => Instruction::Add,
+                        "sub" => Instruction::Sub,
+                        "mul" => Instruction::Mul,
+                        "div" => Instruction::Div,
+                        "mod" => Instruction::Mod,
+                        "eq" => Instruction::Eq,
+                        "lt" => Instruction::Lt,
+                        "gt" => Instruction::Gt,
+                        "not" => Instruction::Not,
+                        "topoheight" => Instruction::TopoHeight,
+                        "assert" => Instruction::Assert,
+                        "revert" => Instruction::Revert,
+                        "stop" => Instruction::Stop,
+                        _ => return Err(ContractError::UnknownInstruction(number, name.to_owned()))
+                    }
+                }
+            };
+            instructions.push(instruction);
+        }
+
+        if instructions.is_empty() {
+            return Err(ContractError::EmptyCode)
+        }
+
+        Ok(Self {
+            instructions
+        })
+    }
+
+    pub fn get_instructions(&self) -> &Vec<Instruction> {
+        &self.instructions
+    }
+
+    // storage keys are constants of the program
+    // so they can all be loaded before executing it
+    pub fn get_storage_keys(&self) -> HashSet<&String> {
+        self.instructions.iter().filter_map(|instruction| match instruction {
+            Instruction::Load(key) | Instruction::Store(key) => Some(key),
+            _ => None
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_program() {
+        let code = "# counter\nstart:\n  load counter # current value\n  push 1\n  add\n  store counter\n\n  jump start\n";
+        let program = Program::parse(code).unwrap();
+        assert_eq!(program.get_instructions(), &vec![
+            Instruction::Load("counter".to_owned()),
+            Instruction::Push(1),
+            Instruction::Add,
+            Instruction::Store("counter".to_owned()),
+            Instruction::Jump(0)
+        ]);
+        assert_eq!(program.get_storage_keys().len(), 1);
+
+        // a label can be used before its definition
+        let program = Program::parse("jumpif end\npush 1\nend:\nstop").unwrap();
+        assert_eq!(program.get_instructions()[0], Instruction::JumpIf(2));
+    }
+
+    #[test]
+    fn test_parse_invalid_program() {
+        assert_eq!(Program::parse("").unwrap_err(), ContractError::EmptyCode);
+        assert_eq!(Program::parse("# only a comment\nlabel:").unwrap_err(), ContractError::EmptyCode);
+        assert_eq!(Program::parse("push 1\ncall").unwrap_err(), ContractError::UnknownInstruction(2, "call".to_owned()));
+
+        let code = "stop\n".repeat(MAX_CONTRACT_CODE_SIZE / 5 + 1);
+        assert_eq!(Program::parse(&code).unwrap_err(), ContractError::CodeTooBig(code.len(), MAX_CONTRACT_CODE_SIZE));
+
+        // missing, invalid or unexpected operands
+        assert_eq!(Program::parse("push").unwrap_err(), ContractError::InvalidOperand(1));
+        assert_eq!(Program::parse("push -1").unwrap_err(), ContractError::InvalidOperand(1));
+        assert_eq!(Program::parse("push 1 2").unwrap_err(), ContractError::InvalidOperand(1));
+        assert_eq!(Program::parse("add 1").unwrap_err(), ContractError::InvalidOperand(1));
+        assert_eq!(Program::parse("store").unwrap_err(), ContractError::InvalidOperand(1));
+        assert_eq!(Program::parse("jump").unwrap_err(), ContractError::InvalidOperand(1));
+
+        assert_eq!(Program::parse("a:\na:\nstop").unwrap_err(), ContractError::DuplicatedLabel("a".to_owned()));
+        assert_eq!(Program::parse("jump end").unwrap_err(), ContractError::UnknownLabel("end".to_owned()));
+    }
+
+    #[test]
+    fn test_gas_limit_for_fee() {
+        assert_eq!(get_gas_limit_for_fee(0), 0);
+        assert_eq!(get_gas_limit_for_fee(1000 * FEE_PER_GAS + FEE_PER_GAS - 1), 1000);
+        assert_eq!(get_gas_limit_for_fee(MAX_GAS_PER_CALL * FEE_PER_GAS), MAX_GAS_PER_CALL);
+        assert_eq!(get_gas_limit_for_fee(u64::MAX), MAX_GAS_PER_CALL);
+    }
+
+    #[test]
+    fn test_key_size_limit() {
+        let key = "k".repeat(MAX_KEY_SIZE);
+        assert!(Program::parse(&format!("load {}", key)).is_ok());
+        assert!(Program::parse(&format!("param {}", key)).is_ok());
+
+        let key = "k".repeat(MAX_KEY_SIZE + 1);
+        for instruction in ["load", "store", "param", "emit"] {
+            assert_eq!(Program::parse(&format!("push 1\n{} {}", instruction, key)).unwrap_err(), ContractError::InvalidOperand(2));
+        }
+
+        let event = ContractEvent { name: key, value: 1 };
+        assert!(ContractEvent::from_bytes(&event.to_bytes()).is_err());
+    }
+}
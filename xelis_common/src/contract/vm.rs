@@ -0,0 +1,357 @@
+use std::collections::{HashMap, BTreeMap};
+use super::{
+    ContractError,
+    ContractEvent,
+    Instruction,
+    Program,
+    MAX_STACK_SIZE,
+    MAX_EVENTS_PER_CALL,
+    GAS_COST_INSTRUCTION,
+    GAS_COST_LOAD,
+    GAS_COST_STORE,
+    GAS_COST_EMIT
+};
+
+// Data available to the contract during its execution
+pub struct ExecutionContext<'a> {
+    pub params: &'a BTreeMap<String, String>,
+    pub topoheight: u64
+}
+
+// changes and events are always empty if the execution failed
+pub struct ExecutionResult {
+    pub gas_used: u64,
+    // values written by the contract
+    pub changes: HashMap<String, u64>,
+    pub events: Vec<ContractEvent>,
+    pub error: Option<ContractError>
+}
+
+struct Stack {
+    values: Vec<u64>
+}
+
+impl Stack {
+    fn push(&mut self, value: u64) -> Result<(), ContractError> {
+        if self.values.len() >= MAX_STACK_SIZE {
+            return Err(ContractError::StackOverflow)
+        }
+        self.values.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<u64, ContractError> {
+        self.values.pop().ok_or(ContractError::StackUnderflow)
+    }
+
+    // pop the two last values, the first one returned was pushed first
+    fn pop_pair(&mut self) -> Result<(u64, u64), ContractError> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        Ok((left, right))
+    }
+}
+
+// Execute the program until its end, a `stop` or an error
+// `storage` must contains the current value of every key returned by `Program::get_storage_keys`
+// Execution is fully deterministic: the same program, context and storage always give the same result
+pub fn execute(program: &Program, context: &ExecutionContext, storage: &HashMap<String, u64>, gas_limit: u64) -> ExecutionResult {
+    let mut result = ExecutionResult {
+        gas_used: 0,
+        changes: HashMap::new(),
+        events: Vec::new(),
+        error: None
+    };
+
+    if let Err(e) = run(program, context, storage, gas_limit, &mut result) {
+        result.changes.clear();
+        result.events.clear();
+        result.error = Some(e);
+    }
+
+    result
+}
+
+fn run(program: &Program, context: &ExecutionContext, storage: &HashMap<String, u64>, gas_limit: u64, result: &mut ExecutionResult) -> Result<(), ContractError> {
+    let instructions = program.get_instructions();
+    let mut stack = Stack { values: Vec::new() };
+    let changes = &mut result.changes;
+    let mut pc = 0;
+
+    while let Some(instruction) = instructions.get(pc) {
+        let cost = match instruction {
+            Instruction::Load(_) => GAS_COST_LOAD,
+            Instruction::Store(_) => GAS_COST_STORE,
+            Instruction::Emit(_) => GAS_COST_EMIT,
+            _ => GAS_COST_INSTRUCTION
+        };
+        // gas used can't be higher than the limit
+        if result.gas_used + cost > gas_limit {
+            result.gas_used = gas_limit;
+            return Err(ContractError::OutOfGas(gas_limit))
+        }
+        result.gas_used += cost;
+
+        pc += 1;
+        match instruction {
+            Instruction::Push(value) => stack.push(*value)?,
+            Instruction::Pop => {
+                stack.pop()?;
+            },
+            Instruction::Dup => {
+                let value = stack.pop()?;
+                stack.push(value)?;
+                stack.push(value)?;
+            },
+            Instruction::Swap => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push(right)?;
+                stack.push(left)?;
+            },
+            Instruction::Add => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push(left.checked_add(right).ok_or(ContractError::Overflow)?)?;
+            },
+            Instruction::Sub => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push(left.checked_sub(right).ok_or(ContractError::Overflow)?)?;
+            },
+            Instruction::Mul => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push(left.checked_mul(right).ok_or(ContractError::Overflow)?)?;
+            },
+            Instruction::Div => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push(left.checked_div(right).ok_or(ContractError::DivisionByZero)?)?;
+            },
+            Instruction::Mod => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push(left.checked_rem(right).ok_or(ContractError::DivisionByZero)?)?;
+            },
+            Instruction::Eq => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push((left == right) as u64)?;
+            },
+            Instruction::Lt => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push((left < right) as u64)?;
+            },
+            Instruction::Gt => {
+                let (left, right) = stack.pop_pair()?;
+                stack.push((left > right) as u64)?;
+            },
+            Instruction::Not => {
+                let value = stack.pop()?;
+                stack.push((value == 0) as u64)?;
+            },
+            Instruction::Load(key) => {
+                let value = changes.get(key).or_else(|| storage.get(key)).copied().unwrap_or(0);
+                stack.push(value)?;
+            },
+            Instruction::Store(key) => {
+                let value = stack.pop()?;
+                changes.insert(key.clone(), value);
+            },
+            Instruction::Param(name) => {
+                let value = context.params.get(name)
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| ContractError::InvalidParam(name.clone()))?;
+                stack.push(value)?;
+            },
+            Instruction::Emit(name) => {
+                let value = stack.pop()?;
+                if result.events.len() >= MAX_EVENTS_PER_CALL {
+                    return Err(ContractError::TooManyEvents)
+                }
+                result.events.push(ContractEvent {
+                    name: name.clone(),
+                    value
+                });
+            },
+            Instruction::TopoHeight => stack.push(context.topoheight)?,
+            Instruction::Jump(index) => {
+                pc = *index;
+            },
+            Instruction::JumpIf(index) => {
+                if stack.pop()? != 0 {
+                    pc = *index;
+                }
+            },
+            Instruction::Assert => {
+                if stack.pop()? == 0 {
+                    return Err(ContractError::Reverted)
+                }
+            },
+            Instruction::Revert => return Err(ContractError::Reverted),
+            Instruction::Stop => break
+        };
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::MAX_GAS_PER_CALL;
+
+    fn _run_with(code: &str, params: &[(&str, &str)], storage: &[(&str, u64)], gas_limit: u64) -> ExecutionResult {
+        let program = Program::parse(code).unwrap();
+        let params = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let storage = storage.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        execute(&program, &ExecutionContext { params: &params, topoheight: 42 }, &storage, gas_limit)
+    }
+
+    fn _run(code: &str) -> ExecutionResult {
+        _run_with(code, &[], &[], MAX_GAS_PER_CALL)
+    }
+
+    // value stored in the `result` key by the program
+    fn _result(code: &str) -> Result<u64, ContractError> {
+        let result = _run(&format!("{}\nstore result", code));
+        match result.error {
+            Some(e) => Err(e),
+            None => Ok(result.changes["result"])
+        }
+    }
+
+    #[test]
+    fn test_stack_instructions() {
+        assert_eq!(_result("push 7"), Ok(7));
+        assert_eq!(_result("push 1\npush 2\npop"), Ok(1));
+        assert_eq!(_result("push 3\ndup\nadd"), Ok(6));
+        assert_eq!(_result("push 10\npush 3\nswap\nsub"), Err(ContractError::Overflow));
+        assert_eq!(_result("push 3\npush 10\nswap\nsub"), Ok(7));
+        assert_eq!(_result("pop"), Err(ContractError::StackUnderflow));
+        assert_eq!(_result("dup"), Err(ContractError::StackUnderflow));
+        assert_eq!(_result("push 1\nswap"), Err(ContractError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_arithmetic_instructions() {
+        assert_eq!(_result("push 2\npush 3\nadd"), Ok(5));
+        assert_eq!(_result("push 5\npush 3\nsub"), Ok(2));
+        assert_eq!(_result("push 4\npush 3\nmul"), Ok(12));
+        assert_eq!(_result("push 7\npush 2\ndiv"), Ok(3));
+        assert_eq!(_result("push 7\npush 2\nmod"), Ok(1));
+
+        assert_eq!(_result(&format!("push {}\npush 1\nadd", u64::MAX)), Err(ContractError::Overflow));
+        assert_eq!(_result("push 1\npush 2\nsub"), Err(ContractError::Overflow));
+        assert_eq!(_result(&format!("push {}\npush 2\nmul", u64::MAX)), Err(ContractError::Overflow));
+        assert_eq!(_result("push 1\npush 0\ndiv"), Err(ContractError::DivisionByZero));
+        assert_eq!(_result("push 1\npush 0\nmod"), Err(ContractError::DivisionByZero));
+        assert_eq!(_result("push 1\nadd"), Err(ContractError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_comparison_instructions() {
+        assert_eq!(_result("push 2\npush 2\neq"), Ok(1));
+        assert_eq!(_result("push 2\npush 3\neq"), Ok(0));
+        assert_eq!(_result("push 2\npush 3\nlt"), Ok(1));
+        assert_eq!(_result("push 3\npush 2\nlt"), Ok(0));
+        assert_eq!(_result("push 3\npush 2\ngt"), Ok(1));
+        assert_eq!(_result("push 2\npush 2\ngt"), Ok(0));
+        assert_eq!(_result("push 0\nnot"), Ok(1));
+        assert_eq!(_result("push 5\nnot"), Ok(0));
+    }
+
+    #[test]
+    fn test_storage_instructions() {
+        // not set keys are read as 0
+        let result = _run_with("load a\nload b\nadd\nstore b\nload b\nstore c", &[], &[("a", 5)], MAX_GAS_PER_CALL);
+        assert!(result.error.is_none());
+        assert_eq!(result.changes.len(), 2);
+        assert_eq!(result.changes["b"], 5);
+        // a value written is read back in the same call
+        assert_eq!(result.changes["c"], 5);
+        assert_eq!(result.gas_used, 3 * GAS_COST_LOAD + 2 * GAS_COST_STORE + GAS_COST_INSTRUCTION);
+    }
+
+    #[test]
+    fn test_context_instructions() {
+        let result = _run_with("param amount\ntopoheight\nadd\nstore result", &[("amount", "8")], &[], MAX_GAS_PER_CALL);
+        assert_eq!(result.changes["result"], 50);
+
+        let result = _run_with("param amount", &[("amount", "abc")], &[], MAX_GAS_PER_CALL);
+        assert_eq!(result.error, Some(ContractError::InvalidParam("amount".to_owned())));
+        let result = _run("param amount");
+        assert_eq!(result.error, Some(ContractError::InvalidParam("amount".to_owned())));
+    }
+
+    #[test]
+    fn test_emit_instruction() {
+        let result = _run("push 1\nemit first\npush 2\nemit second");
+        assert!(result.error.is_none());
+        assert_eq!(result.events, vec![ContractEvent { name: "first".to_owned(), value: 1 }, ContractEvent { name: "second".to_owned(), value: 2 }]);
+
+        let code = "loop:\npush 1\nemit event\njump loop";
+        let result = _run(code);
+        assert_eq!(result.error, Some(ContractError::TooManyEvents));
+        assert!(result.events.is_empty());
+    }
+
+    #[test]
+    fn test_control_flow_instructions() {
+        // sum of 1 to 10
+        let code = "push 10\nstore i\nloop:\nload i\nload sum\nadd\nstore sum\nload i\npush 1\nsub\ndup\nstore i\njumpif loop\nload sum\nstore result";
+        assert_eq!(_run(code).changes["result"], 55);
+
+        assert_eq!(_result("push 0\njumpif end\npush 1\nstore result\nstop\nend:\npush 2"), Ok(1));
+        assert_eq!(_result("push 1\njumpif end\npush 1\nstore result\nstop\nend:\npush 2"), Ok(2));
+        assert_eq!(_result("jump end\npush 1\nend:\npush 2"), Ok(2));
+        assert_eq!(_result("push 1\nassert\npush 3"), Ok(3));
+
+        // a stopped call keeps its changes
+        let result = _run("push 1\nstore a\nstop\npush 2\nstore a");
+        assert!(result.error.is_none());
+        assert_eq!(result.changes["a"], 1);
+    }
+
+    #[test]
+    fn test_failed_execution_discards_changes() {
+        let result = _run("push 1\nstore a\npush 1\nemit event\npush 0\nassert");
+        assert_eq!(result.error, Some(ContractError::Reverted));
+        assert!(result.changes.is_empty());
+        assert!(result.events.is_empty());
+        assert!(result.gas_used > 0);
+
+        let result = _run("push 1\nstore a\nrevert");
+        assert_eq!(result.error, Some(ContractError::Reverted));
+        assert!(result.changes.is_empty());
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let result = _run("loop:\npush 1\njump loop");
+        assert_eq!(result.error, Some(ContractError::StackOverflow));
+        // one push and one jump for each value in the stack, the last push failed
+        assert_eq!(result.gas_used, (2 * MAX_STACK_SIZE as u64 + 1) * GAS_COST_INSTRUCTION);
+    }
+
+    #[test]
+    fn test_out_of_gas() {
+        let result = _run_with("loop:\njump loop", &[], &[], 1000);
+        assert_eq!(result.error, Some(ContractError::OutOfGas(1000)));
+        assert_eq!(result.gas_used, 1000);
+
+        // an instruction is not executed if its cost is above the gas left
+        let result = _run_with("push 1\nstore a", &[], &[], GAS_COST_STORE);
+        assert_eq!(result.error, Some(ContractError::OutOfGas(GAS_COST_STORE)));
+        assert_eq!(result.gas_used, GAS_COST_STORE);
+        assert!(result.changes.is_empty());
+
+        let result = _run_with("push 1\nstore a", &[], &[], GAS_COST_STORE + GAS_COST_INSTRUCTION);
+        assert!(result.error.is_none());
+        assert_eq!(result.gas_used, GAS_COST_STORE + GAS_COST_INSTRUCTION);
+    }
+
+    #[test]
+    fn test_deterministic_execution() {
+        let code = "param a\nload b\nadd\ndup\nstore b\nemit sum";
+        let first = _run_with(code, &[("a", "3")], &[("b", 4)], MAX_GAS_PER_CALL);
+        let second = _run_with(code, &[("a", "3")], &[("b", 4)], MAX_GAS_PER_CALL);
+        assert_eq!(first.gas_used, second.gas_used);
+        assert_eq!(first.changes, second.changes);
+        assert_eq!(first.events, second.events);
+    }
+}
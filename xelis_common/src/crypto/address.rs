@@ -9,6 +9,7 @@ use super::bech32::{Bech32Error, encode, convert_bits, decode};
 use super::key::PublicKey;
 use log::debug;
 use serde::de::Error as SerdeError;
+use schemars::{JsonSchema, gen::SchemaGenerator, schema::{Schema, SchemaObject, InstanceType}};
 use anyhow::Error;
 
 #[derive(Clone)]
@@ -197,6 +198,21 @@ impl<'a> serde::Deserialize<'a> for Address {
     }
 }
 
+// Address is serialized in its bech32 string format (normal or integrated)
+impl JsonSchema for Address {
+    fn schema_name() -> String {
+        "Address".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some("address".to_owned()),
+            ..Default::default()
+        }.into()
+    }
+}
+
 impl Display for Address {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.as_string().unwrap())
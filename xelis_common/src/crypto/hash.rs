@@ -2,11 +2,14 @@ use crate::serializer::{Writer, Serializer, ReaderError, Reader};
 use std::fmt::{Display, Error, Formatter};
 use serde::de::Error as SerdeError;
 use serde::{Deserialize, Serialize};
+use schemars::{JsonSchema, gen::SchemaGenerator, schema::{Schema, SchemaObject, InstanceType, StringValidation}};
 use sha3::{Keccak256, Digest};
 use std::convert::TryInto;
 use std::hash::Hasher;
 
 pub const HASH_SIZE: usize = 32; // 32 bytes / 256 bits
+// number of values hashed at once by hash_x4
+pub const HASH_LANES: usize = 4;
 
 #[derive(Eq, PartialEq, PartialOrd, Ord, Clone, Debug)]
 pub struct Hash([u8; HASH_SIZE]);
@@ -82,6 +85,25 @@ impl<'a> Deserialize<'a> for Hash {
     }
 }
 
+// Hash is serialized as a hex string of 64 characters
+impl JsonSchema for Hash {
+    fn schema_name() -> String {
+        "Hash".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                max_length: Some(HASH_SIZE as u32 * 2),
+                min_length: Some(HASH_SIZE as u32 * 2),
+                pattern: Some("^[0-9a-fA-F]*$".to_owned())
+            })),
+            ..Default::default()
+        }.into()
+    }
+}
+
 pub trait Hashable: Serializer {
     #[inline(always)]
     fn hash(&self) -> Hash {
@@ -96,8 +118,77 @@ pub fn hash(value: &[u8]) -> Hash {
     Hash(result)
 }
 
+// name of the implementation used by `hash_x4` on this CPU
+pub fn get_hash_x4_implementation() -> &'static str {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return "avx2"
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        return "neon"
+    }
+
+    "scalar"
+}
+
+// hash 4 values at once, each result is the same as using `hash` on the value
+// AVX2 (x86_64) or NEON (aarch64) is used if the CPU supports it and the values have the same length,
+// otherwise they are hashed one by one
+pub fn hash_x4(values: [&[u8]; HASH_LANES]) -> [Hash; HASH_LANES] {
+    let len = values[0].len();
+    if values.iter().all(|value| value.len() == len) {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support has been checked just above
+            return unsafe { super::keccak_x4::avx2::keccak256_x4(values) }
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // SAFETY: NEON support has been checked just above
+            return unsafe { super::keccak_x4::neon::keccak256_x4(values) }
+        }
+    }
+
+    values.map(hash)
+}
+
 impl AsRef<[u8]> for Hash {
     fn as_ref(&self) -> &[u8] {
         &self.0
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_hash_x4_implementation, hash, hash_x4, HASH_LANES};
+
+    fn _values(len: usize) -> Vec<Vec<u8>> {
+        (0..HASH_LANES).map(|lane| (0..len).map(|i| (i * 7 + lane) as u8).collect()).collect()
+    }
+
+    #[test]
+    fn test_hash_x4_same_length() {
+        // uses AVX2 or NEON if supported by the CPU, otherwise the values are hashed one by one
+        assert!(["avx2", "neon", "scalar"].contains(&get_hash_x4_implementation()));
+        for len in [0, 1, 31, 32, 64, 112, 135, 136, 137, 200, 271, 272, 273, 1000] {
+            let values = _values(len);
+            let results = hash_x4([&values[0], &values[1], &values[2], &values[3]]);
+            for (value, result) in values.iter().zip(results) {
+                assert_eq!(result, hash(value), "length {}", len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_x4_different_lengths() {
+        // values with different lengths always use the fallback
+        let values = [_values(0).remove(0), _values(1).remove(1), _values(136).remove(2), _values(300).remove(3)];
+        let results = hash_x4([&values[0], &values[1], &values[2], &values[3]]);
+        for (value, result) in values.iter().zip(results) {
+            assert_eq!(result, hash(value));
+        }
+    }
 }
\ No newline at end of file
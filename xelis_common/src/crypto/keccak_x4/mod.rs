@@ -0,0 +1,35 @@
+// Keccak256 of 4 messages at once using the SIMD instructions of the CPU
+// only used through `hash_x4` which checks the CPU support first
+
+#[cfg(target_arch = "x86_64")]
+pub mod avx2;
+#[cfg(target_arch = "aarch64")]
+pub mod neon;
+
+// bytes absorbed per permutation for a 256 bits output
+const RATE: usize = 136;
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808A, 0x8000000080008000,
+    0x000000000000808B, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008A, 0x0000000000000088, 0x0000000080008009, 0x000000008000000A,
+    0x000000008000808B, 0x800000000000008B, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800A, 0x800000008000000A,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008
+];
+
+#[inline]
+fn read_word(block: &[u8], index: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&block[index * 8..index * 8 + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+// last block with the Keccak padding, it can't be empty as at least the padding is added
+fn pad_last_block(remaining: &[u8]) -> [u8; RATE] {
+    let mut block = [0u8; RATE];
+    block[..remaining.len()].copy_from_slice(remaining);
+    block[remaining.len()] ^= 0x01;
+    block[RATE - 1] ^= 0x80;
+    block
+}
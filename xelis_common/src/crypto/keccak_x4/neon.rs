@@ -0,0 +1,169 @@
+// Keccak256 of 4 messages at once using NEON
+// each 128 bits register contains the same state word of 2 messages, so 2 states are used for the 4 messages
+
+use std::arch::aarch64::*;
+use crate::crypto::hash::{Hash, HASH_SIZE};
+use super::{pad_last_block, read_word, RATE, ROUND_CONSTANTS};
+
+type State = [uint64x2_t; 25];
+
+// the left shift is merged with the right one using a shift right and insert
+macro_rules! rotate_left {
+    ($value: expr, $n: literal) => {
+        vsriq_n_u64::<{ 64 - $n }>(vshlq_n_u64::<$n>($value), $value)
+    };
+}
+
+#[target_feature(enable = "neon")]
+unsafe fn keccak_f1600(state: &mut State) {
+    let mut c = [vdupq_n_u64(0); 5];
+    let mut b = [vdupq_n_u64(0); 25];
+    for round_constant in ROUND_CONSTANTS {
+        // theta
+        for x in 0..5 {
+            c[x] = veorq_u64(
+                veorq_u64(state[x], state[x + 5]),
+                veorq_u64(veorq_u64(state[x + 10], state[x + 15]), state[x + 20])
+            );
+        }
+        for x in 0..5 {
+            let d = veorq_u64(c[(x + 4) % 5], rotate_left!(c[(x + 1) % 5], 1));
+            for y in 0..5 {
+                state[x + 5 * y] = veorq_u64(state[x + 5 * y], d);
+            }
+        }
+
+        // rho and pi: the word at (x, y) is rotated and moved to (y, 2x + 3y)
+        b[0] = state[0];
+        b[16] = rotate_left!(state[5], 36);
+        b[7] = rotate_left!(state[10], 3);
+        b[23] = rotate_left!(state[15], 41);
+        b[14] = rotate_left!(state[20], 18);
+        b[10] = rotate_left!(state[1], 1);
+        b[1] = rotate_left!(state[6], 44);
+        b[17] = rotate_left!(state[11], 10);
+        b[8] = rotate_left!(state[16], 45);
+        b[24] = rotate_left!(state[21], 2);
+        b[20] = rotate_left!(state[2], 62);
+        b[11] = rotate_left!(state[7], 6);
+        b[2] = rotate_left!(state[12], 43);
+        b[18] = rotate_left!(state[17], 15);
+        b[9] = rotate_left!(state[22], 61);
+        b[5] = rotate_left!(state[3], 28);
+        b[21] = rotate_left!(state[8], 55);
+        b[12] = rotate_left!(state[13], 25);
+        b[3] = rotate_left!(state[18], 21);
+        b[19] = rotate_left!(state[23], 56);
+        b[15] = rotate_left!(state[4], 27);
+        b[6] = rotate_left!(state[9], 20);
+        b[22] = rotate_left!(state[14], 39);
+        b[13] = rotate_left!(state[19], 8);
+        b[4] = rotate_left!(state[24], 14);
+
+        // chi, vbicq_u64(a, b) computes a & !b
+        for y in 0..5 {
+            for x in 0..5 {
+                state[x + 5 * y] = veorq_u64(
+                    b[x + 5 * y],
+                    vbicq_u64(b[(x + 2) % 5 + 5 * y], b[(x + 1) % 5 + 5 * y])
+                );
+            }
+        }
+
+        // iota
+        state[0] = veorq_u64(state[0], vdupq_n_u64(round_constant));
+    }
+}
+
+// xor a block of each message in the state and permute it
+#[target_feature(enable = "neon")]
+unsafe fn absorb(state: &mut State, blocks: [&[u8]; 2]) {
+    for (i, word) in state.iter_mut().enumerate().take(RATE / 8) {
+        let words = [read_word(blocks[0], i), read_word(blocks[1], i)];
+        *word = veorq_u64(*word, vld1q_u64(words.as_ptr()));
+    }
+    keccak_f1600(state);
+}
+
+// both messages must have the same length
+#[target_feature(enable = "neon")]
+unsafe fn keccak256_x2(values: [&[u8]; 2]) -> [Hash; 2] {
+    let len = values[0].len();
+    let mut state: State = [vdupq_n_u64(0); 25];
+
+    let full_blocks = len / RATE;
+    for i in 0..full_blocks {
+        absorb(&mut state, values.map(|value| &value[i * RATE..(i + 1) * RATE]));
+    }
+
+    let last = values.map(|value| pad_last_block(&value[full_blocks * RATE..]));
+    absorb(&mut state, [&last[0], &last[1]]);
+
+    let mut output = [[0u8; HASH_SIZE]; 2];
+    for i in 0..HASH_SIZE / 8 {
+        let mut words = [0u64; 2];
+        vst1q_u64(words.as_mut_ptr(), state[i]);
+        for (bytes, word) in output.iter_mut().zip(words) {
+            bytes[i * 8..i * 8 + 8].copy_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    output.map(Hash::new)
+}
+
+// all messages must have the same length
+#[target_feature(enable = "neon")]
+pub unsafe fn keccak256_x4(values: [&[u8]; 4]) -> [Hash; 4] {
+    let [a, b] = keccak256_x2([values[0], values[1]]);
+    let [c, d] = keccak256_x2([values[2], values[3]]);
+    [a, b, c, d]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::hash::{hash, Hash};
+    use super::keccak256_x4;
+
+    // Keccak256 of "" and "abc"
+    const EMPTY_HASH: &str = "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470";
+    const ABC_HASH: &str = "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45";
+
+    fn _supported() -> bool {
+        std::arch::is_aarch64_feature_detected!("neon")
+    }
+
+    fn _keccak256_x4(values: [&[u8]; 4]) -> [Hash; 4] {
+        // SAFETY: only called once the CPU support has been checked
+        unsafe { keccak256_x4(values) }
+    }
+
+    #[test]
+    fn test_known_answers() {
+        if !_supported() {
+            return
+        }
+
+        for result in _keccak256_x4([b"", b"", b"", b""]) {
+            assert_eq!(result.to_hex(), EMPTY_HASH);
+        }
+        for result in _keccak256_x4([b"abc", b"abc", b"abc", b"abc"]) {
+            assert_eq!(result.to_hex(), ABC_HASH);
+        }
+    }
+
+    #[test]
+    fn test_lanes_are_independent() {
+        if !_supported() {
+            return
+        }
+
+        // lengths around the rate to cover the padding in its own block and multiple blocks
+        for len in [1, 32, 135, 136, 137, 271, 272, 300] {
+            let values: Vec<Vec<u8>> = (0..4u8).map(|lane| (0..len).map(|i| (i as u8).wrapping_mul(31) ^ lane).collect()).collect();
+            let results = _keccak256_x4([&values[0], &values[1], &values[2], &values[3]]);
+            for (value, result) in values.iter().zip(results) {
+                assert_eq!(result, hash(value), "length {}", len);
+            }
+        }
+    }
+}
@@ -7,6 +7,7 @@ use std::cmp::Ordering;
 use std::fmt::{Display, Error, Formatter};
 use rand::{rngs::OsRng, RngCore};
 use std::hash::Hasher;
+use schemars::{JsonSchema, gen::SchemaGenerator, schema::{Schema, SchemaObject, InstanceType, StringValidation}};
 
 pub const KEY_LENGTH: usize = 32;
 pub const SIGNATURE_LENGTH: usize = 64;
@@ -102,6 +103,21 @@ impl<'de> serde::Deserialize<'de> for PublicKey {
     }
 }
 
+// PublicKey is serialized as a normal address of the current network
+impl JsonSchema for PublicKey {
+    fn schema_name() -> String {
+        "PublicKey".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some("address".to_owned()),
+            ..Default::default()
+        }.into()
+    }
+}
+
 impl Display for PublicKey {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         write!(f, "{}", &self.to_address(get_network().is_mainnet()))
@@ -235,6 +251,24 @@ impl<'de> serde::Deserialize<'de> for Signature {
     }
 }
 
+impl JsonSchema for Signature {
+    fn schema_name() -> String {
+        "Signature".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                max_length: Some(SIGNATURE_LENGTH as u32 * 2),
+                min_length: Some(SIGNATURE_LENGTH as u32 * 2),
+                pattern: Some("^[0-9a-fA-F]*$".to_owned())
+            })),
+            ..Default::default()
+        }.into()
+    }
+}
+
 impl Display for Signature {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         write!(f, "{}", &self.to_hex())
@@ -2,4 +2,7 @@ pub mod hash;
 pub mod key;
 pub mod bech32;
 pub mod address;
-pub mod elgamal;
\ No newline at end of file
+pub mod elgamal;
+pub mod threshold;
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+mod keccak_x4;
\ No newline at end of file
@@ -0,0 +1,448 @@
+use std::fmt::{Display, Error, Formatter};
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_TABLE,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    traits::{Identity, IsIdentity}
+};
+use ed25519_dalek::{Digest, Sha512};
+use rand::rngs::OsRng;
+use schemars::{JsonSchema, gen::SchemaGenerator, schema::{Schema, SchemaObject, InstanceType, StringValidation}};
+use serde::de::Error as SerdeError;
+use thiserror::Error;
+use crate::serializer::{Reader, ReaderError, Serializer, Writer};
+use super::{hash::Hash, key::{PublicKey, Signature, KEY_LENGTH}};
+
+// This file implements a 2-of-3 threshold Schnorr signature (FROST with a trusted dealer)
+// The aggregated signature is a standard ed25519 signature of the group key,
+// so a transaction of the shared account is verified like any other one
+pub const THRESHOLD: usize = 2;
+pub const PARTICIPANTS: u8 = 3;
+
+// domain separation of the binding factors
+const BINDING_DOMAIN: &[u8] = b"XELIS_THRESHOLD_BINDING";
+
+#[derive(Error, Debug)]
+pub enum ThresholdError {
+    #[error("Expected {} commitments, got {}", THRESHOLD, _0)]
+    InvalidCommitmentsCount(usize),
+    #[error("Invalid participant index {}", _0)]
+    InvalidIndex(u8),
+    #[error("Duplicated participant index {}", _0)]
+    DuplicatedIndex(u8),
+    #[error("Commitment of participant {} is not a valid point", _0)]
+    InvalidCommitment(u8),
+    #[error("No commitment found for participant {}", _0)]
+    MissingCommitment(u8),
+    #[error("Commitment of participant {} doesn't match its nonces", _0)]
+    CommitmentMismatch(u8),
+    #[error("Aggregated signature is invalid")]
+    InvalidSignature
+}
+
+// Share of the group private key owned by a participant
+pub struct KeyShare {
+    // x coordinate of the share, from 1 to PARTICIPANTS
+    index: u8,
+    secret: Scalar,
+    group_key: PublicKey
+}
+
+// Secret nonces of a participant for one signature, they must never be used twice
+pub struct SigningNonces {
+    hiding: Scalar,
+    binding: Scalar
+}
+
+// Public part of the nonces, shared with the other signers before signing
+#[derive(Clone, Debug, PartialEq)]
+pub struct SigningCommitment {
+    index: u8,
+    hiding: CompressedEdwardsY,
+    binding: CompressedEdwardsY
+}
+
+// Partial signature of a participant, the signers shares are summed to build the signature
+#[derive(Clone, Debug)]
+pub struct SignatureShare {
+    index: u8,
+    share: Scalar
+}
+
+impl KeyShare {
+    // the caller is the trusted dealer: it splits a random group key in PARTICIPANTS shares, any THRESHOLD of them can sign
+    // the dealer knows the group private key while generating the shares, so it must only keep its own share
+    // and discard the shares of the other participants once they are distributed, no participant knows it afterwards
+    pub fn generate() -> Vec<KeyShare> {
+        let coefficients: Vec<Scalar> = (0..THRESHOLD).map(|_| Scalar::random(&mut OsRng)).collect();
+        let group_key = to_public_key(&(&coefficients[0] * &ED25519_BASEPOINT_TABLE));
+
+        (1..=PARTICIPANTS).map(|index| {
+            // evaluate the polynomial at the index of the participant
+            let x = Scalar::from(index as u64);
+            let secret = coefficients.iter().rev().fold(Scalar::zero(), |acc, coefficient| acc * x + coefficient);
+            KeyShare {
+                index,
+                secret,
+                group_key: group_key.clone()
+            }
+        }).collect()
+    }
+
+    pub fn get_index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn get_group_key(&self) -> &PublicKey {
+        &self.group_key
+    }
+
+    // first round: generate the nonces of a new signature
+    pub fn commit(&self) -> (SigningNonces, SigningCommitment) {
+        let nonces = SigningNonces {
+            hiding: Scalar::random(&mut OsRng),
+            binding: Scalar::random(&mut OsRng)
+        };
+        let commitment = nonces.to_commitment(self.index);
+        (nonces, commitment)
+    }
+
+    // second round: sign the message using the commitments of all the signers
+    // nonces are consumed so they can't be reused for another message
+    pub fn sign(&self, nonces: SigningNonces, message: &Hash, commitments: &[SigningCommitment]) -> Result<SignatureShare, ThresholdError> {
+        let commitments = sort_commitments(commitments)?;
+        let own = commitments.iter()
+            .find(|commitment| commitment.index == self.index)
+            .ok_or(ThresholdError::MissingCommitment(self.index))?;
+
+        if *own != nonces.to_commitment(self.index) {
+            return Err(ThresholdError::CommitmentMismatch(self.index))
+        }
+
+        let (group_commitment, binding_factors) = compute_group_commitment(&self.group_key, message, &commitments)?;
+        let challenge = compute_challenge(&group_commitment, &self.group_key, message);
+        let binding_factor = binding_factors.iter()
+            .find(|(index, _)| *index == self.index)
+            .map(|(_, factor)| *factor)
+            .ok_or(ThresholdError::MissingCommitment(self.index))?;
+
+        let indexes: Vec<u8> = commitments.iter().map(|commitment| commitment.index).collect();
+        let lambda = compute_lagrange_coefficient(self.index, &indexes);
+        Ok(SignatureShare {
+            index: self.index,
+            share: nonces.hiding + nonces.binding * binding_factor + lambda * self.secret * challenge
+        })
+    }
+}
+
+impl SigningNonces {
+    fn to_commitment(&self, index: u8) -> SigningCommitment {
+        SigningCommitment {
+            index,
+            hiding: (&self.hiding * &ED25519_BASEPOINT_TABLE).compress(),
+            binding: (&self.binding * &ED25519_BASEPOINT_TABLE).compress()
+        }
+    }
+}
+
+impl SigningCommitment {
+    pub fn get_index(&self) -> u8 {
+        self.index
+    }
+}
+
+impl SignatureShare {
+    pub fn get_index(&self) -> u8 {
+        self.index
+    }
+}
+
+// build the signature of the group from the shares of all the signers
+// the signature is verified, so an invalid share is detected before broadcasting it
+pub fn aggregate(group_key: &PublicKey, message: &Hash, commitments: &[SigningCommitment], shares: &[SignatureShare]) -> Result<Signature, ThresholdError> {
+    let commitments = sort_commitments(commitments)?;
+    if shares.len() != commitments.len() {
+        return Err(ThresholdError::InvalidCommitmentsCount(shares.len()))
+    }
+
+    let mut s = Scalar::zero();
+    for commitment in &commitments {
+        let share = shares.iter()
+            .find(|share| share.index == commitment.index)
+            .ok_or(ThresholdError::MissingCommitment(commitment.index))?;
+        s += share.share;
+    }
+
+    let (group_commitment, _) = compute_group_commitment(group_key, message, &commitments)?;
+    let mut bytes = [0u8; 64];
+    bytes[0..32].copy_from_slice(group_commitment.compress().as_bytes());
+    bytes[32..64].copy_from_slice(s.as_bytes());
+
+    let signature = Signature::from_bytes(&bytes).map_err(|_| ThresholdError::InvalidSignature)?;
+    if !group_key.verify_signature(message, &signature) {
+        return Err(ThresholdError::InvalidSignature)
+    }
+    Ok(signature)
+}
+
+fn to_public_key(point: &EdwardsPoint) -> PublicKey {
+    PublicKey::from_bytes(point.compress().as_bytes()).expect("valid point")
+}
+
+// the commitments are ordered by index so every signer computes the same binding factors
+fn sort_commitments(commitments: &[SigningCommitment]) -> Result<Vec<SigningCommitment>, ThresholdError> {
+    if commitments.len() != THRESHOLD {
+        return Err(ThresholdError::InvalidCommitmentsCount(commitments.len()))
+    }
+
+    let mut commitments = commitments.to_vec();
+    commitments.sort_by_key(|commitment| commitment.index);
+    for (i, commitment) in commitments.iter().enumerate() {
+        if commitment.index == 0 || commitment.index > PARTICIPANTS {
+            return Err(ThresholdError::InvalidIndex(commitment.index))
+        }
+
+        if i > 0 && commitments[i - 1].index == commitment.index {
+            return Err(ThresholdError::DuplicatedIndex(commitment.index))
+        }
+    }
+    Ok(commitments)
+}
+
+// R = sum(D_i + p_i * E_i), each binding factor p_i depends on the group key, the message and all the commitments (RFC 9591 section 4.4)
+fn compute_group_commitment(group_key: &PublicKey, message: &Hash, commitments: &[SigningCommitment]) -> Result<(EdwardsPoint, Vec<(u8, Scalar)>), ThresholdError> {
+    let mut encoded = Vec::with_capacity(commitments.len() * (1 + KEY_LENGTH * 2));
+    for commitment in commitments {
+        encoded.push(commitment.index);
+        encoded.extend_from_slice(commitment.hiding.as_bytes());
+        encoded.extend_from_slice(commitment.binding.as_bytes());
+    }
+
+    let mut group_commitment = EdwardsPoint::identity();
+    let mut binding_factors = Vec::with_capacity(commitments.len());
+    for commitment in commitments {
+        let hiding = commitment.hiding.decompress().ok_or(ThresholdError::InvalidCommitment(commitment.index))?;
+        let binding = commitment.binding.decompress().ok_or(ThresholdError::InvalidCommitment(commitment.index))?;
+        // a nonce of zero would leak the share of the signer
+        if hiding.is_identity() || binding.is_identity() {
+            return Err(ThresholdError::InvalidCommitment(commitment.index))
+        }
+
+        let mut hasher = Sha512::new();
+        hasher.update(BINDING_DOMAIN);
+        hasher.update(group_key.as_bytes());
+        hasher.update(message.as_bytes());
+        hasher.update(&encoded);
+        hasher.update([commitment.index]);
+        let factor = Scalar::from_hash(hasher);
+
+        group_commitment += hiding + binding * factor;
+        binding_factors.push((commitment.index, factor));
+    }
+    Ok((group_commitment, binding_factors))
+}
+
+// same challenge as the ed25519 verification: H(R || A || M)
+fn compute_challenge(group_commitment: &EdwardsPoint, group_key: &PublicKey, message: &Hash) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(group_commitment.compress().as_bytes());
+    hasher.update(group_key.as_bytes());
+    hasher.update(message.as_bytes());
+    Scalar::from_hash(hasher)
+}
+
+// coefficient of the share at x = 0 for the signers set
+fn compute_lagrange_coefficient(index: u8, indexes: &[u8]) -> Scalar {
+    let x = Scalar::from(index as u64);
+    let mut numerator = Scalar::one();
+    let mut denominator = Scalar::one();
+    for other in indexes.iter().filter(|other| **other != index) {
+        let other = Scalar::from(*other as u64);
+        numerator *= other;
+        denominator *= other - x;
+    }
+    numerator * denominator.invert()
+}
+
+fn read_scalar(reader: &mut Reader) -> Result<Scalar, ReaderError> {
+    Scalar::from_canonical_bytes(reader.read_bytes_32()?).ok_or(ReaderError::InvalidValue)
+}
+
+fn read_index(reader: &mut Reader) -> Result<u8, ReaderError> {
+    let index = reader.read_u8()?;
+    if index == 0 || index > PARTICIPANTS {
+        return Err(ReaderError::InvalidValue)
+    }
+    Ok(index)
+}
+
+impl Serializer for KeyShare {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(self.index);
+        writer.write_bytes(self.secret.as_bytes());
+        self.group_key.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(Self {
+            index: read_index(reader)?,
+            secret: read_scalar(reader)?,
+            group_key: PublicKey::read(reader)?
+        })
+    }
+}
+
+impl Serializer for SigningCommitment {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(self.index);
+        writer.write_bytes(self.hiding.as_bytes());
+        writer.write_bytes(self.binding.as_bytes());
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(Self {
+            index: read_index(reader)?,
+            hiding: CompressedEdwardsY(reader.read_bytes_32()?),
+            binding: CompressedEdwardsY(reader.read_bytes_32()?)
+        })
+    }
+}
+
+impl Serializer for SignatureShare {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(self.index);
+        writer.write_bytes(self.share.as_bytes());
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(Self {
+            index: read_index(reader)?,
+            share: read_scalar(reader)?
+        })
+    }
+}
+
+// commitments and shares are exchanged in the API as their serialized bytes in hex
+macro_rules! impl_hex_serde {
+    ($type: ty, $name: expr, $size: expr) => {
+        impl serde::Serialize for $type {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where S: serde::Serializer {
+                serializer.serialize_str(&self.to_hex())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where D: serde::Deserializer<'de> {
+                let hex = String::deserialize(deserializer)?;
+                Self::from_hex(hex).map_err(SerdeError::custom)
+            }
+        }
+
+        impl JsonSchema for $type {
+            fn schema_name() -> String {
+                $name.to_owned()
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                SchemaObject {
+                    instance_type: Some(InstanceType::String.into()),
+                    string: Some(Box::new(StringValidation {
+                        max_length: Some($size as u32 * 2),
+                        min_length: Some($size as u32 * 2),
+                        pattern: Some("^[0-9a-fA-F]*$".to_owned())
+                    })),
+                    ..Default::default()
+                }.into()
+            }
+        }
+
+        impl Display for $type {
+            fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+                write!(f, "{}", &self.to_hex())
+            }
+        }
+    };
+}
+
+impl_hex_serde!(SigningCommitment, "SigningCommitment", 1 + KEY_LENGTH * 2);
+impl_hex_serde!(SignatureShare, "SignatureShare", 1 + KEY_LENGTH);
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::hash::hash;
+    use curve25519_dalek::{edwards::EdwardsPoint, traits::Identity};
+    use super::{aggregate, KeyShare, SigningCommitment, ThresholdError};
+
+    #[test]
+    fn test_threshold_signature() {
+        let shares = KeyShare::generate();
+        let group_key = shares[0].get_group_key().clone();
+        let message = hash(b"Hello World!");
+
+        // every pair of participants can sign
+        for (a, b) in [(0, 1), (0, 2), (1, 2)] {
+            let (nonces_a, commitment_a) = shares[a].commit();
+            let (nonces_b, commitment_b) = shares[b].commit();
+            let commitments = [commitment_a, commitment_b];
+
+            let share_a = shares[a].sign(nonces_a, &message, &commitments).unwrap();
+            let share_b = shares[b].sign(nonces_b, &message, &commitments).unwrap();
+            let signature = aggregate(&group_key, &message, &commitments, &[share_a, share_b]).unwrap();
+            assert!(group_key.verify_signature(&message, &signature));
+        }
+    }
+
+    #[test]
+    fn test_threshold_signature_invalid_share() {
+        let shares = KeyShare::generate();
+        let group_key = shares[0].get_group_key().clone();
+        let message = hash(b"Hello World!");
+
+        let (nonces_a, commitment_a) = shares[0].commit();
+        let (nonces_b, commitment_b) = shares[1].commit();
+        let commitments = [commitment_a, commitment_b];
+
+        // second signer signs another message
+        let share_a = shares[0].sign(nonces_a, &message, &commitments).unwrap();
+        let share_b = shares[1].sign(nonces_b, &hash(b"Another message"), &commitments).unwrap();
+        assert!(matches!(aggregate(&group_key, &message, &commitments, &[share_a, share_b]), Err(ThresholdError::InvalidSignature)));
+
+        // a single participant can't sign
+        let (nonces, commitment) = shares[2].commit();
+        assert!(matches!(shares[2].sign(nonces, &message, &[commitment]), Err(ThresholdError::InvalidCommitmentsCount(1))));
+    }
+
+    #[test]
+    fn test_nonces_mismatch() {
+        let shares = KeyShare::generate();
+        let message = hash(b"Hello World!");
+
+        let (nonces_a, _) = shares[0].commit();
+        let (_, commitment_a) = shares[0].commit();
+        let (_, commitment_b) = shares[1].commit();
+        assert!(matches!(shares[0].sign(nonces_a, &message, &[commitment_a, commitment_b]), Err(ThresholdError::CommitmentMismatch(1))));
+    }
+
+    #[test]
+    fn test_identity_commitment() {
+        let shares = KeyShare::generate();
+        let message = hash(b"Hello World!");
+        let identity = EdwardsPoint::identity().compress();
+
+        // the second signer uses a zero nonce
+        for zero_hiding in [true, false] {
+            let (nonces_a, commitment_a) = shares[0].commit();
+            let (_, commitment_b) = shares[1].commit();
+            let commitment_b = if zero_hiding {
+                SigningCommitment { hiding: identity, ..commitment_b }
+            } else {
+                SigningCommitment { binding: identity, ..commitment_b }
+            };
+            let commitments = [commitment_a, commitment_b];
+
+            assert!(matches!(shares[0].sign(nonces_a, &message, &commitments), Err(ThresholdError::InvalidCommitment(2))));
+        }
+    }
+}
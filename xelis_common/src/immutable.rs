@@ -1,6 +1,7 @@
 use std::{/*rc::Rc,*/ sync::Arc, ops::Deref};
 
 use serde::{Serialize, Deserialize};
+use schemars::{JsonSchema, gen::SchemaGenerator, schema::Schema};
 
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, Hash, PartialEq)]
 #[serde(untagged)]
@@ -10,6 +11,21 @@ pub enum Immutable<T: Clone> {
     //Rc(Rc<T>),
 }
 
+// Both variants are serialized the same way, so it has the schema of T
+impl<T: Clone + JsonSchema> JsonSchema for Immutable<T> {
+    fn is_referenceable() -> bool {
+        T::is_referenceable()
+    }
+
+    fn schema_name() -> String {
+        T::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        T::json_schema(gen)
+    }
+}
+
 impl<T: Clone> Immutable<T> {
     pub fn get_inner(&self) -> &T {
         match &self {
@@ -0,0 +1,135 @@
+use std::sync::atomic::AtomicUsize;
+use reqwest::{
+    Client as HttpClient,
+    Certificate,
+    Identity,
+    header::{HeaderMap, HeaderName, HeaderValue}
+};
+use tokio::sync::Mutex;
+use crate::crypto::key::PublicKey;
+use super::{JsonRPCClient, JsonRPCError, JsonRPCResult, RetryPolicy};
+
+// Authentication sent in each request to the server
+#[derive(Clone, Debug)]
+pub enum Authentication {
+    Basic {
+        username: String,
+        password: Option<String>
+    },
+    Bearer(String)
+}
+
+// Builder to configure a JsonRPCClient
+// useful to reach a daemon behind a reverse proxy using HTTPS and/or authentication
+pub struct JsonRPCClientBuilder {
+    target: String,
+    // PEM encoded certificates added to the trusted roots
+    root_certificates: Vec<Vec<u8>>,
+    // trust the built-in roots in addition to the custom ones
+    built_in_roots: bool,
+    // PEM encoded client certificate and its private key
+    identity: Option<Vec<u8>>,
+    headers: Vec<(String, String)>,
+    authentication: Option<Authentication>,
+    pipelining: bool,
+    retry_policy: RetryPolicy,
+    node_key: Option<PublicKey>
+}
+
+impl JsonRPCClientBuilder {
+    pub fn new(target: String) -> Self {
+        Self {
+            target,
+            root_certificates: Vec::new(),
+            built_in_roots: true,
+            identity: None,
+            headers: Vec::new(),
+            authentication: None,
+            pipelining: false,
+            retry_policy: RetryPolicy::default(),
+            node_key: None
+        }
+    }
+
+    pub fn with_root_certificate(mut self, pem: Vec<u8>) -> Self {
+        self.root_certificates.push(pem);
+        self
+    }
+
+    // only trust the custom root certificates
+    pub fn with_built_in_roots(mut self, built_in_roots: bool) -> Self {
+        self.built_in_roots = built_in_roots;
+        self
+    }
+
+    // client certificate followed by its private key in PEM format
+    pub fn with_identity(mut self, pem: Vec<u8>) -> Self {
+        self.identity = Some(pem);
+        self
+    }
+
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.headers.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn with_basic_auth<U: Into<String>>(mut self, username: U, password: Option<String>) -> Self {
+        self.authentication = Some(Authentication::Basic { username: username.into(), password });
+        self
+    }
+
+    pub fn with_bearer_auth<T: Into<String>>(mut self, token: T) -> Self {
+        self.authentication = Some(Authentication::Bearer(token.into()));
+        self
+    }
+
+    pub fn with_pipelining(mut self, pipelining: bool) -> Self {
+        self.pipelining = pipelining;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    // only accept responses signed by this node key
+    // useful when connecting to a public node through an untrusted proxy
+    pub fn with_node_key(mut self, node_key: PublicKey) -> Self {
+        self.node_key = Some(node_key);
+        self
+    }
+
+    pub fn build(self) -> JsonRPCResult<JsonRPCClient> {
+        let mut headers = HeaderMap::with_capacity(self.headers.len());
+        for (key, value) in self.headers {
+            let name = HeaderName::from_bytes(key.as_bytes()).map_err(|_| JsonRPCError::InvalidHeader(key.clone()))?;
+            let value = HeaderValue::from_str(&value).map_err(|_| JsonRPCError::InvalidHeader(key))?;
+            headers.insert(name, value);
+        }
+
+        let mut builder = HttpClient::builder()
+            .default_headers(headers)
+            .tls_built_in_root_certs(self.built_in_roots);
+
+        for pem in self.root_certificates {
+            builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+
+        if let Some(pem) = self.identity {
+            builder = builder.identity(Identity::from_pem(&pem)?);
+        }
+
+        Ok(JsonRPCClient {
+            http: builder.build()?,
+            target: self.target,
+            count: AtomicUsize::new(0),
+            pipelining: self.pipelining,
+            pipeline: Mutex::new(Vec::new()),
+            retry_policy: self.retry_policy,
+            authentication: self.authentication,
+            node_key: self.node_key,
+            local_handler: None,
+        })
+    }
+}
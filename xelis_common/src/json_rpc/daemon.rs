@@ -0,0 +1,401 @@
+use std::{borrow::Cow, sync::Arc};
+use crate::{
+    api::{daemon::{
+        BlockResponse,
+        GetTopBlockParams,
+        GetBlockAtTopoHeightParams,
+        GetBlocksAtHeightParams,
+        GetBlockByHashParams,
+        GetBlockTemplateParams,
+        GetBlockTemplateResult,
+        SubmitBlockParams,
+        GetBalanceParams,
+        GetBalanceAtTopoHeightParams,
+        GetNonceParams,
+        HasNonceParams,
+        GetNonceResult,
+        HasNonceResult,
+        GetLastBalanceResult,
+        GetInfoResult,
+        SubmitTransactionParams,
+        SubmitTransactionResult,
+        WaitForConfirmationParams,
+        WaitForConfirmationResult,
+        GetTransactionParams,
+        PeerEntry,
+        P2pStatusResult,
+        GetSyncStatusResult,
+        GetP2pMetricsResult,
+        GetBandwidthResult,
+        GetMinersResult,
+        GetTopoHeightRangeParams,
+        GetHeightRangeParams,
+        GetTransactionsParams,
+        TransactionResponse,
+        GetAccountHistoryParams,
+        GetDepositsParams,
+        AccountHistoryEntry,
+        GetAccountAssetsParams,
+        GetAssetParams,
+        GetAssetsParams,
+        GetAccountsParams,
+        IsTxExecutedInBlockParams,
+        DevFeeThreshold,
+        HardFork,
+        SizeOnDiskResult,
+        GetTransactionConfirmationsResult,
+        GetAccountPendingParams,
+        GetAccountPendingResult,
+        GetTipsInfoResult,
+        EvaluateBlockParams,
+        EvaluateBlockResult,
+        ResolveNameParams,
+        ResolveNameResult
+    }, DepositGroup, ValidateAddressParams, ValidateAddressResult},
+    account::VersionedBalance,
+    asset::{AssetData, AssetWithData},
+    block::{Block, BlockHeader, Difficulty},
+    crypto::{address::Address, hash::Hash, key::PublicKey},
+    serializer::Serializer,
+    transaction::Transaction
+};
+use super::{JsonRPCClient, JsonRPCResult};
+
+pub type BlockHeaderResponse = BlockResponse<'static, BlockHeader>;
+pub type BlockWithTxsResponse = BlockResponse<'static, Block>;
+pub type TransactionWithDataResponse = TransactionResponse<'static, Arc<Transaction>>;
+
+// Typed client for the daemon JSON-RPC API
+// Each method use the same params and result structs as the daemon
+pub struct DaemonClient {
+    client: JsonRPCClient
+}
+
+impl DaemonClient {
+    pub fn new(target: String) -> Self {
+        Self::with_client(JsonRPCClient::new(target))
+    }
+
+    pub fn with_client(client: JsonRPCClient) -> Self {
+        Self {
+            client
+        }
+    }
+
+    // underlying client, to send requests not covered here
+    pub fn get_client(&self) -> &JsonRPCClient {
+        &self.client
+    }
+
+    pub async fn get_version(&self) -> JsonRPCResult<String> {
+        self.client.call("get_version").await
+    }
+
+    pub async fn get_height(&self) -> JsonRPCResult<u64> {
+        self.client.call("get_height").await
+    }
+
+    pub async fn get_topoheight(&self) -> JsonRPCResult<u64> {
+        self.client.call("get_topoheight").await
+    }
+
+    pub async fn get_stableheight(&self) -> JsonRPCResult<u64> {
+        self.client.call("get_stableheight").await
+    }
+
+    pub async fn get_block_template(&self, address: &Address) -> JsonRPCResult<GetBlockTemplateResult> {
+        self.client.call_with("get_block_template", &GetBlockTemplateParams {
+            address: Cow::Borrowed(address),
+            aux_commitment: None
+        }).await
+    }
+
+    pub async fn get_block_at_topoheight(&self, topoheight: u64) -> JsonRPCResult<BlockHeaderResponse> {
+        self.client.call_with("get_block_at_topoheight", &GetBlockAtTopoHeightParams {
+            topoheight,
+            include_txs: false
+        }).await
+    }
+
+    pub async fn get_block_with_txs_at_topoheight(&self, topoheight: u64) -> JsonRPCResult<BlockWithTxsResponse> {
+        self.client.call_with("get_block_at_topoheight", &GetBlockAtTopoHeightParams {
+            topoheight,
+            include_txs: true
+        }).await
+    }
+
+    pub async fn get_blocks_at_height(&self, height: u64) -> JsonRPCResult<Vec<BlockHeaderResponse>> {
+        self.client.call_with("get_blocks_at_height", &GetBlocksAtHeightParams {
+            height,
+            include_txs: false
+        }).await
+    }
+
+    pub async fn get_block_by_hash(&self, hash: &Hash) -> JsonRPCResult<BlockHeaderResponse> {
+        self.client.call_with("get_block_by_hash", &GetBlockByHashParams {
+            hash: Cow::Borrowed(hash),
+            include_txs: false
+        }).await
+    }
+
+    pub async fn get_block_with_txs_by_hash(&self, hash: &Hash) -> JsonRPCResult<BlockWithTxsResponse> {
+        self.client.call_with("get_block_by_hash", &GetBlockByHashParams {
+            hash: Cow::Borrowed(hash),
+            include_txs: true
+        }).await
+    }
+
+    pub async fn get_top_block(&self) -> JsonRPCResult<BlockHeaderResponse> {
+        self.client.call_with("get_top_block", &GetTopBlockParams {
+            include_txs: false
+        }).await
+    }
+
+    pub async fn get_top_block_with_txs(&self) -> JsonRPCResult<BlockWithTxsResponse> {
+        self.client.call_with("get_top_block", &GetTopBlockParams {
+            include_txs: true
+        }).await
+    }
+
+    // block template must be in hex format
+    pub async fn submit_block(&self, block_template: String) -> JsonRPCResult<bool> {
+        self.client.call_with("submit_block", &SubmitBlockParams {
+            block_template
+        }).await
+    }
+
+    pub async fn get_last_balance(&self, address: &Address, asset: &Hash) -> JsonRPCResult<GetLastBalanceResult> {
+        self.client.call_with("get_last_balance", &GetBalanceParams {
+            address: Cow::Borrowed(address),
+            asset: Cow::Borrowed(asset)
+        }).await
+    }
+
+    pub async fn get_balance_at_topoheight(&self, address: &Address, asset: &Hash, topoheight: u64) -> JsonRPCResult<VersionedBalance> {
+        self.client.call_with("get_balance_at_topoheight", &GetBalanceAtTopoHeightParams {
+            address: Cow::Borrowed(address),
+            asset: Cow::Borrowed(asset),
+            topoheight
+        }).await
+    }
+
+    pub async fn get_info(&self) -> JsonRPCResult<GetInfoResult> {
+        self.client.call("get_info").await
+    }
+
+    pub async fn get_nonce(&self, address: &Address, topoheight: Option<u64>) -> JsonRPCResult<GetNonceResult> {
+        self.client.call_with("get_nonce", &GetNonceParams {
+            address: Cow::Borrowed(address),
+            topoheight
+        }).await
+    }
+
+    pub async fn get_account_pending(&self, address: &Address) -> JsonRPCResult<GetAccountPendingResult<'static>> {
+        self.client.call_with("get_account_pending", &GetAccountPendingParams {
+            address: Cow::Borrowed(address)
+        }).await
+    }
+
+    pub async fn has_nonce(&self, address: &Address, topoheight: Option<u64>) -> JsonRPCResult<bool> {
+        let result: HasNonceResult = self.client.call_with("has_nonce", &HasNonceParams {
+            address: Cow::Borrowed(address),
+            topoheight
+        }).await?;
+        Ok(result.exist)
+    }
+
+    pub async fn get_asset(&self, asset: &Hash) -> JsonRPCResult<AssetData> {
+        self.client.call_with("get_asset", &GetAssetParams {
+            asset: asset.clone()
+        }).await
+    }
+
+    pub async fn get_assets(&self, skip: Option<usize>, maximum: Option<usize>, minimum_topoheight: Option<u64>, maximum_topoheight: Option<u64>) -> JsonRPCResult<Vec<AssetWithData>> {
+        self.client.call_with("get_assets", &GetAssetsParams {
+            skip,
+            maximum,
+            minimum_topoheight,
+            maximum_topoheight
+        }).await
+    }
+
+    pub async fn count_assets(&self) -> JsonRPCResult<usize> {
+        self.client.call("count_assets").await
+    }
+
+    pub async fn count_accounts(&self) -> JsonRPCResult<usize> {
+        self.client.call("count_accounts").await
+    }
+
+    pub async fn count_transactions(&self) -> JsonRPCResult<usize> {
+        self.client.call("count_transactions").await
+    }
+
+    pub async fn submit_transaction(&self, transaction: &Transaction) -> JsonRPCResult<SubmitTransactionResult> {
+        self.submit_transaction_with_reference(transaction, None).await
+    }
+
+    pub async fn submit_transaction_with_reference(&self, transaction: &Transaction, reference: Option<&str>) -> JsonRPCResult<SubmitTransactionResult> {
+        self.client.call_with("submit_transaction", &SubmitTransactionParams {
+            data: transaction.to_hex(),
+            reference: reference.map(str::to_owned)
+        }).await
+    }
+
+    pub async fn wait_for_confirmation(&self, hash: &Hash, confirmations: u64, timeout: Option<u64>) -> JsonRPCResult<WaitForConfirmationResult> {
+        self.client.call_with("wait_for_confirmation", &WaitForConfirmationParams {
+            hash: Cow::Borrowed(hash),
+            confirmations,
+            timeout
+        }).await
+    }
+
+    pub async fn get_transaction(&self, hash: &Hash) -> JsonRPCResult<TransactionWithDataResponse> {
+        self.client.call_with("get_transaction", &GetTransactionParams {
+            hash: Cow::Borrowed(hash)
+        }).await
+    }
+
+    pub async fn get_transaction_confirmations(&self, hash: &Hash) -> JsonRPCResult<GetTransactionConfirmationsResult<'static>> {
+        self.client.call_with("get_transaction_confirmations", &GetTransactionParams {
+            hash: Cow::Borrowed(hash)
+        }).await
+    }
+
+    pub async fn p2p_status(&self) -> JsonRPCResult<P2pStatusResult<'static>> {
+        self.client.call("p2p_status").await
+    }
+
+    pub async fn get_sync_status(&self) -> JsonRPCResult<GetSyncStatusResult> {
+        self.client.call("get_sync_status").await
+    }
+
+    pub async fn get_p2p_metrics(&self) -> JsonRPCResult<GetP2pMetricsResult<'static>> {
+        self.client.call("get_p2p_metrics").await
+    }
+
+    pub async fn get_bandwidth(&self) -> JsonRPCResult<GetBandwidthResult> {
+        self.client.call("get_bandwidth").await
+    }
+
+    pub async fn get_miners(&self) -> JsonRPCResult<GetMinersResult> {
+        self.client.call("get_miners").await
+    }
+
+    pub async fn get_peers(&self) -> JsonRPCResult<Vec<PeerEntry<'static>>> {
+        self.client.call("get_peers").await
+    }
+
+    pub async fn get_mempool(&self) -> JsonRPCResult<Vec<TransactionWithDataResponse>> {
+        self.client.call("get_mempool").await
+    }
+
+    pub async fn get_tips(&self) -> JsonRPCResult<Vec<Hash>> {
+        self.client.call("get_tips").await
+    }
+
+    pub async fn get_tips_info(&self) -> JsonRPCResult<GetTipsInfoResult> {
+        self.client.call("get_tips_info").await
+    }
+
+    pub async fn evaluate_block(&self, hash: &Hash, cumulative_difficulty: Option<Difficulty>) -> JsonRPCResult<EvaluateBlockResult> {
+        self.client.call_with("evaluate_block", &EvaluateBlockParams {
+            hash: Cow::Borrowed(hash),
+            cumulative_difficulty
+        }).await
+    }
+
+    pub async fn get_dag_order(&self, start_topoheight: Option<u64>, end_topoheight: Option<u64>) -> JsonRPCResult<Vec<Hash>> {
+        self.client.call_with("get_dag_order", &GetTopoHeightRangeParams {
+            start_topoheight,
+            end_topoheight
+        }).await
+    }
+
+    pub async fn get_blocks_range_by_topoheight(&self, start_topoheight: Option<u64>, end_topoheight: Option<u64>) -> JsonRPCResult<Vec<BlockHeaderResponse>> {
+        self.client.call_with("get_blocks_range_by_topoheight", &GetTopoHeightRangeParams {
+            start_topoheight,
+            end_topoheight
+        }).await
+    }
+
+    pub async fn get_blocks_range_by_height(&self, start_height: Option<u64>, end_height: Option<u64>) -> JsonRPCResult<Vec<BlockHeaderResponse>> {
+        self.client.call_with("get_blocks_range_by_height", &GetHeightRangeParams {
+            start_height,
+            end_height
+        }).await
+    }
+
+    // a transaction not found is returned as None, order is kept
+    pub async fn get_transactions(&self, tx_hashes: Vec<Hash>) -> JsonRPCResult<Vec<Option<TransactionWithDataResponse>>> {
+        self.client.call_with("get_transactions", &GetTransactionsParams {
+            tx_hashes
+        }).await
+    }
+
+    pub async fn get_account_history(&self, address: Address, asset: Hash, minimum_topoheight: Option<u64>, maximum_topoheight: Option<u64>) -> JsonRPCResult<Vec<AccountHistoryEntry>> {
+        self.client.call_with("get_account_history", &GetAccountHistoryParams {
+            address,
+            asset,
+            minimum_topoheight,
+            maximum_topoheight
+        }).await
+    }
+
+    pub async fn validate_address(&self, address: &String) -> JsonRPCResult<ValidateAddressResult> {
+        self.client.call_with("validate_address", &ValidateAddressParams {
+            address: Cow::Borrowed(address)
+        }).await
+    }
+
+    pub async fn get_deposits(&self, address: Address, asset: Option<Hash>, minimum_topoheight: Option<u64>, maximum_topoheight: Option<u64>, confirmations: u64) -> JsonRPCResult<Vec<DepositGroup>> {
+        self.client.call_with("get_deposits", &GetDepositsParams {
+            address,
+            asset,
+            minimum_topoheight,
+            maximum_topoheight,
+            confirmations
+        }).await
+    }
+
+    pub async fn get_account_assets(&self, address: Address) -> JsonRPCResult<Vec<Hash>> {
+        self.client.call_with("get_account_assets", &GetAccountAssetsParams {
+            address
+        }).await
+    }
+
+    pub async fn get_accounts(&self, skip: Option<usize>, maximum: Option<usize>, minimum_topoheight: Option<u64>, maximum_topoheight: Option<u64>) -> JsonRPCResult<Vec<PublicKey>> {
+        self.client.call_with("get_accounts", &GetAccountsParams {
+            skip,
+            maximum,
+            minimum_topoheight,
+            maximum_topoheight
+        }).await
+    }
+
+    pub async fn is_tx_executed_in_block(&self, tx_hash: &Hash, block_hash: &Hash) -> JsonRPCResult<bool> {
+        self.client.call_with("is_tx_executed_in_block", &IsTxExecutedInBlockParams {
+            tx_hash: Cow::Borrowed(tx_hash),
+            block_hash: Cow::Borrowed(block_hash)
+        }).await
+    }
+
+    pub async fn get_dev_fee_thresholds(&self) -> JsonRPCResult<Vec<DevFeeThreshold>> {
+        self.client.call("get_dev_fee_thresholds").await
+    }
+
+    pub async fn get_size_on_disk(&self) -> JsonRPCResult<SizeOnDiskResult> {
+        self.client.call("get_size_on_disk").await
+    }
+
+    pub async fn get_hard_forks(&self) -> JsonRPCResult<Vec<HardFork>> {
+        self.client.call("get_hard_forks").await
+    }
+
+    pub async fn resolve_name(&self, name: &str, topoheight: Option<u64>) -> JsonRPCResult<ResolveNameResult> {
+        self.client.call_with("resolve_name", &ResolveNameParams {
+            name: Cow::Owned(name.to_owned()),
+            topoheight
+        }).await
+    }
+}
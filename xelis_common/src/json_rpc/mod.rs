@@ -0,0 +1,539 @@
+use async_trait::async_trait;
+use reqwest::{Client as HttpClient, header::{CONTENT_TYPE, HeaderMap}};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use thiserror::Error;
+use tokio::{sync::{Mutex, oneshot}, task::yield_now, time::sleep};
+use log::debug;
+use std::{collections::HashMap, sync::{Arc, atomic::{AtomicUsize, Ordering}}};
+use crate::{
+    api::{signature::{verify_response, SIGNATURE_HEADER, SIGNATURE_TIMESTAMP_HEADER, MAX_SIGNATURE_AGE}, ErrorCode, ErrorData},
+    crypto::key::{PublicKey, Signature},
+    serializer::Serializer,
+    utils::get_current_time
+};
+
+mod websocket;
+mod retry;
+mod builder;
+mod daemon;
+
+pub use websocket::{WebSocketJsonRPCClient, WebSocketJsonRPCClientShared, EventReceiver};
+pub use retry::RetryPolicy;
+pub use builder::{JsonRPCClientBuilder, Authentication};
+pub use daemon::{DaemonClient, BlockHeaderResponse, BlockWithTxsResponse, TransactionWithDataResponse};
+
+const JSON_RPC_VERSION: &str = "2.0";
+const PARSE_ERROR_CODE: i16 = -32700;
+const INVALID_REQUEST_CODE: i16 = -32600;
+const METHOD_NOT_FOUND_CODE: i16 = -32601;
+const INVALID_PARAMS_CODE: i16 = -32602;
+const INTERNAL_ERROR_CODE: i16 = -32603;
+
+pub type JsonRPCResult<T> = Result<T, JsonRPCError>;
+
+#[derive(Deserialize)]
+struct JsonRPCErrorResponse {
+    code: i16,
+    message: String,
+    #[serde(default)]
+    data: Option<Value>,
+}
+
+#[derive(Debug, Error)]
+pub enum JsonRPCError {
+    #[error("Server failed to parse request JSON data")]
+    ParseError,
+    #[error("Server received invalid JSON-RPC request")]
+    InvalidRequest,
+    #[error("Unknown method requested to the server")]
+    MethodNotFound,
+    #[error("Invalid parameters were provided")]
+    InvalidParams,
+    #[error("Server internal JSON-RPC error: {}", message)]
+    InternalError {
+        message: String,
+        data: Option<String>,
+    },
+    #[error("Server returned error: [{}] {}", code, message)]
+    ServerError {
+        code: i16,
+        message: String,
+        data: Option<String>,
+    },
+    #[error("Server returned a response without result")]
+    MissingResult,
+    #[error("Error while (de)serializing JSON data: {}", _0)]
+    SerializationError(#[from] serde_json::Error),
+    #[error("HTTP error during JSON-RPC communication: {}", _0)]
+    HttpError(#[from] reqwest::Error),
+    #[error("WebSocket error during JSON-RPC communication: {}", _0)]
+    WebSocketError(Box<tokio_tungstenite::tungstenite::Error>),
+    #[error("WebSocket connection is closed")]
+    ConnectionClosed,
+    #[error("Event is not subscribed")]
+    EventNotSubscribed,
+    #[error("Batch request failed: {}", _0)]
+    BatchFailed(String),
+    #[error("Invalid HTTP header: {}", _0)]
+    InvalidHeader(String),
+    #[error("Invalid response signature: {}", _0)]
+    InvalidSignature(&'static str),
+}
+
+impl JsonRPCError {
+    // stable code set by the server in the error data, if any
+    pub fn get_error_code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::InternalError { data: Some(data), .. } | Self::ServerError { data: Some(data), .. } => {
+                serde_json::from_str::<ErrorData>(data).ok().map(|data| data.code)
+            },
+            _ => None
+        }
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for JsonRPCError {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        JsonRPCError::WebSocketError(Box::new(e))
+    }
+}
+
+// Multiple requests sent at once in a single JSON-RPC batch
+// each request is identified by the id returned when adding it
+#[derive(Default)]
+pub struct BatchRequest {
+    requests: Vec<Value>
+}
+
+impl BatchRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, method: &str) -> usize {
+        self.push(json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method
+        }))
+    }
+
+    pub fn add_with<P>(&mut self, method: &str, params: &P) -> usize
+        where P: Serialize + Sized
+    {
+        self.push(json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method,
+            "params": params
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    // ids only have to be unique inside the same batch
+    fn push(&mut self, mut request: Value) -> usize {
+        let id = self.requests.len();
+        request["id"] = json!(id);
+        self.requests.push(request);
+        id
+    }
+}
+
+// Responses of a batch, mapped back by request id
+pub struct BatchResponse {
+    responses: HashMap<usize, Value>
+}
+
+impl BatchResponse {
+    // get the result of the request with this id
+    pub fn get<R: DeserializeOwned>(&mut self, id: usize) -> JsonRPCResult<R> {
+        parse_response(self.take(id)?)
+    }
+
+    fn take(&mut self, id: usize) -> JsonRPCResult<Value> {
+        self.responses.remove(&id).ok_or(JsonRPCError::MissingResult)
+    }
+}
+
+type PipelinedRequest = (Value, oneshot::Sender<JsonRPCResult<Value>>);
+
+// Execute the requests in the same process instead of sending them over HTTP
+// used when the client and the server are running in the same binary
+#[async_trait]
+pub trait LocalRequestHandler: Send + Sync {
+    // response of a single or batch request, errors are returned as JSON-RPC error responses
+    async fn handle_local_request(&self, body: &[u8]) -> Value;
+}
+
+pub struct JsonRPCClient {
+    http: HttpClient,
+    target: String,
+    count: AtomicUsize,
+    // when enabled, concurrent calls are merged in a single batch request
+    pipelining: bool,
+    pipeline: Mutex<Vec<PipelinedRequest>>,
+    retry_policy: RetryPolicy,
+    authentication: Option<Authentication>,
+    // if set, every response must be signed by this node key
+    node_key: Option<PublicKey>,
+    // if set, requests are given to it instead of the HTTP server
+    local_handler: Option<Arc<dyn LocalRequestHandler>>,
+}
+
+impl JsonRPCClient {
+    pub fn new(target: String) -> Self {
+        Self::with_pipelining(target, false)
+    }
+
+    pub fn with_pipelining(target: String, pipelining: bool) -> Self {
+        JsonRPCClient {
+            http: HttpClient::new(),
+            target,
+            count: AtomicUsize::new(0),
+            pipelining,
+            pipeline: Mutex::new(Vec::new()),
+            retry_policy: RetryPolicy::default(),
+            authentication: None,
+            node_key: None,
+            local_handler: None,
+        }
+    }
+
+    // the target is only used in the logs
+    pub fn with_local_handler(target: String, handler: Arc<dyn LocalRequestHandler>) -> Self {
+        let mut client = Self::new(target);
+        client.local_handler = Some(handler);
+        client
+    }
+
+    // configure the client (TLS, headers, authentication...)
+    pub fn builder(target: String) -> JsonRPCClientBuilder {
+        JsonRPCClientBuilder::new(target)
+    }
+
+    pub fn is_pipelining(&self) -> bool {
+        self.pipelining
+    }
+
+    pub fn get_retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    pub fn get_node_key(&self) -> Option<&PublicKey> {
+        self.node_key.as_ref()
+    }
+
+    // reject all responses not signed by this node key
+    pub fn set_node_key(&mut self, node_key: Option<PublicKey>) {
+        self.node_key = node_key;
+    }
+
+    pub async fn call<R: DeserializeOwned>(&self, method: &str) -> JsonRPCResult<R> {
+        let id = self.count.fetch_add(1, Ordering::SeqCst);
+        self.dispatch(json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method,
+            "id": id
+        })).await
+    }
+
+    pub async fn call_with<P, R>(&self, method: &str, params: &P) -> JsonRPCResult<R>
+        where P: Serialize + Sized, R: DeserializeOwned
+    {
+        let id = self.count.fetch_add(1, Ordering::SeqCst);
+        self.dispatch(json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method,
+            "id": id,
+            "params": params
+        })).await
+    }
+
+    // send all requests in one HTTP request and map the results back by id
+    pub async fn call_batch(&self, batch: BatchRequest) -> JsonRPCResult<BatchResponse> {
+        if batch.is_empty() {
+            return Ok(BatchResponse { responses: HashMap::new() })
+        }
+
+        // retry it only if all requests can be executed again
+        let idempotent = batch.requests.iter()
+            .all(|request| self.retry_policy.is_idempotent(request["method"].as_str().unwrap_or_default()));
+        let response = self.post(&batch.requests, idempotent, true).await?;
+
+        let values = match response {
+            Value::Array(values) => values,
+            // the whole batch was rejected
+            value => {
+                parse_response::<Value>(value)?;
+                return Err(JsonRPCError::MissingResult)
+            }
+        };
+
+        let mut responses = HashMap::with_capacity(values.len());
+        for value in values {
+            if let Some(id) = value.get("id").and_then(Value::as_u64) {
+                responses.insert(id as usize, value);
+            }
+        }
+        Ok(BatchResponse { responses })
+    }
+
+    pub async fn notify(&self, method: &str) -> JsonRPCResult<()> {
+        self.post(&json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method
+        }), self.retry_policy.is_idempotent(method), false).await?;
+        Ok(())
+    }
+
+    pub async fn notify_with<P>(&self, method: &str, params: P) -> JsonRPCResult<()>
+        where P: Serialize + Sized
+    {
+        self.post(&json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method,
+            "params": &params
+        }), self.retry_policy.is_idempotent(method), false).await?;
+        Ok(())
+    }
+
+    pub async fn send<R: DeserializeOwned>(&self, value: Value) -> JsonRPCResult<R> {
+        let response = self.send_raw(value).await?;
+        parse_response(response)
+    }
+
+    async fn send_raw(&self, value: Value) -> JsonRPCResult<Value> {
+        let idempotent = self.retry_policy.is_idempotent(value["method"].as_str().unwrap_or_default());
+        self.post(&value, idempotent, true).await
+    }
+
+    // send the body to the server, retrying on transient errors following the retry policy
+    async fn post<B: Serialize>(&self, body: &B, idempotent: bool, expect_response: bool) -> JsonRPCResult<Value> {
+        let mut retry = 0;
+        loop {
+            match self.post_once(body, expect_response).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    if retry >= self.retry_policy.get_max_retries() || !self.retry_policy.is_retryable(&e, idempotent) {
+                        return Err(e)
+                    }
+
+                    retry += 1;
+                    let delay = self.retry_policy.get_delay(retry);
+                    debug!("Error while sending request to {}: {}, retrying in {}ms ({}/{})", self.target, e, delay.as_millis(), retry, self.retry_policy.get_max_retries());
+                    sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    async fn post_once<B: Serialize>(&self, body: &B, expect_response: bool) -> JsonRPCResult<Value> {
+        // keep the raw body to verify the signature of the response
+        let body = serde_json::to_vec(body)?;
+        // no signature to verify, the handler is in the same process
+        if let Some(handler) = &self.local_handler {
+            let response = handler.handle_local_request(&body).await;
+            return Ok(if expect_response { response } else { Value::Null })
+        }
+
+        let mut request = self.http.post(&self.target)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(timeout) = self.retry_policy.get_timeout() {
+            request = request.timeout(timeout);
+        }
+
+        request = match &self.authentication {
+            Some(Authentication::Basic { username, password }) => request.basic_auth(username, password.as_ref()),
+            Some(Authentication::Bearer(token)) => request.bearer_auth(token),
+            None => request
+        };
+
+        let response = request.send().await?.error_for_status()?;
+        if !expect_response && self.node_key.is_none() {
+            return Ok(Value::Null)
+        }
+
+        let Some(node_key) = &self.node_key else {
+            return Ok(response.json().await?)
+        };
+
+        let headers = response.headers().clone();
+        let bytes = response.bytes().await?;
+        verify_signed_response(node_key, &headers, &body, &bytes)?;
+        if !expect_response {
+            return Ok(Value::Null)
+        }
+
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    async fn dispatch<R: DeserializeOwned>(&self, value: Value) -> JsonRPCResult<R> {
+        if !self.pipelining {
+            return self.send(value).await
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let flush = {
+            let mut pipeline = self.pipeline.lock().await;
+            pipeline.push((value, sender));
+            pipeline.len() == 1
+        };
+
+        // first request in the pipeline is responsible to send it
+        if flush {
+            // let the other concurrent calls join the pipeline
+            yield_now().await;
+            let requests = std::mem::take(&mut *self.pipeline.lock().await);
+            self.flush_pipeline(requests).await;
+        }
+
+        let response = receiver.await.map_err(|_| JsonRPCError::MissingResult)??;
+        parse_response(response)
+    }
+
+    async fn flush_pipeline(&self, mut requests: Vec<PipelinedRequest>) {
+        if requests.len() == 1 {
+            if let Some((value, sender)) = requests.pop() {
+                let _ = sender.send(self.send_raw(value).await);
+            }
+            return;
+        }
+
+        let mut batch = BatchRequest::new();
+        let mut senders = Vec::with_capacity(requests.len());
+        for (value, sender) in requests {
+            senders.push((batch.push(value), sender));
+        }
+
+        match self.call_batch(batch).await {
+            Ok(mut response) => {
+                for (id, sender) in senders {
+                    let _ = sender.send(response.take(id));
+                }
+            },
+            Err(e) => {
+                let message = e.to_string();
+                for (_, sender) in senders {
+                    let _ = sender.send(Err(JsonRPCError::BatchFailed(message.clone())));
+                }
+            }
+        };
+    }
+}
+
+// check that the response was signed by the node key for this request
+fn verify_signed_response(node_key: &PublicKey, headers: &HeaderMap, request: &[u8], response: &[u8]) -> JsonRPCResult<()> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let signature = header(SIGNATURE_HEADER)
+        .and_then(|value| Signature::from_hex(value.to_owned()).ok())
+        .ok_or(JsonRPCError::InvalidSignature("missing or invalid signature"))?;
+    let timestamp: u64 = header(SIGNATURE_TIMESTAMP_HEADER)
+        .and_then(|value| value.parse().ok())
+        .ok_or(JsonRPCError::InvalidSignature("missing or invalid timestamp"))?;
+
+    if get_current_time().abs_diff(timestamp) > MAX_SIGNATURE_AGE {
+        return Err(JsonRPCError::InvalidSignature("timestamp is too far from local time"))
+    }
+
+    if !verify_response(node_key, &signature, timestamp, request, response) {
+        return Err(JsonRPCError::InvalidSignature("signature doesn't match the node key"))
+    }
+
+    Ok(())
+}
+
+// parse a JSON-RPC response, shared by all transports
+fn parse_response<R: DeserializeOwned>(mut response: Value) -> JsonRPCResult<R> {
+    if let Some(error) = response.get_mut("error") {
+        let error: JsonRPCErrorResponse = serde_json::from_value(error.take())?;
+        let data = match error.data {
+            Some(content) => Some(serde_json::to_string_pretty(&content)?),
+            None => None,
+        };
+
+        return Err(match error.code {
+            PARSE_ERROR_CODE => JsonRPCError::ParseError,
+            INVALID_REQUEST_CODE => JsonRPCError::InvalidRequest,
+            METHOD_NOT_FOUND_CODE => JsonRPCError::MethodNotFound,
+            INVALID_PARAMS_CODE => JsonRPCError::InvalidParams,
+            INTERNAL_ERROR_CODE => JsonRPCError::InternalError {
+                message: error.message.clone(),
+                data,
+            },
+            code => JsonRPCError::ServerError {
+                code,
+                message: error.message.clone(),
+                data,
+            },
+        });
+    }
+
+    Ok(serde_json::from_value(
+        response
+            .get_mut("result")
+            .ok_or(JsonRPCError::MissingResult)?
+            .take(),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderMap;
+    use crate::{
+        api::signature::{sign_response, MAX_SIGNATURE_AGE, SIGNATURE_HEADER, SIGNATURE_TIMESTAMP_HEADER},
+        crypto::key::KeyPair,
+        utils::get_current_time
+    };
+    use super::{verify_signed_response, JsonRPCError};
+
+    const REQUEST: &[u8] = br#"{"jsonrpc":"2.0","method":"get_info","id":1}"#;
+    const RESPONSE: &[u8] = br#"{"jsonrpc":"2.0","result":{"height":1},"id":1}"#;
+
+    fn _signed_headers(keypair: &KeyPair, timestamp: u64, response: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SIGNATURE_HEADER, sign_response(keypair, timestamp, REQUEST, response).to_hex().parse().unwrap());
+        headers.insert(SIGNATURE_TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_verify_signed_response() {
+        let keypair = KeyPair::new();
+        let headers = _signed_headers(&keypair, get_current_time(), RESPONSE);
+        assert!(verify_signed_response(keypair.get_public_key(), &headers, REQUEST, RESPONSE).is_ok());
+
+        // signed by another node
+        assert!(matches!(verify_signed_response(KeyPair::new().get_public_key(), &headers, REQUEST, RESPONSE), Err(JsonRPCError::InvalidSignature(_))));
+        // no signature at all
+        assert!(matches!(verify_signed_response(keypair.get_public_key(), &HeaderMap::new(), REQUEST, RESPONSE), Err(JsonRPCError::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_verify_tampered_response() {
+        let keypair = KeyPair::new();
+        let headers = _signed_headers(&keypair, get_current_time(), RESPONSE);
+        let tampered = br#"{"jsonrpc":"2.0","result":{"height":2},"id":1}"#;
+        assert!(matches!(verify_signed_response(keypair.get_public_key(), &headers, REQUEST, tampered), Err(JsonRPCError::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_verify_stale_response() {
+        let keypair = KeyPair::new();
+        for timestamp in [get_current_time() - MAX_SIGNATURE_AGE - 10, get_current_time() + MAX_SIGNATURE_AGE + 10] {
+            let headers = _signed_headers(&keypair, timestamp, RESPONSE);
+            assert!(matches!(verify_signed_response(keypair.get_public_key(), &headers, REQUEST, RESPONSE), Err(JsonRPCError::InvalidSignature("timestamp is too far from local time"))));
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::{collections::HashSet, time::Duration};
+use rand::Rng;
+use super::JsonRPCError;
+
+// default timeout for each HTTP request
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+// default retries count after the first attempt
+const DEFAULT_MAX_RETRIES: u32 = 3;
+// delay before the first retry, doubled at each retry
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(250);
+// maximum delay between two retries
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(5);
+
+// Policy used by the JSON-RPC client to retry the requests failing because of a transient error
+// A request is only retried if we know that sending it again has no side effect:
+// idempotent methods are retried on any transport error (connection, timeout, server unavailable),
+// but non idempotent methods are only retried when the request was never received by the server
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    // timeout for each attempt, None for no timeout
+    timeout: Option<Duration>,
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    // methods that must not be executed twice by the server
+    non_idempotent_methods: HashSet<String>
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Some(DEFAULT_TIMEOUT),
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            non_idempotent_methods: HashSet::new()
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(timeout: Option<Duration>, max_retries: u32, base_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            timeout,
+            max_retries,
+            base_delay,
+            max_delay,
+            non_idempotent_methods: HashSet::new()
+        }
+    }
+
+    // policy without any retry, errors are returned directly
+    pub fn no_retry(timeout: Option<Duration>) -> Self {
+        Self::new(timeout, 0, Duration::ZERO, Duration::ZERO)
+    }
+
+    pub fn with_non_idempotent_method<S: Into<String>>(mut self, method: S) -> Self {
+        self.non_idempotent_methods.insert(method.into());
+        self
+    }
+
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn get_max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    pub fn is_idempotent(&self, method: &str) -> bool {
+        !self.non_idempotent_methods.contains(method)
+    }
+
+    // check if the request can be sent again after this error
+    pub fn is_retryable(&self, error: &JsonRPCError, idempotent: bool) -> bool {
+        let e = match error {
+            JsonRPCError::HttpError(e) => e,
+            // errors returned by the server are definitive
+            _ => return false
+        };
+
+        // request was never sent, always safe to retry
+        if e.is_connect() {
+            return true
+        }
+
+        if !idempotent {
+            return false
+        }
+
+        e.is_timeout() || e.is_request() || e.status().is_some_and(|status| status.is_server_error())
+    }
+
+    // delay to wait before this retry (starting at 1)
+    // exponential backoff with a random jitter to not have all clients retrying at the same time
+    pub fn get_delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        if delay.is_zero() {
+            return delay
+        }
+
+        let half = delay / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+    }
+}
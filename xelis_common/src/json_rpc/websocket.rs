@@ -0,0 +1,372 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    marker::PhantomData,
+    sync::{Arc, atomic::{AtomicUsize, AtomicBool, Ordering}},
+    time::Duration
+};
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, warn, info, error, trace};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{json, Value};
+use tokio::{
+    net::TcpStream,
+    select,
+    sync::{Mutex, mpsc, oneshot, broadcast},
+    time::{interval, sleep}
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, WebSocketStream, MaybeTlsStream};
+use crate::api::SubscribeParams;
+use super::{JsonRPCError, JsonRPCResult, JSON_RPC_VERSION, parse_response};
+
+pub type WebSocketJsonRPCClientShared<E> = Arc<WebSocketJsonRPCClient<E>>;
+
+type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// delay between each reconnection attempt
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// interval to send a ping to keep the connection alive
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+// how many events can be buffered per subscription before lagging
+const EVENT_CHANNEL_SIZE: usize = 64;
+
+// A subscribed event and the channel used to forward it to its receivers
+struct Subscription {
+    // id used by the server to send us this event
+    id: usize,
+    sender: broadcast::Sender<Value>
+}
+
+// JSON-RPC client over a WebSocket connection
+// It supports requests/responses correlation using the request id,
+// events pushed by the server after a subscription,
+// and reconnects automatically in case of connection loss by subscribing again to all events
+pub struct WebSocketJsonRPCClient<E: Serialize + DeserializeOwned + Eq + Hash + Clone + Send + Sync + 'static> {
+    target: String,
+    count: AtomicUsize,
+    // channel to send messages to the websocket
+    sender: mpsc::UnboundedSender<Message>,
+    // requests waiting for their response
+    requests: Mutex<HashMap<usize, oneshot::Sender<Value>>>,
+    // events subscribed by this client
+    subscriptions: Mutex<HashMap<E, Subscription>>,
+    // is the websocket currently connected
+    online: AtomicBool,
+    // client was closed manually, don't reconnect
+    closed: AtomicBool
+}
+
+impl<E> WebSocketJsonRPCClient<E>
+where
+    E: Serialize + DeserializeOwned + Eq + Hash + Clone + Send + Sync + 'static
+{
+    // connect to the target (ws:// or wss://) and start the background task handling the connection
+    pub async fn new(target: String) -> JsonRPCResult<WebSocketJsonRPCClientShared<E>> {
+        let (websocket, _) = connect_async(&target).await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let client = Arc::new(Self {
+            target,
+            count: AtomicUsize::new(0),
+            sender,
+            requests: Mutex::new(HashMap::new()),
+            subscriptions: Mutex::new(HashMap::new()),
+            online: AtomicBool::new(true),
+            closed: AtomicBool::new(false)
+        });
+
+        {
+            let client = Arc::clone(&client);
+            tokio::spawn(client.background_task(websocket, receiver));
+        }
+
+        Ok(client)
+    }
+
+    pub fn get_target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::SeqCst)
+    }
+
+    pub async fn call<R: DeserializeOwned>(&self, method: &str) -> JsonRPCResult<R> {
+        let id = self.next_id();
+        self.send(id, json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method,
+            "id": id
+        })).await
+    }
+
+    pub async fn call_with<P, R>(&self, method: &str, params: &P) -> JsonRPCResult<R>
+        where P: Serialize + Sized, R: DeserializeOwned
+    {
+        let id = self.next_id();
+        self.send(id, json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method,
+            "id": id,
+            "params": params
+        })).await
+    }
+
+    pub async fn notify(&self, method: &str) -> JsonRPCResult<()> {
+        self.send_message(json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method
+        }))
+    }
+
+    pub async fn notify_with<P>(&self, method: &str, params: P) -> JsonRPCResult<()>
+        where P: Serialize + Sized
+    {
+        self.send_message(json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": method,
+            "params": &params
+        }))
+    }
+
+    // subscribe to an event and returns a receiver for it
+    // if the event is already subscribed, a new receiver is returned without asking the server again
+    pub async fn subscribe_event<T: DeserializeOwned>(&self, event: E) -> JsonRPCResult<EventReceiver<T>> {
+        let id = {
+            let mut subscriptions = self.subscriptions.lock().await;
+            if let Some(subscription) = subscriptions.get(&event) {
+                return Ok(EventReceiver::new(subscription.sender.subscribe()))
+            }
+
+            // register it before sending the request so we don't miss any event
+            let id = self.next_id();
+            let (sender, _) = broadcast::channel(EVENT_CHANNEL_SIZE);
+            subscriptions.insert(event.clone(), Subscription { id, sender });
+            id
+        };
+
+        let params = SubscribeParams { notify: event.clone() };
+        let res: JsonRPCResult<bool> = self.send(id, json!({
+            "jsonrpc": JSON_RPC_VERSION,
+            "method": "subscribe",
+            "id": id,
+            "params": params
+        })).await;
+
+        let mut subscriptions = self.subscriptions.lock().await;
+        match res {
+            Ok(_) => {
+                let subscription = subscriptions.get(&event).ok_or(JsonRPCError::EventNotSubscribed)?;
+                Ok(EventReceiver::new(subscription.sender.subscribe()))
+            },
+            Err(e) => {
+                subscriptions.remove(&event);
+                Err(e)
+            }
+        }
+    }
+
+    // unsubscribe from an event, all its receivers will be closed
+    pub async fn unsubscribe_event(&self, event: &E) -> JsonRPCResult<()> {
+        let subscription = {
+            let mut subscriptions = self.subscriptions.lock().await;
+            subscriptions.remove(event).ok_or(JsonRPCError::EventNotSubscribed)?
+        };
+        debug!("Unsubscribing from event with id {}", subscription.id);
+
+        let params = SubscribeParams { notify: event.clone() };
+        let _: bool = self.call_with("unsubscribe", &params).await?;
+        Ok(())
+    }
+
+    // close the connection, the client can't be used anymore after this
+    pub async fn close(&self) -> JsonRPCResult<()> {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return Err(JsonRPCError::ConnectionClosed)
+        }
+
+        self.subscriptions.lock().await.clear();
+        self.sender.send(Message::Close(None)).map_err(|_| JsonRPCError::ConnectionClosed)
+    }
+
+    fn next_id(&self) -> usize {
+        self.count.fetch_add(1, Ordering::SeqCst)
+    }
+
+    fn send_message(&self, value: Value) -> JsonRPCResult<()> {
+        if !self.is_online() {
+            return Err(JsonRPCError::ConnectionClosed)
+        }
+
+        self.sender.send(Message::Text(value.to_string())).map_err(|_| JsonRPCError::ConnectionClosed)
+    }
+
+    // send the request and wait for the response with the same id
+    async fn send<R: DeserializeOwned>(&self, id: usize, value: Value) -> JsonRPCResult<R> {
+        let (sender, receiver) = oneshot::channel();
+        self.requests.lock().await.insert(id, sender);
+
+        if let Err(e) = self.send_message(value) {
+            self.requests.lock().await.remove(&id);
+            return Err(e)
+        }
+
+        // sender is dropped if the connection is lost before receiving the response
+        let response = receiver.await.map_err(|_| JsonRPCError::ConnectionClosed)?;
+        parse_response(response)
+    }
+
+    // dispatch a message received to the pending request or to the subscribed event
+    async fn on_message(&self, text: &str) {
+        let mut response: Value = match serde_json::from_str(text) {
+            Ok(value) => value,
+            Err(e) => {
+                debug!("Error while parsing message received from {}: {}", self.target, e);
+                return;
+            }
+        };
+
+        let id = match response.get("id").and_then(Value::as_u64) {
+            Some(id) => id as usize,
+            None => {
+                debug!("Received a message without id from {}: {}", self.target, text);
+                return;
+            }
+        };
+
+        if let Some(sender) = self.requests.lock().await.remove(&id) {
+            if sender.send(response).is_err() {
+                trace!("Request {} was dropped before receiving its response", id);
+            }
+            return;
+        }
+
+        let subscriptions = self.subscriptions.lock().await;
+        match subscriptions.values().find(|subscription| subscription.id == id) {
+            Some(subscription) => {
+                if let Some(result) = response.get_mut("result") {
+                    // no receiver is not an error, they may subscribe again later
+                    let _ = subscription.sender.send(result.take());
+                }
+            },
+            None => debug!("Received a message with unknown id {} from {}", id, self.target)
+        };
+    }
+
+    // handle the websocket until it's closed
+    async fn handle_connection(&self, websocket: WebSocket, receiver: &mut mpsc::UnboundedReceiver<Message>) -> JsonRPCResult<()> {
+        let (mut write, mut read) = websocket.split();
+        let mut keep_alive = interval(KEEP_ALIVE_INTERVAL);
+        loop {
+            select! {
+                message = receiver.recv() => {
+                    let message = message.ok_or(JsonRPCError::ConnectionClosed)?;
+                    let close = matches!(message, Message::Close(_));
+                    write.send(message).await?;
+                    if close {
+                        return Ok(())
+                    }
+                },
+                message = read.next() => {
+                    match message {
+                        Some(Ok(Message::Text(text))) => self.on_message(&text).await,
+                        Some(Ok(Message::Close(_))) | None => return Ok(()),
+                        Some(Ok(_)) => {},
+                        Some(Err(e)) => return Err(e.into())
+                    }
+                },
+                _ = keep_alive.tick() => {
+                    write.send(Message::Ping(Vec::new())).await?;
+                }
+            }
+        }
+    }
+
+    // subscribe again to all events after a reconnection
+    async fn resubscribe(&self) {
+        let mut subscriptions = self.subscriptions.lock().await;
+        for (event, subscription) in subscriptions.iter_mut() {
+            // use a new id so the response is not forwarded as an event
+            subscription.id = self.next_id();
+            let (sender, receiver) = oneshot::channel();
+            self.requests.lock().await.insert(subscription.id, sender);
+
+            let params = SubscribeParams { notify: event };
+            if let Err(e) = self.send_message(json!({
+                "jsonrpc": JSON_RPC_VERSION,
+                "method": "subscribe",
+                "id": subscription.id,
+                "params": params
+            })) {
+                warn!("Error while subscribing again to an event: {}", e);
+                continue;
+            }
+
+            tokio::spawn(async move {
+                if let Ok(response) = receiver.await {
+                    if let Err(e) = parse_response::<bool>(response) {
+                        warn!("Error while subscribing again to an event: {}", e);
+                    }
+                }
+            });
+        }
+    }
+
+    async fn background_task(self: Arc<Self>, websocket: WebSocket, mut receiver: mpsc::UnboundedReceiver<Message>) {
+        let mut websocket = Some(websocket);
+        while let Some(current) = websocket.take() {
+            if let Err(e) = self.handle_connection(current, &mut receiver).await {
+                debug!("Error on websocket connection with {}: {}", self.target, e);
+            }
+
+            self.online.store(false, Ordering::SeqCst);
+            // dropping the senders will notify all pending requests
+            self.requests.lock().await.clear();
+            // messages queued while disconnected are not related to the next connection
+            while receiver.try_recv().is_ok() {}
+
+            while !self.closed.load(Ordering::SeqCst) {
+                warn!("Connection lost with {}, trying to reconnect in {}s", self.target, RECONNECT_DELAY.as_secs());
+                sleep(RECONNECT_DELAY).await;
+                match connect_async(&self.target).await {
+                    Ok((new, _)) => {
+                        info!("Reconnected to {}", self.target);
+                        self.online.store(true, Ordering::SeqCst);
+                        self.resubscribe().await;
+                        websocket = Some(new);
+                        break;
+                    },
+                    Err(e) => error!("Error while reconnecting to {}: {}", self.target, e)
+                };
+            }
+        }
+        debug!("WebSocket client for {} is now closed", self.target);
+    }
+}
+
+// Receiver of a subscribed event, deserialized in the expected type
+pub struct EventReceiver<T: DeserializeOwned> {
+    inner: broadcast::Receiver<Value>,
+    _phantom: PhantomData<T>
+}
+
+impl<T: DeserializeOwned> EventReceiver<T> {
+    fn new(inner: broadcast::Receiver<Value>) -> Self {
+        Self {
+            inner,
+            _phantom: PhantomData
+        }
+    }
+
+    // wait for the next event, returns an error if the event was unsubscribed
+    pub async fn next(&mut self) -> JsonRPCResult<T> {
+        loop {
+            match self.inner.recv().await {
+                Ok(value) => return Ok(serde_json::from_value(value)?),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Event receiver lagged, {} events were skipped", skipped);
+                },
+                Err(broadcast::error::RecvError::Closed) => return Err(JsonRPCError::EventNotSubscribed)
+            }
+        }
+    }
+}
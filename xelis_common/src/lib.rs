@@ -1,17 +1,21 @@
 pub mod crypto;
 pub mod serializer;
 pub mod transaction;
+pub mod contract;
 pub mod block;
 pub mod account;
 pub mod api;
 
 pub mod utils;
+pub mod time;
 pub mod config;
 pub mod immutable;
 pub mod difficulty;
 pub mod network;
 pub mod asset;
+pub mod name;
 pub mod context;
+pub mod pow;
 
 #[cfg(feature = "json_rpc")]
 pub mod json_rpc;
@@ -20,4 +24,10 @@ pub mod json_rpc;
 pub mod prompt;
 
 #[cfg(feature = "rpc_server")]
-pub mod rpc_server;
\ No newline at end of file
+pub mod rpc_server;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "python")]
+pub mod python;
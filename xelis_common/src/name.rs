@@ -0,0 +1,158 @@
+use schemars::JsonSchema;
+
+use crate::{
+    config::COIN_VALUE,
+    crypto::key::PublicKey,
+    serializer::{Serializer, Writer, Reader, ReaderError}
+};
+
+// suffix used by wallets to recognize a name as recipient
+pub const NAME_SUFFIX: &str = ".xel";
+pub const NAME_MIN_LENGTH: usize = 3;
+pub const NAME_MAX_LENGTH: usize = 32;
+// ~30 days with 15s blocks, a registration is charged per started period
+pub const NAME_REGISTRATION_PERIOD: u64 = 172_800;
+pub const NAME_MIN_DURATION: u64 = NAME_REGISTRATION_PERIOD;
+// ~1 year, a name can be renewed by its owner to keep it longer
+pub const NAME_MAX_DURATION: u64 = 12 * NAME_REGISTRATION_PERIOD;
+// burned for each period of the registration
+pub const NAME_COST_PER_PERIOD: u64 = COIN_VALUE;
+
+// lowercase letters, digits and dashes, a dash can't start or end the name
+pub fn is_valid_name(name: &str) -> bool {
+    name.len() >= NAME_MIN_LENGTH
+        && name.len() <= NAME_MAX_LENGTH
+        && name.bytes().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+// amount burned to register or renew a name for this duration in topoheights
+pub fn get_name_registration_cost(duration: u64) -> u64 {
+    duration.div_ceil(NAME_REGISTRATION_PERIOD).saturating_mul(NAME_COST_PER_PERIOD)
+}
+
+// returns the name if the recipient is in the `name.xel` format
+pub fn parse_name_recipient(recipient: &str) -> Option<&str> {
+    recipient.strip_suffix(NAME_SUFFIX).filter(|name| is_valid_name(name))
+}
+
+// Registration of a name stored by the daemon
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
+pub struct NameEntry {
+    owner: PublicKey,
+    // topoheight of the first registration by this owner
+    registered_topoheight: u64,
+    // first topoheight at which the name can be registered by anyone
+    expiration: u64
+}
+
+impl NameEntry {
+    pub fn new(owner: PublicKey, registered_topoheight: u64, expiration: u64) -> Self {
+        Self {
+            owner,
+            registered_topoheight,
+            expiration
+        }
+    }
+
+    pub fn get_owner(&self) -> &PublicKey {
+        &self.owner
+    }
+
+    pub fn get_registered_topoheight(&self) -> u64 {
+        self.registered_topoheight
+    }
+
+    pub fn get_expiration(&self) -> u64 {
+        self.expiration
+    }
+
+    pub fn is_active_at(&self, topoheight: u64) -> bool {
+        topoheight < self.expiration
+    }
+
+    // registration applied at topoheight by this owner for this duration
+    // the owner of an active name extends its expiration, otherwise the name starts a new registration
+    pub fn register(current: Option<&NameEntry>, owner: &PublicKey, topoheight: u64, duration: u64) -> Option<Self> {
+        match current {
+            Some(entry) if entry.is_active_at(topoheight) => {
+                if entry.owner != *owner {
+                    return None
+                }
+                Some(Self::new(owner.clone(), entry.registered_topoheight, entry.expiration.saturating_add(duration)))
+            },
+            _ => Some(Self::new(owner.clone(), topoheight, topoheight.saturating_add(duration)))
+        }
+    }
+}
+
+impl Serializer for NameEntry {
+    fn write(&self, writer: &mut Writer) {
+        self.owner.write(writer);
+        writer.write_u64(&self.registered_topoheight);
+        writer.write_u64(&self.expiration);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(Self::new(PublicKey::read(reader)?, reader.read_u64()?, reader.read_u64()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::key::KeyPair;
+
+    #[test]
+    fn test_valid_names() {
+        assert!(is_valid_name("xelis"));
+        assert!(is_valid_name("my-wallet-42"));
+        assert!(!is_valid_name("ab"));
+        assert!(!is_valid_name(&"a".repeat(NAME_MAX_LENGTH + 1)));
+        assert!(!is_valid_name("Xelis"));
+        assert!(!is_valid_name("-xelis"));
+        assert!(!is_valid_name("xelis-"));
+        assert!(!is_valid_name("xel.is"));
+    }
+
+    #[test]
+    fn test_parse_name_recipient() {
+        assert_eq!(parse_name_recipient("alice.xel"), Some("alice"));
+        assert_eq!(parse_name_recipient("alice"), None);
+        assert_eq!(parse_name_recipient("a.xel"), None);
+        assert_eq!(parse_name_recipient("xel1qyqxcfxdc8ywarcz3wx2leahnfn2pyp0ymvfm42waluq408j2x5680g05xfx5"), None);
+    }
+
+    #[test]
+    fn test_registration_cost() {
+        assert_eq!(get_name_registration_cost(NAME_MIN_DURATION), NAME_COST_PER_PERIOD);
+        assert_eq!(get_name_registration_cost(NAME_REGISTRATION_PERIOD + 1), 2 * NAME_COST_PER_PERIOD);
+        assert_eq!(get_name_registration_cost(NAME_MAX_DURATION), 12 * NAME_COST_PER_PERIOD);
+    }
+
+    #[test]
+    fn test_register_and_renew() {
+        let alice = KeyPair::new().get_public_key().clone();
+        let bob = KeyPair::new().get_public_key().clone();
+
+        let entry = NameEntry::register(None, &alice, 10, 100).unwrap();
+        assert_eq!(entry.get_expiration(), 110);
+
+        // renewal keeps the first registration and extends the expiration
+        let renewed = NameEntry::register(Some(&entry), &alice, 50, 100).unwrap();
+        assert_eq!(renewed.get_registered_topoheight(), 10);
+        assert_eq!(renewed.get_expiration(), 210);
+
+        // taken until it expires
+        assert!(NameEntry::register(Some(&renewed), &bob, 209, 100).is_none());
+        let taken = NameEntry::register(Some(&renewed), &bob, 210, 100).unwrap();
+        assert_eq!(taken.get_owner(), &bob);
+        assert_eq!(taken.get_registered_topoheight(), 210);
+
+        let bytes = taken.to_bytes();
+        let decoded = NameEntry::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.get_owner(), &bob);
+        assert_eq!(decoded.get_expiration(), 310);
+    }
+}
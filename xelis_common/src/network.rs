@@ -1,9 +1,13 @@
 use std::{fmt::{Display, Formatter, self}, str::FromStr};
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 
-use crate::serializer::{Serializer, Reader, ReaderError, Writer};
+use crate::{
+    config::{PREFIX_ADDRESS, TESTNET_PREFIX_ADDRESS},
+    serializer::{Serializer, Reader, ReaderError, Writer}
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(feature = "clap", derive(clap::ArgEnum))]
 pub enum Network {
     Mainnet,
@@ -18,6 +22,42 @@ impl Network {
             _ => false
         }
     }
+
+    // default port used by the daemon to listen for P2P connections
+    pub fn get_default_p2p_port(&self) -> u16 {
+        match &self {
+            Self::Mainnet => 2125,
+            Self::Testnet => 2225,
+            Self::Dev => 2325
+        }
+    }
+
+    // default port used by the daemon RPC server
+    pub fn get_default_rpc_port(&self) -> u16 {
+        match &self {
+            Self::Mainnet => 8080,
+            Self::Testnet => 8180,
+            Self::Dev => 8280
+        }
+    }
+
+    // default directory name used to store the data of this network
+    pub fn get_default_dir_name(&self) -> &'static str {
+        match &self {
+            Self::Mainnet => "mainnet",
+            Self::Testnet => "testnet",
+            Self::Dev => "dev"
+        }
+    }
+
+    // prefix of the addresses used on this network
+    pub fn get_address_prefix(&self) -> &'static str {
+        if self.is_mainnet() {
+            PREFIX_ADDRESS
+        } else {
+            TESTNET_PREFIX_ADDRESS
+        }
+    }
 }
 
 impl Display for Network {
@@ -38,7 +78,7 @@ impl FromStr for Network {
         Ok(match s {
             "mainnet" | "0" => Self::Mainnet,
             "testnet" | "1" => Self::Testnet,
-            "dev" | "2" => Self::Dev,
+            "dev" | "devnet" | "2" => Self::Dev,
             _ => return Err("Invalid network".into())
         })
     }
@@ -62,4 +102,30 @@ impl Serializer for Network {
         };
         writer.write_u8(id);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_ports_are_unique() {
+        let networks = [Network::Mainnet, Network::Testnet, Network::Dev];
+        for (i, a) in networks.iter().enumerate() {
+            for b in networks.iter().skip(i + 1) {
+                assert_ne!(a.get_default_p2p_port(), b.get_default_p2p_port());
+                assert_ne!(a.get_default_rpc_port(), b.get_default_rpc_port());
+                assert_ne!(a.get_default_dir_name(), b.get_default_dir_name());
+            }
+        }
+    }
+
+    #[test]
+    fn test_network_from_str() {
+        for network in [Network::Mainnet, Network::Testnet, Network::Dev] {
+            assert_eq!(Network::from_str(network.get_default_dir_name()), Ok(network));
+        }
+        assert_eq!(Network::from_str("devnet"), Ok(Network::Dev));
+        assert!(Network::from_str("unknown").is_err());
+    }
+}
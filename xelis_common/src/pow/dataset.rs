@@ -0,0 +1,164 @@
+use std::thread;
+use sha3::{Digest, Keccak512};
+use crate::crypto::hash::Hash;
+use super::{memory::LargeBuffer, DatasetMode, DatasetParams};
+
+// size in bytes of an item of the cache and of the dataset
+pub const ITEM_SIZE: usize = 64;
+const ITEM_WORDS: usize = ITEM_SIZE / 4;
+const FNV_PRIME: u32 = 0x01000193;
+
+pub type DatasetItem = [u8; ITEM_SIZE];
+
+fn keccak512(value: &[u8]) -> DatasetItem {
+    let mut item = [0u8; ITEM_SIZE];
+    item.copy_from_slice(&Keccak512::digest(value));
+    item
+}
+
+fn fnv(a: u32, b: u32) -> u32 {
+    a.wrapping_mul(FNV_PRIME) ^ b
+}
+
+fn read_word(item: &[u8], index: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&item[index * 4..index * 4 + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+// Memory of an epoch, derived from its seed
+// the cache is always kept, the dataset is only computed in full mode
+pub struct EpochDataset {
+    seed_topoheight: u64,
+    seed: Hash,
+    params: DatasetParams,
+    cache: LargeBuffer,
+    dataset: Option<LargeBuffer>
+}
+
+impl EpochDataset {
+    pub fn new(seed_topoheight: u64, seed: Hash, params: DatasetParams, mode: DatasetMode, huge_pages: bool, threads: usize) -> Self {
+        let mut cache = LargeBuffer::new(params.cache_size, huge_pages);
+        init_cache(&mut cache, &seed, params.cache_rounds);
+
+        let dataset = match mode {
+            DatasetMode::Light => None,
+            DatasetMode::Full => {
+                let mut dataset = LargeBuffer::new(params.dataset_size, huge_pages);
+                fill_dataset(&mut dataset, &cache, params.parents, threads);
+                Some(dataset)
+            }
+        };
+
+        Self {
+            seed_topoheight,
+            seed,
+            params,
+            cache,
+            dataset
+        }
+    }
+
+    pub fn get_seed_topoheight(&self) -> u64 {
+        self.seed_topoheight
+    }
+
+    pub fn get_seed(&self) -> &Hash {
+        &self.seed
+    }
+
+    pub fn get_mode(&self) -> DatasetMode {
+        if self.dataset.is_some() {
+            DatasetMode::Full
+        } else {
+            DatasetMode::Light
+        }
+    }
+
+    pub fn is_huge_pages(&self) -> bool {
+        self.cache.is_huge_pages() && self.dataset.as_ref().is_none_or(LargeBuffer::is_huge_pages)
+    }
+
+    // bytes allocated for this epoch
+    pub fn get_memory_usage(&self) -> usize {
+        self.params.get_memory_usage(self.get_mode())
+    }
+
+    pub fn get_items_count(&self) -> u64 {
+        (self.params.dataset_size / ITEM_SIZE) as u64
+    }
+
+    // item of the dataset at this index (modulo the items count)
+    // both modes return the same item, the light mode computes it from the cache
+    pub fn get_item(&self, index: u64) -> DatasetItem {
+        let index = index % self.get_items_count();
+        match &self.dataset {
+            Some(dataset) => {
+                let start = index as usize * ITEM_SIZE;
+                let mut item = [0u8; ITEM_SIZE];
+                item.copy_from_slice(&dataset[start..start + ITEM_SIZE]);
+                item
+            },
+            None => compute_item(&self.cache, index, self.params.parents)
+        }
+    }
+}
+
+// chain of hashes from the seed, then mixed with random items of the cache
+// so it can't be computed with less memory
+fn init_cache(cache: &mut [u8], seed: &Hash, rounds: usize) {
+    let count = cache.len() / ITEM_SIZE;
+    cache[..ITEM_SIZE].copy_from_slice(&keccak512(seed.as_bytes()));
+    for i in 1..count {
+        let item = keccak512(&cache[(i - 1) * ITEM_SIZE..i * ITEM_SIZE]);
+        cache[i * ITEM_SIZE..(i + 1) * ITEM_SIZE].copy_from_slice(&item);
+    }
+
+    for _ in 0..rounds {
+        for i in 0..count {
+            let previous = (i + count - 1) % count;
+            let other = read_word(&cache[i * ITEM_SIZE..], 0) as usize % count;
+            let mut mixed = [0u8; ITEM_SIZE];
+            for (j, byte) in mixed.iter_mut().enumerate() {
+                *byte = cache[previous * ITEM_SIZE + j] ^ cache[other * ITEM_SIZE + j];
+            }
+            cache[i * ITEM_SIZE..(i + 1) * ITEM_SIZE].copy_from_slice(&keccak512(&mixed));
+        }
+    }
+}
+
+// each dataset item depends on `parents` random items of the cache
+fn compute_item(cache: &[u8], index: u64, parents: usize) -> DatasetItem {
+    let count = cache.len() / ITEM_SIZE;
+    let start = (index as usize % count) * ITEM_SIZE;
+    let mut mix = [0u8; ITEM_SIZE];
+    mix.copy_from_slice(&cache[start..start + ITEM_SIZE]);
+    let first = read_word(&mix, 0) ^ index as u32;
+    mix[..4].copy_from_slice(&first.to_le_bytes());
+    mix = keccak512(&mix);
+
+    for i in 0..parents {
+        let parent = fnv(index as u32 ^ i as u32, read_word(&mix, i % ITEM_WORDS)) as usize % count;
+        let parent = &cache[parent * ITEM_SIZE..(parent + 1) * ITEM_SIZE];
+        for word in 0..ITEM_WORDS {
+            let value = fnv(read_word(&mix, word), read_word(parent, word));
+            mix[word * 4..word * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    keccak512(&mix)
+}
+
+fn fill_dataset(dataset: &mut [u8], cache: &[u8], parents: usize, threads: usize) {
+    let items_per_thread = (dataset.len() / ITEM_SIZE).div_ceil(threads.max(1)).max(1);
+    thread::scope(|scope| {
+        for (i, chunk) in dataset.chunks_mut(items_per_thread * ITEM_SIZE).enumerate() {
+            scope.spawn(move || {
+                let start = i * items_per_thread;
+                for (j, item) in chunk.chunks_exact_mut(ITEM_SIZE).enumerate() {
+                    item.copy_from_slice(&compute_item(cache, (start + j) as u64, parents));
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,105 @@
+use std::ops::{Deref, DerefMut};
+use log::warn;
+
+// size of the huge pages on Linux x86_64 and aarch64
+#[cfg(target_os = "linux")]
+const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+// Zeroed memory of a cache or dataset, allocated on huge pages if requested and available
+// huge pages reduce the TLB misses of the random accesses in the dataset
+pub struct LargeBuffer {
+    inner: Inner,
+    len: usize
+}
+
+enum Inner {
+    Heap(Vec<u8>),
+    #[cfg(target_os = "linux")]
+    HugePages(*mut u8, usize)
+}
+
+// the mapping is owned by the buffer and only accessed through it
+unsafe impl Send for LargeBuffer {}
+unsafe impl Sync for LargeBuffer {}
+
+impl LargeBuffer {
+    pub fn new(len: usize, huge_pages: bool) -> Self {
+        if huge_pages {
+            #[cfg(target_os = "linux")]
+            if let Some(buffer) = Self::allocate_huge_pages(len) {
+                return buffer
+            }
+
+            warn!("Huge pages are not available, {} bytes are allocated on normal pages", len);
+        }
+
+        Self {
+            inner: Inner::Heap(vec![0u8; len]),
+            len
+        }
+    }
+
+    // requires huge pages reserved by the system, such as with `sysctl vm.nr_hugepages`
+    #[cfg(target_os = "linux")]
+    fn allocate_huge_pages(len: usize) -> Option<Self> {
+        let size = len.div_ceil(HUGE_PAGE_SIZE).max(1) * HUGE_PAGE_SIZE;
+        // anonymous mappings are zeroed by the kernel
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB,
+                -1,
+                0
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            log::debug!("Error while mapping {} bytes on huge pages: {}", size, std::io::Error::last_os_error());
+            return None
+        }
+
+        Some(Self {
+            inner: Inner::HugePages(ptr as *mut u8, size),
+            len
+        })
+    }
+
+    pub fn is_huge_pages(&self) -> bool {
+        !matches!(self.inner, Inner::Heap(_))
+    }
+}
+
+impl Deref for LargeBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.inner {
+            Inner::Heap(bytes) => bytes,
+            #[cfg(target_os = "linux")]
+            Inner::HugePages(ptr, _) => unsafe { std::slice::from_raw_parts(*ptr, self.len) }
+        }
+    }
+}
+
+impl DerefMut for LargeBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.inner {
+            Inner::Heap(bytes) => bytes,
+            #[cfg(target_os = "linux")]
+            Inner::HugePages(ptr, _) => unsafe { std::slice::from_raw_parts_mut(*ptr, self.len) }
+        }
+    }
+}
+
+impl Drop for LargeBuffer {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Inner::HugePages(ptr, size) = self.inner {
+            unsafe {
+                libc::munmap(ptr as *mut libc::c_void, size);
+            }
+        }
+    }
+}
@@ -0,0 +1,392 @@
+mod dataset;
+mod memory;
+
+use std::{
+    collections::VecDeque,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    thread
+};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use thiserror::Error;
+use crate::crypto::hash::Hash;
+
+pub use dataset::{EpochDataset, DatasetItem, ITEM_SIZE};
+
+// topoheights using the same seed, must be a power of two
+pub const EPOCH_LENGTH: u64 = 2048;
+// delay in topoheights before a new seed is used, so miners and nodes can prepare the next epoch
+// and its block is stable, so a reorg of the last blocks can't change the seed in use
+pub const EPOCH_LAG: u64 = 64;
+// epochs kept in memory, the current one and the one being prepared
+pub const MAX_EPOCHS: usize = 2;
+
+// parameters of the memory-hard PoW
+pub const DATASET_PARAMS: DatasetParams = DatasetParams {
+    cache_size: 256 * 1024 * 1024,
+    dataset_size: 2 * 1024 * 1024 * 1024,
+    cache_rounds: 3,
+    parents: 256
+};
+
+#[derive(Error, Debug)]
+pub enum DatasetError {
+    #[error("Memory limit of {} MiB is too low, at least {} MiB are required", limit, required)]
+    MemoryLimitTooLow { limit: usize, required: usize },
+    #[error("Invalid dataset parameters, sizes must be non zero multiples of {}", ITEM_SIZE)]
+    InvalidParams
+}
+
+// Light mode only keeps the cache and computes the dataset items on the fly, enough to verify blocks
+// Full mode computes the whole dataset once per epoch, required to mine efficiently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "clap", derive(clap::ArgEnum))]
+pub enum DatasetMode {
+    Light,
+    Full
+}
+
+// Options of the dataset used by memory-hard PoW algorithms
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct DatasetConfig {
+    /// Keep only the cache (light) or compute the whole dataset (full) of the memory-hard PoW
+    #[cfg_attr(feature = "clap", clap(long = "pow-dataset-mode", arg_enum))]
+    pub mode: Option<DatasetMode>,
+    /// Allocate the PoW cache and dataset on huge pages, they must be reserved by the system
+    #[cfg_attr(feature = "clap", clap(long = "pow-huge-pages"))]
+    pub huge_pages: bool,
+    /// Maximum memory in MiB used by the PoW cache and dataset
+    #[cfg_attr(feature = "clap", clap(long = "pow-max-memory"))]
+    pub max_memory: Option<usize>,
+    /// Number of threads computing the PoW dataset, default is the number of CPU cores
+    #[cfg_attr(feature = "clap", clap(long = "pow-dataset-threads"))]
+    pub dataset_threads: Option<usize>
+}
+
+impl DatasetConfig {
+    pub fn get_threads(&self) -> usize {
+        self.dataset_threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get)).max(1)
+    }
+
+    // limit in bytes
+    pub fn get_max_memory(&self) -> Option<usize> {
+        self.max_memory.map(|limit| limit.saturating_mul(1024 * 1024))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DatasetParams {
+    // bytes of the cache derived from the seed
+    pub cache_size: usize,
+    // bytes of the dataset derived from the cache
+    pub dataset_size: usize,
+    // memory-hard rounds applied on the cache
+    pub cache_rounds: usize,
+    // cache items mixed in each dataset item
+    pub parents: usize
+}
+
+impl DatasetParams {
+    pub fn is_valid(&self) -> bool {
+        self.cache_size >= ITEM_SIZE && self.cache_size.is_multiple_of(ITEM_SIZE)
+            && self.dataset_size >= ITEM_SIZE && self.dataset_size.is_multiple_of(ITEM_SIZE)
+    }
+
+    // bytes allocated for one epoch
+    pub fn get_memory_usage(&self, mode: DatasetMode) -> usize {
+        match mode {
+            DatasetMode::Light => self.cache_size,
+            DatasetMode::Full => self.cache_size + self.dataset_size
+        }
+    }
+}
+
+// topoheight of the block whose hash is the seed of the epoch used at this topoheight
+pub fn get_seed_topoheight(topoheight: u64) -> u64 {
+    if topoheight <= EPOCH_LENGTH + EPOCH_LAG {
+        0
+    } else {
+        (topoheight - EPOCH_LAG - 1) & !(EPOCH_LENGTH - 1)
+    }
+}
+
+// seed topoheight of the epoch following the one used at this topoheight
+pub fn get_next_seed_topoheight(topoheight: u64) -> u64 {
+    get_seed_topoheight(topoheight) + EPOCH_LENGTH
+}
+
+// first topoheight using the seed of this seed topoheight
+pub fn get_seed_activation_topoheight(seed_topoheight: u64) -> u64 {
+    if seed_topoheight == 0 {
+        0
+    } else {
+        seed_topoheight + EPOCH_LAG + 1
+    }
+}
+
+// Keeps the datasets of the current and next epochs, shared by the block verification and the miner
+// nothing is allocated until an epoch is requested
+pub struct DatasetManager {
+    config: DatasetConfig,
+    params: DatasetParams,
+    mode: DatasetMode,
+    // oldest first
+    epochs: Mutex<VecDeque<Arc<EpochDataset>>>,
+    // only one epoch is initialized at a time
+    init_lock: Mutex<()>
+}
+
+impl DatasetManager {
+    pub fn new(config: DatasetConfig, params: DatasetParams, default_mode: DatasetMode) -> Result<Self, DatasetError> {
+        if !params.is_valid() {
+            return Err(DatasetError::InvalidParams)
+        }
+
+        let mut mode = config.mode.unwrap_or(default_mode);
+        if let Some(limit) = config.get_max_memory() {
+            let required = params.get_memory_usage(DatasetMode::Light);
+            if required > limit {
+                return Err(DatasetError::MemoryLimitTooLow {
+                    limit: limit / (1024 * 1024),
+                    required: required.div_ceil(1024 * 1024)
+                })
+            }
+
+            if mode == DatasetMode::Full && params.get_memory_usage(DatasetMode::Full) > limit {
+                warn!("Memory limit is too low for the full dataset, switching to light mode");
+                mode = DatasetMode::Light;
+            }
+        }
+
+        Ok(Self {
+            config,
+            params,
+            mode,
+            epochs: Mutex::new(VecDeque::with_capacity(MAX_EPOCHS)),
+            init_lock: Mutex::new(())
+        })
+    }
+
+    pub fn get_mode(&self) -> DatasetMode {
+        self.mode
+    }
+
+    pub fn get_params(&self) -> &DatasetParams {
+        &self.params
+    }
+
+    // bytes allocated by the epochs in memory
+    pub fn get_memory_usage(&self) -> usize {
+        let epochs = self.epochs.lock().expect("dataset epochs lock");
+        epochs.iter().map(|epoch| epoch.get_memory_usage()).sum()
+    }
+
+    // dataset of the epoch if it's already in memory
+    pub fn get_ready_epoch(&self, seed_topoheight: u64, seed: &Hash) -> Option<Arc<EpochDataset>> {
+        let epochs = self.epochs.lock().expect("dataset epochs lock");
+        epochs.iter()
+            .find(|epoch| epoch.get_seed_topoheight() == seed_topoheight && epoch.get_seed() == seed)
+            .cloned()
+    }
+
+    // dataset of the epoch, initialized if it's not in memory yet
+    // the oldest epochs are dropped to stay under the limits
+    pub fn get_epoch(&self, seed_topoheight: u64, seed: &Hash) -> Arc<EpochDataset> {
+        if let Some(epoch) = self.get_ready_epoch(seed_topoheight, seed) {
+            return epoch
+        }
+
+        // the epochs in memory stay available during the initialization
+        let _guard = self.init_lock.lock().expect("dataset init lock");
+        // it may have been initialized while waiting
+        if let Some(epoch) = self.get_ready_epoch(seed_topoheight, seed) {
+            return epoch
+        }
+
+        {
+            let mut epochs = self.epochs.lock().expect("dataset epochs lock");
+            let required = self.params.get_memory_usage(self.mode);
+            let limit = self.config.get_max_memory();
+            while !epochs.is_empty() && (epochs.len() >= MAX_EPOCHS || limit.is_some_and(|limit| {
+                epochs.iter().map(|epoch| epoch.get_memory_usage()).sum::<usize>() + required > limit
+            })) {
+                if let Some(epoch) = epochs.pop_front() {
+                    debug!("Dropping PoW dataset of seed topoheight {}", epoch.get_seed_topoheight());
+                }
+            }
+        }
+
+        info!("Initializing PoW dataset in {:?} mode for seed topoheight {}", self.mode, seed_topoheight);
+        let epoch = Arc::new(EpochDataset::new(
+            seed_topoheight,
+            seed.clone(),
+            self.params,
+            self.mode,
+            self.config.huge_pages,
+            self.config.get_threads()
+        ));
+        info!("PoW dataset for seed topoheight {} is ready", seed_topoheight);
+        self.epochs.lock().expect("dataset epochs lock").push_back(epoch.clone());
+        epoch
+    }
+
+    // initialize an epoch in background, used to prepare the next one before its rotation
+    pub fn prepare(self: &Arc<Self>, seed_topoheight: u64, seed: Hash) {
+        let zelf = self.clone();
+        thread::spawn(move || {
+            zelf.get_epoch(seed_topoheight, &seed);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PARAMS: DatasetParams = DatasetParams {
+        cache_size: 64 * ITEM_SIZE,
+        dataset_size: 256 * ITEM_SIZE,
+        cache_rounds: 2,
+        parents: 16
+    };
+
+    fn seed(value: u8) -> Hash {
+        Hash::new([value; 32])
+    }
+
+    fn config(mode: DatasetMode) -> DatasetConfig {
+        DatasetConfig {
+            mode: Some(mode),
+            dataset_threads: Some(4),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_seed_topoheight() {
+        assert_eq!(get_seed_topoheight(0), 0);
+        assert_eq!(get_seed_topoheight(EPOCH_LENGTH + EPOCH_LAG), 0);
+        assert_eq!(get_seed_topoheight(EPOCH_LENGTH + EPOCH_LAG + 1), EPOCH_LENGTH);
+        assert_eq!(get_seed_topoheight(2 * EPOCH_LENGTH + EPOCH_LAG), EPOCH_LENGTH);
+        assert_eq!(get_seed_topoheight(2 * EPOCH_LENGTH + EPOCH_LAG + 1), 2 * EPOCH_LENGTH);
+    }
+
+    #[test]
+    fn test_seed_rotation() {
+        assert_eq!(get_next_seed_topoheight(0), EPOCH_LENGTH);
+        assert_eq!(get_seed_activation_topoheight(0), 0);
+        for seed_topoheight in [EPOCH_LENGTH, 2 * EPOCH_LENGTH, 10 * EPOCH_LENGTH] {
+            let activation = get_seed_activation_topoheight(seed_topoheight);
+            // the next seed is used from its activation topoheight
+            assert_eq!(get_seed_topoheight(activation - 1), seed_topoheight - EPOCH_LENGTH);
+            assert_eq!(get_next_seed_topoheight(activation - 1), seed_topoheight);
+            assert_eq!(get_seed_topoheight(activation), seed_topoheight);
+            // its block is known before it's used
+            assert!(activation - seed_topoheight > EPOCH_LAG);
+        }
+    }
+
+    #[test]
+    fn test_epoch_available_during_initialization() {
+        let manager = Arc::new(DatasetManager::new(config(DatasetMode::Light), TEST_PARAMS, DatasetMode::Light).unwrap());
+        let current = manager.get_epoch(0, &seed(1));
+        assert!(manager.get_ready_epoch(EPOCH_LENGTH, &seed(2)).is_none());
+
+        let guard = manager.init_lock.lock().unwrap();
+        manager.prepare(EPOCH_LENGTH, seed(2));
+        // the current epoch doesn't wait for the next one
+        assert!(Arc::ptr_eq(&current, &manager.get_epoch(0, &seed(1))));
+        drop(guard);
+
+        while manager.get_ready_epoch(EPOCH_LENGTH, &seed(2)).is_none() {
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(manager.get_ready_epoch(0, &seed(1)).is_some());
+    }
+
+    #[test]
+    fn test_light_and_full_items_match() {
+        let light = EpochDataset::new(0, seed(1), TEST_PARAMS, DatasetMode::Light, false, 1);
+        let full = EpochDataset::new(0, seed(1), TEST_PARAMS, DatasetMode::Full, false, 3);
+        assert_eq!(light.get_items_count(), 256);
+        for index in 0..light.get_items_count() {
+            assert_eq!(light.get_item(index), full.get_item(index));
+        }
+        // index is wrapped on the items count
+        assert_eq!(full.get_item(256), full.get_item(0));
+    }
+
+    #[test]
+    fn test_dataset_depends_on_seed() {
+        let a = EpochDataset::new(0, seed(1), TEST_PARAMS, DatasetMode::Light, false, 1);
+        let b = EpochDataset::new(0, seed(1), TEST_PARAMS, DatasetMode::Light, false, 1);
+        let c = EpochDataset::new(0, seed(2), TEST_PARAMS, DatasetMode::Light, false, 1);
+        assert_eq!(a.get_item(42), b.get_item(42));
+        assert_ne!(a.get_item(42), c.get_item(42));
+    }
+
+    #[test]
+    fn test_epoch_rotation() {
+        let manager = DatasetManager::new(config(DatasetMode::Full), TEST_PARAMS, DatasetMode::Light).unwrap();
+        assert_eq!(manager.get_memory_usage(), 0);
+
+        let first = manager.get_epoch(0, &seed(1));
+        assert!(Arc::ptr_eq(&first, &manager.get_epoch(0, &seed(1))));
+        assert_eq!(manager.get_memory_usage(), TEST_PARAMS.get_memory_usage(DatasetMode::Full));
+
+        manager.get_epoch(EPOCH_LENGTH, &seed(2));
+        manager.get_epoch(2 * EPOCH_LENGTH, &seed(3));
+        assert_eq!(manager.get_memory_usage(), MAX_EPOCHS * TEST_PARAMS.get_memory_usage(DatasetMode::Full));
+        // first epoch has been dropped
+        assert!(!Arc::ptr_eq(&first, &manager.get_epoch(0, &seed(1))));
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let params = DatasetParams {
+            cache_size: 1024 * 1024,
+            dataset_size: 4 * 1024 * 1024,
+            ..TEST_PARAMS
+        };
+
+        // full dataset doesn't fit, fallback on light mode
+        let manager = DatasetManager::new(DatasetConfig { max_memory: Some(2), ..config(DatasetMode::Full) }, params, DatasetMode::Light).unwrap();
+        assert_eq!(manager.get_mode(), DatasetMode::Light);
+
+        // only one epoch fits in the limit
+        manager.get_epoch(0, &seed(1));
+        manager.get_epoch(EPOCH_LENGTH, &seed(2));
+        assert_eq!(manager.get_memory_usage(), 2 * 1024 * 1024);
+        let manager = DatasetManager::new(DatasetConfig { max_memory: Some(1), ..config(DatasetMode::Light) }, params, DatasetMode::Light).unwrap();
+        manager.get_epoch(0, &seed(1));
+        manager.get_epoch(EPOCH_LENGTH, &seed(2));
+        assert_eq!(manager.get_memory_usage(), 1024 * 1024);
+
+        // even the cache doesn't fit
+        let params = DatasetParams { cache_size: 2 * 1024 * 1024, ..params };
+        assert!(matches!(
+            DatasetManager::new(DatasetConfig { max_memory: Some(1), ..config(DatasetMode::Light) }, params, DatasetMode::Full),
+            Err(DatasetError::MemoryLimitTooLow { limit: 1, required: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let params = DatasetParams { cache_size: ITEM_SIZE + 1, ..TEST_PARAMS };
+        assert!(matches!(DatasetManager::new(DatasetConfig::default(), params, DatasetMode::Light), Err(DatasetError::InvalidParams)));
+    }
+
+    #[test]
+    fn test_huge_pages_fallback() {
+        // huge pages are usually not reserved, the dataset must still be usable
+        let light = EpochDataset::new(0, seed(1), TEST_PARAMS, DatasetMode::Light, false, 1);
+        let huge = EpochDataset::new(0, seed(1), TEST_PARAMS, DatasetMode::Full, true, 2);
+        for index in 0..huge.get_items_count() {
+            assert_eq!(light.get_item(index), huge.get_item(index));
+        }
+    }
+}
@@ -19,6 +19,7 @@ use crossterm::event::{self, Event, KeyCode, KeyModifiers, KeyEventKind};
 use crossterm::terminal;
 use fern::colors::{ColoredLevelConfig, Color};
 use regex::Regex;
+use serde::{Serialize, Deserialize};
 use tokio::sync::{
     mpsc::{self, UnboundedSender, UnboundedReceiver, Sender, Receiver},
     oneshot,
@@ -32,7 +33,8 @@ use std::future::Future;
 use thiserror::Error;
 
 // used for launch param
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[cfg_attr(feature = "clap", derive(clap::ArgEnum))]
 pub enum LogLevel {
     Off,
@@ -75,6 +77,7 @@ impl FromStr for LogLevel {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
+            "off" => Self::Off,
             "error" => Self::Error,
             "warn" => Self::Warn,
             "info" => Self::Info,
@@ -361,6 +364,16 @@ type AsyncF<'a, T1, T2, R> = Box<dyn Fn(&'a T1, &'a T2) -> LocalBoxFuture<'a, R>
 
 impl Prompt {
     pub fn new(level: LogLevel, filename_log: String, disable_file_logging: bool) -> Result<ShareablePrompt, PromptError> {
+        Self::with_input(level, filename_log, disable_file_logging, true)
+    }
+
+    // only setup the logger, stdin is not read and the terminal is left untouched
+    // used when running as a service without any terminal attached
+    pub fn new_headless(level: LogLevel, filename_log: String, disable_file_logging: bool) -> Result<ShareablePrompt, PromptError> {
+        Self::with_input(level, filename_log, disable_file_logging, false)
+    }
+
+    fn with_input(level: LogLevel, filename_log: String, disable_file_logging: bool, read_input: bool) -> Result<ShareablePrompt, PromptError> {
         let (read_input_sender, read_input_receiver) = mpsc::channel(1);
         let zelf = Self {
             state: Arc::new(State::new()),
@@ -370,6 +383,9 @@ impl Prompt {
             read_input_sender,
         };
         zelf.setup_logger(level, filename_log, disable_file_logging)?;
+        if !read_input {
+            return Ok(Arc::new(zelf))
+        }
 
         // spawn a thread to prevent IO blocking - https://github.com/tokio-rs/tokio/issues/2466
         let (input_sender, input_receiver) = mpsc::unbounded_channel::<String>();
@@ -626,6 +642,11 @@ impl Prompt {
         self.state.mask_input.store(value, Ordering::SeqCst);
     }
 
+    // change the log level of both stdout and file logs
+    pub fn set_log_level(level: LogLevel) {
+        log::set_max_level(level.into());
+    }
+
     // configure fern and print prompt message after each new output
     fn setup_logger(&self, level: LogLevel, filename_log: String, disable_file_logging: bool) -> Result<(), fern::InitError> {
         let colors = ColoredLevelConfig::new()
@@ -658,7 +679,8 @@ impl Prompt {
                 res
             })
             .chain(std::io::stdout())
-            .level(level.into());
+            // filtered by the global max level so it can be changed at runtime
+            .level(LevelFilter::Trace);
 
         let mut base = base.chain(stdout_log);
         if !disable_file_logging {
@@ -670,7 +692,7 @@ impl Prompt {
             }
 
             let file_log = fern::Dispatch::new()
-            .level(level.into())
+            .level(LevelFilter::Trace)
             .format(move |out, message, record| {
                 let pad = " ".repeat((30i16 - record.target().len() as i16).max(0) as usize);
                 let level_pad = if record.level() == Level::Error || record.level() == Level::Debug { "" } else { " " };
@@ -696,6 +718,8 @@ impl Prompt {
         .level_for("tungstenite", log::LevelFilter::Warn)
         .apply()?;
 
+        Self::set_log_level(level);
+
         Ok(())
     }
 }
@@ -0,0 +1,437 @@
+// Python bindings over the daemon JSON-RPC clients, to script against a node from Python
+// Requests are validated using the same params structs as the daemon, results are returned as dicts
+// in the JSON format of the API, so they can be read using the API documentation
+// Calls are blocking and release the GIL while waiting for the node
+
+use std::{future::Future, sync::Arc, time::Duration};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tokio::runtime::Runtime;
+use crate::{
+    api::daemon::NotifyEvent,
+    config::XELIS_ASSET,
+    crypto::{address::Address, hash::Hash},
+    json_rpc::{DaemonClient, EventReceiver, JsonRPCError, JsonRPCResult, WebSocketJsonRPCClient, WebSocketJsonRPCClientShared},
+    network::Network,
+    serializer::Serializer,
+    transaction::Transaction,
+    utils::set_network_to
+};
+
+create_exception!(xelis_common, RpcError, PyException);
+
+fn to_py_error(e: JsonRPCError) -> PyErr {
+    RpcError::new_err(e.to_string())
+}
+
+fn create_runtime() -> PyResult<Arc<Runtime>> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map(Arc::new)
+        .map_err(|e| PyException::new_err(format!("Error while creating the runtime: {}", e)))
+}
+
+// wait on the future without holding the GIL so other Python threads can run
+fn block_on<F, T>(py: Python, runtime: &Runtime, future: F) -> PyResult<T>
+where
+    F: Future<Output = JsonRPCResult<T>> + Send,
+    T: Send
+{
+    py.allow_threads(|| runtime.block_on(future)).map_err(to_py_error)
+}
+
+// values are converted through JSON so they keep the format of the API
+fn to_python<T: Serialize>(py: Python, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+fn from_python<T: DeserializeOwned>(py: Python, value: &Bound<PyAny>) -> PyResult<T> {
+    let json: String = py.import("json")?.call_method1("dumps", (value,))?.extract()?;
+    serde_json::from_str(&json).map_err(|e| PyValueError::new_err(format!("Invalid params: {}", e)))
+}
+
+fn parse_hash(hash: String) -> PyResult<Hash> {
+    Hash::from_hex(hash).map_err(|e| PyValueError::new_err(format!("Invalid hash: {}", e)))
+}
+
+fn parse_address(address: String) -> PyResult<Address> {
+    Address::from_string(&address).map_err(|e| PyValueError::new_err(format!("Invalid address: {}", e)))
+}
+
+fn parse_asset(asset: Option<String>) -> PyResult<Hash> {
+    asset.map(parse_hash).unwrap_or(Ok(XELIS_ASSET))
+}
+
+// event name as in the API, for example "NewBlock"
+fn parse_event(event: &str) -> PyResult<NotifyEvent> {
+    serde_json::from_value(Value::String(event.to_owned())).map_err(|_| PyValueError::new_err(format!("Unknown event {}", event)))
+}
+
+// network used to display the keys in JSON, mainnet by default
+// must be set to the network of the node before any call
+#[pyfunction]
+fn set_network(network: &str) -> PyResult<()> {
+    let network: Network = network.parse().map_err(PyValueError::new_err)?;
+    set_network_to(network);
+    Ok(())
+}
+
+// Client over HTTP for the request/response methods
+#[pyclass(name = "DaemonClient")]
+pub struct PythonDaemonClient {
+    client: DaemonClient,
+    runtime: Arc<Runtime>
+}
+
+#[pymethods]
+impl PythonDaemonClient {
+    // target is the JSON-RPC route of the daemon, for example http://127.0.0.1:8080/json_rpc
+    #[new]
+    fn new(target: String) -> PyResult<Self> {
+        Ok(Self {
+            client: DaemonClient::new(target),
+            runtime: create_runtime()?
+        })
+    }
+
+    // call any method of the API, params are a dict
+    #[pyo3(signature = (method, params = None))]
+    fn call(&self, py: Python, method: &str, params: Option<&Bound<PyAny>>) -> PyResult<PyObject> {
+        let client = self.client.get_client();
+        let result: Value = match params {
+            Some(params) => {
+                let params: Value = from_python(py, params)?;
+                block_on(py, &self.runtime, client.call_with(method, &params))?
+            },
+            None => block_on(py, &self.runtime, client.call(method))?
+        };
+        to_python(py, &result)
+    }
+
+    fn get_version(&self, py: Python) -> PyResult<String> {
+        block_on(py, &self.runtime, self.client.get_version())
+    }
+
+    fn get_info(&self, py: Python) -> PyResult<PyObject> {
+        let info = block_on(py, &self.runtime, self.client.get_info())?;
+        to_python(py, &info)
+    }
+
+    fn get_height(&self, py: Python) -> PyResult<u64> {
+        block_on(py, &self.runtime, self.client.get_height())
+    }
+
+    fn get_topoheight(&self, py: Python) -> PyResult<u64> {
+        block_on(py, &self.runtime, self.client.get_topoheight())
+    }
+
+    fn get_stableheight(&self, py: Python) -> PyResult<u64> {
+        block_on(py, &self.runtime, self.client.get_stableheight())
+    }
+
+    #[pyo3(signature = (include_txs = false))]
+    fn get_top_block(&self, py: Python, include_txs: bool) -> PyResult<PyObject> {
+        if include_txs {
+            let block = block_on(py, &self.runtime, self.client.get_top_block_with_txs())?;
+            to_python(py, &block)
+        } else {
+            let block = block_on(py, &self.runtime, self.client.get_top_block())?;
+            to_python(py, &block)
+        }
+    }
+
+    #[pyo3(signature = (topoheight, include_txs = false))]
+    fn get_block_at_topoheight(&self, py: Python, topoheight: u64, include_txs: bool) -> PyResult<PyObject> {
+        if include_txs {
+            let block = block_on(py, &self.runtime, self.client.get_block_with_txs_at_topoheight(topoheight))?;
+            to_python(py, &block)
+        } else {
+            let block = block_on(py, &self.runtime, self.client.get_block_at_topoheight(topoheight))?;
+            to_python(py, &block)
+        }
+    }
+
+    #[pyo3(signature = (hash, include_txs = false))]
+    fn get_block_by_hash(&self, py: Python, hash: String, include_txs: bool) -> PyResult<PyObject> {
+        let hash = parse_hash(hash)?;
+        if include_txs {
+            let block = block_on(py, &self.runtime, self.client.get_block_with_txs_by_hash(&hash))?;
+            to_python(py, &block)
+        } else {
+            let block = block_on(py, &self.runtime, self.client.get_block_by_hash(&hash))?;
+            to_python(py, &block)
+        }
+    }
+
+    fn get_blocks_at_height(&self, py: Python, height: u64) -> PyResult<PyObject> {
+        let blocks = block_on(py, &self.runtime, self.client.get_blocks_at_height(height))?;
+        to_python(py, &blocks)
+    }
+
+    #[pyo3(signature = (start_topoheight = None, end_topoheight = None))]
+    fn get_blocks_range_by_topoheight(&self, py: Python, start_topoheight: Option<u64>, end_topoheight: Option<u64>) -> PyResult<PyObject> {
+        let blocks = block_on(py, &self.runtime, self.client.get_blocks_range_by_topoheight(start_topoheight, end_topoheight))?;
+        to_python(py, &blocks)
+    }
+
+    fn get_tips(&self, py: Python) -> PyResult<PyObject> {
+        let tips = block_on(py, &self.runtime, self.client.get_tips())?;
+        to_python(py, &tips)
+    }
+
+    fn get_tips_info(&self, py: Python) -> PyResult<PyObject> {
+        let info = block_on(py, &self.runtime, self.client.get_tips_info())?;
+        to_python(py, &info)
+    }
+
+    #[pyo3(signature = (hash, cumulative_difficulty = None))]
+    fn evaluate_block(&self, py: Python, hash: String, cumulative_difficulty: Option<u64>) -> PyResult<PyObject> {
+        let hash = parse_hash(hash)?;
+        let result = block_on(py, &self.runtime, self.client.evaluate_block(&hash, cumulative_difficulty))?;
+        to_python(py, &result)
+    }
+
+    #[pyo3(signature = (start_topoheight = None, end_topoheight = None))]
+    fn get_dag_order(&self, py: Python, start_topoheight: Option<u64>, end_topoheight: Option<u64>) -> PyResult<PyObject> {
+        let order = block_on(py, &self.runtime, self.client.get_dag_order(start_topoheight, end_topoheight))?;
+        to_python(py, &order)
+    }
+
+    // asset is XELIS if not set
+    #[pyo3(signature = (address, asset = None))]
+    fn get_last_balance(&self, py: Python, address: String, asset: Option<String>) -> PyResult<PyObject> {
+        let (address, asset) = (parse_address(address)?, parse_asset(asset)?);
+        let balance = block_on(py, &self.runtime, self.client.get_last_balance(&address, &asset))?;
+        to_python(py, &balance)
+    }
+
+    #[pyo3(signature = (address, topoheight, asset = None))]
+    fn get_balance_at_topoheight(&self, py: Python, address: String, topoheight: u64, asset: Option<String>) -> PyResult<PyObject> {
+        let (address, asset) = (parse_address(address)?, parse_asset(asset)?);
+        let balance = block_on(py, &self.runtime, self.client.get_balance_at_topoheight(&address, &asset, topoheight))?;
+        to_python(py, &balance)
+    }
+
+    #[pyo3(signature = (address, topoheight = None))]
+    fn get_nonce(&self, py: Python, address: String, topoheight: Option<u64>) -> PyResult<PyObject> {
+        let address = parse_address(address)?;
+        let nonce = block_on(py, &self.runtime, self.client.get_nonce(&address, topoheight))?;
+        to_python(py, &nonce)
+    }
+
+    fn get_account_pending(&self, py: Python, address: String) -> PyResult<PyObject> {
+        let address = parse_address(address)?;
+        let pending = block_on(py, &self.runtime, self.client.get_account_pending(&address))?;
+        to_python(py, &pending)
+    }
+
+    fn get_account_assets(&self, py: Python, address: String) -> PyResult<PyObject> {
+        let address = parse_address(address)?;
+        let assets = block_on(py, &self.runtime, self.client.get_account_assets(address))?;
+        to_python(py, &assets)
+    }
+
+    #[pyo3(signature = (address, asset = None, minimum_topoheight = None, maximum_topoheight = None))]
+    fn get_account_history(&self, py: Python, address: String, asset: Option<String>, minimum_topoheight: Option<u64>, maximum_topoheight: Option<u64>) -> PyResult<PyObject> {
+        let (address, asset) = (parse_address(address)?, parse_asset(asset)?);
+        let history = block_on(py, &self.runtime, self.client.get_account_history(address, asset, minimum_topoheight, maximum_topoheight))?;
+        to_python(py, &history)
+    }
+
+    fn get_asset(&self, py: Python, asset: String) -> PyResult<PyObject> {
+        let asset = parse_hash(asset)?;
+        let data = block_on(py, &self.runtime, self.client.get_asset(&asset))?;
+        to_python(py, &data)
+    }
+
+    fn get_transaction(&self, py: Python, hash: String) -> PyResult<PyObject> {
+        let hash = parse_hash(hash)?;
+        let tx = block_on(py, &self.runtime, self.client.get_transaction(&hash))?;
+        to_python(py, &tx)
+    }
+
+    fn get_transaction_confirmations(&self, py: Python, hash: String) -> PyResult<PyObject> {
+        let hash = parse_hash(hash)?;
+        let confirmations = block_on(py, &self.runtime, self.client.get_transaction_confirmations(&hash))?;
+        to_python(py, &confirmations)
+    }
+
+    fn get_transactions(&self, py: Python, hashes: Vec<String>) -> PyResult<PyObject> {
+        let hashes = hashes.into_iter().map(parse_hash).collect::<PyResult<Vec<Hash>>>()?;
+        let txs = block_on(py, &self.runtime, self.client.get_transactions(hashes))?;
+        to_python(py, &txs)
+    }
+
+    fn get_mempool(&self, py: Python) -> PyResult<PyObject> {
+        let txs = block_on(py, &self.runtime, self.client.get_mempool())?;
+        to_python(py, &txs)
+    }
+
+    // transaction signed by a wallet, in hex
+    fn submit_transaction(&self, py: Python, tx_as_hex: String) -> PyResult<PyObject> {
+        let tx = Transaction::from_hex(tx_as_hex).map_err(|e| PyValueError::new_err(format!("Invalid transaction: {}", e)))?;
+        let result = block_on(py, &self.runtime, self.client.submit_transaction(&tx))?;
+        to_python(py, &result)
+    }
+
+    // timeout is in seconds
+    #[pyo3(signature = (hash, confirmations, timeout = None))]
+    fn wait_for_confirmation(&self, py: Python, hash: String, confirmations: u64, timeout: Option<u64>) -> PyResult<PyObject> {
+        let hash = parse_hash(hash)?;
+        let result = block_on(py, &self.runtime, self.client.wait_for_confirmation(&hash, confirmations, timeout))?;
+        to_python(py, &result)
+    }
+
+    fn get_peers(&self, py: Python) -> PyResult<PyObject> {
+        let peers = block_on(py, &self.runtime, self.client.get_peers())?;
+        to_python(py, &peers)
+    }
+
+    fn p2p_status(&self, py: Python) -> PyResult<PyObject> {
+        let status = block_on(py, &self.runtime, self.client.p2p_status())?;
+        to_python(py, &status)
+    }
+
+    fn get_sync_status(&self, py: Python) -> PyResult<PyObject> {
+        let status = block_on(py, &self.runtime, self.client.get_sync_status())?;
+        to_python(py, &status)
+    }
+
+    fn get_bandwidth(&self, py: Python) -> PyResult<PyObject> {
+        let bandwidth = block_on(py, &self.runtime, self.client.get_bandwidth())?;
+        to_python(py, &bandwidth)
+    }
+
+    fn get_miners(&self, py: Python) -> PyResult<PyObject> {
+        let miners = block_on(py, &self.runtime, self.client.get_miners())?;
+        to_python(py, &miners)
+    }
+
+    fn get_hard_forks(&self, py: Python) -> PyResult<PyObject> {
+        let hard_forks = block_on(py, &self.runtime, self.client.get_hard_forks())?;
+        to_python(py, &hard_forks)
+    }
+
+    fn get_size_on_disk(&self, py: Python) -> PyResult<PyObject> {
+        let size = block_on(py, &self.runtime, self.client.get_size_on_disk())?;
+        to_python(py, &size)
+    }
+}
+
+// Client over WebSocket, to call methods and be notified of the daemon events
+// It reconnects automatically and subscribes again to its events in case of connection loss
+#[pyclass(name = "DaemonWebSocket")]
+pub struct PythonDaemonWebSocket {
+    client: WebSocketJsonRPCClientShared<NotifyEvent>,
+    runtime: Arc<Runtime>
+}
+
+#[pymethods]
+impl PythonDaemonWebSocket {
+    // target is the WebSocket route of the daemon, for example ws://127.0.0.1:8080/ws
+    #[new]
+    fn new(py: Python, target: String) -> PyResult<Self> {
+        let runtime = create_runtime()?;
+        let client = block_on(py, &runtime, WebSocketJsonRPCClient::new(target))?;
+        Ok(Self {
+            client,
+            runtime
+        })
+    }
+
+    fn is_online(&self) -> bool {
+        self.client.is_online()
+    }
+
+    #[pyo3(signature = (method, params = None))]
+    fn call(&self, py: Python, method: &str, params: Option<&Bound<PyAny>>) -> PyResult<PyObject> {
+        let result: Value = match params {
+            Some(params) => {
+                let params: Value = from_python(py, params)?;
+                block_on(py, &self.runtime, self.client.call_with(method, &params))?
+            },
+            None => block_on(py, &self.runtime, self.client.call(method))?
+        };
+        to_python(py, &result)
+    }
+
+    // event name as in the API, for example "NewBlock"
+    fn subscribe(&self, py: Python, event: &str) -> PyResult<EventSubscription> {
+        let event = parse_event(event)?;
+        let receiver = block_on(py, &self.runtime, self.client.subscribe_event(event))?;
+        Ok(EventSubscription {
+            receiver,
+            runtime: Arc::clone(&self.runtime)
+        })
+    }
+
+    // every subscription of this event is stopped
+    fn unsubscribe(&self, py: Python, event: &str) -> PyResult<()> {
+        let event = parse_event(event)?;
+        block_on(py, &self.runtime, self.client.unsubscribe_event(&event))
+    }
+
+    fn close(&self, py: Python) -> PyResult<()> {
+        block_on(py, &self.runtime, self.client.close())
+    }
+}
+
+// Events received for a subscription, it can be iterated until the event is unsubscribed
+#[pyclass]
+pub struct EventSubscription {
+    receiver: EventReceiver<Value>,
+    runtime: Arc<Runtime>
+}
+
+#[pymethods]
+impl EventSubscription {
+    // wait for the next event, returns None if the timeout in seconds is reached
+    #[pyo3(signature = (timeout = None))]
+    fn next(&mut self, py: Python, timeout: Option<f64>) -> PyResult<Option<PyObject>> {
+        let receiver = &mut self.receiver;
+        let event = match timeout {
+            Some(timeout) => {
+                let timeout = Duration::try_from_secs_f64(timeout).map_err(|e| PyValueError::new_err(e.to_string()))?;
+                match block_on(py, &self.runtime, async {
+                    Ok(tokio::time::timeout(timeout, receiver.next()).await)
+                })? {
+                    Ok(event) => event.map_err(to_py_error)?,
+                    Err(_) => return Ok(None)
+                }
+            },
+            None => block_on(py, &self.runtime, receiver.next())?
+        };
+        to_python(py, &event).map(Some)
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    // stop the iteration once the event is unsubscribed or the client is closed
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let (runtime, receiver) = (&self.runtime, &mut self.receiver);
+        match py.allow_threads(|| runtime.block_on(receiver.next())) {
+            Ok(event) => to_python(py, &event).map(Some),
+            Err(JsonRPCError::EventNotSubscribed) => Ok(None),
+            Err(e) => Err(to_py_error(e))
+        }
+    }
+}
+
+#[pymodule]
+fn xelis_common(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(set_network, m)?)?;
+    m.add_class::<PythonDaemonClient>()?;
+    m.add_class::<PythonDaemonWebSocket>()?;
+    m.add_class::<EventSubscription>()?;
+    m.add("RpcError", m.py().get_type::<RpcError>())?;
+    Ok(())
+}
@@ -3,7 +3,7 @@ use actix_web::{ResponseError, HttpResponse};
 use serde_json::{Value, Error as SerdeError, json};
 use thiserror::Error;
 use anyhow::Error as AnyError;
-use crate::{serializer::ReaderError, rpc_server::JSON_RPC_VERSION};
+use crate::{serializer::ReaderError, rpc_server::JSON_RPC_VERSION, api::{ErrorCode, ErrorData}};
 
 #[derive(Error, Debug)]
 pub enum InternalRpcError {
@@ -33,6 +33,8 @@ pub enum InternalRpcError {
     EventNotSubscribed,
     #[error("Event is already subscribed")]
     EventAlreadySubscribed,
+    #[error("A session can't watch more than {} accounts", _0)]
+    TooManyWatchedAccounts(usize),
     #[error("{}", _0)]
     Custom(String),
     #[error("{}", _0)]
@@ -54,17 +56,25 @@ impl InternalRpcError {
 #[derive(Debug)]
 pub struct RpcResponseError {
     id: Option<usize>,
-    error: InternalRpcError
+    error: InternalRpcError,
+    // stable code sent in the error data
+    error_code: Option<ErrorCode>
 }
 
 impl RpcResponseError {
     pub fn new(id: Option<usize>, error: InternalRpcError) -> Self {
         Self {
             id,
-            error
+            error,
+            error_code: None
         }
     }
 
+    pub fn with_error_code(mut self, error_code: Option<ErrorCode>) -> Self {
+        self.error_code = error_code;
+        self
+    }
+
     pub fn get_id(&self) -> Value {
         match self.id {
             Some(id) => json!(id),
@@ -73,13 +83,18 @@ impl RpcResponseError {
     }
 
     pub fn to_json(&self) -> Value {
+        let mut error = json!({
+            "code": self.error.get_code(),
+            "message": self.error.to_string()
+        });
+        if let Some(code) = self.error_code {
+            error["data"] = json!(ErrorData { code });
+        }
+
         json!({
             "jsonrpc": JSON_RPC_VERSION,
             "id": self.get_id(),
-            "error": {
-                "code": self.error.get_code(),
-                "message": self.error.to_string()
-            }
+            "error": error
         })
     }
 }
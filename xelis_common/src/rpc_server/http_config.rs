@@ -0,0 +1,104 @@
+use std::{net::IpAddr, num::NonZeroUsize, thread};
+use actix_cors::Cors;
+use actix_web::{HttpRequest, http::Method};
+
+// header set by reverse proxies with the address of the client
+const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+// how long a browser can cache the CORS preflight response
+const CORS_MAX_AGE: usize = 3600;
+
+// Options of the HTTP server to run it behind a reverse proxy (nginx, Caddy...)
+// and to allow browser dApps to call it directly
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct HttpServerConfig {
+    /// Origin allowed to send cross-origin requests to the RPC server, * for any (can be set multiple times)
+    #[cfg_attr(feature = "clap", clap(long = "rpc-cors-origin"))]
+    pub cors_origins: Vec<String>,
+    /// IP of a reverse proxy trusted to set the X-Forwarded-For header (can be set multiple times)
+    #[cfg_attr(feature = "clap", clap(long = "rpc-trusted-proxy"))]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Path under which all the RPC endpoints are mounted, for example /xelis
+    #[cfg_attr(feature = "clap", clap(long = "rpc-path-prefix"))]
+    pub path_prefix: Option<String>,
+    /// Number of threads handling the RPC requests, default is the number of CPU cores
+    #[cfg_attr(feature = "clap", clap(long = "rpc-threads"))]
+    pub threads: Option<usize>
+}
+
+impl HttpServerConfig {
+    pub fn is_cors_enabled(&self) -> bool {
+        !self.cors_origins.is_empty()
+    }
+
+    // workers of the HTTP server, each one runs on its own thread
+    pub fn get_threads(&self) -> usize {
+        self.threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get)).max(1)
+    }
+
+    // CORS middleware, only configured origins are allowed
+    pub fn build_cors(&self) -> Cors {
+        let mut cors = Cors::default()
+            .allowed_methods([Method::GET, Method::POST])
+            .allow_any_header()
+            .max_age(CORS_MAX_AGE);
+
+        for origin in &self.cors_origins {
+            if origin == "*" {
+                cors = cors.allow_any_origin();
+            } else {
+                cors = cors.allowed_origin(origin);
+            }
+        }
+
+        cors
+    }
+
+    // path prefix without trailing slash, empty if not set
+    pub fn get_path_prefix(&self) -> String {
+        match &self.path_prefix {
+            Some(prefix) => {
+                let prefix = prefix.trim_matches('/');
+                if prefix.is_empty() {
+                    String::new()
+                } else {
+                    format!("/{}", prefix)
+                }
+            },
+            None => String::new()
+        }
+    }
+
+    pub fn is_trusted_proxy(&self, ip: &IpAddr) -> bool {
+        self.trusted_proxies.contains(ip)
+    }
+
+    // address of the client who sent the request
+    // X-Forwarded-For header is only used if the request comes from a trusted proxy,
+    // and it is read from the right to skip the addresses added by the trusted proxies
+    pub fn get_client_address(&self, request: &HttpRequest) -> Option<IpAddr> {
+        let mut address = request.peer_addr()?.ip();
+        if !self.is_trusted_proxy(&address) {
+            return Some(address)
+        }
+
+        let Some(header) = request.headers().get(FORWARDED_FOR_HEADER).and_then(|value| value.to_str().ok()) else {
+            return Some(address)
+        };
+
+        for value in header.rsplit(',') {
+            match value.trim().parse::<IpAddr>() {
+                Ok(ip) => {
+                    address = ip;
+                    if !self.is_trusted_proxy(&ip) {
+                        break
+                    }
+                },
+                // invalid value, we can't go further
+                Err(_) => break
+            }
+        }
+
+        Some(address)
+    }
+}
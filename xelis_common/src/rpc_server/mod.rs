@@ -1,17 +1,27 @@
 pub mod websocket;
 mod error;
 mod rpc_handler;
+mod schema;
+mod http_config;
 
 use std::borrow::Cow;
 
 pub use error::{RpcResponseError, InternalRpcError};
-pub use rpc_handler::{RPCHandler, Handler};
+pub use rpc_handler::{RPCHandler, Handler, RPCHook, ErrorCodeResolver};
 pub use rpc_handler::parse_params;
+pub use schema::{RPCSchema, OPENRPC_VERSION};
+pub use http_config::HttpServerConfig;
 
-use actix_web::{HttpResponse, web::{self, Data, Payload}, Responder, HttpRequest};
+use actix_web::{HttpResponse, web::{self, Data, Payload}, Responder, HttpRequest, http::header::ContentType};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::{
+    api::signature::{sign_response, NODE_KEY_HEADER, SIGNATURE_HEADER, SIGNATURE_TIMESTAMP_HEADER},
+    config::VERSION,
+    crypto::key::KeyPair,
+    utils::get_current_time
+};
 use self::websocket::{WebSocketServerShared, WebSocketHandler};
 
 pub const JSON_RPC_VERSION: &str = "2.0";
@@ -44,6 +54,11 @@ impl<'a> RpcResponse<'a> {
 // trait to retrieve easily a JSON RPC handler for registered route
 pub trait RPCServerHandler<T: Send + Clone> {
     fn get_rpc_handler(&self) -> &RPCHandler<T>;
+
+    // key used to sign the JSON-RPC responses, None to not sign them
+    fn get_response_signer(&self) -> Option<&KeyPair> {
+        None
+    }
 }
 
 // JSON RPC handler endpoint
@@ -52,8 +67,30 @@ where
     T: Send + Sync + Clone + 'static,
     H: RPCServerHandler<T>
 {
-    let result = server.get_rpc_handler().handle_request(&body).await?;
-    Ok(HttpResponse::Ok().json(result))
+    let Some(keypair) = server.get_response_signer() else {
+        let result = server.get_rpc_handler().handle_request(&body).await?;
+        // a batch of notifications only has no response
+        if result.is_null() {
+            return Ok(HttpResponse::NoContent().finish())
+        }
+        return Ok(HttpResponse::Ok().json(result))
+    };
+
+    // errors are signed too, so a middleman can't forge them
+    let result = server.get_rpc_handler().handle_request(&body).await.unwrap_or_else(|e| e.to_json());
+    if result.is_null() {
+        return Ok(HttpResponse::NoContent().finish())
+    }
+    let response = serde_json::to_vec(&result).map_err(|e| RpcResponseError::new(None, InternalRpcError::AnyError(e.into())))?;
+    let timestamp = get_current_time();
+    let signature = sign_response(keypair, timestamp, &body, &response);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .insert_header((NODE_KEY_HEADER, hex::encode(keypair.get_public_key().as_bytes())))
+        .insert_header((SIGNATURE_HEADER, signature.to_hex()))
+        .insert_header((SIGNATURE_TIMESTAMP_HEADER, timestamp.to_string()))
+        .body(response))
 }
 
 // trait to retrieve easily a websocket handler for registered route
@@ -69,4 +106,18 @@ where
 {
     let response = server.get_websocket().handle_connection(request, body).await?;
     Ok(response)
-}
\ No newline at end of file
+}
+
+// default index endpoint shared by all RPC servers
+pub async fn index() -> impl Responder {
+    HttpResponse::Ok().body(format!("Hello, world!\nRunning on: {}", VERSION))
+}
+
+// OpenRPC document of all the methods available, used by SDK generators
+pub async fn schema<T, H>(server: Data<H>) -> impl Responder
+where
+    T: Send + Sync + Clone + 'static,
+    H: RPCServerHandler<T>
+{
+    HttpResponse::Ok().json(server.get_rpc_handler().get_openrpc_document())
+}
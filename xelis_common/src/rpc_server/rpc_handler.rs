@@ -1,15 +1,50 @@
-use std::{collections::HashMap, pin::Pin, future::Future};
+use std::{collections::HashMap, pin::Pin, future::Future, sync::Arc};
+use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde_json::{Value, json};
-use crate::context::Context;
+use crate::{context::Context, config::MAX_BATCH_REQUESTS, api::ErrorCode};
 
-use super::{InternalRpcError, RpcResponseError, RpcRequest, JSON_RPC_VERSION};
+use super::{InternalRpcError, RpcResponseError, RpcRequest, RPCSchema, JSON_RPC_VERSION};
 use log::{error, trace};
 
 pub type Handler = fn(Context, Value) -> Pin<Box<dyn Future<Output = Result<Value, InternalRpcError>> + Send>>;
 
+// Find the stable code of an error returned by a method, set in the error data of the response
+pub type ErrorCodeResolver = fn(&InternalRpcError) -> Option<ErrorCode>;
+
+// Middleware called around the execution of each RPC method
+// It can be used for logging, metrics, permissions or rate limiting
+#[async_trait]
+pub trait RPCHook: Send + Sync {
+    // called before executing the method, returning an error rejects the request
+    // also called for an unknown method, so a rejected client can't probe the methods registered
+    async fn before_execution(&self, _request: &RpcRequest) -> Result<(), InternalRpcError> {
+        Ok(())
+    }
+
+    // called once the method was executed, with the params of the request
+    // also called with a MethodNotFound error for an unknown method
+    async fn after_execution(&self, _request: &RpcRequest, _result: &Result<Value, InternalRpcError>) {}
+}
+
+// allow to keep a reference on a hook registered
+#[async_trait]
+impl<H: RPCHook> RPCHook for Arc<H> {
+    async fn before_execution(&self, request: &RpcRequest) -> Result<(), InternalRpcError> {
+        self.as_ref().before_execution(request).await
+    }
+
+    async fn after_execution(&self, request: &RpcRequest, result: &Result<Value, InternalRpcError>) {
+        self.as_ref().after_execution(request, result).await
+    }
+}
+
 pub struct RPCHandler<T: Send + Clone + 'static> {
     methods: HashMap<String, Handler>, // all RPC methods registered
+    hooks: Vec<Box<dyn RPCHook>>, // called in registration order
+    schema: RPCSchema, // params and result types of the methods
+    error_code_resolver: Option<ErrorCodeResolver>,
     data: T
 }
 
@@ -20,17 +55,61 @@ where
     pub fn new(data: T) -> Self {
         Self {
             methods: HashMap::new(),
+            hooks: Vec::new(),
+            schema: RPCSchema::new("XELIS RPC".to_owned()),
+            error_code_resolver: None,
             data
         }
     }
 
+    // returns Null if nothing must be sent back (a batch of notifications only)
     pub async fn handle_request(&self, body: &[u8]) -> Result<Value, RpcResponseError> {
-        self.handle_request_with_context(Context::default(), body).await
+        self.handle_request_with_context(Context::default, body).await
     }
 
-    pub async fn handle_request_with_context(&self, context: Context, body: &[u8]) -> Result<Value, RpcResponseError> {
-        let request = self.parse_request(body)?;
-        self.execute_method(context, request).await
+    // a new context is built for each request executed, as a batch may contain several of them
+    pub async fn handle_request_with_context<F: Fn() -> Context>(&self, build_context: F, body: &[u8]) -> Result<Value, RpcResponseError> {
+        let value: Value = serde_json::from_slice(body).map_err(|_| RpcResponseError::new(None, InternalRpcError::ParseBodyError))?;
+        match value {
+            Value::Array(requests) => self.handle_batch_request(build_context, requests).await,
+            value => {
+                let request = self.parse_request_from_value(value)?;
+                self.execute_method(build_context(), request).await
+            }
+        }
+    }
+
+    // execute all requests of a JSON-RPC batch in order, a failing request doesn't stop the others
+    // notifications (requests without id) are executed but don't have a response
+    // returns Null instead of an empty array if the batch only contains notifications
+    pub async fn handle_batch_request<F: Fn() -> Context>(&self, build_context: F, requests: Vec<Value>) -> Result<Value, RpcResponseError> {
+        if requests.is_empty() || requests.len() > MAX_BATCH_REQUESTS {
+            return Err(RpcResponseError::new(None, InternalRpcError::InvalidRequest));
+        }
+
+        let mut responses = Vec::with_capacity(requests.len());
+        for value in requests {
+            let response = match self.parse_request_from_value(value) {
+                Ok(request) => {
+                    let notification = request.id.is_none();
+                    let response = match self.execute_method(build_context(), request).await {
+                        Ok(result) => result,
+                        Err(e) => e.to_json()
+                    };
+                    if notification {
+                        continue;
+                    }
+                    response
+                },
+                Err(e) => e.to_json()
+            };
+            responses.push(response);
+        }
+
+        if responses.is_empty() {
+            return Ok(Value::Null)
+        }
+        Ok(Value::Array(responses))
     }
 
     pub fn parse_request(&self, body: &[u8]) -> Result<RpcRequest, RpcResponseError> {
@@ -41,20 +120,49 @@ where
         Ok(request)
     }
 
+    pub fn parse_request_from_value(&self, value: Value) -> Result<RpcRequest, RpcResponseError> {
+        let request: RpcRequest = serde_json::from_value(value).map_err(|_| RpcResponseError::new(None, InternalRpcError::ParseBodyError))?;
+        if request.jsonrpc != JSON_RPC_VERSION {
+            return Err(RpcResponseError::new(request.id, InternalRpcError::InvalidVersion));
+        }
+        Ok(request)
+    }
+
     pub fn has_method(&self, method_name: &String) -> bool {
         self.methods.contains_key(method_name)
     }
 
     pub async fn execute_method(&self, mut context: Context, mut request: RpcRequest) -> Result<Value, RpcResponseError> {
+        for hook in &self.hooks {
+            hook.before_execution(&request).await.map_err(|err| self.to_response_error(request.id, err))?;
+        }
+
         let handler = match self.methods.get(&request.method) {
             Some(handler) => handler,
-            None => return Err(RpcResponseError::new(request.id, InternalRpcError::MethodNotFound(request.method)))
+            None => {
+                let result = Err(InternalRpcError::MethodNotFound(request.method.clone()));
+                for hook in &self.hooks {
+                    hook.after_execution(&request, &result).await;
+                }
+                return Err(RpcResponseError::new(request.id, InternalRpcError::MethodNotFound(request.method)))
+            }
         };
+
         trace!("executing '{}' RPC method", request.method);
-        let params = request.params.take().unwrap_or(Value::Null);
+        // params are only copied if a hook needs them after the execution
+        let params = if self.hooks.is_empty() {
+            request.params.take()
+        } else {
+            request.params.clone()
+        }.unwrap_or(Value::Null);
         // Add the data
         context.store(self.get_data().clone());
-        let result = handler(context, params).await.map_err(|err| RpcResponseError::new(request.id, err))?;
+        let result = handler(context, params).await;
+        for hook in &self.hooks {
+            hook.after_execution(&request, &result).await;
+        }
+
+        let result = result.map_err(|err| self.to_response_error(request.id, err))?;
         Ok(json!({
             "jsonrpc": JSON_RPC_VERSION,
             "id": request.id,
@@ -62,6 +170,15 @@ where
         }))
     }
 
+    fn to_response_error(&self, id: Option<usize>, error: InternalRpcError) -> RpcResponseError {
+        let error_code = self.error_code_resolver.and_then(|resolver| resolver(&error));
+        RpcResponseError::new(id, error).with_error_code(error_code)
+    }
+
+    pub fn set_error_code_resolver(&mut self, resolver: ErrorCodeResolver) {
+        self.error_code_resolver = Some(resolver);
+    }
+
     // register a new RPC method handler
     pub fn register_method(&mut self, name: &str, handler: Handler) {
         if self.methods.insert(name.into(), handler).is_some() {
@@ -69,11 +186,142 @@ where
         }
     }
 
+    // register a new RPC method handler and describe it in the OpenRPC document
+    // P is the params type (() if none) and R the result type
+    pub fn register_method_with_schema<P: JsonSchema, R: JsonSchema>(&mut self, name: &str, handler: Handler) {
+        self.register_method(name, handler);
+        self.schema.register_method::<P, R>(name);
+    }
+
+    // describe the events notified by this server in the OpenRPC document
+    pub fn register_events_schema<E: JsonSchema>(&mut self) {
+        self.schema.register_events::<E>();
+    }
+
+    // title of the generated OpenRPC document
+    pub fn set_schema_title<S: Into<String>>(&mut self, title: S) {
+        self.schema.set_title(title.into());
+    }
+
+    // OpenRPC document of all the registered methods
+    pub fn get_openrpc_document(&self) -> Value {
+        self.schema.to_openrpc(self.methods.keys())
+    }
+
+    // register a new hook called around each method execution
+    pub fn register_hook<H: RPCHook + 'static>(&mut self, hook: H) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    // names of all the registered methods
+    pub fn get_methods(&self) -> impl Iterator<Item = &String> {
+        self.methods.keys()
+    }
+
     pub fn get_data(&self) -> &T {
         &self.data
     }
 }
 
+// allow a client in the same process to execute its requests directly
+#[cfg(feature = "json_rpc")]
+#[async_trait]
+impl<T> crate::json_rpc::LocalRequestHandler for RPCHandler<T>
+where
+    T: Send + Sync + Clone + 'static
+{
+    async fn handle_local_request(&self, body: &[u8]) -> Value {
+        match self.handle_request(body).await {
+            Ok(response) => response,
+            Err(e) => e.to_json()
+        }
+    }
+}
+
 pub fn parse_params<P: DeserializeOwned>(value: Value) -> Result<P, InternalRpcError> {
     serde_json::from_value(value).map_err(|e| InternalRpcError::InvalidParams(e))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use async_trait::async_trait;
+    use serde_json::{Value, json};
+    use crate::{context::Context, async_handler};
+    use super::{RPCHandler, RPCHook, RpcRequest, InternalRpcError};
+
+    // rejects every request and records the methods called
+    #[derive(Default)]
+    struct _RejectHook {
+        before: Mutex<Vec<String>>,
+        after: Mutex<Vec<String>>
+    }
+
+    #[async_trait]
+    impl RPCHook for _RejectHook {
+        async fn before_execution(&self, request: &RpcRequest) -> Result<(), InternalRpcError> {
+            self.before.lock().unwrap().push(request.method.clone());
+            Err(InternalRpcError::CustomStr("rejected"))
+        }
+
+        async fn after_execution(&self, request: &RpcRequest, _: &Result<Value, InternalRpcError>) {
+            self.after.lock().unwrap().push(request.method.clone());
+        }
+    }
+
+    async fn _echo(_: Context, body: Value) -> Result<Value, InternalRpcError> {
+        Ok(body)
+    }
+
+    fn _create_handler() -> RPCHandler<()> {
+        let mut handler = RPCHandler::new(());
+        handler.register_method("echo", async_handler!(_echo));
+        handler
+    }
+
+    #[tokio::test]
+    async fn test_batch_skips_notifications() {
+        let handler = _create_handler();
+        let body = json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "echo", "params": 1 },
+            { "jsonrpc": "2.0", "method": "echo", "params": 2 },
+            { "jsonrpc": "2.0", "method": "unknown" },
+            { "jsonrpc": "2.0", "id": 2, "method": "unknown" }
+        ]);
+        let response = handler.handle_request(body.to_string().as_bytes()).await.unwrap();
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"], json!(1));
+        assert_eq!(responses[1]["id"], json!(2));
+        assert!(responses[1].get("error").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_batch_of_notifications() {
+        let handler = _create_handler();
+        let body = json!([
+            { "jsonrpc": "2.0", "method": "echo", "params": 1 },
+            { "jsonrpc": "2.0", "method": "echo", "params": 2 }
+        ]);
+        let response = handler.handle_request(body.to_string().as_bytes()).await.unwrap();
+        assert!(response.is_null());
+
+        assert!(handler.handle_request(b"[]").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_before_method_not_found() {
+        let mut handler = _create_handler();
+        let hook = Arc::new(_RejectHook::default());
+        handler.register_hook(Arc::clone(&hook));
+
+        // an unknown method is rejected by the hook like any other one
+        for method in ["echo", "unknown"] {
+            let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method });
+            let error = handler.handle_request(body.to_string().as_bytes()).await.unwrap_err();
+            assert_eq!(error.to_json()["error"]["message"], json!("rejected"));
+        }
+
+        assert_eq!(*hook.before.lock().unwrap(), vec!["echo", "unknown"]);
+        assert!(hook.after.lock().unwrap().is_empty());
+    }
+}
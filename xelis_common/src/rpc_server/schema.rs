@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, HashSet};
+use schemars::{
+    JsonSchema,
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::{Schema, SchemaObject, InstanceType, SingleOrVec},
+    Map
+};
+use serde_json::{Value, json};
+use crate::{config::VERSION, api::EVENTS_VERSION};
+
+// OpenRPC specification version of the generated document
+pub const OPENRPC_VERSION: &str = "1.2.6";
+// where all the shared types are stored in the document
+const DEFINITIONS_PATH: &str = "#/components/schemas/";
+
+// Params and result of a RPC method
+struct MethodSchema {
+    // None if the method doesn't expect any params
+    params: Option<SchemaObject>,
+    result: Schema
+}
+
+// Build an OpenRPC document describing the registered methods
+// Schemas are generated from the API structs used by the handlers,
+// so the document is always up to date with the code
+pub struct RPCSchema {
+    title: String,
+    methods: BTreeMap<String, MethodSchema>,
+    // payloads of the events notified to the websocket clients
+    events: Option<Schema>,
+    // all types referenced by the methods and events
+    definitions: Map<String, Schema>
+}
+
+impl RPCSchema {
+    pub fn new(title: String) -> Self {
+        Self {
+            title,
+            methods: BTreeMap::new(),
+            events: None,
+            definitions: Map::new()
+        }
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    fn create_generator() -> SchemaGenerator {
+        SchemaSettings::draft07().with(|settings| {
+            settings.definitions_path = DEFINITIONS_PATH.to_owned();
+        }).into_generator()
+    }
+
+    // register the params and result types of a method
+    // use () as params type for a method without params
+    pub fn register_method<P: JsonSchema, R: JsonSchema>(&mut self, name: &str) {
+        let mut generator = Self::create_generator();
+        let root = generator.root_schema_for::<P>();
+        let params = if is_null_schema(&root.schema) {
+            None
+        } else {
+            Some(root.schema)
+        };
+        self.definitions.extend(root.definitions);
+
+        let result = generator.subschema_for::<R>();
+        self.definitions.extend(generator.take_definitions());
+
+        self.methods.insert(name.to_owned(), MethodSchema { params, result });
+    }
+
+    // register the enum of the events notified by the server
+    // exported under the `x-events` extension as OpenRPC has no notion of subscriptions
+    pub fn register_events<E: JsonSchema>(&mut self) {
+        let mut generator = Self::create_generator();
+        let schema = generator.subschema_for::<E>();
+        self.definitions.extend(generator.take_definitions());
+        self.events = Some(schema);
+    }
+
+    // generate the OpenRPC document
+    // methods without registered schema accept any params and can return any value
+    pub fn to_openrpc<'a, I: Iterator<Item = &'a String>>(&self, method_names: I) -> Value {
+        let mut names: Vec<&String> = method_names.collect();
+        names.sort();
+
+        let methods: Vec<Value> = names.into_iter().map(|name| match self.methods.get(name) {
+            Some(schema) => json!({
+                "name": name,
+                "paramStructure": "by-name",
+                "params": schema.params.as_ref().map(build_params).unwrap_or_default(),
+                "result": {
+                    "name": "result",
+                    "schema": schema.result
+                }
+            }),
+            None => json!({
+                "name": name,
+                "params": [],
+                "result": {
+                    "name": "result",
+                    "schema": Schema::Bool(true)
+                }
+            })
+        }).collect();
+
+        let mut document = json!({
+            "openrpc": OPENRPC_VERSION,
+            "info": {
+                "title": self.title,
+                "version": VERSION
+            },
+            "methods": methods,
+            "components": {
+                "schemas": self.definitions
+            }
+        });
+
+        if let Some(events) = self.events.as_ref() {
+            document["x-events"] = json!({
+                "version": EVENTS_VERSION,
+                "schema": events
+            });
+        }
+
+        document
+    }
+}
+
+fn is_null_schema(schema: &SchemaObject) -> bool {
+    matches!(schema.instance_type, Some(SingleOrVec::Single(ref kind)) if **kind == InstanceType::Null)
+}
+
+// Each field of the params object is described as a param
+// Fields coming from a flattened enum are added as optional params
+fn build_params(schema: &SchemaObject) -> Vec<Value> {
+    let mut params = Vec::new();
+    let mut names = HashSet::new();
+    let mut add_object = |object: &SchemaObject, required: bool| {
+        let Some(validation) = object.object.as_ref() else {
+            return
+        };
+
+        for (name, schema) in &validation.properties {
+            if !names.insert(name.clone()) {
+                continue
+            }
+
+            params.push(json!({
+                "name": name,
+                "required": required && validation.required.contains(name),
+                "schema": schema
+            }));
+        }
+    };
+
+    add_object(schema, true);
+    if let Some(subschemas) = schema.subschemas.as_ref() {
+        let variants = subschemas.one_of.iter()
+            .chain(subschemas.any_of.iter())
+            .flatten();
+
+        for variant in variants {
+            if let Schema::Object(object) = variant {
+                add_object(object, false);
+            }
+        }
+
+        for schema in subschemas.all_of.iter().flatten() {
+            if let Schema::Object(object) = schema {
+                add_object(object, true);
+            }
+        }
+    }
+
+    params
+}
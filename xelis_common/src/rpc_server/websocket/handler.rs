@@ -5,12 +5,14 @@ use log::debug;
 use serde_json::{Value, json};
 use serde::{de::DeserializeOwned, Serialize};
 use tokio::sync::Mutex;
-use crate::{rpc_server::{RPCHandler, RpcResponseError, InternalRpcError, RpcRequest, RpcResponse}, api::{SubscribeParams, EventResult}, context::Context};
+use crate::{rpc_server::{RPCHandler, RpcResponseError, InternalRpcError, RpcRequest, RpcResponse}, api::{SubscribeParams, EventResult}, context::Context, crypto::key::PublicKey, config::MAX_BATCH_REQUESTS};
 use super::{WebSocketSessionShared, WebSocketHandler};
 
 // generic websocket handler supporting event subscriptions 
 pub struct EventWebSocketHandler<T: Sync + Send + Clone + 'static, E: Serialize + DeserializeOwned + Send + Eq + Hash + Clone + 'static> {
     sessions: Mutex<HashMap<WebSocketSessionShared<Self>, HashMap<E, Option<usize>>>>,
+    // accounts watched by a session, it only receives the events notified for one of them
+    watched_keys: Mutex<HashMap<WebSocketSessionShared<Self>, HashSet<PublicKey>>>,
     handler: RPCHandler<T>
 }
 
@@ -22,6 +24,7 @@ where
     pub fn new(handler: RPCHandler<T>) -> Self {
         Self {
             sessions: Mutex::new(HashMap::new()),
+            watched_keys: Mutex::new(HashMap::new()),
             handler
         }
     }
@@ -40,10 +43,27 @@ where
     }
 
     pub async fn notify(&self, event: &E, value: Value) {
+        self.notify_internal(event, value, None).await
+    }
+
+    // notify an event involving these accounts
+    // sessions watching accounts only receive it if one of them is watched
+    pub async fn notify_for_keys(&self, event: &E, value: Value, keys: &[PublicKey]) {
+        self.notify_internal(event, value, Some(keys)).await
+    }
+
+    async fn notify_internal(&self, event: &E, value: Value, keys: Option<&[PublicKey]>) {
         let value = json!(EventResult { event: Cow::Borrowed(event), value });
         let sessions = self.sessions.lock().await;
+        let watched_keys = self.watched_keys.lock().await;
         for (session, subscriptions) in sessions.iter() {
             if let Some(id) = subscriptions.get(event) {
+                if let (Some(keys), Some(watched)) = (keys, watched_keys.get(session)) {
+                    if !keys.iter().any(|key| watched.contains(key)) {
+                        continue;
+                    }
+                }
+
                 let response = json!(RpcResponse::new(Cow::Borrowed(&id), Cow::Borrowed(&value)));
                 let session = session.clone();
                 tokio::spawn(async move {
@@ -55,6 +75,49 @@ where
         }
     }
 
+    // add accounts to the ones watched by the session, returns the count of accounts watched
+    pub async fn watch_keys(&self, session: &WebSocketSessionShared<Self>, keys: Vec<PublicKey>, limit: usize) -> Result<usize, InternalRpcError> {
+        let mut watched_keys = self.watched_keys.lock().await;
+        let watched = watched_keys.entry(session.clone()).or_insert_with(HashSet::new);
+        let count = keys.iter().filter(|key| !watched.contains(*key)).count();
+        if watched.len() + count > limit {
+            return Err(InternalRpcError::TooManyWatchedAccounts(limit))
+        }
+
+        watched.extend(keys);
+        let count = watched.len();
+        // an empty set would filter all the events
+        if count == 0 {
+            watched_keys.remove(session);
+        }
+        Ok(count)
+    }
+
+    // remove accounts from the ones watched by the session, or all of them
+    // a session watching no account receives all the events again
+    pub async fn unwatch_keys(&self, session: &WebSocketSessionShared<Self>, keys: Option<Vec<PublicKey>>) -> usize {
+        let mut watched_keys = self.watched_keys.lock().await;
+        let count = match (keys, watched_keys.get_mut(session)) {
+            (Some(keys), Some(watched)) => {
+                for key in keys {
+                    watched.remove(&key);
+                }
+                watched.len()
+            },
+            _ => 0
+        };
+
+        if count == 0 {
+            watched_keys.remove(session);
+        }
+        count
+    }
+
+    pub async fn get_watched_keys(&self, session: &WebSocketSessionShared<Self>) -> Vec<PublicKey> {
+        let watched_keys = self.watched_keys.lock().await;
+        watched_keys.get(session).map(|watched| watched.iter().cloned().collect()).unwrap_or_default()
+    }
+
     async fn subscribe_session_to_event(&self, session: &WebSocketSessionShared<Self>, event: E, id: Option<usize>) -> Result<(), RpcResponseError> {
         let mut sessions = self.sessions.lock().await;
         let events = sessions.entry(session.clone()).or_insert_with(HashMap::new);
@@ -83,8 +146,45 @@ where
         Ok(params.notify)
     }
 
+    // returns Null if nothing must be sent back (a batch of notifications only)
     async fn on_message_internal(&self, session: &WebSocketSessionShared<Self>, message: Bytes) -> Result<Value, RpcResponseError> {
-        let mut request: RpcRequest = self.handler.parse_request(&message)?;
+        let value: Value = serde_json::from_slice(&message).map_err(|_| RpcResponseError::new(None, InternalRpcError::ParseBodyError))?;
+        let Value::Array(requests) = value else {
+            let request = self.handler.parse_request_from_value(value)?;
+            return self.execute_request(session, request).await
+        };
+
+        // same rules as the batches sent over HTTP
+        if requests.is_empty() || requests.len() > MAX_BATCH_REQUESTS {
+            return Err(RpcResponseError::new(None, InternalRpcError::InvalidRequest));
+        }
+
+        let mut responses = Vec::with_capacity(requests.len());
+        for value in requests {
+            let response = match self.handler.parse_request_from_value(value) {
+                Ok(request) => {
+                    let notification = request.id.is_none();
+                    let response = match self.execute_request(session, request).await {
+                        Ok(result) => result,
+                        Err(e) => e.to_json()
+                    };
+                    if notification {
+                        continue;
+                    }
+                    response
+                },
+                Err(e) => e.to_json()
+            };
+            responses.push(response);
+        }
+
+        if responses.is_empty() {
+            return Ok(Value::Null)
+        }
+        Ok(Value::Array(responses))
+    }
+
+    async fn execute_request(&self, session: &WebSocketSessionShared<Self>, mut request: RpcRequest) -> Result<Value, RpcResponseError> {
         let response: Value = match request.method.as_str() {
             "subscribe" => {
                 let event = self.parse_event(&mut request)?;
@@ -123,6 +223,7 @@ where
         debug!("closing websocket connection");
         let mut sessions = self.sessions.lock().await;
         sessions.remove(session);
+        self.watched_keys.lock().await.remove(session);
         Ok(())
     }
 
@@ -132,7 +233,9 @@ where
             Ok(result) => result,
             Err(e) => e.to_json(),
         };
-        session.send_text(response.to_string()).await?;
+        if !response.is_null() {
+            session.send_text(response.to_string()).await?;
+        }
         Ok(())
     }
 }
\ No newline at end of file
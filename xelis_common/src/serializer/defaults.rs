@@ -179,7 +179,7 @@ impl<T: Serializer> Serializer for Vec<T> {
             return Err(ReaderError::InvalidSize)
         }
 
-        let mut values = Vec::with_capacity(count as usize);
+        let mut values = Vec::with_capacity(reader.bounded_capacity(count as usize));
         for _ in 0..count {
             values.push(T::read(reader)?);
         }
@@ -220,7 +220,7 @@ impl Serializer for bool {
 impl<K: Serializer + Eq + StdHash, V: Serializer + Eq + StdHash> Serializer for HashMap<K, V> {
     fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
         let size = reader.read_u16()?;
-        let mut map = HashMap::with_capacity(size as usize);
+        let mut map = HashMap::with_capacity(reader.bounded_capacity(size as usize));
         for _ in 0..size {
             let k = K::read(reader)?;
             let v = V::read(reader)?;
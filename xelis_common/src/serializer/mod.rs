@@ -226,6 +226,21 @@ impl<'a> Reader<'a> {
         Ok(BigUint::from_bytes_be(bytes))
     }
 
+    // counts are read before their items, so they can't be trusted to allocate memory up-front
+    // verify that `count` items of at least `min_item_size` bytes each are still available
+    pub fn check_count(&self, count: usize, min_item_size: usize) -> Result<(), ReaderError> {
+        if count.saturating_mul(min_item_size) > self.size() {
+            return Err(ReaderError::InvalidSize)
+        }
+        Ok(())
+    }
+
+    // capacity to reserve for `count` items whose size is unknown, bounded by the bytes available
+    // the collection will grow as its items are really read
+    pub fn bounded_capacity(&self, count: usize) -> usize {
+        count.min(self.size())
+    }
+
     pub fn total_size(&self) -> usize {
         self.bytes.len()
     }
@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::utils::get_current_timestamp;
+
+// Source of the current time in milliseconds
+// the code depending on the time uses it instead of the system clock so tests can control the time
+pub trait TimeSource: Send + Sync {
+    fn now(&self) -> u128;
+
+    // current time in seconds
+    fn now_secs(&self) -> u64 {
+        (self.now() / 1000) as u64
+    }
+}
+
+// System clock which never goes backward
+// if the system clock jumps back (NTP step, leap second, manual change),
+// the last time returned is kept until the system clock catches up
+#[derive(Default)]
+pub struct MonotonicClock {
+    last: AtomicU64
+}
+
+impl TimeSource for MonotonicClock {
+    fn now(&self) -> u128 {
+        let now = get_current_timestamp() as u64;
+        self.last.fetch_max(now, Ordering::SeqCst).max(now) as u128
+    }
+}
+
+// Time set manually, used by tests
+#[derive(Default)]
+pub struct MockClock {
+    now: AtomicU64
+}
+
+impl MockClock {
+    pub fn new(now: u64) -> Self {
+        Self {
+            now: AtomicU64::new(now)
+        }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, millis: u64) {
+        self.now.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+impl TimeSource for MockClock {
+    fn now(&self) -> u128 {
+        self.now.load(Ordering::SeqCst) as u128
+    }
+}
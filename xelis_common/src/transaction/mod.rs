@@ -1,11 +1,31 @@
-use crate::crypto::key::{PublicKey, Signature, SIGNATURE_LENGTH};
-use crate::crypto::hash::{Hashable, hash, Hash};
+mod receipt;
+mod psxt;
+
+pub use receipt::{TransactionReceipt, ReceiptStatus};
+pub use psxt::*;
+
+use crate::crypto::key::{PublicKey, Signature, KeyPair, KEY_LENGTH, SIGNATURE_LENGTH};
+use crate::crypto::hash::{Hashable, hash, Hash, HASH_SIZE};
 use crate::serializer::{Serializer, Writer, Reader, ReaderError};
-use std::collections::HashMap;
+use crate::contract::MAX_CONTRACT_CODE_SIZE;
+use crate::name::{get_name_registration_cost, NAME_MAX_LENGTH};
+use crate::config::XELIS_ASSET;
+use std::{collections::BTreeMap, sync::OnceLock};
+use schemars::JsonSchema;
 
 pub const EXTRA_DATA_LIMIT_SIZE: usize = 1024;
+// version of a transaction with its fee paid by a sponsor
+pub const SPONSORED_TX_VERSION: u8 = 1;
+// version of a transaction which can't be executed above a topoheight, its fee can be paid by a sponsor or not
+pub const EXPIRING_TX_VERSION: u8 = 2;
+// smallest serialized transaction: version, owner, data type, fee, nonce and signature
+pub const MIN_TX_SIZE: usize = 1 + KEY_LENGTH + 1 + 8 + 8 + SIGNATURE_LENGTH;
+// largest signed transaction accepted, so it always fits in a block with its header
+pub const MAX_TX_SIZE: usize = 256 * 1024;
+// the count of transfers is serialized on one byte
+pub const MAX_TRANSFERS_PER_TX: usize = u8::MAX as usize;
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
 pub struct Transfer {
     pub amount: u64,
     pub asset: Hash,
@@ -13,18 +33,31 @@ pub struct Transfer {
     pub extra_data: Option<Vec<u8>> // we can put whatever we want up to EXTRA_DATA_LIMIT_SIZE bytes
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+impl Transfer {
+    // serialized size of this transfer in the transaction
+    pub fn size(&self) -> usize {
+        // asset, amount, destination and the extra data flag
+        let mut size = HASH_SIZE + 8 + KEY_LENGTH + 1;
+        if let Some(extra_data) = &self.extra_data {
+            size += 2 + extra_data.len();
+        }
+        size
+    }
+}
+
+// sorted maps are used so the serialized TX is always the same
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
 pub struct SmartContractCall {
     pub contract: Hash,
-    pub assets: HashMap<Hash, u64>,
-    pub params: HashMap<String, String> // TODO
+    pub assets: BTreeMap<Hash, u64>,
+    pub params: BTreeMap<String, String> // values are parsed by the contract
 }
 
 // this enum represent all types of transaction available on XELIS Network
 // you're able to send multi assets in one TX to different addresses
 // you can burn one asset at a time (so the TX Hash can be used as unique proof)
-// Smart Contract system is not yet available but types are already there
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+// a deployed contract is identified by the hash of its deploy TX
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
 pub enum TransactionType {
     #[serde(rename = "transfers")]
     Transfer(Vec<Transfer>),
@@ -34,16 +67,94 @@ pub enum TransactionType {
     CallContract(SmartContractCall),
     #[serde(rename = "deploy_contract")]
     DeployContract(String), // represent the code to deploy
+    // register a name pointing to the owner for a duration in topoheights, or renew it
+    #[serde(rename = "register_name")]
+    RegisterName { name: String, duration: u64 },
+}
+
+// Account paying the fee of a transaction on behalf of its owner
+// the owner signs the sponsor key, then the sponsor signs the transaction including the owner signature
+#[derive(serde::Serialize, serde::Deserialize, JsonSchema, Clone, Debug)]
+pub struct FeeSponsor {
+    key: PublicKey,
+    // not set until the sponsor accepted to pay the fee
+    signature: Option<Signature>
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+impl FeeSponsor {
+    pub fn get_key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    pub fn get_signature(&self) -> Option<&Signature> {
+        self.signature.as_ref()
+    }
+}
+
+// deserialized using TransactionFields, so a transaction received in JSON is checked as one read from bytes
+#[derive(serde::Serialize, JsonSchema, Clone, Debug)]
 pub struct Transaction {
     version: u8,
     owner: PublicKey, // creator of this transaction
     data: TransactionType,
     fee: u64, // fees in XELIS for this tx
     nonce: u64, // nonce must be equal to the one on account
-    signature: Signature // signature of this Transaction by the owner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sponsor: Option<FeeSponsor>, // only set for sponsored and expiring versions
+    // topoheight above which the TX can't be executed anymore, only set for expiring version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expiration: Option<u64>,
+    signature: Signature, // signature of this Transaction by the owner
+    // hash computed on first use, reset when the sponsor signs it
+    #[serde(skip)]
+    #[schemars(skip)]
+    hash: OnceLock<Hash>
+}
+
+// the sponsor and the expiration are only allowed by the versions writing them
+fn is_valid_version_fields(version: u8, has_sponsor: bool, has_expiration: bool) -> bool {
+    match version {
+        0 => !has_sponsor && !has_expiration,
+        SPONSORED_TX_VERSION => has_sponsor && !has_expiration,
+        EXPIRING_TX_VERSION => has_expiration,
+        _ => false
+    }
+}
+
+// fields of a transaction received in JSON, checked before building it
+#[derive(serde::Deserialize)]
+struct TransactionFields {
+    version: u8,
+    owner: PublicKey,
+    data: TransactionType,
+    fee: u64,
+    nonce: u64,
+    #[serde(default)]
+    sponsor: Option<FeeSponsor>,
+    #[serde(default)]
+    expiration: Option<u64>,
+    signature: Signature
+}
+
+impl<'de> serde::Deserialize<'de> for Transaction {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = TransactionFields::deserialize(deserializer)?;
+        if !is_valid_version_fields(fields.version, fields.sponsor.is_some(), fields.expiration.is_some()) {
+            return Err(serde::de::Error::custom(format!("invalid sponsor or expiration for transaction version {}", fields.version)))
+        }
+
+        Ok(Transaction {
+            version: fields.version,
+            owner: fields.owner,
+            data: fields.data,
+            fee: fields.fee,
+            nonce: fields.nonce,
+            sponsor: fields.sponsor,
+            expiration: fields.expiration,
+            signature: fields.signature,
+            hash: OnceLock::new()
+        })
+    }
 }
 
 impl Serializer for TransactionType {
@@ -87,7 +198,14 @@ impl Serializer for TransactionType {
             }
             TransactionType::DeployContract(code) => {
                 writer.write_u8(3);
-                writer.write_string(code);
+                // code above MAX_CONTRACT_CODE_SIZE must be rejected before being serialized
+                writer.write_u16(code.len() as u16);
+                writer.write_bytes(code.as_bytes());
+            }
+            TransactionType::RegisterName { name, duration } => {
+                writer.write_u8(4);
+                writer.write_string(name);
+                writer.write_u64(duration);
             }
         };
     }
@@ -101,6 +219,8 @@ impl Serializer for TransactionType {
             },
             1 => { // Normal
                 let txs_count = reader.read_u8()?;
+                // asset, amount, destination and the extra data flag
+                reader.check_count(txs_count as usize, HASH_SIZE + 8 + KEY_LENGTH + 1)?;
                 let mut txs = Vec::with_capacity(txs_count as usize);
                 for _ in 0..txs_count {
                     let asset = reader.read_hash()?;
@@ -132,7 +252,7 @@ impl Serializer for TransactionType {
             2 => {
                 let contract = reader.read_hash()?;
                 let assets_count = reader.read_u8()?;
-                let mut assets = HashMap::with_capacity(assets_count as usize);
+                let mut assets = BTreeMap::new();
                 for _ in 0..assets_count {
                     let asset = reader.read_hash()?;
                     let amount = reader.read_u64()?;
@@ -140,7 +260,7 @@ impl Serializer for TransactionType {
                 }
 
                 let params_count = reader.read_u8()?;
-                let mut params = HashMap::with_capacity(params_count as usize);
+                let mut params = BTreeMap::new();
                 for _ in 0..params_count {
                     let key = reader.read_string()?;
                     let value = reader.read_string()?;
@@ -150,7 +270,20 @@ impl Serializer for TransactionType {
                 TransactionType::CallContract(SmartContractCall { contract, assets, params })
             },
             3 => {
-                TransactionType::DeployContract(reader.read_string()?)
+                let code_size = reader.read_u16()? as usize;
+                if code_size > MAX_CONTRACT_CODE_SIZE {
+                    return Err(ReaderError::InvalidSize)
+                }
+                TransactionType::DeployContract(reader.read_string_with_size(code_size)?)
+            },
+            4 => {
+                let name_size = reader.read_u8()? as usize;
+                if name_size > NAME_MAX_LENGTH {
+                    return Err(ReaderError::InvalidSize)
+                }
+                let name = reader.read_string_with_size(name_size)?;
+                let duration = reader.read_u64()?;
+                TransactionType::RegisterName { name, duration }
             }
             _ => {
                 return Err(ReaderError::InvalidValue)
@@ -167,10 +300,44 @@ impl Transaction {
             data,
             fee,
             nonce,
-            signature
+            sponsor: None,
+            expiration: None,
+            signature,
+            hash: OnceLock::new()
+        }
+    }
+
+    // the fee will be paid by the sponsor once it signed the transaction
+    pub fn new_sponsored(owner: PublicKey, data: TransactionType, fee: u64, nonce: u64, sponsor: PublicKey, signature: Signature) -> Self {
+        Transaction {
+            version: SPONSORED_TX_VERSION,
+            owner,
+            data,
+            fee,
+            nonce,
+            sponsor: Some(FeeSponsor {
+                key: sponsor,
+                signature: None
+            }),
+            expiration: None,
+            signature,
+            hash: OnceLock::new()
         }
     }
 
+    // the expiration is signed by the owner, it must be set before signing the transaction
+    pub fn set_expiration(&mut self, topoheight: u64) {
+        self.version = EXPIRING_TX_VERSION;
+        self.expiration = Some(topoheight);
+        self.hash.take();
+    }
+
+    // set the owner signature once built, e.g. aggregated from threshold signature shares
+    pub fn set_signature(&mut self, signature: Signature) {
+        self.signature = signature;
+        self.hash.take();
+    }
+
     pub fn get_version(&self) -> u8 {
         self.version
     }
@@ -191,11 +358,150 @@ impl Transaction {
         self.nonce
     }
 
-    // verify the validity of the signature
+    pub fn get_signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn get_sponsor(&self) -> Option<&FeeSponsor> {
+        self.sponsor.as_ref()
+    }
+
+    pub fn get_expiration(&self) -> Option<u64> {
+        self.expiration
+    }
+
+    // an expired TX can't be executed at this topoheight
+    pub fn is_expired_at(&self, topoheight: u64) -> bool {
+        self.expiration.is_some_and(|expiration| topoheight > expiration)
+    }
+
+    // account from which the fee is deducted
+    pub fn get_fee_payer(&self) -> &PublicKey {
+        match &self.sponsor {
+            Some(sponsor) => &sponsor.key,
+            None => &self.owner
+        }
+    }
+
+    // accounts involved in the transaction: its owner, its sponsor and the recipients of its transfers
+    pub fn get_involved_keys(&self) -> Vec<PublicKey> {
+        let mut keys = vec![self.owner.clone()];
+        if let Some(sponsor) = &self.sponsor {
+            keys.push(sponsor.key.clone());
+        }
+
+        if let TransactionType::Transfer(transfers) = &self.data {
+            for transfer in transfers {
+                if !keys.contains(&transfer.to) {
+                    keys.push(transfer.to.clone());
+                }
+            }
+        }
+        keys
+    }
+
+    // all the data signed by the owner
+    fn get_signing_data(&self) -> Writer {
+        let mut writer = Writer::new();
+        writer.write_u8(self.version);
+        self.owner.write(&mut writer);
+        self.data.write(&mut writer);
+        writer.write_u64(&self.fee);
+        writer.write_u64(&self.nonce);
+        self.write_version_fields(&mut writer);
+        writer
+    }
+
+    // fields following the nonce, depending on the version
+    fn write_version_fields(&self, writer: &mut Writer) {
+        match self.version {
+            SPONSORED_TX_VERSION => if let Some(sponsor) = &self.sponsor {
+                sponsor.key.write(writer);
+            },
+            EXPIRING_TX_VERSION => {
+                writer.write_bool(self.sponsor.is_some());
+                if let Some(sponsor) = &self.sponsor {
+                    sponsor.key.write(writer);
+                }
+                writer.write_u64(&self.expiration.unwrap_or(0));
+            },
+            _ => {}
+        }
+    }
+
+    // size of the transaction once signed by its owner and its sponsor if any
+    // signatures have a fixed size, so it can be computed before signing it
+    pub fn get_signed_size(&self) -> usize {
+        let mut size = self.size();
+        if self.sponsor.as_ref().is_some_and(|sponsor| sponsor.signature.is_none()) {
+            size += SIGNATURE_LENGTH;
+        }
+        size
+    }
+
+    // amount of each asset debited from the owner, without the fee
+    pub fn get_amounts_spent(&self) -> BTreeMap<&Hash, u64> {
+        let mut spent = BTreeMap::new();
+        match &self.data {
+            TransactionType::Transfer(transfers) => for transfer in transfers {
+                let amount = spent.entry(&transfer.asset).or_insert(0u64);
+                *amount = amount.saturating_add(transfer.amount);
+            },
+            TransactionType::Burn { asset, amount } => {
+                spent.insert(asset, *amount);
+            },
+            TransactionType::CallContract(call) => for (asset, amount) in &call.assets {
+                spent.insert(asset, *amount);
+            },
+            TransactionType::DeployContract(_) => {},
+            // the registration cost is burned
+            TransactionType::RegisterName { duration, .. } => {
+                spent.insert(&XELIS_ASSET, get_name_registration_cost(*duration));
+            }
+        };
+        spent
+    }
+
+    // hash signed by the owner
+    pub fn get_signing_hash(&self) -> Hash {
+        hash(self.get_signing_data().as_bytes())
+    }
+
+    // the sponsor signs the data signed by the owner and its signature
+    pub fn get_sponsor_signing_hash(&self) -> Hash {
+        let mut writer = self.get_signing_data();
+        self.signature.write(&mut writer);
+        hash(writer.as_bytes())
+    }
+
+    // verify the validity of the signature, and of the sponsor signature if any
     pub fn verify_signature(&self) -> bool {
-        let bytes = self.to_bytes();
-        let bytes = &bytes[0..bytes.len() - SIGNATURE_LENGTH]; // remove signature bytes for verification
-        self.get_owner().verify_signature(&hash(bytes), &self.signature)
+        if !self.get_owner().verify_signature(&self.get_signing_hash(), &self.signature) {
+            return false
+        }
+
+        match &self.sponsor {
+            Some(FeeSponsor { key, signature: Some(signature) }) => {
+                key.verify_signature(&self.get_sponsor_signing_hash(), signature)
+            },
+            Some(_) => false,
+            None => true
+        }
+    }
+
+    // sign the transaction to pay its fee, returns false if the keypair is not the sponsor requested
+    pub fn sign_as_sponsor(&mut self, keypair: &KeyPair) -> bool {
+        match &self.sponsor {
+            Some(sponsor) if sponsor.key == *keypair.get_public_key() => {},
+            _ => return false
+        };
+
+        let signature = keypair.sign(self.get_sponsor_signing_hash().as_bytes());
+        if let Some(sponsor) = self.sponsor.as_mut() {
+            sponsor.signature = Some(signature);
+        }
+        self.hash.take();
+        true
     }
 
     pub fn consume(self) -> (PublicKey, TransactionType) {
@@ -210,16 +516,37 @@ impl Serializer for Transaction {
         self.data.write(writer);
         writer.write_u64(&self.fee);
         writer.write_u64(&self.nonce);
+        self.write_version_fields(writer);
         self.signature.write(writer);
+        if let Some(sponsor) = &self.sponsor {
+            sponsor.signature.write(writer);
+        }
     }
 
     fn read(reader: &mut Reader) -> Result<Transaction, ReaderError> {
         let version = reader.read_u8()?;
+        // the fields following the nonce depend on the version
+        if !is_valid_version_fields(version, version == SPONSORED_TX_VERSION, version == EXPIRING_TX_VERSION) {
+            return Err(ReaderError::InvalidValue)
+        }
+
         let owner = PublicKey::read(reader)?;
         let data = TransactionType::read(reader)?;
         let fee = reader.read_u64()?;
         let nonce = reader.read_u64()?;
+        let (sponsor_key, expiration) = match version {
+            SPONSORED_TX_VERSION => (Some(PublicKey::read(reader)?), None),
+            EXPIRING_TX_VERSION => (Option::read(reader)?, Some(reader.read_u64()?)),
+            _ => (None, None)
+        };
         let signature = Signature::read(reader)?;
+        let sponsor = match sponsor_key {
+            Some(key) => Some(FeeSponsor {
+                key,
+                signature: Option::read(reader)?
+            }),
+            None => None
+        };
 
         Ok(Transaction {
             version,
@@ -227,9 +554,54 @@ impl Serializer for Transaction {
             data,
             fee,
             nonce,
-            signature
+            sponsor,
+            expiration,
+            signature,
+            hash: OnceLock::new()
         })
     }
 }
 
-impl Hashable for Transaction {}
\ No newline at end of file
+impl Hashable for Transaction {
+    fn hash(&self) -> Hash {
+        self.hash.get_or_init(|| hash(&self.to_bytes())).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::crypto::key::KeyPair;
+    use super::*;
+
+    fn _transaction(version: u8, sponsor: Option<&KeyPair>, expiration: Option<u64>) -> Value {
+        let owner = KeyPair::new();
+        let mut tx = Transaction::new(owner.get_public_key().clone(), TransactionType::Burn { asset: XELIS_ASSET, amount: 1 }, 50, 3, owner.sign(b"tx"));
+        tx.version = version;
+        tx.sponsor = sponsor.map(|sponsor| FeeSponsor { key: sponsor.get_public_key().clone(), signature: None });
+        tx.expiration = expiration;
+        serde_json::to_value(&tx).unwrap()
+    }
+
+    #[test]
+    fn test_deserialize_version_fields() {
+        let sponsor = KeyPair::new();
+        for (version, sponsor, expiration) in [(0, None, None), (SPONSORED_TX_VERSION, Some(&sponsor), None), (EXPIRING_TX_VERSION, None, Some(100)), (EXPIRING_TX_VERSION, Some(&sponsor), Some(100))] {
+            let tx: Transaction = serde_json::from_value(_transaction(version, sponsor, expiration)).unwrap();
+            // same bytes once read back
+            assert_eq!(Transaction::from_bytes(&tx.to_bytes()).unwrap().to_bytes(), tx.to_bytes());
+        }
+    }
+
+    #[test]
+    fn test_deserialize_invalid_version_fields() {
+        let sponsor = KeyPair::new();
+        for (version, sponsor, expiration) in [(0, Some(&sponsor), None), (0, None, Some(100)), (SPONSORED_TX_VERSION, None, None), (SPONSORED_TX_VERSION, Some(&sponsor), Some(100)), (EXPIRING_TX_VERSION, Some(&sponsor), None), (EXPIRING_TX_VERSION + 1, None, None)] {
+            assert!(serde_json::from_value::<Transaction>(_transaction(version, sponsor, expiration)).is_err(), "version {} accepted", version);
+        }
+
+        let mut tx = _transaction(0, None, None);
+        tx["sponsor"] = json!({ "key": sponsor.get_public_key(), "signature": null });
+        assert!(serde_json::from_value::<Transaction>(tx).is_err());
+    }
+}
\ No newline at end of file
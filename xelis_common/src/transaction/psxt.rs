@@ -0,0 +1,511 @@
+use std::collections::BTreeMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use thiserror::Error;
+
+use crate::{
+    config::XELIS_ASSET,
+    crypto::{hash::Hash, key::{KeyPair, PublicKey, Signature, SIGNATURE_LENGTH}},
+    network::Network,
+    serializer::{Serializer, Writer, Reader, ReaderError}
+};
+use super::{Transaction, FeeSponsor};
+
+// magic bytes at the beginning of a serialized PSXT
+pub const PSXT_MAGIC: [u8; 4] = *b"psxt";
+pub const PSXT_VERSION: u8 = 0;
+
+#[derive(Error, Debug)]
+pub enum PsxtError {
+    #[error("Key {} is not a signer of this transaction", _0)]
+    UnknownSigner(String),
+    #[error("The owner must sign the transaction before its sponsor")]
+    MissingOwnerSignature,
+    #[error("Invalid signature for the {:?} of the transaction", _0)]
+    InvalidSignature(SignerRole),
+    #[error("Transactions to merge are different")]
+    TransactionMismatch,
+    #[error("Signature of the {:?} is missing", _0)]
+    Incomplete(SignerRole),
+    #[error("Input of {} for asset {} is not debited by the transaction", _0, _1)]
+    UnknownInput(String, Hash),
+    #[error("Invalid PSXT: {}", _0)]
+    InvalidFormat(#[from] ReaderError),
+    #[error("Invalid base64 encoding")]
+    InvalidBase64
+}
+
+// who must sign the transaction, in this order
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerRole {
+    // signs the data of the transaction
+    Owner,
+    // pays the fee, signs the transaction including the owner signature
+    Sponsor
+}
+
+impl Serializer for SignerRole {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(match self {
+            Self::Owner => 0,
+            Self::Sponsor => 1
+        });
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(match reader.read_u8()? {
+            0 => Self::Owner,
+            1 => Self::Sponsor,
+            _ => return Err(ReaderError::InvalidValue)
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct SignatureSlot {
+    pub role: SignerRole,
+    pub key: PublicKey,
+    // not set until the signer has signed
+    pub signature: Option<Signature>
+}
+
+impl Serializer for SignatureSlot {
+    fn write(&self, writer: &mut Writer) {
+        self.role.write(writer);
+        self.key.write(writer);
+        self.signature.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let role = SignerRole::read(reader)?;
+        let key = PublicKey::read(reader)?;
+        let signature = Option::read(reader)?;
+        Ok(Self { role, key, signature })
+    }
+}
+
+// amount of an asset debited from an account, shown to the signers
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct PsxtInput {
+    pub account: PublicKey,
+    pub asset: Hash,
+    // including the fee if paid by this account
+    pub amount: u64,
+    // balance of the account known by the creator, so an offline signer can check the funds
+    pub balance: Option<u64>
+}
+
+impl Serializer for PsxtInput {
+    fn write(&self, writer: &mut Writer) {
+        self.account.write(writer);
+        writer.write_hash(&self.asset);
+        writer.write_u64(&self.amount);
+        self.balance.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let account = PublicKey::read(reader)?;
+        let asset = reader.read_hash()?;
+        let amount = reader.read_u64()?;
+        let balance = Option::read(reader)?;
+        Ok(Self { account, asset, amount, balance })
+    }
+}
+
+// Partially Signed Xelis Transaction
+// container of an unsigned transaction passed between its signers (air-gapped wallets, co-signers, sponsors)
+// each signer adds its signature, the copies can be merged, and the transaction is finalized once all the slots are signed
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct PartiallySignedTransaction {
+    version: u8,
+    network: Network,
+    // signatures of the transaction are not used, they are set from the slots when finalized
+    transaction: Transaction,
+    inputs: Vec<PsxtInput>,
+    signatures: Vec<SignatureSlot>
+}
+
+impl PartiallySignedTransaction {
+    // the signatures already set in the transaction are kept in their slots
+    pub fn new(network: Network, mut transaction: Transaction) -> Self {
+        let placeholder = get_placeholder_signature();
+        let owner_signature = std::mem::replace(&mut transaction.signature, placeholder);
+        let mut signatures = vec![SignatureSlot {
+            role: SignerRole::Owner,
+            key: transaction.owner.clone(),
+            signature: None
+        }];
+        if let Some(sponsor) = transaction.sponsor.as_mut() {
+            signatures.push(SignatureSlot {
+                role: SignerRole::Sponsor,
+                key: sponsor.key.clone(),
+                signature: sponsor.signature.take()
+            });
+        }
+        transaction.hash.take();
+
+        let inputs = get_inputs(&transaction);
+        let mut psxt = Self {
+            version: PSXT_VERSION,
+            network,
+            transaction,
+            inputs,
+            signatures
+        };
+
+        // a placeholder or invalid signature is ignored
+        if psxt.transaction.owner.verify_signature(&psxt.transaction.get_signing_hash(), &owner_signature) {
+            psxt.signatures[0].signature = Some(owner_signature);
+        }
+        if psxt.get_slot(SignerRole::Sponsor).is_some_and(|slot| !psxt.is_valid_signature(slot)) {
+            psxt.signatures[1].signature = None;
+        }
+        psxt
+    }
+
+    pub fn get_network(&self) -> &Network {
+        &self.network
+    }
+
+    pub fn get_transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    pub fn get_inputs(&self) -> &Vec<PsxtInput> {
+        &self.inputs
+    }
+
+    pub fn get_signatures(&self) -> &Vec<SignatureSlot> {
+        &self.signatures
+    }
+
+    // set the balance known for an input
+    pub fn set_input_balance(&mut self, account: &PublicKey, asset: &Hash, balance: u64) -> Result<(), PsxtError> {
+        let input = self.inputs.iter_mut()
+            .find(|input| input.account == *account && input.asset == *asset)
+            .ok_or_else(|| PsxtError::UnknownInput(account.to_string(), asset.clone()))?;
+        input.balance = Some(balance);
+        Ok(())
+    }
+
+    fn get_slot(&self, role: SignerRole) -> Option<&SignatureSlot> {
+        self.signatures.iter().find(|slot| slot.role == role)
+    }
+
+    // hash to sign for this role
+    // the sponsor signs the owner signature, so it's only available once the owner has signed
+    pub fn get_signing_hash(&self, role: SignerRole) -> Result<Hash, PsxtError> {
+        match role {
+            SignerRole::Owner => Ok(self.transaction.get_signing_hash()),
+            SignerRole::Sponsor => {
+                let owner_signature = self.get_slot(SignerRole::Owner)
+                    .and_then(|slot| slot.signature.clone())
+                    .ok_or(PsxtError::MissingOwnerSignature)?;
+                let mut transaction = self.transaction.clone();
+                transaction.set_signature(owner_signature);
+                Ok(transaction.get_sponsor_signing_hash())
+            }
+        }
+    }
+
+    fn is_valid_signature(&self, slot: &SignatureSlot) -> bool {
+        match (&slot.signature, self.get_signing_hash(slot.role)) {
+            (Some(signature), Ok(hash)) => slot.key.verify_signature(&hash, signature),
+            _ => false
+        }
+    }
+
+    // add a signature computed by an external signer (hardware wallet, threshold signature...)
+    pub fn add_signature(&mut self, role: SignerRole, signature: Signature) -> Result<(), PsxtError> {
+        let hash = self.get_signing_hash(role)?;
+        let slot = self.signatures.iter_mut()
+            .find(|slot| slot.role == role)
+            .ok_or(PsxtError::Incomplete(role))?;
+        if !slot.key.verify_signature(&hash, &signature) {
+            return Err(PsxtError::InvalidSignature(role))
+        }
+
+        slot.signature = Some(signature);
+        Ok(())
+    }
+
+    // sign all the slots of this keypair, returns the roles signed
+    pub fn sign(&mut self, keypair: &KeyPair) -> Result<Vec<SignerRole>, PsxtError> {
+        let roles: Vec<SignerRole> = self.signatures.iter()
+            .filter(|slot| slot.key == *keypair.get_public_key())
+            .map(|slot| slot.role)
+            .collect();
+        if roles.is_empty() {
+            return Err(PsxtError::UnknownSigner(keypair.get_public_key().to_string()))
+        }
+
+        // slots are ordered, so the owner signs before the sponsor
+        for role in &roles {
+            let hash = self.get_signing_hash(*role)?;
+            self.add_signature(*role, keypair.sign(hash.as_bytes()))?;
+        }
+        Ok(roles)
+    }
+
+    // add the signatures of another copy of the same transaction
+    pub fn merge(&mut self, other: &Self) -> Result<(), PsxtError> {
+        if self.network != other.network || self.transaction.to_bytes() != other.transaction.to_bytes() || self.signatures.len() != other.signatures.len() {
+            return Err(PsxtError::TransactionMismatch)
+        }
+
+        for slot in &other.signatures {
+            if let Some(signature) = &slot.signature {
+                // the owner signature of the other copy is verified first, as the sponsor one depends on it
+                if self.get_slot(slot.role).is_some_and(|current| current.signature.is_none()) {
+                    self.add_signature(slot.role, signature.clone())?;
+                }
+            }
+        }
+
+        // balances known by the other copy
+        for input in &other.inputs {
+            if let Some(balance) = input.balance {
+                if self.inputs.iter().any(|current| current.account == input.account && current.asset == input.asset && current.balance.is_none()) {
+                    self.set_input_balance(&input.account, &input.asset, balance)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signatures.iter().all(|slot| slot.signature.is_some())
+    }
+
+    // build the signed transaction, ready to be submitted
+    pub fn finalize(self) -> Result<Transaction, PsxtError> {
+        // signatures read from a file are not verified yet
+        for slot in &self.signatures {
+            if slot.signature.is_none() {
+                return Err(PsxtError::Incomplete(slot.role))
+            }
+
+            if !self.is_valid_signature(slot) {
+                return Err(PsxtError::InvalidSignature(slot.role))
+            }
+        }
+
+        let mut transaction = self.transaction;
+        for slot in self.signatures {
+            let signature = slot.signature.ok_or(PsxtError::Incomplete(slot.role))?;
+            match slot.role {
+                SignerRole::Owner => transaction.set_signature(signature),
+                SignerRole::Sponsor => if let Some(FeeSponsor { signature: sponsor_signature, .. }) = transaction.sponsor.as_mut() {
+                    *sponsor_signature = Some(signature);
+                }
+            }
+        }
+        transaction.hash.take();
+        Ok(transaction)
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.to_bytes())
+    }
+
+    pub fn from_base64(value: &str) -> Result<Self, PsxtError> {
+        let bytes = STANDARD.decode(value.trim()).map_err(|_| PsxtError::InvalidBase64)?;
+        Ok(Self::from_bytes(&bytes)?)
+    }
+}
+
+// signatures have a fixed size, a placeholder is set until the transaction is finalized
+fn get_placeholder_signature() -> Signature {
+    Signature::from_bytes(&[0u8; SIGNATURE_LENGTH]).expect("valid placeholder signature")
+}
+
+// accounts and assets debited by the transaction
+fn get_inputs(transaction: &Transaction) -> Vec<PsxtInput> {
+    let mut spent: BTreeMap<(&PublicKey, &Hash), u64> = transaction.get_amounts_spent().into_iter()
+        .map(|(asset, amount)| ((transaction.get_owner(), asset), amount))
+        .collect();
+    let fee = spent.entry((transaction.get_fee_payer(), &XELIS_ASSET)).or_insert(0);
+    *fee = fee.saturating_add(transaction.get_fee());
+
+    spent.into_iter().map(|((account, asset), amount)| PsxtInput {
+        account: account.clone(),
+        asset: asset.clone(),
+        amount,
+        balance: None
+    }).collect()
+}
+
+impl Serializer for PartiallySignedTransaction {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_bytes(&PSXT_MAGIC);
+        writer.write_u8(self.version);
+        self.network.write(writer);
+        self.transaction.write(writer);
+        // up to 255 assets transferred and the fee paid by a sponsor
+        writer.write_u16(self.inputs.len() as u16);
+        for input in &self.inputs {
+            input.write(writer);
+        }
+        writer.write_u8(self.signatures.len() as u8);
+        for slot in &self.signatures {
+            slot.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let magic: [u8; 4] = reader.read_bytes(PSXT_MAGIC.len())?;
+        if magic != PSXT_MAGIC {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        let version = reader.read_u8()?;
+        if version != PSXT_VERSION {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        let network = Network::read(reader)?;
+        let transaction = Transaction::read(reader)?;
+        let inputs_count = reader.read_u16()? as usize;
+        let mut inputs = Vec::with_capacity(inputs_count);
+        for _ in 0..inputs_count {
+            inputs.push(PsxtInput::read(reader)?);
+        }
+
+        // the slots must match the signers of the transaction
+        let signatures_count = reader.read_u8()? as usize;
+        let expected = if transaction.sponsor.is_some() { 2 } else { 1 };
+        if signatures_count != expected {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        let mut signatures = Vec::with_capacity(signatures_count);
+        for _ in 0..signatures_count {
+            signatures.push(SignatureSlot::read(reader)?);
+        }
+
+        let owner = &signatures[0];
+        if owner.role != SignerRole::Owner || owner.key != transaction.owner {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        if let Some(sponsor) = transaction.sponsor.as_ref() {
+            let slot = &signatures[1];
+            if slot.role != SignerRole::Sponsor || slot.key != sponsor.key {
+                return Err(ReaderError::InvalidValue)
+            }
+        }
+
+        Ok(Self {
+            version,
+            network,
+            transaction,
+            inputs,
+            signatures
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{crypto::hash::Hashable, transaction::{TransactionType, Transfer}};
+    use super::*;
+
+    fn transfer(owner: &KeyPair, sponsor: Option<&KeyPair>) -> Transaction {
+        let data = TransactionType::Transfer(vec![Transfer {
+            amount: 1000,
+            asset: XELIS_ASSET,
+            to: KeyPair::new().get_public_key().clone(),
+            extra_data: None
+        }]);
+        match sponsor {
+            Some(sponsor) => Transaction::new_sponsored(owner.get_public_key().clone(), data, 50, 3, sponsor.get_public_key().clone(), get_placeholder_signature()),
+            None => Transaction::new(owner.get_public_key().clone(), data, 50, 3, get_placeholder_signature())
+        }
+    }
+
+    #[test]
+    fn test_sign_and_finalize() {
+        let owner = KeyPair::new();
+        let mut psxt = PartiallySignedTransaction::new(Network::Testnet, transfer(&owner, None));
+        assert_eq!(psxt.get_inputs().len(), 1);
+        assert_eq!(psxt.get_inputs()[0].amount, 1050);
+        assert!(!psxt.is_complete());
+        assert!(matches!(psxt.sign(&KeyPair::new()), Err(PsxtError::UnknownSigner(_))));
+
+        assert_eq!(psxt.sign(&owner).unwrap(), vec![SignerRole::Owner]);
+        assert!(psxt.is_complete());
+        let tx = psxt.finalize().unwrap();
+        assert!(tx.verify_signature());
+    }
+
+    #[test]
+    fn test_sponsor_signs_after_owner() {
+        let owner = KeyPair::new();
+        let sponsor = KeyPair::new();
+        let mut psxt = PartiallySignedTransaction::new(Network::Testnet, transfer(&owner, Some(&sponsor)));
+        // the fee is paid by the sponsor
+        assert_eq!(psxt.get_inputs().len(), 2);
+        assert!(matches!(psxt.sign(&sponsor), Err(PsxtError::MissingOwnerSignature)));
+
+        psxt.sign(&owner).unwrap();
+        psxt.sign(&sponsor).unwrap();
+        let tx = psxt.finalize().unwrap();
+        assert!(tx.verify_signature());
+        assert!(tx.get_sponsor().unwrap().get_signature().is_some());
+    }
+
+    #[test]
+    fn test_merge_copies() {
+        let owner = KeyPair::new();
+        let sponsor = KeyPair::new();
+        let unsigned = PartiallySignedTransaction::new(Network::Testnet, transfer(&owner, Some(&sponsor)));
+
+        // the copy signed by the owner is sent to the sponsor through a base64 string
+        let mut owner_copy = PartiallySignedTransaction::from_base64(&unsigned.to_base64()).unwrap();
+        owner_copy.sign(&owner).unwrap();
+        let mut sponsor_copy = PartiallySignedTransaction::from_base64(&owner_copy.to_base64()).unwrap();
+        sponsor_copy.sign(&sponsor).unwrap();
+
+        let mut merged = unsigned.clone();
+        merged.set_input_balance(owner.get_public_key(), &XELIS_ASSET, 5000).unwrap();
+        merged.merge(&sponsor_copy).unwrap();
+        assert!(merged.is_complete());
+        assert_eq!(merged.get_inputs().iter().find(|input| input.account == *owner.get_public_key()).unwrap().balance, Some(5000));
+
+        let other = PartiallySignedTransaction::new(Network::Testnet, transfer(&owner, None));
+        assert!(matches!(merged.merge(&other), Err(PsxtError::TransactionMismatch)));
+
+        let tx = merged.finalize().unwrap();
+        assert!(tx.verify_signature());
+    }
+
+    #[test]
+    fn test_existing_signatures_are_kept() {
+        let owner = KeyPair::new();
+        let mut tx = transfer(&owner, None);
+        tx.set_signature(owner.sign(tx.get_signing_hash().as_bytes()));
+        let hash = tx.hash();
+
+        let psxt = PartiallySignedTransaction::new(Network::Mainnet, tx);
+        assert!(psxt.is_complete());
+        assert_eq!(psxt.finalize().unwrap().hash(), hash);
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        let owner = KeyPair::new();
+        let psxt = PartiallySignedTransaction::new(Network::Testnet, transfer(&owner, None));
+        let mut bytes = psxt.to_bytes();
+        assert!(PartiallySignedTransaction::from_bytes(&bytes).is_ok());
+
+        bytes[0] = b'x';
+        assert!(PartiallySignedTransaction::from_bytes(&bytes).is_err());
+        assert!(matches!(PartiallySignedTransaction::from_base64("not base64!"), Err(PsxtError::InvalidBase64)));
+
+        let mut psxt = psxt;
+        assert!(matches!(psxt.add_signature(SignerRole::Owner, get_placeholder_signature()), Err(PsxtError::InvalidSignature(SignerRole::Owner))));
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::{
+    contract::ContractEvent,
+    crypto::hash::Hash,
+    serializer::{Serializer, Reader, ReaderError, Writer}
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    Success,
+    // only a contract call can fail, its fees are still paid
+    Failed(String)
+}
+
+// Outcome of a transaction once executed in a block
+// A transaction is executed only once, its receipt is deleted if its execution is reverted by a DAG reorg
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TransactionReceipt {
+    // topoheight at which the transaction was executed
+    pub topoheight: u64,
+    pub status: ReceiptStatus,
+    pub fee: u64,
+    // gas consumed by the contract call, 0 for others types
+    pub gas_used: u64,
+    // total amount deducted from the owner for each asset, fee included if not paid by a sponsor
+    pub spent: BTreeMap<Hash, u64>,
+    // how many accounts balances were credited
+    pub balances_credited: u16,
+    // how many contract storage keys were written
+    pub storage_changes: u16,
+    pub events: Vec<ContractEvent>
+}
+
+impl TransactionReceipt {
+    pub fn new(topoheight: u64, fee: u64) -> Self {
+        Self {
+            topoheight,
+            status: ReceiptStatus::Success,
+            fee,
+            gas_used: 0,
+            spent: BTreeMap::new(),
+            balances_credited: 0,
+            storage_changes: 0,
+            events: Vec::new()
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status == ReceiptStatus::Success
+    }
+}
+
+impl Serializer for TransactionReceipt {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u64(&self.topoheight);
+        match &self.status {
+            ReceiptStatus::Success => writer.write_u8(0),
+            ReceiptStatus::Failed(reason) => {
+                writer.write_u8(1);
+                writer.write_string(reason);
+            }
+        };
+        writer.write_u64(&self.fee);
+        writer.write_u64(&self.gas_used);
+        writer.write_u16(self.spent.len() as u16);
+        for (asset, amount) in &self.spent {
+            writer.write_hash(asset);
+            writer.write_u64(amount);
+        }
+        writer.write_u16(self.balances_credited);
+        writer.write_u16(self.storage_changes);
+        self.events.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let topoheight = reader.read_u64()?;
+        let status = match reader.read_u8()? {
+            0 => ReceiptStatus::Success,
+            1 => ReceiptStatus::Failed(reader.read_string()?),
+            _ => return Err(ReaderError::InvalidValue)
+        };
+        let fee = reader.read_u64()?;
+        let gas_used = reader.read_u64()?;
+        let count = reader.read_u16()?;
+        let mut spent = BTreeMap::new();
+        for _ in 0..count {
+            let asset = reader.read_hash()?;
+            let amount = reader.read_u64()?;
+            spent.insert(asset, amount);
+        }
+        let balances_credited = reader.read_u16()?;
+        let storage_changes = reader.read_u16()?;
+        let events = Vec::read(reader)?;
+
+        Ok(Self {
+            topoheight,
+            status,
+            fee,
+            gas_used,
+            spent,
+            balances_credited,
+            storage_changes,
+            events
+        })
+    }
+}
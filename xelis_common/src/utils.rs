@@ -3,6 +3,7 @@ use crate::network::Network;
 use crate::serializer::{Reader, ReaderError};
 use crate::config::{FEE_PER_KB, COIN_DECIMALS};
 use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::net::{SocketAddr, IpAddr, Ipv4Addr, Ipv6Addr};
 
@@ -17,18 +18,23 @@ macro_rules! async_handler {
 
 // return timestamp in seconds
 pub fn get_current_time() -> u64 {
-    let start = SystemTime::now();
-    let time = start.duration_since(UNIX_EPOCH).expect("Incorrect time returned from get_current_time");
-    time.as_secs()
+    (get_current_timestamp() / 1000) as u64
 }
 
 // return timestamp in milliseconds
+#[cfg(not(target_arch = "wasm32"))]
 pub fn get_current_timestamp() -> u128 {
     let start = SystemTime::now();
     let time = start.duration_since(UNIX_EPOCH).expect("Incorrect time returned from get_current_timestamp");
     time.as_millis()
 }
 
+// SystemTime is not available in the browser
+#[cfg(target_arch = "wasm32")]
+pub fn get_current_timestamp() -> u128 {
+    js_sys::Date::now() as u128
+}
+
 pub fn format_coin(value: u64, decimals: u8) -> String {
     format!("{:.1$}", value as f64 / 10usize.pow(decimals as u32) as f64, decimals as usize)
 }
@@ -94,6 +100,14 @@ pub fn calculate_tx_fee(tx_size: usize) -> u64 {
 const HASHRATE_FORMATS: [&str; 5] = ["H/s", "KH/s", "MH/s", "GH/s", "TH/s"];
 
 // Format a hashrate in human-readable format
+// a client reference is an UUID in its hyphenated format, such as 67e55044-10b1-426f-9247-bb680e5fe0c8
+pub fn is_valid_reference(reference: &str) -> bool {
+    reference.len() == 36 && reference.char_indices().all(|(i, c)| match i {
+        8 | 13 | 18 | 23 => c == '-',
+        _ => c.is_ascii_hexdigit()
+    })
+}
+
 pub fn format_hashrate(mut hashrate: f64) -> String {
     let max = HASHRATE_FORMATS.len() - 1;
     let mut count = 0;
@@ -0,0 +1,161 @@
+// Bindings for browser and mobile wallets built with wasm-pack
+// values are exchanged as hex, addresses or JSON strings so the serialization stays the one used by the consensus
+
+use std::fmt::Display;
+use wasm_bindgen::prelude::*;
+use crate::{
+    crypto::{
+        address::Address,
+        hash::Hashable,
+        key::{KeyPair, PrivateKey, Signature}
+    },
+    network::Network,
+    serializer::Serializer,
+    transaction::{Transaction, TransactionType},
+    utils::{calculate_tx_fee, set_network_to}
+};
+
+fn to_js_error<E: Display>(e: E) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+fn parse_address(address: &str) -> Result<Address, JsError> {
+    Address::from_string(&address.to_owned()).map_err(to_js_error)
+}
+
+fn parse_transaction(tx_as_hex: String) -> Result<Transaction, JsError> {
+    Transaction::from_hex(tx_as_hex).map_err(to_js_error)
+}
+
+// network used to display the keys in JSON, mainnet by default
+// must be called once before anything else
+#[wasm_bindgen]
+pub fn set_network(network: &str) -> Result<(), JsError> {
+    let network: Network = network.parse().map_err(to_js_error)?;
+    set_network_to(network);
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn validate_address(address: &str) -> bool {
+    parse_address(address).is_ok()
+}
+
+// returns the public key in hex
+#[wasm_bindgen]
+pub fn address_to_public_key(address: &str) -> Result<String, JsError> {
+    Ok(parse_address(address)?.to_public_key().to_hex())
+}
+
+#[wasm_bindgen]
+pub fn calculate_fees(tx_size: usize) -> u64 {
+    calculate_tx_fee(tx_size)
+}
+
+#[wasm_bindgen]
+pub fn transaction_to_json(tx_as_hex: String) -> Result<String, JsError> {
+    serde_json::to_string(&parse_transaction(tx_as_hex)?).map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub fn transaction_hash(tx_as_hex: String) -> Result<String, JsError> {
+    Ok(parse_transaction(tx_as_hex)?.hash().to_hex())
+}
+
+// verify the owner signature, and the sponsor one if any
+#[wasm_bindgen]
+pub fn verify_transaction_signature(tx_as_hex: String) -> Result<bool, JsError> {
+    Ok(parse_transaction(tx_as_hex)?.verify_signature())
+}
+
+#[wasm_bindgen]
+pub struct WalletKeyPair {
+    inner: KeyPair
+}
+
+#[wasm_bindgen]
+impl WalletKeyPair {
+    // generate a new random keypair
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            inner: KeyPair::new()
+        }
+    }
+
+    pub fn from_private_key(private_key: String) -> Result<WalletKeyPair, JsError> {
+        let private_key = PrivateKey::from_hex(private_key).map_err(to_js_error)?;
+        Ok(Self {
+            inner: KeyPair::from_private_key(private_key)
+        })
+    }
+
+    pub fn get_private_key(&self) -> String {
+        self.inner.get_private_key().to_hex()
+    }
+
+    pub fn get_public_key(&self) -> String {
+        self.inner.get_public_key().to_hex()
+    }
+
+    pub fn get_address(&self, mainnet: bool) -> String {
+        self.inner.get_public_key().to_address(mainnet).to_string()
+    }
+
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        self.inner.sign(data).to_bytes()
+    }
+
+    // minimum fees for a transaction with this data in JSON, the same format as the wallet `build_transaction`
+    // the sponsor signature is counted even if it is not set yet
+    pub fn estimate_fees(&self, data: &str, nonce: u64, sponsor: Option<String>) -> Result<u64, JsError> {
+        let tx = self.create_transaction(data, 0, nonce, sponsor)?;
+        Ok(calculate_tx_fee(tx.get_signed_size()))
+    }
+
+    // build and sign a transaction, returns it in hex
+    // a sponsored transaction must then be signed by its sponsor using `sponsor_transaction`
+    pub fn build_transaction(&self, data: &str, fee: u64, nonce: u64, sponsor: Option<String>) -> Result<String, JsError> {
+        Ok(self.create_transaction(data, fee, nonce, sponsor)?.to_hex())
+    }
+
+    // sign a transaction built by another wallet to pay its fees, returns it in hex
+    pub fn sponsor_transaction(&self, tx_as_hex: String) -> Result<String, JsError> {
+        let mut tx = parse_transaction(tx_as_hex)?;
+        if !tx.sign_as_sponsor(&self.inner) {
+            return Err(JsError::new("This keypair is not the sponsor of the transaction"))
+        }
+        Ok(tx.to_hex())
+    }
+}
+
+impl WalletKeyPair {
+    fn new_transaction(&self, data: TransactionType, fee: u64, nonce: u64, sponsor: Option<&Address>, signature: Signature) -> Transaction {
+        let owner = self.inner.get_public_key().clone();
+        match sponsor {
+            Some(sponsor) => Transaction::new_sponsored(owner, data, fee, nonce, sponsor.get_public_key().clone(), signature),
+            None => Transaction::new(owner, data, fee, nonce, signature)
+        }
+    }
+
+    fn create_transaction(&self, data: &str, fee: u64, nonce: u64, sponsor: Option<String>) -> Result<Transaction, JsError> {
+        let data: TransactionType = serde_json::from_str(data).map_err(to_js_error)?;
+        let sponsor = match sponsor {
+            Some(address) => Some(parse_address(&address)?),
+            None => None
+        };
+
+        // the signature is not part of the signing hash, so any value can be used to compute it
+        let unsigned = self.new_transaction(data, fee, nonce, sponsor.as_ref(), self.inner.sign(&[]));
+        let signature = self.inner.sign(unsigned.get_signing_hash().as_bytes());
+        let (_, data) = unsigned.consume();
+
+        Ok(self.new_transaction(data, fee, nonce, sponsor.as_ref(), signature))
+    }
+}
+
+impl Default for WalletKeyPair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,13 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // gRPC service is only generated when the feature is enabled
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/daemon.proto");
+        // use the bundled protoc so no system install is required
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("protoc binary not found");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::compile_protos("proto/daemon.proto").expect("Error while compiling proto files");
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(unix)]
+mod server;
+
+#[cfg(unix)]
+pub use server::AdminServer;
+
+use std::net::IpAddr;
+use serde::{Serialize, Deserialize};
+use xelis_common::prompt::LogLevel;
+
+// Runtime operations available on the local admin socket
+// They are never exposed on the public RPC server
+#[derive(Serialize, Deserialize, Debug, Clone, clap::Subcommand)]
+#[serde(rename_all = "snake_case", tag = "command", content = "params")]
+pub enum AdminCommand {
+    /// Blacklist a peer address
+    BanPeer {
+        address: IpAddr
+    },
+    /// Remove a peer address from the blacklist
+    UnbanPeer {
+        address: IpAddr
+    },
+    /// Change the log level of the daemon
+    SetLogLevel {
+        #[clap(arg_enum)]
+        level: LogLevel
+    },
+    /// Remove all transactions from mempool
+    FlushMempool,
+    /// Prune the chain until the specified topoheight
+    Prune {
+        topoheight: u64
+    },
+    /// Copy the database in a new directory while the daemon is running
+    Snapshot {
+        path: String
+    },
+    /// Write the XELIS balance and nonce of all accounts in a file, used by `generate-genesis --state`
+    ExportState {
+        path: String,
+        /// Topoheight of the state, default is the current one
+        #[clap(long)]
+        topoheight: Option<u64>
+    },
+    /// Show the last requests executed by the RPC server, the RPC journal must be enabled
+    GetRecentRequests {
+        /// Maximum number of requests returned, 20 by default
+        #[clap(long)]
+        limit: Option<usize>,
+        /// Only return the requests of this method
+        #[clap(long)]
+        method: Option<String>
+    },
+    /// Stop the daemon
+    Stop
+}
+
+// Response sent for each command, one JSON per line
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AdminResponse {
+    pub success: bool,
+    pub message: String
+}
+
+impl AdminResponse {
+    pub fn ok(message: String) -> Self {
+        Self { success: true, message }
+    }
+
+    pub fn error(message: String) -> Self {
+        Self { success: false, message }
+    }
+}
+
+// Send a command to the daemon listening on the admin socket
+#[cfg(unix)]
+pub async fn send_command(socket_path: &str, command: &AdminCommand) -> Result<AdminResponse, anyhow::Error> {
+    use anyhow::Context;
+    use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, net::UnixStream};
+
+    let stream = UnixStream::connect(socket_path).await.context(format!("Error while connecting to admin socket {}", socket_path))?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut request = serde_json::to_vec(command)?;
+    request.push(b'\n');
+    writer.write_all(&request).await?;
+
+    let mut lines = BufReader::new(reader).lines();
+    let line = lines.next_line().await?.context("Admin socket was closed before sending a response")?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+#[cfg(not(unix))]
+pub async fn send_command(_: &str, _: &AdminCommand) -> Result<AdminResponse, anyhow::Error> {
+    anyhow::bail!("Admin socket is only available on Unix systems")
+}
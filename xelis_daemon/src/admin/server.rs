@@ -0,0 +1,181 @@
+use std::{
+    fs,
+    os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+    path::Path,
+    sync::Arc
+};
+use anyhow::{Context, bail};
+use log::{info, debug, error, warn};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    task::JoinHandle
+};
+use xelis_common::prompt::{Prompt, ShareablePrompt};
+use crate::{core::{blockchain::Blockchain, storage::Storage}, config::RPC_JOURNAL_DEFAULT_LIMIT};
+use super::{AdminCommand, AdminResponse};
+
+// only the user running the daemon can use the socket
+const SOCKET_PERMISSIONS: u32 = 0o600;
+// directory in which the socket is created before being moved to its path
+const PRIVATE_DIR_PERMISSIONS: u32 = 0o700;
+
+// Local admin channel using a Unix domain socket
+// Each line received is a JSON command, and a JSON response is sent back for each of them
+pub struct AdminServer {
+    path: String,
+    handle: JoinHandle<()>
+}
+
+impl AdminServer {
+    pub async fn new<S: Storage>(path: String, blockchain: Arc<Blockchain<S>>, prompt: ShareablePrompt) -> Result<Self, anyhow::Error> {
+        if let Ok(metadata) = fs::symlink_metadata(&path) {
+            // only a socket is removed, never a file or a symlink placed at this path
+            if !metadata.file_type().is_socket() {
+                bail!("Admin socket path {} already exists and is not a socket", path);
+            }
+
+            // don't steal the socket of a running daemon
+            if UnixStream::connect(&path).await.is_ok() {
+                bail!("Admin socket {} is already used by another process", path);
+            }
+            debug!("Removing stale admin socket {}", path);
+            fs::remove_file(&path).context("Error while removing stale admin socket")?;
+        }
+
+        // the socket is bound in a directory only accessible by us and moved to its path once its permissions are set,
+        // so no other user can connect to it in between
+        let private_dir = format!("{}.tmp", path);
+        if let Ok(metadata) = fs::symlink_metadata(&private_dir) {
+            if !metadata.is_dir() {
+                bail!("Admin socket directory {} already exists and is not a directory", private_dir);
+            }
+            debug!("Removing stale admin socket directory {}", private_dir);
+            fs::remove_dir_all(&private_dir).context("Error while removing stale admin socket directory")?;
+        }
+        fs::DirBuilder::new().mode(PRIVATE_DIR_PERMISSIONS).create(&private_dir).context("Error while creating admin socket directory")?;
+
+        let private_path = Path::new(&private_dir).join("admin.sock");
+        let listener = UnixListener::bind(&private_path).context("Error while binding admin socket");
+        let res = listener.and_then(|listener| {
+            fs::set_permissions(&private_path, fs::Permissions::from_mode(SOCKET_PERMISSIONS)).context("Error while setting admin socket permissions")?;
+            fs::rename(&private_path, &path).context("Error while moving admin socket")?;
+            Ok(listener)
+        });
+        if let Err(e) = fs::remove_dir_all(&private_dir) {
+            warn!("Error while removing admin socket directory {}: {}", private_dir, e);
+        }
+        let listener = res?;
+        info!("Admin socket listening on {}", path);
+
+        let handle = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let blockchain = Arc::clone(&blockchain);
+                        let prompt = Arc::clone(&prompt);
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_connection(stream, blockchain, prompt).await {
+                                debug!("Error on admin connection: {}", e);
+                            }
+                        });
+                    },
+                    Err(e) => {
+                        error!("Error while accepting admin connection: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            path,
+            handle
+        })
+    }
+
+    pub fn stop(&self) {
+        info!("Stopping admin socket...");
+        self.handle.abort();
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Error while removing admin socket {}: {}", self.path, e);
+        }
+    }
+}
+
+async fn handle_connection<S: Storage>(stream: UnixStream, blockchain: Arc<Blockchain<S>>, prompt: ShareablePrompt) -> Result<(), anyhow::Error> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let response = match serde_json::from_str::<AdminCommand>(&line) {
+            Ok(command) => {
+                info!("Executing admin command: {:?}", command);
+                match execute_command(command, &blockchain, &prompt).await {
+                    Ok(message) => AdminResponse::ok(message),
+                    Err(e) => AdminResponse::error(format!("{:#}", e))
+                }
+            },
+            Err(e) => AdminResponse::error(format!("Invalid command: {}", e))
+        };
+
+        let mut bytes = serde_json::to_vec(&response)?;
+        bytes.push(b'\n');
+        writer.write_all(&bytes).await?;
+    }
+
+    Ok(())
+}
+
+async fn execute_command<S: Storage>(command: AdminCommand, blockchain: &Arc<Blockchain<S>>, prompt: &ShareablePrompt) -> Result<String, anyhow::Error> {
+    Ok(match command {
+        AdminCommand::BanPeer { address } => {
+            let p2p = blockchain.get_p2p().read().await;
+            let p2p = p2p.as_ref().context("P2P is not enabled")?;
+            p2p.get_peer_list().write().await.blacklist_address(&address).await;
+            format!("Peer {} has been blacklisted", address)
+        },
+        AdminCommand::UnbanPeer { address } => {
+            let p2p = blockchain.get_p2p().read().await;
+            let p2p = p2p.as_ref().context("P2P is not enabled")?;
+            let mut peer_list = p2p.get_peer_list().write().await;
+            if !peer_list.is_blacklisted(&address) {
+                bail!("Peer {} is not blacklisted", address);
+            }
+            peer_list.set_graylist_for_peer(&address);
+            format!("Peer {} is not blacklisted anymore", address)
+        },
+        AdminCommand::SetLogLevel { level } => {
+            Prompt::set_log_level(level);
+            format!("Log level set to {}", level)
+        },
+        AdminCommand::FlushMempool => {
+            let mut mempool = blockchain.get_mempool().write().await;
+            let size = mempool.size();
+            mempool.clear();
+            format!("{} transactions removed from mempool", size)
+        },
+        AdminCommand::Prune { topoheight } => {
+            let pruned_topoheight = blockchain.prune_until_topoheight(topoheight).await.context("Error while pruning chain")?;
+            format!("Chain has been pruned until topoheight {}", pruned_topoheight)
+        },
+        AdminCommand::Snapshot { path } => {
+            let topoheight = blockchain.create_snapshot(&path).await.context("Error while creating snapshot")?;
+            format!("Snapshot at topoheight {} has been created in {}", topoheight, path)
+        },
+        AdminCommand::ExportState { path, topoheight } => {
+            let state = blockchain.export_state(topoheight).await.context("Error while exporting state")?;
+            state.save(&path).context("Error while writing state file")?;
+            format!("State of {} accounts at topoheight {} has been written to {}", state.accounts.len(), state.topoheight, path)
+        },
+        AdminCommand::GetRecentRequests { limit, method } => {
+            let rpc = blockchain.get_rpc().read().await;
+            let journal = rpc.as_ref().and_then(|rpc| rpc.get_journal()).context("RPC journal is not enabled")?;
+            let requests = journal.get_recent_requests(method.as_deref(), limit.unwrap_or(RPC_JOURNAL_DEFAULT_LIMIT)).await;
+            serde_json::to_string_pretty(&requests).context("Error while serializing requests")?
+        },
+        AdminCommand::Stop => {
+            prompt.stop().context("Error while stopping daemon")?;
+            "Daemon is stopping".to_owned()
+        }
+    })
+}
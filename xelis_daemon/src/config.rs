@@ -6,17 +6,42 @@ use xelis_common::{
         key::PublicKey, address::Address, hash::{Hash, Hashable}
     },
     serializer::Serializer,
-    api::daemon::DevFeeThreshold
+    network::Network,
+    transaction::EXPIRING_TX_VERSION,
+    api::daemon::{DevFeeThreshold, HardFork, PowAlgorithm, DifficultyAlgorithm}
 };
 
 // In case of potential forks, have a unique network id to not connect to others compatible chains
+// it is also changed when the handshake can't be parsed by the previous versions anymore:
+// the node key, signature, services, prune depth and P2P protocol version were added to it
 pub const NETWORK_ID_SIZE: usize = 16;
-pub const NETWORK_ID: [u8; NETWORK_ID_SIZE] = [0x73, 0x6c, 0x69, 0x78, 0x65, 0x5f, 0x78, 0x65, 0x6c, 0x69, 0x73, 0x5f, 0x62, 0x6c, 0x6f, 0x63];
+pub const NETWORK_ID: [u8; NETWORK_ID_SIZE] = [0x73, 0x6c, 0x69, 0x78, 0x65, 0x5f, 0x78, 0x65, 0x6c, 0x69, 0x73, 0x5f, 0x62, 0x6c, 0x6f, 0x32];
 pub const SEED_NODES: [&str; 2] = ["74.208.251.149:2125", "162.19.249.100:2125"];
 
-// bind addresses
-pub const DEFAULT_P2P_BIND_ADDRESS: &str = "0.0.0.0:2125";
-pub const DEFAULT_RPC_BIND_ADDRESS: &str = "0.0.0.0:8080";
+// bind host, the port depends on the network
+pub const DEFAULT_BIND_HOST: &str = "0.0.0.0";
+// local socket for admin commands
+pub const DEFAULT_ADMIN_SOCKET_PATH: &str = "xelis_daemon.sock";
+
+// P2p bind address by default for the selected network
+pub fn get_default_p2p_bind_address(network: &Network) -> String {
+    format!("{}:{}", DEFAULT_BIND_HOST, network.get_default_p2p_port())
+}
+
+// Rpc bind address by default for the selected network
+pub fn get_default_rpc_bind_address(network: &Network) -> String {
+    format!("{}:{}", DEFAULT_BIND_HOST, network.get_default_rpc_port())
+}
+
+// Admin socket path by default for the selected network
+// mainnet keeps the historical name so existing setups are not broken
+pub fn get_default_admin_socket_path(network: &Network) -> String {
+    if network.is_mainnet() {
+        DEFAULT_ADMIN_SOCKET_PATH.to_owned()
+    } else {
+        format!("xelis_daemon_{}.sock", network.get_default_dir_name())
+    }
+}
 
 // Default cache size for storage DB
 pub const DEFAULT_CACHE_SIZE: usize = 1024;
@@ -34,14 +59,95 @@ pub const GENESIS_BLOCK_DIFFICULTY: Difficulty = 1;
 pub const MAX_BLOCK_SIZE: usize = (1024 * 1024) + (256 * 1024);
 // 2 seconds maximum in future (prevent any attack on reducing difficulty but keep margin for unsynced devices)
 pub const TIMESTAMP_IN_FUTURE_LIMIT: u128 = 2 * 1000;
+// how many blocks are used to compute the median time past
+pub const MEDIAN_TIME_PAST_BLOCKS: usize = 11;
+// how many solve times of the main chain are used by the LWMA difficulty algorithm
+// it must stay below PRUNE_SAFETY_LIMIT so a pruned node still has all the blocks of the window
+pub const LWMA_WINDOW: usize = 45;
+// maximum solve time counted by the LWMA, in block times, to limit the effect of a timestamp in the future
+pub const LWMA_MAX_SOLVE_TIME_FACTOR: u128 = 6;
+
+// Time synchronization
+// maximum peers time offsets kept to compute the network adjusted time
+pub const TIME_OFFSET_SAMPLES: usize = 64;
+// minimum peers time offsets required before adjusting our local time
+pub const MINIMUM_TIME_OFFSET_SAMPLES: usize = 5;
+// maximum offset in seconds applied to the timestamp of our block templates
+// it must stay below TIMESTAMP_IN_FUTURE_LIMIT, a bigger drift is only reported to the operator
+pub const MAX_TIME_OFFSET_ADJUSTMENT: i64 = 1;
 
 // keep at least last N blocks until top topoheight when pruning the chain
 pub const PRUNE_SAFETY_LIMIT: u64 = STABLE_LIMIT * 10;
+// maximum blocks moved to the cold store at each new block
+pub const COLD_STORE_BLOCKS_PER_BLOCK: u64 = 250;
+// maximum heights checked for orphaned blocks to delete at each new block
+pub const ORPHANED_BLOCKS_HEIGHTS_PER_BLOCK: u64 = 250;
+// accounts loaded from storage at once when exporting the state
+pub const STATE_EXPORT_BATCH_SIZE: usize = 1024;
 
 // BlockDAG rules
 pub const TIPS_LIMIT: usize = 3; // maximum 3 previous blocks
 pub const STABLE_LIMIT: u64 = 8; // in how many height we consider the block stable
 
+// Hard forks rules, sorted by ascending height
+// each block at or above the hard fork height must use its version
+// the next versions are only added once the release activating them is coordinated
+pub const HARD_FORKS: [HardFork; 1] = [
+    HardFork {
+        height: 0,
+        version: 0,
+        median_time_past: false,
+        max_block_size: MAX_BLOCK_SIZE,
+        pow_algorithm: PowAlgorithm::Keccak256,
+        difficulty_algorithm: DifficultyAlgorithm::Legacy,
+        smart_contracts: false,
+        name_service: false,
+        max_tx_version: 0
+    }
+];
+
+// Hard forks of the dev network, same genesis version as the other networks
+// the next block versions are scheduled here to be tested before the other networks,
+// and the features not yet scheduled on them are enabled since the genesis
+pub const DEV_HARD_FORKS: [HardFork; 3] = [
+    HardFork {
+        difficulty_algorithm: DifficultyAlgorithm::Lwma,
+        smart_contracts: true,
+        name_service: true,
+        max_tx_version: EXPIRING_TX_VERSION,
+        ..HARD_FORKS[0]
+    },
+    HardFork {
+        height: 100,
+        version: 1,
+        median_time_past: true,
+        difficulty_algorithm: DifficultyAlgorithm::Lwma,
+        smart_contracts: true,
+        name_service: true,
+        max_tx_version: EXPIRING_TX_VERSION,
+        ..HARD_FORKS[0]
+    },
+    // block headers can include an auxiliary commitment (AUX_COMMITMENT_VERSION)
+    HardFork {
+        height: 200,
+        version: 2,
+        median_time_past: true,
+        difficulty_algorithm: DifficultyAlgorithm::Lwma,
+        smart_contracts: true,
+        name_service: true,
+        max_tx_version: EXPIRING_TX_VERSION,
+        ..HARD_FORKS[0]
+    }
+];
+
+// hard forks applied on the selected network
+pub fn get_hard_forks(network: &Network) -> &'static [HardFork] {
+    match network {
+        Network::Dev => &DEV_HARD_FORKS,
+        _ => &HARD_FORKS
+    }
+}
+
 // Emission rules
 pub const DEV_FEES: [DevFeeThreshold; 3] = [
     DevFeeThreshold {
@@ -57,6 +163,7 @@ pub const DEV_FEES: [DevFeeThreshold; 3] = [
         fee_percentage: 5
     }
 ]; // 15%, 10%, 5% per block going to dev address
+
 pub const SIDE_BLOCK_REWARD_PERCENT: u64 = 30; // only 30% of reward for side block
 pub const EMISSION_SPEED_FACTOR: u64 = 20;
 pub const MAXIMUM_SUPPLY: u64 = 18_400_000 * COIN_VALUE; // 18.4M full coin
@@ -75,19 +182,65 @@ pub const CHAIN_SYNC_RESPONSE_MIN_BLOCKS: usize = 512; // minimum X blocks hashe
 pub const CHAIN_SYNC_DEFAULT_RESPONSE_BLOCKS: usize = 4096; // Default response blocks sent/accepted
 pub const CHAIN_SYNC_RESPONSE_MAX_BLOCKS: usize = 16384; // allows up to X blocks hashes sent for response
 pub const CHAIN_SYNC_TOP_BLOCKS: usize = 10; // send last 10 heights
+pub const CHAIN_SYNC_PROGRESS_LOG_DELAY: u64 = 30; // log the sync progress every X seconds while syncing
+
+// Mempool rules
+pub const MEMPOOL_DEFAULT_MAX_PENDING_TXS_PER_ACCOUNT: usize = 64; // default maximum unconfirmed txs chained by an account
+pub const MEMPOOL_MAX_ORPHANS_PER_ACCOUNT: usize = 16; // maximum txs waiting for a previous nonce per account, also the maximum nonce gap accepted
+pub const MEMPOOL_MAX_ORPHANS: usize = 4096; // maximum txs waiting for a previous nonce for all accounts
+pub const MEMPOOL_ORPHAN_EXPIRATION: u64 = 60 * 10; // time in seconds before deleting a tx still waiting for its previous nonce
+pub const MEMPOOL_TX_TTL: u64 = 60 * 60 * 24; // time in seconds before deleting a tx without expiration topoheight
+
+// Block verification
+pub const MIN_TXS_PER_VERIFICATION_THREAD: usize = 16; // don't spawn a thread to verify less than X signatures
+pub const SIGNATURE_VERIFIER_QUEUE_SIZE: usize = 4096; // maximum txs waiting for their signature verification before adding them to mempool
+pub const SIGNATURE_VERIFIER_CACHE_SIZE: usize = 8192; // keep the signature result of the last X txs verified
+pub const TX_REFERENCES_CACHE_SIZE: usize = 8192; // keep the hash of the last X txs submitted with a client reference
+
+// RPC rules
+pub const RPC_MAX_WATCHED_ADDRESSES: usize = 100_000; // maximum addresses watched by a websocket session
+pub const RPC_JOURNAL_DEFAULT_SIZE: usize = 1000; // default number of requests kept in memory by the RPC journal
+pub const RPC_JOURNAL_DEFAULT_LIMIT: usize = 20; // default number of requests returned by the admin command
+pub const RPC_JOURNAL_DEFAULT_MAX_FILE_SIZE: u64 = 100; // default size in MB of the RPC journal file before it is rotated
+pub const RPC_JOURNAL_WRITER_QUEUE_SIZE: usize = 1024; // maximum requests waiting to be written in the RPC journal file
+pub const RPC_JOURNAL_MAX_VALUE_SIZE: usize = 16 * 1024; // params or result bigger than X bytes are truncated in the journal
+pub const RPC_JOURNAL_REDACTED_FIELDS: [&str; 7] = ["password", "secret", "seed", "mnemonic", "private_key", "token", "api_key"]; // fields always redacted in the journal
 
 // P2p rules
+pub const P2P_PROTOCOL_VERSION: u8 = 2; // version of the P2P protocol advertised in the handshake
+pub const P2P_OBJECTS_BATCH_VERSION: u8 = 1; // first protocol version supporting the batched objects packets
+pub const P2P_KEEP_ALIVE_VERSION: u8 = 1; // first protocol version supporting the keep alive packets
+pub const P2P_BURNED_SUPPLY_VERSION: u8 = 2; // first protocol version sending the burned supply in the blocks metadata of the fast sync
 pub const P2P_PING_DELAY: u64 = 10; // time between each ping
 pub const P2P_PING_PEER_LIST_DELAY: u64 = 60 * 5; // time in seconds between each update of peerlist
 pub const P2P_PING_PEER_LIST_LIMIT: usize = 16; // maximum number of addresses to be send
 pub const P2P_DEFAULT_MAX_PEERS: usize = 32; // default number of maximum peers
 pub const P2P_EXTEND_PEERLIST_DELAY: u64 = 60; // time in seconds between each time we try to connect to a new peer
+pub const P2P_SLOW_PACKET_THRESHOLD_MS: u64 = 500; // packets handled in more than X millis are logged as slow
+pub const P2P_OBJECTS_RESPONSE_MAX_SIZE: usize = MAX_BLOCK_SIZE - 1024; // maximum size in bytes of objects sent in one batch response
+pub const P2P_KEEP_ALIVE_DELAY: u64 = 15; // time in seconds between each keep alive packet sent
+pub const P2P_KEEP_ALIVE_MAX_MISSED: u8 = 4; // number of keep alive packets not answered before disconnecting the peer
+pub const P2P_CRAWLER_DELAY: u64 = 60 * 10; // time in seconds between each crawl of the network
+pub const P2P_CRAWLER_MAX_PROBES: usize = 64; // maximum number of nodes reached during one crawl
+pub const P2P_CORPUS_MAX_FILES: usize = 100_000; // maximum number of packets recorded to the corpus directory
+pub const P2P_CRAWLER_NODE_EXPIRATION: u64 = 60 * 60; // time in seconds before forgetting a node not announced or reached anymore
+// Chain split monitor
+pub const SPLIT_MONITOR_DELAY: u64 = 60; // time in seconds between each comparison with the reference nodes
+pub const SPLIT_MONITOR_WEBHOOK_TIMEOUT: u64 = 10; // time in seconds before giving up on a webhook call
 // Peer rules
 pub const PEER_FAIL_TIME_RESET: u64 = 60 * 5; // number of seconds to reset the counter
 pub const PEER_FAIL_LIMIT: u8 = 20; // number of fail to disconnect the peer
+pub const PEER_TRUSTED_FAIL_LIMIT: u8 = 100; // number of fail to disconnect a trusted peer
+pub const PEER_TRUSTED_MAX_TIME_DRIFT: u64 = 60; // maximum seconds between the handshake time and ours to trust a peer
 pub const PEER_TIMEOUT_REQUEST_OBJECT: u64 = 15000; // millis until we timeout
 pub const PEER_TIMEOUT_BOOTSTRAP_STEP: u64 = 60000; // millis until we timeout
 pub const PEER_TIMEOUT_INIT_CONNECTION: u64 = 3000; // millis until we timeout
+pub const PEER_DEFAULT_REPUTATION: u8 = 50; // reputation of a peer never connected before
+pub const PEER_MAX_REPUTATION: u8 = 100; // reputation can't go above it
+pub const PEER_REPUTATION_MIN_SESSION: u64 = 60 * 10; // seconds a connection must last to be rewarded
+pub const PEER_REPUTATION_REWARD: u8 = 5; // reputation gained for a long session or for blocks synced from it
+pub const PEER_REPUTATION_PENALTY: u8 = 20; // reputation lost when disconnected for reaching its fail limit
+pub const PEER_REPUTATION_CONNECTION_PENALTY: u8 = 5; // reputation lost when we can't connect to it
 
 lazy_static! {
     pub static ref DEV_PUBLIC_KEY: PublicKey = Address::from_string(&DEV_ADDRESS.to_owned()).unwrap().to_public_key();
@@ -1,74 +1,131 @@
 use anyhow::Error;
 use lru::LruCache;
-use serde_json::{Value, json};
 use xelis_common::{
     config::{XELIS_ASSET, COIN_DECIMALS},
-    crypto::{key::PublicKey, hash::{Hashable, Hash, HASH_SIZE}},
+    crypto::{key::{PublicKey, KeyPair}, hash::{Hashable, Hash, HASH_SIZE}},
     difficulty::check_difficulty,
-    transaction::{Transaction, TransactionType, EXTRA_DATA_LIMIT_SIZE},
+    transaction::{Transaction, TransactionType, SmartContractCall, TransactionReceipt, ReceiptStatus, EXTRA_DATA_LIMIT_SIZE, MAX_TX_SIZE, MAX_TRANSFERS_PER_TX},
+    contract::{self, Program, ExecutionContext, MAX_KEY_SIZE},
     utils::{get_current_timestamp, format_xelis, get_current_time},
-    block::{Block, BlockHeader, EXTRA_NONCE_SIZE, Difficulty},
+    block::{Block, BlockHeader, EXTRA_NONCE_SIZE, AUX_COMMITMENT_VERSION, Difficulty},
     immutable::Immutable,
     serializer::Serializer,
     account::VersionedBalance,
     api::{
         daemon::{
             NotifyEvent,
+            DaemonEvent,
             BlockOrderedEvent,
             TransactionExecutedEvent,
+            BalanceChangedEvent,
             BlockType,
             StableHeightChangedEvent,
-            TransactionResponse
+            TransactionResponse,
+            TransactionOrphanedEvent,
+            TransactionExpiredEvent,
+            TransactionConflictedEvent,
+            PowAlgorithm,
+            DifficultyAlgorithm,
+            GetTipsInfoResult,
+            GetPowSeedResult,
+            TipInfo,
+            TipSelection,
+            TipSelectionStrategy
         },
         DataHash
     },
     network::Network,
-    asset::AssetData
+    asset::AssetData,
+    name::{NameEntry, is_valid_name, get_name_registration_cost, NAME_MIN_DURATION, NAME_MAX_DURATION},
+    rpc_server::HttpServerConfig,
+    time::MonotonicClock,
+    pow::{self, DatasetConfig, DatasetManager, DatasetMode, DATASET_PARAMS}
 };
 use crate::{
     config::{
-        DEFAULT_P2P_BIND_ADDRESS, P2P_DEFAULT_MAX_PEERS, DEFAULT_RPC_BIND_ADDRESS, DEFAULT_CACHE_SIZE, MAX_BLOCK_SIZE,
+        get_default_p2p_bind_address, P2P_DEFAULT_MAX_PEERS, get_default_rpc_bind_address, DEFAULT_CACHE_SIZE,
         EMISSION_SPEED_FACTOR, MAXIMUM_SUPPLY, DEV_FEES, GENESIS_BLOCK, TIPS_LIMIT, TIMESTAMP_IN_FUTURE_LIMIT,
         STABLE_LIMIT, GENESIS_BLOCK_HASH, MINIMUM_DIFFICULTY, GENESIS_BLOCK_DIFFICULTY, SIDE_BLOCK_REWARD_PERCENT,
-        DEV_PUBLIC_KEY, PRUNE_SAFETY_LIMIT, BLOCK_TIME_MILLIS, MILLIS_PER_SECOND,
+        DEV_PUBLIC_KEY, PRUNE_SAFETY_LIMIT, COLD_STORE_BLOCKS_PER_BLOCK, BLOCK_TIME_MILLIS, MILLIS_PER_SECOND, MEDIAN_TIME_PAST_BLOCKS,
+        MEMPOOL_MAX_ORPHANS_PER_ACCOUNT, MEMPOOL_DEFAULT_MAX_PENDING_TXS_PER_ACCOUNT, MIN_TXS_PER_VERIFICATION_THREAD,
+        LWMA_WINDOW, TX_REFERENCES_CACHE_SIZE, STATE_EXPORT_BATCH_SIZE, ORPHANED_BLOCKS_HEIGHTS_PER_BLOCK, SPLIT_MONITOR_DELAY,
+        RPC_JOURNAL_DEFAULT_SIZE, RPC_JOURNAL_DEFAULT_MAX_FILE_SIZE
     },
-    core::difficulty::calculate_difficulty,
-    p2p::P2pServer,
+    core::difficulty::{calculate_difficulty, calculate_difficulty_lwma},
+    p2p::{P2pServer, bandwidth::BandwidthTracker, transport::{TcpTransport, Transports}},
     rpc::{
         rpc::{
-            get_block_response_for_hash, get_block_type_for_block
+            get_block_header_response_for_hash, get_block_type_for_block
         },
+        journal::RequestJournal,
         DaemonRpcServer, SharedDaemonRpcServer
     }
 };
+#[cfg(feature = "grpc")]
+use crate::grpc::{self, GrpcServer, SharedGrpcServer};
+#[cfg(feature = "quic")]
+use crate::p2p::quic::QuicTransport;
 use super::storage::{Storage, DifficultyProvider};
-use std::{sync::atomic::{Ordering, AtomicU64}, collections::hash_map::Entry, time::{Duration, Instant}, borrow::Cow};
-use std::collections::{HashMap, HashSet};
+use std::{sync::atomic::{Ordering, AtomicU64}, collections::hash_map::Entry, time::{Duration, Instant}, borrow::Cow, num::NonZeroUsize, thread};
+use std::collections::{HashMap, HashSet, VecDeque};
 use async_recursion::async_recursion;
-use tokio::{time::interval, sync::{Mutex, RwLock}};
+use tokio::{time::interval, sync::{Mutex, RwLock, watch}};
 use log::{info, error, debug, warn, trace};
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
 use rand::Rng;
 
 use super::blockdag;
+use super::hard_fork;
+use super::genesis::{GenesisConfig, StateExport, AccountState};
 use super::error::BlockchainError;
 use super::mempool::Mempool;
+use super::time_sync::{self, TimeSync};
+use super::signature_verifier::SignatureVerifier;
+use super::split_monitor::SplitMonitor;
+use super::template::{TemplateConfig, TemplateBuilder, TxCandidate, TipCandidate, select_tips};
 
 #[derive(Debug, clap::StructOpt)]
 pub struct Config {
     /// Optional node tag
     #[clap(short, long)]
     pub tag: Option<String>,
-    /// P2p bind address to listen for incoming connections
-    #[clap(short, long, default_value_t = String::from(DEFAULT_P2P_BIND_ADDRESS))]
-    pub p2p_bind_address: String,
+    /// P2p bind address to listen for incoming connections, default port depends on the network
+    #[clap(short, long)]
+    pub p2p_bind_address: Option<String>,
     /// Number of maximums peers allowed
     #[clap(short, long, default_value_t = P2P_DEFAULT_MAX_PEERS)]
     pub max_peers: usize,
-    /// Rpc bind address to listen for HTTP requests
-    #[clap(short, long, default_value_t = String::from(DEFAULT_RPC_BIND_ADDRESS))]
-    pub rpc_bind_address: String,
+    /// Rpc bind address to listen for HTTP requests, default port depends on the network
+    #[clap(short, long)]
+    pub rpc_bind_address: Option<String>,
+    #[clap(flatten)]
+    pub rpc_http_config: HttpServerConfig,
+    /// Sign all JSON-RPC responses with the node key
+    /// Allows light wallets to detect responses modified by a middleman
+    #[clap(long)]
+    pub sign_rpc_responses: bool,
+    /// Keep the last requests executed by the RPC server and their responses to debug integrations
+    /// They can be retrieved using the `get-recent-requests` admin command
+    #[clap(long)]
+    pub enable_rpc_journal: bool,
+    /// Number of requests kept in memory by the RPC journal, 0 to only write them in the journal file
+    #[clap(long, default_value_t = RPC_JOURNAL_DEFAULT_SIZE)]
+    pub rpc_journal_size: usize,
+    /// Also append each request of the RPC journal in this file, one JSON per line
+    #[clap(long)]
+    pub rpc_journal_file: Option<String>,
+    /// Maximum size in MB of the RPC journal file, it is then renamed with a `.1` suffix and a new file is started
+    #[clap(long, default_value_t = RPC_JOURNAL_DEFAULT_MAX_FILE_SIZE)]
+    pub rpc_journal_max_file_size: u64,
+    /// Name of a field to redact in the RPC journal, in addition to the default ones (password, seed, token...)
+    #[clap(long)]
+    pub rpc_journal_redact: Vec<String>,
+    /// gRPC bind address, the gRPC server is disabled if not set
+    #[cfg(feature = "grpc")]
+    #[clap(long)]
+    pub grpc_bind_address: Option<String>,
     /// Add a priority node to connect when P2p is started
     /// A priority node is connected only one time
     #[clap(short = 'o', long)]
@@ -77,9 +134,18 @@ pub struct Config {
     /// it also replaces seed nodes
     #[clap(short, long)]
     pub exclusive_nodes: Vec<String>,
+    /// Node key in hex of a trusted peer, verified in its handshake.
+    /// A trusted peer gets a reserved slot, relaxed rate limits and receives the blocks first
+    #[clap(long)]
+    pub trusted_node_keys: Vec<String>,
     /// Set dir path for blockchain storage
     #[clap(short = 's', long)]
     pub dir_path: Option<String>,
+    /// File containing a 32 bytes key in hex used to encrypt the versioned balances and nonces in storage.
+    /// The accounts having a balance or a nonce and the topoheight of their last change are not encrypted.
+    /// It must be set when the storage is created and is then required to open it
+    #[clap(long)]
+    pub storage_key_file: Option<String>,
     /// Set LRUCache size (0 = disabled)
     #[clap(short, long, default_value_t = DEFAULT_CACHE_SIZE)]
     pub cache_size: usize,
@@ -92,26 +158,103 @@ pub struct Config {
     /// Disable the p2p connections
     #[clap(long)]
     pub disable_p2p_server: bool,
+    /// Crawl the network periodically to estimate its size, versions and spread.
+    /// Results are available using the `get_network_topology` RPC method
+    #[clap(long)]
+    pub enable_network_crawler: bool,
+    /// Record each unique packet received from peers in this directory.
+    /// The corpus can be checked using the `replay_packets` command
+    #[clap(long)]
+    pub p2p_record_packets: Option<String>,
+    /// Maximum traffic in MB (upload and download) per UTC day.
+    /// Once reached, the chain sync is not served to peers anymore until the next day
+    #[clap(long)]
+    pub p2p_daily_bandwidth_cap: Option<u64>,
+    /// Maximum traffic in MB (upload and download) per UTC month.
+    /// Once reached, the chain sync is not served to peers anymore until the next month
+    #[clap(long)]
+    pub p2p_monthly_bandwidth_cap: Option<u64>,
+    /// Don't relay the transactions of the network: they are not requested from the peers
+    /// and only the transactions submitted through RPC are broadcasted
+    #[clap(long)]
+    pub p2p_disable_tx_relay: bool,
+    /// Listen for QUIC connections on the P2P bind address (UDP).
+    /// Nodes can be reached using QUIC by prefixing their address with `quic://`,
+    /// and their key can be pinned using `quic://<key>@<address>`
+    #[cfg(feature = "quic")]
+    #[clap(long)]
+    pub p2p_enable_quic: bool,
+    /// JSON-RPC URL of a reference node (daemon or explorer) to compare our stable chain with.
+    /// A chain split is reported in the logs, through the `ChainSplitDetected` event and the `get_chain_split_status` RPC method
+    #[clap(long)]
+    pub split_monitor_nodes: Vec<String>,
+    /// URL called with a POST request containing the alert when a chain split is detected
+    #[clap(long)]
+    pub split_monitor_webhook: Option<String>,
     /// Enable the auto prune mode and prune the chain
     /// at each new block by keeping at least N blocks
     /// before the top.
     #[clap(long)]
     pub auto_prune_keep_n_blocks: Option<u64>,
+    /// Move the block headers and transactions older than
+    /// N blocks before the top to an append-only cold store
+    /// to reduce the size of the database.
+    #[clap(long)]
+    pub cold_store_depth: Option<u64>,
+    /// Delete the orphaned blocks once they are N blocks below the top height.
+    /// By default, orphaned blocks are kept so they can be queried using `get_orphaned_blocks`.
+    #[clap(long)]
+    pub orphaned_blocks_retention: Option<u64>,
     /// Sync a bootstrapped chain if your local copy is outdated.
     /// It will not store any blocks / TXs and will not verify the history locally.
     /// Use it with extreme cautions and trusted nodes to have a valid bootstrapped chain
     #[clap(long)]
-    pub allow_fast_sync: bool
+    pub allow_fast_sync: bool,
+    /// Maximum number of transactions selected in a block template
+    #[clap(long)]
+    pub template_max_txs: Option<usize>,
+    /// Maximum size in bytes of all transactions selected in a block template
+    #[clap(long)]
+    pub template_max_txs_size: Option<usize>,
+    /// Minimum fee per KB required for a transaction to be selected in a block template
+    #[clap(long, default_value_t = 0)]
+    pub template_min_fee_per_kb: u64,
+    /// Addresses from which transactions are selected first in a block template
+    #[clap(long)]
+    pub template_priority_addresses: Vec<String>,
+    /// Addresses from which transactions are never selected in a block template
+    #[clap(long)]
+    pub template_blacklisted_addresses: Vec<String>,
+    /// Strategy used to select the tips referenced by a block template
+    #[clap(long, arg_enum, default_value_t = TipSelectionStrategy::Heaviest)]
+    pub template_tip_selection: TipSelectionStrategy,
+    /// Maximum unconfirmed transactions chained by an account in mempool
+    #[clap(long, default_value_t = MEMPOOL_DEFAULT_MAX_PENDING_TXS_PER_ACCOUNT)]
+    pub max_pending_txs_per_account: usize,
+    /// Number of threads verifying the transactions signatures of a block, default is the number of CPU cores
+    #[clap(long)]
+    pub verification_threads: Option<usize>,
+    /// Number of threads verifying the signatures of the transactions received for the mempool, default is the number of CPU cores
+    #[clap(long)]
+    pub mempool_verification_threads: Option<usize>,
+    #[clap(flatten)]
+    pub pow_dataset: DatasetConfig,
+    /// Genesis file of a custom network generated by the `generate-genesis` command
+    #[clap(long)]
+    pub genesis_file: Option<String>
 }
 
 pub struct Blockchain<S: Storage> {
     height: AtomicU64, // current block height
     topoheight: AtomicU64, // current topo height
     stable_height: AtomicU64, // current stable height
+    stable_topoheight: AtomicU64, // topoheight of the block at the stable height
     mempool: RwLock<Mempool>, // mempool to retrieve/add all txs
     storage: RwLock<S>, // storage to retrieve/add blocks
     p2p: RwLock<Option<Arc<P2pServer<S>>>>, // P2p module
     rpc: RwLock<Option<SharedDaemonRpcServer<S>>>, // Rpc module
+    #[cfg(feature = "grpc")]
+    grpc: RwLock<Option<SharedGrpcServer>>, // gRPC module
     // current difficulty at tips
     // its used as cache to display current network hashrate
     difficulty: AtomicU64,
@@ -125,15 +268,43 @@ pub struct Blockchain<S: Storage> {
     // tip work score is used to determine the best tip based on a block, tip base ands a base height
     tip_work_score_cache: Mutex<LruCache<(Hash, Hash, u64), (HashSet<Hash>, Difficulty)>>,
     full_order_cache: Mutex<LruCache<(Hash, Hash, u64), Vec<Hash>>>,
+    // hash of the last txs submitted through RPC with a client reference
+    tx_references: Mutex<LruCache<String, Hash>>,
     // auto prune mode if enabled, will delete all blocks every N and keep only N top blocks (topoheight based)
     auto_prune_keep_n_blocks: Option<u64>,
+    // move blocks older than N blocks before the top to the cold store if enabled
+    cold_store_depth: Option<u64>,
+    // delete orphaned blocks N blocks below the top height if enabled
+    orphaned_blocks_retention: Option<u64>,
     // allow fast syncing (only balances / assets / Smart Contracts changes)
     // without syncing the history
-    allow_fast_sync_mode: bool
+    allow_fast_sync_mode: bool,
+    // network adjusted time based on peers time offsets
+    time_sync: TimeSync,
+    // policy used to select the transactions of a block template
+    template_config: TemplateConfig,
+    // maximum unconfirmed txs chained by an account in mempool
+    max_pending_txs_per_account: usize,
+    // notified with the current topoheight each time the chain changes
+    // used to wait for transactions confirmations
+    topoheight_notifier: watch::Sender<u64>,
+    // persistent identity of the node, used to sign the RPC responses
+    node_key: KeyPair,
+    // compare our chain with reference nodes if enabled
+    split_monitor: Option<Arc<SplitMonitor>>,
+    // maximum threads used to verify the signatures of a block
+    verification_threads: usize,
+    // verify the signatures of the TXs received for the mempool in background
+    signature_verifier: SignatureVerifier,
+    // dataset of the memory-hard PoW algorithms, only allocated when one is active
+    pow_dataset: Arc<DatasetManager>,
+    // genesis of a custom network, none when using the genesis of the network
+    genesis: Option<GenesisConfig>,
+    genesis_hash: Hash
 }
 
 impl<S: Storage> Blockchain<S> {
-    pub async fn new(config: Config, network: Network, storage: S) -> Result<Arc<Self>, Error> {
+    pub async fn new(config: Config, network: Network, mut storage: S) -> Result<Arc<Self>, Error> {
         if config.simulator && network != Network::Dev {
             error!("Impossible to enable simulator mode except in dev network!");
             return Err(BlockchainError::InvalidNetwork.into())
@@ -146,6 +317,54 @@ impl<S: Storage> Blockchain<S> {
             }
         }
 
+        if let Some(depth) = config.cold_store_depth {
+            if depth < PRUNE_SAFETY_LIMIT {
+                error!("Cold store depth should be at least {} blocks", PRUNE_SAFETY_LIMIT);
+                return Err(BlockchainError::ColdStoreDepth.into())
+            }
+        }
+
+        if let Some(retention) = config.orphaned_blocks_retention {
+            if retention < PRUNE_SAFETY_LIMIT {
+                error!("Orphaned blocks retention should be at least {} blocks", PRUNE_SAFETY_LIMIT);
+                return Err(BlockchainError::OrphanedBlocksRetention.into())
+            }
+        }
+
+        let template_config = TemplateConfig::from_addresses(
+            config.template_max_txs,
+            config.template_max_txs_size,
+            config.template_min_fee_per_kb,
+            &config.template_priority_addresses,
+            &config.template_blacklisted_addresses,
+            config.template_tip_selection,
+            &network
+        )?;
+
+        // verifying blocks doesn't require the full dataset
+        let pow_dataset = match DatasetManager::new(config.pow_dataset, DATASET_PARAMS, DatasetMode::Light) {
+            Ok(manager) => Arc::new(manager),
+            Err(e) => {
+                error!("Invalid PoW dataset configuration: {}", e);
+                return Err(e.into())
+            }
+        };
+
+        let (genesis, genesis_hash) = if let Some(path) = config.genesis_file.as_ref() {
+            info!("Loading genesis file {}", path);
+            let genesis = match GenesisConfig::load(path).and_then(|genesis| genesis.to_block(&network).map(|block| (genesis, block.hash()))) {
+                Ok(genesis) => genesis,
+                Err(e) => {
+                    error!("Invalid genesis file {}: {}", path, e);
+                    return Err(e.into())
+                }
+            };
+            info!("Genesis block {} loaded with {} allocation(s) and {} registration(s)", genesis.1, genesis.0.allocations.len(), genesis.0.registrations.len());
+            (Some(genesis.0), genesis.1)
+        } else {
+            (None, GENESIS_BLOCK_HASH.clone())
+        };
+
         let on_disk = storage.has_blocks();
         let (height, topoheight) = if on_disk {
             info!("Reading last metadata available...");
@@ -155,29 +374,70 @@ impl<S: Storage> Blockchain<S> {
             (height, topoheight)
         } else { (0, 0) };
 
+        // identity of the node, generated on first start
+        let node_key = match storage.get_node_key()? {
+            Some(keypair) => keypair,
+            None => {
+                info!("Generating a new node key...");
+                let keypair = KeyPair::new();
+                storage.set_node_key(&keypair)?;
+                keypair
+            }
+        };
+        info!("Node key: {}", hex::encode(node_key.get_public_key().as_bytes()));
+
         info!("Initializing chain...");
         let blockchain = Self {
             height: AtomicU64::new(height),
             topoheight: AtomicU64::new(topoheight),
             stable_height: AtomicU64::new(0),
+            stable_topoheight: AtomicU64::new(0),
             mempool: RwLock::new(Mempool::new()),
             storage: RwLock::new(storage),
             p2p: RwLock::new(None),
             rpc: RwLock::new(None),
+            #[cfg(feature = "grpc")]
+            grpc: RwLock::new(None),
             difficulty: AtomicU64::new(GENESIS_BLOCK_DIFFICULTY),
             simulator: config.simulator,
             network,
             tip_base_cache: Mutex::new(LruCache::new(1024)),
             tip_work_score_cache: Mutex::new(LruCache::new(1024)),
             full_order_cache: Mutex::new(LruCache::new(1024)),
+            tx_references: Mutex::new(LruCache::new(TX_REFERENCES_CACHE_SIZE)),
             auto_prune_keep_n_blocks: config.auto_prune_keep_n_blocks,
-            allow_fast_sync_mode: config.allow_fast_sync
+            cold_store_depth: config.cold_store_depth,
+            orphaned_blocks_retention: config.orphaned_blocks_retention,
+            allow_fast_sync_mode: config.allow_fast_sync,
+            time_sync: TimeSync::new(Arc::new(MonotonicClock::default())),
+            template_config,
+            max_pending_txs_per_account: config.max_pending_txs_per_account,
+            topoheight_notifier: watch::channel(topoheight).0,
+            node_key,
+            split_monitor: if !config.split_monitor_nodes.is_empty() {
+                Some(Arc::new(SplitMonitor::new(config.split_monitor_nodes, config.split_monitor_webhook)))
+            } else {
+                None
+            },
+            verification_threads: config.verification_threads.unwrap_or_else(get_available_threads).max(1),
+            signature_verifier: SignatureVerifier::new(config.mempool_verification_threads.unwrap_or_else(get_available_threads)),
+            pow_dataset,
+            genesis,
+            genesis_hash
         };
 
         // include genesis block
         if !on_disk {
             blockchain.create_genesis_block().await?;
         } else {
+            // the chain stored must have been created with the same genesis
+            // it may not be available anymore after a fast sync
+            let stored_genesis_hash = blockchain.get_storage().read().await.get_hash_at_topo_height(0).await.ok();
+            if let Some(stored_genesis_hash) = stored_genesis_hash.filter(|hash| *hash != blockchain.genesis_hash) {
+                error!("Genesis block {} stored is not the genesis block {} expected, use another dir path", stored_genesis_hash, blockchain.genesis_hash);
+                return Err(BlockchainError::InvalidGenesisHash.into())
+            }
+
             debug!("Retrieving tips for computing current difficulty");
             let storage = blockchain.get_storage().read().await;
             let tips_set = storage.get_tips().await?;
@@ -195,37 +455,79 @@ impl<S: Storage> Blockchain<S> {
             debug!("Retrieving tips for computing current stable height");
             let storage = blockchain.get_storage().read().await;
             let tips = storage.get_tips().await?;
-            let (_, stable_height) = blockchain.find_common_base(&storage, &tips).await?;
+            let (stable_hash, stable_height) = blockchain.find_common_base(&storage, &tips).await?;
             blockchain.stable_height.store(stable_height, Ordering::SeqCst);
+            blockchain.stable_topoheight.store(storage.get_topo_height_for_hash(&stable_hash).await?, Ordering::SeqCst);
+        }
+
+        // prepare the dataset of the current epoch before receiving the next blocks
+        if hard_fork::get_hard_fork_at_height(&blockchain.network, height + 1).pow_algorithm.requires_dataset() {
+            let storage = blockchain.get_storage().read().await;
+            let seed = blockchain.get_pow_seed(&*storage).await?;
+            blockchain.pow_dataset.prepare(seed.seed_topoheight, seed.seed);
+        }
+
+        for fork in hard_fork::get_upcoming_hard_forks(&blockchain.network, height) {
+            info!("Hard fork with block version {} is scheduled at height {}", fork.version, fork.height);
         }
 
         let arc = Arc::new(blockchain);
+        // resolve the bind addresses of the selected network when not set
+        let p2p_bind_address = config.p2p_bind_address.unwrap_or_else(|| get_default_p2p_bind_address(&network));
+        let rpc_bind_address = config.rpc_bind_address.unwrap_or_else(|| get_default_rpc_bind_address(&network));
         // create P2P Server
         if !config.disable_p2p_server && arc.network != Network::Dev  {
             info!("Starting P2p server...");
+            let mut transports = Transports::new(Box::new(TcpTransport));
+            #[cfg(feature = "quic")]
+            if config.p2p_enable_quic {
+                // the certificate of the node is kept with its data
+                let identity_dir = config.dir_path.clone().unwrap_or_else(|| network.get_default_dir_name().to_owned());
+                match QuicTransport::new(&p2p_bind_address, &identity_dir) {
+                    Ok(transport) => transports.add(Box::new(transport)),
+                    Err(e) => error!("Error while starting QUIC transport: {}", e)
+                };
+            }
+
             // setup exclusive nodes
             let mut exclusive_nodes: Vec<SocketAddr> = Vec::with_capacity(config.exclusive_nodes.len());
             for peer in config.exclusive_nodes {
-                let addr: SocketAddr = match peer.parse() {
+                let addr = match transports.parse_peer_address(&peer) {
                     Ok(addr) => addr,
                     Err(e) => {
-                        error!("Error while parsing priority node address: {}", e);
+                        error!("Error while parsing exclusive node address: {}", e);
                         continue;
                     }
                 };
                 exclusive_nodes.push(addr);
             }
-            match P2pServer::new(config.tag, config.max_peers, config.p2p_bind_address, Arc::clone(&arc), exclusive_nodes.is_empty(), exclusive_nodes) {
+            // setup priority nodes, they are connected once the server is started
+            let mut priority_nodes: Vec<SocketAddr> = Vec::with_capacity(config.priority_nodes.len());
+            for peer in config.priority_nodes {
+                match transports.parse_peer_address(&peer) {
+                    Ok(addr) => priority_nodes.push(addr),
+                    Err(e) => error!("Error while parsing priority node address: {}", e)
+                };
+            }
+            // setup trusted node keys
+            let mut trusted_node_keys: HashSet<PublicKey> = HashSet::with_capacity(config.trusted_node_keys.len());
+            for key in config.trusted_node_keys {
+                match PublicKey::from_hex(key) {
+                    Ok(key) => {
+                        trusted_node_keys.insert(key);
+                    },
+                    Err(e) => error!("Error while parsing trusted node key: {}", e)
+                };
+            }
+            // caps are configured in MB
+            let bandwidth = BandwidthTracker::new(
+                config.p2p_daily_bandwidth_cap.map(|cap| cap * 1024 * 1024),
+                config.p2p_monthly_bandwidth_cap.map(|cap| cap * 1024 * 1024)
+            );
+            match P2pServer::new(config.tag, config.max_peers, p2p_bind_address, Arc::clone(&arc), exclusive_nodes.is_empty(), exclusive_nodes, config.enable_network_crawler, trusted_node_keys, config.p2p_record_packets, bandwidth, transports, !config.p2p_disable_tx_relay) {
                 Ok(p2p) => {
                     // connect to priority nodes
-                    for addr in config.priority_nodes {
-                        let addr: SocketAddr = match addr.parse() {
-                            Ok(addr) => addr,
-                            Err(e) => {
-                                error!("Error while parsing priority node address: {}", e);
-                                continue;
-                            }
-                        };
+                    for addr in priority_nodes {
                         info!("Trying to connect to priority node: {}", addr);
                         p2p.try_to_connect_to_peer(addr, true).await;
                     }
@@ -238,12 +540,35 @@ impl<S: Storage> Blockchain<S> {
         // create RPC Server
         {
             info!("Starting RPC server...");
-            match DaemonRpcServer::new(config.rpc_bind_address, Arc::clone(&arc), config.disable_getwork_server).await {
+            let journal = if config.enable_rpc_journal {
+                // max file size is configured in MB
+                match RequestJournal::new(config.rpc_journal_size, config.rpc_journal_file, config.rpc_journal_max_file_size * 1024 * 1024, config.rpc_journal_redact) {
+                    Ok(journal) => Some(Arc::new(journal)),
+                    Err(e) => {
+                        error!("Error while creating RPC journal: {:#}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+            match DaemonRpcServer::new(rpc_bind_address, Arc::clone(&arc), config.disable_getwork_server, config.sign_rpc_responses, journal, config.rpc_http_config).await {
                 Ok(server) => *arc.rpc.write().await = Some(server),
                 Err(e) => error!("Error while starting RPC server: {}", e)
             };
         }
 
+        // create gRPC Server
+        #[cfg(feature = "grpc")]
+        if let Some(bind_address) = config.grpc_bind_address {
+            info!("Starting gRPC server...");
+            match GrpcServer::new(bind_address, Arc::clone(&arc)).await {
+                Ok(server) => *arc.grpc.write().await = Some(server),
+                Err(e) => error!("Error while starting gRPC server: {}", e)
+            };
+        }
+
         if arc.simulator {
             warn!("Simulator mode enabled!");
             let zelf = Arc::clone(&arc);
@@ -259,6 +584,18 @@ impl<S: Storage> Blockchain<S> {
             });
         }
 
+        if let Some(monitor) = arc.split_monitor.clone() {
+            info!("Starting chain split monitor...");
+            let zelf = Arc::clone(&arc);
+            tokio::spawn(async move {
+                let mut interval = interval(Duration::from_secs(SPLIT_MONITOR_DELAY));
+                loop {
+                    interval.tick().await;
+                    monitor.check(&zelf).await;
+                }
+            });
+        }
+
         Ok(arc)
     }
 
@@ -281,6 +618,14 @@ impl<S: Storage> Blockchain<S> {
             }
         }
 
+        #[cfg(feature = "grpc")]
+        {
+            let mut grpc = self.grpc.write().await;
+            if let Some(grpc) = grpc.take() {
+                grpc.stop().await;
+            }
+        }
+
         {
             let mut storage = self.storage.write().await;
             if let Err(e) = storage.stop().await {
@@ -300,8 +645,9 @@ impl<S: Storage> Blockchain<S> {
         self.height.store(height, Ordering::SeqCst);
 
         let tips = storage.get_tips().await?;
-        let (_, stable_height) = self.find_common_base(&*storage, &tips).await?;
+        let (stable_hash, stable_height) = self.find_common_base(&*storage, &tips).await?;
         self.stable_height.store(stable_height, Ordering::SeqCst);
+        self.stable_topoheight.store(storage.get_topo_height_for_hash(&stable_hash).await?, Ordering::SeqCst);
 
         let difficulty = self.get_difficulty_at_tips(&*storage, &tips.into_iter().collect()).await?;
         self.difficulty.store(difficulty, Ordering::SeqCst);
@@ -323,7 +669,10 @@ impl<S: Storage> Blockchain<S> {
         debug!("Registering XELIS asset: {} at topoheight 0", XELIS_ASSET);
         storage.add_asset(&XELIS_ASSET, AssetData::new(0, COIN_DECIMALS)).await?;
 
-        let genesis_block = if GENESIS_BLOCK.len() != 0 {
+        let genesis_block = if let Some(genesis) = self.genesis.as_ref() {
+            info!("Using genesis block from the genesis file");
+            genesis.to_block(&self.network)?
+        } else if !GENESIS_BLOCK.is_empty() {
             info!("De-serializing genesis block...");
             let genesis = Block::from_hex(GENESIS_BLOCK.to_owned())?;
             if *genesis.get_miner() != *DEV_PUBLIC_KEY {
@@ -371,8 +720,9 @@ impl<S: Storage> Blockchain<S> {
                 current_height = self.get_height();
                 header = self.get_block_template(key.clone()).await?;
             }
-            header.nonce += 1;
-            header.timestamp = get_current_timestamp();
+            header.set_nonce(header.get_nonce() + 1);
+            // never below the template timestamp, in case the clock went backward
+            header.set_timestamp(self.get_adjusted_timestamp().max(header.get_timestamp()));
             hash = header.hash();
         }
 
@@ -384,11 +734,87 @@ impl<S: Storage> Blockchain<S> {
         Ok(())
     }
 
+    // number of blocks kept below the top if the auto prune mode is enabled
+    pub fn get_auto_prune_keep_n_blocks(&self) -> Option<u64> {
+        self.auto_prune_keep_n_blocks
+    }
+
+    // orphaned blocks are deleted once N blocks below the top height, none if they are kept
+    pub fn get_orphaned_blocks_retention(&self) -> Option<u64> {
+        self.orphaned_blocks_retention
+    }
+
     // check if user has accepted to do fast sync mode
     pub fn is_fast_sync_mode_allowed(&self) -> bool {
         self.allow_fast_sync_mode
     }
 
+    // write a point-in-time copy of the storage in a new directory, used for backups or to bootstrap another node
+    // blocks are not added during the copy to keep it consistent, sync resumes once done
+    // returns the topoheight of the snapshot
+    pub async fn create_snapshot(&self, path: &str) -> Result<u64, BlockchainError> {
+        if Path::new(path).exists() {
+            return Err(BlockchainError::SnapshotDirectoryExists(path.to_owned()))
+        }
+
+        let storage = self.storage.read().await;
+        let topoheight = self.get_topo_height();
+        info!("Creating snapshot at topoheight {} in {}", topoheight, path);
+        let start = Instant::now();
+        storage.create_database_snapshot(path).await?;
+        info!("Snapshot created in {}ms", start.elapsed().as_millis());
+
+        Ok(topoheight)
+    }
+
+    // XELIS balance and nonce of all the accounts at a topoheight (the current one by default)
+    // used to generate the genesis of a reset network
+    pub async fn export_state(&self, topoheight: Option<u64>) -> Result<StateExport, BlockchainError> {
+        let storage = self.storage.read().await;
+        let current_topoheight = self.get_topo_height();
+        let topoheight = topoheight.unwrap_or(current_topoheight);
+        let pruned_topoheight = storage.get_pruned_topoheight()?.unwrap_or(0);
+        if topoheight > current_topoheight || topoheight < pruned_topoheight {
+            return Err(BlockchainError::InvalidStateTopoheight(topoheight, pruned_topoheight, current_topoheight))
+        }
+
+        info!("Exporting state at topoheight {}", topoheight);
+        let mainnet = self.network.is_mainnet();
+        let mut accounts = Vec::new();
+        let mut skip = 0;
+        loop {
+            let keys = storage.get_partial_keys(STATE_EXPORT_BATCH_SIZE, skip, 0, topoheight).await?;
+            skip += keys.len();
+            for key in &keys {
+                let balance = match storage.get_balance_at_maximum_topoheight(key, &XELIS_ASSET, topoheight).await? {
+                    Some((_, version)) => version.get_balance(),
+                    None => 0
+                };
+                let nonce = match storage.get_nonce_at_maximum_topoheight(key, topoheight).await? {
+                    Some((_, version)) => version.get_nonce(),
+                    None => 0
+                };
+
+                accounts.push(AccountState {
+                    address: key.to_address(mainnet),
+                    balance,
+                    nonce
+                });
+            }
+
+            if keys.len() < STATE_EXPORT_BATCH_SIZE {
+                break;
+            }
+        }
+
+        Ok(StateExport {
+            network: self.network,
+            topoheight,
+            hash: storage.get_hash_at_topo_height(topoheight).await?,
+            accounts
+        })
+    }
+
     pub async fn prune_until_topoheight(&self, topoheight: u64) -> Result<u64, BlockchainError> {
         let mut storage = self.storage.write().await;
         self.prune_until_topoheight_for_storage(topoheight, &mut storage).await
@@ -459,12 +885,151 @@ impl<S: Storage> Blockchain<S> {
         Ok(0)
     }
 
+    // the PoW seed is only used by the memory-hard algorithms, none is scheduled yet on this network
+    pub fn is_pow_seed_used(&self) -> bool {
+        hard_fork::is_pow_seed_used(&self.network)
+    }
+
+    // PoW seeds of the epoch used by the next block and of the next epoch
+    // the seed is the hash of the stable block at the seed topoheight, so it is the same on every node
+    // the next seed is only set once its block is stable, so it can't change anymore
+    pub async fn get_pow_seed(&self, storage: &S) -> Result<GetPowSeedResult, BlockchainError> {
+        let topoheight = self.get_topo_height() + 1;
+        let height = self.get_height() + 1;
+        let (seed_topoheight, next_seed_known) = get_pow_seed_state(topoheight, self.get_stable_topoheight());
+        let next_seed_topoheight = pow::get_next_seed_topoheight(topoheight);
+        let next_seed = if next_seed_known {
+            Some(storage.get_hash_at_topo_height(next_seed_topoheight).await?)
+        } else {
+            None
+        };
+        let next_seed_activation_topoheight = pow::get_seed_activation_topoheight(next_seed_topoheight);
+        // hard forks are scheduled by height, each block of the main chain increases both
+        let next_seed_activation_height = height + (next_seed_activation_topoheight - topoheight);
+
+        Ok(GetPowSeedResult {
+            pow_algorithm: hard_fork::get_hard_fork_at_height(&self.network, height).pow_algorithm,
+            seed_topoheight,
+            seed: storage.get_hash_at_topo_height(seed_topoheight).await?,
+            next_seed_topoheight,
+            next_seed,
+            next_seed_activation_topoheight,
+            next_pow_algorithm: hard_fork::get_hard_fork_at_height(&self.network, next_seed_activation_height).pow_algorithm
+        })
+    }
+
+    // build the BalanceChanged events of an ordered block, sorted by account and asset
+    fn get_balance_changed_events(&self, block_hash: &Hash, topoheight: u64, mut changes: Vec<(&PublicKey, &Hash, VersionedBalance)>) -> Vec<(DaemonEvent<'static>, PublicKey)> {
+        changes.sort_by(|(a_key, a_asset, _), (b_key, b_asset, _)| (a_key.as_bytes(), a_asset).cmp(&(b_key.as_bytes(), b_asset)));
+        let mainnet = self.network.is_mainnet();
+        changes.into_iter().map(|(key, asset, balance)| (DaemonEvent::BalanceChanged(BalanceChangedEvent {
+            block_hash: Cow::Owned(block_hash.clone()),
+            address: key.to_address(mainnet),
+            asset: Cow::Owned(asset.clone()),
+            topoheight,
+            balance: balance.get_balance(),
+            previous_topoheight: balance.get_previous_topoheight()
+        }), key.clone())).collect()
+    }
+
+    // rebuild the events notified when the block at this topoheight was ordered, in the same order
+    // only BlockOrdered, TransactionExecuted and BalanceChanged events can be replayed
+    // each event is returned with the accounts involved if it concerns accounts
+    pub async fn get_events_at_topoheight(&self, storage: &S, topoheight: u64, tracked: &HashSet<NotifyEvent>) -> Result<Vec<(DaemonEvent<'static>, Option<Vec<PublicKey>>)>, BlockchainError> {
+        let hash = storage.get_hash_at_topo_height(topoheight).await?;
+        let block = storage.get_block(&hash).await?;
+        let mut events = Vec::new();
+
+        // accounts which may have a new version of their balance at this topoheight
+        let mut accounts: HashSet<(&PublicKey, &Hash)> = HashSet::new();
+        for (tx, tx_hash) in block.get_transactions().iter().zip(block.get_txs_hashes()) {
+            if !storage.is_tx_executed_in_block(tx_hash, &hash)? {
+                continue;
+            }
+
+            if tracked.contains(&NotifyEvent::TransactionExecuted) {
+                events.push((DaemonEvent::TransactionExecuted(TransactionExecutedEvent {
+                    tx_hash: Cow::Owned(tx_hash.clone()),
+                    block_hash: Cow::Owned(hash.clone()),
+                    topoheight,
+                }), Some(tx.get_involved_keys())));
+            }
+
+            accounts.insert((tx.get_owner(), &XELIS_ASSET));
+            if let Some(sponsor) = tx.get_sponsor() {
+                accounts.insert((sponsor.get_key(), &XELIS_ASSET));
+            }
+
+            match tx.get_data() {
+                TransactionType::Burn { asset, .. } => {
+                    accounts.insert((tx.get_owner(), asset));
+                },
+                TransactionType::Transfer(outputs) => {
+                    for output in outputs {
+                        accounts.insert((tx.get_owner(), &output.asset));
+                        accounts.insert((&output.to, &output.asset));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        if tracked.contains(&NotifyEvent::BalanceChanged) {
+            accounts.insert((block.get_miner(), &XELIS_ASSET));
+            if get_block_dev_fee(block.get_height()) != 0 {
+                accounts.insert((&DEV_PUBLIC_KEY, &XELIS_ASSET));
+            }
+
+            if topoheight == 0 {
+                if let Some(genesis) = self.genesis.as_ref() {
+                    for allocation in &genesis.allocations {
+                        accounts.insert((allocation.address.get_public_key(), &XELIS_ASSET));
+                    }
+                }
+            }
+
+            // a version at this exact topoheight can only be written by this block
+            let mut changes = Vec::new();
+            for (key, asset) in accounts {
+                if storage.has_balance_at_exact_topoheight(key, asset, topoheight).await? {
+                    changes.push((key, asset, storage.get_balance_at_exact_topoheight(key, asset, topoheight).await?));
+                }
+            }
+
+            for (event, key) in self.get_balance_changed_events(&hash, topoheight, changes) {
+                events.push((event, Some(vec![key])));
+            }
+        }
+
+        if tracked.contains(&NotifyEvent::BlockOrdered) {
+            let block_type = get_block_type_for_block(self, storage, &hash).await.unwrap_or(BlockType::Normal);
+            events.push((DaemonEvent::BlockOrdered(BlockOrderedEvent {
+                block_hash: Cow::Owned(hash),
+                block_type,
+                topoheight,
+            }), None));
+        }
+
+        Ok(events)
+    }
+
+    // returns the topoheight of the block at the stable height
+    // blocks at or below it can't be reordered anymore
+    pub fn get_stable_topoheight(&self) -> u64 {
+        self.stable_topoheight.load(Ordering::Acquire)
+    }
+
     // returns the highest (unstable) height on the chain
     pub fn get_height(&self) -> u64 {
         self.height.load(Ordering::Acquire)
     }
 
     // returns the highest topological height
+    // receiver notified after each new block or rewind of the chain
+    pub fn subscribe_topoheight(&self) -> watch::Receiver<u64> {
+        self.topoheight_notifier.subscribe()
+    }
+
     pub fn get_topo_height(&self) -> u64 {
         self.topoheight.load(Ordering::Acquire)
     }
@@ -473,14 +1038,91 @@ impl<S: Storage> Blockchain<S> {
         self.stable_height.load(Ordering::Acquire)
     }
 
+    // heuristic of the probability for a block at this height to be reordered by a reorganization of the DAG
+    // each height above it halves the probability, until it is stable
+    pub fn get_reorg_probability_at_height(&self, height: u64) -> f64 {
+        if height <= self.get_stable_height() {
+            return 0f64
+        }
+
+        let depth = self.get_height().saturating_sub(height).min(STABLE_LIMIT);
+        0.5f64.powi(depth as i32 + 1)
+    }
+
+    pub fn get_template_config(&self) -> &TemplateConfig {
+        &self.template_config
+    }
+
+    pub fn get_max_pending_txs_per_account(&self) -> usize {
+        self.max_pending_txs_per_account
+    }
+
+    pub fn get_genesis_hash(&self) -> &Hash {
+        &self.genesis_hash
+    }
+
+    // difficulty of the first blocks, set by the genesis file of a custom network
+    pub fn get_initial_difficulty(&self) -> Difficulty {
+        self.genesis.as_ref().map_or(MINIMUM_DIFFICULTY, |genesis| genesis.difficulty)
+    }
+
+    // coins allocated to accounts by the genesis file of a custom network
+    pub fn get_genesis_allocated_supply(&self) -> u64 {
+        self.genesis.as_ref().map_or(0, |genesis| genesis.allocations.iter().map(|allocation| allocation.amount).sum())
+    }
+
     pub fn get_network(&self) -> &Network {
         &self.network
     }
 
+    pub fn get_node_key(&self) -> &KeyPair {
+        &self.node_key
+    }
+
+    pub fn get_split_monitor(&self) -> Option<&Arc<SplitMonitor>> {
+        self.split_monitor.as_ref()
+    }
+
+    pub fn get_time_sync(&self) -> &TimeSync {
+        &self.time_sync
+    }
+
+    // returns the current timestamp in milliseconds adjusted with the peers time offsets
+    pub fn get_adjusted_timestamp(&self) -> u128 {
+        self.time_sync.get_adjusted_timestamp()
+    }
+
+    // returns the current timestamp in milliseconds of the local clock
+    // blocks in the future are rejected using it, so the peers can't shift our consensus time
+    pub fn get_local_timestamp(&self) -> u128 {
+        self.time_sync.get_clock().now()
+    }
+
     pub async fn get_supply(&self) -> Result<u64, BlockchainError> {
         self.storage.read().await.get_supply_at_topo_height(self.get_topo_height()).await
     }
 
+    pub fn is_smart_contracts_enabled_at_height(&self, height: u64) -> bool {
+        hard_fork::get_hard_fork_at_height(&self.network, height).smart_contracts
+    }
+
+    pub fn is_name_service_enabled_at_height(&self, height: u64) -> bool {
+        hard_fork::get_hard_fork_at_height(&self.network, height).name_service
+    }
+
+    // sponsored and expiring transactions are allowed by the max tx version
+    pub fn get_max_tx_version_at_height(&self, height: u64) -> u8 {
+        hard_fork::get_hard_fork_at_height(&self.network, height).max_tx_version
+    }
+
+    pub fn get_difficulty_algorithm_at_height(&self, height: u64) -> DifficultyAlgorithm {
+        hard_fork::get_hard_fork_at_height(&self.network, height).difficulty_algorithm
+    }
+
+    pub async fn get_burned_supply(&self) -> Result<u64, BlockchainError> {
+        self.storage.read().await.get_burned_supply_at_topo_height(self.get_topo_height()).await
+    }
+
     pub async fn get_mempool_size(&self) -> usize {
         self.mempool.read().await.size()
     }
@@ -840,7 +1482,7 @@ impl<S: Storage> Blockchain<S> {
             let block_tips = storage.get_past_blocks_for_block_hash(hash).await?;
             // only the genesis block can have 0 tips, returns its hash
             if block_tips.len() == 0 {
-                let result = vec![GENESIS_BLOCK_HASH.clone()];
+                let result = vec![self.genesis_hash.clone()];
                 cache.put((hash.clone(), base.clone(), base_height), result.clone());
                 return Ok(result)
             }
@@ -896,6 +1538,62 @@ impl<S: Storage> Blockchain<S> {
         Ok(best_difficulty * 91 / 100 < block_difficulty)
     }
 
+    // inputs of the fork choice: the best tip used to order the DAG
+    // and the tips selected for the next block template with the same rules as `get_block_template_for_storage`
+    pub async fn get_tips_info(&self, storage: &S) -> Result<GetTipsInfoResult, BlockchainError> {
+        let tips = storage.get_tips().await?;
+        let (base, base_height) = self.find_common_base(storage, &tips).await?;
+        let best_tip = self.find_best_tip(storage, &tips, &base, base_height).await?.clone();
+
+        let candidates = self.get_tip_candidates(storage).await?;
+        let strategy = self.template_config.get_tip_selection();
+        let selections = select_tips(strategy, &candidates);
+        let mut infos = Vec::with_capacity(candidates.len());
+        for (candidate, selection) in candidates.into_iter().zip(selections) {
+            let hash = &candidate.hash;
+            let topoheight = if storage.is_block_topological_ordered(hash).await {
+                Some(storage.get_topo_height_for_hash(hash).await?)
+            } else {
+                None
+            };
+
+            let (_, work_score) = self.find_tip_work_score(storage, hash, &base, base_height).await?;
+            infos.push(TipInfo {
+                height: storage.get_height_for_block_hash(hash).await?,
+                topoheight,
+                difficulty: candidate.difficulty,
+                cumulative_difficulty: candidate.cumulative_difficulty,
+                work_score,
+                selection,
+                hash: candidate.hash
+            });
+        }
+
+        Ok(GetTipsInfoResult {
+            base,
+            base_height,
+            best_tip,
+            strategy,
+            tips: infos
+        })
+    }
+
+    // tips that can be referenced by the next block template, sorted like `sort_tips`
+    async fn get_tip_candidates(&self, storage: &S) -> Result<Vec<TipCandidate>, BlockchainError> {
+        let tips = storage.get_tips().await?;
+        let sorted_tips = blockdag::sort_tips(storage, &tips.into_iter().collect()).await?;
+        let mut candidates = Vec::with_capacity(sorted_tips.len());
+        for hash in sorted_tips {
+            candidates.push(TipCandidate {
+                difficulty: storage.get_difficulty_for_block_hash(&hash).await?,
+                cumulative_difficulty: storage.get_cumulative_difficulty_for_block_hash(&hash).await?,
+                timestamp: storage.get_timestamp_for_block_hash(&hash).await?,
+                hash
+            });
+        }
+        Ok(candidates)
+    }
+
     // TODO generic tips type
     pub async fn get_difficulty_at_tips<D: DifficultyProvider>(&self, provider: &D, tips: &Vec<Hash>) -> Result<Difficulty, BlockchainError> {
         if tips.len() == 0 { // Genesis difficulty
@@ -904,19 +1602,53 @@ impl<S: Storage> Blockchain<S> {
 
         let height = blockdag::calculate_height_at_tips(provider, tips).await?;
         if height < 3 {
-            return Ok(MINIMUM_DIFFICULTY)
+            return Ok(self.get_initial_difficulty())
         }
 
         let best_tip = blockdag::find_best_tip_by_cumulative_difficulty(provider, tips).await?;
-        let biggest_difficulty = provider.get_difficulty_for_block_hash(best_tip).await?;
-        let best_tip_timestamp = provider.get_timestamp_for_block_hash(best_tip).await?;
+        let difficulty = match self.get_difficulty_algorithm_at_height(height) {
+            DifficultyAlgorithm::Legacy => {
+                let biggest_difficulty = provider.get_difficulty_for_block_hash(best_tip).await?;
+                let best_tip_timestamp = provider.get_timestamp_for_block_hash(best_tip).await?;
+
+                let parent_tips = provider.get_past_blocks_for_block_hash(best_tip).await?;
+                let parent_best_tip = blockdag::find_best_tip_by_cumulative_difficulty(provider, &parent_tips).await?;
+                let parent_best_tip_timestamp = provider.get_timestamp_for_block_hash(parent_best_tip).await?;
+
+                calculate_difficulty(parent_best_tip_timestamp, best_tip_timestamp, biggest_difficulty)
+            },
+            DifficultyAlgorithm::Lwma => {
+                let blocks = self.get_difficulty_window(provider, best_tip, hard_fork::get_last_difficulty_reset_height(&self.network, height)).await?;
+                calculate_difficulty_lwma(&blocks)
+            }
+        };
 
-        let parent_tips = provider.get_past_blocks_for_block_hash(best_tip).await?;
-        let parent_best_tip = blockdag::find_best_tip_by_cumulative_difficulty(provider, &parent_tips).await?;
-        let parent_best_tip_timestamp = provider.get_timestamp_for_block_hash(parent_best_tip).await?;
- 
-        let difficulty = calculate_difficulty(parent_best_tip_timestamp, best_tip_timestamp, biggest_difficulty);
-        Ok(difficulty)
+        Ok(hard_fork::get_difficulty_reset_at_height(&self.network, height, difficulty, MINIMUM_DIFFICULTY))
+    }
+
+    // (timestamp, difficulty) of the last blocks of the main chain ending at this tip, ordered from the oldest
+    // the main chain is followed using the best parent of each block, and stops at the minimum height
+    // side blocks are ignored on purpose: the block time targets the height, and a side block shares its height with
+    // a block of the main chain, so counting it would shorten the solve times without the chain moving forward
+    // it also keeps the window to one block per height, instead of a traversal of the DAG of unbounded width
+    async fn get_difficulty_window<D: DifficultyProvider>(&self, provider: &D, tip: &Hash, min_height: u64) -> Result<Vec<(u128, Difficulty)>, BlockchainError> {
+        let mut blocks = Vec::with_capacity(LWMA_WINDOW + 1);
+        let mut hash = tip.clone();
+        loop {
+            blocks.push((provider.get_timestamp_for_block_hash(&hash).await?, provider.get_difficulty_for_block_hash(&hash).await?));
+            if blocks.len() > LWMA_WINDOW || provider.get_height_for_block_hash(&hash).await? <= min_height {
+                break
+            }
+
+            let past_blocks = provider.get_past_blocks_for_block_hash(&hash).await?;
+            if past_blocks.is_empty() {
+                break
+            }
+            hash = blockdag::find_best_tip_by_cumulative_difficulty(provider, &past_blocks).await?.clone();
+        }
+
+        blocks.reverse();
+        Ok(blocks)
     }
 
     // Get the current difficulty target for the next block
@@ -952,6 +1684,15 @@ impl<S: Storage> Blockchain<S> {
         &self.mempool
     }
 
+    // client reference -> hash of the tx submitted with it
+    pub fn get_tx_references(&self) -> &Mutex<LruCache<String, Hash>> {
+        &self.tx_references
+    }
+
+    pub fn get_signature_verifier(&self) -> &SignatureVerifier {
+        &self.signature_verifier
+    }
+
     pub async fn add_tx_to_mempool(&self, tx: Transaction, broadcast: bool) -> Result<(), BlockchainError> {
         let hash = tx.hash();
         self.add_tx_to_mempool_with_hash(tx, hash, broadcast).await
@@ -962,13 +1703,78 @@ impl<S: Storage> Blockchain<S> {
         self.add_tx_to_mempool_with_storage_and_hash(&*storage, Arc::new(tx), hash, broadcast).await
     }
 
-    pub async fn add_tx_to_mempool_with_storage_and_hash<'a>(&'a self, storage: &S, tx: Arc<Transaction>, hash: Hash, broadcast: bool) -> Result<(), BlockchainError> {
+    pub async fn add_tx_to_mempool_with_storage_and_hash(&self, storage: &S, tx: Arc<Transaction>, hash: Hash, broadcast: bool) -> Result<(), BlockchainError> {
+        let owner = tx.get_owner().clone();
+        let nonce = tx.get_nonce();
+        if self.add_tx_to_mempool_or_orphans(storage, tx, hash, broadcast).await? {
+            self.add_orphan_txs_to_mempool(storage, &owner, nonce + 1).await;
+        }
+
+        Ok(())
+    }
+
+    // add all the orphan txs of this owner following the nonce until a gap or an invalid TX
+    async fn add_orphan_txs_to_mempool(&self, storage: &S, key: &PublicKey, mut nonce: u64) {
+        loop {
+            let orphan = self.mempool.write().await.take_orphan_tx(key, nonce);
+            let (hash, tx) = match orphan {
+                Some(orphan) => orphan.consume(),
+                None => break
+            };
+
+            debug!("Adding orphan TX {} with nonce {} to mempool", hash, nonce);
+            match self.add_tx_to_mempool_or_orphans(storage, tx, hash.clone(), true).await {
+                Ok(true) => nonce += 1,
+                Ok(false) => break,
+                Err(e) => {
+                    debug!("Orphan TX {} was rejected: {}", hash, e);
+                    break;
+                }
+            };
+        }
+    }
+
+    // returns false if the TX is waiting for a previous nonce of its owner
+    async fn add_tx_to_mempool_or_orphans(&self, storage: &S, tx: Arc<Transaction>, hash: Hash, broadcast: bool) -> Result<bool, BlockchainError> {
+        // signature is only verified here, the hash of a TX includes its signature
+        // so any TX found in mempool later can skip this verification
+        // it is done before locking the mempool, and is already done if the TX was submitted when received
+        if !self.signature_verifier.verify(&hash, &tx).await {
+            return Err(BlockchainError::InvalidTransactionSignature)
+        }
+
+        // TX with same owner and nonce that will be replaced by this one
+        let mut conflicted_tx = None;
         {
             let mut mempool = self.mempool.write().await;
     
-            if mempool.contains_tx(&hash) {
+            if mempool.contains_tx(&hash) || mempool.contains_orphan_tx(&hash) {
                 return Err(BlockchainError::TxAlreadyInMempool(hash))
             }
+
+            if let TransactionType::CallContract(_) | TransactionType::DeployContract(_) = tx.get_data() {
+                if !self.is_smart_contracts_enabled_at_height(self.get_height() + 1) {
+                    return Err(BlockchainError::TxTypeNotAllowed(hash))
+                }
+            }
+
+            if let TransactionType::RegisterName { .. } = tx.get_data() {
+                if !self.is_name_service_enabled_at_height(self.get_height() + 1) {
+                    return Err(BlockchainError::TxTypeNotAllowed(hash))
+                }
+            }
+
+            let max_version = self.get_max_tx_version_at_height(self.get_height() + 1);
+            if tx.get_version() > max_version {
+                return Err(BlockchainError::InvalidTxVersion(hash, tx.get_version(), max_version))
+            }
+
+            // next block will be above the current topoheight
+            if let Some(expiration) = tx.get_expiration() {
+                if tx.is_expired_at(self.get_topo_height() + 1) {
+                    return Err(BlockchainError::TxExpired(hash, expiration))
+                }
+            }
     
             // check that the TX is not already in blockchain
             if storage.is_tx_executed_in_a_block(&hash)? {
@@ -977,17 +1783,34 @@ impl<S: Storage> Blockchain<S> {
     
             // get the highest nonce for this owner
             let owner = tx.get_owner();
+            let next_nonce = match mempool.get_cached_nonce(owner) {
+                Some(cache) => cache.get_max() + 1,
+                None => if storage.has_nonce(owner).await? {
+                    let (_, version) = storage.get_last_nonce(owner).await?;
+                    version.get_nonce()
+                } else {
+                    0
+                }
+            };
+
+            // keep it until the previous nonces are received
+            if tx.get_nonce() > next_nonce {
+                if tx.get_nonce() - next_nonce > MEMPOOL_MAX_ORPHANS_PER_ACCOUNT as u64 {
+                    return Err(BlockchainError::InvalidTxNonce(hash, tx.get_nonce(), next_nonce, owner.clone()))
+                }
+
+                debug!("TX {} is waiting for nonce {} of {}", hash, next_nonce, owner);
+                mempool.add_orphan_tx(hash, tx)?;
+                return Ok(false)
+            }
+
             // get the highest nonce available
             // if presents, it means we have at least one tx from this owner in mempool
             if let Some(cache) = mempool.get_cached_nonce(owner) {
                 // we accept to delete a tx from mempool if the new one has a higher fee
-                if let Some(hash) = cache.has_tx_with_same_nonce(tx.get_nonce()) {
-                    // TX is in range, we have to delete an existing TX
-                    // check that fees are higher than the future deleted one
-                    let other_tx = mempool.view_tx(hash)?;
-                    if other_tx.get_fee() >= tx.get_fee() {
-                        return Err(BlockchainError::InvalidTxFee(other_tx.get_fee() + 1, tx.get_fee()));
-                    }
+                conflicted_tx = mempool.get_replaced_tx(&tx)?;
+                if conflicted_tx.is_none() && cache.get_txs().len() >= self.max_pending_txs_per_account {
+                    return Err(BlockchainError::TooManyPendingTxs(owner.clone(), self.max_pending_txs_per_account))
                 }
 
                 // check that the nonce is in the range
@@ -1012,12 +1835,31 @@ impl<S: Storage> Blockchain<S> {
             mempool.add_tx(hash.clone(), tx.clone())?;
         }
 
+        if let Some(conflicted_tx) = conflicted_tx {
+            if let Some(rpc) = self.rpc.read().await.as_ref() {
+                if rpc.is_event_tracked(&NotifyEvent::TransactionConflicted).await {
+                    rpc.notify_clients(&get_tx_conflicted_event(conflicted_tx, Some(hash.clone()), &tx)).await;
+                }
+            }
+        }
+
         if broadcast {
             // P2p broadcast to others peers
             if let Some(p2p) = self.p2p.read().await.as_ref() {
                 p2p.broadcast_tx_hash(hash.clone()).await;
             }
 
+            // stream it to the gRPC subscribers
+            #[cfg(feature = "grpc")]
+            if let Some(grpc) = self.grpc.read().await.as_ref() {
+                if grpc.has_transaction_subscribers() {
+                    match grpc::build_transaction(storage, &tx, hash.clone(), true, Some(get_current_time())) {
+                        Ok(transaction) => grpc.notify_new_transaction(transaction),
+                        Err(e) => debug!("Error while building transaction for gRPC: {}", e)
+                    };
+                }
+            }
+
             // broadcast to websocket this tx
             if let Some(rpc) = self.rpc.read().await.as_ref() {
                 // Notify miners if getwork is enabled
@@ -1028,27 +1870,24 @@ impl<S: Storage> Blockchain<S> {
                 }
 
                 if rpc.is_event_tracked(&NotifyEvent::TransactionAddedInMempool).await {
-                    let data: TransactionResponse<'_, Arc<Transaction>> = TransactionResponse {
+                    let event = DaemonEvent::TransactionAddedInMempool(TransactionResponse {
                         blocks: None,
                         executed_in_block: None,
                         in_mempool: true,
                         first_seen: Some(get_current_time()),
                         data: DataHash { hash: Cow::Owned(hash), data: Cow::Borrowed(&tx) }
-                    };
-
-                    if let Err(e) = rpc.notify_clients(&NotifyEvent::TransactionAddedInMempool, json!(data)).await {
-                        debug!("Error while broadcasting event TransactionAddedInMempool to websocket: {}", e);
-                    }
+                    });
+                    rpc.notify_clients_for_keys(&event, &tx.get_involved_keys()).await;
                 }
             }
         }
         
-        Ok(())
+        Ok(true)
     }
 
-    // this will be used in future for hard fork versions
-    pub fn get_version_at_height(&self, _height: u64) -> u8 {
-        0
+    // get the block version to use based on the hard forks table
+    pub fn get_version_at_height(&self, height: u64) -> u8 {
+        hard_fork::get_version_at_height(&self.network, height)
     }
 
     pub async fn get_block_template(&self, address: PublicKey) -> Result<BlockHeader, BlockchainError> {
@@ -1093,91 +1932,138 @@ impl<S: Storage> Blockchain<S> {
 
     pub async fn get_block_template_for_storage(&self, storage: &S, address: PublicKey) -> Result<BlockHeader, BlockchainError> {
         let extra_nonce: [u8; EXTRA_NONCE_SIZE] = rand::thread_rng().gen::<[u8; EXTRA_NONCE_SIZE]>(); // generate random bytes
-        let tips_set = storage.get_tips().await?;
-        let mut tips = Vec::with_capacity(tips_set.len());
-        for hash in tips_set {
-            tips.push(hash);
+        let candidates = self.get_tip_candidates(storage).await?;
+        let selections = select_tips(self.template_config.get_tip_selection(), &candidates);
+        let mut sorted_tips = Vec::with_capacity(TIPS_LIMIT);
+        for (candidate, selection) in candidates.into_iter().zip(selections) {
+            if selection == TipSelection::Selected {
+                sorted_tips.push(candidate.hash);
+            } else {
+                debug!("Tip {} is not selected for this block template: {:?}", candidate.hash, selection);
+            }
         }
 
-        if tips.len() > 1 {
-            let best_tip = blockdag::find_best_tip_by_cumulative_difficulty(storage, &tips).await?.clone();
-            debug!("Best tip selected for this block template is {}", best_tip);
-            let mut selected_tips = Vec::with_capacity(tips.len());
-            for hash in tips {
-                if best_tip != hash {
-                    if !self.validate_tips(storage, &best_tip, &hash).await? {
-                        debug!("Tip {} is invalid, not selecting it because difficulty can't be less than 91% of {}", hash, best_tip);
-                        continue;
-                    }
-                }
-                selected_tips.push(hash);
+        // use the network adjusted time, but never go below the timestamp of a tip
+        let mut timestamp = self.get_adjusted_timestamp();
+        for hash in &sorted_tips {
+            let tip_timestamp = storage.get_timestamp_for_block_hash(hash).await?;
+            if tip_timestamp > timestamp {
+                timestamp = tip_timestamp;
             }
-            tips = selected_tips;
         }
 
-        let mut sorted_tips = blockdag::sort_tips(storage, &tips).await?;
-        if sorted_tips.len() > TIPS_LIMIT {
-            let dropped_tips = sorted_tips.drain(TIPS_LIMIT..); // keep only first 3 heavier tips
-            for hash in dropped_tips {
-                debug!("Dropping tip {} because it is not in the first 3 heavier tips", hash);
+        let height = blockdag::calculate_height_at_tips(storage, &sorted_tips).await?;
+        // the timestamp must also be above the median time past once the hard fork is active
+        if hard_fork::get_hard_fork_at_height(&self.network, height).median_time_past {
+            let median_time_past = self.get_median_time_past_for_tips(storage, &sorted_tips).await?;
+            if median_time_past >= timestamp {
+                timestamp = median_time_past + 1;
             }
         }
 
-        let height = blockdag::calculate_height_at_tips(storage, &sorted_tips).await?;
-        let mut block = BlockHeader::new(self.get_version_at_height(height), height, get_current_timestamp(), sorted_tips, extra_nonce, address, Vec::new());
+        let mut block = BlockHeader::new(self.get_version_at_height(height), height, timestamp, sorted_tips, extra_nonce, address, Vec::new());
 
+        let topoheight = self.get_topo_height();
         trace!("Locking mempool for building block template");
         let mempool = self.mempool.read().await;
         trace!("Mempool locked for building block template");
 
-        // get all availables txs and sort them by fee per size
-        let mut txs = mempool.get_txs()
+        // get all availables txs and sort them using the template policy
+        let max_block_size = hard_fork::get_hard_fork_at_height(&self.network, height).max_block_size;
+        let mut builder = TemplateBuilder::new(&self.template_config, max_block_size, block.size());
+        let txs = builder.sort_candidates(mempool.get_txs()
             .iter()
-            .map(|(hash, tx)| (tx.get_fee(), tx.get_size(), hash, tx.get_tx()))
-            .collect::<Vec<_>>();
-        txs.sort_by(|(a_fee, a_size, _, a_tx), (b_fee, b_size, _, b_tx)| {
-            // If its the same sender, check the nonce
-            if a_tx.get_owner() == b_tx.get_owner() {
-                // Increasing nonces (lower first)
-                let cmp = a_tx.get_nonce().cmp(&b_tx.get_nonce());
-                // If its not equal nonce, returns it
-                if cmp != std::cmp::Ordering::Equal {
-                    return cmp
-                }
-            }
-
-            let a = a_fee * *a_size as u64;
-            let b = b_fee * *b_size as u64;
-            // Decreasing fees (higher first)
-            b.cmp(&a)
-        });
-
-        let mut total_txs_size = 0;
+            .map(|(hash, tx)| TxCandidate { hash, tx: tx.get_tx(), size: tx.get_size() })
+        );
+
         let mut nonces: HashMap<&PublicKey, u64> = HashMap::new();
-        let mut block_size = block.size();
         {
             let mut balances = HashMap::new();
-            'main: for (fee, size, hash, tx) in txs {
-                if block_size + total_txs_size + size >= MAX_BLOCK_SIZE {
+            'main: for TxCandidate { hash, tx, size } in txs {
+                if !builder.can_fit(size) {
                     break 'main;
                 }
 
+                if tx.is_expired_at(topoheight + 1) {
+                    debug!("TX {} is expired, not selecting it for mining", hash);
+                    continue;
+                }
+
                 // Check if the TX is valid for this potential block
                 trace!("Checking TX {} with nonce {}", hash, tx.get_nonce());
                 if let Err(e) = self.verify_transaction_with_hash(&storage, tx, hash, &mut balances, Some(&mut nonces), false).await {
                     warn!("TX {} is not valid for mining: {}", hash, e);
                 } else {
-                    trace!("Selected {} (nonce: {}, fees: {}) for mining", hash, tx.get_nonce(), format_xelis(fee));
+                    trace!("Selected {} (nonce: {}, fees: {}) for mining", hash, tx.get_nonce(), format_xelis(tx.get_fee()));
                     // TODO no clone
-                    block.txs_hashes.push(hash.as_ref().clone());
-                    block_size += HASH_SIZE; // add the hash size
-                    total_txs_size += size;
+                    block.add_txs_hash(hash.as_ref().clone());
+                    builder.add(size, HASH_SIZE); // add the hash size
                 }
             }
         }
         Ok(block)
     }
 
+    // cheap checks of a header submitted by a miner, done before searching its TXs to build the block
+    // same rules as when the block is added, which verifies it again fully
+    pub async fn pre_verify_block_header(&self, header: &BlockHeader) -> Result<(), BlockchainError> {
+        let storage = self.storage.read().await;
+        if storage.has_block(&header.hash()).await? {
+            return Err(BlockchainError::AlreadyInChain)
+        }
+
+        let local_timestamp = self.get_local_timestamp();
+        if header.get_timestamp() > local_timestamp + TIMESTAMP_IN_FUTURE_LIMIT {
+            return Err(BlockchainError::TimestampIsInFuture(local_timestamp, header.get_timestamp()))
+        }
+
+        let tips = header.get_tips();
+        if tips.is_empty() {
+            return Err(BlockchainError::ExpectedTips)
+        }
+
+        if tips.len() > TIPS_LIMIT {
+            return Err(BlockchainError::InvalidTips)
+        }
+
+        for tip in tips {
+            if !storage.has_block(tip).await? {
+                return Err(BlockchainError::InvalidTips)
+            }
+
+            if storage.get_timestamp_for_block_hash(tip).await? > header.get_timestamp() {
+                return Err(BlockchainError::TimestampIsLessThanParent(header.get_timestamp()))
+            }
+        }
+
+        let hard_fork = hard_fork::get_hard_fork_at_height(&self.network, header.get_height());
+        if !hard_fork::is_version_allowed_at_height(&self.network, header.get_height(), header.get_version()) {
+            return Err(BlockchainError::InvalidBlockVersion(hard_fork.version, header.get_version()))
+        }
+        verify_aux_commitment(header)?;
+
+        if hard_fork.median_time_past {
+            let median_time_past = self.get_median_time_past_for_tips(&*storage, tips).await?;
+            time_sync::verify_median_time_past(header.get_timestamp(), median_time_past)?;
+        }
+
+        let height = blockdag::calculate_height_at_tips(&*storage, tips).await?;
+        if height != header.get_height() {
+            return Err(BlockchainError::InvalidBlockHeight(height, header.get_height()))
+        }
+
+        // the template has been built on tips which are now too deep in the chain
+        if height < self.get_stable_height() {
+            return Err(BlockchainError::InvalidBlockHeightStableHeight)
+        }
+
+        let pow_hash = match hard_fork.pow_algorithm {
+            PowAlgorithm::Keccak256 => header.get_pow_hash()
+        };
+        self.verify_proof_of_work(&*storage, &pow_hash, tips).await?;
+        Ok(())
+    }
+
     pub async fn build_block_from_header(&self, header: Immutable<BlockHeader>) -> Result<Block, BlockchainError> {
         trace!("Searching TXs for block at height {}", header.get_height());
         let mut transactions: Vec<Immutable<Transaction>> = Vec::with_capacity(header.get_txs_count());
@@ -1206,6 +2092,22 @@ impl<S: Storage> Blockchain<S> {
     }
 
     pub async fn add_new_block_for_storage(&self, storage: &mut S, block: Block, broadcast: bool, mining: bool) -> Result<(), BlockchainError> {
+        let result = self.add_new_block_with_journal(storage, block, broadcast, mining).await;
+        if result.is_err() && storage.is_block_journal_open() {
+            // don't keep the changes already written for this block
+            if let Some(hash) = storage.rollback_block_journal().await? {
+                warn!("Changes of block {} have been rolled back", hash);
+            }
+            self.height.store(storage.get_top_height()?, Ordering::Release);
+            let topoheight = storage.get_top_topoheight()?;
+            self.topoheight.store(topoheight, Ordering::Release);
+            self.topoheight_notifier.send_replace(topoheight);
+        }
+        result
+    }
+
+    // all the changes written to apply the block are journaled, so they are never partially kept
+    async fn add_new_block_with_journal(&self, storage: &mut S, block: Block, broadcast: bool, mining: bool) -> Result<(), BlockchainError> {
         let start = Instant::now();
         let block_hash = block.hash();
         debug!("Add new block {}", block_hash);
@@ -1214,9 +2116,10 @@ impl<S: Storage> Blockchain<S> {
             return Err(BlockchainError::AlreadyInChain)
         }
 
-        if block.get_timestamp() > get_current_timestamp() + TIMESTAMP_IN_FUTURE_LIMIT { // accept 2s in future
+        let local_timestamp = self.get_local_timestamp();
+        if block.get_timestamp() > local_timestamp + TIMESTAMP_IN_FUTURE_LIMIT { // accept 2s in future
             error!("Block timestamp is too much in future!");
-            return Err(BlockchainError::TimestampIsInFuture(get_current_timestamp(), block.get_timestamp()));
+            return Err(BlockchainError::TimestampIsInFuture(local_timestamp, block.get_timestamp()));
         }
 
         let tips_count = block.get_tips().len();
@@ -1232,10 +2135,22 @@ impl<S: Storage> Blockchain<S> {
             return Err(BlockchainError::ExpectedTips)
         }
 
+        // rules to apply based on the block height
+        let hard_fork = hard_fork::get_hard_fork_at_height(&self.network, block.get_height());
+        if !hard_fork::is_version_allowed_at_height(&self.network, block.get_height(), block.get_version()) {
+            error!("Invalid block version {} for block {}, expected {} at height {}", block.get_version(), block_hash, hard_fork.version, block.get_height());
+            return Err(BlockchainError::InvalidBlockVersion(hard_fork.version, block.get_version()))
+        }
+
+        if let Err(e) = verify_aux_commitment(&block) {
+            error!("Invalid auxiliary commitment for block {}: {}", block_hash, e);
+            return Err(e)
+        }
+
         // block contains header and full TXs
-        if block.size() > MAX_BLOCK_SIZE {
-            error!("Block size ({} bytes) is greater than the limit ({} bytes)", block.size(), MAX_BLOCK_SIZE);
-            return Err(BlockchainError::InvalidBlockSize(MAX_BLOCK_SIZE, block.size()));
+        if block.size() > hard_fork.max_block_size {
+            error!("Block size ({} bytes) is greater than the limit ({} bytes)", block.size(), hard_fork.max_block_size);
+            return Err(BlockchainError::InvalidBlockSize(hard_fork.max_block_size, block.size()));
         }
 
         {
@@ -1290,6 +2205,15 @@ impl<S: Storage> Blockchain<S> {
             }
         }
 
+        // block timestamp must be greater than the median time past of its tips
+        if hard_fork.median_time_past {
+            let median_time_past = self.get_median_time_past_for_tips(storage, block.get_tips()).await?;
+            if let Err(e) = time_sync::verify_median_time_past(block.get_timestamp(), median_time_past) {
+                error!("Invalid block timestamp {} for block {}, median time past is {}", block.get_timestamp(), block_hash, median_time_past);
+                return Err(e)
+            }
+        }
+
         if tips_count > 1 {
             let best_tip = blockdag::find_best_tip_by_cumulative_difficulty(storage, block.get_tips()).await?;
             debug!("Best tip selected for this new block is {}", best_tip);
@@ -1304,7 +2228,11 @@ impl<S: Storage> Blockchain<S> {
         }
 
         // verify PoW and get difficulty for this block based on tips
-        let pow_hash = block.get_pow_hash();
+        // Keccak256 doesn't use the PoW seed, a memory-hard algorithm will verify the block
+        // using the dataset of its epoch once its hard fork is scheduled
+        let pow_hash = match hard_fork.pow_algorithm {
+            PowAlgorithm::Keccak256 => block.get_pow_hash()
+        };
         debug!("POW hash: {}", pow_hash);
         let difficulty = self.verify_proof_of_work(storage, &pow_hash, block.get_tips()).await?;
         debug!("PoW is valid for difficulty {}", difficulty);
@@ -1319,6 +2247,21 @@ impl<S: Storage> Blockchain<S> {
 
             let mut cache_account: HashMap<&PublicKey, u64> = HashMap::new();
             let mut cache_tx: HashMap<Hash, bool> = HashMap::new(); // avoid using a TX multiple times
+            // TXs coming from our mempool (like in a block mined by us) already have a verified signature
+            let mempool = self.mempool.read().await;
+            // signatures are the most expensive check, verify them in parallel before the others
+            {
+                let txs: Vec<&Transaction> = block.get_transactions().iter()
+                    .filter(|tx| !mempool.contains_tx(&tx.hash()))
+                    .map(|tx| tx.get_inner())
+                    .collect();
+
+                if !verify_signatures(&txs, self.verification_threads) {
+                    error!("Block {} contains a TX with an invalid signature", block_hash);
+                    return Err(BlockchainError::InvalidTransactionSignature)
+                }
+            }
+
             let mut balances = HashMap::new();
             let mut all_parents_txs: Option<HashSet<Hash>> = None;
             for (tx, hash) in block.get_transactions().iter().zip(block.get_txs_hashes()) {
@@ -1331,6 +2274,35 @@ impl<S: Storage> Blockchain<S> {
 
                 debug!("Verifying TX {}", tx_hash);
 
+                if !self.is_smart_contracts_enabled_at_height(block.get_height()) {
+                    if let TransactionType::CallContract(_) | TransactionType::DeployContract(_) = tx.get_data() {
+                        error!("Block {} contains TX {} using Smart Contracts which are not enabled", block_hash, tx_hash);
+                        return Err(BlockchainError::TxTypeNotAllowed(tx_hash))
+                    }
+                }
+
+                if !self.is_name_service_enabled_at_height(block.get_height()) {
+                    if let TransactionType::RegisterName { .. } = tx.get_data() {
+                        error!("Block {} contains TX {} registering a name which is not enabled", block_hash, tx_hash);
+                        return Err(BlockchainError::TxTypeNotAllowed(tx_hash))
+                    }
+                }
+
+                let max_version = self.get_max_tx_version_at_height(block.get_height());
+                if tx.get_version() > max_version {
+                    error!("Block {} contains TX {} with version {} which is not allowed", block_hash, tx_hash, tx.get_version());
+                    return Err(BlockchainError::InvalidTxVersion(tx_hash, tx.get_version(), max_version))
+                }
+
+                // all the blocks below it are ordered before it, so the topoheight of a block is at least its height
+                // the TX can't be executed in it if it's already expired at this topoheight
+                if let Some(expiration) = tx.get_expiration() {
+                    if tx.is_expired_at(block.get_height()) {
+                        error!("Block {} contains TX {} which expired at topoheight {}", block_hash, tx_hash, expiration);
+                        return Err(BlockchainError::TxExpired(tx_hash, expiration))
+                    }
+                }
+
                 // block can't contains the same tx and should have tx hash in block header
                 if cache_tx.contains_key(&tx_hash) {
                     error!("Block cannot contains the same TX {}", tx_hash);
@@ -1397,6 +2369,7 @@ impl<S: Storage> Blockchain<S> {
         let (block, txs) = block.split();
         let block = block.to_arc();
         debug!("Saving block {} on disk", block_hash);
+        storage.start_block_journal(&block_hash).await?;
         // Add block to chain
         storage.save_block(block.clone(), &txs, difficulty, block_hash.clone()).await?;
 
@@ -1444,10 +2417,13 @@ impl<S: Storage> Blockchain<S> {
         
         // track all changes in nonces to clean mempool from invalid txs stuck
         let mut nonces: HashMap<PublicKey, u64> = HashMap::new();
-        // track all events to notify websocket
-        let mut events: HashMap<NotifyEvent, Vec<Value>> = HashMap::new();
+        // track all events to notify websocket, in the order they happened, with the accounts involved
+        let mut events: Vec<(DaemonEvent<'static>, Option<Vec<PublicKey>>)> = Vec::new();
+        // txs which are not executed anymore after the DAG re-order
+        let mut orphaned_txs = OrphanedTxs::default();
 
         let mut current_topoheight = self.get_topo_height();
+        let previous_topoheight = current_topoheight;
         // order the DAG (up to TOP_HEIGHT - STABLE_LIMIT)
         let mut highest_topo = 0;
         {
@@ -1456,6 +2432,7 @@ impl<S: Storage> Blockchain<S> {
             // detect which part of DAG reorg stay, for other part, undo all executed txs
             debug!("Detecting stable point of DAG and cleaning txs above it");
             {
+                let mut contracts_cleaned = false;
                 let mut topoheight = base_topo_height;
                 while topoheight <= current_topoheight {
                     let hash_at_topo = storage.get_hash_at_topo_height(topoheight).await?;
@@ -1477,6 +2454,11 @@ impl<S: Storage> Blockchain<S> {
                     }
 
                     trace!("Cleaning transactions executions at topo height {} (block {})", topoheight, hash_at_topo);
+                    if !contracts_cleaned {
+                        // all contracts deployed and their data written from this topoheight will be executed again
+                        storage.delete_contracts_above_topoheight(topoheight.saturating_sub(1)).await?;
+                        contracts_cleaned = true;
+                    }
 
                     let block = storage.get_block_header_by_hash(&hash_at_topo).await?;
 
@@ -1485,6 +2467,7 @@ impl<S: Storage> Blockchain<S> {
                         if storage.is_tx_executed_in_block(tx_hash, &hash_at_topo)? {
                             trace!("Removing execution of {}", tx_hash);
                             storage.remove_tx_executed(&tx_hash)?;
+                            orphaned_txs.unexecuted(tx_hash, &hash_at_topo);
                         }
                     }
 
@@ -1519,10 +2502,21 @@ impl<S: Storage> Blockchain<S> {
                 trace!("set block reward to {} at {}", block_reward, highest_topo);
                 storage.set_block_reward_at_topo_height(highest_topo, block_reward)?;
                 
-                let supply = past_supply + block_reward;
+                // coins allocated by the genesis are part of the supply since the beginning
+                let supply = if highest_topo == 0 {
+                    block_reward + self.get_genesis_allocated_supply()
+                } else {
+                    past_supply + block_reward
+                };
                 trace!("set block supply to {} at {}", supply, highest_topo);
                 storage.set_supply_at_topo_height(highest_topo, supply)?;
 
+                let mut burned_supply = if highest_topo == 0 {
+                    0
+                } else {
+                    storage.get_burned_supply_at_topo_height(highest_topo - 1).await?
+                };
+
                 // track all changes in balances
                 let mut balances: HashMap<&PublicKey, HashMap<&Hash, VersionedBalance>> = HashMap::new();
                 let block = storage.get_block(&hash).await?;
@@ -1548,36 +2542,79 @@ impl<S: Storage> Blockchain<S> {
                             if tx.get_nonce() < *nonce {
                                 warn!("Tx {} is a potential double spending, skipping...", tx_hash);
                                 // TX will be orphaned
+                                orphaned_txs.skipped(tx_hash, &hash);
                                 continue;
                             }
                         }
+
+                        // block was ordered above the expiration of the TX, its nonce can be used by another one
+                        if tx.is_expired_at(highest_topo) {
+                            debug!("Tx {} is expired at topoheight {}, skipping...", tx_hash, highest_topo);
+                            orphaned_txs.skipped(tx_hash, &hash);
+                            continue;
+                        }
                         // mark tx as executed
                         trace!("Executing tx {} in block {}", tx_hash, hash);
                         storage.set_tx_executed_in_block(tx_hash, &hash)?;
+                        orphaned_txs.executed(tx_hash);
 
-                        self.execute_transaction(storage, &tx, &mut local_nonces, &mut balances, highest_topo).await?;    
+                        let receipt = self.execute_transaction(storage, tx, tx_hash, &mut local_nonces, &mut balances, highest_topo).await?;
+                        storage.set_transaction_receipt(tx_hash, &receipt)?;    
                         // if the rpc_server is enable, track events
                         if should_track_events.contains(&NotifyEvent::TransactionExecuted) {
-                            let value = json!(TransactionExecutedEvent {
-                                tx_hash: Cow::Borrowed(&tx_hash),
-                                block_hash: Cow::Borrowed(&hash),
+                            let event = DaemonEvent::TransactionExecuted(TransactionExecutedEvent {
+                                tx_hash: Cow::Owned(tx_hash.clone()),
+                                block_hash: Cow::Owned(hash.clone()),
                                 topoheight: highest_topo,
                             });
-                            events.entry(NotifyEvent::TransactionExecuted).or_insert_with(Vec::new).push(value);
+                            events.push((event, Some(tx.get_involved_keys())));
                         }
                         total_fees += tx.get_fee();
+
+                        // coins burned are destroyed forever and removed from the circulating supply
+                        match tx.get_data() {
+                            TransactionType::Burn { asset, amount } if *asset == XELIS_ASSET => {
+                                burned_supply += amount;
+                            },
+                            // the cost of a registration is burned only if it succeeded
+                            TransactionType::RegisterName { duration, .. } if receipt.status == ReceiptStatus::Success => {
+                                burned_supply += get_name_registration_cost(*duration);
+                            },
+                            _ => {}
+                        }
                     }
                 }
 
+                trace!("set burned supply to {} at {}", burned_supply, highest_topo);
+                storage.set_burned_supply_at_topo_height(highest_topo, burned_supply)?;
+
                 // reward the miner
                 self.reward_miner(storage, &block, block_reward, total_fees, &mut balances, highest_topo).await?;
 
+                // credit the allocations of a custom genesis
+                if highest_topo == 0 {
+                    if let Some(genesis) = self.genesis.as_ref() {
+                        for allocation in &genesis.allocations {
+                            self.add_balance(storage, &mut balances, allocation.address.get_public_key(), &XELIS_ASSET, allocation.amount, highest_topo).await?;
+                        }
+
+                        // accounts carried over without coins
+                        for address in &genesis.registrations {
+                            local_nonces.entry(address.get_public_key().clone()).or_insert(0);
+                        }
+                    }
+                }
+
                 // save balances for each topoheight
+                let mut balances_changed = Vec::new();
                 for (key, assets) in balances {
                     for (asset, balance) in assets {
                         trace!("Saving balance {} for {} at topo {}, previous: {:?}", asset, key, highest_topo, balance.get_previous_topoheight());
                         // Save the balance as the latest one
                         storage.set_balance_to(key, asset, highest_topo, &balance).await?;
+                        if should_track_events.contains(&NotifyEvent::BalanceChanged) {
+                            balances_changed.push((key, asset, balance));
+                        }
                     }
 
                     // No nonce update for this key
@@ -1599,13 +2636,17 @@ impl<S: Storage> Blockchain<S> {
                     nonces.insert(key, nonce);
                 }
 
+                for (event, key) in self.get_balance_changed_events(&hash, highest_topo, balances_changed) {
+                    events.push((event, Some(vec![key])));
+                }
+
                 if should_track_events.contains(&NotifyEvent::BlockOrdered) {
-                    let value = json!(BlockOrderedEvent {
-                        block_hash: Cow::Borrowed(&hash),
+                    let event = DaemonEvent::BlockOrdered(BlockOrderedEvent {
+                        block_hash: Cow::Owned(hash.clone()),
                         block_type: get_block_type_for_block(self, &storage, &hash).await.unwrap_or(BlockType::Normal),
                         topoheight: highest_topo,
                     });
-                    events.entry(NotifyEvent::BlockOrdered).or_insert_with(Vec::new).push(value);
+                    events.push((event, None));
                 }
             }
         }
@@ -1648,6 +2689,18 @@ impl<S: Storage> Blockchain<S> {
             current_topoheight = highest_topo;
         }
 
+        storage.store_tips(&tips)?;
+
+        let mut current_height = current_height;
+        if current_height == 0 || block.get_height() > current_height {
+            debug!("storing new top height {}", block.get_height());
+            storage.set_top_height(block.get_height())?;
+            self.height.store(block.get_height(), Ordering::Release);
+            current_height = block.get_height();
+        }
+        // block is fully applied
+        storage.commit_block_journal()?;
+
         // auto prune mode
         if extended {
             if let Some(keep_only) = self.auto_prune_keep_n_blocks {
@@ -1660,17 +2713,39 @@ impl<S: Storage> Blockchain<S> {
                     }
                 }
             }
+
+            // a limited number of blocks is moved each time so an existing database is moved gradually
+            if let Some(depth) = self.cold_store_depth {
+                if current_topoheight > depth {
+                    if let Err(e) = storage.move_blocks_to_cold_store(current_topoheight - depth, COLD_STORE_BLOCKS_PER_BLOCK).await {
+                        warn!("Error while moving blocks to the cold store: {}", e);
+                    }
+                }
+            }
+
+            // a block below the stable height can't be ordered anymore
+            if let Some(retention) = self.orphaned_blocks_retention {
+                if current_height > retention {
+                    if let Err(e) = storage.delete_orphaned_blocks_until_height(current_height - retention, ORPHANED_BLOCKS_HEIGHTS_PER_BLOCK).await {
+                        warn!("Error while deleting orphaned blocks: {}", e);
+                    }
+                }
+            }
         }
 
-        storage.store_tips(&tips)?;
+        if extended && hard_fork::has_hard_fork_at_height(&self.network, block.get_height()) {
+            info!("Hard fork at height {} is now active, block version is {}", block.get_height(), hard_fork.version);
+        }
 
-        let mut current_height = current_height;
-        if current_height == 0 || block.get_height() > current_height {
-            debug!("storing new top height {}", block.get_height());
-            storage.set_top_height(block.get_height())?;
-            self.height.store(block.get_height(), Ordering::Release);
-            current_height = block.get_height();
+        // median time past of the chain can't be in our future, otherwise our clock is late
+        if extended {
+            let tips: Vec<Hash> = tips.iter().cloned().collect();
+            let median_time_past = self.get_median_time_past_for_tips(storage, &tips).await?;
+            if median_time_past > local_timestamp {
+                warn!("Median time past of the chain ({}) is ahead of your local time ({}), please check your system clock!", median_time_past, local_timestamp);
+            }
         }
+
         if storage.is_block_topological_ordered(&block_hash).await {
             let topoheight = storage.get_topo_height_for_hash(&block_hash).await?;
             debug!("Adding new '{}' {} at topoheight {}", block_hash, block, topoheight);
@@ -1680,19 +2755,21 @@ impl<S: Storage> Blockchain<S> {
         }
 
         // update stable height and difficulty in cache
+        let previous_stable_height = self.get_stable_height();
+        let previous_stable_topoheight = self.get_stable_topoheight();
         {
-            let (_, height) = self.find_common_base(&storage, &tips).await?;
+            let (stable_hash, height) = self.find_common_base(storage, &tips).await?;
             if should_track_events.contains(&NotifyEvent::StableHeightChanged) { // detect the change in stable height
-                let previous_stable_height = self.get_stable_height();
                 if height != previous_stable_height {
-                    let value = json!(StableHeightChangedEvent {
+                    let event = DaemonEvent::StableHeightChanged(StableHeightChangedEvent {
                         previous_stable_height,
                         new_stable_height: height
                     });
-                    events.entry(NotifyEvent::StableHeightChanged).or_insert_with(Vec::new).push(value);
+                    events.push((event, None));
                 }
             }
             self.stable_height.store(height, Ordering::SeqCst);
+            self.stable_topoheight.store(storage.get_topo_height_for_hash(&stable_hash).await?, Ordering::SeqCst);
 
             trace!("update difficulty in cache");
             let mut tips_vec = Vec::with_capacity(tips.len());
@@ -1703,8 +2780,52 @@ impl<S: Storage> Blockchain<S> {
             self.difficulty.store(difficulty, Ordering::SeqCst);
         }
 
+        // the seed of the next blocks has changed, the next one is announced or activated
+        if should_track_events.contains(&NotifyEvent::NewPowSeed) && self.is_pow_seed_used()
+            && get_pow_seed_state(previous_topoheight + 1, previous_stable_topoheight) != get_pow_seed_state(current_topoheight + 1, self.get_stable_topoheight()) {
+            match self.get_pow_seed(storage).await {
+                Ok(seed) => events.push((DaemonEvent::NewPowSeed(seed), None)),
+                Err(e) => debug!("Error while retrieving the PoW seed for websocket: {}", e)
+            };
+        }
+
+        if should_track_events.contains(&NotifyEvent::TransactionOrphaned) {
+            for event in orphaned_txs.into_events() {
+                events.push((event, None));
+            }
+        }
+
         // Clean all old txs
-        mempool.clean_up(nonces).await;
+        let deleted_txs = mempool.clean_up(nonces.clone()).await;
+        let orphan_txs = mempool.clean_up_orphans(nonces);
+        let expired_txs = mempool.clean_up_expired(current_topoheight);
+        if !expired_txs.is_empty() {
+            debug!("{} TXs expired in mempool", expired_txs.len());
+            if should_track_events.contains(&NotifyEvent::TransactionExpired) {
+                for (tx_hash, sorted_tx) in expired_txs {
+                    let tx = sorted_tx.get_tx();
+                    let event = DaemonEvent::TransactionExpired(TransactionExpiredEvent {
+                        tx_hash: Cow::Owned(tx_hash.as_ref().clone()),
+                        owner: Cow::Owned(tx.get_owner().clone()),
+                        nonce: tx.get_nonce(),
+                        expiration: tx.get_expiration()
+                    });
+                    events.push((event, Some(vec![tx.get_owner().clone()])));
+                }
+            }
+        }
+        if should_track_events.contains(&NotifyEvent::TransactionConflicted) {
+            for (tx_hash, sorted_tx) in deleted_txs {
+                // TX was included in the chain, nothing to report
+                if storage.is_tx_executed_in_a_block(&tx_hash)? {
+                    continue;
+                }
+
+                let tx = sorted_tx.get_tx();
+                let event = get_tx_conflicted_event(tx_hash.as_ref().clone(), None, tx);
+                events.push((event, Some(vec![tx.get_owner().clone()])));
+            }
+        }
 
         info!("Processed block {} at height {} in {} ms with {} txs", block_hash, block.get_height(), start.elapsed().as_millis(), block.get_txs_count());
 
@@ -1721,6 +2842,19 @@ impl<S: Storage> Blockchain<S> {
             }
         }
 
+        self.topoheight_notifier.send_replace(current_topoheight);
+
+        // stream the new block to the gRPC subscribers
+        #[cfg(feature = "grpc")]
+        if let Some(grpc) = self.grpc.read().await.as_ref() {
+            if grpc.has_block_subscribers() {
+                match grpc::build_block(self, storage, block_hash.clone(), false).await {
+                    Ok(block) => grpc.notify_new_block(block),
+                    Err(e) => debug!("Error while building block for gRPC: {}", e)
+                };
+            }
+        }
+
         // broadcast to websocket new block
         if let Some(rpc) = rpc_server.as_ref() {
             // if we have a getwork server, notify miners
@@ -1736,9 +2870,9 @@ impl<S: Storage> Blockchain<S> {
             // notify websocket clients
             trace!("Notifying websocket clients");
             if should_track_events.contains(&NotifyEvent::NewBlock) {
-                match get_block_response_for_hash(self, storage, block_hash, false).await {
+                match get_block_header_response_for_hash(self, storage, block_hash).await {
                     Ok(response) => {
-                        events.entry(NotifyEvent::NewBlock).or_insert_with(Vec::new).push(response);
+                        events.push((DaemonEvent::NewBlock(response), None));
                     },
                     Err(e) => {
                         debug!("Error while getting block response for websocket: {}", e);
@@ -1749,16 +2883,24 @@ impl<S: Storage> Blockchain<S> {
             let rpc = rpc.clone();
             // don't block mutex/lock more than necessary, we move it in another task
             tokio::spawn(async move {
-                for (event, values) in events {
-                    for value in values {
-                        if let Err(e) = rpc.notify_clients(&event, value).await {
-                            debug!("Error while broadcasting event to websocket: {}", e);
-                        }
-                    }
+                for (event, keys) in events {
+                    match keys {
+                        Some(keys) => rpc.notify_clients_for_keys(&event, &keys).await,
+                        None => rpc.notify_clients(&event).await
+                    };
                 }
             });
         }
 
+        // txs waiting for a nonce used in this block can now be added to the mempool
+        drop(mempool);
+        for orphan in orphan_txs {
+            let (hash, tx) = orphan.consume();
+            if let Err(e) = self.add_tx_to_mempool_with_storage_and_hash(storage, tx, hash.clone(), true).await {
+                debug!("Orphan TX {} was rejected: {}", hash, e);
+            }
+        }
+
         Ok(())
     }
 
@@ -1872,20 +3014,36 @@ impl<S: Storage> Blockchain<S> {
         debug!("New topoheight: {} (diff: {})", new_topoheight, current_topoheight - new_topoheight);
 
         {
+            let mut orphaned_txs = Vec::new();
             for (hash, tx) in txs {
                 debug!("Trying to add TX {} to mempool again", hash);
-                if let Err(e) = self.add_tx_to_mempool_with_storage_and_hash(storage, tx, hash, false).await {
+                if let Err(e) = self.add_tx_to_mempool_with_storage_and_hash(storage, tx, hash.clone(), false).await {
                     debug!("TX rewinded is not compatible anymore: {}", e);
+                    orphaned_txs.push(hash);
+                }
+            }
+
+            if !orphaned_txs.is_empty() {
+                if let Some(rpc) = self.rpc.read().await.as_ref() {
+                    if rpc.is_event_tracked(&NotifyEvent::TransactionOrphaned).await {
+                        for tx_hash in orphaned_txs {
+                            rpc.notify_clients(&DaemonEvent::TransactionOrphaned(TransactionOrphanedEvent {
+                                tx_hash: Cow::Owned(tx_hash),
+                                block_hash: None
+                            })).await;
+                        }
+                    }
                 }
             }
         }
 
         self.height.store(new_height, Ordering::Release);
         self.topoheight.store(new_topoheight, Ordering::Release);
+        self.topoheight_notifier.send_replace(new_topoheight);
         // update stable height
         {
             let tips = storage.get_tips().await?;
-            let (_, height) = self.find_common_base(&storage, &tips).await?;
+            let (stable_hash, height) = self.find_common_base(storage, &tips).await?;
 
             // if we have a RPC server, propagate the StableHeightChanged if necessary
             if let Some(rpc) = self.rpc.read().await.as_ref() {
@@ -1894,19 +3052,17 @@ impl<S: Storage> Blockchain<S> {
                     if rpc.is_event_tracked(&NotifyEvent::StableHeightChanged).await {
                         let rpc = rpc.clone();
                         tokio::spawn(async move {
-                            let event = json!(StableHeightChangedEvent {
+                            let event = DaemonEvent::StableHeightChanged(StableHeightChangedEvent {
                                 previous_stable_height,
                                 new_stable_height: height
                             });
-    
-                            if let Err(e) = rpc.notify_clients(&NotifyEvent::StableHeightChanged, event).await {
-                                debug!("Error while broadcasting event StableHeightChanged to websocket: {}", e);
-                            }
+                            rpc.notify_clients(&event).await;
                         });
                     }
                 }
             }
             self.stable_height.store(height, Ordering::Release);
+            self.stable_topoheight.store(storage.get_topo_height_for_hash(&stable_hash).await?, Ordering::Release);
         }
 
         Ok(new_topoheight)
@@ -1918,15 +3074,24 @@ impl<S: Storage> Blockchain<S> {
     async fn verify_transaction_with_hash<'a>(&self, storage: &S, tx: &'a Transaction, hash: &Hash, balances: &mut HashMap<&'a PublicKey, HashMap<&'a Hash, u64>>, nonces: Option<&mut HashMap<&'a PublicKey, u64>>, skip_nonces: bool) -> Result<(), BlockchainError> {
         trace!("Verify transaction with hash {}", hash);
 
-        if !tx.verify_signature() {
-            return Err(BlockchainError::InvalidTransactionSignature)
+        let size = tx.size();
+        if size > MAX_TX_SIZE {
+            return Err(BlockchainError::TxTooBig(hash.clone(), MAX_TX_SIZE, size))
+        }
+
+        if let Some(sponsor) = tx.get_sponsor() {
+            if sponsor.get_key() == tx.get_owner() {
+                return Err(BlockchainError::InvalidTxSponsor(hash.clone()))
+            }
         }
 
-        let owner_balances: &mut HashMap<&'a Hash, u64> = balances.entry(tx.get_owner()).or_insert_with(HashMap::new);
+        // fees are paid by the sponsor if any
         {
-            let balance = match owner_balances.entry(&XELIS_ASSET) {
+            let payer = tx.get_fee_payer();
+            let payer_balances: &mut HashMap<&'a Hash, u64> = balances.entry(payer).or_default();
+            let balance = match payer_balances.entry(&XELIS_ASSET) {
                 Entry::Vacant(entry) => {
-                    let (_, balance) = storage.get_last_balance(tx.get_owner(), &XELIS_ASSET).await?;
+                    let (_, balance) = storage.get_last_balance(payer, &XELIS_ASSET).await?;
                     entry.insert(balance.get_balance())
                 },
                 Entry::Occupied(entry) => entry.into_mut(),
@@ -1940,6 +3105,7 @@ impl<S: Storage> Blockchain<S> {
             }
         }
 
+        let owner_balances: &mut HashMap<&'a Hash, u64> = balances.entry(tx.get_owner()).or_default();
         match tx.get_data() {
             TransactionType::Transfer(txs) => {
                 if txs.len() == 0 { // don't accept any empty tx
@@ -1947,7 +3113,7 @@ impl<S: Storage> Blockchain<S> {
                 }
 
                 // invalid serde tx
-                if txs.len() > u8::MAX as usize {
+                if txs.len() > MAX_TRANSFERS_PER_TX {
                     return Err(BlockchainError::TooManyOutputInTx(hash.clone()))
                 }
 
@@ -2000,9 +3166,55 @@ impl<S: Storage> Blockchain<S> {
                     return Err(BlockchainError::Overflow)
                 }
             },
-            _ => {
-                // TODO implement SC
-                return Err(BlockchainError::SmartContractTodo)
+            TransactionType::DeployContract(code) => {
+                if let Err(e) = Program::parse(code) {
+                    return Err(BlockchainError::InvalidContractCode(hash.clone(), e))
+                }
+            },
+            TransactionType::CallContract(call) => {
+                if !call.assets.is_empty() {
+                    return Err(BlockchainError::ContractDepositsNotSupported(hash.clone()))
+                }
+
+                if call.params.keys().any(|name| name.len() > MAX_KEY_SIZE) {
+                    return Err(BlockchainError::InvalidContractParam(hash.clone()))
+                }
+
+                // the call can still fail during its execution if the contract isn't deployed anymore after a DAG reorg
+                if !storage.has_contract(&call.contract).await? {
+                    return Err(BlockchainError::ContractNotFound(call.contract.clone()))
+                }
+            },
+            TransactionType::RegisterName { name, duration } => {
+                if !is_valid_name(name) {
+                    return Err(BlockchainError::InvalidName(hash.clone()))
+                }
+
+                if *duration < NAME_MIN_DURATION || *duration > NAME_MAX_DURATION {
+                    return Err(BlockchainError::InvalidNameDuration(hash.clone(), *duration))
+                }
+
+                // the registration can still fail during its execution if another one is executed before it
+                let topoheight = self.get_topo_height();
+                if let Some((_, entry)) = storage.get_name_at_maximum_topoheight(name, topoheight).await? {
+                    if entry.is_active_at(topoheight + 1) && entry.get_owner() != tx.get_owner() {
+                        return Err(BlockchainError::NameAlreadyRegistered(name.clone()))
+                    }
+                }
+
+                let balance = match owner_balances.entry(&XELIS_ASSET) {
+                    Entry::Vacant(entry) => {
+                        let (_, balance) = storage.get_last_balance(tx.get_owner(), &XELIS_ASSET).await?;
+                        entry.insert(balance.get_balance())
+                    },
+                    Entry::Occupied(entry) => entry.into_mut(),
+                };
+                if let Some(value) = balance.checked_sub(get_name_registration_cost(*duration)) {
+                    *balance = value;
+                } else {
+                    warn!("Overflow detected with name registration {}", hash);
+                    return Err(BlockchainError::Overflow)
+                }
             }
         };
 
@@ -2095,29 +3307,98 @@ impl<S: Storage> Blockchain<S> {
         self.add_balance(storage, balances, block.get_miner(), &XELIS_ASSET, block_reward + total_fees, topoheight).await
     }
 
-    async fn execute_transaction<'a>(&self, storage: &mut S, transaction: &'a Transaction, nonces: &mut HashMap<PublicKey, u64>, balances: &mut HashMap<&'a PublicKey, HashMap<&'a Hash, VersionedBalance>>, topoheight: u64) -> Result<(), BlockchainError> {
+    // a failed contract call is still executed: its fees are paid but its changes are discarded
+    // the gas available to the call is limited by the fee paid
+    async fn execute_contract_call(&self, storage: &mut S, call: &SmartContractCall, hash: &Hash, fee: u64, topoheight: u64, receipt: &mut TransactionReceipt) -> Result<(), BlockchainError> {
+        if !storage.has_contract(&call.contract).await? {
+            debug!("Contract {} called by {} is not deployed", call.contract, hash);
+            receipt.status = ReceiptStatus::Failed(format!("Contract {} is not deployed", call.contract));
+            return Ok(())
+        }
+
+        let (_, code) = storage.get_contract(&call.contract).await?;
+        let program = Program::parse(&code).map_err(|e| BlockchainError::InvalidContractCode(call.contract.clone(), e))?;
+
+        // load the current value of all the keys the contract can read
+        let mut values = HashMap::new();
+        for key in program.get_storage_keys() {
+            if let Some((_, value)) = storage.get_contract_data_at_maximum_topoheight(&call.contract, key, topoheight).await? {
+                values.insert(key.clone(), value);
+            }
+        }
+
+        let context = ExecutionContext {
+            params: &call.params,
+            topoheight
+        };
+        let result = contract::execute(&program, &context, &values, contract::get_gas_limit_for_fee(fee));
+        debug!("Contract {} called by {} used {} gas", call.contract, hash, result.gas_used);
+        receipt.gas_used = result.gas_used;
+        if let Some(e) = result.error {
+            debug!("Contract {} called by {} failed: {}", call.contract, hash, e);
+            receipt.status = ReceiptStatus::Failed(e.to_string());
+            return Ok(())
+        }
+
+        receipt.storage_changes = result.changes.len() as u16;
+        receipt.events = result.events;
+        for (key, value) in result.changes {
+            storage.set_contract_data_at_topoheight(&call.contract, &key, topoheight, value).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn execute_transaction<'a>(&self, storage: &mut S, transaction: &'a Transaction, hash: &Hash, nonces: &mut HashMap<PublicKey, u64>, balances: &mut HashMap<&'a PublicKey, HashMap<&'a Hash, VersionedBalance>>, topoheight: u64) -> Result<TransactionReceipt, BlockchainError> {
+        let mut receipt = TransactionReceipt::new(topoheight, transaction.get_fee());
         let mut total_deducted: HashMap<&'a Hash, u64> = HashMap::new();
-        total_deducted.insert(&XELIS_ASSET, transaction.get_fee());
+        match transaction.get_sponsor() {
+            Some(sponsor) => self.sub_balance(storage, balances, sponsor.get_key(), &XELIS_ASSET, transaction.get_fee(), topoheight).await?,
+            None => {
+                total_deducted.insert(&XELIS_ASSET, transaction.get_fee());
+            }
+        };
 
         match transaction.get_data() {
             TransactionType::Burn { asset, amount } => {
                 *total_deducted.entry(asset).or_insert(0) += amount;
             }
             TransactionType::Transfer(txs) => {
+                let mut credited = HashSet::new();
                 for output in txs {
                     // update receiver's account
                     self.add_balance(storage, balances, &output.to, &output.asset, output.amount, topoheight).await?;
                     *total_deducted.entry(&output.asset).or_insert(0) += output.amount;
+                    credited.insert((&output.to, &output.asset));
                 }
+                receipt.balances_credited = credited.len() as u16;
             }
-            _ => {
-                return Err(BlockchainError::SmartContractTodo)
+            TransactionType::DeployContract(code) => {
+                storage.set_contract(hash, topoheight, code).await?;
+            },
+            TransactionType::CallContract(call) => {
+                self.execute_contract_call(storage, call, hash, transaction.get_fee(), topoheight, &mut receipt).await?;
+            },
+            TransactionType::RegisterName { name, duration } => {
+                // a registration fails if the name was taken by another registration executed before
+                let current = storage.get_name_at_maximum_topoheight(name, topoheight).await?;
+                match NameEntry::register(current.as_ref().map(|(_, entry)| entry), transaction.get_owner(), topoheight, *duration) {
+                    Some(entry) => {
+                        storage.set_name_at_topoheight(name, topoheight, &entry).await?;
+                        *total_deducted.entry(&XELIS_ASSET).or_insert(0) += get_name_registration_cost(*duration);
+                    },
+                    None => {
+                        debug!("Name {} registered by {} is already taken", name, hash);
+                        receipt.status = ReceiptStatus::Failed(format!("Name {} is already registered", name));
+                    }
+                }
             }
         };
 
         // now we substract all assets spent from this sender
         for (asset, amount) in total_deducted {
             self.sub_balance(storage, balances, transaction.get_owner(), asset, amount, topoheight).await?;
+            receipt.spent.insert(asset.clone(), amount);
         }
 
         // no need to read from disk, transaction nonce has been verified already
@@ -2132,7 +3413,34 @@ impl<S: Storage> Blockchain<S> {
             nonces.insert(transaction.get_owner().clone(), nonce);
         }
 
-        Ok(())
+        Ok(receipt)
+    }
+
+    // compute the median timestamp of the last N blocks reachable from the tips
+    pub async fn get_median_time_past_for_tips(&self, storage: &S, tips: &Vec<Hash>) -> Result<u128, BlockchainError> {
+        let mut timestamps = Vec::with_capacity(MEDIAN_TIME_PAST_BLOCKS);
+        let mut processed = HashSet::new();
+        let mut queue: VecDeque<Hash> = tips.iter().cloned().collect();
+        while let Some(hash) = queue.pop_front() {
+            if timestamps.len() >= MEDIAN_TIME_PAST_BLOCKS {
+                break;
+            }
+
+            if !processed.insert(hash.clone()) {
+                continue;
+            }
+
+            // past blocks may have been deleted by pruning
+            if !storage.has_block(&hash).await? {
+                continue;
+            }
+
+            timestamps.push(storage.get_timestamp_for_block_hash(&hash).await?);
+            let past_blocks = storage.get_past_blocks_for_block_hash(&hash).await?;
+            queue.extend(past_blocks.iter().cloned());
+        }
+
+        Ok(time_sync::get_median_timestamp(&mut timestamps))
     }
 
     pub async fn get_average_block_time_for_storage(&self, storage: &S) -> Result<u64, BlockchainError> {
@@ -2168,6 +3476,87 @@ impl<S: Storage> Blockchain<S> {
     }
 }
 
+fn get_available_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+// seed topoheight used at this topoheight and if the block of the next seed is stable
+fn get_pow_seed_state(topoheight: u64, stable_topoheight: u64) -> (u64, bool) {
+    (pow::get_seed_topoheight(topoheight), pow::get_next_seed_topoheight(topoheight) <= stable_topoheight)
+}
+
+// event of a TX which can't be executed anymore, replaced by another TX if known
+fn get_tx_conflicted_event(tx_hash: Hash, conflicting_tx_hash: Option<Hash>, tx: &Transaction) -> DaemonEvent<'static> {
+    DaemonEvent::TransactionConflicted(TransactionConflictedEvent {
+        tx_hash: Cow::Owned(tx_hash),
+        conflicting_tx_hash: conflicting_tx_hash.map(Cow::Owned),
+        owner: Cow::Owned(tx.get_owner().clone()),
+        nonce: tx.get_nonce()
+    })
+}
+
+// TXs which are not executed anymore after the DAG re-order, with the block in which they were executed
+#[derive(Default)]
+struct OrphanedTxs(HashMap<Hash, Hash>);
+
+impl OrphanedTxs {
+    // the TX was executed in a block which is re-ordered
+    fn unexecuted(&mut self, tx_hash: &Hash, block_hash: &Hash) {
+        self.0.insert(tx_hash.clone(), block_hash.clone());
+    }
+
+    // the TX is skipped in a block of the new order, the block of its previous execution is kept
+    fn skipped(&mut self, tx_hash: &Hash, block_hash: &Hash) {
+        self.0.entry(tx_hash.clone()).or_insert_with(|| block_hash.clone());
+    }
+
+    // the TX is executed again in the new order
+    fn executed(&mut self, tx_hash: &Hash) {
+        self.0.remove(tx_hash);
+    }
+
+    fn into_events(self) -> impl Iterator<Item = DaemonEvent<'static>> {
+        self.0.into_iter().map(|(tx_hash, block_hash)| DaemonEvent::TransactionOrphaned(TransactionOrphanedEvent {
+            tx_hash: Cow::Owned(tx_hash),
+            block_hash: Some(Cow::Owned(block_hash))
+        }))
+    }
+}
+
+// an auxiliary commitment is optional, but when set it must be supported by the block version
+// and commit each external chain only once
+pub fn verify_aux_commitment(header: &BlockHeader) -> Result<(), BlockchainError> {
+    if let Some(aux_commitment) = header.get_aux_commitment() {
+        if header.get_version() < AUX_COMMITMENT_VERSION {
+            return Err(BlockchainError::AuxCommitmentNotAllowed(header.get_version()))
+        }
+
+        if !aux_commitment.is_valid() {
+            return Err(BlockchainError::InvalidAuxCommitment)
+        }
+    }
+
+    Ok(())
+}
+
+// verify the signatures by splitting the transactions between up to `threads` threads
+// returns false if at least one signature is invalid
+fn verify_signatures(txs: &[&Transaction], threads: usize) -> bool {
+    let threads = threads.min(txs.len() / MIN_TXS_PER_VERIFICATION_THREAD);
+    if threads <= 1 {
+        return txs.iter().all(|tx| tx.verify_signature())
+    }
+
+    let chunk_size = txs.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = txs.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().all(|tx| tx.verify_signature())))
+            .collect();
+
+        handles.into_iter().all(|handle| handle.join().unwrap_or(false))
+    })
+}
+
 pub fn get_block_reward(supply: u64) -> u64 {
     // Prevent any overflow
     if supply >= MAXIMUM_SUPPLY {
@@ -2187,4 +3576,71 @@ pub fn get_block_dev_fee(height: u64) -> u64 {
     }
 
     0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use xelis_common::{
+        api::daemon::DaemonEvent,
+        crypto::{hash::{Hash, Hashable}, key::KeyPair},
+        transaction::{Transaction, TransactionType}
+    };
+    use crate::core::mempool::Mempool;
+    use super::{get_tx_conflicted_event, OrphanedTxs};
+
+    fn _create_tx(keypair: &KeyPair, fee: u64, nonce: u64) -> (Hash, Arc<Transaction>) {
+        let data = TransactionType::Burn { asset: Hash::zero(), amount: 1 };
+        let tx = Transaction::new(keypair.get_public_key().clone(), data, fee, nonce, keypair.sign(b"test"));
+        (tx.hash(), Arc::new(tx))
+    }
+
+    #[test]
+    fn test_tx_conflicted_event() {
+        let keypair = KeyPair::new();
+        let mut mempool = Mempool::new();
+        let (old_hash, old) = _create_tx(&keypair, 1000, 5);
+        let (new_hash, new) = _create_tx(&keypair, 2000, 5);
+        mempool.add_tx(old_hash.clone(), old).unwrap();
+
+        // the TX replaced is reported with the one taking its place
+        let conflicted = mempool.get_replaced_tx(&new).unwrap().unwrap();
+        let DaemonEvent::TransactionConflicted(event) = get_tx_conflicted_event(conflicted, Some(new_hash.clone()), &new) else {
+            panic!("expected a conflicted event")
+        };
+        assert_eq!(event.tx_hash.as_ref(), &old_hash);
+        assert_eq!(event.conflicting_tx_hash.as_deref(), Some(&new_hash));
+        assert_eq!(event.owner.as_ref(), keypair.get_public_key());
+        assert_eq!(event.nonce, 5);
+    }
+
+    #[test]
+    fn test_replaced_tx_orphaned() {
+        let keypair = KeyPair::new();
+        let (replaced, _) = _create_tx(&keypair, 1000, 0);
+        let (replacing, _) = _create_tx(&keypair, 2000, 0);
+        let (executed_again, _) = _create_tx(&keypair, 1000, 1);
+        let block = Hash::new([1u8; 32]);
+        let other_block = Hash::new([2u8; 32]);
+
+        let mut orphaned_txs = OrphanedTxs::default();
+        // the block executing the TXs is re-ordered after a block of another branch
+        orphaned_txs.unexecuted(&replaced, &block);
+        orphaned_txs.unexecuted(&executed_again, &block);
+        // the other branch executes a TX with the same nonce
+        orphaned_txs.executed(&replacing);
+        // so the TX is skipped when its block is executed again, the other one is still valid
+        orphaned_txs.skipped(&replaced, &block);
+        orphaned_txs.executed(&executed_again);
+        // a TX skipped twice keeps the first block
+        orphaned_txs.skipped(&replaced, &other_block);
+
+        let events: Vec<_> = orphaned_txs.into_events().collect();
+        assert_eq!(events.len(), 1);
+        let DaemonEvent::TransactionOrphaned(event) = &events[0] else {
+            panic!("expected an orphaned event")
+        };
+        assert_eq!(event.tx_hash.as_ref(), &replaced);
+        assert_eq!(event.block_hash.as_deref(), Some(&block));
+    }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use std::f64::consts::E;
 
 use log::trace;
 use xelis_common::block::Difficulty;
-use crate::config::{STABLE_LIMIT, BLOCK_TIME_MILLIS, MINIMUM_DIFFICULTY};
+use crate::config::{STABLE_LIMIT, BLOCK_TIME_MILLIS, MINIMUM_DIFFICULTY, LWMA_MAX_SOLVE_TIME_FACTOR};
 
 const M: f64 = STABLE_LIMIT as f64;
 const BLOCK_TIME: f64 = BLOCK_TIME_MILLIS as f64;
@@ -28,4 +28,235 @@ pub fn calculate_difficulty(parent_timestamp: u128, new_timestamp: u128, previou
     }
 
     diff
-}
\ No newline at end of file
+}
+
+// Calculate the difficulty for the next block using a Linearly Weighted Moving Average of the solve times
+// blocks are the (timestamp, difficulty) of the main chain ordered from the oldest, the first one is only used for its timestamp
+// the most recent solve times have the biggest weights so the difficulty follows quickly the hashrate changes
+// timestamps are forced to increase and each solve time is limited, so a miner can't lower the difficulty by much
+// using a timestamp in the future, and a timestamp in the past only increases it
+pub fn calculate_difficulty_lwma(blocks: &[(u128, Difficulty)]) -> Difficulty {
+    let count = blocks.len().saturating_sub(1) as u128;
+    if count == 0 {
+        return MINIMUM_DIFFICULTY
+    }
+
+    let block_time = BLOCK_TIME_MILLIS as u128;
+    let mut previous_timestamp = blocks[0].0;
+    let mut weighted_solve_times = 0;
+    let mut total_difficulty = 0;
+    for (weight, (timestamp, difficulty)) in (1..).zip(blocks.iter().skip(1)) {
+        let timestamp = (*timestamp).max(previous_timestamp + 1);
+        let solve_time = (timestamp - previous_timestamp).min(block_time * LWMA_MAX_SOLVE_TIME_FACTOR);
+        previous_timestamp = timestamp;
+
+        weighted_solve_times += solve_time * weight;
+        total_difficulty += *difficulty as u128;
+    }
+
+    // limit the increase to 10x the average difficulty when blocks are found instantly
+    let weighted_solve_times = weighted_solve_times.max(count * count * block_time / 20);
+    // sum of the weights is count * (count + 1) / 2
+    let diff = total_difficulty * block_time * (count + 1) / (2 * weighted_solve_times);
+    trace!("LWMA over {} blocks, weighted solve times: {}, new difficulty: {}", count, weighted_solve_times, diff);
+
+    Difficulty::try_from(diff).unwrap_or(Difficulty::MAX).max(MINIMUM_DIFFICULTY)
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::block::Difficulty;
+    use crate::config::{BLOCK_TIME_MILLIS, LWMA_WINDOW, MINIMUM_DIFFICULTY};
+    use super::{calculate_difficulty, calculate_difficulty_lwma};
+
+    const BLOCK_TIME: u128 = BLOCK_TIME_MILLIS as u128;
+
+    // hashrate in H/s at which the difficulty is solved in one block time
+    fn _equilibrium(hashrate: u128) -> Difficulty {
+        (hashrate * BLOCK_TIME / 1000) as Difficulty
+    }
+
+    // random solve time following an exponential distribution, using a xorshift to keep the simulation deterministic
+    fn _solve_time(difficulty: Difficulty, hashrate: u128, state: &mut u64) -> u128 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        let uniform = (*state >> 11) as f64 / (1u64 << 53) as f64;
+        (difficulty as f64 * 1000f64 / hashrate as f64 * -(1f64 - uniform).ln()) as u128
+    }
+
+    // mine `count` blocks on the chain with the hashrate, each solve time is the expected one
+    // or a random one following an exponential distribution if a seed is set
+    fn _mine(chain: &mut Vec<(u128, Difficulty)>, hashrate: u128, count: usize, mut seed: Option<&mut u64>) {
+        for _ in 0..count {
+            let start = chain.len().saturating_sub(LWMA_WINDOW + 1);
+            let difficulty = calculate_difficulty_lwma(&chain[start..]);
+            let solve_time = match seed.as_deref_mut() {
+                Some(state) => _solve_time(difficulty, hashrate, state),
+                None => (difficulty as f64 * 1000f64 / hashrate as f64) as u128
+            };
+
+            let timestamp = chain.last().map_or(0, |(timestamp, _)| *timestamp) + solve_time;
+            chain.push((timestamp, difficulty));
+        }
+    }
+
+    fn _chain_at_equilibrium(hashrate: u128) -> Vec<(u128, Difficulty)> {
+        let difficulty = _equilibrium(hashrate);
+        (0..=LWMA_WINDOW as u128).map(|i| (i * BLOCK_TIME, difficulty)).collect()
+    }
+
+    fn _last_difficulty(chain: &[(u128, Difficulty)]) -> Difficulty {
+        calculate_difficulty_lwma(&chain[chain.len() - LWMA_WINDOW - 1..])
+    }
+
+    // hashrate of each block of a recorded chain, estimated using the solve times of the previous blocks
+    // to smooth the randomness of a single solve time, the first blocks are skipped as they have not enough previous blocks
+    fn _recorded_hashrates(recorded: &[(u128, Difficulty)]) -> Vec<u128> {
+        const SMOOTHING: usize = 10;
+        (SMOOTHING..recorded.len()).map(|i| {
+            let start = i - SMOOTHING;
+            let elapsed = recorded[i].0.saturating_sub(recorded[start].0).max(1);
+            let work: u128 = recorded[start + 1..=i].iter().map(|(_, difficulty)| *difficulty as u128).sum();
+            (work * 1000 / elapsed).max(1)
+        }).collect()
+    }
+
+    // relative distance of the average block time to the target between these blocks
+    fn _block_time_deviation(chain: &[(u128, Difficulty)]) -> f64 {
+        let elapsed = chain[chain.len() - 1].0 - chain[0].0;
+        let average = elapsed as f64 / (chain.len() - 1) as f64;
+        (average / BLOCK_TIME as f64 - 1f64).abs()
+    }
+
+    // mine again a recorded chain with the same hashrate at each block using the LWMA
+    // returns the deviation of its average block time and the chain mined
+    fn _replay(recorded: &[(u128, Difficulty)]) -> (f64, Vec<(u128, Difficulty)>) {
+        let hashrates = _recorded_hashrates(recorded);
+        let mut chain = _chain_at_equilibrium(hashrates[0]);
+        let start = chain.len() - 1;
+        let mut seed = 0x5eed;
+        for hashrate in hashrates.iter() {
+            _mine(&mut chain, *hashrate, 1, Some(&mut seed));
+        }
+
+        (_block_time_deviation(&chain[start..]), chain)
+    }
+
+    fn _assert_close(value: Difficulty, expected: Difficulty, tolerance: f64) {
+        let ratio = value as f64 / expected as f64;
+        assert!((1f64 - ratio).abs() <= tolerance, "{} is not close to {} (ratio {})", value, expected, ratio);
+    }
+
+    #[test]
+    fn test_stable_hashrate() {
+        let hashrate = 10_000_000;
+        let mut chain = _chain_at_equilibrium(hashrate);
+        _assert_close(_last_difficulty(&chain), _equilibrium(hashrate), 0.05);
+
+        _mine(&mut chain, hashrate, 500, None);
+        _assert_close(_last_difficulty(&chain), _equilibrium(hashrate), 0.05);
+    }
+
+    #[test]
+    fn test_hashrate_swings() {
+        let hashrate = 10_000_000;
+        let mut chain = _chain_at_equilibrium(hashrate);
+
+        // a big miner joins the network
+        _mine(&mut chain, hashrate * 10, LWMA_WINDOW * 3, None);
+        _assert_close(_last_difficulty(&chain), _equilibrium(hashrate * 10), 0.1);
+
+        // and leaves it
+        _mine(&mut chain, hashrate, LWMA_WINDOW * 3, None);
+        _assert_close(_last_difficulty(&chain), _equilibrium(hashrate), 0.1);
+    }
+
+    #[test]
+    fn test_random_solve_times() {
+        let hashrate = 10_000_000;
+        let mut chain = _chain_at_equilibrium(hashrate);
+        let blocks = 5000;
+        _mine(&mut chain, hashrate, blocks, Some(&mut 0x5eed));
+
+        // average block time stays close to the target
+        let elapsed = chain[chain.len() - 1].0 - chain[chain.len() - 1 - blocks].0;
+        let average = elapsed as f64 / blocks as f64;
+        assert!((average / BLOCK_TIME as f64 - 1f64).abs() < 0.1, "average block time {}", average);
+    }
+
+    #[test]
+    fn test_timestamp_manipulation() {
+        let hashrate = 10_000_000;
+        let expected = _equilibrium(hashrate);
+        let mut chain = _chain_at_equilibrium(hashrate);
+        let last = chain[chain.len() - 1].0;
+
+        // a timestamp far in the future is limited to the maximum solve time
+        let mut future = chain.clone();
+        future.push((last + BLOCK_TIME * 1000, expected));
+        assert!(_last_difficulty(&future) > expected * 8 / 10);
+
+        // a timestamp in the past can't lower the difficulty
+        let mut past = chain.clone();
+        past.push((0, expected));
+        assert!(_last_difficulty(&past) >= expected);
+
+        // instant blocks can't increase the difficulty more than 10x
+        for _ in 0..LWMA_WINDOW {
+            chain.push((last, expected));
+        }
+        assert!(_last_difficulty(&chain) <= expected * 11);
+    }
+
+    #[test]
+    fn test_minimum_difficulty() {
+        assert_eq!(calculate_difficulty_lwma(&[]), MINIMUM_DIFFICULTY);
+        assert_eq!(calculate_difficulty_lwma(&[(0, 1), (BLOCK_TIME * 100, 1)]), MINIMUM_DIFFICULTY);
+    }
+
+    #[test]
+    fn test_replay() {
+        // chain mined with the legacy algorithm while the hashrate swings
+        let hashrate = 10_000_000;
+        let mut recorded = vec![(0, _equilibrium(hashrate))];
+        let mut difficulty = _equilibrium(hashrate);
+        let mut seed = 0xcafe;
+        for (hashrate, count) in [(hashrate, 300), (hashrate * 5, 300), (hashrate / 2, 300), (hashrate, 300)] {
+            for _ in 0..count {
+                let timestamp = recorded[recorded.len() - 1].0;
+                let solve_time = _solve_time(difficulty, hashrate, &mut seed);
+                recorded.push((timestamp + solve_time, difficulty));
+                difficulty = calculate_difficulty(timestamp, timestamp + solve_time, difficulty);
+            }
+        }
+
+        // the LWMA follows the swings better than the legacy algorithm
+        let (deviation, chain) = _replay(&recorded);
+        let recorded_deviation = _block_time_deviation(&recorded);
+        assert!(deviation < recorded_deviation, "deviation of the block time {} while recorded {}", deviation, recorded_deviation);
+        assert!(deviation < 0.15, "deviation of the block time {}", deviation);
+        _assert_close(_last_difficulty(&chain), _equilibrium(hashrate), 0.3);
+    }
+
+    // replay the chain of `tests/data/difficulty_replay.csv`, meant to be recorded on a node (testnet for example)
+    // it is a CSV file of the `timestamp,difficulty` of the main chain ordered by height, lines starting with `#` are skipped
+    #[test]
+    fn test_replay_recorded_chain() {
+        let content = include_str!("../../tests/data/difficulty_replay.csv");
+        let recorded: Vec<(u128, Difficulty)> = content.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (timestamp, difficulty) = line.split_once(',').expect("timestamp,difficulty");
+                (timestamp.trim().parse().expect("timestamp"), difficulty.trim().parse().expect("difficulty"))
+            })
+            .collect();
+        assert!(recorded.len() > LWMA_WINDOW, "not enough blocks recorded");
+
+        // the LWMA must keep the block time at least as close to the target as the algorithm which mined the chain
+        let (deviation, _) = _replay(&recorded);
+        let recorded_deviation = _block_time_deviation(&recorded);
+        assert!(deviation <= recorded_deviation.max(0.1), "deviation of the block time {} while recorded {}", deviation, recorded_deviation);
+    }
+}
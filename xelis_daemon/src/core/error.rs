@@ -1,7 +1,8 @@
 use crate::p2p::error::P2pError;
+use super::genesis::GenesisError;
 use std::sync::PoisonError;
 use thiserror::Error;
-use xelis_common::{crypto::{hash::Hash, key::PublicKey, bech32::Bech32Error}, serializer::ReaderError, prompt::PromptError, difficulty::DifficultyError};
+use xelis_common::{crypto::{hash::Hash, key::PublicKey, bech32::Bech32Error}, serializer::ReaderError, prompt::PromptError, difficulty::DifficultyError, contract::ContractError, api::ErrorCode};
 
 #[derive(Error, Debug)]
 pub enum DiskContext {
@@ -25,10 +26,14 @@ pub enum BlockchainError {
     TimestampIsLessThanParent(u128),
     #[error("Timestamp {} is greater than current time {}", _0, _1)]
     TimestampIsInFuture(u128, u128), // left is expected, right is got
+    #[error("Timestamp {} is not greater than the median time past {}", _0, _1)]
+    TimestampIsLessThanMedianTimePast(u128, u128),
     #[error("Block height mismatch, expected {}, got {}.", _0, _1)]
     InvalidBlockHeight(u64, u64),
     #[error("Block height is in stable height which is not allowed")]
     InvalidBlockHeightStableHeight,
+    #[error("Invalid block version, expected {}, got {}", _0, _1)]
+    InvalidBlockVersion(u8, u8),
     #[error("Invalid difficulty")]
     InvalidDifficulty,
     #[error("Invalid hash, expected {}, got {}", _0, _1)]
@@ -45,6 +50,8 @@ pub enum BlockchainError {
     TxNotFound(Hash),
     #[error("Tx {} was present in mempool but not in sorted list!", _0)]
     TxNotFoundInSortedList(Hash),
+    #[error("No receipt found for tx {}, it is not executed", _0)]
+    NoTxReceipt(Hash),
     #[error("Tx {} already in mempool", _0)]
     TxAlreadyInMempool(Hash),
     #[error("Normal Tx {} is empty", _0)]
@@ -83,6 +90,16 @@ pub enum BlockchainError {
     InvalidTransactionToSender(Hash),
     #[error("Invalid extra data in this transaction, expected maximum {} bytes but got {} bytes", _0, _1)]
     InvalidTransactionExtraDataTooBig(usize, usize),
+    #[error("Tx {} is too big, expected maximum {} bytes but got {} bytes", _0, _1, _2)]
+    TxTooBig(Hash, usize, usize),
+    #[error("Tx {} registers an invalid name", _0)]
+    InvalidName(Hash),
+    #[error("Tx {} registers a name for an invalid duration of {} topoheights", _0, _1)]
+    InvalidNameDuration(Hash, u64),
+    #[error("Name {} is already registered", _0)]
+    NameAlreadyRegistered(String),
+    #[error("Name {} is not registered", _0)]
+    NameNotFound(String),
     #[error("Invalid network state")]
     InvalidNetwork,
     #[error("Error while retrieving block by hash: {} not found", _0)]
@@ -121,8 +138,22 @@ pub enum BlockchainError {
     Unknown,
     #[error("No signature found for this TX")]
     NoTxSignature,
-    #[error("Smart Contract not supported yet")]
-    SmartContractTodo,
+    #[error("Transaction type of {} is not allowed at this block version", _0)]
+    TxTypeNotAllowed(Hash),
+    #[error("Transaction {} has version {} which is not allowed, maximum is {}", _0, _1, _2)]
+    InvalidTxVersion(Hash, u8, u8),
+    #[error("Transaction {} expired at topoheight {}", _0, _1)]
+    TxExpired(Hash, u64),
+    #[error("Transaction {} sponsor cannot be its owner", _0)]
+    InvalidTxSponsor(Hash),
+    #[error("Contract {} not found", _0)]
+    ContractNotFound(Hash),
+    #[error("Invalid contract code in TX {}: {}", _0, _1)]
+    InvalidContractCode(Hash, ContractError),
+    #[error("Contract call {} cannot send assets, deposits are not supported yet", _0)]
+    ContractDepositsNotSupported(Hash),
+    #[error("Contract call {} has an invalid param name", _0)]
+    InvalidContractParam(Hash),
     #[error("Unexpected transaction variant to set fees")]
     UnexpectedTransactionVariant,
     #[error("Unexpected error on database: {}", _0)]
@@ -141,14 +172,28 @@ pub enum BlockchainError {
     BlockDeviation,
     #[error("Invalid genesis block hash")]
     InvalidGenesisHash,
+    #[error("Auxiliary commitment is not allowed in block version {}", _0)]
+    AuxCommitmentNotAllowed(u8),
+    #[error("Auxiliary commitment is invalid")]
+    InvalidAuxCommitment,
+    #[error("Block {} doesn't commit to the chain {}", _0, _1)]
+    AuxChainNotCommitted(Hash, Hash),
     #[error("Invalid tx {} nonce (got {} expected {}) for {}", _0, _1, _2, _3)]
     InvalidTxNonce(Hash, u64, u64, PublicKey),
     #[error("Invalid tx nonce for mempool cache")]
     InvalidTxNonceMempoolCache,
+    #[error("Too many unconfirmed txs from {}, maximum is {}", _0, _1)]
+    TooManyPendingTxs(PublicKey, usize),
+    #[error("Too many txs from {} are waiting for a previous nonce", _0)]
+    TooManyOrphanTxs(PublicKey),
+    #[error("Mempool is full of txs waiting for a previous nonce")]
+    OrphanTxsLimitReached,
     #[error("Invalid asset ID: {}", _0)]
     AssetNotFound(Hash),
     #[error(transparent)]
     DifficultyError(#[from] DifficultyError),
+    #[error(transparent)]
+    GenesisError(#[from] GenesisError),
     #[error("No balance found on disk for {}", _0)]
     NoBalance(PublicKey),
     #[error("No balance changes for {} at topoheight {} and asset {}", _0, _1, _2)]
@@ -172,7 +217,64 @@ pub enum BlockchainError {
     #[error("Prune topoheight is lower or equal than previous pruned topoheight")]
     PruneLowerThanLastPruned,
     #[error("Auto prune mode is misconfigured")]
-    AutoPruneMode
+    AutoPruneMode,
+    #[error("Cold store depth is misconfigured")]
+    ColdStoreDepth,
+    #[error("Orphaned blocks retention is misconfigured")]
+    OrphanedBlocksRetention,
+    #[error("Snapshot directory {} already exists", _0)]
+    SnapshotDirectoryExists(String),
+    #[error("Error while creating the snapshot: {}", _0)]
+    SnapshotFailed(String),
+    #[error("Storage is in version {} but only versions up to {} are supported, please update your node", _0, _1)]
+    UnsupportedStorageVersion(u8, u8),
+    #[error("Invalid storage key file {}, expected {} bytes in hexadecimal", _0, _1)]
+    InvalidStorageKeyFile(String, usize),
+    #[error("Storage is encrypted, its key file is required")]
+    StorageKeyRequired,
+    #[error("Storage key doesn't match the key used to encrypt the storage")]
+    InvalidStorageKey,
+    #[error("Storage was created without encryption, it can't be encrypted afterwards")]
+    StorageNotEncrypted,
+    #[error("Error while decrypting a value from storage")]
+    StorageDecryptionFailed,
+    #[error("State can't be exported at topoheight {}, it must be between the pruned topoheight {} and the current topoheight {}", _0, _1, _2)]
+    InvalidStateTopoheight(u64, u64, u64)
+}
+
+impl BlockchainError {
+    // stable code sent to the RPC clients, None for the internal errors
+    pub fn get_error_code(&self) -> Option<ErrorCode> {
+        Some(match self {
+            Self::BlockNotFound(_) | Self::BlockHeightNotFound(_) => ErrorCode::UnknownBlock,
+            Self::TxNotFound(_) => ErrorCode::UnknownTransaction,
+            Self::AddressNotRegistered(_) | Self::NoBalance(_) | Self::NoNonce(_) => ErrorCode::UnknownAccount,
+            Self::AssetNotFound(_) => ErrorCode::UnknownAsset,
+            Self::ContractNotFound(_) => ErrorCode::UnknownContract,
+            Self::NameNotFound(_) => ErrorCode::UnknownName,
+            Self::NoTxReceipt(_) => ErrorCode::TransactionNotExecuted,
+            Self::TxAlreadyInMempool(_) | Self::TxAlreadyInBlock(_) | Self::TxAlreadyInBlockchain(_) => ErrorCode::TransactionAlreadyKnown,
+            Self::NotEnoughFunds(..) => ErrorCode::InsufficientFunds,
+            Self::InvalidTxNonce(..) | Self::InvalidTransactionNonce(..) | Self::InvalidTxNonceMempoolCache => ErrorCode::InvalidNonce,
+            Self::InvalidTxFee(..) | Self::FeesToLowToOverride(..) => ErrorCode::FeeTooLow,
+            Self::InvalidTransactionSignature | Self::NoTxSignature | Self::UnexpectedTransactionSignature => ErrorCode::InvalidSignature,
+            Self::TxEmpty(_) | Self::TooManyOutputInTx(_) | Self::TxTooBig(..) | Self::InvalidName(_) | Self::InvalidNameDuration(..) | Self::NameAlreadyRegistered(_) | Self::InvalidTransactionToSender(_) | Self::InvalidTransactionExtraDataTooBig(..)
+            | Self::InvalidTxSponsor(_) | Self::InvalidContractCode(..) | Self::ContractDepositsNotSupported(_) | Self::InvalidContractParam(_)
+            | Self::NoValueForBurn | Self::Overflow => ErrorCode::InvalidTransaction,
+            Self::TxTypeNotAllowed(_) | Self::InvalidTxVersion(..) | Self::TxExpired(..) => ErrorCode::TransactionNotAllowed,
+            Self::TooManyPendingTxs(..) | Self::TooManyOrphanTxs(_) | Self::OrphanTxsLimitReached => ErrorCode::MempoolLimitReached,
+            Self::ErrorOnBech32(_) => ErrorCode::InvalidAddress,
+            Self::IsSyncing => ErrorCode::Syncing,
+            Self::AlreadyInChain => ErrorCode::BlockAlreadyKnown,
+            Self::InvalidBlockHeightStableHeight | Self::BlockDeviation => ErrorCode::StaleBlock,
+            Self::TimestampIsInFuture(..) | Self::TimestampIsLessThanParent(_) | Self::TimestampIsLessThanMedianTimePast(..) => ErrorCode::InvalidTimestamp,
+            Self::InvalidDifficulty => ErrorCode::InvalidProofOfWork,
+            Self::InvalidTips | Self::ExpectedTips | Self::InvalidBlockHeight(..) | Self::InvalidBlockVersion(..) | Self::InvalidReachability
+            | Self::AuxCommitmentNotAllowed(_) | Self::InvalidAuxCommitment => ErrorCode::InvalidBlock,
+            Self::AuxChainNotCommitted(..) => ErrorCode::UnknownAuxChain,
+            _ => return None
+        })
+    }
 }
 
 impl<T> From<PoisonError<T>> for BlockchainError {
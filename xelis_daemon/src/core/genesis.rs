@@ -0,0 +1,392 @@
+use std::{fs, path::Path};
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use xelis_common::{
+    block::{Block, BlockHeader, Difficulty},
+    crypto::{address::Address, hash::{hash, Hash, Hashable}, key::PublicKey},
+    immutable::Immutable,
+    network::Network,
+    serializer::{Serializer, Writer}
+};
+use crate::config::{MAXIMUM_SUPPLY, MINIMUM_DIFFICULTY};
+use super::hard_fork;
+
+// maximum size in bytes of the genesis message
+pub const MAX_GENESIS_MESSAGE_SIZE: usize = 255;
+
+#[derive(Error, Debug)]
+pub enum GenesisError {
+    #[error("Genesis file is for network {}, but daemon is running on {}", _0, _1)]
+    NetworkMismatch(Network, Network),
+    #[error("Genesis message is too long: {} bytes, maximum is {}", _0, MAX_GENESIS_MESSAGE_SIZE)]
+    MessageTooLong(usize),
+    #[error("Initial difficulty {} is lower than the minimum difficulty {}", _0, MINIMUM_DIFFICULTY)]
+    DifficultyTooLow(Difficulty),
+    #[error("Address {} of an allocation is not for network {}", _0, _1)]
+    AllocationNetwork(String, Network),
+    #[error("Address {} of an allocation is an integrated address", _0)]
+    AllocationIntegratedAddress(String),
+    #[error("Allocation to {} has no amount", _0)]
+    AllocationZeroAmount(String),
+    #[error("Address {} has several allocations", _0)]
+    DuplicatedAllocation(String),
+    #[error("Address {} of a registration is not for network {}", _0, _1)]
+    RegistrationNetwork(String, Network),
+    #[error("Address {} of a registration is an integrated address", _0)]
+    RegistrationIntegratedAddress(String),
+    #[error("Address {} is registered several times or also has an allocation", _0)]
+    DuplicatedRegistration(String),
+    #[error("Too many accounts in genesis: {}, maximum is {}", _0, u16::MAX)]
+    TooManyAccounts(usize),
+    #[error("Allocations are above the maximum supply")]
+    AllocationsAboveMaximumSupply,
+    #[error("Invalid allocation '{}', expected <address>:<amount in atomic units>", _0)]
+    InvalidAllocationFormat(String),
+    #[error("Genesis block is invalid")]
+    InvalidBlock,
+    #[error("Genesis block doesn't commit to the genesis configuration")]
+    InvalidCommitment,
+    #[error("Genesis block hash is invalid, expected {}, got {}", _0, _1)]
+    InvalidHash(Hash, Hash),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GenesisAllocation {
+    pub address: Address,
+    // in atomic units
+    pub amount: u64
+}
+
+impl GenesisAllocation {
+    // parse an allocation in the format <address>:<amount in atomic units>
+    pub fn from_string(value: &str) -> Result<Self, GenesisError> {
+        let (address, amount) = value.split_once(':')
+            .ok_or_else(|| GenesisError::InvalidAllocationFormat(value.to_owned()))?;
+        let address = Address::from_string(&address.to_owned())
+            .map_err(|_| GenesisError::InvalidAllocationFormat(value.to_owned()))?;
+        let amount = amount.parse()
+            .map_err(|_| GenesisError::InvalidAllocationFormat(value.to_owned()))?;
+
+        Ok(Self { address, amount })
+    }
+}
+
+// Genesis of a custom network, written by the `generate-genesis` command and loaded using `--genesis-file`
+// every field is committed in the extra nonce of the genesis block, so two nodes with the same genesis hash share the same state
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GenesisConfig {
+    pub network: Network,
+    pub message: String,
+    pub timestamp: u128,
+    // difficulty of the first blocks, until enough blocks are available to compute it
+    pub difficulty: Difficulty,
+    pub allocations: Vec<GenesisAllocation>,
+    // accounts registered at genesis without coins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub registrations: Vec<Address>,
+    pub hash: Hash,
+    // genesis block in hexadecimal format
+    pub block: String
+}
+
+impl GenesisConfig {
+    // build the genesis block for this configuration
+    pub fn generate(network: Network, miner: PublicKey, message: String, timestamp: u128, difficulty: Difficulty, allocations: Vec<GenesisAllocation>, registrations: Vec<Address>) -> Result<Self, GenesisError> {
+        verify_parameters(&network, &message, difficulty, &allocations, &registrations)?;
+
+        let commitment = get_commitment(&network, &message, timestamp, difficulty, &allocations, &registrations);
+        let header = BlockHeader::new(hard_fork::get_version_at_height(&network, 0), 0, timestamp, Vec::new(), commitment.to_bytes(), miner, Vec::new());
+        let block = Block::new(Immutable::Owned(header), Vec::new());
+
+        Ok(Self {
+            network,
+            message,
+            timestamp,
+            difficulty,
+            allocations,
+            registrations,
+            hash: block.hash(),
+            block: block.to_hex()
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GenesisError> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GenesisError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // verify the configuration for the network used and returns its genesis block
+    pub fn to_block(&self, network: &Network) -> Result<Block, GenesisError> {
+        if self.network != *network {
+            return Err(GenesisError::NetworkMismatch(self.network, *network))
+        }
+        verify_parameters(&self.network, &self.message, self.difficulty, &self.allocations, &self.registrations)?;
+
+        let block = Block::from_hex(self.block.clone()).map_err(|_| GenesisError::InvalidBlock)?;
+        if block.get_height() != 0 || !block.get_tips().is_empty() || block.get_txs_count() != 0 || block.get_timestamp() != self.timestamp {
+            return Err(GenesisError::InvalidBlock)
+        }
+
+        let commitment = get_commitment(&self.network, &self.message, self.timestamp, self.difficulty, &self.allocations, &self.registrations);
+        if *block.get_extra_nonce() != commitment.to_bytes() {
+            return Err(GenesisError::InvalidCommitment)
+        }
+
+        let block_hash = block.hash();
+        if block_hash != self.hash {
+            return Err(GenesisError::InvalidHash(self.hash.clone(), block_hash))
+        }
+
+        Ok(block)
+    }
+}
+
+// State of an account exported by the admin command `export-state`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AccountState {
+    pub address: Address,
+    // XELIS balance in atomic units
+    pub balance: u64,
+    pub nonce: u64
+}
+
+// Accounts of a network at a topoheight, used to generate the genesis of a reset network
+// the file can be edited before generating the genesis with `generate-genesis --state`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StateExport {
+    pub network: Network,
+    pub topoheight: u64,
+    // hash of the block at this topoheight
+    pub hash: Hash,
+    pub accounts: Vec<AccountState>
+}
+
+impl StateExport {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GenesisError> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GenesisError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // allocations and registrations of the accounts for a new genesis on this network
+    // balances are dropped if zero_balances is set, accounts without coins are kept only if carry_registrations is set
+    pub fn to_genesis_accounts(&self, network: &Network, zero_balances: bool, carry_registrations: bool) -> (Vec<GenesisAllocation>, Vec<Address>) {
+        let mut allocations = Vec::new();
+        let mut registrations = Vec::new();
+        for account in &self.accounts {
+            let address = account.address.get_public_key().to_address(network.is_mainnet());
+            if account.balance > 0 && !zero_balances {
+                allocations.push(GenesisAllocation { address, amount: account.balance });
+            } else if carry_registrations {
+                registrations.push(address);
+            }
+        }
+
+        (allocations, registrations)
+    }
+}
+
+fn verify_parameters(network: &Network, message: &str, difficulty: Difficulty, allocations: &[GenesisAllocation], registrations: &[Address]) -> Result<(), GenesisError> {
+    if message.len() > MAX_GENESIS_MESSAGE_SIZE {
+        return Err(GenesisError::MessageTooLong(message.len()))
+    }
+
+    let accounts = allocations.len() + registrations.len();
+    if accounts > u16::MAX as usize {
+        return Err(GenesisError::TooManyAccounts(accounts))
+    }
+
+    if difficulty < MINIMUM_DIFFICULTY {
+        return Err(GenesisError::DifficultyTooLow(difficulty))
+    }
+
+    let mut total: u64 = 0;
+    for (i, allocation) in allocations.iter().enumerate() {
+        let address = &allocation.address;
+        if address.is_mainnet() != network.is_mainnet() {
+            return Err(GenesisError::AllocationNetwork(address.to_string(), *network))
+        }
+
+        if !address.is_normal() {
+            return Err(GenesisError::AllocationIntegratedAddress(address.to_string()))
+        }
+
+        if allocation.amount == 0 {
+            return Err(GenesisError::AllocationZeroAmount(address.to_string()))
+        }
+
+        if allocations[..i].iter().any(|a| a.address.get_public_key() == address.get_public_key()) {
+            return Err(GenesisError::DuplicatedAllocation(address.to_string()))
+        }
+
+        total = total.checked_add(allocation.amount).ok_or(GenesisError::AllocationsAboveMaximumSupply)?;
+    }
+
+    if total > MAXIMUM_SUPPLY {
+        return Err(GenesisError::AllocationsAboveMaximumSupply)
+    }
+
+    for (i, address) in registrations.iter().enumerate() {
+        if address.is_mainnet() != network.is_mainnet() {
+            return Err(GenesisError::RegistrationNetwork(address.to_string(), *network))
+        }
+
+        if !address.is_normal() {
+            return Err(GenesisError::RegistrationIntegratedAddress(address.to_string()))
+        }
+
+        let key = address.get_public_key();
+        if registrations[..i].iter().any(|a| a.get_public_key() == key) || allocations.iter().any(|a| a.address.get_public_key() == key) {
+            return Err(GenesisError::DuplicatedRegistration(address.to_string()))
+        }
+    }
+
+    Ok(())
+}
+
+// hash of all the parameters of the genesis, stored in the extra nonce of the genesis block
+fn get_commitment(network: &Network, message: &str, timestamp: u128, difficulty: Difficulty, allocations: &[GenesisAllocation], registrations: &[Address]) -> Hash {
+    let mut writer = Writer::new();
+    network.write(&mut writer);
+    writer.write_string(&message.to_owned());
+    writer.write_u128(&timestamp);
+    writer.write_u64(&difficulty);
+    writer.write_u16(allocations.len() as u16);
+    for allocation in allocations {
+        allocation.address.get_public_key().write(&mut writer);
+        writer.write_u64(&allocation.amount);
+    }
+
+    // only committed when set, so genesis files without registrations keep their hash
+    if !registrations.is_empty() {
+        writer.write_u16(registrations.len() as u16);
+        for address in registrations {
+            address.get_public_key().write(&mut writer);
+        }
+    }
+
+    hash(writer.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::crypto::key::KeyPair;
+    use super::*;
+
+    fn allocation(amount: u64) -> GenesisAllocation {
+        GenesisAllocation {
+            address: KeyPair::new().get_public_key().to_address(false),
+            amount
+        }
+    }
+
+    #[test]
+    fn test_generate_and_load() {
+        let miner = KeyPair::new().get_public_key().clone();
+        let config = GenesisConfig::generate(Network::Testnet, miner, "hello".to_owned(), 1000, MINIMUM_DIFFICULTY, vec![allocation(100), allocation(200)], Vec::new()).unwrap();
+        let block = config.to_block(&Network::Testnet).unwrap();
+        assert_eq!(block.hash(), config.hash);
+
+        let json = serde_json::to_string(&config).unwrap();
+        let loaded: GenesisConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.to_block(&Network::Testnet).unwrap().hash(), config.hash);
+    }
+
+    #[test]
+    fn test_tampered_config() {
+        let miner = KeyPair::new().get_public_key().clone();
+        let config = GenesisConfig::generate(Network::Testnet, miner, "hello".to_owned(), 1000, MINIMUM_DIFFICULTY, vec![allocation(100)], Vec::new()).unwrap();
+        assert!(matches!(config.to_block(&Network::Dev), Err(GenesisError::NetworkMismatch(_, _))));
+
+        let mut tampered = config.clone();
+        tampered.allocations[0].amount = 1_000;
+        assert!(matches!(tampered.to_block(&Network::Testnet), Err(GenesisError::InvalidCommitment)));
+
+        let mut tampered = config.clone();
+        tampered.message = "world".to_owned();
+        assert!(matches!(tampered.to_block(&Network::Testnet), Err(GenesisError::InvalidCommitment)));
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let miner = KeyPair::new().get_public_key().clone();
+        assert!(matches!(GenesisConfig::generate(Network::Testnet, miner.clone(), String::new(), 0, 1, Vec::new(), Vec::new()), Err(GenesisError::DifficultyTooLow(1))));
+        assert!(matches!(GenesisConfig::generate(Network::Mainnet, miner.clone(), String::new(), 0, MINIMUM_DIFFICULTY, vec![allocation(1)], Vec::new()), Err(GenesisError::AllocationNetwork(_, _))));
+        assert!(matches!(GenesisConfig::generate(Network::Testnet, miner.clone(), String::new(), 0, MINIMUM_DIFFICULTY, vec![allocation(0)], Vec::new()), Err(GenesisError::AllocationZeroAmount(_))));
+        assert!(matches!(GenesisConfig::generate(Network::Testnet, miner.clone(), String::new(), 0, MINIMUM_DIFFICULTY, vec![allocation(MAXIMUM_SUPPLY), allocation(1)], Vec::new()), Err(GenesisError::AllocationsAboveMaximumSupply)));
+
+        let duplicated = allocation(1);
+        assert!(matches!(GenesisConfig::generate(Network::Testnet, miner, String::new(), 0, MINIMUM_DIFFICULTY, vec![duplicated.clone(), duplicated], Vec::new()), Err(GenesisError::DuplicatedAllocation(_))));
+    }
+
+    #[test]
+    fn test_registrations() {
+        let miner = KeyPair::new().get_public_key().clone();
+        let registered = KeyPair::new().get_public_key().to_address(false);
+        let config = GenesisConfig::generate(Network::Testnet, miner.clone(), String::new(), 1000, MINIMUM_DIFFICULTY, vec![allocation(100)], vec![registered.clone()]).unwrap();
+        assert_eq!(config.to_block(&Network::Testnet).unwrap().hash(), config.hash);
+
+        let mut tampered = config.clone();
+        tampered.registrations.clear();
+        assert!(matches!(tampered.to_block(&Network::Testnet), Err(GenesisError::InvalidCommitment)));
+
+        let allocated = allocation(100);
+        assert!(matches!(GenesisConfig::generate(Network::Testnet, miner.clone(), String::new(), 0, MINIMUM_DIFFICULTY, vec![allocated.clone()], vec![allocated.address]), Err(GenesisError::DuplicatedRegistration(_))));
+        assert!(matches!(GenesisConfig::generate(Network::Testnet, miner, String::new(), 0, MINIMUM_DIFFICULTY, Vec::new(), vec![registered.clone(), registered]), Err(GenesisError::DuplicatedRegistration(_))));
+    }
+
+    #[test]
+    fn test_state_to_genesis_accounts() {
+        let account = |balance| AccountState {
+            address: KeyPair::new().get_public_key().to_address(true),
+            balance,
+            nonce: 5
+        };
+        let state = StateExport {
+            network: Network::Mainnet,
+            topoheight: 10,
+            hash: Hash::zero(),
+            accounts: vec![account(100), account(0)]
+        };
+
+        let (allocations, registrations) = state.to_genesis_accounts(&Network::Testnet, false, false);
+        assert_eq!(allocations.len(), 1);
+        assert_eq!(allocations[0].amount, 100);
+        assert!(!allocations[0].address.is_mainnet());
+        assert!(registrations.is_empty());
+
+        let (allocations, registrations) = state.to_genesis_accounts(&Network::Testnet, false, true);
+        assert_eq!(allocations.len(), 1);
+        assert_eq!(registrations.len(), 1);
+
+        // participants are kept without their coins
+        let (allocations, registrations) = state.to_genesis_accounts(&Network::Testnet, true, true);
+        assert!(allocations.is_empty());
+        assert_eq!(registrations.len(), 2);
+        assert_eq!(registrations[0].get_public_key(), state.accounts[0].address.get_public_key());
+    }
+
+    #[test]
+    fn test_parse_allocation() {
+        let address = KeyPair::new().get_public_key().to_address(false);
+        let parsed = GenesisAllocation::from_string(&format!("{}:1500", address)).unwrap();
+        assert_eq!(parsed.amount, 1500);
+        assert_eq!(parsed.address.get_public_key(), address.get_public_key());
+
+        assert!(GenesisAllocation::from_string(&address.to_string()).is_err());
+        assert!(GenesisAllocation::from_string(&format!("{}:abc", address)).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use xelis_common::{api::daemon::HardFork, block::Difficulty, network::Network};
+use crate::config::get_hard_forks;
+
+// Get the hard fork active at the requested height
+// the hard forks of each network are sorted by ascending height and always start at height 0
+pub fn get_hard_fork_at_height(network: &Network, height: u64) -> &'static HardFork {
+    let hard_forks = get_hard_forks(network);
+    let mut hard_fork = &hard_forks[0];
+    for fork in hard_forks.iter() {
+        if fork.height > height {
+            break;
+        }
+        hard_fork = fork;
+    }
+    hard_fork
+}
+
+// Get the block version expected at this height
+pub fn get_version_at_height(network: &Network, height: u64) -> u8 {
+    get_hard_fork_at_height(network, height).version
+}
+
+// Verify if the block version is the one expected at this height
+pub fn is_version_allowed_at_height(network: &Network, height: u64, version: u8) -> bool {
+    get_version_at_height(network, height) == version
+}
+
+// Verify if a hard fork of this network uses a PoW algorithm requiring the dataset of its epoch
+pub fn is_pow_seed_used(network: &Network) -> bool {
+    get_hard_forks(network).iter().any(|fork| fork.pow_algorithm.requires_dataset())
+}
+
+// Get all hard forks not yet activated at this height
+pub fn get_upcoming_hard_forks(network: &Network, height: u64) -> impl Iterator<Item = &'static HardFork> {
+    get_hard_forks(network).iter().filter(move |fork| fork.height > height)
+}
+
+// Check if a hard fork is activated exactly at this height
+pub fn has_hard_fork_at_height(network: &Network, height: u64) -> bool {
+    get_hard_forks(network).iter().any(|fork| fork.height == height)
+}
+
+// Blocks before the hard fork height may not be able to pay the new difficulty
+// this is used to reset the difficulty when a new PoW algorithm is activated
+pub fn get_difficulty_reset_at_height(network: &Network, height: u64, current: Difficulty, minimum: Difficulty) -> Difficulty {
+    if height != 0 && has_hard_fork_at_height(network, height) {
+        let previous = get_hard_fork_at_height(network, height - 1);
+        if previous.pow_algorithm != get_hard_fork_at_height(network, height).pow_algorithm {
+            return minimum
+        }
+    }
+    current
+}
+
+// Get the height of the last difficulty reset at or below this height
+// blocks below it are not used to compute the difficulty as they were mined with another PoW algorithm
+pub fn get_last_difficulty_reset_height(network: &Network, height: u64) -> u64 {
+    let hard_forks = get_hard_forks(network);
+    hard_forks.iter().zip(hard_forks.iter().skip(1))
+        .filter(|(previous, fork)| fork.height <= height && previous.pow_algorithm != fork.pow_algorithm)
+        .map(|(_, fork)| fork.height)
+        .next_back()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::{block::AUX_COMMITMENT_VERSION, network::Network};
+    use crate::config::get_hard_forks;
+    use super::{get_hard_fork_at_height, get_version_at_height, is_version_allowed_at_height, get_upcoming_hard_forks, is_pow_seed_used};
+
+    const NETWORKS: [Network; 3] = [Network::Mainnet, Network::Testnet, Network::Dev];
+
+    #[test]
+    fn test_hard_forks_sorted() {
+        for network in &NETWORKS {
+            let hard_forks = get_hard_forks(network);
+            assert_eq!(hard_forks[0].height, 0);
+            assert!(hard_forks.windows(2).all(|forks| forks[0].height < forks[1].height && forks[0].version < forks[1].version));
+        }
+    }
+
+    #[test]
+    fn test_version_at_height() {
+        for network in &NETWORKS {
+            let hard_forks = get_hard_forks(network);
+            for (i, fork) in hard_forks.iter().enumerate() {
+                assert_eq!(get_hard_fork_at_height(network, fork.height).version, fork.version);
+                assert!(is_version_allowed_at_height(network, fork.height, fork.version));
+                if i > 0 {
+                    let previous = &hard_forks[i - 1];
+                    // the previous version is refused once the hard fork is active
+                    assert_eq!(get_version_at_height(network, fork.height - 1), previous.version);
+                    assert!(is_version_allowed_at_height(network, fork.height - 1, previous.version));
+                    assert!(!is_version_allowed_at_height(network, fork.height, previous.version));
+                    // and the new version is refused before it
+                    assert!(!is_version_allowed_at_height(network, fork.height - 1, fork.version));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_upcoming_hard_forks() {
+        for network in &NETWORKS {
+            let hard_forks = get_hard_forks(network);
+            assert_eq!(get_upcoming_hard_forks(network, 0).count(), hard_forks.len() - 1);
+            let last = &hard_forks[hard_forks.len() - 1];
+            assert_eq!(get_upcoming_hard_forks(network, last.height).count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_dev_hard_forks() {
+        // dev chains start with the same block version as the other networks
+        let genesis = get_hard_fork_at_height(&Network::Mainnet, 0);
+        assert_eq!((genesis.height, genesis.version), (0, 0));
+        assert_eq!(get_hard_fork_at_height(&Network::Dev, 0).version, genesis.version);
+
+        // the next versions are not scheduled on the other networks
+        for network in [Network::Mainnet, Network::Testnet] {
+            assert_eq!(get_upcoming_hard_forks(&network, 0).count(), 0);
+        }
+        assert!(get_upcoming_hard_forks(&Network::Dev, 0).count() > 0);
+
+        let genesis = get_hard_fork_at_height(&Network::Dev, 0);
+        assert!(genesis.smart_contracts && genesis.name_service);
+        assert!(!get_hard_fork_at_height(&Network::Mainnet, 0).smart_contracts);
+    }
+
+    #[test]
+    fn test_aux_commitment_hard_fork() {
+        // the auxiliary commitment has its own block version, only scheduled on the dev network
+        assert!(get_hard_forks(&Network::Dev).iter().any(|fork| fork.version == AUX_COMMITMENT_VERSION));
+        for network in [Network::Mainnet, Network::Testnet] {
+            assert!(get_version_at_height(&network, u64::MAX) < AUX_COMMITMENT_VERSION);
+        }
+    }
+
+    #[test]
+    fn test_pow_seed_not_used() {
+        // no memory-hard algorithm is scheduled, the seed isn't exposed on any network
+        for network in [Network::Mainnet, Network::Testnet, Network::Dev] {
+            assert!(!is_pow_seed_used(&network));
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::error::BlockchainError;
-use std::collections::HashMap;
+use crate::config::{MEMPOOL_MAX_ORPHANS, MEMPOOL_MAX_ORPHANS_PER_ACCOUNT, MEMPOOL_ORPHAN_EXPIRATION, MEMPOOL_TX_TTL};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use indexmap::IndexSet;
 use log::{trace, debug, warn};
@@ -28,20 +29,133 @@ pub struct NonceCache {
     txs: IndexSet<Arc<Hash>>,
 }
 
+// TX with a nonce ahead of the next one expected for its owner
+// it is verified and added to the mempool once the gap is filled
+#[derive(serde::Serialize)]
+pub struct OrphanTx {
+    hash: Hash,
+    tx: Arc<Transaction>,
+    first_seen: u64
+}
+
 #[derive(serde::Serialize)]
 pub struct Mempool {
     // store all txs waiting to be included in a block
     txs: HashMap<Arc<Hash>, SortedTx>,
     // store all sender's nonce for faster finding
-    nonces_cache: HashMap<PublicKey, NonceCache>
+    nonces_cache: HashMap<PublicKey, NonceCache>,
+    // txs waiting for a previous nonce, ordered by nonce for each sender
+    orphans: HashMap<PublicKey, BTreeMap<u64, OrphanTx>>,
+    orphans_count: usize
 }
 
 impl Mempool {
     pub fn new() -> Self {
         Mempool {
             txs: HashMap::new(),
-            nonces_cache: HashMap::new()
+            nonces_cache: HashMap::new(),
+            orphans: HashMap::new(),
+            orphans_count: 0
+        }
+    }
+
+    // Signature must be verified before calling this function, others checks are made once the gap is filled
+    // a TX with the same nonce is replaced only if the new one has a higher fee
+    pub fn add_orphan_tx(&mut self, hash: Hash, tx: Arc<Transaction>) -> Result<(), BlockchainError> {
+        let orphans = self.orphans.get(tx.get_owner());
+        if let Some(orphan) = orphans.and_then(|orphans| orphans.get(&tx.get_nonce())) {
+            if orphan.tx.get_fee() >= tx.get_fee() {
+                return Err(BlockchainError::InvalidTxFee(orphan.tx.get_fee() + 1, tx.get_fee()))
+            }
+        } else {
+            if orphans.map_or(0, |orphans| orphans.len()) >= MEMPOOL_MAX_ORPHANS_PER_ACCOUNT {
+                return Err(BlockchainError::TooManyOrphanTxs(tx.get_owner().clone()))
+            }
+
+            if self.orphans_count >= MEMPOOL_MAX_ORPHANS {
+                return Err(BlockchainError::OrphanTxsLimitReached)
+            }
+            self.orphans_count += 1;
         }
+
+        trace!("Adding orphan TX {} with nonce {} for {}", hash, tx.get_nonce(), tx.get_owner());
+        self.orphans.entry(tx.get_owner().clone()).or_default().insert(tx.get_nonce(), OrphanTx {
+            hash,
+            tx,
+            first_seen: get_current_time()
+        });
+
+        Ok(())
+    }
+
+    pub fn contains_orphan_tx(&self, hash: &Hash) -> bool {
+        self.orphans.values().any(|orphans| orphans.values().any(|orphan| orphan.hash == *hash))
+    }
+
+    // take the orphan TX of this owner with the nonce to add it in mempool
+    pub fn take_orphan_tx(&mut self, key: &PublicKey, nonce: u64) -> Option<OrphanTx> {
+        let orphans = self.orphans.get_mut(key)?;
+        let orphan = orphans.remove(&nonce)?;
+        if orphans.is_empty() {
+            self.orphans.remove(key);
+        }
+        self.orphans_count -= 1;
+
+        Some(orphan)
+    }
+
+    pub fn get_orphan_txs(&self, key: &PublicKey) -> Option<&BTreeMap<u64, OrphanTx>> {
+        self.orphans.get(key)
+    }
+
+    pub fn get_orphans(&self) -> &HashMap<PublicKey, BTreeMap<u64, OrphanTx>> {
+        &self.orphans
+    }
+
+    pub fn get_orphans_count(&self) -> usize {
+        self.orphans_count
+    }
+
+    // delete all expired orphan txs and the ones with a nonce already used
+    // returns the orphan txs that are now following the nonce of their owner
+    pub fn clean_up_orphans(&mut self, nonces: HashMap<PublicKey, u64>) -> Vec<OrphanTx> {
+        let mut promoted = Vec::new();
+        for (key, nonce) in nonces {
+            // next nonce expected for this owner
+            let next_nonce = match self.nonces_cache.get(&key) {
+                Some(cache) => nonce.max(cache.get_max() + 1),
+                None => nonce
+            };
+
+            if let Some(orphans) = self.orphans.get_mut(&key) {
+                let len = orphans.len();
+                orphans.retain(|orphan_nonce, _| *orphan_nonce >= nonce);
+                self.orphans_count -= len - orphans.len();
+                if orphans.is_empty() {
+                    self.orphans.remove(&key);
+                }
+            }
+
+            if let Some(orphan) = self.take_orphan_tx(&key, next_nonce) {
+                promoted.push(orphan);
+            }
+        }
+
+        let now = get_current_time();
+        let mut expired = 0;
+        self.orphans.retain(|_, orphans| {
+            let len = orphans.len();
+            orphans.retain(|_, orphan| orphan.first_seen + MEMPOOL_ORPHAN_EXPIRATION > now);
+            expired += len - orphans.len();
+            !orphans.is_empty()
+        });
+
+        if expired > 0 {
+            debug!("{} orphan TXs expired", expired);
+            self.orphans_count -= expired;
+        }
+
+        promoted
     }
 
     // All checks are made in Blockchain before calling this function
@@ -55,8 +169,8 @@ impl Mempool {
             trace!("Cache found for owner {} with nonce range {}-{}, nonce = {}", tx.get_owner(), cache.get_min(), cache.get_max(), nonce);
             if nonce >= cache.get_min() && nonce <= cache.get_max() {
                 trace!("nonce {} is in range {}-{}", nonce, cache.get_min(), cache.get_max());
-                // because it's based on order and we may have the same order
-                let index = ((nonce - cache.get_min()) % (cache.get_max() - cache.get_min())) as usize;
+                // txs are ordered by nonce, the new one takes the place of the one replaced
+                let index = (nonce - cache.get_min()) as usize;
                 cache.txs.insert(hash.clone());
                 must_update = false;
 
@@ -99,6 +213,20 @@ impl Mempool {
         Ok(())
     }
 
+    // TX of the same owner using the nonce of this one, which would be replaced by it
+    // the new TX must pay a higher fee than the one replaced
+    pub fn get_replaced_tx(&self, tx: &Transaction) -> Result<Option<Hash>, BlockchainError> {
+        let Some(hash) = self.get_cached_nonce(tx.get_owner()).and_then(|cache| cache.has_tx_with_same_nonce(tx.get_nonce())) else {
+            return Ok(None)
+        };
+
+        let other_tx = self.view_tx(hash)?;
+        if other_tx.get_fee() >= tx.get_fee() {
+            return Err(BlockchainError::InvalidTxFee(other_tx.get_fee() + 1, tx.get_fee()))
+        }
+        Ok(Some(hash.as_ref().clone()))
+    }
+
     pub fn get_nonces_cache(&self) -> &HashMap<PublicKey, NonceCache> {
         &self.nonces_cache
     }
@@ -140,13 +268,17 @@ impl Mempool {
     pub fn clear(&mut self) {
         self.txs.clear();
         self.nonces_cache.clear();
+        self.orphans.clear();
+        self.orphans_count = 0;
     }
 
     // delete all old txs not compatible anymore with current state of account
-    pub async fn clean_up(&mut self, nonces: HashMap<PublicKey, u64>) {
+    // returns all the deleted txs
+    pub async fn clean_up(&mut self, nonces: HashMap<PublicKey, u64>) -> Vec<(Arc<Hash>, SortedTx)> {
+        let mut deleted = Vec::new();
         if self.nonces_cache.is_empty() || nonces.is_empty() {
             debug!("No mempool cleanup needed");
-            return;
+            return deleted;
         }
 
         debug!("Cleaning up mempool ({} accounts)...", nonces.len());
@@ -177,10 +309,16 @@ impl Mempool {
 
                     // delete the nonce cache if no txs are left
                     delete_cache = cache.txs.is_empty();
+                    // the lowest nonce left is used to find a tx by its nonce
+                    if let Some(min) = cache.txs.iter().filter_map(|hash| self.txs.get(hash)).map(|tx| tx.get_tx().get_nonce()).min() {
+                        cache.min = min;
+                    }
 
                     // now delete all necessary txs
                     for hash in hashes {
-                        if self.txs.remove(&hash).is_none() {
+                        if let Some(sorted_tx) = self.txs.remove(&hash) {
+                            deleted.push((hash, sorted_tx));
+                        } else {
                             warn!("TX {} not found in mempool while deleting", hash);
                         }
                     }
@@ -192,6 +330,45 @@ impl Mempool {
                 self.nonces_cache.remove(&key);
             }
         }
+
+        deleted
+    }
+
+    // delete all txs that can't be executed after this topoheight, or in mempool for too long if they don't have an expiration
+    // txs of the same owner with a higher nonce are also deleted as they can't be executed without them
+    pub fn clean_up_expired(&mut self, topoheight: u64) -> Vec<(Arc<Hash>, SortedTx)> {
+        let now = get_current_time();
+        let mut deleted = Vec::new();
+        self.nonces_cache.retain(|key, cache| {
+            let expired = cache.txs.iter()
+                .filter_map(|hash| self.txs.get(hash))
+                .filter(|tx| tx.is_expired(topoheight, now))
+                .map(|tx| tx.get_tx().get_nonce())
+                .min();
+
+            let Some(nonce) = expired else {
+                return true
+            };
+
+            trace!("TX with nonce {} of {} is expired", nonce, key);
+            cache.txs.retain(|hash| {
+                let delete = self.txs.get(hash).is_none_or(|tx| tx.get_tx().get_nonce() >= nonce);
+                if delete {
+                    if let Some(sorted_tx) = self.txs.remove(hash) {
+                        deleted.push((Arc::clone(hash), sorted_tx));
+                    }
+                }
+                !delete
+            });
+
+            if cache.txs.is_empty() {
+                return false
+            }
+            cache.max = nonce - 1;
+            true
+        });
+
+        deleted
     }
 }
 
@@ -212,11 +389,37 @@ impl SortedTx {
         self.first_seen
     }
 
+    // the next block is above this topoheight
+    pub fn is_expired(&self, topoheight: u64, now: u64) -> bool {
+        match self.tx.get_expiration() {
+            Some(_) => self.tx.is_expired_at(topoheight + 1),
+            None => self.first_seen + MEMPOOL_TX_TTL <= now
+        }
+    }
+
     pub fn consume(self) -> Arc<Transaction> {
         self.tx
     }
 }
 
+impl OrphanTx {
+    pub fn get_hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    pub fn get_tx(&self) -> &Arc<Transaction> {
+        &self.tx
+    }
+
+    pub fn get_first_seen(&self) -> u64 {
+        self.first_seen
+    }
+
+    pub fn consume(self) -> (Hash, Arc<Transaction>) {
+        (self.hash, self.tx)
+    }
+}
+
 impl NonceCache {
     pub fn get_min(&self) -> u64 {
         self.min
@@ -253,12 +456,121 @@ impl NonceCache {
         }
 
         trace!("has tx with same nonce: {}, max: {}, min: {}, size: {}", nonce, self.max, self.min, self.txs.len());
-        let mut r = self.max - self.min;
-        if r == 0 {
-            r = self.txs.len() as u64;
+        // txs are ordered by nonce
+        self.txs.get_index((nonce - self.min) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+    use xelis_common::{
+        crypto::{key::KeyPair, hash::{Hash, Hashable}},
+        transaction::{Transaction, TransactionType}
+    };
+    use crate::core::error::BlockchainError;
+    use super::Mempool;
+
+    fn _create_tx(keypair: &KeyPair, fee: u64, nonce: u64) -> (Hash, Arc<Transaction>) {
+        let data = TransactionType::Burn { asset: Hash::zero(), amount: 1 };
+        let tx = Transaction::new(keypair.get_public_key().clone(), data, fee, nonce, keypair.sign(b"test"));
+        (tx.hash(), Arc::new(tx))
+    }
+
+    #[test]
+    fn test_replace_only_pending_tx() {
+        let keypair = KeyPair::new();
+        let mut mempool = Mempool::new();
+        let (old_hash, old) = _create_tx(&keypair, 1000, 5);
+        let (new_hash, new) = _create_tx(&keypair, 2000, 5);
+
+        mempool.add_tx(old_hash.clone(), old).unwrap();
+        mempool.add_tx(new_hash.clone(), new).unwrap();
+
+        assert_eq!(mempool.size(), 1);
+        assert!(!mempool.contains_tx(&old_hash));
+        let cache = mempool.get_cached_nonce(keypair.get_public_key()).unwrap();
+        assert_eq!((cache.get_min(), cache.get_max()), (5, 5));
+        assert_eq!(cache.has_tx_with_same_nonce(5).map(|hash| hash.as_ref()), Some(&new_hash));
+    }
+
+    #[test]
+    fn test_replaced_tx() {
+        let keypair = KeyPair::new();
+        let mut mempool = Mempool::new();
+        let (hash, tx) = _create_tx(&keypair, 1000, 5);
+        mempool.add_tx(hash.clone(), tx).unwrap();
+
+        // same nonce with a higher fee
+        let (_, new) = _create_tx(&keypair, 2000, 5);
+        assert_eq!(mempool.get_replaced_tx(&new).unwrap(), Some(hash));
+
+        // same nonce without a higher fee
+        let (_, new) = _create_tx(&keypair, 1000, 5);
+        assert!(matches!(mempool.get_replaced_tx(&new), Err(BlockchainError::InvalidTxFee(1001, 1000))));
+
+        // next nonce or another owner
+        let (_, next) = _create_tx(&keypair, 2000, 6);
+        assert_eq!(mempool.get_replaced_tx(&next).unwrap(), None);
+        let (_, other) = _create_tx(&KeyPair::new(), 2000, 5);
+        assert_eq!(mempool.get_replaced_tx(&other).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_clean_up_partial_range() {
+        let keypair = KeyPair::new();
+        let mut mempool = Mempool::new();
+        let txs: Vec<_> = (0..4).map(|nonce| _create_tx(&keypair, 1000, nonce)).collect();
+        for (hash, tx) in &txs {
+            mempool.add_tx(hash.clone(), tx.clone()).unwrap();
         }
 
-        let index = ((nonce - self.min) % r) as usize;
-        self.txs.get_index(index)
+        // nonces 0 and 1 were included in a block
+        let deleted = mempool.clean_up(HashMap::from([(keypair.get_public_key().clone(), 2)])).await;
+        assert_eq!(deleted.len(), 2);
+        assert_eq!(mempool.size(), 2);
+
+        let cache = mempool.get_cached_nonce(keypair.get_public_key()).unwrap();
+        assert_eq!((cache.get_min(), cache.get_max()), (2, 3));
+        assert_eq!(cache.has_tx_with_same_nonce(2).map(|hash| hash.as_ref()), Some(&txs[2].0));
+        assert_eq!(cache.has_tx_with_same_nonce(3).map(|hash| hash.as_ref()), Some(&txs[3].0));
+
+        // the remaining txs can still be replaced
+        let (hash, tx) = _create_tx(&keypair, 2000, 3);
+        mempool.add_tx(hash.clone(), tx).unwrap();
+        assert_eq!(mempool.size(), 2);
+        assert!(!mempool.contains_tx(&txs[3].0));
+        let cache = mempool.get_cached_nonce(keypair.get_public_key()).unwrap();
+        assert_eq!(cache.has_tx_with_same_nonce(3).map(|hash| hash.as_ref()), Some(&hash));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_clean_up_expired_boundary() {
+        let keypair = KeyPair::new();
+        let mut mempool = Mempool::new();
+        let expiration = 100;
+        let txs: Vec<_> = (0..2).map(|nonce| {
+            let data = TransactionType::Burn { asset: Hash::zero(), amount: 1 };
+            let mut tx = Transaction::new(keypair.get_public_key().clone(), data, 1000, nonce, keypair.sign(b"test"));
+            tx.set_expiration(expiration);
+            (tx.hash(), Arc::new(tx))
+        }).collect();
+        for (hash, tx) in &txs {
+            mempool.add_tx(hash.clone(), tx.clone()).unwrap();
+        }
+
+        // a block can still execute it at the expiration topoheight
+        assert!(!txs[0].1.is_expired_at(expiration));
+        assert!(txs[0].1.is_expired_at(expiration + 1));
+
+        // so it's kept while the next block can be at the expiration topoheight
+        assert!(mempool.clean_up_expired(expiration - 1).is_empty());
+        assert_eq!(mempool.size(), 2);
+
+        // and deleted with the following txs once the next block is above it
+        let deleted = mempool.clean_up_expired(expiration);
+        assert_eq!(deleted.len(), 2);
+        assert_eq!(mempool.size(), 0);
+        assert!(mempool.get_cached_nonce(keypair.get_public_key()).is_none());
+    }
+}
@@ -3,4 +3,9 @@ pub mod mempool;
 pub mod error;
 pub mod blockdag;
 pub mod storage;
-pub mod difficulty;
\ No newline at end of file
+pub mod difficulty;
+pub mod hard_fork;
+pub mod time_sync;
+pub mod template;
+pub mod signature_verifier;pub mod genesis;
+pub mod split_monitor;
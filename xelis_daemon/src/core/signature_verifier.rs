@@ -0,0 +1,198 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc::{self, Receiver, SyncSender, TrySendError}, Arc, Mutex},
+    thread
+};
+use log::{debug, error, trace};
+use lru::LruCache;
+use tokio::sync::oneshot;
+use xelis_common::{crypto::hash::Hash, transaction::Transaction};
+use crate::config::{SIGNATURE_VERIFIER_CACHE_SIZE, SIGNATURE_VERIFIER_QUEUE_SIZE};
+
+struct Job {
+    hash: Hash,
+    tx: Arc<Transaction>
+}
+
+// results shared between the workers and the callers
+struct State {
+    // result of each TX already verified, the hash of a TX includes its signature
+    verified: Mutex<LruCache<Hash, bool>>,
+    // callers waiting for a TX queued or being verified
+    pending: Mutex<HashMap<Hash, Vec<oneshot::Sender<bool>>>>
+}
+
+impl State {
+    fn set_result(&self, hash: Hash, valid: bool) {
+        match self.verified.lock() {
+            Ok(mut verified) => {
+                verified.put(hash.clone(), valid);
+            },
+            Err(e) => error!("Error while caching the signature result of TX {}: {}", hash, e)
+        };
+
+        let waiters = match self.pending.lock() {
+            Ok(mut pending) => pending.remove(&hash),
+            Err(e) => {
+                error!("Error while notifying the signature result of TX {}: {}", hash, e);
+                None
+            }
+        };
+        for waiter in waiters.into_iter().flatten() {
+            // the caller may not wait anymore
+            let _ = waiter.send(valid);
+        }
+    }
+}
+
+// Pool of threads verifying the signatures of the TXs received for the mempool
+// TXs can be queued as soon as they are received so their signature is already verified
+// when they are added to the mempool, without blocking the task handling them
+pub struct SignatureVerifier {
+    sender: SyncSender<Job>,
+    state: Arc<State>
+}
+
+impl SignatureVerifier {
+    // threads are stopped once the verifier is dropped
+    pub fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(SIGNATURE_VERIFIER_QUEUE_SIZE);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let state = Arc::new(State {
+            verified: Mutex::new(LruCache::new(SIGNATURE_VERIFIER_CACHE_SIZE)),
+            pending: Mutex::new(HashMap::new())
+        });
+
+        for i in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+            let state = Arc::clone(&state);
+            let result = thread::Builder::new()
+                .name(format!("signature-verifier-{}", i))
+                .spawn(move || Self::worker(receiver, state));
+
+            if let Err(e) = result {
+                error!("Error while starting signature verifier thread #{}: {}", i, e);
+            }
+        }
+
+        Self {
+            sender,
+            state
+        }
+    }
+
+    fn worker(receiver: Arc<Mutex<Receiver<Job>>>, state: Arc<State>) {
+        loop {
+            let job = match receiver.lock() {
+                Ok(receiver) => receiver.recv(),
+                Err(_) => break
+            };
+
+            let Ok(Job { hash, tx }) = job else {
+                break
+            };
+
+            trace!("Verifying signature of TX {}", hash);
+            let valid = tx.verify_signature();
+            state.set_result(hash, valid);
+        }
+        debug!("Signature verifier thread stopped");
+    }
+
+    fn get_result(&self, hash: &Hash) -> Option<bool> {
+        self.state.verified.lock().ok()?.get(hash).copied()
+    }
+
+    // register a caller for this TX, returns true if the TX must be queued
+    fn add_waiter(&self, hash: &Hash, waiter: Option<oneshot::Sender<bool>>) -> bool {
+        let Ok(mut pending) = self.state.pending.lock() else {
+            return false
+        };
+
+        let queue = !pending.contains_key(hash);
+        let waiters = pending.entry(hash.clone()).or_default();
+        if let Some(waiter) = waiter {
+            waiters.push(waiter);
+        }
+        queue
+    }
+
+    // returns false if the queue is full
+    fn queue(&self, hash: &Hash, tx: &Arc<Transaction>) -> bool {
+        match self.sender.try_send(Job { hash: hash.clone(), tx: Arc::clone(tx) }) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                debug!("Signature verifier queue is full, TX {} is not queued", hash);
+                if let Ok(mut pending) = self.state.pending.lock() {
+                    pending.remove(hash);
+                }
+                false
+            }
+        }
+    }
+
+    // queue the TX to verify its signature in background, ignored if it is already verified or queued
+    pub fn submit(&self, hash: &Hash, tx: &Arc<Transaction>) {
+        if self.get_result(hash).is_none() && self.add_waiter(hash, None) {
+            self.queue(hash, tx);
+        }
+    }
+
+    // returns true if the signature of the TX is valid
+    // the result is awaited if the TX is already queued, and it is verified directly if the queue is full
+    pub async fn verify(&self, hash: &Hash, tx: &Arc<Transaction>) -> bool {
+        if let Some(valid) = self.get_result(hash) {
+            trace!("Signature of TX {} is already verified", hash);
+            return valid
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        if self.add_waiter(hash, Some(sender)) && !self.queue(hash, tx) {
+            return tx.verify_signature()
+        }
+
+        match receiver.await {
+            Ok(valid) => valid,
+            Err(_) => tx.verify_signature()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use xelis_common::{
+        crypto::{key::KeyPair, hash::{Hash, Hashable}},
+        transaction::{Transaction, TransactionType}
+    };
+    use super::SignatureVerifier;
+
+    fn _create_tx(keypair: &KeyPair, valid: bool) -> (Hash, Arc<Transaction>) {
+        let data = TransactionType::Burn { asset: Hash::zero(), amount: 1 };
+        let unsigned = Transaction::new(keypair.get_public_key().clone(), data.clone(), 0, 0, keypair.sign(b"test"));
+        let signature = if valid {
+            keypair.sign(unsigned.get_signing_hash().as_bytes())
+        } else {
+            keypair.sign(b"test")
+        };
+        let tx = Transaction::new(keypair.get_public_key().clone(), data, 0, 0, signature);
+        (tx.hash(), Arc::new(tx))
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let verifier = SignatureVerifier::new(2);
+        let keypair = KeyPair::new();
+        let (valid_hash, valid) = _create_tx(&keypair, true);
+        let (invalid_hash, invalid) = _create_tx(&keypair, false);
+
+        verifier.submit(&valid_hash, &valid);
+        assert!(verifier.verify(&valid_hash, &valid).await);
+        assert!(!verifier.verify(&invalid_hash, &invalid).await);
+
+        // results are cached
+        assert_eq!(verifier.get_result(&valid_hash), Some(true));
+        assert_eq!(verifier.get_result(&invalid_hash), Some(false));
+        assert!(verifier.state.pending.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+use std::{borrow::Cow, time::Duration};
+use log::{debug, error, info, warn};
+use reqwest::Client;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use xelis_common::{
+    api::daemon::{ChainSplitAlert, DaemonEvent, GetChainSplitStatusResult, NotifyEvent, SplitMonitorNodeEntry},
+    crypto::hash::Hash,
+    json_rpc::{DaemonClient, JsonRPCError},
+    network::Network,
+    utils::get_current_time
+};
+use crate::config::SPLIT_MONITOR_WEBHOOK_TIMEOUT;
+use super::{blockchain::Blockchain, error::BlockchainError, storage::Storage};
+
+#[derive(Error, Debug)]
+pub enum SplitMonitorError {
+    #[error("Reference node is on network {} instead of {}", _0, _1)]
+    NetworkMismatch(Network, Network),
+    #[error("No block ordered at stable height {}", _0)]
+    NoOrderedBlock(u64),
+    #[error(transparent)]
+    Rpc(#[from] JsonRPCError),
+    #[error(transparent)]
+    Blockchain(#[from] BlockchainError)
+}
+
+// result of the last check of a reference node
+#[derive(Default)]
+struct NodeState {
+    last_check: u64,
+    compared_topoheight: Option<u64>,
+    alert: Option<ChainSplitAlert>,
+    error: Option<String>
+}
+
+struct ReferenceNode {
+    url: String,
+    client: DaemonClient,
+    state: RwLock<NodeState>
+}
+
+// Compare periodically our stable chain with the one of reference nodes (daemons or explorers exposing the daemon JSON-RPC API)
+// a block at or below the stable height can't be reorganized anymore, so a different hash means
+// that one of the nodes is on another chain and needs the attention of the operator
+pub struct SplitMonitor {
+    nodes: Vec<ReferenceNode>,
+    webhook: Option<String>,
+    http: Client
+}
+
+impl SplitMonitor {
+    pub fn new(urls: Vec<String>, webhook: Option<String>) -> Self {
+        Self {
+            nodes: urls.into_iter().map(|url| ReferenceNode {
+                client: DaemonClient::new(url.clone()),
+                url,
+                state: RwLock::new(NodeState::default())
+            }).collect(),
+            webhook,
+            http: Client::builder()
+                .timeout(Duration::from_secs(SPLIT_MONITOR_WEBHOOK_TIMEOUT))
+                .build()
+                .unwrap_or_default()
+        }
+    }
+
+    // compare our chain with each reference node and raise the alerts of the new splits
+    pub async fn check<S: Storage>(&self, blockchain: &Blockchain<S>) {
+        for node in &self.nodes {
+            let res = self.compare(node, blockchain).await;
+            let mut state = node.state.write().await;
+            state.last_check = get_current_time();
+            match res {
+                Ok((topoheight, alert)) => {
+                    state.compared_topoheight = Some(topoheight);
+                    state.error = None;
+                    match (state.alert.is_some(), alert) {
+                        (false, Some(alert)) => {
+                            error!("CHAIN SPLIT DETECTED with reference node {}: block at stable topoheight {} is {} locally but {} on the reference node, chains are different since topoheight {}. Your node may be on another chain than the network!", node.url, alert.topoheight, alert.local_hash, alert.reference_hash, alert.fork_topoheight);
+                            self.raise_alert(blockchain, &alert).await;
+                            state.alert = Some(alert);
+                        },
+                        (true, Some(mut alert)) => {
+                            // keep the time of the first detection
+                            if let Some(previous) = state.alert.as_ref() {
+                                alert.detected_at = previous.detected_at;
+                            }
+                            state.alert = Some(alert);
+                        },
+                        (true, None) => {
+                            info!("Chain split with reference node {} is resolved, chains are the same at stable topoheight {}", node.url, topoheight);
+                            state.alert = None;
+                        },
+                        (false, None) => debug!("Chain is the same as reference node {} at stable topoheight {}", node.url, topoheight)
+                    };
+                },
+                Err(e) => {
+                    // the last alert is kept until the node can be compared again
+                    warn!("Error while comparing our chain with reference node {}: {}", node.url, e);
+                    state.error = Some(e.to_string());
+                }
+            };
+        }
+    }
+
+    // returns the topoheight compared and the alert if the chains are different
+    async fn compare<S: Storage>(&self, node: &ReferenceNode, blockchain: &Blockchain<S>) -> Result<(u64, Option<ChainSplitAlert>), SplitMonitorError> {
+        let info = node.client.get_info().await?;
+        if info.network != *blockchain.get_network() {
+            return Err(SplitMonitorError::NetworkMismatch(info.network, *blockchain.get_network()))
+        }
+
+        // highest height which is stable on both nodes
+        let height = info.stableheight.min(blockchain.get_stable_height());
+        let (topoheight, lowest_topoheight, local_hash) = {
+            let storage = blockchain.get_storage().read().await;
+            let mut topoheight = None;
+            for hash in storage.get_blocks_at_height(height).await? {
+                if storage.is_block_topological_ordered(&hash).await {
+                    let block_topoheight = storage.get_topo_height_for_hash(&hash).await?;
+                    topoheight = Some(topoheight.map_or(block_topoheight, |t: u64| t.min(block_topoheight)));
+                }
+            }
+            let topoheight = topoheight.ok_or(SplitMonitorError::NoOrderedBlock(height))?;
+            // blocks below a pruned topoheight can't be compared
+            let lowest_topoheight = storage.get_pruned_topoheight()?.max(info.pruned_topoheight).unwrap_or(0).min(topoheight);
+            (topoheight, lowest_topoheight, storage.get_hash_at_topo_height(topoheight).await?)
+        };
+
+        let reference_hash = self.get_reference_hash(node, topoheight).await?;
+        if local_hash == reference_hash {
+            return Ok((topoheight, None))
+        }
+
+        // search the first topoheight at which the chains are different
+        let (mut low, mut high) = (lowest_topoheight, topoheight);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let local = blockchain.get_storage().read().await.get_hash_at_topo_height(mid).await?;
+            if local != self.get_reference_hash(node, mid).await? {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+
+        Ok((topoheight, Some(ChainSplitAlert {
+            url: node.url.clone(),
+            topoheight,
+            local_hash,
+            reference_hash,
+            fork_topoheight: low,
+            detected_at: get_current_time()
+        })))
+    }
+
+    async fn get_reference_hash(&self, node: &ReferenceNode, topoheight: u64) -> Result<Hash, SplitMonitorError> {
+        let block = node.client.get_block_at_topoheight(topoheight).await?;
+        Ok(block.data.hash.into_owned())
+    }
+
+    // notify the RPC clients subscribed and call the webhook if configured
+    async fn raise_alert<S: Storage>(&self, blockchain: &Blockchain<S>, alert: &ChainSplitAlert) {
+        if let Some(rpc) = blockchain.get_rpc().read().await.as_ref() {
+            if rpc.is_event_tracked(&NotifyEvent::ChainSplitDetected).await {
+                rpc.notify_clients(&DaemonEvent::ChainSplitDetected(alert.clone())).await;
+            }
+        }
+
+        if let Some(url) = self.webhook.clone() {
+            let request = self.http.post(&url).json(alert);
+            tokio::spawn(async move {
+                match request.send().await {
+                    Ok(response) if response.status().is_success() => debug!("Chain split alert delivered to webhook {}", url),
+                    Ok(response) => warn!("Chain split alert to webhook {} failed: HTTP status {}", url, response.status()),
+                    Err(e) => warn!("Chain split alert to webhook {} failed: {}", url, e)
+                };
+            });
+        }
+    }
+
+    pub async fn get_status(&self) -> GetChainSplitStatusResult<'_> {
+        let mut split_detected = false;
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let state = node.state.read().await;
+            split_detected |= state.alert.is_some();
+            nodes.push(SplitMonitorNodeEntry {
+                url: Cow::Borrowed(&node.url),
+                last_check: state.last_check,
+                compared_topoheight: state.compared_topoheight,
+                alert: state.alert.clone().map(Cow::Owned),
+                error: state.error.clone().map(Cow::Owned)
+            });
+        }
+
+        GetChainSplitStatusResult {
+            split_detected,
+            nodes
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use chacha20poly1305::{aead::{Aead, OsRng, Payload}, AeadCore, KeyInit, XChaCha20Poly1305, XNonce};
+use crate::core::error::BlockchainError;
+
+pub const STORAGE_KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 24;
+
+// Encryption of the values saved on disk when the node runs with a storage key
+// the key of the value in its tree is authenticated, so a value can't be moved to another key
+pub struct StorageCipher {
+    cipher: XChaCha20Poly1305
+}
+
+impl StorageCipher {
+    pub fn new(key: &[u8; STORAGE_KEY_SIZE]) -> Self {
+        Self {
+            cipher: XChaCha20Poly1305::new(key.into())
+        }
+    }
+
+    // a random nonce is generated for each value and saved before it
+    pub fn encrypt(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, BlockchainError> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let data = self.cipher.encrypt(&nonce, Payload { msg: value, aad: key })
+            .map_err(|_| BlockchainError::StorageDecryptionFailed)?;
+
+        let mut encrypted = Vec::with_capacity(NONCE_SIZE + data.len());
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&data);
+        Ok(encrypted)
+    }
+
+    pub fn decrypt(&self, key: &[u8], encrypted: &[u8]) -> Result<Vec<u8>, BlockchainError> {
+        if encrypted.len() <= NONCE_SIZE {
+            return Err(BlockchainError::StorageDecryptionFailed)
+        }
+
+        let nonce = XNonce::from_slice(&encrypted[0..NONCE_SIZE]);
+        self.cipher.decrypt(nonce, Payload { msg: &encrypted[NONCE_SIZE..], aad: key })
+            .map_err(|_| BlockchainError::StorageDecryptionFailed)
+    }
+}
+
+// the key file contains the key in hexadecimal, surrounding whitespaces are ignored
+pub fn load_storage_key(path: &str) -> Result<[u8; STORAGE_KEY_SIZE], BlockchainError> {
+    let invalid = || BlockchainError::InvalidStorageKeyFile(path.to_owned(), STORAGE_KEY_SIZE);
+    let content = fs::read_to_string(path).map_err(|_| invalid())?;
+    let bytes = hex::decode(content.trim()).map_err(|_| invalid())?;
+    bytes.try_into().map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let cipher = StorageCipher::new(&[7u8; STORAGE_KEY_SIZE]);
+        let encrypted = cipher.encrypt(b"key", b"value").unwrap();
+        assert_ne!(&encrypted[NONCE_SIZE..], b"value");
+        assert_eq!(cipher.decrypt(b"key", &encrypted).unwrap(), b"value");
+
+        // a new nonce is used each time
+        assert_ne!(cipher.encrypt(b"key", b"value").unwrap(), encrypted);
+    }
+
+    #[test]
+    fn test_decrypt_invalid() {
+        let cipher = StorageCipher::new(&[7u8; STORAGE_KEY_SIZE]);
+        let encrypted = cipher.encrypt(b"key", b"value").unwrap();
+
+        // moved to another key
+        assert!(cipher.decrypt(b"other", &encrypted).is_err());
+        // another storage key
+        assert!(StorageCipher::new(&[8u8; STORAGE_KEY_SIZE]).decrypt(b"key", &encrypted).is_err());
+        // altered
+        let mut altered = encrypted.clone();
+        *altered.last_mut().unwrap() ^= 1;
+        assert!(cipher.decrypt(b"key", &altered).is_err());
+        assert!(cipher.decrypt(b"key", &encrypted[..NONCE_SIZE]).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Mutex
+};
+use sled::Tree;
+use xelis_common::{crypto::hash::Hash, serializer::{Reader, Writer}};
+use crate::core::error::BlockchainError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColdKind {
+    BlockHeader,
+    Transaction
+}
+
+impl ColdKind {
+    fn get_prefix(&self) -> u8 {
+        match self {
+            Self::BlockHeader => 0,
+            Self::Transaction => 1
+        }
+    }
+}
+
+// Append-only file storing the old block headers and transactions moved out of sled
+// the position and size of each record are saved in an index tree to read it directly
+// a deleted record is only removed from the index, its space in the file is not reclaimed
+pub struct ColdStore {
+    path: String,
+    index: Tree,
+    // opened on first use, the file doesn't exist until something is moved to it
+    file: Mutex<Option<File>>
+}
+
+impl ColdStore {
+    pub fn new(path: String, index: Tree) -> Self {
+        Self {
+            path,
+            index,
+            file: Mutex::new(None)
+        }
+    }
+
+    fn get_key(kind: ColdKind, hash: &Hash) -> [u8; 33] {
+        let mut key = [0; 33];
+        key[0] = kind.get_prefix();
+        key[1..].copy_from_slice(hash.as_bytes());
+        key
+    }
+
+    // returns the offset and the size of the record
+    fn get_position(&self, kind: ColdKind, hash: &Hash) -> Result<Option<(u64, u32)>, BlockchainError> {
+        match self.index.get(Self::get_key(kind, hash))? {
+            Some(value) => {
+                let mut reader = Reader::new(&value);
+                Ok(Some((reader.read_u64()?, reader.read_u32()?)))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn with_file<T>(&self, f: impl FnOnce(&mut File) -> Result<T, BlockchainError>) -> Result<T, BlockchainError> {
+        let mut guard = self.file.lock()?;
+        let mut file = match guard.take() {
+            Some(file) => file,
+            None => OpenOptions::new().read(true).append(true).create(true).open(&self.path)?
+        };
+        let result = f(&mut file);
+        *guard = Some(file);
+        result
+    }
+
+    pub fn contains(&self, kind: ColdKind, hash: &Hash) -> Result<bool, BlockchainError> {
+        Ok(self.index.contains_key(Self::get_key(kind, hash))?)
+    }
+
+    pub fn get_size(&self, kind: ColdKind, hash: &Hash) -> Result<Option<usize>, BlockchainError> {
+        Ok(self.get_position(kind, hash)?.map(|(_, size)| size as usize))
+    }
+
+    pub fn get(&self, kind: ColdKind, hash: &Hash) -> Result<Option<Vec<u8>>, BlockchainError> {
+        let (offset, size) = match self.get_position(kind, hash)? {
+            Some(position) => position,
+            None => return Ok(None)
+        };
+
+        self.with_file(|file| {
+            let mut bytes = vec![0; size as usize];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut bytes)?;
+            Ok(Some(bytes))
+        })
+    }
+
+    // records are written and synced to the file before being indexed
+    // so an interrupted write only leaves unreachable bytes in the file
+    pub fn append(&self, records: &[(ColdKind, Hash, Vec<u8>)]) -> Result<(), BlockchainError> {
+        if records.is_empty() {
+            return Ok(())
+        }
+
+        let positions = self.with_file(|file| {
+            let mut positions = Vec::with_capacity(records.len());
+            let mut offset = file.seek(SeekFrom::End(0))?;
+            for (_, _, bytes) in records {
+                file.write_all(bytes)?;
+                positions.push((offset, bytes.len() as u32));
+                offset += bytes.len() as u64;
+            }
+            file.sync_data()?;
+            Ok(positions)
+        })?;
+
+        for ((kind, hash, _), (offset, size)) in records.iter().zip(positions) {
+            let mut writer = Writer::new();
+            writer.write_u64(&offset);
+            writer.write_u32(&size);
+            self.index.insert(Self::get_key(*kind, hash), writer.bytes())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(&self, kind: ColdKind, hash: &Hash) -> Result<Option<Vec<u8>>, BlockchainError> {
+        let bytes = self.get(kind, hash)?;
+        if bytes.is_some() {
+            self.index.remove(Self::get_key(kind, hash))?;
+        }
+        Ok(bytes)
+    }
+
+    pub fn get_size_on_disk(&self) -> Result<u64, BlockchainError> {
+        if !Path::new(&self.path).exists() {
+            return Ok(0)
+        }
+        Ok(fs::metadata(&self.path)?.len())
+    }
+
+    // the index is copied with the database, records appended after it are not indexed in the copy
+    pub fn copy_to(&self, path: &str) -> Result<(), BlockchainError> {
+        if Path::new(&self.path).exists() {
+            fs::copy(&self.path, path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xelis_common::crypto::hash::hash;
+
+    fn create_store(name: &str) -> ColdStore {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let path = std::env::temp_dir().join(format!("xelis_cold_{}_{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        ColdStore::new(path.to_string_lossy().into_owned(), db.open_tree("cold_index").unwrap())
+    }
+
+    #[test]
+    fn test_append_and_get() {
+        let store = create_store("append");
+        let (block, tx) = (hash(b"block"), hash(b"tx"));
+        assert!(!store.contains(ColdKind::BlockHeader, &block).unwrap());
+        assert_eq!(store.get_size_on_disk().unwrap(), 0);
+
+        store.append(&[
+            (ColdKind::Transaction, tx.clone(), vec![1, 2, 3]),
+            (ColdKind::BlockHeader, block.clone(), vec![4, 5])
+        ]).unwrap();
+
+        assert!(store.contains(ColdKind::BlockHeader, &block).unwrap());
+        // same hash but another kind of record
+        assert!(!store.contains(ColdKind::Transaction, &block).unwrap());
+        assert_eq!(store.get(ColdKind::Transaction, &tx).unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(store.get(ColdKind::BlockHeader, &block).unwrap(), Some(vec![4, 5]));
+        assert_eq!(store.get_size(ColdKind::Transaction, &tx).unwrap(), Some(3));
+        assert_eq!(store.get_size_on_disk().unwrap(), 5);
+
+        fs::remove_file(&store.path).unwrap();
+    }
+
+    #[test]
+    fn test_remove() {
+        let store = create_store("remove");
+        let tx = hash(b"tx");
+        store.append(&[(ColdKind::Transaction, tx.clone(), vec![7; 10])]).unwrap();
+
+        assert_eq!(store.remove(ColdKind::Transaction, &tx).unwrap(), Some(vec![7; 10]));
+        assert!(!store.contains(ColdKind::Transaction, &tx).unwrap());
+        assert_eq!(store.remove(ColdKind::Transaction, &tx).unwrap(), None);
+        // the file is append-only
+        assert_eq!(store.get_size_on_disk().unwrap(), 10);
+
+        fs::remove_file(&store.path).unwrap();
+    }
+}
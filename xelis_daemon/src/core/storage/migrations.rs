@@ -0,0 +1,243 @@
+use std::{fs, path::Path};
+use log::{debug, error, info, warn};
+use sled::{Db, Tree};
+use xelis_common::{
+    block::BlockHeader,
+    config::XELIS_ASSET,
+    crypto::hash::Hash,
+    name::get_name_registration_cost,
+    serializer::Serializer,
+    transaction::{ReceiptStatus, Transaction, TransactionReceipt, TransactionType}
+};
+use crate::core::error::{BlockchainError, DiskContext};
+use super::{
+    cold::{ColdKind, ColdStore},
+    sled::{SledStorage, get_cold_store_path, NETWORK, TOP_TOPO_HEIGHT, PRUNED_TOPOHEIGHT, ACCOUNTS_COUNT, TXS_COUNT, ASSETS_COUNT, BLOCKS_COUNT}
+};
+
+// Version of the storage layout, saved in the extra tree
+const VERSION: &[u8; 4] = b"VERS";
+
+// Current version of the storage layout
+// Databases created before the layout was versioned don't have the version record and are in version 1
+pub const STORAGE_VERSION: u8 = 4;
+
+// Migration of the storage from a version to the next one
+// It is applied on the database before any tree is opened by the storage
+// the directory path is given to reach the files stored next to the database
+type Migration = fn(&Db, &str) -> Result<(), BlockchainError>;
+
+// migrations ordered by version, the first one is from version 1 to 2
+const MIGRATIONS: [Migration; STORAGE_VERSION as usize - 1] = [
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4
+];
+
+// counters could be missing or out of sync in old databases, compute them again from their trees
+fn migrate_v1_to_v2(db: &Db, _: &str) -> Result<(), BlockchainError> {
+    let extra = db.open_tree("extra")?;
+    for (tree, key) in [("nonces", ACCOUNTS_COUNT), ("transactions", TXS_COUNT), ("assets", ASSETS_COUNT), ("blocks", BLOCKS_COUNT)] {
+        let count = db.open_tree(tree)?.len() as u64;
+        info!("Counted {} entries in {}", count, tree);
+        extra.insert(key, &count.to_be_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn load<T: Serializer>(tree: &Tree, key: &[u8]) -> Result<T, BlockchainError> {
+    let bytes = tree.get(key)?.ok_or(BlockchainError::NotFoundOnDisk(DiskContext::LoadData))?;
+    Ok(T::from_bytes(&bytes)?)
+}
+
+// block headers and transactions could have been moved to the cold store
+fn load_hot_or_cold<T: Serializer>(tree: &Tree, cold: &ColdStore, kind: ColdKind, hash: &Hash) -> Result<T, BlockchainError> {
+    match tree.get(hash.as_bytes())? {
+        Some(bytes) => Ok(T::from_bytes(&bytes)?),
+        None => {
+            let bytes = cold.get(kind, hash)?.ok_or(BlockchainError::NotFoundOnDisk(DiskContext::LoadData))?;
+            Ok(T::from_bytes(&bytes)?)
+        }
+    }
+}
+
+// blocks ordered before the burned supply was tracked don't have it saved,
+// compute it again for each topoheight from the transactions executed in its block
+fn migrate_v2_to_v3(db: &Db, dir_path: &str) -> Result<(), BlockchainError> {
+    let extra = db.open_tree("extra")?;
+    let top_topoheight: u64 = match extra.get(TOP_TOPO_HEIGHT)? {
+        Some(bytes) => u64::from_bytes(&bytes)?,
+        // no block ordered yet
+        None => return Ok(())
+    };
+
+    let hash_at_topo = db.open_tree("hash_at_topo")?;
+    let blocks = db.open_tree("blocks")?;
+    let transactions = db.open_tree("transactions")?;
+    let txs_executed = db.open_tree("txs_executed")?;
+    let tx_receipts = db.open_tree("tx_receipts")?;
+    let burned_supply_tree = db.open_tree("burned_supply")?;
+    let cold = ColdStore::new(get_cold_store_path(dir_path), db.open_tree("cold_index")?);
+
+    // blocks below the pruned topoheight are deleted, the burned supply of the lowest one is kept as the base
+    let (lowest_topoheight, mut burned_supply) = match extra.get(PRUNED_TOPOHEIGHT)? {
+        Some(bytes) => {
+            let pruned_topoheight = u64::from_bytes(&bytes)?;
+            match burned_supply_tree.get(pruned_topoheight.to_be_bytes())? {
+                Some(bytes) => (pruned_topoheight + 1, u64::from_bytes(&bytes)?),
+                None => {
+                    warn!("No burned supply saved at pruned topoheight {}, coins burned before it are not counted", pruned_topoheight);
+                    (pruned_topoheight, 0)
+                }
+            }
+        },
+        None => (0, 0)
+    };
+
+    for topoheight in lowest_topoheight..=top_topoheight {
+        let hash: Hash = load(&hash_at_topo, &topoheight.to_be_bytes())?;
+        let header: BlockHeader = load_hot_or_cold(&blocks, &cold, ColdKind::BlockHeader, &hash)?;
+        for tx_hash in header.get_txs_hashes() {
+            // a transaction is executed only in one of the blocks including it
+            match txs_executed.get(tx_hash.as_bytes())? {
+                Some(bytes) if Hash::from_bytes(&bytes)? == hash => {},
+                _ => continue
+            };
+
+            let tx: Transaction = load_hot_or_cold(&transactions, &cold, ColdKind::Transaction, tx_hash)?;
+            match tx.get_data() {
+                TransactionType::Burn { asset, amount } if *asset == XELIS_ASSET => {
+                    burned_supply += amount;
+                },
+                TransactionType::RegisterName { duration, .. } => {
+                    let receipt: TransactionReceipt = load(&tx_receipts, tx_hash.as_bytes())?;
+                    if receipt.status == ReceiptStatus::Success {
+                        burned_supply += get_name_registration_cost(*duration);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        burned_supply_tree.insert(topoheight.to_be_bytes(), &burned_supply.to_be_bytes())?;
+    }
+    info!("Computed the burned supply of {} blocks", top_topoheight + 1 - lowest_topoheight);
+
+    Ok(())
+}
+
+// index the topoheight at which each contract and contract data was written
+fn migrate_v3_to_v4(db: &Db, _: &str) -> Result<(), BlockchainError> {
+    let contracts_changes = db.open_tree("contracts_changes")?;
+    for el in db.open_tree("contracts")?.iter() {
+        let (key, value) = el?;
+        let topoheight = u64::from_bytes(&value[0..8])?;
+        contracts_changes.insert(SledStorage::get_contract_change_key(topoheight, &key), &[])?;
+    }
+
+    for el in db.open_tree("contracts_data")?.iter().keys() {
+        let key = el?;
+        let topoheight = u64::from_bytes(&key[64..72])?;
+        contracts_changes.insert(SledStorage::get_contract_change_key(topoheight, &key), &[])?;
+    }
+
+    Ok(())
+}
+
+// the backup is kept until all migrations are applied
+fn get_backup_path(dir_path: &str) -> String {
+    format!("{}.migration_backup", dir_path.trim_end_matches('/'))
+}
+
+// copy the database in a temporary directory, then rename it once fully written
+// so an incomplete backup is never used to restore the database
+fn create_backup(db: &Db, backup_path: &str) -> Result<(), BlockchainError> {
+    let tmp_path = format!("{}.tmp", backup_path);
+    if Path::new(&tmp_path).exists() {
+        fs::remove_dir_all(&tmp_path)?;
+    }
+
+    {
+        let backup = sled::open(&tmp_path)?;
+        backup.import(db.export());
+        backup.flush()?;
+    }
+    fs::rename(&tmp_path, backup_path)?;
+
+    Ok(())
+}
+
+// replace the content of the database by the backup and delete it
+fn restore_backup(db: &Db, backup_path: &str) -> Result<(), BlockchainError> {
+    {
+        let backup = sled::open(backup_path)?;
+        for name in db.tree_names() {
+            // the default tree can't be dropped
+            if name == db.name() {
+                db.clear()?;
+            } else {
+                db.drop_tree(&name)?;
+            }
+        }
+        db.import(backup.export());
+        db.flush()?;
+    }
+    fs::remove_dir_all(backup_path)?;
+
+    Ok(())
+}
+
+// apply in order the migrations required to update the database to the current version
+// if one of them fails, the database is restored as it was before the first one
+pub fn apply_migrations(db: &Db, dir_path: &str) -> Result<(), BlockchainError> {
+    let backup_path = get_backup_path(dir_path);
+    if Path::new(&backup_path).exists() {
+        warn!("Previous storage migration was interrupted, restoring the backup from {}", backup_path);
+        restore_backup(db, &backup_path)?;
+    }
+
+    let extra = db.open_tree("extra")?;
+    let version = match extra.get(VERSION)? {
+        Some(value) => u8::from_bytes(&value)?,
+        None if extra.contains_key(NETWORK)? => 1,
+        None => {
+            debug!("New storage, using version {}", STORAGE_VERSION);
+            extra.insert(VERSION, &[STORAGE_VERSION])?;
+            return Ok(())
+        }
+    };
+
+    if version == 0 || version > STORAGE_VERSION {
+        return Err(BlockchainError::UnsupportedStorageVersion(version, STORAGE_VERSION))
+    }
+
+    if version == STORAGE_VERSION {
+        return Ok(())
+    }
+
+    info!("Storage is in version {}, migrating it to version {}", version, STORAGE_VERSION);
+    info!("Creating a backup of the storage in {}, this may take a while", backup_path);
+    create_backup(db, &backup_path)?;
+
+    for version in version..STORAGE_VERSION {
+        info!("Migrating storage from version {} to {}", version, version + 1);
+        let migration = MIGRATIONS[version as usize - 1];
+        let result = migration(db, dir_path).and_then(|_| {
+            extra.insert(VERSION, &[version + 1])?;
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            error!("Error while migrating storage to version {}: {}, restoring the backup", version + 1, e);
+            restore_backup(db, &backup_path)?;
+            return Err(e)
+        }
+    }
+
+    db.flush()?;
+    fs::remove_dir_all(&backup_path)?;
+    info!("Storage has been migrated to version {}", STORAGE_VERSION);
+
+    Ok(())
+}
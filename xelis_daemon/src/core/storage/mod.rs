@@ -1,18 +1,26 @@
 mod sled;
+mod migrations;
+mod cold;
+mod cipher;
 pub use self::sled::SledStorage;
+pub use self::cipher::{StorageCipher, load_storage_key, STORAGE_KEY_SIZE};
 
 use std::{collections::HashSet, sync::Arc};
 use async_trait::async_trait;
 use indexmap::IndexSet;
 use xelis_common::{
-    crypto::{key::PublicKey, hash::Hash},
-    transaction::Transaction,
+    crypto::{key::{PublicKey, KeyPair}, hash::Hash},
+    transaction::{Transaction, TransactionReceipt},
     block::{Block, BlockHeader, Difficulty}, account::{VersionedBalance, VersionedNonce},
     immutable::Immutable,
     network::Network, asset::{AssetData, AssetWithData},
+    name::NameEntry,
 };
 
-use crate::core::error::BlockchainError;
+use crate::{
+    core::error::BlockchainError,
+    p2p::sync_cursor::SyncCursor
+};
 
 pub type Tips = HashSet<Hash>;
 
@@ -32,6 +40,15 @@ pub trait Storage: DifficultyProvider + Sync + Send + 'static {
     fn get_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError>;
     fn set_pruned_topoheight(&mut self, pruned_topoheight: u64) -> Result<(), BlockchainError>;
 
+    // persistent identity key of the node
+    fn get_node_key(&self) -> Result<Option<KeyPair>, BlockchainError>;
+    fn set_node_key(&mut self, keypair: &KeyPair) -> Result<(), BlockchainError>;
+
+    // state of the chain sync in progress, to resume it after a restart
+    fn get_sync_cursor(&self) -> Result<Option<SyncCursor>, BlockchainError>;
+    // remove it if none
+    fn set_sync_cursor(&mut self, cursor: Option<&SyncCursor>) -> Result<(), BlockchainError>;
+
     // delete block at topoheight, and all pointers (hash_at_topo, topo_by_hash, reward, supply, diff, cumulative diff...)
     async fn delete_block_at_topoheight(&mut self, topoheight: u64) -> Result<(Hash, Arc<BlockHeader>, Vec<(Hash, Arc<Transaction>)>), BlockchainError>;
     async fn delete_tx(&mut self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError>;
@@ -62,6 +79,10 @@ pub trait Storage: DifficultyProvider + Sync + Send + 'static {
     fn remove_tx_executed(&mut self, tx: &Hash) -> Result<(), BlockchainError>;
     fn is_tx_executed_in_a_block(&self, tx: &Hash) -> Result<bool, BlockchainError>;
     fn is_tx_executed_in_block(&self, tx: &Hash, block: &Hash) -> Result<bool, BlockchainError>;
+    // receipt is deleted with the execution of the TX
+    fn set_transaction_receipt(&mut self, tx: &Hash, receipt: &TransactionReceipt) -> Result<(), BlockchainError>;
+    fn has_transaction_receipt(&self, tx: &Hash) -> Result<bool, BlockchainError>;
+    fn get_transaction_receipt(&self, tx: &Hash) -> Result<TransactionReceipt, BlockchainError>;
     fn set_blocks_for_tx(&mut self, tx: &Hash, blocks: &HashSet<Hash>) -> Result<(), BlockchainError>;
 
     fn get_network(&self) -> Result<Network, BlockchainError>;
@@ -113,6 +134,21 @@ pub trait Storage: DifficultyProvider + Sync + Send + 'static {
     async fn has_transaction(&self, hash: &Hash) -> Result<bool, BlockchainError>;
 
     async fn save_block(&mut self, block: Arc<BlockHeader>, txs: &Vec<Immutable<Transaction>>, difficulty: Difficulty, hash: Hash) -> Result<(), BlockchainError>;
+    // move the block headers and transactions until this topoheight to the cold store, at most N blocks at once
+    // returns how many blocks were moved
+    async fn move_blocks_to_cold_store(&mut self, topoheight: u64, maximum: u64) -> Result<u64, BlockchainError>;
+    // delete the blocks which are not ordered until this height, at most N heights at once
+    // returns how many blocks were deleted
+    async fn delete_orphaned_blocks_until_height(&mut self, height: u64, maximum: u64) -> Result<u64, BlockchainError>;
+
+    // record the changes written while this block is applied, so they can be rolled back if it is interrupted
+    async fn start_block_journal(&mut self, hash: &Hash) -> Result<(), BlockchainError>;
+    // the block is fully applied, its changes are kept
+    fn commit_block_journal(&mut self) -> Result<(), BlockchainError>;
+    fn is_block_journal_open(&self) -> bool;
+    // revert all the changes written since the journal was started, returns the hash of the block reverted
+    async fn rollback_block_journal(&mut self) -> Result<Option<Hash>, BlockchainError>;
+
     // Count is the number of blocks (topoheight) to rewind
     async fn pop_blocks(&mut self, mut height: u64, mut topoheight: u64, count: u64) -> Result<(u64, u64, Vec<(Hash, Arc<Transaction>)>), BlockchainError>;
     fn has_blocks(&self) -> bool;
@@ -139,8 +175,29 @@ pub trait Storage: DifficultyProvider + Sync + Send + 'static {
     async fn get_supply_at_topo_height(&self, topoheight: u64) -> Result<u64, BlockchainError>;
     fn set_supply_at_topo_height(&mut self, topoheight: u64, supply: u64) -> Result<(), BlockchainError>;
 
+    // cumulative amount of XELIS destroyed by burn transactions until this topoheight
+    async fn get_burned_supply_at_topo_height(&self, topoheight: u64) -> Result<u64, BlockchainError>;
+    fn set_burned_supply_at_topo_height(&mut self, topoheight: u64, burned_supply: u64) -> Result<(), BlockchainError>;
+
     async fn set_cumulative_difficulty_for_block_hash(&mut self, hash: &Hash, cumulative_difficulty: Difficulty) -> Result<(), BlockchainError>;
 
+    // smart contracts are identified by the hash of their deploy TX
+    async fn has_contract(&self, contract: &Hash) -> Result<bool, BlockchainError>;
+    // returns the topoheight at which it was deployed and its code
+    async fn get_contract(&self, contract: &Hash) -> Result<(u64, String), BlockchainError>;
+    async fn set_contract(&mut self, contract: &Hash, topoheight: u64, code: &str) -> Result<(), BlockchainError>;
+    // contract storage is versioned, returns the topoheight of the value found and the value
+    async fn get_contract_data_at_maximum_topoheight(&self, contract: &Hash, key: &str, topoheight: u64) -> Result<Option<(u64, u64)>, BlockchainError>;
+    async fn set_contract_data_at_topoheight(&mut self, contract: &Hash, key: &str, topoheight: u64, value: u64) -> Result<(), BlockchainError>;
+    // delete contracts deployed and contract data written above topoheight
+    async fn delete_contracts_above_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError>;
+
+    // names registrations are versioned, returns the topoheight of the registration found and the registration
+    async fn get_name_at_maximum_topoheight(&self, name: &str, topoheight: u64) -> Result<Option<(u64, NameEntry)>, BlockchainError>;
+    async fn set_name_at_topoheight(&mut self, name: &str, topoheight: u64, entry: &NameEntry) -> Result<(), BlockchainError>;
+    // delete names registrations made above topoheight
+    async fn delete_names_above_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError>;
+
     fn get_top_topoheight(&self) -> Result<u64, BlockchainError>;
     fn set_top_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError>;
 
@@ -152,6 +209,10 @@ pub trait Storage: DifficultyProvider + Sync + Send + 'static {
 
     async fn get_size_on_disk(&self) -> Result<u64, BlockchainError>;
 
+    // copy the whole storage in a new database created in this directory
+    // the caller must prevent any write during the copy to have a consistent snapshot
+    async fn create_database_snapshot(&self, path: &str) -> Result<(), BlockchainError>;
+
     async fn stop(&mut self) -> Result<(), BlockchainError>;
 
     //async fn execute_db_transaction<'a>(&mut self, transaction: DatabaseTransaction<'a, Self>) -> Result<(), BlockchainError>;
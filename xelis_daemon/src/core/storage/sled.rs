@@ -2,16 +2,18 @@ use async_trait::async_trait;
 use indexmap::IndexSet;
 use crate::{
     core::error::{BlockchainError, DiskContext},
-    config::PRUNE_SAFETY_LIMIT
+    config::PRUNE_SAFETY_LIMIT,
+    p2p::sync_cursor::SyncCursor
 };
 use xelis_common::{
     serializer::{Reader, Serializer},
-    crypto::{key::PublicKey, hash::Hash},
+    crypto::{key::{PublicKey, KeyPair}, hash::{Hash, hash, HASH_SIZE}},
     immutable::Immutable,
-    transaction::Transaction,
+    transaction::{Transaction, TransactionReceipt},
     block::{BlockHeader, Block, Difficulty},
     account::{VersionedBalance, VersionedNonce},
     network::Network, asset::{AssetData, AssetWithData},
+    name::NameEntry,
 };
 use std::{
     collections::HashSet,
@@ -20,26 +22,39 @@ use std::{
 };
 use tokio::sync::Mutex;
 use lru::LruCache;
-use sled::Tree;
+use sled::{Db, IVec, Tree};
 use log::{debug, trace, error, warn, info};
 
-use super::{Tips, Storage, DifficultyProvider};
+use super::{Tips, Storage, DifficultyProvider, migrations::apply_migrations, cold::{ColdStore, ColdKind}, cipher::{StorageCipher, STORAGE_KEY_SIZE}};
 
 // Constant keys used for extra Tree
 const TIPS: &[u8; 4] = b"TIPS";
-const TOP_TOPO_HEIGHT: &[u8; 4] = b"TOPO";
+pub(super) const TOP_TOPO_HEIGHT: &[u8; 4] = b"TOPO";
 const TOP_HEIGHT: &[u8; 4] = b"TOPH";
-const NETWORK: &[u8] = b"NET";
-const PRUNED_TOPOHEIGHT: &[u8; 4] = b"PRUN";
+pub(super) const NETWORK: &[u8] = b"NET";
+pub(super) const PRUNED_TOPOHEIGHT: &[u8; 4] = b"PRUN";
+const NODE_KEY: &[u8; 4] = b"NKEY";
+// blocks left to add from the last chain response
+const SYNC_CURSOR: &[u8; 4] = b"SYNC";
+// last topoheight moved to the cold store
+const COLD_TOPOHEIGHT: &[u8; 4] = b"COLD";
+// last height checked for orphaned blocks to delete
+const ORPHANED_HEIGHT: &[u8; 4] = b"ORPH";
+// value encrypted with the storage key, set when the storage is created with one
+const ENCRYPTION: &[u8; 4] = b"ENCR";
 // Counters (prevent to perform a O(n))
-const ACCOUNTS_COUNT: &[u8; 4] = b"CACC";
-const TXS_COUNT: &[u8; 4] = b"CTXS";
-const ASSETS_COUNT: &[u8; 4] = b"CAST";
-const BLOCKS_COUNT: &[u8; 4] = b"CBLK";
+pub(super) const ACCOUNTS_COUNT: &[u8; 4] = b"CACC";
+pub(super) const TXS_COUNT: &[u8; 4] = b"CTXS";
+pub(super) const ASSETS_COUNT: &[u8; 4] = b"CAST";
+pub(super) const BLOCKS_COUNT: &[u8; 4] = b"CBLK";
+// Key of the journal Tree containing the hash of the block being applied
+// journal keys of the changes are prefixed by the tree name length which is never 0
+const JOURNAL_BLOCK: &[u8; 1] = &[0];
 
 pub struct SledStorage {
     transactions: Tree, // all txs stored on disk
     txs_executed: Tree, // all txs executed in block
+    tx_receipts: Tree, // receipt of each tx executed
     blocks: Tree, // all blocks on disk
     blocks_at_height: Tree, // all blocks height at specific height
     extra: Tree, // all extra data saved on disk
@@ -50,12 +65,21 @@ pub struct SledStorage {
     nonces: Tree, // account nonces to prevent TX replay attack
     rewards: Tree, // block reward for each block topoheight
     supply: Tree, // supply for each block topoheight
+    burned_supply: Tree, // burned supply for each block topoheight
     difficulty: Tree, // difficulty for each block hash
     tx_blocks: Tree, // tree to store all blocks hashes where a tx was included in 
     versioned_nonces: Tree, // Tree that store all versioned nonces using hashed keys
     balances: Tree, // Tree that store all balances with prefixed keys
     versioned_balances: Tree, // Tree that store all versioned balances using hashed keys
+    contracts: Tree, // code of all deployed contracts with their deploy topoheight
+    contracts_data: Tree, // versioned storage of all contracts using contract + hashed key + topoheight keys
+    contracts_changes: Tree, // index of the contracts and contracts data keys written at each topoheight
+    names: Tree, // versioned names registrations using hashed name + topoheight keys
     db: sled::Db, // opened DB used for assets to create dynamic assets
+    cold: ColdStore, // old block headers and transactions moved out of the trees above
+    journal: Tree, // previous value of each key written while a block is applied
+    journal_open: bool, // set while a block is applied to record the changes in the journal
+    cipher: Option<StorageCipher>, // encrypt the values of the versioned balances and nonces when a storage key is set, the other trees stay in plaintext
     // cached in memory
     transactions_cache: Option<Mutex<LruCache<Hash, Arc<Transaction>>>>,
     blocks_cache: Option<Mutex<LruCache<Hash, Arc<BlockHeader>>>>,
@@ -70,6 +94,11 @@ pub struct SledStorage {
     pruned_topoheight: Option<u64>
 }
 
+// the cold store is next to the database directory, like its migration backup
+pub(super) fn get_cold_store_path(dir_path: &str) -> String {
+    format!("{}.cold", dir_path.trim_end_matches('/'))
+}
+
 macro_rules! init_cache {
     ($cache_size: expr) => {{
         if let Some(size) = &$cache_size {
@@ -81,11 +110,19 @@ macro_rules! init_cache {
 }
 
 impl SledStorage {
-    pub fn new(dir_path: String, cache_size: Option<usize>, network: Network) -> Result<Self, BlockchainError> {
-        let sled = sled::open(dir_path)?;
+    pub fn new(dir_path: String, cache_size: Option<usize>, network: Network, storage_key: Option<[u8; STORAGE_KEY_SIZE]>) -> Result<Self, BlockchainError> {
+        let sled = sled::open(&dir_path)?;
+        Self::from_db(sled, dir_path, cache_size, network, storage_key)
+    }
+
+    // load the storage from an already opened database
+    fn from_db(sled: Db, dir_path: String, cache_size: Option<usize>, network: Network, storage_key: Option<[u8; STORAGE_KEY_SIZE]>) -> Result<Self, BlockchainError> {
+        apply_migrations(&sled, &dir_path)?;
+
         let mut storage = Self {
             transactions: sled.open_tree("transactions")?,
             txs_executed: sled.open_tree("txs_executed")?,
+            tx_receipts: sled.open_tree("tx_receipts")?,
             blocks: sled.open_tree("blocks")?,
             blocks_at_height: sled.open_tree("blocks_at_height")?,
             extra: sled.open_tree("extra")?,
@@ -96,11 +133,20 @@ impl SledStorage {
             nonces: sled.open_tree("nonces")?,
             rewards: sled.open_tree("rewards")?,
             supply: sled.open_tree("supply")?,
+            burned_supply: sled.open_tree("burned_supply")?,
             difficulty: sled.open_tree("difficulty")?,
             tx_blocks: sled.open_tree("tx_blocks")?,
             versioned_nonces: sled.open_tree("versioned_nonces")?,
             balances: sled.open_tree("balances")?,
             versioned_balances: sled.open_tree("versioned_balances")?,
+            contracts: sled.open_tree("contracts")?,
+            contracts_data: sled.open_tree("contracts_data")?,
+            contracts_changes: sled.open_tree("contracts_changes")?,
+            names: sled.open_tree("names")?,
+            cold: ColdStore::new(get_cold_store_path(&dir_path), sled.open_tree("cold_index")?),
+            journal: sled.open_tree("journal")?,
+            journal_open: false,
+            cipher: storage_key.as_ref().map(StorageCipher::new),
             db: sled,
             transactions_cache: init_cache!(cache_size),
             blocks_cache: init_cache!(cache_size),
@@ -115,6 +161,13 @@ impl SledStorage {
             pruned_topoheight: None
         };
 
+        // the daemon stopped while a block was applied, revert its changes to the previous state
+        if let Some(hash) = storage.revert_journal()? {
+            warn!("Block {} was not fully applied, its changes have been rolled back", hash);
+        }
+
+        storage.check_encryption()?;
+
         if storage.has_network()? {
             let storage_network = storage.get_network()?;
             if storage_network != network {
@@ -184,6 +237,132 @@ impl SledStorage {
         }
     }
 
+    fn get_journal_key(tree: &Tree, key: &[u8]) -> Vec<u8> {
+        let name = tree.name();
+        let mut journal_key = Vec::with_capacity(1 + name.len() + key.len());
+        journal_key.push(name.len() as u8);
+        journal_key.extend_from_slice(&name);
+        journal_key.extend_from_slice(key);
+        journal_key
+    }
+
+    // save the value of the key before its first change while a block is applied
+    // sled recovers the writes in order, so the journal entry is always on disk before the change
+    fn journal_previous_value(&self, tree: &Tree, key: &[u8]) -> Result<(), BlockchainError> {
+        if !self.journal_open {
+            return Ok(())
+        }
+
+        let journal_key = Self::get_journal_key(tree, key);
+        if !self.journal.contains_key(&journal_key)? {
+            let value = match tree.get(key)? {
+                Some(previous) => {
+                    let mut value = Vec::with_capacity(1 + previous.len());
+                    value.push(1);
+                    value.extend_from_slice(&previous);
+                    value
+                },
+                None => vec![0]
+            };
+            self.journal.insert(journal_key, value)?;
+        }
+        Ok(())
+    }
+
+    // verify that the storage key matches the one used to create the storage
+    fn check_encryption(&self) -> Result<(), BlockchainError> {
+        match (self.extra.get(ENCRYPTION)?, &self.cipher) {
+            (Some(value), Some(cipher)) => {
+                cipher.decrypt(ENCRYPTION, &value).map_err(|_| BlockchainError::InvalidStorageKey)?;
+            },
+            (Some(_), None) => return Err(BlockchainError::StorageKeyRequired),
+            (None, Some(cipher)) => {
+                // existing balances and nonces are in plaintext
+                if !self.versioned_balances.is_empty() || !self.versioned_nonces.is_empty() {
+                    return Err(BlockchainError::StorageNotEncrypted)
+                }
+                debug!("New encrypted storage");
+                self.extra.insert(ENCRYPTION, cipher.encrypt(ENCRYPTION, ENCRYPTION)?)?;
+            },
+            (None, None) => {}
+        };
+        Ok(())
+    }
+
+    // values of the versioned balances and nonces trees go through these functions
+    fn encrypt_value(&self, key: &[u8], value: Vec<u8>) -> Result<Vec<u8>, BlockchainError> {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(key, &value),
+            None => Ok(value)
+        }
+    }
+
+    fn read_encrypted_value<T: Serializer>(&self, key: &[u8], bytes: &[u8]) -> Result<T, BlockchainError> {
+        let value = match &self.cipher {
+            Some(cipher) => T::from_bytes(&cipher.decrypt(key, bytes)?)?,
+            None => T::from_bytes(bytes)?
+        };
+        Ok(value)
+    }
+
+    fn load_encrypted_from_disk<T: Serializer>(&self, tree: &Tree, key: &[u8]) -> Result<T, BlockchainError> {
+        match tree.get(key)? {
+            Some(bytes) => self.read_encrypted_value(key, &bytes),
+            None => Err(BlockchainError::NotFoundOnDisk(DiskContext::LoadData))
+        }
+    }
+
+    fn insert_into_tree<K: AsRef<[u8]>, V: Into<IVec>>(&self, tree: &Tree, key: K, value: V) -> Result<Option<IVec>, BlockchainError> {
+        self.journal_previous_value(tree, key.as_ref())?;
+        Ok(tree.insert(key, value)?)
+    }
+
+    fn remove_from_tree<K: AsRef<[u8]>>(&self, tree: &Tree, key: K) -> Result<Option<IVec>, BlockchainError> {
+        self.journal_previous_value(tree, key.as_ref())?;
+        Ok(tree.remove(key)?)
+    }
+
+    // restore the previous value of each key changed by the block being applied
+    // returns the hash of the block reverted, none if no block was being applied
+    fn revert_journal(&mut self) -> Result<Option<Hash>, BlockchainError> {
+        self.journal_open = false;
+        let hash = match self.journal.get(JOURNAL_BLOCK)? {
+            Some(bytes) => Hash::from_bytes(&bytes)?,
+            None => {
+                // the block was fully applied but the journal was not entirely cleared
+                self.journal.clear()?;
+                return Ok(None)
+            }
+        };
+
+        for el in self.journal.iter() {
+            let (journal_key, value) = el?;
+            if journal_key.as_ref() == JOURNAL_BLOCK {
+                continue;
+            }
+
+            let name_len = journal_key[0] as usize;
+            let tree = self.db.open_tree(&journal_key[1..1 + name_len])?;
+            let key = &journal_key[1 + name_len..];
+            match value.split_first() {
+                Some((1, previous)) => {
+                    tree.insert(key, previous)?;
+                },
+                _ => {
+                    tree.remove(key)?;
+                }
+            };
+        }
+
+        // the block is marked as reverted before the journal is cleared
+        self.journal.remove(JOURNAL_BLOCK)?;
+        self.journal.clear()?;
+
+        self.tips_cache = self.load_from_disk::<Tips>(&self.extra, TIPS).unwrap_or_default();
+        self.pruned_topoheight = self.load_from_disk::<u64>(&self.extra, PRUNED_TOPOHEIGHT).ok();
+        Ok(Some(hash))
+    }
+
     fn load_from_disk<T: Serializer>(&self, tree: &Tree, key: &[u8]) -> Result<T, BlockchainError> {
         match tree.get(key)? {
             Some(bytes) => {
@@ -231,7 +410,7 @@ impl SledStorage {
     }
 
     async fn delete_cacheable_data<K: Eq + StdHash + Serializer + Clone, V: Serializer>(&self, tree: &Tree, cache: &Option<Mutex<LruCache<K, V>>>, key: &K) -> Result<V, BlockchainError> {
-        let bytes = match tree.remove(key.to_bytes())? {
+        let bytes = match self.remove_from_tree(tree, key.to_bytes())? {
             Some(data) => data.to_vec(),
             None => return Err(BlockchainError::NotFoundOnDisk(DiskContext::DeleteData))
         };
@@ -249,7 +428,7 @@ impl SledStorage {
     }
 
     async fn delete_data<K: Eq + StdHash + Serializer + Clone, V: Serializer>(&self, tree: &Tree, cache: &Option<Mutex<LruCache<K, Arc<V>>>>, key: &K) -> Result<Arc<V>, BlockchainError> {
-        let bytes = match tree.remove(key.to_bytes())? {
+        let bytes = match self.remove_from_tree(tree, key.to_bytes())? {
             Some(data) => data.to_vec(),
             None => return Err(BlockchainError::NotFoundOnDisk(DiskContext::DeleteData))
         };
@@ -275,6 +454,36 @@ impl SledStorage {
         Ok(tree.contains_key(&key.to_bytes())?)
     }
 
+    // block headers and transactions moved to the cold store are not in their tree anymore
+    async fn get_hot_or_cold_data<V: Serializer>(&self, tree: &Tree, cache: &Option<Mutex<LruCache<Hash, Arc<V>>>>, kind: ColdKind, hash: &Hash) -> Result<Arc<V>, BlockchainError> {
+        match self.get_cacheable_arc_data(tree, cache, hash).await {
+            Err(BlockchainError::NotFoundOnDisk(context)) => match self.cold.get(kind, hash)? {
+                Some(bytes) => Ok(Arc::new(V::from_bytes(&bytes)?)),
+                None => Err(BlockchainError::NotFoundOnDisk(context))
+            },
+            result => result
+        }
+    }
+
+    async fn delete_hot_or_cold_data<V: Serializer>(&self, tree: &Tree, cache: &Option<Mutex<LruCache<Hash, Arc<V>>>>, kind: ColdKind, hash: &Hash) -> Result<Arc<V>, BlockchainError> {
+        match self.delete_data(tree, cache, hash).await {
+            Err(BlockchainError::NotFoundOnDisk(context)) => match self.cold.remove(kind, hash)? {
+                Some(bytes) => {
+                    if let Some(cache) = cache {
+                        cache.lock().await.pop(hash);
+                    }
+                    Ok(Arc::new(V::from_bytes(&bytes)?))
+                },
+                None => Err(BlockchainError::NotFoundOnDisk(context))
+            },
+            result => result
+        }
+    }
+
+    async fn contains_hot_or_cold_data<V>(&self, tree: &Tree, cache: &Option<Mutex<LruCache<Hash, V>>>, kind: ColdKind, hash: &Hash) -> Result<bool, BlockchainError> {
+        Ok(self.contains_data(tree, cache, hash).await? || self.cold.contains(kind, hash)?)
+    }
+
     fn get_balance_key_for(&self, key: &PublicKey, asset: &Hash) -> [u8; 64] {
         let mut bytes = [0; 64];
         bytes[0..32].copy_from_slice(key.as_bytes());
@@ -293,6 +502,35 @@ impl SledStorage {
     }
 
 
+    // topoheight is at the end and in big endian to iterate over the versions of a key in order
+    fn get_contract_data_key(&self, contract: &Hash, key: &str, topoheight: u64) -> [u8; 72] {
+        let mut bytes = [0; 72];
+        bytes[0..32].copy_from_slice(contract.as_bytes());
+        bytes[32..64].copy_from_slice(hash(key.as_bytes()).as_bytes());
+        bytes[64..72].copy_from_slice(&topoheight.to_be_bytes());
+
+        bytes
+    }
+
+    // topoheight is at the start to find all the keys written above a topoheight
+    // the key is a contract hash in the contracts tree or a contract data key in the contracts data tree
+    pub(super) fn get_contract_change_key(topoheight: u64, key: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + key.len());
+        bytes.extend(topoheight.to_be_bytes());
+        bytes.extend(key);
+
+        bytes
+    }
+
+    // same layout as contract data to iterate over the versions of a name
+    fn get_name_key(&self, name: &str, topoheight: u64) -> [u8; 40] {
+        let mut bytes = [0; 40];
+        bytes[0..32].copy_from_slice(hash(name.as_bytes()).as_bytes());
+        bytes[32..40].copy_from_slice(&topoheight.to_be_bytes());
+
+        bytes
+    }
+
     async fn has_balance_internal(&self, key: &[u8; 64]) -> Result<bool, BlockchainError> {
         trace!("has balance internal");
         Ok(self.balances.contains_key(key)?)
@@ -304,7 +542,7 @@ impl SledStorage {
             let key = el?;
             let topo = u64::from_bytes(&key[0..8])?;
             if topo > topoheight {
-                tree.remove(&key)?;
+                self.remove_from_tree(tree, &key)?;
             }
         }
         Ok(())
@@ -316,7 +554,7 @@ impl SledStorage {
             let key = el?;
             let topo = u64::from_bytes(&key[0..8])?;
             if topo < topoheight {
-                tree.remove(&key)?;
+                self.remove_from_tree(tree, &key)?;
             }
         }
         Ok(())
@@ -380,7 +618,7 @@ impl DifficultyProvider for SledStorage {
 
     async fn get_block_header_by_hash(&self, hash: &Hash) -> Result<Arc<BlockHeader>, BlockchainError> {
         trace!("get block by hash: {}", hash);
-        self.get_cacheable_arc_data(&self.blocks, &self.blocks_cache, hash).await
+        self.get_hot_or_cold_data(&self.blocks, &self.blocks_cache, ColdKind::BlockHeader, hash).await
     }
 }
 
@@ -392,7 +630,35 @@ impl Storage for SledStorage {
 
     fn set_pruned_topoheight(&mut self, pruned_topoheight: u64) -> Result<(), BlockchainError> {
         self.pruned_topoheight = Some(pruned_topoheight);
-        self.extra.insert(PRUNED_TOPOHEIGHT, &pruned_topoheight.to_be_bytes())?;
+        self.insert_into_tree(&self.extra, PRUNED_TOPOHEIGHT, &pruned_topoheight.to_be_bytes())?;
+        Ok(())
+    }
+
+    fn get_node_key(&self) -> Result<Option<KeyPair>, BlockchainError> {
+        match self.extra.get(NODE_KEY)? {
+            Some(bytes) => Ok(Some(KeyPair::from_bytes(&bytes)?)),
+            None => Ok(None)
+        }
+    }
+
+    fn set_node_key(&mut self, keypair: &KeyPair) -> Result<(), BlockchainError> {
+        self.insert_into_tree(&self.extra, NODE_KEY, keypair.to_bytes())?;
+        Ok(())
+    }
+
+    fn get_sync_cursor(&self) -> Result<Option<SyncCursor>, BlockchainError> {
+        match self.load_from_disk(&self.extra, SYNC_CURSOR) {
+            Ok(cursor) => Ok(Some(cursor)),
+            Err(BlockchainError::NotFoundOnDisk(_)) => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn set_sync_cursor(&mut self, cursor: Option<&SyncCursor>) -> Result<(), BlockchainError> {
+        match cursor {
+            Some(cursor) => self.insert_into_tree(&self.extra, SYNC_CURSOR, cursor.to_bytes())?,
+            None => self.remove_from_tree(&self.extra, SYNC_CURSOR)?
+        };
         Ok(())
     }
 
@@ -407,13 +673,16 @@ impl Storage for SledStorage {
         self.delete_cacheable_data::<Hash, u64>(&self.topo_by_hash, &self.topo_by_hash_cache, &hash).await?;
 
         trace!("deleting block header {}", hash);
-        let block = self.delete_data(&self.blocks, &self.blocks_cache, &hash).await?;
+        let block = self.delete_hot_or_cold_data(&self.blocks, &self.blocks_cache, ColdKind::BlockHeader, &hash).await?;
         trace!("block header deleted successfully");
 
         trace!("Deleting supply and block reward");
         let supply: u64 = self.delete_cacheable_data(&self.supply, &None, &topoheight).await?;
         trace!("Supply was {}", supply);
 
+        let burned_supply: u64 = self.delete_cacheable_data(&self.burned_supply, &None, &topoheight).await?;
+        trace!("Burned supply was {}", burned_supply);
+
         let reward: u64 = self.delete_cacheable_data(&self.rewards, &None, &topoheight).await?;
         trace!("Reward for block {} was: {}", hash, reward);
 
@@ -441,9 +710,9 @@ impl Storage for SledStorage {
 
             // We have to check first as we may have already deleted it because of client protocol
             // which allow multiple time the same txs in differents blocks
-            if self.contains_data(&self.transactions, &self.transactions_cache, tx_hash).await? {
+            if self.has_transaction(tx_hash).await? {
                 trace!("Deleting TX {} in block {}", tx_hash, hash);
-                let tx: Arc<Transaction> = self.delete_data(&self.transactions, &self.transactions_cache, tx_hash).await?;
+                let tx: Arc<Transaction> = self.delete_hot_or_cold_data(&self.transactions, &self.transactions_cache, ColdKind::Transaction, tx_hash).await?;
                 txs.push((tx_hash.clone(), tx));
             }
         }
@@ -464,7 +733,7 @@ impl Storage for SledStorage {
 
     async fn delete_tx(&mut self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError> {
         self.delete_cacheable_data::<Hash, HashSet<Hash>>(&self.tx_blocks, &None, hash).await?;
-        self.delete_data(&self.transactions, &self.transactions_cache, hash).await
+        self.delete_hot_or_cold_data(&self.transactions, &self.transactions_cache, ColdKind::Transaction, hash).await
     }
 
     async fn delete_versioned_balances_above_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
@@ -503,13 +772,13 @@ impl Storage for SledStorage {
             // otherwise, delete the previous topoheight in VersionedBalance which is under topoheight
             if highest_balance_topoheight < topoheight {
                 // save the new highest topoheight
-                self.balances.insert(&key_bytes, &topoheight.to_be_bytes())?;
+                self.insert_into_tree(&self.balances, &key_bytes, &topoheight.to_be_bytes())?;
                 // remove the previous topoheight
                 versioned_balance.set_previous_topoheight(None);
 
                 // save it
                 let key = self.get_versioned_key(&key, topoheight);
-                self.versioned_balances.insert(key, versioned_balance.to_bytes())?;
+                self.insert_into_tree(&self.versioned_balances, key, self.encrypt_value(&key, versioned_balance.to_bytes())?)?;
             } else {
                 // find the first VersionedBalance which is under topoheight
                 while let Some(previous_topoheight) = versioned_balance.get_previous_topoheight() {
@@ -517,7 +786,7 @@ impl Storage for SledStorage {
                         versioned_balance.set_previous_topoheight(None);
                         // save it
                         let key = self.get_versioned_key(&key, topoheight);
-                        self.versioned_balances.insert(key, versioned_balance.to_bytes())?;
+                        self.insert_into_tree(&self.versioned_balances, key, self.encrypt_value(&key, versioned_balance.to_bytes())?)?;
                         break;
                     }
 
@@ -546,13 +815,13 @@ impl Storage for SledStorage {
             // otherwise, delete the previous topoheight in VersionedNonce which is under topoheight
             if highest_topoheight < topoheight {
                 // save the new highest topoheight
-                self.nonces.insert(&key_bytes, &topoheight.to_be_bytes())?;
+                self.insert_into_tree(&self.nonces, &key_bytes, &topoheight.to_be_bytes())?;
                 // remove the previous topoheight
                 versioned_nonce.set_previous_topoheight(None);
 
                 // save it
                 let key = self.get_versioned_key(&key, topoheight);
-                self.versioned_nonces.insert(key, versioned_nonce.to_bytes())?;
+                self.insert_into_tree(&self.versioned_nonces, key, self.encrypt_value(&key, versioned_nonce.to_bytes())?)?;
             } else {
                 // find the first VersionedBalance which is under topoheight
                 while let Some(previous_topoheight) = versioned_nonce.get_previous_topoheight() {
@@ -560,7 +829,7 @@ impl Storage for SledStorage {
                         versioned_nonce.set_previous_topoheight(None);
                         // save it
                         let key = self.get_versioned_key(&key, topoheight);
-                        self.versioned_nonces.insert(key, versioned_nonce.to_bytes())?;
+                        self.insert_into_tree(&self.versioned_nonces, key, self.encrypt_value(&key, versioned_nonce.to_bytes())?)?;
                         break;
                     }
 
@@ -726,12 +995,13 @@ impl Storage for SledStorage {
     }
 
     fn set_tx_executed_in_block(&mut self, tx: &Hash, block: &Hash) -> Result<(), BlockchainError> {
-        self.txs_executed.insert(tx.as_bytes(), block.as_bytes())?;
+        self.insert_into_tree(&self.txs_executed, tx.as_bytes(), block.as_bytes())?;
         Ok(())
     }
 
     fn remove_tx_executed(&mut self, tx: &Hash) -> Result<(), BlockchainError> {
-        self.txs_executed.remove(tx.as_bytes())?;
+        self.remove_from_tree(&self.txs_executed, tx.as_bytes())?;
+        self.remove_from_tree(&self.tx_receipts, tx.as_bytes())?;
         Ok(())
     }
 
@@ -739,6 +1009,19 @@ impl Storage for SledStorage {
         Ok(self.txs_executed.contains_key(tx.as_bytes())?)
     }
 
+    fn set_transaction_receipt(&mut self, tx: &Hash, receipt: &TransactionReceipt) -> Result<(), BlockchainError> {
+        self.insert_into_tree(&self.tx_receipts, tx.as_bytes(), receipt.to_bytes())?;
+        Ok(())
+    }
+
+    fn has_transaction_receipt(&self, tx: &Hash) -> Result<bool, BlockchainError> {
+        Ok(self.tx_receipts.contains_key(tx.as_bytes())?)
+    }
+
+    fn get_transaction_receipt(&self, tx: &Hash) -> Result<TransactionReceipt, BlockchainError> {
+        self.load_from_disk(&self.tx_receipts, tx.as_bytes())
+    }
+
     fn is_tx_executed_in_block(&self, tx: &Hash, block: &Hash) -> Result<bool, BlockchainError> {
         if let Ok(hash) = self.get_block_executer_for_tx(tx) {
             if hash == *block {
@@ -755,7 +1038,7 @@ impl Storage for SledStorage {
 
     fn set_network(&mut self, network: &Network) -> Result<(), BlockchainError> {
         trace!("set network to {}", network);
-        self.extra.insert(NETWORK, network.to_bytes())?;
+        self.insert_into_tree(&self.extra, NETWORK, network.to_bytes())?;
         Ok(())
     }
 
@@ -771,10 +1054,10 @@ impl Storage for SledStorage {
 
     async fn add_asset(&mut self, asset: &Hash, data: AssetData) -> Result<(), BlockchainError> {
         trace!("add asset {} at topoheight {}", asset, data.get_topoheight());
-        self.assets.insert(asset.as_bytes(), data.to_bytes())?;
+        self.insert_into_tree(&self.assets, asset.as_bytes(), data.to_bytes())?;
 
         let assets_count = self.count_assets()? + 1;
-        self.extra.insert(ASSETS_COUNT, &assets_count.to_be_bytes())?;
+        self.insert_into_tree(&self.extra, ASSETS_COUNT, &assets_count.to_be_bytes())?;
 
         if let Some(cache) = &self.assets_cache {
             let mut cache = cache.lock().await;
@@ -842,7 +1125,7 @@ impl Storage for SledStorage {
 
     fn set_blocks_for_tx(&mut self, tx: &Hash, blocks: &HashSet<Hash>) -> Result<(), BlockchainError> {
         trace!("set blocks ({}) for tx {} ", blocks.len(), tx);
-        self.tx_blocks.insert(tx.as_bytes(), blocks.to_bytes())?;
+        self.insert_into_tree(&self.tx_blocks, tx.as_bytes(), blocks.to_bytes())?;
         Ok(())
     }
 
@@ -882,7 +1165,7 @@ impl Storage for SledStorage {
     fn set_last_topoheight_for_balance(&mut self, key: &PublicKey, asset: &Hash, topoheight: u64) -> Result<(), BlockchainError> {
         trace!("set last topoheight to {} for balance {} for {}", topoheight, asset, key);
         let key = self.get_balance_key_for(key, asset);
-        self.balances.insert(&key, &topoheight.to_be_bytes())?;
+        self.insert_into_tree(&self.balances, key, &topoheight.to_be_bytes())?;
         Ok(())
     }
 
@@ -909,7 +1192,7 @@ impl Storage for SledStorage {
         }
 
         let disk_key = self.get_versioned_key(&key, topoheight);
-        self.get_cacheable_data_copiable(&self.versioned_balances, &None, &disk_key).await.map_err(|_| BlockchainError::NoBalanceChanges(key.clone(), topoheight, asset.clone()))
+        self.load_encrypted_from_disk(&self.versioned_balances, &disk_key).map_err(|_| BlockchainError::NoBalanceChanges(key.clone(), topoheight, asset.clone()))
     }
 
     // delete the last topoheight registered for this key
@@ -918,7 +1201,7 @@ impl Storage for SledStorage {
     fn delete_last_topoheight_for_balance(&mut self, key: &PublicKey, asset: &Hash) -> Result<(), BlockchainError> {
         trace!("delete last topoheight balance {} for {}", asset, key);
         let key = self.get_balance_key_for(key, asset);
-        self.balances.remove(&key)?;
+        self.remove_from_tree(&self.balances, key)?;
         Ok(())
     }
 
@@ -972,7 +1255,8 @@ impl Storage for SledStorage {
     async fn delete_balance_at_topoheight(&mut self, key: &PublicKey, asset: &Hash, topoheight: u64) -> Result<VersionedBalance, BlockchainError> {
         trace!("delete balance {} for {} at topoheight {}", asset, key, topoheight);
         let disk_key = self.get_versioned_key(&key, topoheight);
-        self.delete_cacheable_data(&self.versioned_balances, &None, &disk_key).await.map_err(|_| BlockchainError::NoBalanceChanges(key.clone(), topoheight, asset.clone()))
+        let bytes = self.remove_from_tree(&self.versioned_balances, disk_key)?.ok_or_else(|| BlockchainError::NoBalanceChanges(key.clone(), topoheight, asset.clone()))?;
+        self.read_encrypted_value(&disk_key, &bytes)
     }
 
     // returns a new versioned balance with already-set previous topoheight
@@ -1024,7 +1308,7 @@ impl Storage for SledStorage {
     async fn set_balance_at_topoheight(&mut self, asset: &Hash, topoheight: u64, key: &PublicKey, balance: &VersionedBalance) -> Result<(), BlockchainError> {
         trace!("set balance {} at topoheight {} for {}", asset, topoheight, key);
         let key = self.get_versioned_key(&key, topoheight);
-        self.versioned_balances.insert(key, balance.to_bytes())?;
+        self.insert_into_tree(&self.versioned_balances, key, self.encrypt_value(&key, balance.to_bytes())?)?;
         Ok(())
     }
 
@@ -1054,7 +1338,7 @@ impl Storage for SledStorage {
         trace!("get nonce at topoheight {} for {}", topoheight, key);
 
         let key = self.get_versioned_key(key, topoheight);
-        self.load_from_disk(&self.versioned_nonces, &key)
+        self.load_encrypted_from_disk(&self.versioned_nonces, &key)
     }
 
     // topoheight is inclusive bounds
@@ -1104,7 +1388,7 @@ impl Storage for SledStorage {
 
         let versioned = VersionedNonce::new(nonce, previous_topoheight);
         let disk_key = self.get_versioned_key(key, topoheight);
-        self.versioned_nonces.insert(&disk_key, versioned.to_bytes())?;
+        self.insert_into_tree(&self.versioned_nonces, disk_key, self.encrypt_value(&disk_key, versioned.to_bytes())?)?;
 
         self.set_last_topoheight_for_nonce(key, topoheight)?;
         Ok(())
@@ -1112,9 +1396,9 @@ impl Storage for SledStorage {
 
     fn set_last_topoheight_for_nonce(&mut self, key: &PublicKey, topoheight: u64) -> Result<(), BlockchainError> {
         trace!("set last topoheight for nonce {} to {}", key, topoheight);
-        if self.nonces.insert(&key.as_bytes(), &topoheight.to_be_bytes())?.is_none() {
+        if self.insert_into_tree(&self.nonces, key.as_bytes(), &topoheight.to_be_bytes())?.is_none() {
             let count = self.count_accounts()? + 1;
-            self.extra.insert(ACCOUNTS_COUNT, &count.to_be_bytes())?;
+            self.insert_into_tree(&self.extra, ACCOUNTS_COUNT, &count.to_be_bytes())?;
         }
 
         Ok(())
@@ -1127,22 +1411,25 @@ impl Storage for SledStorage {
 
     fn set_block_reward_at_topo_height(&mut self, topoheight: u64, reward: u64) -> Result<(), BlockchainError> {
         trace!("set block reward to {} at topo height {}", reward, topoheight);
-        self.rewards.insert(topoheight.to_be_bytes(), &reward.to_be_bytes())?;
+        self.insert_into_tree(&self.rewards, topoheight.to_be_bytes(), &reward.to_be_bytes())?;
         Ok(())
     }
 
     async fn get_transaction(&self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError> {
         trace!("get transaction for hash {}", hash);
-        self.get_cacheable_arc_data(&self.transactions, &self.transactions_cache, hash).await
+        self.get_hot_or_cold_data(&self.transactions, &self.transactions_cache, ColdKind::Transaction, hash).await
     }
 
     async fn get_transaction_size(&self, hash: &Hash) -> Result<usize, BlockchainError> {
-        let data = self.transactions.get(hash.as_bytes())?;
-        data.map(|data| data.len()).ok_or(BlockchainError::NotFoundOnDisk(DiskContext::LoadData))
+        let size = match self.transactions.get(hash.as_bytes())? {
+            Some(data) => Some(data.len()),
+            None => self.cold.get_size(ColdKind::Transaction, hash)?
+        };
+        size.ok_or(BlockchainError::NotFoundOnDisk(DiskContext::LoadData))
     }
 
     async fn has_transaction(&self, hash: &Hash) -> Result<bool, BlockchainError> {
-        self.contains_data(&self.transactions, &self.transactions_cache, hash).await
+        self.contains_hot_or_cold_data(&self.transactions, &self.transactions_cache, ColdKind::Transaction, hash).await
     }
 
     fn count_transactions(&self) -> Result<u64, BlockchainError> {
@@ -1157,20 +1444,20 @@ impl Storage for SledStorage {
         let mut txs_count = 0;
         for (hash, tx) in block.get_transactions().iter().zip(txs) { // first save all txs, then save block
             if !self.has_transaction(hash).await? {
-                self.transactions.insert(hash.as_bytes(), tx.to_bytes())?;
+                self.insert_into_tree(&self.transactions, hash.as_bytes(), tx.to_bytes())?;
                 txs_count += 1;
             }
         }
         let current_txs_count = self.count_transactions()?;
-        self.extra.insert(TXS_COUNT, &(current_txs_count + txs_count).to_be_bytes())?;
+        self.insert_into_tree(&self.extra, TXS_COUNT, &(current_txs_count + txs_count).to_be_bytes())?;
 
         // Store block header
-        self.blocks.insert(hash.as_bytes(), block.to_bytes())?;
+        self.insert_into_tree(&self.blocks, hash.as_bytes(), block.to_bytes())?;
         let blocks_count = self.count_blocks()? + 1;
-        self.extra.insert(BLOCKS_COUNT, &blocks_count.to_be_bytes())?;
+        self.insert_into_tree(&self.extra, BLOCKS_COUNT, &blocks_count.to_be_bytes())?;
 
         // Store difficulty
-        self.difficulty.insert(hash.as_bytes(), &difficulty.to_be_bytes())?;
+        self.insert_into_tree(&self.difficulty, hash.as_bytes(), &difficulty.to_be_bytes())?;
 
         self.add_block_hash_at_height(hash.clone(), block.get_height()).await?;
 
@@ -1181,6 +1468,157 @@ impl Storage for SledStorage {
         Ok(())
     }
 
+    async fn move_blocks_to_cold_store(&mut self, topoheight: u64, maximum: u64) -> Result<u64, BlockchainError> {
+        let start = match self.load_from_disk::<u64>(&self.extra, COLD_TOPOHEIGHT) {
+            Ok(cold_topoheight) => cold_topoheight + 1,
+            Err(_) => 0
+        }.max(self.get_pruned_topoheight()?.unwrap_or(0));
+        if maximum == 0 || start > topoheight {
+            return Ok(0)
+        }
+
+        let end = topoheight.min(start + maximum - 1);
+        let mut records = Vec::new();
+        let mut txs = HashSet::new();
+        let mut moved = 0;
+        for topoheight in start..=end {
+            let hash = self.get_hash_at_topo_height(topoheight).await?;
+            let header = match self.blocks.get(hash.as_bytes())? {
+                Some(header) => header,
+                None => continue
+            };
+
+            let block = BlockHeader::from_bytes(&header)?;
+            for tx_hash in block.get_transactions() {
+                if txs.contains(tx_hash) {
+                    continue;
+                }
+
+                if let Some(tx) = self.transactions.get(tx_hash.as_bytes())? {
+                    records.push((ColdKind::Transaction, tx_hash.clone(), tx.to_vec()));
+                    txs.insert(tx_hash.clone());
+                }
+            }
+            records.push((ColdKind::BlockHeader, hash, header.to_vec()));
+            moved += 1;
+        }
+
+        // data is removed from the trees only once written in the cold store
+        self.cold.append(&records)?;
+        for (kind, hash, _) in &records {
+            match kind {
+                ColdKind::BlockHeader => self.remove_from_tree(&self.blocks, hash.as_bytes())?,
+                ColdKind::Transaction => self.remove_from_tree(&self.transactions, hash.as_bytes())?
+            };
+        }
+        self.insert_into_tree(&self.extra, COLD_TOPOHEIGHT, &end.to_be_bytes())?;
+        debug!("Moved {} blocks and {} transactions to the cold store until topoheight {}", moved, txs.len(), end);
+
+        Ok(moved)
+    }
+
+    async fn delete_orphaned_blocks_until_height(&mut self, height: u64, maximum: u64) -> Result<u64, BlockchainError> {
+        let start = match self.load_from_disk::<u64>(&self.extra, ORPHANED_HEIGHT) {
+            Ok(orphaned_height) => orphaned_height + 1,
+            Err(_) => 0
+        };
+        if maximum == 0 || start > height {
+            return Ok(0)
+        }
+
+        let end = height.min(start + maximum - 1);
+        let mut deleted = 0;
+        for height in start..=end {
+            if !self.has_blocks_at_height(height).await? {
+                continue;
+            }
+
+            for hash in self.get_blocks_at_height(height).await? {
+                if self.is_block_topological_ordered(&hash).await {
+                    continue;
+                }
+
+                trace!("Deleting orphaned block {} at height {}", hash, height);
+                let block: Arc<BlockHeader> = self.delete_data(&self.blocks, &self.blocks_cache, &hash).await?;
+                self.remove_from_tree(&self.difficulty, hash.as_bytes())?;
+                self.remove_from_tree(&self.cumulative_difficulty, hash.as_bytes())?;
+                if let Some(cache) = &self.cumulative_difficulty_cache {
+                    cache.lock().await.pop(&hash);
+                }
+
+                if let Some(cache) = &self.past_blocks_cache {
+                    cache.lock().await.pop(&hash);
+                }
+
+                // its transactions are kept if they are included in another block
+                let mut txs_deleted = 0;
+                for tx_hash in block.get_transactions() {
+                    let mut blocks = if self.has_tx_blocks(tx_hash)? {
+                        self.get_blocks_for_tx(tx_hash)?
+                    } else {
+                        Tips::new()
+                    };
+                    blocks.remove(&hash);
+
+                    if blocks.is_empty() && !self.is_tx_executed_in_a_block(tx_hash)? {
+                        self.remove_from_tree(&self.tx_blocks, tx_hash.as_bytes())?;
+                        if self.has_transaction(tx_hash).await? {
+                            self.delete_hot_or_cold_data(&self.transactions, &self.transactions_cache, ColdKind::Transaction, tx_hash).await?;
+                            txs_deleted += 1;
+                        }
+                    } else {
+                        self.set_blocks_for_tx(tx_hash, &blocks)?;
+                    }
+                }
+
+                self.remove_block_hash_at_height(&hash, height).await?;
+                self.insert_into_tree(&self.extra, BLOCKS_COUNT, &self.count_blocks()?.saturating_sub(1).to_be_bytes())?;
+                self.insert_into_tree(&self.extra, TXS_COUNT, &self.count_transactions()?.saturating_sub(txs_deleted).to_be_bytes())?;
+                deleted += 1;
+            }
+        }
+
+        self.insert_into_tree(&self.extra, ORPHANED_HEIGHT, &end.to_be_bytes())?;
+        debug!("Deleted {} orphaned blocks until height {}", deleted, end);
+
+        Ok(deleted)
+    }
+
+    async fn start_block_journal(&mut self, hash: &Hash) -> Result<(), BlockchainError> {
+        trace!("start block journal for {}", hash);
+        // changes of a previous block which was not closed must not be kept
+        if let Some(previous) = self.rollback_block_journal().await? {
+            warn!("Block {} was not fully applied, its changes have been rolled back", previous);
+        }
+
+        self.journal.insert(JOURNAL_BLOCK, hash.as_bytes())?;
+        self.journal_open = true;
+        Ok(())
+    }
+
+    fn commit_block_journal(&mut self) -> Result<(), BlockchainError> {
+        trace!("commit block journal");
+        self.journal_open = false;
+        // once the block key is deleted, the changes are not reverted anymore even if the clear is interrupted
+        self.journal.remove(JOURNAL_BLOCK)?;
+        self.journal.clear()?;
+        Ok(())
+    }
+
+    fn is_block_journal_open(&self) -> bool {
+        self.journal_open
+    }
+
+    async fn rollback_block_journal(&mut self) -> Result<Option<Hash>, BlockchainError> {
+        trace!("rollback block journal");
+        let hash = self.revert_journal()?;
+        if hash.is_some() {
+            // the caches may contain data written by the block
+            self.clear_caches().await;
+        }
+        Ok(hash)
+    }
+
     async fn pop_blocks(&mut self, mut height: u64, mut topoheight: u64, count: u64) -> Result<(u64, u64, Vec<(Hash, Arc<Transaction>)>), BlockchainError> {
         trace!("pop blocks from height: {}, topoheight: {}, count: {}", height, topoheight, count);
         if height < count as u64 { // also prevent removing genesis block
@@ -1236,6 +1674,13 @@ impl Storage for SledStorage {
 
         debug!("Blocks processed {}, new topoheight: {}, new height: {}, tips: {}", done, topoheight, height, tips.len());
 
+        // blocks added again above the new topoheight must be moved again to the cold store
+        if let Ok(cold_topoheight) = self.load_from_disk::<u64>(&self.extra, COLD_TOPOHEIGHT) {
+            if cold_topoheight > topoheight {
+                self.insert_into_tree(&self.extra, COLD_TOPOHEIGHT, &topoheight.to_be_bytes())?;
+            }
+        }
+
         // clean all assets
         let mut deleted_assets = HashSet::new();
         let mut assets = HashSet::new();
@@ -1245,7 +1690,7 @@ impl Storage for SledStorage {
             let registration_topoheight = u64::from_bytes(&value)?;
             if registration_topoheight > topoheight {
                 trace!("Asset {} was registered at topoheight {}, deleting", asset, registration_topoheight);
-                self.assets.remove(&key)?;
+                self.remove_from_tree(&self.assets, &key)?;
                 deleted_assets.insert(asset);
 
                 // drop the tree for this asset
@@ -1261,7 +1706,7 @@ impl Storage for SledStorage {
             let (key, value) = el?;
             let highest_topoheight = u64::from_bytes(&value)?;
             if highest_topoheight > topoheight {
-                self.nonces.remove(&key)?;
+                self.remove_from_tree(&self.nonces, &key)?;
                 // find the first version which is under topoheight
                 let pkey = PublicKey::from_bytes(&key)?;
                 let mut version = self.get_nonce_at_exact_topoheight(&pkey, highest_topoheight).await?;
@@ -1269,7 +1714,7 @@ impl Storage for SledStorage {
                     if previous_topoheight < topoheight {
                         // we find the new highest version which is under new topoheight
                         trace!("New highest version nonce for {} is at topoheight {}", pkey, previous_topoheight);
-                        self.nonces.insert(&key, &previous_topoheight.to_be_bytes())?;
+                        self.insert_into_tree(&self.nonces, &key, &previous_topoheight.to_be_bytes())?;
                         break;
                     }
 
@@ -1295,7 +1740,7 @@ impl Storage for SledStorage {
                     if previous_topoheight < topoheight {
                         // we find the new highest version which is under new topoheight
                         trace!("New highest version balance for {} is at topoheight {} with asset {}", pkey, previous_topoheight, asset);
-                        self.balances.insert(&key, &previous_topoheight.to_be_bytes())?;
+                        self.insert_into_tree(&self.balances, &key, &previous_topoheight.to_be_bytes())?;
                         delete = false;
                         break;
                     }
@@ -1305,7 +1750,7 @@ impl Storage for SledStorage {
                 }
 
                 if delete {
-                    self.balances.remove(&key)?;
+                    self.remove_from_tree(&self.balances, &key)?;
                 }
             } else {
                 // nothing to do as its under the rewinded topoheight
@@ -1315,6 +1760,8 @@ impl Storage for SledStorage {
         // now delete all versioned balances and nonces above the new topoheight
         self.delete_versioned_balances_above_topoheight(topoheight).await?;
         self.delete_versioned_nonces_above_topoheight(topoheight).await?;
+        self.delete_contracts_above_topoheight(topoheight).await?;
+        self.delete_names_above_topoheight(topoheight).await?;
 
         // Clear all caches to not have old data after rewind
         self.clear_caches().await;
@@ -1339,7 +1786,7 @@ impl Storage for SledStorage {
 
     async fn has_block(&self, hash: &Hash) -> Result<bool, BlockchainError> {
         trace!("has block {}", hash);
-        self.contains_data(&self.blocks, &self.blocks_cache, hash).await
+        self.contains_hot_or_cold_data(&self.blocks, &self.blocks_cache, ColdKind::BlockHeader, hash).await
     }
 
     async fn get_block_header_at_topoheight(&self, topoheight: u64) -> Result<(Hash, Arc<BlockHeader>), BlockchainError> {
@@ -1374,7 +1821,7 @@ impl Storage for SledStorage {
 
     fn set_top_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
         trace!("set new top topoheight at {}", topoheight);
-        self.extra.insert(TOP_TOPO_HEIGHT, &topoheight.to_be_bytes())?;
+        self.insert_into_tree(&self.extra, TOP_TOPO_HEIGHT, &topoheight.to_be_bytes())?;
         Ok(())
     }
 
@@ -1385,7 +1832,7 @@ impl Storage for SledStorage {
 
     fn set_top_height(&mut self, height: u64) -> Result<(), BlockchainError> {
         trace!("set new top height at {}", height);
-        self.extra.insert(TOP_HEIGHT, &height.to_be_bytes())?;
+        self.insert_into_tree(&self.extra, TOP_HEIGHT, &height.to_be_bytes())?;
         Ok(())
     }
 
@@ -1415,7 +1862,7 @@ impl Storage for SledStorage {
 
     fn store_tips(&mut self, tips: &Tips) -> Result<(), BlockchainError> {
         trace!("Saving {} Tips", tips.len());
-        self.extra.insert(TIPS, tips.to_bytes())?;
+        self.insert_into_tree(&self.extra, TIPS, tips.to_bytes())?;
         self.tips_cache = tips.clone();
         Ok(())
     }
@@ -1427,7 +1874,7 @@ impl Storage for SledStorage {
 
     async fn set_blocks_at_height(&self, tips: Tips, height: u64) -> Result<(), BlockchainError> {
         trace!("set {} blocks at height {}", tips.len(), height);
-        self.blocks_at_height.insert(height.to_be_bytes(), tips.to_bytes())?;
+        self.insert_into_tree(&self.blocks_at_height, height.to_be_bytes(), tips.to_bytes())?;
         Ok(())
     }
 
@@ -1459,7 +1906,7 @@ impl Storage for SledStorage {
 
         // Delete the height if there is no blocks present anymore
         if tips.is_empty() {
-            self.blocks_at_height.remove(&height.to_be_bytes())?;
+            self.remove_from_tree(&self.blocks_at_height, height.to_be_bytes())?;
         } else {
             self.set_blocks_at_height(tips, height).await?;
         }
@@ -1469,8 +1916,8 @@ impl Storage for SledStorage {
 
     async fn set_topo_height_for_block(&mut self, hash: &Hash, topoheight: u64) -> Result<(), BlockchainError> {
         trace!("set topo height for {} at {}", hash, topoheight);
-        self.topo_by_hash.insert(hash.as_bytes(), topoheight.to_bytes())?;
-        self.hash_at_topo.insert(topoheight.to_be_bytes(), hash.as_bytes())?;
+        self.insert_into_tree(&self.topo_by_hash, hash.as_bytes(), topoheight.to_bytes())?;
+        self.insert_into_tree(&self.hash_at_topo, topoheight.to_be_bytes(), hash.as_bytes())?;
 
         // save in cache
         if let Some(cache) = &self.topo_by_hash_cache {
@@ -1535,19 +1982,141 @@ impl Storage for SledStorage {
 
     fn set_supply_at_topo_height(&mut self, topoheight: u64, supply: u64) -> Result<(), BlockchainError> {
         trace!("set supply at topo height {}", topoheight);
-        self.supply.insert(topoheight.to_be_bytes(), &supply.to_be_bytes())?;
+        self.insert_into_tree(&self.supply, topoheight.to_be_bytes(), &supply.to_be_bytes())?;
+        Ok(())
+    }
+
+    async fn get_burned_supply_at_topo_height(&self, topoheight: u64) -> Result<u64, BlockchainError> {
+        trace!("get burned supply at topo height {}", topoheight);
+        self.load_from_disk(&self.burned_supply, &topoheight.to_be_bytes())
+    }
+
+    fn set_burned_supply_at_topo_height(&mut self, topoheight: u64, burned_supply: u64) -> Result<(), BlockchainError> {
+        trace!("set burned supply at topo height {}", topoheight);
+        self.insert_into_tree(&self.burned_supply, topoheight.to_be_bytes(), &burned_supply.to_be_bytes())?;
         Ok(())
     }
 
     async fn set_cumulative_difficulty_for_block_hash(&mut self, hash: &Hash, cumulative_difficulty: Difficulty) -> Result<(), BlockchainError> {
         trace!("set cumulative difficulty for hash {}", hash);
-        self.cumulative_difficulty.insert(hash.as_bytes(), cumulative_difficulty.to_bytes())?;
+        self.insert_into_tree(&self.cumulative_difficulty, hash.as_bytes(), cumulative_difficulty.to_bytes())?;
+        Ok(())
+    }
+
+    async fn has_contract(&self, contract: &Hash) -> Result<bool, BlockchainError> {
+        trace!("has contract {}", contract);
+        Ok(self.contracts.contains_key(contract.as_bytes())?)
+    }
+
+    async fn get_contract(&self, contract: &Hash) -> Result<(u64, String), BlockchainError> {
+        trace!("get contract {}", contract);
+        let bytes = self.contracts.get(contract.as_bytes())?.ok_or(BlockchainError::ContractNotFound(contract.clone()))?;
+        let mut reader = Reader::new(&bytes);
+        let topoheight = reader.read_u64()?;
+        let code = reader.read_string_with_size(reader.size())?;
+        Ok((topoheight, code))
+    }
+
+    async fn set_contract(&mut self, contract: &Hash, topoheight: u64, code: &str) -> Result<(), BlockchainError> {
+        trace!("set contract {} at topoheight {}", contract, topoheight);
+        let mut bytes = Vec::with_capacity(8 + code.len());
+        bytes.extend(topoheight.to_be_bytes());
+        bytes.extend(code.as_bytes());
+        self.insert_into_tree(&self.contracts, contract.as_bytes(), bytes)?;
+        self.insert_into_tree(&self.contracts_changes, Self::get_contract_change_key(topoheight, contract.as_bytes()), &[])?;
+        Ok(())
+    }
+
+    async fn get_contract_data_at_maximum_topoheight(&self, contract: &Hash, key: &str, topoheight: u64) -> Result<Option<(u64, u64)>, BlockchainError> {
+        trace!("get contract {} data at maximum topoheight {}", contract, topoheight);
+        let start = self.get_contract_data_key(contract, key, 0);
+        let end = self.get_contract_data_key(contract, key, topoheight);
+        match self.contracts_data.range(start..=end).next_back() {
+            Some(el) => {
+                let (key, value) = el?;
+                let topoheight = u64::from_bytes(&key[64..72])?;
+                Ok(Some((topoheight, u64::from_bytes(&value)?)))
+            },
+            None => Ok(None)
+        }
+    }
+
+    async fn set_contract_data_at_topoheight(&mut self, contract: &Hash, key: &str, topoheight: u64, value: u64) -> Result<(), BlockchainError> {
+        trace!("set contract {} data at topoheight {}", contract, topoheight);
+        let key = self.get_contract_data_key(contract, key, topoheight);
+        self.insert_into_tree(&self.contracts_data, key, &value.to_be_bytes())?;
+        self.insert_into_tree(&self.contracts_changes, Self::get_contract_change_key(topoheight, &key), &[])?;
+        Ok(())
+    }
+
+    async fn delete_contracts_above_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete contracts above topoheight {}", topoheight);
+        // only the keys written above the topoheight are visited
+        for el in self.contracts_changes.range((topoheight + 1).to_be_bytes()..).keys() {
+            let change = el?;
+            let key = &change[8..];
+            if key.len() == HASH_SIZE {
+                self.remove_from_tree(&self.contracts, key)?;
+            } else {
+                self.remove_from_tree(&self.contracts_data, key)?;
+            }
+            self.remove_from_tree(&self.contracts_changes, &change)?;
+        }
+        Ok(())
+    }
+
+    async fn get_name_at_maximum_topoheight(&self, name: &str, topoheight: u64) -> Result<Option<(u64, NameEntry)>, BlockchainError> {
+        trace!("get name {} at maximum topoheight {}", name, topoheight);
+        let start = self.get_name_key(name, 0);
+        let end = self.get_name_key(name, topoheight);
+        match self.names.range(start..=end).next_back() {
+            Some(el) => {
+                let (key, value) = el?;
+                let topoheight = u64::from_bytes(&key[32..40])?;
+                Ok(Some((topoheight, NameEntry::from_bytes(&value)?)))
+            },
+            None => Ok(None)
+        }
+    }
+
+    async fn set_name_at_topoheight(&mut self, name: &str, topoheight: u64, entry: &NameEntry) -> Result<(), BlockchainError> {
+        trace!("set name {} at topoheight {}", name, topoheight);
+        self.insert_into_tree(&self.names, self.get_name_key(name, topoheight), entry.to_bytes())?;
+        Ok(())
+    }
+
+    async fn delete_names_above_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete names above topoheight {}", topoheight);
+        for el in self.names.iter().keys() {
+            let key = el?;
+            if u64::from_bytes(&key[32..40])? > topoheight {
+                self.remove_from_tree(&self.names, &key)?;
+            }
+        }
         Ok(())
     }
 
     // Returns the current size on disk in bytes
     async fn get_size_on_disk(&self) -> Result<u64, BlockchainError> {
-        Ok(self.db.size_on_disk()?)
+        Ok(self.db.size_on_disk()? + self.cold.get_size_on_disk()?)
+    }
+
+    async fn create_database_snapshot(&self, path: &str) -> Result<(), BlockchainError> {
+        let db = self.db.clone();
+        let cold_path = get_cold_store_path(path);
+        let path = path.to_owned();
+        // copying all the trees is long, don't block the async runtime
+        tokio::task::spawn_blocking(move || -> sled::Result<()> {
+            let snapshot = sled::open(&path)?;
+            snapshot.import(db.export());
+            snapshot.flush()?;
+            Ok(())
+        }).await.map_err(|e| BlockchainError::SnapshotFailed(e.to_string()))??;
+
+        // copied after the database so every record indexed in the snapshot is in the copy
+        self.cold.copy_to(&cold_path)?;
+
+        Ok(())
     }
 
     async fn stop(&mut self) -> Result<(), BlockchainError> {
@@ -1556,4 +2125,274 @@ impl Storage for SledStorage {
         info!("Sled database flushed");
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xelis_common::{config::XELIS_ASSET, crypto::hash::{hash, Hashable}, transaction::TransactionType};
+    use crate::p2p::packet::chain::BlockId;
+
+    fn _get_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("xelis_journal_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    // sled releases the lock of its files in background once dropped,
+    // so a restart is simulated by loading the storage again from the same opened database
+    fn _open(db: &Db, path: &str, storage_key: Option<[u8; STORAGE_KEY_SIZE]>) -> Result<SledStorage, BlockchainError> {
+        SledStorage::from_db(db.clone(), path.to_owned(), None, Network::Dev, storage_key)
+    }
+
+    #[tokio::test]
+    async fn test_rollback_block_journal() {
+        let path = _get_path("rollback");
+        let mut storage = SledStorage::new(path.clone(), None, Network::Dev, None).unwrap();
+        storage.set_top_topoheight(10).unwrap();
+
+        let block = hash(b"block");
+        storage.start_block_journal(&block).await.unwrap();
+        storage.set_top_topoheight(11).unwrap();
+        storage.set_top_topoheight(12).unwrap();
+        storage.set_tx_executed_in_block(&hash(b"tx"), &block).unwrap();
+        assert!(storage.is_block_journal_open());
+
+        assert_eq!(storage.rollback_block_journal().await.unwrap(), Some(block));
+        assert!(!storage.is_block_journal_open());
+        // the value before the first change is restored
+        assert_eq!(storage.get_top_topoheight().unwrap(), 10);
+        assert!(!storage.is_tx_executed_in_a_block(&hash(b"tx")).unwrap());
+        // nothing to rollback anymore
+        assert_eq!(storage.rollback_block_journal().await.unwrap(), None);
+
+        drop(storage);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_block_journal() {
+        let path = _get_path("interrupted");
+        let db = sled::open(&path).unwrap();
+        let mut storage = _open(&db, &path, None).unwrap();
+        storage.set_top_topoheight(10).unwrap();
+
+        // committed changes are kept
+        storage.start_block_journal(&hash(b"first")).await.unwrap();
+        storage.set_top_topoheight(11).unwrap();
+        storage.commit_block_journal().unwrap();
+
+        storage.start_block_journal(&hash(b"second")).await.unwrap();
+        storage.set_top_topoheight(12).unwrap();
+        storage.store_tips(&[hash(b"second")].into_iter().collect()).unwrap();
+        drop(storage);
+
+        // the daemon restarts before the block is fully applied
+        let storage = _open(&db, &path, None).unwrap();
+        assert_eq!(storage.get_top_topoheight().unwrap(), 11);
+        assert!(storage.get_tips().await.unwrap().is_empty());
+
+        drop(storage);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_storage() {
+        let path = _get_path("encrypted");
+        let key = KeyPair::new().get_public_key().clone();
+        let asset = hash(b"asset");
+        let db = sled::open(&path).unwrap();
+        let mut storage = _open(&db, &path, Some([1u8; STORAGE_KEY_SIZE])).unwrap();
+        storage.add_asset(&asset, AssetData::new(0, 8)).await.unwrap();
+        storage.set_balance_to(&key, &asset, 5, &VersionedBalance::new(42, None)).await.unwrap();
+        storage.set_nonce_at_topoheight(&key, 3, 5).await.unwrap();
+
+        // values are not saved in plaintext
+        let raw = storage.versioned_balances.get(storage.get_versioned_key(&key, 5)).unwrap().unwrap();
+        assert_ne!(raw.as_ref(), VersionedBalance::new(42, None).to_bytes().as_slice());
+        drop(storage);
+
+        // a wrong or missing key can't open it
+        assert!(matches!(_open(&db, &path, Some([2u8; STORAGE_KEY_SIZE])), Err(BlockchainError::InvalidStorageKey)));
+        assert!(matches!(_open(&db, &path, None), Err(BlockchainError::StorageKeyRequired)));
+
+        let mut storage = _open(&db, &path, Some([1u8; STORAGE_KEY_SIZE])).unwrap();
+        assert_eq!(storage.get_balance_at_exact_topoheight(&key, &asset, 5).await.unwrap().get_balance(), 42);
+        assert_eq!(storage.get_nonce_at_exact_topoheight(&key, 5).await.unwrap().get_nonce(), 3);
+        assert_eq!(storage.delete_balance_at_topoheight(&key, &asset, 5).await.unwrap().get_balance(), 42);
+
+        drop(storage);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_existing_storage() {
+        let path = _get_path("plaintext");
+        let key = KeyPair::new().get_public_key().clone();
+        let db = sled::open(&path).unwrap();
+        let mut storage = _open(&db, &path, None).unwrap();
+        storage.set_nonce_at_topoheight(&key, 1, 0).await.unwrap();
+        drop(storage);
+
+        assert!(matches!(_open(&db, &path, Some([1u8; STORAGE_KEY_SIZE])), Err(BlockchainError::StorageNotEncrypted)));
+
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_orphaned_blocks() {
+        let path = _get_path("orphaned");
+        let mut storage = SledStorage::new(path.clone(), None, Network::Dev, None).unwrap();
+        let miner = KeyPair::new().get_public_key().clone();
+        let mut hashes = Vec::new();
+        for nonce in 0..2u8 {
+            let block = BlockHeader::new(0, 1, 0, Vec::new(), [nonce; 32], miner.clone(), Vec::new());
+            let hash = block.hash();
+            storage.save_block(Arc::new(block), &Vec::new(), 1, hash.clone()).await.unwrap();
+            hashes.push(hash);
+        }
+        storage.set_topo_height_for_block(&hashes[0], 1).await.unwrap();
+
+        assert_eq!(storage.delete_orphaned_blocks_until_height(0, 10).await.unwrap(), 0);
+        assert_eq!(storage.delete_orphaned_blocks_until_height(5, 10).await.unwrap(), 1);
+        assert!(storage.has_block(&hashes[0]).await.unwrap());
+        assert!(!storage.has_block(&hashes[1]).await.unwrap());
+        assert_eq!(storage.get_blocks_at_height(1).await.unwrap().len(), 1);
+        assert_eq!(storage.count_blocks().unwrap(), 1);
+
+        // heights already checked are skipped
+        let block = BlockHeader::new(0, 1, 0, Vec::new(), [2; 32], miner, Vec::new());
+        let hash = block.hash();
+        storage.save_block(Arc::new(block), &Vec::new(), 1, hash.clone()).await.unwrap();
+        assert_eq!(storage.delete_orphaned_blocks_until_height(5, 10).await.unwrap(), 0);
+        assert!(storage.has_block(&hash).await.unwrap());
+
+        drop(storage);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_cursor() {
+        let path = _get_path("sync_cursor");
+        let db = sled::open(&path).unwrap();
+        let mut storage = _open(&db, &path, None).unwrap();
+        assert!(storage.get_sync_cursor().unwrap().is_none());
+
+        let pending: IndexSet<Hash> = (0..3u8).map(|i| hash(&[i])).collect();
+        let cursor = SyncCursor::new(BlockId::new(hash(b"common"), 42), pending.clone());
+        storage.set_sync_cursor(Some(&cursor)).unwrap();
+        drop(storage);
+
+        // still there after a restart
+        let mut storage = _open(&db, &path, None).unwrap();
+        let (common_point, saved) = storage.get_sync_cursor().unwrap().unwrap().consume();
+        assert_eq!(common_point.get_topoheight(), 42);
+        assert_eq!(common_point.get_hash(), &hash(b"common"));
+        assert_eq!(saved, pending);
+
+        storage.set_sync_cursor(None).unwrap();
+        assert!(storage.get_sync_cursor().unwrap().is_none());
+
+        drop(storage);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_versioned_names() {
+        let path = _get_path("names");
+        let mut storage = SledStorage::new(path.clone(), None, Network::Dev, None).unwrap();
+        let alice = KeyPair::new().get_public_key().clone();
+        let bob = KeyPair::new().get_public_key().clone();
+
+        storage.set_name_at_topoheight("alice", 5, &NameEntry::new(alice.clone(), 5, 100)).await.unwrap();
+        storage.set_name_at_topoheight("alice", 100, &NameEntry::new(bob.clone(), 100, 200)).await.unwrap();
+
+        assert!(storage.get_name_at_maximum_topoheight("alice", 4).await.unwrap().is_none());
+        assert!(storage.get_name_at_maximum_topoheight("bob", 150).await.unwrap().is_none());
+        let (topoheight, entry) = storage.get_name_at_maximum_topoheight("alice", 99).await.unwrap().unwrap();
+        assert_eq!(topoheight, 5);
+        assert_eq!(entry.get_owner(), &alice);
+        let (topoheight, entry) = storage.get_name_at_maximum_topoheight("alice", 150).await.unwrap().unwrap();
+        assert_eq!(topoheight, 100);
+        assert_eq!(entry.get_owner(), &bob);
+
+        // a rewind restores the previous registration
+        storage.delete_names_above_topoheight(50).await.unwrap();
+        let (topoheight, entry) = storage.get_name_at_maximum_topoheight("alice", 150).await.unwrap().unwrap();
+        assert_eq!(topoheight, 5);
+        assert_eq!(entry.get_owner(), &alice);
+
+        drop(storage);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_migrate_burned_supply() {
+        let path = _get_path("burned_supply");
+        let db = sled::open(&path).unwrap();
+        let mut storage = _open(&db, &path, None).unwrap();
+        let keypair = KeyPair::new();
+        for topoheight in 0..3u64 {
+            let tx = Transaction::new(keypair.get_public_key().clone(), TransactionType::Burn { asset: XELIS_ASSET, amount: 5 }, 0, topoheight, keypair.sign(b"test"));
+            let tx_hash = tx.hash();
+            let block = BlockHeader::new(0, topoheight, 0, Vec::new(), [topoheight as u8; 32], keypair.get_public_key().clone(), vec![tx_hash.clone()]);
+            let hash = block.hash();
+            storage.save_block(Arc::new(block), &vec![Immutable::Owned(tx)], 1, hash.clone()).await.unwrap();
+            storage.set_topo_height_for_block(&hash, topoheight).await.unwrap();
+            // the last transaction is not executed in its block
+            if topoheight < 2 {
+                storage.set_tx_executed_in_block(&tx_hash, &hash).unwrap();
+            }
+        }
+        storage.set_top_topoheight(2).unwrap();
+        drop(storage);
+
+        // database in version 2, created before the burned supply was tracked
+        db.open_tree("burned_supply").unwrap().clear().unwrap();
+        db.open_tree("extra").unwrap().insert(b"VERS", &[2]).unwrap();
+
+        let storage = _open(&db, &path, None).unwrap();
+        assert_eq!(storage.get_burned_supply_at_topo_height(0).await.unwrap(), 5);
+        assert_eq!(storage.get_burned_supply_at_topo_height(1).await.unwrap(), 10);
+        assert_eq!(storage.get_burned_supply_at_topo_height(2).await.unwrap(), 10);
+        // not considered without burn
+        assert!(storage.get_burned_supply_at_topo_height(3).await.is_err());
+
+        drop(storage);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_contracts_above_topoheight() {
+        let path = _get_path("contracts");
+        let db = sled::open(&path).unwrap();
+        let mut storage = _open(&db, &path, None).unwrap();
+        let (first, second) = (hash(b"first"), hash(b"second"));
+        storage.set_contract(&first, 5, "stop").await.unwrap();
+        storage.set_contract_data_at_topoheight(&first, "a", 5, 1).await.unwrap();
+        storage.set_contract_data_at_topoheight(&first, "a", 10, 2).await.unwrap();
+        storage.set_contract(&second, 12, "stop").await.unwrap();
+        drop(storage);
+
+        // database in version 3, created before the contracts changes were indexed
+        db.open_tree("contracts_changes").unwrap().clear().unwrap();
+        db.open_tree("extra").unwrap().insert(b"VERS", &[3]).unwrap();
+
+        let mut storage = _open(&db, &path, None).unwrap();
+        assert_eq!(storage.contracts_changes.len(), 4);
+        storage.delete_contracts_above_topoheight(8).await.unwrap();
+        assert!(storage.has_contract(&first).await.unwrap());
+        assert!(!storage.has_contract(&second).await.unwrap());
+        assert_eq!(storage.get_contract_data_at_maximum_topoheight(&first, "a", 20).await.unwrap(), Some((5, 1)));
+        assert_eq!(storage.contracts_changes.len(), 2);
+
+        drop(storage);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,439 @@
+use std::{collections::HashSet, sync::Arc};
+use anyhow::Error;
+use log::{debug, info};
+use xelis_common::{
+    api::daemon::{TipSelection, TipSelectionStrategy},
+    block::Difficulty,
+    crypto::{hash::Hash, key::PublicKey, address::Address},
+    transaction::Transaction,
+    network::Network,
+    utils::calculate_tx_fee,
+    config::FEE_PER_KB
+};
+use crate::config::TIPS_LIMIT;
+use super::error::BlockchainError;
+
+// Policy used by the daemon to fill the block templates with mempool transactions
+pub struct TemplateConfig {
+    // maximum transactions count per block template
+    max_txs: Option<usize>,
+    // maximum size in bytes of all transactions per block template
+    max_txs_size: Option<usize>,
+    // minimum fee per KB required to be selected
+    min_fee_per_kb: u64,
+    // transactions from these keys are selected first (own wallets)
+    priority_keys: HashSet<PublicKey>,
+    // transactions from these keys are never selected
+    blacklisted_keys: HashSet<PublicKey>,
+    // how the tips referenced are selected
+    tip_selection: TipSelectionStrategy
+}
+
+impl TemplateConfig {
+    pub fn new(max_txs: Option<usize>, max_txs_size: Option<usize>, min_fee_per_kb: u64, priority_keys: HashSet<PublicKey>, blacklisted_keys: HashSet<PublicKey>, tip_selection: TipSelectionStrategy) -> Self {
+        Self {
+            max_txs,
+            max_txs_size,
+            min_fee_per_kb,
+            priority_keys,
+            blacklisted_keys,
+            tip_selection
+        }
+    }
+
+    // build the policy from the addresses set by the operator
+    pub fn from_addresses(max_txs: Option<usize>, max_txs_size: Option<usize>, min_fee_per_kb: u64, priority_addresses: &Vec<String>, blacklisted_addresses: &Vec<String>, tip_selection: TipSelectionStrategy, network: &Network) -> Result<Self, Error> {
+        let priority_keys = parse_addresses(priority_addresses, network)?;
+        let blacklisted_keys = parse_addresses(blacklisted_addresses, network)?;
+        if !priority_keys.is_empty() {
+            info!("{} address(es) will be prioritized in block templates", priority_keys.len());
+        }
+
+        if !blacklisted_keys.is_empty() {
+            info!("{} address(es) are blacklisted from block templates", blacklisted_keys.len());
+        }
+
+        if tip_selection != TipSelectionStrategy::default() {
+            info!("Tips of block templates are selected using the {:?} strategy", tip_selection);
+        }
+
+        Ok(Self::new(max_txs, max_txs_size, min_fee_per_kb, priority_keys, blacklisted_keys, tip_selection))
+    }
+
+    pub fn get_max_txs(&self) -> Option<usize> {
+        self.max_txs
+    }
+
+    pub fn get_max_txs_size(&self) -> Option<usize> {
+        self.max_txs_size
+    }
+
+    pub fn get_min_fee_per_kb(&self) -> u64 {
+        self.min_fee_per_kb
+    }
+
+    pub fn is_priority(&self, key: &PublicKey) -> bool {
+        self.priority_keys.contains(key)
+    }
+
+    pub fn is_blacklisted(&self, key: &PublicKey) -> bool {
+        self.blacklisted_keys.contains(key)
+    }
+
+    pub fn get_tip_selection(&self) -> TipSelectionStrategy {
+        self.tip_selection
+    }
+
+    // minimum fee required by this policy for a transaction of this size
+    pub fn get_min_fee_for_size(&self, size: usize) -> u64 {
+        // we consume a full kb for fee
+        let size_in_kb = calculate_tx_fee(size) / FEE_PER_KB;
+        size_in_kb * self.min_fee_per_kb
+    }
+}
+
+fn parse_addresses(addresses: &Vec<String>, network: &Network) -> Result<HashSet<PublicKey>, Error> {
+    let mut keys = HashSet::with_capacity(addresses.len());
+    for value in addresses {
+        let address = Address::from_string(value)?;
+        if address.is_mainnet() != network.is_mainnet() {
+            return Err(BlockchainError::InvalidNetwork.into())
+        }
+        keys.insert(address.to_public_key());
+    }
+    Ok(keys)
+}
+
+// A tip that can be referenced by a block template
+pub struct TipCandidate {
+    pub hash: Hash,
+    pub difficulty: Difficulty,
+    pub cumulative_difficulty: Difficulty,
+    pub timestamp: u128
+}
+
+// same rule as the block verification: difficulty must be within 9% of the reference tip
+fn is_valid_tip(reference: &TipCandidate, tip: &TipCandidate) -> bool {
+    reference.difficulty * 91 / 100 < tip.difficulty
+}
+
+// select the tips of a block template using the strategy
+// candidates must be sorted by descending cumulative difficulty like `sort_tips`
+// so the first one selected is the reference tip used by the block verification
+// returns the selection of each candidate, in the same order
+pub fn select_tips(strategy: TipSelectionStrategy, candidates: &[TipCandidate]) -> Vec<TipSelection> {
+    if candidates.is_empty() {
+        return Vec::new()
+    }
+
+    let reference = match strategy {
+        TipSelectionStrategy::Inclusive => {
+            // a lighter reference is only used if it allows to merge more tips
+            let mut best = (0, 0);
+            for (i, reference) in candidates.iter().enumerate() {
+                let count = 1 + candidates[i + 1..].iter().filter(|tip| is_valid_tip(reference, tip)).count();
+                let count = count.min(TIPS_LIMIT);
+                if count > best.1 {
+                    best = (i, count);
+                }
+            }
+            best.0
+        },
+        _ => 0
+    };
+
+    let mut selections: Vec<TipSelection> = candidates.iter().enumerate().map(|(i, tip)| {
+        if i < reference {
+            TipSelection::AboveReference
+        } else if i == reference || is_valid_tip(&candidates[reference], tip) {
+            TipSelection::TipsLimit
+        } else {
+            TipSelection::LowDifficulty
+        }
+    }).collect();
+
+    // order of priority of the valid tips, the reference is always selected first
+    let mut valid: Vec<usize> = (reference..candidates.len()).filter(|i| selections[*i] == TipSelection::TipsLimit).collect();
+    if strategy == TipSelectionStrategy::Freshest {
+        valid[1..].sort_by(|a, b| candidates[*b].timestamp.cmp(&candidates[*a].timestamp));
+    }
+
+    for i in valid.into_iter().take(TIPS_LIMIT) {
+        selections[i] = TipSelection::Selected;
+    }
+
+    selections
+}
+
+// A mempool transaction that can be selected for a block template
+pub struct TxCandidate<'a> {
+    pub hash: &'a Arc<Hash>,
+    pub tx: &'a Arc<Transaction>,
+    pub size: usize
+}
+
+// Select and order the transactions to include in a block template
+// It only applies the policy rules, transactions must still be verified against the chain state
+pub struct TemplateBuilder<'a> {
+    config: &'a TemplateConfig,
+    // maximum block size allowed at this height
+    max_block_size: usize,
+    // current block size (header + txs hashes)
+    block_size: usize,
+    // transactions selected
+    txs_count: usize,
+    txs_size: usize
+}
+
+impl<'a> TemplateBuilder<'a> {
+    pub fn new(config: &'a TemplateConfig, max_block_size: usize, block_size: usize) -> Self {
+        Self {
+            config,
+            max_block_size,
+            block_size,
+            txs_count: 0,
+            txs_size: 0
+        }
+    }
+
+    // filter the candidates rejected by the policy and sort them in selection order
+    pub fn sort_candidates<'b>(&self, candidates: impl Iterator<Item = TxCandidate<'b>>) -> Vec<TxCandidate<'b>> {
+        let mut candidates: Vec<TxCandidate<'b>> = candidates.filter(|candidate| {
+            let tx = candidate.tx;
+            if self.config.is_blacklisted(tx.get_owner()) {
+                debug!("TX {} is from a blacklisted address, skipping it", candidate.hash);
+                return false
+            }
+
+            let min_fee = self.config.get_min_fee_for_size(candidate.size);
+            if tx.get_fee() < min_fee {
+                debug!("TX {} has a fee {} under the minimum {} required, skipping it", candidate.hash, tx.get_fee(), min_fee);
+                return false
+            }
+
+            true
+        }).collect();
+
+        candidates.sort_by(|a, b| {
+            let (a_tx, b_tx) = (a.tx, b.tx);
+            // If its the same sender, check the nonce
+            if a_tx.get_owner() == b_tx.get_owner() {
+                // Increasing nonces (lower first)
+                let cmp = a_tx.get_nonce().cmp(&b_tx.get_nonce());
+                // If its not equal nonce, returns it
+                if cmp != std::cmp::Ordering::Equal {
+                    return cmp
+                }
+            }
+
+            // Prioritized senders first
+            let a_priority = self.config.is_priority(a_tx.get_owner());
+            let b_priority = self.config.is_priority(b_tx.get_owner());
+            if a_priority != b_priority {
+                return b_priority.cmp(&a_priority)
+            }
+
+            let a = a_tx.get_fee() * a.size as u64;
+            let b = b_tx.get_fee() * b.size as u64;
+            // Decreasing fees (higher first)
+            b.cmp(&a)
+        });
+
+        candidates
+    }
+
+    // check if we can still add a transaction of this size
+    pub fn can_fit(&self, size: usize) -> bool {
+        if let Some(max_txs) = self.config.get_max_txs() {
+            if self.txs_count >= max_txs {
+                return false
+            }
+        }
+
+        if let Some(max_txs_size) = self.config.get_max_txs_size() {
+            if self.txs_size + size > max_txs_size {
+                return false
+            }
+        }
+
+        self.block_size + self.txs_size + size < self.max_block_size
+    }
+
+    // register a selected transaction, hash_size is the space used in the block header
+    pub fn add(&mut self, size: usize, hash_size: usize) {
+        self.txs_count += 1;
+        self.txs_size += size;
+        self.block_size += hash_size;
+    }
+
+    pub fn get_txs_count(&self) -> usize {
+        self.txs_count
+    }
+
+    pub fn get_txs_size(&self) -> usize {
+        self.txs_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Arc};
+    use xelis_common::{
+        api::daemon::{TipSelection, TipSelectionStrategy},
+        block::Difficulty,
+        crypto::{key::KeyPair, hash::{Hash, Hashable, hash}},
+        transaction::{Transaction, TransactionType}
+    };
+    use crate::config::TIPS_LIMIT;
+    use super::{TemplateConfig, TemplateBuilder, TxCandidate, TipCandidate, select_tips, is_valid_tip};
+
+    fn _create_tx(keypair: &KeyPair, fee: u64, nonce: u64) -> (Arc<Hash>, Arc<Transaction>) {
+        let data = TransactionType::Burn { asset: Hash::zero(), amount: 1 };
+        let signature = keypair.sign(b"test");
+        let tx = Transaction::new(keypair.get_public_key().clone(), data, fee, nonce, signature);
+        (Arc::new(tx.hash()), Arc::new(tx))
+    }
+
+    fn _candidates(txs: &[(Arc<Hash>, Arc<Transaction>)]) -> Vec<TxCandidate<'_>> {
+        txs.iter().map(|(hash, tx)| TxCandidate { hash, tx, size: 100 }).collect()
+    }
+
+    #[test]
+    fn test_blacklist_and_fee_floor() {
+        let (good, bad) = (KeyPair::new(), KeyPair::new());
+        let txs = vec![_create_tx(&good, 1000, 0), _create_tx(&good, 10, 1), _create_tx(&bad, 5000, 0)];
+        let config = TemplateConfig::new(None, None, 1000, HashSet::new(), HashSet::from([bad.get_public_key().clone()]), TipSelectionStrategy::Heaviest);
+        let builder = TemplateBuilder::new(&config, usize::MAX, 0);
+
+        let selected = builder.sort_candidates(_candidates(&txs).into_iter());
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].hash, &txs[0].0);
+    }
+
+    #[test]
+    fn test_priority_and_nonce_order() {
+        let (own, other) = (KeyPair::new(), KeyPair::new());
+        let txs = vec![_create_tx(&other, 5000, 0), _create_tx(&own, 1000, 1), _create_tx(&own, 1000, 0)];
+        let config = TemplateConfig::new(None, None, 0, HashSet::from([own.get_public_key().clone()]), HashSet::new(), TipSelectionStrategy::Heaviest);
+        let builder = TemplateBuilder::new(&config, usize::MAX, 0);
+
+        let selected = builder.sort_candidates(_candidates(&txs).into_iter());
+        let nonces: Vec<(bool, u64)> = selected.iter().map(|c| (c.tx.get_owner() == own.get_public_key(), c.tx.get_nonce())).collect();
+        assert_eq!(nonces, vec![(true, 0), (true, 1), (false, 0)]);
+    }
+
+    #[test]
+    fn test_limits() {
+        let config = TemplateConfig::new(Some(2), Some(250), 0, HashSet::new(), HashSet::new(), TipSelectionStrategy::Heaviest);
+        let mut builder = TemplateBuilder::new(&config, 1000, 100);
+        assert!(builder.can_fit(100));
+        builder.add(100, 32);
+        assert!(!builder.can_fit(200)); // above max txs size
+        assert!(builder.can_fit(100));
+        builder.add(100, 32);
+        assert!(!builder.can_fit(1)); // above max txs count
+        assert_eq!(builder.get_txs_count(), 2);
+        assert_eq!(builder.get_txs_size(), 200);
+    }
+
+    fn _tip(id: u64, difficulty: Difficulty, cumulative_difficulty: Difficulty, timestamp: u128) -> TipCandidate {
+        TipCandidate { hash: hash(&id.to_be_bytes()), difficulty, cumulative_difficulty, timestamp }
+    }
+
+    #[test]
+    fn test_tip_selection_strategies() {
+        use TipSelection::*;
+        // sorted by cumulative difficulty, the heaviest has a higher difficulty than the others
+        let tips = vec![_tip(0, 100, 300, 1), _tip(1, 85, 290, 2), _tip(2, 85, 280, 3), _tip(3, 86, 270, 4)];
+        assert_eq!(select_tips(TipSelectionStrategy::Heaviest, &tips), vec![Selected, LowDifficulty, LowDifficulty, LowDifficulty]);
+        assert_eq!(select_tips(TipSelectionStrategy::Freshest, &tips), vec![Selected, LowDifficulty, LowDifficulty, LowDifficulty]);
+        // a lighter reference allows to merge the three others
+        assert_eq!(select_tips(TipSelectionStrategy::Inclusive, &tips), vec![AboveReference, Selected, Selected, Selected]);
+
+        // more valid tips than allowed
+        let tips = vec![_tip(0, 100, 300, 1), _tip(1, 100, 290, 5), _tip(2, 100, 280, 2), _tip(3, 100, 270, 4)];
+        assert_eq!(select_tips(TipSelectionStrategy::Heaviest, &tips), vec![Selected, Selected, Selected, TipsLimit]);
+        assert_eq!(select_tips(TipSelectionStrategy::Freshest, &tips), vec![Selected, Selected, TipsLimit, Selected]);
+        assert_eq!(select_tips(TipSelectionStrategy::Inclusive, &tips), vec![Selected, Selected, Selected, TipsLimit]);
+        assert!(select_tips(TipSelectionStrategy::Inclusive, &[]).is_empty());
+    }
+
+    // xorshift, to have the same simulation on each run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, max: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % max
+        }
+    }
+
+    struct SimBlock {
+        tip: TipCandidate,
+        round: u64,
+        referenced: bool
+    }
+
+    // Miners finding blocks during the same propagation delay build on the same tips
+    // A tip not referenced after a few rounds is too far from the main chain and is orphaned
+    // returns the rate of blocks never referenced
+    fn _simulate_orphan_rate(strategy: TipSelectionStrategy) -> f64 {
+        const ROUNDS: u64 = 2000;
+        const MAX_TIP_AGE: u64 = 8;
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+        let mut blocks = vec![SimBlock { tip: _tip(0, 1000, 1000, 0), round: 0, referenced: false }];
+        let mut tips = vec![0];
+        for round in 1..=ROUNDS {
+            tips.retain(|i: &usize| round - blocks[*i].round <= MAX_TIP_AGE);
+            tips.sort_by(|a, b| {
+                let (a, b) = (&blocks[*a].tip, &blocks[*b].tip);
+                b.cumulative_difficulty.cmp(&a.cumulative_difficulty).then_with(|| b.hash.cmp(&a.hash))
+            });
+            let candidates: Vec<TipCandidate> = tips.iter()
+                .map(|i| { let tip = &blocks[*i].tip; TipCandidate { hash: tip.hash.clone(), ..*tip } })
+                .collect();
+            let selections = select_tips(strategy, &candidates);
+
+            // every template must be accepted by the block verification
+            let selected: Vec<usize> = tips.iter().zip(&selections)
+                .filter(|(_, selection)| **selection == TipSelection::Selected)
+                .map(|(i, _)| *i)
+                .collect();
+            assert!(!selected.is_empty() && selected.len() <= TIPS_LIMIT);
+            assert!(selected.iter().all(|i| is_valid_tip(&blocks[selected[0]].tip, &blocks[*i].tip)));
+
+            let found = 1 + rng.next(4);
+            let mut new_tips = Vec::new();
+            for _ in 0..found {
+                let difficulty = 1000 * (80 + rng.next(41)) / 100;
+                let cumulative_difficulty = blocks[selected[0]].tip.cumulative_difficulty + difficulty;
+                let timestamp = (round * 1000 + rng.next(1000)) as u128;
+                new_tips.push(blocks.len());
+                blocks.push(SimBlock { tip: _tip(blocks.len() as u64, difficulty, cumulative_difficulty, timestamp), round, referenced: false });
+            }
+
+            for i in &selected {
+                blocks[*i].referenced = true;
+            }
+            tips.retain(|i| !blocks[*i].referenced);
+            tips.extend(new_tips);
+        }
+
+        // the last blocks may still be referenced
+        let counted: Vec<&SimBlock> = blocks.iter().filter(|block| block.round + MAX_TIP_AGE < ROUNDS).collect();
+        counted.iter().filter(|block| !block.referenced).count() as f64 / counted.len() as f64
+    }
+
+    #[test]
+    fn test_tip_selection_orphan_rate() {
+        // ~21% for heaviest and freshest, the tips with a lower difficulty are never merged
+        let heaviest = _simulate_orphan_rate(TipSelectionStrategy::Heaviest);
+        let freshest = _simulate_orphan_rate(TipSelectionStrategy::Freshest);
+        assert!(heaviest > 0.1 && freshest > 0.1);
+        // ~2% for inclusive
+        let inclusive = _simulate_orphan_rate(TipSelectionStrategy::Inclusive);
+        assert!(inclusive < heaviest / 4.0 && inclusive < freshest / 4.0);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{collections::VecDeque, net::IpAddr, sync::{Arc, atomic::{AtomicI64, AtomicBool, Ordering}}};
+use tokio::sync::Mutex;
+use log::{debug, warn, info};
+use xelis_common::time::TimeSource;
+use crate::core::error::BlockchainError;
+use crate::config::{
+    TIME_OFFSET_SAMPLES, MINIMUM_TIME_OFFSET_SAMPLES, MAX_TIME_OFFSET_ADJUSTMENT,
+    MILLIS_PER_SECOND
+};
+
+// Keep track of the time offsets sent by peers in their handshake
+// the operator is warned when its clock drifts from the network time
+// a small median offset is applied to the timestamp of our block templates,
+// but the blocks are always verified using the local clock so the peers can't shift it
+pub struct TimeSync {
+    // local clock, never going backward
+    clock: Arc<dyn TimeSource>,
+    // (peer ip, offset in seconds between peer time and our local time)
+    samples: Mutex<VecDeque<(IpAddr, i64)>>,
+    // current median offset in seconds applied to our local time
+    offset: AtomicI64,
+    // prevent spamming the logs about the same clock drift
+    warned: AtomicBool
+}
+
+impl TimeSync {
+    pub fn new(clock: Arc<dyn TimeSource>) -> Self {
+        Self {
+            clock,
+            samples: Mutex::new(VecDeque::with_capacity(TIME_OFFSET_SAMPLES)),
+            offset: AtomicI64::new(0),
+            warned: AtomicBool::new(false)
+        }
+    }
+
+    // register the time announced by a peer (in seconds) in its handshake
+    pub async fn add_sample(&self, ip: IpAddr, peer_time: u64) {
+        let offset = peer_time as i64 - self.clock.now_secs() as i64;
+        debug!("Time offset with peer {}: {}s", ip, offset);
+
+        let mut samples = self.samples.lock().await;
+        // only one sample per IP, so a peer reconnecting can't influence the median
+        samples.retain(|(addr, _)| *addr != ip);
+        if samples.len() >= TIME_OFFSET_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back((ip, offset));
+
+        if samples.len() < MINIMUM_TIME_OFFSET_SAMPLES {
+            return;
+        }
+
+        let mut offsets: Vec<i64> = samples.iter().map(|(_, offset)| *offset).collect();
+        offsets.sort_unstable();
+        let median = offsets[offsets.len() / 2];
+
+        if median.abs() > MAX_TIME_OFFSET_ADJUSTMENT {
+            // too much difference to be adjusted without being rejected by the peers, only report it
+            self.offset.store(0, Ordering::SeqCst);
+            if !self.warned.swap(true, Ordering::SeqCst) {
+                warn!("Your local clock is drifting by {}s from the network time, please check your system clock!", median);
+            }
+            return;
+        }
+
+        self.offset.store(median, Ordering::SeqCst);
+        if self.warned.swap(false, Ordering::SeqCst) {
+            info!("Local clock is now synchronized with the network time (offset: {}s)", median);
+        }
+    }
+
+    pub fn get_clock(&self) -> &dyn TimeSource {
+        self.clock.as_ref()
+    }
+
+    // current offset in seconds applied to our local time
+    pub fn get_offset(&self) -> i64 {
+        self.offset.load(Ordering::SeqCst)
+    }
+
+    // local timestamp in milliseconds adjusted using the median offset of our peers
+    // only used to build the block templates
+    pub fn get_adjusted_timestamp(&self) -> u128 {
+        let offset = self.get_offset() as i128 * MILLIS_PER_SECOND as i128;
+        (self.clock.now() as i128 + offset).max(0) as u128
+    }
+}
+
+// median of the timestamps of the last blocks, 0 if there is none
+pub fn get_median_timestamp(timestamps: &mut Vec<u128>) -> u128 {
+    if timestamps.is_empty() {
+        return 0
+    }
+
+    timestamps.sort_unstable();
+    timestamps[timestamps.len() / 2]
+}
+
+// a block timestamp must be strictly greater than the median time past of its tips
+// so a miner can't move the chain time backward with old timestamps
+pub fn verify_median_time_past(timestamp: u128, median_time_past: u128) -> Result<(), BlockchainError> {
+    if timestamp <= median_time_past {
+        return Err(BlockchainError::TimestampIsLessThanMedianTimePast(timestamp, median_time_past))
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::{IpAddr, Ipv4Addr}, sync::Arc};
+    use xelis_common::{network::Network, time::{MockClock, TimeSource}};
+    use crate::{config::{MINIMUM_TIME_OFFSET_SAMPLES, MAX_TIME_OFFSET_ADJUSTMENT, MILLIS_PER_SECOND, TIMESTAMP_IN_FUTURE_LIMIT}, core::{error::BlockchainError, hard_fork}};
+    use super::{TimeSync, get_median_timestamp, verify_median_time_past};
+
+    const NOW: u64 = 1_700_000_000_000;
+
+    fn _ip(i: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, i))
+    }
+
+    #[tokio::test]
+    async fn test_median_offset() {
+        let clock = Arc::new(MockClock::new(NOW));
+        let time_sync = TimeSync::new(clock.clone());
+        for i in 0..MINIMUM_TIME_OFFSET_SAMPLES as u8 {
+            assert_eq!(time_sync.get_offset(), 0);
+            time_sync.add_sample(_ip(i), clock.now_secs() + 1).await;
+        }
+        assert_eq!(time_sync.get_offset(), 1);
+        assert_eq!(time_sync.get_adjusted_timestamp(), NOW as u128 + 1000);
+
+        // the local clock moves, the offset stays applied
+        clock.advance(1000);
+        assert_eq!(time_sync.get_adjusted_timestamp(), NOW as u128 + 2000);
+    }
+
+    #[tokio::test]
+    async fn test_offset_too_big() {
+        let clock = Arc::new(MockClock::new(NOW));
+        let time_sync = TimeSync::new(clock.clone());
+        for i in 0..MINIMUM_TIME_OFFSET_SAMPLES as u8 {
+            time_sync.add_sample(_ip(i), clock.now_secs() + MAX_TIME_OFFSET_ADJUSTMENT as u64 + 1).await;
+        }
+        assert_eq!(time_sync.get_offset(), 0);
+        assert_eq!(time_sync.get_adjusted_timestamp(), NOW as u128);
+
+        // our templates must stay accepted by the peers using their local clock
+        assert!(((MAX_TIME_OFFSET_ADJUSTMENT as u64 * MILLIS_PER_SECOND) as u128) < TIMESTAMP_IN_FUTURE_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn test_same_peer_counted_once() {
+        let clock = Arc::new(MockClock::new(NOW));
+        let time_sync = TimeSync::new(clock.clone());
+        for _ in 0..MINIMUM_TIME_OFFSET_SAMPLES {
+            time_sync.add_sample(_ip(0), clock.now_secs() + 5).await;
+        }
+        assert_eq!(time_sync.get_offset(), 0);
+    }
+
+    #[test]
+    fn test_median_timestamp() {
+        assert_eq!(get_median_timestamp(&mut Vec::new()), 0);
+        assert_eq!(get_median_timestamp(&mut vec![5, 1, 3]), 3);
+        assert_eq!(get_median_timestamp(&mut vec![40, 10, 30, 20]), 30);
+    }
+
+    #[test]
+    fn test_reject_timestamp_below_median_time_past() {
+        let mut timestamps = vec![1000, 1015, 1030, 1045, 1060, 1075, 1090, 1105, 1120, 1135, 1150];
+        let median_time_past = get_median_timestamp(&mut timestamps);
+        assert_eq!(median_time_past, 1075);
+
+        assert!(matches!(verify_median_time_past(1000, median_time_past), Err(BlockchainError::TimestampIsLessThanMedianTimePast(1000, 1075))));
+        assert!(matches!(verify_median_time_past(1075, median_time_past), Err(BlockchainError::TimestampIsLessThanMedianTimePast(1075, 1075))));
+        assert!(verify_median_time_past(1076, median_time_past).is_ok());
+    }
+
+    #[test]
+    fn test_median_time_past_hard_fork() {
+        // only scheduled on the dev network for now
+        let fork = hard_fork::get_upcoming_hard_forks(&Network::Dev, 0).find(|fork| fork.median_time_past).unwrap();
+        assert!(!hard_fork::get_hard_fork_at_height(&Network::Dev, fork.height - 1).median_time_past);
+        assert!(hard_fork::get_hard_fork_at_height(&Network::Dev, fork.height).median_time_past);
+        for network in [Network::Mainnet, Network::Testnet] {
+            assert!(!hard_fork::get_hard_fork_at_height(&network, u64::MAX).median_time_past);
+        }
+    }
+}
@@ -0,0 +1,394 @@
+mod proto {
+    tonic::include_proto!("xelis.daemon");
+}
+
+use std::{net::SocketAddr, pin::Pin, sync::Arc, fmt::Display};
+use anyhow::Context;
+use log::{info, warn, error, debug};
+use tokio::sync::{broadcast, Mutex, oneshot};
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+use tonic::{Request, Response, Status, transport::Server};
+use xelis_common::{
+    api::daemon::BlockType as ApiBlockType,
+    block::BlockHeader,
+    config::VERSION,
+    crypto::{hash::{Hash, Hashable, HASH_SIZE}, address::Address, key::PublicKey},
+    immutable::Immutable,
+    serializer::Serializer,
+    transaction::{Transaction as ApiTransaction, TransactionType}
+};
+use crate::{
+    config::{BLOCK_TIME_MILLIS, MAXIMUM_SUPPLY},
+    core::{blockchain::{Blockchain, get_block_reward}, storage::Storage},
+    rpc::rpc::get_block_type_for_block
+};
+use self::proto::{
+    daemon_server::{Daemon, DaemonServer},
+    transaction::Data,
+    Block, BlockType, Burn, GetBalanceRequest, GetBalanceResponse, GetBlockAtTopoHeightRequest,
+    GetBlockByHashRequest, GetInfoRequest, GetInfoResponse, GetNonceRequest, GetNonceResponse,
+    GetTransactionRequest, SubmitBlockRequest, SubmitBlockResponse, SubmitTransactionRequest,
+    SubmitTransactionResponse, SubscribeRequest, RegisterName, Transaction, Transfer, Transfers
+};
+
+// how many events can be buffered for a slow subscriber before it starts missing them
+const STREAM_CAPACITY: usize = 64;
+
+pub type SharedGrpcServer = Arc<GrpcServer>;
+type EventStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+// gRPC server mirroring the core RPC methods
+// new blocks and mempool transactions are streamed to the subscribers
+pub struct GrpcServer {
+    blocks: broadcast::Sender<Block>,
+    transactions: broadcast::Sender<Transaction>,
+    // used to stop the server
+    exit: Mutex<Option<oneshot::Sender<()>>>
+}
+
+impl GrpcServer {
+    pub async fn new<S: Storage>(bind_address: String, blockchain: Arc<Blockchain<S>>) -> Result<SharedGrpcServer, anyhow::Error> {
+        let addr: SocketAddr = bind_address.parse().context("Invalid gRPC bind address")?;
+        let (blocks, _) = broadcast::channel(STREAM_CAPACITY);
+        let (transactions, _) = broadcast::channel(STREAM_CAPACITY);
+        let (exit_sender, exit_receiver) = oneshot::channel();
+
+        let server = Arc::new(Self {
+            blocks,
+            transactions,
+            exit: Mutex::new(Some(exit_sender))
+        });
+
+        let service = DaemonService {
+            blockchain,
+            server: Arc::clone(&server)
+        };
+
+        info!("gRPC server listening on {}", addr);
+        tokio::spawn(async move {
+            let res = Server::builder()
+                .add_service(DaemonServer::new(service))
+                .serve_with_shutdown(addr, async move {
+                    let _ = exit_receiver.await;
+                }).await;
+
+            if let Err(e) = res {
+                error!("Error while running gRPC server: {}", e);
+            }
+        });
+
+        Ok(server)
+    }
+
+    pub fn has_block_subscribers(&self) -> bool {
+        self.blocks.receiver_count() > 0
+    }
+
+    pub fn has_transaction_subscribers(&self) -> bool {
+        self.transactions.receiver_count() > 0
+    }
+
+    pub fn notify_new_block(&self, block: Block) {
+        if self.blocks.send(block).is_err() {
+            debug!("No subscriber for new blocks on gRPC server");
+        }
+    }
+
+    pub fn notify_new_transaction(&self, transaction: Transaction) {
+        if self.transactions.send(transaction).is_err() {
+            debug!("No subscriber for mempool on gRPC server");
+        }
+    }
+
+    pub async fn stop(&self) {
+        info!("Stopping gRPC Server...");
+        match self.exit.lock().await.take() {
+            Some(exit) => {
+                let _ = exit.send(());
+                info!("gRPC Server is now stopped!");
+            },
+            None => warn!("gRPC Server is not running!")
+        }
+    }
+
+    fn subscribe<T: Clone + Send + 'static>(sender: &broadcast::Sender<T>) -> EventStream<T> {
+        // a lagging subscriber skips the events it missed
+        let stream = BroadcastStream::new(sender.subscribe())
+            .filter_map(|event| event.ok().map(Ok));
+        Box::pin(stream)
+    }
+}
+
+struct DaemonService<S: Storage> {
+    blockchain: Arc<Blockchain<S>>,
+    server: SharedGrpcServer
+}
+
+fn internal_error<E: Display>(e: E) -> Status {
+    Status::internal(e.to_string())
+}
+
+fn parse_hash(bytes: &[u8]) -> Result<Hash, Status> {
+    let bytes: [u8; HASH_SIZE] = bytes.try_into().map_err(|_| Status::invalid_argument("Invalid hash length"))?;
+    Ok(Hash::new(bytes))
+}
+
+impl<S: Storage> DaemonService<S> {
+    fn parse_address(&self, value: &str) -> Result<PublicKey, Status> {
+        let address = Address::from_string(&value.to_owned()).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        if address.is_mainnet() != self.blockchain.get_network().is_mainnet() {
+            return Err(Status::invalid_argument("Address is not in the same network"))
+        }
+        Ok(address.to_public_key())
+    }
+
+    async fn get_block_for_hash(&self, hash: Hash, include_txs: bool) -> Result<Block, Status> {
+        let storage = self.blockchain.get_storage().read().await;
+        build_block(&self.blockchain, &storage, hash, include_txs).await.map_err(|e| Status::not_found(e.to_string()))
+    }
+}
+
+// Build a block message with its metadata, like the JSON-RPC block response
+pub async fn build_block<S: Storage>(blockchain: &Blockchain<S>, storage: &S, hash: Hash, include_txs: bool) -> Result<Block, anyhow::Error> {
+    let (topoheight, supply, burned_supply, reward) = if storage.is_block_topological_ordered(&hash).await {
+        let topoheight = storage.get_topo_height_for_hash(&hash).await?;
+        (
+            Some(topoheight),
+            Some(storage.get_supply_at_topo_height(topoheight).await?),
+            Some(storage.get_burned_supply_at_topo_height(topoheight).await?),
+            Some(storage.get_block_reward_at_topo_height(topoheight)?)
+        )
+    } else {
+        (None, None, None, None)
+    };
+
+    let block_type = match get_block_type_for_block(blockchain, storage, &hash).await? {
+        ApiBlockType::Normal => BlockType::Normal,
+        ApiBlockType::Sync => BlockType::Sync,
+        ApiBlockType::Side => BlockType::Side,
+        ApiBlockType::Orphaned => BlockType::Orphaned
+    };
+    let cumulative_difficulty = storage.get_cumulative_difficulty_for_block_hash(&hash).await?;
+    let difficulty = storage.get_difficulty_for_block_hash(&hash).await?;
+
+    let (header, total_size_in_bytes, total_fees, transactions) = if include_txs {
+        let block = storage.get_block(&hash).await?;
+        let total_size_in_bytes = block.size();
+        let mut total_fees = 0;
+        let mut transactions = Vec::with_capacity(block.get_txs_count());
+        for (tx, tx_hash) in block.get_transactions().iter().zip(block.get_txs_hashes()) {
+            // only the fees of the txs executed in this block are counted
+            if storage.is_tx_executed_in_block(tx_hash, &hash)? {
+                total_fees += tx.get_fee();
+            }
+            transactions.push(build_transaction(storage, tx, tx_hash.clone(), false, None)?);
+        }
+
+        (Immutable::Owned(block.get_header().clone()), total_size_in_bytes, Some(total_fees), transactions)
+    } else {
+        let header = storage.get_block_header_by_hash(&hash).await?;
+        let mut total_size_in_bytes = header.size();
+        for tx_hash in header.get_txs_hashes() {
+            total_size_in_bytes += storage.get_transaction_size(tx_hash).await?;
+        }
+
+        (Immutable::Arc(header), total_size_in_bytes, None, Vec::new())
+    };
+
+    let mut block = build_block_header(&header, hash);
+    block.topoheight = topoheight;
+    block.set_block_type(block_type);
+    block.difficulty = difficulty;
+    block.cumulative_difficulty = cumulative_difficulty;
+    block.supply = supply;
+    block.burned_supply = burned_supply;
+    block.reward = reward;
+    block.total_fees = total_fees;
+    block.total_size_in_bytes = total_size_in_bytes as u64;
+    block.transactions = transactions;
+
+    Ok(block)
+}
+
+fn build_block_header(header: &BlockHeader, hash: Hash) -> Block {
+    Block {
+        hash: hash.as_bytes().to_vec(),
+        version: header.get_version() as u32,
+        tips: header.get_tips().iter().map(|tip| tip.as_bytes().to_vec()).collect(),
+        timestamp: header.get_timestamp() as u64,
+        height: header.get_height(),
+        nonce: header.get_nonce(),
+        extra_nonce: header.get_extra_nonce().to_vec(),
+        miner: header.get_miner().to_string(),
+        txs_hashes: header.get_txs_hashes().iter().map(|tx| tx.as_bytes().to_vec()).collect(),
+        ..Default::default()
+    }
+}
+
+// Build a transaction message, blocks in which it was included are added if available
+pub fn build_transaction<S: Storage>(storage: &S, tx: &ApiTransaction, hash: Hash, in_mempool: bool, first_seen: Option<u64>) -> Result<Transaction, anyhow::Error> {
+    let blocks = if storage.has_tx_blocks(&hash)? {
+        storage.get_blocks_for_tx(&hash)?.into_iter().map(|block| block.as_bytes().to_vec()).collect()
+    } else {
+        Vec::new()
+    };
+    let executed_in_block = storage.get_block_executer_for_tx(&hash).ok().map(|block| block.as_bytes().to_vec());
+
+    let data = match tx.get_data() {
+        TransactionType::Transfer(transfers) => Data::Transfers(Transfers {
+            transfers: transfers.iter().map(|transfer| Transfer {
+                amount: transfer.amount,
+                asset: transfer.asset.as_bytes().to_vec(),
+                to: transfer.to.to_string(),
+                extra_data: transfer.extra_data.clone()
+            }).collect()
+        }),
+        TransactionType::Burn { asset, amount } => Data::Burn(Burn {
+            asset: asset.as_bytes().to_vec(),
+            amount: *amount
+        }),
+        TransactionType::CallContract(_) | TransactionType::DeployContract(_) => Data::SmartContract(true),
+        TransactionType::RegisterName { name, duration } => Data::RegisterName(RegisterName {
+            name: name.clone(),
+            duration: *duration
+        })
+    };
+
+    Ok(Transaction {
+        hash: hash.as_bytes().to_vec(),
+        version: tx.get_version() as u32,
+        owner: tx.get_owner().to_string(),
+        fee: tx.get_fee(),
+        nonce: tx.get_nonce(),
+        signature: tx.get_signature().to_bytes(),
+        data: Some(data),
+        raw: tx.to_bytes(),
+        blocks,
+        executed_in_block,
+        in_mempool,
+        first_seen
+    })
+}
+
+#[tonic::async_trait]
+impl<S: Storage> Daemon for DaemonService<S> {
+    type SubscribeNewBlocksStream = EventStream<Block>;
+    type SubscribeMempoolStream = EventStream<Transaction>;
+
+    async fn get_info(&self, _: Request<GetInfoRequest>) -> Result<Response<GetInfoResponse>, Status> {
+        let blockchain = &self.blockchain;
+        let topoheight = blockchain.get_topo_height();
+        let (top_block_hash, emitted_supply, burned_supply, pruned_topoheight, average_block_time) = {
+            let storage = blockchain.get_storage().read().await;
+            let top_block_hash = storage.get_hash_at_topo_height(topoheight).await.map_err(internal_error)?;
+            let supply = storage.get_supply_at_topo_height(topoheight).await.map_err(internal_error)?;
+            let burned_supply = storage.get_burned_supply_at_topo_height(topoheight).await.map_err(internal_error)?;
+            let pruned_topoheight = storage.get_pruned_topoheight().map_err(internal_error)?;
+            let average_block_time = blockchain.get_average_block_time_for_storage(&storage).await.map_err(internal_error)?;
+            (top_block_hash, supply, burned_supply, pruned_topoheight, average_block_time)
+        };
+
+        Ok(Response::new(GetInfoResponse {
+            height: blockchain.get_height(),
+            topoheight,
+            stableheight: blockchain.get_stable_height(),
+            pruned_topoheight,
+            top_block_hash: top_block_hash.as_bytes().to_vec(),
+            circulating_supply: emitted_supply.saturating_sub(burned_supply),
+            maximum_supply: MAXIMUM_SUPPLY,
+            difficulty: blockchain.get_difficulty(),
+            block_time_target: BLOCK_TIME_MILLIS,
+            average_block_time,
+            block_reward: get_block_reward(emitted_supply),
+            mempool_size: blockchain.get_mempool_size().await as u64,
+            version: VERSION.to_owned(),
+            network: blockchain.get_network().to_string(),
+            emitted_supply,
+            burned_supply
+        }))
+    }
+
+    async fn get_block_by_hash(&self, request: Request<GetBlockByHashRequest>) -> Result<Response<Block>, Status> {
+        let request = request.into_inner();
+        let hash = parse_hash(&request.hash)?;
+        let block = self.get_block_for_hash(hash, request.include_txs).await?;
+        Ok(Response::new(block))
+    }
+
+    async fn get_block_at_topo_height(&self, request: Request<GetBlockAtTopoHeightRequest>) -> Result<Response<Block>, Status> {
+        let request = request.into_inner();
+        let hash = {
+            let storage = self.blockchain.get_storage().read().await;
+            storage.get_hash_at_topo_height(request.topoheight).await.map_err(|e| Status::not_found(e.to_string()))?
+        };
+        let block = self.get_block_for_hash(hash, request.include_txs).await?;
+        Ok(Response::new(block))
+    }
+
+    async fn get_transaction(&self, request: Request<GetTransactionRequest>) -> Result<Response<Transaction>, Status> {
+        let hash = parse_hash(&request.into_inner().hash)?;
+        let storage = self.blockchain.get_storage().read().await;
+        // first check on disk, then check in mempool
+        let transaction = match storage.get_transaction(&hash).await {
+            Ok(tx) => build_transaction(&*storage, &tx, hash, false, None),
+            Err(_) => {
+                let mempool = self.blockchain.get_mempool().read().await;
+                let tx = mempool.get_sorted_tx(&hash).map_err(|e| Status::not_found(e.to_string()))?;
+                build_transaction(&*storage, tx.get_tx(), hash, true, Some(tx.get_first_seen()))
+            }
+        }.map_err(internal_error)?;
+
+        Ok(Response::new(transaction))
+    }
+
+    async fn get_balance(&self, request: Request<GetBalanceRequest>) -> Result<Response<GetBalanceResponse>, Status> {
+        let request = request.into_inner();
+        let key = self.parse_address(&request.address)?;
+        let asset = parse_hash(&request.asset)?;
+        let storage = self.blockchain.get_storage().read().await;
+        let (topoheight, balance) = storage.get_last_balance(&key, &asset).await.map_err(|e| Status::not_found(e.to_string()))?;
+        Ok(Response::new(GetBalanceResponse {
+            balance: balance.get_balance(),
+            previous_topoheight: balance.get_previous_topoheight(),
+            topoheight
+        }))
+    }
+
+    async fn get_nonce(&self, request: Request<GetNonceRequest>) -> Result<Response<GetNonceResponse>, Status> {
+        let key = self.parse_address(&request.into_inner().address)?;
+        let storage = self.blockchain.get_storage().read().await;
+        let (topoheight, nonce) = storage.get_last_nonce(&key).await.map_err(|e| Status::not_found(e.to_string()))?;
+        Ok(Response::new(GetNonceResponse {
+            nonce: nonce.get_nonce(),
+            previous_topoheight: nonce.get_previous_topoheight(),
+            topoheight
+        }))
+    }
+
+    async fn submit_transaction(&self, request: Request<SubmitTransactionRequest>) -> Result<Response<SubmitTransactionResponse>, Status> {
+        let tx = ApiTransaction::from_bytes(&request.into_inner().data).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let hash = tx.hash();
+        self.blockchain.add_tx_to_mempool_with_hash(tx, hash.clone(), true).await.map_err(|e| Status::failed_precondition(e.to_string()))?;
+        Ok(Response::new(SubmitTransactionResponse {
+            hash: hash.as_bytes().to_vec()
+        }))
+    }
+
+    async fn submit_block(&self, request: Request<SubmitBlockRequest>) -> Result<Response<SubmitBlockResponse>, Status> {
+        let header = BlockHeader::from_bytes(&request.into_inner().block_template).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let block = self.blockchain.build_block_from_header(Immutable::Owned(header)).await.map_err(|e| Status::failed_precondition(e.to_string()))?;
+        let hash = block.hash();
+        self.blockchain.add_new_block(block, true, true).await.map_err(|e| Status::failed_precondition(e.to_string()))?;
+        Ok(Response::new(SubmitBlockResponse {
+            hash: hash.as_bytes().to_vec()
+        }))
+    }
+
+    async fn subscribe_new_blocks(&self, _: Request<SubscribeRequest>) -> Result<Response<Self::SubscribeNewBlocksStream>, Status> {
+        Ok(Response::new(GrpcServer::subscribe(&self.server.blocks)))
+    }
+
+    async fn subscribe_mempool(&self, _: Request<SubscribeRequest>) -> Result<Response<Self::SubscribeMempoolStream>, Status> {
+        Ok(Response::new(GrpcServer::subscribe(&self.server.transactions)))
+    }
+}
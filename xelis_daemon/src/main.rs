@@ -2,26 +2,33 @@ pub mod rpc;
 pub mod p2p;
 pub mod core;
 pub mod config;
+pub mod admin;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "wallet")]
+pub mod wallet;
 
 use fern::colors::Color;
 use humantime::format_duration;
 use log::{info, error, warn};
-use p2p::P2pServer;
+use p2p::{P2pServer, corpus::replay_corpus};
 use rpc::{getwork_server::SharedGetWorkServer, rpc::get_block_response_for_hash};
 use xelis_common::{
     prompt::{Prompt, command::{CommandManager, CommandError, Command, CommandHandler}, PromptError, argument::{ArgumentManager, Arg, ArgType}, LogLevel, self, ShareablePrompt},
-    config::{VERSION, XELIS_ASSET}, utils::{format_hashrate, set_network_to, format_xelis, format_coin, format_difficulty}, async_handler, crypto::{address::Address, hash::Hashable}, network::Network, transaction::Transaction, serializer::Serializer, block::Difficulty
+    config::{VERSION, XELIS_ASSET}, utils::{format_hashrate, set_network_to, format_xelis, format_coin, format_difficulty, get_current_timestamp}, async_handler, crypto::{address::Address, hash::Hashable}, network::Network, transaction::{Transaction, TransactionType}, serializer::Serializer, block::Difficulty
 };
 use crate::{
     core::{
         blockchain::{Config, Blockchain, get_block_reward},
-        storage::{Storage, SledStorage}
+        storage::{Storage, SledStorage, load_storage_key},
+        genesis::{GenesisConfig, GenesisAllocation, StateExport}
     },
-    config::{BLOCK_TIME_MILLIS, MILLIS_PER_SECOND}
+    config::{BLOCK_TIME_MILLIS, MILLIS_PER_SECOND, MINIMUM_DIFFICULTY, DEV_PUBLIC_KEY, get_default_admin_socket_path},
+    admin::AdminCommand
 };
-use std::{sync::Arc, net::IpAddr};
+use std::{sync::Arc, net::IpAddr, path::Path};
 use std::time::Duration;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use anyhow::{Result, Context};
 
 #[derive(Parser)]
@@ -40,12 +47,85 @@ pub struct NodeConfig {
     filename_log: String,
     /// Network selected for chain
     #[clap(long, arg_enum, default_value_t = Network::Mainnet)]
-    network: Network
+    network: Network,
+    /// Enable the local admin socket to run admin commands using `xelis_daemon admin`
+    #[clap(long)]
+    enable_admin_socket: bool,
+    /// Path of the admin socket, default depends on the network
+    #[clap(long, global = true)]
+    admin_socket_path: Option<String>,
+    /// Number of threads running the P2P server and the other async tasks, default is the number of CPU cores
+    #[clap(long)]
+    worker_threads: Option<usize>,
+    #[cfg(feature = "wallet")]
+    #[structopt(flatten)]
+    wallet: wallet::WalletConfig,
+    #[clap(subcommand)]
+    command: Option<DaemonCommand>
+}
+
+#[derive(Subcommand)]
+enum DaemonCommand {
+    /// Send an admin command to a running daemon
+    #[clap(subcommand)]
+    Admin(AdminCommand),
+    /// Generate the genesis file of a custom network, loaded by the daemon using `--genesis-file`
+    GenerateGenesis(GenerateGenesisCommand)
+}
+
+#[derive(clap::Args)]
+struct GenerateGenesisCommand {
+    /// File to write the genesis to
+    #[clap(long, default_value_t = String::from("genesis.json"))]
+    output: String,
+    /// Message committed in the genesis block
+    #[clap(long, default_value_t = String::new())]
+    message: String,
+    /// Timestamp in milliseconds of the genesis block, default is now
+    #[clap(long)]
+    timestamp: Option<u128>,
+    /// Difficulty of the first blocks, until enough blocks are available to compute it
+    #[clap(long, default_value_t = MINIMUM_DIFFICULTY)]
+    initial_difficulty: Difficulty,
+    /// Coins allocated at genesis as <address>:<amount in atomic units>, can be set several times
+    #[clap(long)]
+    allocation: Vec<String>,
+    /// Address receiving the reward of the genesis block, default is the dev address
+    #[clap(long)]
+    miner_address: Option<String>,
+    /// State file written by `admin export-state`, its accounts are added to the genesis
+    #[clap(long)]
+    state: Option<String>,
+    /// Don't allocate the balances of the state file
+    #[clap(long)]
+    zero_balances: bool,
+    /// Register the accounts of the state file without coins in the genesis
+    #[clap(long)]
+    carry_registrations: bool
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let mut config: NodeConfig = NodeConfig::parse();
+fn main() -> Result<()> {
+    let config: NodeConfig = NodeConfig::parse();
+    // RPC requests and block verification have their own threads, see `rpc_threads`, `verification_threads` and `mempool_verification_threads`
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all();
+    if let Some(threads) = config.worker_threads {
+        builder.worker_threads(threads.max(1));
+    }
+
+    let runtime = builder.build().context("Error while building the async runtime")?;
+    runtime.block_on(run(config))
+}
+
+async fn run(mut config: NodeConfig) -> Result<()> {
+    if let Some(DaemonCommand::Admin(command)) = config.command {
+        let path = config.admin_socket_path.unwrap_or_else(|| get_default_admin_socket_path(&config.network));
+        return run_admin_command(&path, command).await;
+    }
+
+    if let Some(DaemonCommand::GenerateGenesis(command)) = config.command {
+        return generate_genesis(command, config.network);
+    }
 
     let prompt = Prompt::new(config.log_level, config.filename_log, config.disable_file_logging)?;
     info!("XELIS Blockchain running version: {}", VERSION);
@@ -68,20 +148,106 @@ async fn main() -> Result<()> {
         let dir_path = if let Some(path) = blockchain_config.dir_path.as_ref() {
             path.clone()
         } else {
-            config.network.to_string().to_lowercase()
+            config.network.get_default_dir_name().to_owned()
         };
-        SledStorage::new(dir_path, use_cache, config.network)?
+        let storage_key = match blockchain_config.storage_key_file.as_ref() {
+            Some(path) => Some(load_storage_key(path)?),
+            None => None
+        };
+        SledStorage::new(dir_path, use_cache, config.network, storage_key)?
     };
 
     let blockchain = Blockchain::new(blockchain_config, config.network, storage).await?;
+
+    #[cfg(unix)]
+    let admin_server = if config.enable_admin_socket {
+        let path = config.admin_socket_path.unwrap_or_else(|| get_default_admin_socket_path(&config.network));
+        match admin::AdminServer::new(path, blockchain.clone(), prompt.clone()).await {
+            Ok(server) => Some(server),
+            Err(e) => {
+                error!("Error while starting admin socket: {:#}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    #[cfg(not(unix))]
+    if config.enable_admin_socket {
+        warn!("Admin socket is only available on Unix systems");
+    }
+
+    #[cfg(feature = "wallet")]
+    let wallet = match wallet::start_wallet(config.wallet, blockchain.clone(), config.network, &prompt).await {
+        Ok(wallet) => wallet,
+        Err(e) => {
+            error!("Error while starting wallet: {:#}", e);
+            None
+        }
+    };
+
     if let Err(e) = run_prompt(prompt, blockchain.clone(), config.network).await {
         error!("Error while running prompt: {}", e);
     }
 
+    #[cfg(feature = "wallet")]
+    if let Some(wallet) = wallet {
+        wallet::stop_wallet(&wallet).await;
+    }
+
+    #[cfg(unix)]
+    if let Some(server) = admin_server {
+        server.stop();
+    }
+
     blockchain.stop().await;
     Ok(())
 }
 
+// Send the command to the running daemon and print its response
+async fn run_admin_command(socket_path: &str, command: AdminCommand) -> Result<()> {
+    let response = admin::send_command(socket_path, &command).await?;
+    if response.success {
+        println!("{}", response.message);
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(response.message))
+    }
+}
+
+fn generate_genesis(command: GenerateGenesisCommand, network: Network) -> Result<()> {
+    let miner = match command.miner_address {
+        Some(address) => {
+            let address = Address::from_string(&address).context("Invalid miner address")?;
+            if address.is_mainnet() != network.is_mainnet() {
+                anyhow::bail!("Miner address {} is not for network {}", address, network)
+            }
+            address.to_public_key()
+        },
+        None => DEV_PUBLIC_KEY.clone()
+    };
+
+    let mut allocations = command.allocation.iter()
+        .map(|value| GenesisAllocation::from_string(value))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut registrations = Vec::new();
+    if let Some(path) = command.state.as_ref() {
+        let state = StateExport::load(path).context("Error while reading the state file")?;
+        let (state_allocations, state_registrations) = state.to_genesis_accounts(&network, command.zero_balances, command.carry_registrations);
+        println!("Loaded {} accounts of network {} at topoheight {}: {} allocation(s), {} registration(s)", state.accounts.len(), state.network, state.topoheight, state_allocations.len(), state_registrations.len());
+        allocations.extend(state_allocations);
+        registrations = state_registrations;
+    } else if command.zero_balances || command.carry_registrations {
+        anyhow::bail!("--zero-balances and --carry-registrations require a state file");
+    }
+    let timestamp = command.timestamp.unwrap_or_else(get_current_timestamp);
+
+    let genesis = GenesisConfig::generate(network, miner, command.message, timestamp, command.initial_difficulty, allocations, registrations)?;
+    genesis.save(&command.output).context("Error while writing the genesis file")?;
+    println!("Genesis block {} for network {} written to {}", genesis.hash, network, command.output);
+    Ok(())
+}
+
 async fn run_prompt<S: Storage>(prompt: ShareablePrompt, blockchain: Arc<Blockchain<S>>, network: Network) -> Result<(), PromptError> {
     let command_manager: CommandManager<Arc<Blockchain<S>>> = CommandManager::default(prompt.clone())?;
     // Set the data to use
@@ -101,6 +267,7 @@ async fn run_prompt<S: Storage>(prompt: ShareablePrompt, blockchain: Arc<Blockch
     command_manager.add_command(Command::with_optional_arguments("blacklist", "View blacklist or add a peer address in it", vec![Arg::new("address", ArgType::String)], CommandHandler::Async(async_handler!(blacklist))))?;
     command_manager.add_command(Command::with_optional_arguments("whitelist", "View whitelist or add a peer address in it", vec![Arg::new("address", ArgType::String)], CommandHandler::Async(async_handler!(whitelist))))?;
     command_manager.add_command(Command::new("verify_chain", "Check chain supply/balances", CommandHandler::Async(async_handler!(verify_chain))))?;
+    command_manager.add_command(Command::with_arguments("replay_packets", "Decode the packets recorded in a directory, and N random mutations of each", vec![Arg::new("dir", ArgType::String)], vec![Arg::new("mutations", ArgType::Number)], CommandHandler::Async(async_handler!(replay_packets))))?;
 
     // Don't keep the lock for ever
     let (p2p, getwork) = {
@@ -204,6 +371,7 @@ async fn verify_chain<S: Storage>(manager: &CommandManager<Arc<Blockchain<S>>>,
 
     let storage = blockchain.get_storage().read().await;
     let mut expected_supply = 0;
+    let mut expected_burned_supply = 0;
     for topo in 0..=blockchain.get_topo_height() {
         let hash_at_topo = storage.get_hash_at_topo_height(topo).await.context("Error while retrieving hash at topo")?;
         let block_reward = blockchain.get_block_reward(&*storage, &hash_at_topo, expected_supply).await.context("Error while calculating block reward")?;
@@ -221,6 +389,22 @@ async fn verify_chain<S: Storage>(manager: &CommandManager<Arc<Blockchain<S>>>,
             manager.error(format!("Error for block {} at topoheight {}, expected {} found {}", hash_at_topo, topo, expected_supply, supply));
             return Ok(())
         }
+
+        let block = storage.get_block(&hash_at_topo).await.context("Error while retrieving block")?;
+        for (tx, tx_hash) in block.get_transactions().iter().zip(block.get_txs_hashes()) {
+            if let TransactionType::Burn { asset, amount } = tx.get_data() {
+                if *asset == XELIS_ASSET && storage.is_tx_executed_in_block(tx_hash, &hash_at_topo).context("Error while checking if tx was executed")? {
+                    expected_burned_supply += amount;
+                }
+            }
+        }
+
+        // Verify the burned supply at block
+        let burned_supply = storage.get_burned_supply_at_topo_height(topo).await.context("Error while retrieving burned supply at topoheight")?;
+        if burned_supply != expected_burned_supply {
+            manager.error(format!("Error for block {} at topoheight {}, expected {} burned found {}", hash_at_topo, topo, expected_burned_supply, burned_supply));
+            return Ok(())
+        }
     }
     manager.message("Supply is valid");
 
@@ -242,6 +426,11 @@ async fn verify_chain<S: Storage>(manager: &CommandManager<Arc<Blockchain<S>>>,
         skip += chunk_size;
     }
 
+    // burned coins are not owned by any account anymore
+    let Some(expected_supply) = expected_supply.checked_sub(expected_burned_supply) else {
+        manager.error(format!("Burned supply {} is greater than the emitted supply {}", expected_burned_supply, expected_supply));
+        return Ok(())
+    };
     if total_balances != expected_supply {
         manager.error(format!("Total balances is not equal to expected supply! Balances: {}, Supply: {}", total_balances, expected_supply));
     } else {
@@ -374,6 +563,28 @@ async fn pop_blocks<S: Storage>(manager: &CommandManager<Arc<Blockchain<S>>>, mu
     Ok(())
 }
 
+async fn replay_packets<S: Storage>(manager: &CommandManager<Arc<Blockchain<S>>>, mut arguments: ArgumentManager) -> Result<(), CommandError> {
+    let dir = arguments.get_value("dir")?.to_string_value()?;
+    let mutations = if arguments.has_argument("mutations") {
+        arguments.get_value("mutations")?.to_number()? as usize
+    } else {
+        0
+    };
+
+    info!("Replaying packets from {}...", dir);
+    // decoding can take a while with many mutations
+    let report = tokio::task::spawn_blocking(move || {
+        replay_corpus(Path::new(&dir), mutations, &mut rand::thread_rng())
+    }).await.context("Error while waiting on replay task")?.context("Error while replaying packets")?;
+
+    manager.message(format!("Valid: {}, rejected: {}, panics: {}", report.valid, report.rejected, report.panics.len()));
+    for panic in report.panics {
+        manager.error(format!("Decoder panicked on {}", panic));
+    }
+
+    Ok(())
+}
+
 async fn clear_mempool<S: Storage>(manager: &CommandManager<Arc<Blockchain<S>>>, _: ArgumentManager) -> Result<(), CommandError> {
     let lock = manager.get_data().lock()?;
     let blockchain = lock.as_ref().ok_or(CommandError::NoData)?;
@@ -434,6 +645,7 @@ async fn status<S: Storage>(manager: &CommandManager<Arc<Blockchain<S>>>, _: Arg
     let top_block_hash = blockchain.get_top_block_hash().await.context("Error while retrieving top block hash")?;
     let avg_block_time = blockchain.get_average_block_time_for_storage(&storage).await.context("Error while retrieving average block time")?;
     let supply = blockchain.get_supply().await.context("Error while retrieving supply")?;
+    let burned_supply = storage.get_burned_supply_at_topo_height(topoheight).await.context("Error while retrieving burned supply")?;
 
     manager.message(format!("Height: {}", height));
     manager.message(format!("Stable Height: {}", stableheight));
@@ -443,7 +655,8 @@ async fn status<S: Storage>(manager: &CommandManager<Arc<Blockchain<S>>>, _: Arg
     manager.message(format!("Top block hash: {}", top_block_hash));
     manager.message(format!("Average Block Time: {:.2}s", avg_block_time as f64 / MILLIS_PER_SECOND as f64));
     manager.message(format!("Target Block Time: {:.2}s", BLOCK_TIME_MILLIS as f64 / MILLIS_PER_SECOND as f64));
-    manager.message(format!("Current Supply: {} XELIS", format_xelis(supply)));
+    manager.message(format!("Current Supply: {} XELIS", format_xelis(supply.saturating_sub(burned_supply))));
+    manager.message(format!("Burned Supply: {} XELIS", format_xelis(burned_supply)));
     manager.message(format!("Current Block Reward: {} XELIS", format_xelis(get_block_reward(supply))));
 
     manager.message(format!("Tips ({}):", tips.len()));
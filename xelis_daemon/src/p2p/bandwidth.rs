@@ -0,0 +1,197 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex
+};
+use log::{error, warn};
+use xelis_common::{
+    api::daemon::BandwidthPeriod,
+    utils::get_current_time
+};
+
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+// year * 12 + month (0 based) of the UTC day since unix epoch
+// see the days_from_civil / civil_from_days algorithms of Howard Hinnant
+fn get_month_id(days: u64) -> u64 {
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // march based month
+    let mp = (5 * day_of_year + 2) / 153;
+    let month = if mp < 10 { mp + 2 } else { mp - 10 };
+    let year = year_of_era + era * 400 + if month <= 1 { 1 } else { 0 };
+    year * 12 + month
+}
+
+// traffic of the current period, reset when a new one starts
+#[derive(Default)]
+struct Period {
+    id: u64,
+    bytes_in: u64,
+    bytes_out: u64,
+    // only warn once per period
+    warned: bool
+}
+
+impl Period {
+    fn get_total(&self) -> u64 {
+        self.bytes_in + self.bytes_out
+    }
+}
+
+// Traffic of all the peers since the start, and by UTC day and month to enforce the caps
+// counters are kept in memory only, they are reset when the daemon is restarted
+pub struct BandwidthTracker {
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    daily: Mutex<Period>,
+    monthly: Mutex<Period>,
+    // caps in bytes, both directions are counted
+    daily_cap: Option<u64>,
+    monthly_cap: Option<u64>
+}
+
+impl BandwidthTracker {
+    pub fn new(daily_cap: Option<u64>, monthly_cap: Option<u64>) -> Self {
+        Self {
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            daily: Mutex::new(Period::default()),
+            monthly: Mutex::new(Period::default()),
+            daily_cap,
+            monthly_cap
+        }
+    }
+
+    fn get_period_ids() -> (u64, u64) {
+        let day = get_current_time() / SECONDS_PER_DAY;
+        (day, get_month_id(day))
+    }
+
+    fn update_period(name: &str, period: &Mutex<Period>, id: u64, cap: Option<u64>, bytes_in: u64, bytes_out: u64) {
+        let mut period = match period.lock() {
+            Ok(period) => period,
+            Err(e) => {
+                error!("Error while updating {} bandwidth: {}", name, e);
+                return
+            }
+        };
+
+        if period.id != id {
+            *period = Period { id, ..Default::default() };
+        }
+        period.bytes_in += bytes_in;
+        period.bytes_out += bytes_out;
+
+        if let Some(cap) = cap {
+            if !period.warned && period.get_total() >= cap {
+                period.warned = true;
+                warn!("{} bandwidth cap of {} bytes reached, the chain sync is not served to peers anymore", name, cap);
+            }
+        }
+    }
+
+    fn record(&self, bytes_in: u64, bytes_out: u64) {
+        self.bytes_in.fetch_add(bytes_in, Ordering::Relaxed);
+        self.bytes_out.fetch_add(bytes_out, Ordering::Relaxed);
+
+        let (day, month) = Self::get_period_ids();
+        Self::update_period("Daily", &self.daily, day, self.daily_cap, bytes_in, bytes_out);
+        Self::update_period("Monthly", &self.monthly, month, self.monthly_cap, bytes_in, bytes_out);
+    }
+
+    pub fn record_in(&self, bytes: usize) {
+        self.record(bytes as u64, 0);
+    }
+
+    pub fn record_out(&self, bytes: usize) {
+        self.record(0, bytes as u64);
+    }
+
+    pub fn get_bytes_in(&self) -> u64 {
+        self.bytes_in.load(Ordering::Relaxed)
+    }
+
+    pub fn get_bytes_out(&self) -> u64 {
+        self.bytes_out.load(Ordering::Relaxed)
+    }
+
+    // traffic of the period if it is still the current one
+    fn get_period_total(period: &Mutex<Period>, id: u64) -> (u64, u64) {
+        match period.lock() {
+            Ok(period) if period.id == id => (period.bytes_in, period.bytes_out),
+            _ => (0, 0)
+        }
+    }
+
+    fn is_period_exceeded(period: &Mutex<Period>, id: u64, cap: Option<u64>) -> bool {
+        match cap {
+            Some(cap) => {
+                let (bytes_in, bytes_out) = Self::get_period_total(period, id);
+                bytes_in + bytes_out >= cap
+            },
+            None => false
+        }
+    }
+
+    // if a cap is reached, only the blocks and transactions are still relayed
+    pub fn is_cap_exceeded(&self) -> bool {
+        let (day, month) = Self::get_period_ids();
+        Self::is_period_exceeded(&self.daily, day, self.daily_cap)
+            || Self::is_period_exceeded(&self.monthly, month, self.monthly_cap)
+    }
+
+    fn get_period(period: &Mutex<Period>, id: u64, cap: Option<u64>) -> BandwidthPeriod {
+        let (bytes_in, bytes_out) = Self::get_period_total(period, id);
+        BandwidthPeriod {
+            bytes_in,
+            bytes_out,
+            cap,
+            exceeded: cap.is_some_and(|cap| bytes_in + bytes_out >= cap)
+        }
+    }
+
+    pub fn get_daily(&self) -> BandwidthPeriod {
+        let (day, _) = Self::get_period_ids();
+        Self::get_period(&self.daily, day, self.daily_cap)
+    }
+
+    pub fn get_monthly(&self) -> BandwidthPeriod {
+        let (_, month) = Self::get_period_ids();
+        Self::get_period(&self.monthly, month, self.monthly_cap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month_id() {
+        // 1970-01-01
+        assert_eq!(get_month_id(0), 1970 * 12);
+        // 2024-02-29 and 2024-03-01
+        assert_eq!(get_month_id(19782), 2024 * 12 + 1);
+        assert_eq!(get_month_id(19783), 2024 * 12 + 2);
+        // 2023-12-31 and 2024-01-01
+        assert_eq!(get_month_id(19722), 2023 * 12 + 11);
+        assert_eq!(get_month_id(19723), 2024 * 12);
+    }
+
+    #[test]
+    fn test_cap() {
+        let tracker = BandwidthTracker::new(Some(1000), None);
+        tracker.record_in(600);
+        assert!(!tracker.is_cap_exceeded());
+        tracker.record_out(400);
+        assert!(tracker.is_cap_exceeded());
+
+        let daily = tracker.get_daily();
+        assert_eq!((daily.bytes_in, daily.bytes_out), (600, 400));
+        assert!(daily.exceeded);
+        assert!(!tracker.get_monthly().exceeded);
+        assert_eq!(tracker.get_bytes_in() + tracker.get_bytes_out(), 1000);
+    }
+}
@@ -1,16 +1,11 @@
-use super::error::P2pError;
-use super::packet::Packet;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use super::{bandwidth::BandwidthTracker, error::P2pError, transport::{TrafficClass, TransportReader, TransportStream, TransportWriter}};
+use super::packet::{Packet, parse_packet};
+use std::sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc};
 use std::net::SocketAddr;
 use std::time::Duration;
 use human_bytes::human_bytes;
 use humantime::format_duration;
-use tokio::net::TcpStream;
-use tokio::net::tcp::{OwnedWriteHalf, OwnedReadHalf};
-use xelis_common::{
-    utils::get_current_time,
-    serializer::{Reader, Serializer},
-};
+use xelis_common::utils::get_current_time;
 use std::fmt::{Display, Error, Formatter};
 use tokio::sync::{mpsc, Mutex};
 use tokio::io::{AsyncWriteExt, AsyncReadExt};
@@ -34,33 +29,58 @@ pub enum State {
     Success // handshake is valid
 }
 
+// stream dedicated to the transactions, with its own queue of packets to send
+struct TransactionsStream {
+    write: Mutex<TransportWriter>,
+    read: Mutex<TransportReader>,
+    tx: Mutex<Tx>,
+    rx: Mutex<Rx>
+}
+
 pub struct Connection {
     state: State,
-    write: Mutex<OwnedWriteHalf>, // write to stream
-    read: Mutex<OwnedReadHalf>, // read from stream
-    addr: SocketAddr, // TCP Address
+    write: Mutex<TransportWriter>, // write to stream
+    read: Mutex<TransportReader>, // read from stream
+    addr: SocketAddr, // address of the peer
     tx: Mutex<Tx>, // Tx to send bytes
     rx: Mutex<Rx>, // Rx to read bytes to send
+    priority_tx: Mutex<Tx>, // Tx to send bytes before the ones already queued
+    priority_rx: Mutex<Rx>, // Rx to read bytes to send in priority
+    transactions: Option<TransactionsStream>, // set if the transport opened a stream for the transactions
     bytes_in: AtomicUsize, // total bytes read
     bytes_out: AtomicUsize, // total bytes sent
+    bandwidth: Arc<BandwidthTracker>, // traffic of all the connections
     connected_on: u64,
     closed: AtomicBool, // if Connection#close() is called, close is set to true
 }
 
 impl Connection {
-    pub fn new(stream: TcpStream, addr: SocketAddr) -> Self {
+    pub fn new(stream: TransportStream, addr: SocketAddr, bandwidth: Arc<BandwidthTracker>) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
-        let (read, write) = stream.into_split();
+        let (priority_tx, priority_rx) = mpsc::unbounded_channel();
+        let transactions = stream.transactions.map(|stream| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            TransactionsStream {
+                write: Mutex::new(stream.writer),
+                read: Mutex::new(stream.reader),
+                tx: Mutex::new(tx),
+                rx: Mutex::new(rx)
+            }
+        });
         Self {
             state: State::Pending,
-            write: Mutex::new(write),
-            read: Mutex::new(read),
+            write: Mutex::new(stream.writer),
+            read: Mutex::new(stream.reader),
             addr,
             tx: Mutex::new(tx),
             rx: Mutex::new(rx),
+            priority_tx: Mutex::new(priority_tx),
+            priority_rx: Mutex::new(priority_rx),
+            transactions,
             connected_on: get_current_time(),
             bytes_in: AtomicUsize::new(0),
             bytes_out: AtomicUsize::new(0),
+            bandwidth,
             closed: AtomicBool::new(false)
         }
     }
@@ -73,16 +93,60 @@ impl Connection {
         &self.rx
     }
 
+    pub fn get_priority_tx(&self) -> &Mutex<Tx> {
+        &self.priority_tx
+    }
+
+    pub fn get_priority_rx(&self) -> &Mutex<Rx> {
+        &self.priority_rx
+    }
+
+    pub fn has_transactions_stream(&self) -> bool {
+        self.transactions.is_some()
+    }
+
+    pub fn get_transactions_tx(&self) -> Option<&Mutex<Tx>> {
+        self.transactions.as_ref().map(|stream| &stream.tx)
+    }
+
+    pub fn get_transactions_rx(&self) -> Option<&Mutex<Rx>> {
+        self.transactions.as_ref().map(|stream| &stream.rx)
+    }
+
+    // the chain stream is used for all the traffic if the transport has only one stream
+    fn get_write(&self, class: TrafficClass) -> &Mutex<TransportWriter> {
+        match (class, &self.transactions) {
+            (TrafficClass::Transactions, Some(stream)) => &stream.write,
+            _ => &self.write
+        }
+    }
+
+    fn get_read(&self, class: TrafficClass) -> &Mutex<TransportReader> {
+        match (class, &self.transactions) {
+            (TrafficClass::Transactions, Some(stream)) => &stream.read,
+            _ => &self.read
+        }
+    }
+
     pub async fn send_bytes(&self, buf: &[u8]) -> P2pResult<()> {
-        let mut stream = self.write.lock().await;
+        self.send_bytes_on(TrafficClass::Chain, buf).await
+    }
+
+    pub async fn send_bytes_on(&self, class: TrafficClass, buf: &[u8]) -> P2pResult<()> {
+        let mut stream = self.get_write(class).lock().await;
         stream.write_all(buf).await?;
         self.bytes_out.fetch_add(buf.len(), Ordering::Relaxed);
+        self.bandwidth.record_out(buf.len());
         stream.flush().await?;
         Ok(())
     }
 
     pub async fn read_packet_bytes(&self, buf: &mut [u8], max_size: u32) -> P2pResult<Vec<u8>> {
-        let mut stream = self.read.lock().await;
+        self.read_packet_bytes_on(TrafficClass::Chain, buf, max_size).await
+    }
+
+    pub async fn read_packet_bytes_on(&self, class: TrafficClass, buf: &mut [u8], max_size: u32) -> P2pResult<Vec<u8>> {
+        let mut stream = self.get_read(class).lock().await;
         let size = self.read_packet_size(&mut stream, buf).await?;
         if size == 0 || size > max_size {
             warn!("Received invalid packet size: {} bytes (max: {} bytes) from peer {}", size, max_size, self.get_address());
@@ -95,13 +159,10 @@ impl Connection {
     }
 
     pub async fn read_packet_from_bytes(&self, bytes: &[u8]) -> P2pResult<Packet<'_>> {
-        let mut reader = Reader::new(&bytes);
-        let packet = Packet::read(&mut reader)?;
-        if reader.total_read() != bytes.len() {
-            warn!("read {:?} only {}/{} on bytes available from {}", packet, reader.total_read(), bytes.len(), self);
-            return Err(P2pError::InvalidPacketNotFullRead)
-        }
-        Ok(packet)
+        parse_packet(bytes).map_err(|e| {
+            warn!("Error while reading packet of {} bytes from {}: {}", bytes.len(), self, e);
+            e
+        })
     }
 
     pub async fn read_packet(&self, buf: &mut [u8], max_size: u32) -> P2pResult<Packet<'_>> {
@@ -109,7 +170,7 @@ impl Connection {
         self.read_packet_from_bytes(&bytes).await
     }
 
-    async fn read_packet_size(&self, stream: &mut OwnedReadHalf, buf: &mut [u8]) -> P2pResult<u32> {
+    async fn read_packet_size(&self, stream: &mut TransportReader, buf: &mut [u8]) -> P2pResult<u32> {
         let read = self.read_bytes_from_stream(stream, &mut buf[0..4]).await?;
         if read != 4 {
             warn!("Received invalid packet size: expected to read 4 bytes but read only {} bytes from peer {}", read, self.get_address());
@@ -120,7 +181,7 @@ impl Connection {
         Ok(size)
     }
 
-    async fn read_all_bytes(&self, stream: &mut OwnedReadHalf, buf: &mut [u8], mut left: u32) -> P2pResult<Vec<u8>> {
+    async fn read_all_bytes(&self, stream: &mut TransportReader, buf: &mut [u8], mut left: u32) -> P2pResult<Vec<u8>> {
         let buf_size = buf.len() as u32;
         let mut bytes = Vec::new();
         while left > 0 {
@@ -139,7 +200,7 @@ impl Connection {
     // this function will wait until something is sent to the socket if it's in blocking mode
     // this return the size of data read & set in the buffer.
     // used to only lock one time the stream and read on it
-    async fn read_bytes_from_stream(&self, stream: &mut OwnedReadHalf, buf: &mut [u8]) -> P2pResult<usize> {
+    async fn read_bytes_from_stream(&self, stream: &mut TransportReader, buf: &mut [u8]) -> P2pResult<usize> {
         let result = stream.read(buf).await?;
         match result {
             0 => {
@@ -147,6 +208,7 @@ impl Connection {
             }
             n => {
                 self.bytes_in.fetch_add(n, Ordering::Relaxed);
+                self.bandwidth.record_in(n);
                 Ok(n)
             }
         }
@@ -156,6 +218,11 @@ impl Connection {
         self.closed.store(true, Ordering::Relaxed);
         let tx = self.get_tx().lock().await;
         tx.send(ConnectionMessage::Exit)?; // send a exit message to stop the current lock of stream
+        if let Some(transactions) = &self.transactions {
+            // its write task may already be stopped
+            let _ = transactions.tx.lock().await.send(ConnectionMessage::Exit);
+            transactions.write.lock().await.shutdown().await?;
+        }
         let mut stream = self.write.lock().await;
         stream.shutdown().await?; // sometimes the peer is not removed on other peer side
         Ok(())
@@ -0,0 +1,236 @@
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering}
+};
+use log::{debug, error, info};
+use rand::{Rng, RngCore};
+use xelis_common::crypto::hash::hash;
+use crate::config::P2P_CORPUS_MAX_FILES;
+use super::{
+    error::P2pError,
+    packet::{parse_packet, PACKET_NAMES}
+};
+
+// Record the raw packets received from peers, valid or not, one file per unique packet
+// the files can be replayed later against the decoder, or used as seeds to fuzz it
+pub struct PacketRecorder {
+    dir: PathBuf,
+    // files written since the start, to not fill the disk
+    recorded: AtomicUsize
+}
+
+impl PacketRecorder {
+    pub fn new(dir: String) -> Result<Self, P2pError> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            recorded: AtomicUsize::new(0)
+        })
+    }
+
+    // bytes contain the packet id followed by its payload
+    pub fn record(&self, bytes: &[u8]) {
+        if self.recorded.load(Ordering::Relaxed) >= P2P_CORPUS_MAX_FILES {
+            return
+        }
+
+        let name = bytes.first()
+            .and_then(|id| PACKET_NAMES.get(*id as usize))
+            .copied()
+            .unwrap_or("unknown");
+        // same packet is only saved once
+        let path = self.dir.join(format!("{}-{}.bin", name, hash(bytes)));
+        if path.exists() {
+            return
+        }
+
+        match fs::write(&path, bytes) {
+            Ok(()) => {
+                self.recorded.fetch_add(1, Ordering::Relaxed);
+            },
+            Err(e) => error!("Error while recording packet to {}: {}", path.display(), e)
+        };
+    }
+}
+
+// result of the decoding of a corpus
+#[derive(Default)]
+pub struct ReplayReport {
+    // packets decoded successfully
+    pub valid: usize,
+    // packets rejected with an error
+    pub rejected: usize,
+    // packets making the decoder panic, with the file they come from
+    pub panics: Vec<String>
+}
+
+// returns if the packet is valid, none if the decoder panicked
+fn try_decode(bytes: &[u8]) -> Option<bool> {
+    panic::catch_unwind(AssertUnwindSafe(|| parse_packet(bytes).is_ok())).ok()
+}
+
+// random change of a packet: flip a bit, overwrite, insert or remove bytes
+// lengths and counts are mostly small integers, so they are also replaced by limit values
+pub fn mutate<R: RngCore>(bytes: &mut Vec<u8>, rng: &mut R) {
+    if bytes.is_empty() {
+        bytes.push(rng.gen());
+        return
+    }
+
+    let index = rng.gen_range(0..bytes.len());
+    match rng.gen_range(0..6) {
+        0 => bytes[index] ^= 1 << rng.gen_range(0..8),
+        1 => bytes[index] = rng.gen(),
+        2 => bytes[index] = [0, 1, 0x7F, 0x80, 0xFF][rng.gen_range(0..5)],
+        3 => {
+            let count = rng.gen_range(1..=16);
+            for _ in 0..count {
+                bytes.insert(index, rng.gen());
+            }
+        },
+        4 => {
+            let end = (index + rng.gen_range(1..=16)).min(bytes.len());
+            bytes.drain(index..end);
+        },
+        _ => bytes.truncate(index)
+    };
+}
+
+// decode every file of the corpus directory
+// each packet is also mutated N times to find the inputs close to real packets making the decoder panic
+pub fn replay_corpus<R: RngCore>(dir: &Path, mutations: usize, rng: &mut R) -> Result<ReplayReport, P2pError> {
+    let mut report = ReplayReport::default();
+    // panics are reported at the end, don't print each backtrace
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let bytes = fs::read(&path)?;
+        let file = path.display().to_string();
+        match try_decode(&bytes) {
+            Some(true) => report.valid += 1,
+            Some(false) => report.rejected += 1,
+            None => report.panics.push(file.clone())
+        };
+
+        for i in 0..mutations {
+            let mut mutated = bytes.clone();
+            // several changes at once to go further from the original packet
+            for _ in 0..rng.gen_range(1..=4) {
+                mutate(&mut mutated, rng);
+            }
+
+            match try_decode(&mutated) {
+                Some(true) => report.valid += 1,
+                Some(false) => report.rejected += 1,
+                None => {
+                    debug!("Mutation #{} of {} panicked: {}", i, file, hex::encode(&mutated));
+                    report.panics.push(format!("{} (mutation: {})", file, hex::encode(&mutated)));
+                }
+            };
+        }
+    }
+
+    panic::set_hook(hook);
+    info!("Corpus replayed: {} valid, {} rejected, {} panics", report.valid, report.rejected, report.panics.len());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, net::SocketAddr};
+    use rand::{rngs::StdRng, SeedableRng};
+    use xelis_common::{crypto::{hash::Hash, key::KeyPair}, network::Network, serializer::Serializer};
+    use crate::config::{NETWORK_ID, P2P_PROTOCOL_VERSION, P2P_OBJECTS_BATCH_VERSION, P2P_KEEP_ALIVE_VERSION};
+    use crate::p2p::packet::{
+        chain::{BlockId, ChainRequest},
+        handshake::{Handshake, NodeServices},
+        keep_alive::KeepAlive,
+        object::{ObjectRequest, ObjectsRequest},
+        peer_disconnected::PacketPeerDisconnected,
+        ping::Ping,
+        Packet,
+        PacketWrapper,
+        get_packet_protocol_version
+    };
+    use super::*;
+
+    fn _create_seeds() -> Vec<Vec<u8>> {
+        let addr: SocketAddr = "127.0.0.1:2125".parse().unwrap();
+        let ping = Ping::new(Cow::Owned(Hash::zero()), 10, 10, Some(5), 1000, vec![addr]);
+        vec![
+            Packet::Ping(Cow::Borrowed(&ping)).to_bytes(),
+            Packet::TransactionPropagation(PacketWrapper::new(Cow::Owned(Hash::max()), Cow::Borrowed(&ping))).to_bytes(),
+            Packet::ChainRequest(PacketWrapper::new(Cow::Owned(ChainRequest::new(vec![BlockId::new(Hash::zero(), 0)], 512)), Cow::Borrowed(&ping))).to_bytes(),
+            Packet::ObjectRequest(Cow::Owned(ObjectRequest::Block(Hash::zero()))).to_bytes(),
+            Packet::PeerDisconnected(PacketPeerDisconnected::new(addr)).to_bytes(),
+            Packet::KeepAliveRequest(KeepAlive::new(1)).to_bytes()
+        ].into_iter()
+            // the size prefix is not part of the bytes decoded
+            .map(|bytes| bytes[4..].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_seeds_are_valid() {
+        for seed in _create_seeds() {
+            assert!(parse_packet(&seed).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_max_packet_size() {
+        let mut bytes = _create_seeds().remove(0);
+        bytes.resize(4096, 0);
+        assert!(matches!(parse_packet(&bytes), Err(P2pError::InvalidPacketSize)));
+        assert!(matches!(parse_packet(&[]), Err(P2pError::InvalidPacketSize)));
+        assert!(matches!(parse_packet(&[255]), Err(P2pError::InvalidPacket)));
+    }
+
+    #[test]
+    fn test_fuzz_decoder() {
+        let dir = std::env::temp_dir().join(format!("xelis_corpus_{}", std::process::id()));
+        let recorder = PacketRecorder::new(dir.to_string_lossy().into_owned()).unwrap();
+        let seeds = _create_seeds();
+        for seed in &seeds {
+            recorder.record(seed);
+            // recorded only once
+            recorder.record(seed);
+        }
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), seeds.len());
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let report = replay_corpus(&dir, 2000, &mut rng).unwrap();
+        assert!(report.panics.is_empty(), "{:?}", report.panics);
+        assert!(report.valid >= seeds.len());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_packet_protocol_version() {
+        let keypair = KeyPair::new();
+        let handshake = Handshake::new("1.0.0".to_owned(), P2P_PROTOCOL_VERSION, Network::Dev, None, NETWORK_ID, 1, 2125, 0, 0, 0, None, Hash::zero(), Hash::zero(), 0, NodeServices::default(), None, &keypair);
+        let bytes = Packet::Handshake(Cow::Borrowed(&handshake)).to_bytes();
+        let Ok(Packet::Handshake(decoded)) = parse_packet(&bytes[4..]) else {
+            panic!("invalid handshake")
+        };
+        assert_eq!(decoded.get_protocol_version(), P2P_PROTOCOL_VERSION);
+        assert!(decoded.verify_signature());
+
+        // packets of the first protocol version can be exchanged with any peer
+        let request = Packet::ObjectRequest(Cow::Owned(ObjectRequest::Block(Hash::zero())));
+        assert_eq!(get_packet_protocol_version(request.get_id()), 0);
+        let request = Packet::ObjectsRequest(Cow::Owned(ObjectsRequest::new(0, vec![ObjectRequest::Block(Hash::zero())])));
+        assert_eq!(get_packet_protocol_version(request.get_id()), P2P_OBJECTS_BATCH_VERSION);
+        let keep_alive = Packet::KeepAliveResponse(KeepAlive::new(1));
+        assert_eq!(get_packet_protocol_version(keep_alive.get_id()), P2P_KEEP_ALIVE_VERSION);
+    }
+}
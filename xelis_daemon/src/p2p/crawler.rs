@@ -0,0 +1,182 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    sync::atomic::{AtomicU64, Ordering}
+};
+use tokio::sync::RwLock;
+use log::{debug, trace};
+use xelis_common::{
+    api::daemon::{CrawledNodeEntry, GetNetworkTopologyResult},
+    utils::get_current_time
+};
+use crate::config::P2P_CRAWLER_NODE_EXPIRATION;
+use super::{packet::handshake::Handshake, peer::Peer, error::P2pError};
+
+// node discovered while crawling the network
+struct CrawledNode {
+    peer_id: Option<u64>,
+    tag: Option<String>,
+    version: Option<String>,
+    topoheight: Option<u64>,
+    height: Option<u64>,
+    reachable: bool,
+    connected: bool,
+    last_seen: u64,
+    last_probe: u64,
+    peers: HashSet<SocketAddr>
+}
+
+impl CrawledNode {
+    fn new(last_seen: u64) -> Self {
+        Self {
+            peer_id: None,
+            tag: None,
+            version: None,
+            topoheight: None,
+            height: None,
+            reachable: false,
+            connected: false,
+            last_seen,
+            last_probe: 0,
+            peers: HashSet::new()
+        }
+    }
+}
+
+// Walk the peer exchange data of our peers to estimate the state of the network
+// every address announced is reached once per crawl with a handshake to know its version
+#[derive(Default)]
+pub struct NetworkCrawler {
+    nodes: RwLock<HashMap<SocketAddr, CrawledNode>>,
+    last_crawl: AtomicU64
+}
+
+impl NetworkCrawler {
+    // our peers may have changed since the last crawl
+    pub async fn start_crawl(&self) {
+        let mut nodes = self.nodes.write().await;
+        for node in nodes.values_mut() {
+            node.connected = false;
+        }
+    }
+
+    // save one of our peers and all the addresses it announced to us
+    pub async fn add_connected_peer(&self, peer: &Peer) {
+        let now = get_current_time();
+        let peers: HashSet<SocketAddr> = peer.get_peers().lock().await.keys().copied().collect();
+        let mut nodes = self.nodes.write().await;
+        for addr in &peers {
+            nodes.entry(*addr).or_insert_with(|| CrawledNode::new(now)).last_seen = now;
+        }
+
+        let node = nodes.entry(*peer.get_outgoing_address()).or_insert_with(|| CrawledNode::new(now));
+        node.peer_id = Some(peer.get_id());
+        node.tag = peer.get_node_tag().clone();
+        node.version = Some(peer.get_version().clone());
+        node.topoheight = Some(peer.get_topoheight());
+        node.height = Some(peer.get_height());
+        // an incoming peer may still not be reachable from outside
+        node.reachable |= peer.is_out();
+        node.connected = true;
+        node.last_seen = now;
+        node.peers = peers;
+    }
+
+    // addresses not connected to us, the oldest reached first
+    pub async fn get_addresses_to_probe(&self, limit: usize) -> Vec<SocketAddr> {
+        let nodes = self.nodes.read().await;
+        let mut addresses: Vec<(&SocketAddr, u64)> = nodes.iter()
+            .filter(|(_, node)| !node.connected)
+            .map(|(addr, node)| (addr, node.last_probe))
+            .collect();
+        addresses.sort_by_key(|(_, last_probe)| *last_probe);
+        addresses.into_iter().take(limit).map(|(addr, _)| *addr).collect()
+    }
+
+    pub async fn set_probe_result(&self, addr: SocketAddr, result: Result<Handshake, P2pError>) {
+        let now = get_current_time();
+        let mut nodes = self.nodes.write().await;
+        let node = nodes.entry(addr).or_insert_with(|| CrawledNode::new(now));
+        node.last_probe = now;
+        match result {
+            Ok(handshake) => {
+                trace!("Crawler reached {} running version {}", addr, handshake.get_version());
+                node.peer_id = Some(handshake.get_peer_id());
+                node.tag = handshake.get_node_tag().clone();
+                node.version = Some(handshake.get_version().clone());
+                node.topoheight = Some(handshake.get_topoheight());
+                node.height = Some(handshake.get_block_height());
+                node.reachable = true;
+                node.last_seen = now;
+            },
+            Err(e) => {
+                trace!("Crawler couldn't reach {}: {}", addr, e);
+                node.reachable = false;
+            }
+        }
+    }
+
+    // forget the nodes not seen recently
+    pub async fn finish_crawl(&self) {
+        let now = get_current_time();
+        let mut nodes = self.nodes.write().await;
+        nodes.retain(|_, node| node.last_seen + P2P_CRAWLER_NODE_EXPIRATION > now);
+        debug!("Network crawl finished with {} nodes known", nodes.len());
+        self.last_crawl.store(now, Ordering::SeqCst);
+    }
+
+    pub fn get_last_crawl(&self) -> u64 {
+        self.last_crawl.load(Ordering::SeqCst)
+    }
+
+    pub async fn get_topology(&self) -> GetNetworkTopologyResult<'static> {
+        let nodes = self.nodes.read().await;
+        let mut versions = HashMap::new();
+        let mut subnets = HashMap::new();
+        let mut entries = Vec::with_capacity(nodes.len());
+        for (addr, node) in nodes.iter() {
+            if let Some(version) = node.version.as_ref().filter(|_| node.reachable || node.connected) {
+                *versions.entry(version.clone()).or_insert(0) += 1;
+            }
+            *subnets.entry(get_subnet(&addr.ip())).or_insert(0) += 1;
+
+            entries.push(CrawledNodeEntry {
+                addr: *addr,
+                peer_id: node.peer_id,
+                tag: Cow::Owned(node.tag.clone()),
+                version: Cow::Owned(node.version.clone()),
+                topoheight: node.topoheight,
+                height: node.height,
+                reachable: node.reachable,
+                connected: node.connected,
+                last_seen: node.last_seen,
+                last_probe: node.last_probe,
+                peers: Cow::Owned(node.peers.clone())
+            });
+        }
+
+        GetNetworkTopologyResult {
+            last_crawl: self.get_last_crawl(),
+            nodes_count: nodes.len(),
+            reachable_count: nodes.values().filter(|node| node.reachable).count(),
+            versions,
+            subnets,
+            nodes: entries
+        }
+    }
+}
+
+// nodes in the same subnet are most likely hosted at the same provider or in the same region
+fn get_subnet(ip: &IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            format!("{}.{}.0.0/16", octets[0], octets[1])
+        },
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            format!("{:x}:{:x}::/32", segments[0], segments[1])
+        }
+    }
+}
@@ -57,6 +57,14 @@ pub enum P2pError {
     PeerIdAlreadyUsed(u64),
     #[error("Peer already connected: {}", _0)]
     PeerAlreadyConnected(String),
+    #[error("Maximum peers reached")]
+    MaxPeersReached,
+    #[error("Unknown transport: {}", _0)]
+    UnknownTransport(String),
+    #[error("Transport {} doesn't support the keys of the peers", _0)]
+    PeerKeyNotSupported(&'static str),
+    #[error("Invalid peer key: {}", _0)]
+    InvalidPeerKey(String),
     #[error(transparent)]
     ErrorStd(#[from] IOError),
     #[error("Poison Error: {}", _0)]
@@ -71,6 +79,8 @@ pub enum P2pError {
     ParseAddressError(#[from] AddrParseError),
     #[error("Invalid packet ID")]
     InvalidPacket,
+    #[error("Packet ID {} is not supported by the protocol version of the peer", _0)]
+    UnsupportedPacket(u8),
     #[error("Peer topoheight is higher than our")]
     InvalidRequestedTopoheight,
     #[error("Packet size exceed limit")]
@@ -97,6 +107,10 @@ pub enum P2pError {
     InvalidObjectResponse(Hash),
     #[error("Invalid object response type for request")]
     InvalidObjectResponseType,
+    #[error("Peer sent us an empty objects response")]
+    EmptyObjectsResponse,
+    #[error("Invalid keep alive sequence, expected at most {}, got {}", _0, _1)]
+    InvalidKeepAliveSequence(u64, u64),
     #[error(transparent)]
     ObjectRequestError(#[from] RecvError),
     #[error("Expected a block type")]
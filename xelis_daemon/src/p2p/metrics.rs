@@ -0,0 +1,49 @@
+use std::{borrow::Cow, sync::atomic::{AtomicU64, Ordering}, time::Duration};
+use xelis_common::api::daemon::PacketMetricsEntry;
+use super::packet::PACKET_NAMES;
+
+// counters of the packets received for one packet type
+// durations are in microseconds
+#[derive(Default)]
+struct PacketCounters {
+    count: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+    total_time: AtomicU64,
+    max_time: AtomicU64
+}
+
+// metrics of the packets received from all peers, indexed by packet id
+// only atomics are used so it can be updated from each peer task without locking
+#[derive(Default)]
+pub struct PacketMetrics {
+    counters: [PacketCounters; PACKET_NAMES.len()]
+}
+
+impl PacketMetrics {
+    pub fn record(&self, id: u8, size: usize, duration: Duration, failed: bool) {
+        let Some(counters) = self.counters.get(id as usize) else {
+            return
+        };
+
+        let micros = duration.as_micros() as u64;
+        counters.count.fetch_add(1, Ordering::Relaxed);
+        counters.bytes.fetch_add(size as u64, Ordering::Relaxed);
+        counters.total_time.fetch_add(micros, Ordering::Relaxed);
+        counters.max_time.fetch_max(micros, Ordering::Relaxed);
+        if failed {
+            counters.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get_entries(&self) -> Vec<PacketMetricsEntry<'static>> {
+        PACKET_NAMES.iter().zip(&self.counters).map(|(name, counters)| PacketMetricsEntry {
+            packet: Cow::Borrowed(*name),
+            count: counters.count.load(Ordering::Relaxed),
+            bytes: counters.bytes.load(Ordering::Relaxed),
+            errors: counters.errors.load(Ordering::Relaxed),
+            total_time: counters.total_time.load(Ordering::Relaxed),
+            max_time: counters.max_time.load(Ordering::Relaxed)
+        }).collect()
+    }
+}
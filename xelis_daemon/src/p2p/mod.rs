@@ -4,18 +4,28 @@ pub mod error;
 pub mod packet;
 pub mod peer_list;
 pub mod chain_validator;
+pub mod crawler;
+pub mod corpus;
+pub mod bandwidth;
+pub mod transport;
+#[cfg(feature = "quic")]
+pub mod quic;
+pub mod sync_cursor;
 mod tracker;
+mod sync_progress;
+mod metrics;
 
 use indexmap::IndexSet;
 use lru::LruCache;
 use xelis_common::{
     config::VERSION,
     serializer::Serializer,
-    crypto::hash::{Hashable, Hash},
+    crypto::{hash::{Hashable, Hash}, key::PublicKey},
     block::{BlockHeader, Block, Difficulty},
     utils::get_current_time,
     immutable::Immutable,
-    api::daemon::{NotifyEvent, PeerPeerDisconnectedEvent, Direction}
+    transaction::Transaction,
+    api::daemon::{DaemonEvent, NotifyEvent, PeerPeerDisconnectedEvent, Direction, GetSyncStatusResult, GetP2pMetricsResult, PeerMetricsEntry, GetBandwidthResult, PeerBandwidthEntry}
 };
 use crate::{
     core::{
@@ -35,32 +45,41 @@ use crate::{
             chain::CommonPoint
         },
         tracker::ResponseBlocker,
+        sync_progress::{SyncProgress, get_progress},
+        sync_cursor::SyncCursor,
+        metrics::PacketMetrics,
         connection::ConnectionMessage,
     },
     config::{
         NETWORK_ID, SEED_NODES, MAX_BLOCK_SIZE, CHAIN_SYNC_DELAY, P2P_PING_DELAY, CHAIN_SYNC_REQUEST_MAX_BLOCKS,
-        P2P_PING_PEER_LIST_DELAY, P2P_PING_PEER_LIST_LIMIT, STABLE_LIMIT, PEER_FAIL_LIMIT,
-        CHAIN_SYNC_TOP_BLOCKS, GENESIS_BLOCK_HASH, PRUNE_SAFETY_LIMIT, P2P_EXTEND_PEERLIST_DELAY,
-        TIPS_LIMIT, PEER_TIMEOUT_INIT_CONNECTION, CHAIN_SYNC_DEFAULT_RESPONSE_BLOCKS
+        P2P_PING_PEER_LIST_DELAY, P2P_PING_PEER_LIST_LIMIT, STABLE_LIMIT,
+        CHAIN_SYNC_TOP_BLOCKS, PRUNE_SAFETY_LIMIT, P2P_EXTEND_PEERLIST_DELAY,
+        TIPS_LIMIT, PEER_TIMEOUT_INIT_CONNECTION, CHAIN_SYNC_DEFAULT_RESPONSE_BLOCKS, P2P_OBJECTS_RESPONSE_MAX_SIZE,
+        P2P_KEEP_ALIVE_DELAY, P2P_KEEP_ALIVE_MAX_MISSED, P2P_CRAWLER_DELAY, P2P_CRAWLER_MAX_PROBES,
+        PEER_TRUSTED_MAX_TIME_DRIFT, CHAIN_SYNC_PROGRESS_LOG_DELAY, P2P_SLOW_PACKET_THRESHOLD_MS,
+        P2P_PROTOCOL_VERSION, P2P_KEEP_ALIVE_VERSION, P2P_BURNED_SUPPLY_VERSION
     },
     rpc::rpc::get_peer_entry
 };
 use self::{
     packet::{
         chain::{BlockId, ChainRequest, ChainResponse},
-        object::{ObjectRequest, ObjectResponse, OwnedObjectResponse},
-        handshake::Handshake,
+        object::{ObjectRequest, ObjectResponse, OwnedObjectResponse, ObjectsResponse, MAX_OBJECTS_PER_REQUEST},
+        handshake::{Handshake, NodeServices},
         ping::Ping,
-        {Packet, PacketWrapper}
+        {Packet, PacketWrapper, HANDSHAKE_MAX_SIZE, get_packet_protocol_version}
     },
+    corpus::PacketRecorder,
+    bandwidth::BandwidthTracker,
+    transport::{TrafficClass, Transports, TransportStream},
     peer::Peer,
     tracker::{ObjectTracker, SharedObjectTracker},
+    crawler::NetworkCrawler,
     peer_list::{SharedPeerList, PeerList},
     connection::{State, Connection},
     error::P2pError
 };
 use tokio::{
-    net::{TcpListener, TcpStream},
     sync::{mpsc::{self, UnboundedSender, UnboundedReceiver, Sender, Receiver, unbounded_channel}, Mutex},
     select,
     task::JoinHandle,
@@ -70,14 +89,15 @@ use tokio::{
 use log::{info, warn, error, debug, trace};
 use std::{
     borrow::Cow,
+    cmp::Reverse,
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering}
     },
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     convert::TryInto,
     net::SocketAddr,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use bytes::Bytes;
 use rand::Rng;
@@ -99,14 +119,23 @@ pub struct P2pServer<S: Storage> {
     blockchain: Arc<Blockchain<S>>, // reference to the chain to add blocks/txs
     connections_sender: UnboundedSender<MessageChannel>, // this sender allows to create a queue system in one task only
     syncing_peer: Mutex<Option<Arc<Peer>>>, // used to check if we are already syncing with one peer or not
+    sync_progress: Mutex<Option<SyncProgress>>, // progress of the current sync session, shared by all the peers used
+    packet_metrics: PacketMetrics, // counters and handling durations of the packets received, by packet type
     object_tracker: SharedObjectTracker, // used to requests objects to peers and avoid requesting the same object to multiple peers
     is_running: AtomicBool, // used to check if the server is running or not in tasks
-    blocks_propagation_queue: Mutex<LruCache<Hash, ()>>, // Synced cache to prevent concurrent tasks adding the block
-    blocks_processor: Sender<(Arc<Peer>, BlockHeader, Hash)> // Sender for the blocks processing task to have a ordered queue
+    blocks_propagation_queue: Mutex<LruCache<Hash, bool>>, // Synced cache to prevent concurrent tasks adding the block, value is true if it comes from a priority node
+    blocks_processor: Sender<(Arc<Peer>, BlockHeader, Hash)>, // Sender for the blocks processing task to have a ordered queue
+    priority_blocks_processor: Sender<(Arc<Peer>, BlockHeader, Hash)>, // Sender for the blocks propagated by priority nodes, processed before the others
+    crawler: Option<Arc<NetworkCrawler>>, // used to estimate the network topology, only if enabled
+    trusted_node_keys: HashSet<PublicKey>, // node keys of the peers having reserved slots, relaxed limits and priority relay
+    packet_recorder: Option<PacketRecorder>, // save the packets received to build a corpus for the decoder, only if enabled
+    bandwidth: Arc<BandwidthTracker>, // traffic of all the peers, used to enforce the daily and monthly caps
+    transports: Transports, // carry the packets with the peers, TCP by default
+    tx_relay: bool // relay the transactions of the network, advertised as a service in handshake
 }
 
 impl<S: Storage> P2pServer<S> {
-    pub fn new(tag: Option<String>, max_peers: usize, bind_address: String, blockchain: Arc<Blockchain<S>>, use_peerlist: bool, exclusive_nodes: Vec<SocketAddr>) -> Result<Arc<Self>, P2pError> {
+    pub fn new(tag: Option<String>, max_peers: usize, bind_address: String, blockchain: Arc<Blockchain<S>>, use_peerlist: bool, exclusive_nodes: Vec<SocketAddr>, enable_crawler: bool, trusted_node_keys: HashSet<PublicKey>, record_packets: Option<String>, bandwidth: BandwidthTracker, transports: Transports, tx_relay: bool) -> Result<Arc<Self>, P2pError> {
         if let Some(tag) = &tag {
             debug_assert!(tag.len() > 0 && tag.len() <= 16);
         }
@@ -118,11 +147,20 @@ impl<S: Storage> P2pServer<S> {
         // create mspc channel for connections to peers
         let (connections_sender, connections_receiver) = mpsc::unbounded_channel();
         let (blocks_processor, blocks_processor_receiver) = mpsc::channel(TIPS_LIMIT * STABLE_LIMIT as usize);
+        let (priority_blocks_processor, priority_blocks_processor_receiver) = mpsc::channel(TIPS_LIMIT * STABLE_LIMIT as usize);
         let object_tracker = ObjectTracker::new(blockchain.clone());
 
         let (sender, receiver) = unbounded_channel::<Arc<Peer>>(); 
         let peer_list = PeerList::new(max_peers, format!("peerlist-{}.json", blockchain.get_network().to_string().to_lowercase()), Some(sender));
 
+        let packet_recorder = match record_packets {
+            Some(dir) => {
+                info!("Recording packets received in {}", dir);
+                Some(PacketRecorder::new(dir)?)
+            },
+            None => None
+        };
+
         let server = Self {
             peer_id,
             tag,
@@ -132,10 +170,19 @@ impl<S: Storage> P2pServer<S> {
             blockchain,
             connections_sender,
             syncing_peer: Mutex::new(None),
+            sync_progress: Mutex::new(None),
+            packet_metrics: PacketMetrics::default(),
             object_tracker,
             is_running: AtomicBool::new(true),
             blocks_propagation_queue: Mutex::new(LruCache::new(STABLE_LIMIT as usize * TIPS_LIMIT)),
-            blocks_processor
+            blocks_processor,
+            priority_blocks_processor,
+            crawler: if enable_crawler { Some(Arc::new(NetworkCrawler::default())) } else { None },
+            trusted_node_keys,
+            packet_recorder,
+            bandwidth: Arc::new(bandwidth),
+            transports,
+            tx_relay
         };
 
         let arc = Arc::new(server);
@@ -151,7 +198,7 @@ impl<S: Storage> P2pServer<S> {
         // Start the blocks processing task to have a queued handler
         {
             let zelf = Arc::clone(&arc);
-            tokio::spawn(zelf.blocks_processing_task(blocks_processor_receiver));
+            tokio::spawn(zelf.blocks_processing_task(blocks_processor_receiver, priority_blocks_processor_receiver));
         }
 
         // Start the event loop task to handle peer disconnect events
@@ -237,43 +284,76 @@ impl<S: Storage> P2pServer<S> {
         // start another task for ping loop
         tokio::spawn(Arc::clone(&self).ping_loop());
 
+        // start another task to detect dead connections
+        tokio::spawn(Arc::clone(self).keep_alive_loop());
+
         // start another task for peerlist loop
         if use_peerlist {
             tokio::spawn(Arc::clone(&self).peerlist_loop());
         }
 
-        let listener = TcpListener::bind(self.get_bind_address()).await?;
-        info!("P2p Server will listen on: {}", self.get_bind_address());
+        // start another task to crawl the network
+        if let Some(crawler) = &self.crawler {
+            tokio::spawn(Arc::clone(self).crawler_loop(Arc::clone(crawler)));
+        }
+
+        let transport = self.transports.get_default();
+        let listener = transport.bind(self.get_bind_address()).await?;
+        info!("P2p Server will listen on: {} ({})", self.get_bind_address(), transport.get_name());
+
+        // the other transports listen on the same address, their streams are forwarded to this loop
+        let (incoming_sender, mut incoming_receiver) = mpsc::channel(self.max_peers.max(1));
+        for transport in self.transports.get_others() {
+            let listener = transport.bind(self.get_bind_address()).await?;
+            info!("P2p Server will also listen on: {} ({})", self.get_bind_address(), transport.get_name());
+            let name = transport.get_name();
+            let sender = incoming_sender.clone();
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok(incoming) => {
+                            if sender.send(incoming).await.is_err() {
+                                break;
+                            }
+                        },
+                        Err(e) => {
+                            error!("Error while accepting a new {} connection: {}", name, e);
+                            break;
+                        }
+                    };
+                }
+            });
+        }
+
         // only allocate one time the buffer for this packet
-        let mut handshake_buffer = [0; 512];
+        let mut handshake_buffer = [0; HANDSHAKE_MAX_SIZE];
         loop {
             let (connection, out, priority) = select! {
                 res = listener.accept() => {
                     trace!("New listener result received (is err: {})", res.is_err());
-                    let (mut stream, addr) = res?;
-                    if !self.accept_new_connections().await { // if we have already reached the limit, we ignore this new connection
-                        debug!("Max peers reached, rejecting connection");
-                        if let Err(e) = stream.shutdown().await {
-                            debug!("Error while closing & ignoring incoming connection {}: {}", addr, e);
-                        }
-                        continue;
-                    } else {
-                        // check that this incoming peer isn't blacklisted
-                        let peer_list = self.peer_list.read().await;
-                        if peer_list.is_blacklisted(&addr.ip()) {
-                            debug!("{} is blacklisted, rejecting connection", addr);
-                            if let Err(e) = stream.shutdown().await {
-                                debug!("Error while closing & ignoring incoming connection {}: {}", addr, e);
-                            }
-                            continue;
-                        }
+                    let (stream, addr) = res?;
+                    match self.accept_incoming_stream(stream, addr).await {
+                        Some(connection) => (connection, false, false),
+                        None => continue
+                    }
+                },
+                Some((stream, addr)) = incoming_receiver.recv() => {
+                    match self.accept_incoming_stream(stream, addr).await {
+                        Some(connection) => (connection, false, false),
+                        None => continue
                     }
-                    (Connection::new(stream, addr), false, false)
                 },
                 Some(msg) = receiver.recv() => match msg {
                     MessageChannel::Exit => break,
                     MessageChannel::Connect((addr, priority)) => {
-                        if !self.accept_new_connections().await {
+                        // priority nodes may be trusted peers using the reserved slots
+                        let accept = if priority {
+                            self.accept_new_connections_with_reserved().await
+                        } else {
+                            self.accept_new_connections().await
+                        };
+
+                        if !accept {
                             trace!("Coudln't connect to {}, limit has been reached!", addr);
                             continue;
                         }
@@ -301,6 +381,33 @@ impl<S: Storage> P2pServer<S> {
         Ok(())
     }
 
+    // returns the connection if the incoming peer is accepted, otherwise the stream is closed
+    async fn accept_incoming_stream(&self, mut stream: TransportStream, addr: SocketAddr) -> Option<Connection> {
+        // we don't know yet if it's a trusted peer, so reserved slots are accepted until its handshake is verified
+        if !self.accept_new_connections_with_reserved().await { // if we have already reached the limit, we ignore this new connection
+            debug!("Max peers reached, rejecting connection");
+            if let Err(e) = stream.writer.shutdown().await {
+                debug!("Error while closing & ignoring incoming connection {}: {}", addr, e);
+            }
+            return None
+        }
+
+        // check that this incoming peer isn't blacklisted
+        let blacklisted = {
+            let peer_list = self.peer_list.read().await;
+            peer_list.is_blacklisted(&addr.ip())
+        };
+        if blacklisted {
+            debug!("{} is blacklisted, rejecting connection", addr);
+            if let Err(e) = stream.writer.shutdown().await {
+                debug!("Error while closing & ignoring incoming connection {}: {}", addr, e);
+            }
+            return None
+        }
+
+        Some(Connection::new(stream, addr, Arc::clone(&self.bandwidth)))
+    }
+
     // Verify handshake send by a new connection
     // based on data size, network ID, peers address validity
     // block height and block top hash of this peer (to know if we are on the same chain)
@@ -322,7 +429,7 @@ impl<S: Storage> P2pServer<S> {
             return Err(P2pError::PeerIdAlreadyUsed(handshake.get_peer_id()));
         }
 
-        if *handshake.get_block_genesis_hash() != *GENESIS_BLOCK_HASH {
+        if handshake.get_block_genesis_hash() != self.blockchain.get_genesis_hash() {
             debug!("Invalid genesis block hash {}", handshake.get_block_genesis_hash());
             return Err(P2pError::InvalidHandshake)
         }
@@ -335,18 +442,66 @@ impl<S: Storage> P2pServer<S> {
             }
         }
 
+        if handshake.get_services().is_archive() && (handshake.get_pruned_topoheight().is_some() || handshake.get_prune_depth().is_some()) {
+            debug!("{} advertises the archive service but is pruned", connection);
+            return Err(P2pError::InvalidHandshake)
+        }
+
+        if !handshake.verify_signature() {
+            debug!("{} has an invalid handshake signature for node key {}", connection, handshake.get_node_key());
+            return Err(P2pError::InvalidHandshake)
+        }
+
+        // sample the peer time to compute the network adjusted time
+        self.blockchain.get_time_sync().add_sample(connection.get_address().ip(), handshake.get_utc_time()).await;
+
+        // the time is checked so a handshake captured can't be replayed later to use the trust of its node
+        let trusted = self.trusted_node_keys.contains(handshake.get_node_key())
+            && self.blockchain.get_time_sync().get_clock().now_secs().abs_diff(handshake.get_utc_time()) <= PEER_TRUSTED_MAX_TIME_DRIFT;
+        if trusted {
+            debug!("{} is a trusted peer with node key {}", connection, handshake.get_node_key());
+        } else if !self.accept_new_connections().await {
+            // reserved slots are only for trusted peers
+            debug!("Max peers reached, rejecting {} not trusted", connection);
+            connection.close().await?;
+            return Err(P2pError::MaxPeersReached)
+        }
+
         connection.set_state(State::Success);
-        let peer = handshake.create_peer(connection, out, priority, Arc::clone(&self.peer_list));
+        // a trusted peer is also a priority node
+        let peer = handshake.create_peer(connection, out, priority || trusted, trusted, Arc::clone(&self.peer_list));
         Ok(peer)
     }
 
     async fn build_handshake(&self) -> Result<Handshake, P2pError> {
+        self.build_handshake_with_peer_id(self.get_peer_id()).await
+    }
+
+    async fn build_handshake_with_peer_id(&self, peer_id: u64) -> Result<Handshake, P2pError> {
         let storage = self.blockchain.get_storage().read().await;
         let (block, top_hash) = storage.get_top_block_header().await?;
         let topoheight = self.blockchain.get_topo_height();
         let pruned_topoheight = storage.get_pruned_topoheight()?;
         let cumulative_difficulty = storage.get_cumulative_difficulty_for_block_hash(&top_hash).await.unwrap_or(0);
-        Ok(Handshake::new(VERSION.to_owned(), *self.blockchain.get_network(), self.get_tag().clone(), NETWORK_ID, self.get_peer_id(), self.bind_address.port(), get_current_time(), topoheight, block.get_height(), pruned_topoheight, top_hash, GENESIS_BLOCK_HASH.clone(), cumulative_difficulty))
+        let prune_depth = self.blockchain.get_auto_prune_keep_n_blocks();
+        let services = self.get_services(pruned_topoheight.is_none() && prune_depth.is_none());
+        Ok(Handshake::new(VERSION.to_owned(), P2P_PROTOCOL_VERSION, *self.blockchain.get_network(), self.get_tag().clone(), NETWORK_ID, peer_id, self.bind_address.port(), self.blockchain.get_time_sync().get_clock().now_secs(), topoheight, block.get_height(), pruned_topoheight, top_hash, self.blockchain.get_genesis_hash().clone(), cumulative_difficulty, services, prune_depth, self.blockchain.get_node_key()))
+    }
+
+    // services advertised to the peers, full history means that no block has been pruned and none will be
+    fn get_services(&self, full_history: bool) -> NodeServices {
+        let mut flags = 0;
+        if full_history {
+            flags |= NodeServices::ARCHIVE;
+            // orphaned blocks are needed to find all the blocks including a transaction
+            if self.blockchain.get_orphaned_blocks_retention().is_none() {
+                flags |= NodeServices::TX_INDEX;
+            }
+        }
+        if self.tx_relay {
+            flags |= NodeServices::RELAY;
+        }
+        NodeServices::new(flags)
     }
 
     // this function handle all new connections
@@ -354,7 +509,12 @@ impl<S: Storage> P2pServer<S> {
     // if the handshake is valid, we accept it & register it on server
     async fn handle_new_connection(self: &Arc<Self>, buf: &mut [u8], mut connection: Connection, out: bool, priority: bool) -> Result<(), P2pError> {
         trace!("New connection: {}", connection);
-        let handshake: Handshake = match timeout(Duration::from_millis(PEER_TIMEOUT_INIT_CONNECTION), connection.read_packet(buf, buf.len() as u32)).await?? {
+        let bytes = timeout(Duration::from_millis(PEER_TIMEOUT_INIT_CONNECTION), connection.read_packet_bytes(buf, buf.len() as u32)).await??;
+        if let Some(recorder) = &self.packet_recorder {
+            recorder.record(&bytes);
+        }
+
+        let handshake: Handshake = match connection.read_packet_from_bytes(&bytes).await? {
             Packet::Handshake(h) => h.into_owned(), // only allow handshake packet
             _ => return Err(P2pError::ExpectedHandshake)
         };
@@ -384,7 +544,7 @@ impl<S: Storage> P2pServer<S> {
             if let Some(rpc) = self.blockchain.get_rpc().read().await.as_ref() {
                 if rpc.is_event_tracked(&NotifyEvent::PeerConnected).await {
                     debug!("Notifying clients with PeerConnected event");
-                    rpc.notify_clients_with(&NotifyEvent::PeerConnected, get_peer_entry(&peer).await).await;
+                    rpc.notify_clients(&DaemonEvent::PeerConnected(get_peer_entry(&peer).await)).await;
                 }
             }
             trace!("End locking for PeerConnected event");
@@ -416,8 +576,8 @@ impl<S: Storage> P2pServer<S> {
         if self.is_connected_to_addr(&addr).await? {
             return Err(P2pError::PeerAlreadyConnected(format!("{}", addr)));
         }
-        let stream = timeout(Duration::from_millis(800), TcpStream::connect(&addr)).await??; // allow maximum 800ms of latency
-        let connection = Connection::new(stream, addr);
+        let stream = timeout(Duration::from_millis(800), self.transports.get_for(&addr).connect(&addr)).await??; // allow maximum 800ms of latency
+        let connection = Connection::new(stream, addr, Arc::clone(&self.bandwidth));
         self.send_handshake(&connection).await?;
         Ok(connection)
     }
@@ -442,7 +602,7 @@ impl<S: Storage> P2pServer<S> {
             match storage.get_top_block_hash().await {
                 Err(e) => {
                     error!("Couldn't get the top block hash from storage for generic ping packet: {}", e);
-                    (0, GENESIS_BLOCK_HASH.clone(), pruned_topoheight)
+                    (0, self.blockchain.get_genesis_hash().clone(), pruned_topoheight)
                 },
                 Ok(hash) => (storage.get_cumulative_difficulty_for_block_hash(&hash).await.unwrap_or(0), hash, pruned_topoheight)
             }
@@ -478,6 +638,11 @@ impl<S: Storage> P2pServer<S> {
                 if peer_topoheight < PRUNE_SAFETY_LIMIT || our_topoheight + PRUNE_SAFETY_LIMIT > peer_topoheight {
                     return false
                 }
+
+                // older peers don't send the burned supply of the blocks
+                if !p.has_protocol_version(P2P_BURNED_SUPPLY_VERSION) {
+                    return false
+                }
             } else {
                 // check that the pruned topoheight is less than our topoheight to sync
                 // so we can sync chain from pruned chains
@@ -486,11 +651,25 @@ impl<S: Storage> P2pServer<S> {
                         return false
                     }
                 }
+
+                // a peer pruning automatically will delete the blocks we need while we sync from it
+                if let Some(prune_depth) = p.get_prune_depth() {
+                    if peer_topoheight.saturating_sub(prune_depth) > our_topoheight {
+                        return false
+                    }
+                }
             }
 
             p.get_height() > our_height || peer_topoheight > our_topoheight
         }).collect();
 
+        // archive peers can serve the whole chain, prefer them to sync it
+        let peers = if fast_sync || !peers.iter().any(|p| p.get_services().is_archive()) {
+            peers
+        } else {
+            peers.into_iter().filter(|p| p.get_services().is_archive()).collect()
+        };
+
         let count = peers.len();
         trace!("peers available for random selection: {}", count);
         if count == 0 {
@@ -519,7 +698,7 @@ impl<S: Storage> P2pServer<S> {
                     let our_topoheight = self.blockchain.get_topo_height();
                     peerlist.get_peers().values().find(|p| {
                         let peer_topoheight = p.get_topoheight();
-                        peer_topoheight > our_topoheight && peer_topoheight - our_topoheight > PRUNE_SAFETY_LIMIT
+                        peer_topoheight > our_topoheight && peer_topoheight - our_topoheight > PRUNE_SAFETY_LIMIT && p.has_protocol_version(P2P_BURNED_SUPPLY_VERSION)
                     }).is_some()
                 } else {
                     false
@@ -527,21 +706,31 @@ impl<S: Storage> P2pServer<S> {
 
                 if let Some(peer) = self.select_random_best_peer(fast_sync).await {
                     self.start_syncing(peer.clone()).await;
+                    self.update_sync_progress(&peer).await;
                     debug!("Selected for chain sync is {}", peer);
                     // check if we can maybe fast sync first
                     // otherwise, fallback on the normal chain sync
-                    if fast_sync {
-                        if let Err(e) = self.bootstrap_chain(&peer).await {
-                            warn!("Error occured while fast syncing with {}: {}", peer, e);
-                        }
+                    let res = if fast_sync {
+                        self.bootstrap_chain(&peer).await
                     } else {
-                        if let Err(e) = self.request_sync_chain_for(&peer).await {
-                            warn!("Error occured on chain sync with {}: {}", peer, e);
+                        self.request_sync_chain_for(&peer).await
+                    };
+
+                    if let Err(e) = res {
+                        warn!("Error occured on chain sync (fast sync: {}) with {}: {}", fast_sync, peer, e);
+                        // peer stopped to answer in the middle of the sync, don't wait on it anymore
+                        if let BlockchainError::ErrorOnP2p(P2pError::AsyncTimeOut(_)) = e {
+                            warn!("{} has stalled during chain sync, disconnecting it", peer);
+                            if let Err(e) = peer.close().await {
+                                error!("Error while closing stalled {}: {}", peer, e);
+                            }
                         }
                     }
                     self.stop_syncing().await;
+                    self.log_sync_progress().await;
                 } else {
                     trace!("No peer found for chain sync");
+                    self.end_sync_progress().await;
                 }
             }
         }
@@ -640,6 +829,51 @@ impl<S: Storage> P2pServer<S> {
         }
     }
 
+    // send a keep alive packet to each peer every P2P_KEEP_ALIVE_DELAY seconds
+    // and disconnect peers that didn't answer to the last P2P_KEEP_ALIVE_MAX_MISSED packets
+    async fn keep_alive_loop(self: Arc<Self>) {
+        debug!("Starting keep alive loop...");
+        let duration = Duration::from_secs(P2P_KEEP_ALIVE_DELAY);
+        loop {
+            sleep(duration).await;
+            if !self.is_running() {
+                debug!("Keep alive loop task is stopped!");
+                break;
+            }
+
+            let mut stale_peers = Vec::new();
+            {
+                trace!("Locking peerlist... (keep alive)");
+                let peerlist = self.peer_list.read().await;
+                trace!("End locking peerlist... (keep alive)");
+                for peer in peerlist.get_peers().values() {
+                    // older peers would never answer to the keep alive packets
+                    if !peer.has_protocol_version(P2P_KEEP_ALIVE_VERSION) {
+                        continue;
+                    }
+
+                    if peer.get_keep_alive_missed() >= P2P_KEEP_ALIVE_MAX_MISSED {
+                        stale_peers.push(Arc::clone(peer));
+                        continue;
+                    }
+
+                    let keep_alive = peer.next_keep_alive();
+                    if let Err(e) = peer.send_packet(Packet::KeepAliveRequest(keep_alive)).await {
+                        debug!("Error while sending keep alive packet to {}: {}", peer, e);
+                    }
+                }
+            }
+
+            // close them outside the peerlist lock
+            for peer in stale_peers {
+                warn!("{} didn't answer to the last {} keep alive packets (last activity: {}), disconnecting it", peer, P2P_KEEP_ALIVE_MAX_MISSED, peer.get_last_activity());
+                if let Err(e) = peer.close().await {
+                    error!("Error while closing stale {}: {}", peer, e);
+                }
+            }
+        }
+    }
+
     // try to extend our peerlist each time its possible by searching in known peerlist from disk
     async fn peerlist_loop(self: Arc<Self>) {
         debug!("Starting peerlist task...");
@@ -668,6 +902,73 @@ impl<S: Storage> P2pServer<S> {
         }
     }
 
+    // walk the peer exchange data of our peers and reach every node announced
+    async fn crawler_loop(self: Arc<Self>, crawler: Arc<NetworkCrawler>) {
+        debug!("Starting network crawler task...");
+        loop {
+            // wait first to be connected to some peers
+            sleep(Duration::from_secs(P2P_CRAWLER_DELAY)).await;
+            if !self.is_running() {
+                debug!("Network crawler task is stopped!");
+                break;
+            }
+
+            crawler.start_crawl().await;
+            {
+                let peer_list = self.peer_list.read().await;
+                for peer in peer_list.get_peers().values() {
+                    crawler.add_connected_peer(peer).await;
+                }
+            }
+
+            for addr in crawler.get_addresses_to_probe(P2P_CRAWLER_MAX_PROBES).await {
+                if !self.is_running() {
+                    break;
+                }
+
+                // it may have connected to us since
+                if self.is_connected_to_addr(&addr).await.unwrap_or(true) {
+                    continue;
+                }
+
+                let res = self.probe_node(addr).await;
+                crawler.set_probe_result(addr, res).await;
+            }
+            crawler.finish_crawl().await;
+        }
+    }
+
+    // connect to a node only to receive its handshake, then close the connection
+    async fn probe_node(&self, addr: SocketAddr) -> Result<Handshake, P2pError> {
+        let stream = timeout(Duration::from_millis(PEER_TIMEOUT_INIT_CONNECTION), self.transports.get_for(&addr).connect(&addr)).await??;
+        let connection = Connection::new(stream, addr, Arc::clone(&self.bandwidth));
+        // use a random peer id to not be rejected if we are already connected to it under another address
+        let peer_id: u64 = rand::thread_rng().gen();
+        let handshake = self.build_handshake_with_peer_id(peer_id).await?;
+        connection.send_bytes(&Packet::Handshake(Cow::Owned(handshake)).to_bytes()).await?;
+
+        let mut buf = [0; HANDSHAKE_MAX_SIZE];
+        let res = match timeout(Duration::from_millis(PEER_TIMEOUT_INIT_CONNECTION), connection.read_packet(&mut buf, HANDSHAKE_MAX_SIZE as u32)).await? {
+            Ok(Packet::Handshake(h)) => Ok(h.into_owned()),
+            Ok(_) => Err(P2pError::ExpectedHandshake),
+            Err(e) => Err(e)
+        };
+
+        if let Err(e) = connection.close().await {
+            trace!("Error while closing probe connection to {}: {}", addr, e);
+        }
+
+        let handshake = res?;
+        if handshake.get_network() != self.blockchain.get_network() || handshake.get_block_genesis_hash() != self.blockchain.get_genesis_hash() {
+            return Err(P2pError::InvalidNetwork)
+        }
+
+        if !handshake.verify_signature() {
+            return Err(P2pError::InvalidHandshake)
+        }
+        Ok(handshake)
+    }
+
     // This function is used to broadcast PeerDisconnected event to listeners
     // We use a channel to avoid having to pass the Blockchain<S> to the Peerlist & Peers
     async fn event_loop(self: Arc<Self>, mut receiver: UnboundedReceiver<Arc<Peer>>) {
@@ -680,7 +981,7 @@ impl<S: Storage> P2pServer<S> {
             if let Some(rpc) = self.blockchain.get_rpc().read().await.as_ref() {
                 if rpc.is_event_tracked(&NotifyEvent::PeerDisconnected).await {
                     debug!("Notifying clients with PeerDisconnected event");
-                    rpc.notify_clients_with(&NotifyEvent::PeerDisconnected, get_peer_entry(&peer).await).await;
+                    rpc.notify_clients(&DaemonEvent::PeerDisconnected(get_peer_entry(&peer).await)).await;
                 }
             }
         }
@@ -688,9 +989,17 @@ impl<S: Storage> P2pServer<S> {
     }
 
     // Task for all blocks propagation
-    async fn blocks_processing_task(self: Arc<Self>, mut receiver: Receiver<(Arc<Peer>, BlockHeader, Hash)>) {
+    // blocks from priority nodes are processed first to reduce the orphan rate of their miners
+    async fn blocks_processing_task(self: Arc<Self>, mut receiver: Receiver<(Arc<Peer>, BlockHeader, Hash)>, mut priority_receiver: Receiver<(Arc<Peer>, BlockHeader, Hash)>) {
         debug!("Starting blocks processing task");
-        while let Some((peer, header, block_hash)) = receiver.recv().await {
+        loop {
+            let (peer, header, block_hash) = select! {
+                biased;
+                Some(value) = priority_receiver.recv() => value,
+                Some(value) = receiver.recv() => value,
+                else => break
+            };
+
             let mut response_blockers: Vec<ResponseBlocker> = Vec::new();
             for hash in header.get_txs_hashes() {
                 let contains = { // we don't lock one time because we may wait on p2p response
@@ -747,10 +1056,16 @@ impl<S: Storage> P2pServer<S> {
     }
 
     // this function handle the logic to send all packets to the peer
-    async fn handle_connection_write_side(&self, peer: &Arc<Peer>, rx: &mut UnboundedReceiver<ConnectionMessage>) -> Result<(), P2pError> {
+    async fn handle_connection_write_side(&self, peer: &Arc<Peer>, rx: &mut UnboundedReceiver<ConnectionMessage>, priority_rx: &mut UnboundedReceiver<ConnectionMessage>) -> Result<(), P2pError> {
         loop {
-            // all packets to be sent
-            if let Some(data) = rx.recv().await {
+            // all packets to be sent, the priority ones preempt the queued packets
+            let data = select! {
+                biased;
+                Some(data) = priority_rx.recv() => Some(data),
+                data = rx.recv() => data
+            };
+
+            if let Some(data) = data {
                 if peer.get_connection().is_closed() {
                     break;
                 }
@@ -774,7 +1089,28 @@ impl<S: Storage> P2pServer<S> {
         Ok(())
     }
 
-    async fn handle_connection_read_side(self: Arc<Self>, peer: &Arc<Peer>, mut write_task: JoinHandle<()>) -> Result<(), P2pError> {
+    // send the packets queued for the transactions stream of the peer
+    async fn handle_transactions_write_side(&self, peer: &Arc<Peer>, rx: &mut UnboundedReceiver<ConnectionMessage>) -> Result<(), P2pError> {
+        while let Some(data) = rx.recv().await {
+            if peer.get_connection().is_closed() {
+                break;
+            }
+
+            match data {
+                ConnectionMessage::Packet(bytes) => {
+                    trace!("Sending packet with ID {} on the transactions stream", bytes[4]);
+                    peer.get_connection().send_bytes_on(TrafficClass::Transactions, &bytes).await?;
+                }
+                ConnectionMessage::Exit => {
+                    trace!("Exit message received for transactions stream of {}", peer);
+                    break;
+                }
+            };
+        }
+        Ok(())
+    }
+
+    async fn handle_connection_read_side(self: Arc<Self>, peer: &Arc<Peer>, mut write_task: JoinHandle<()>, class: TrafficClass) -> Result<(), P2pError> {
         // allocate the unique buffer for this connection
         let mut buf = [0u8; 1024];
         loop {
@@ -784,12 +1120,12 @@ impl<S: Storage> P2pServer<S> {
                     debug!("write task for {} has finished, stopping...", peer);
                     break;
                 },
-                res = self.listen_connection(&mut buf, &peer) => {
+                res = self.listen_connection(&mut buf, &peer, class) => {
                     res?;
 
                     // check that we don't have too many fails
                     // otherwise disconnect peer
-                    if peer.get_fail_count() >= PEER_FAIL_LIMIT {
+                    if peer.get_fail_count() >= peer.get_fail_limit() {
                         warn!("High fail count detected for {}! Closing connection...", peer);
                         if let Err(e) = peer.close().await {
                             error!("Error while trying to close connection with {} due to high fail count: {}", peer, e);
@@ -812,7 +1148,8 @@ impl<S: Storage> P2pServer<S> {
             let peer = Arc::clone(&peer);
             tokio::spawn(async move {
                 let mut rx = peer.get_connection().get_rx().lock().await;
-                if let Err(e) = zelf.handle_connection_write_side(&peer, &mut rx).await {
+                let mut priority_rx = peer.get_connection().get_priority_rx().lock().await;
+                if let Err(e) = zelf.handle_connection_write_side(&peer, &mut rx, &mut priority_rx).await {
                     debug!("Error while writing to {}: {}", peer, e);
                     if !peer.get_connection().is_closed() {
                         if let Err(e) = peer.close().await {
@@ -821,15 +1158,52 @@ impl<S: Storage> P2pServer<S> {
                     }
                 }
                 rx.close(); // clean shutdown
+                priority_rx.close();
             })
         };
 
+        // tasks for the transactions stream, so they are relayed while the chain is synced
+        if peer.get_connection().has_transactions_stream() {
+            let write_task = {
+                let zelf = Arc::clone(self);
+                let peer = Arc::clone(&peer);
+                tokio::spawn(async move {
+                    let Some(rx) = peer.get_connection().get_transactions_rx() else {
+                        return
+                    };
+                    let mut rx = rx.lock().await;
+                    if let Err(e) = zelf.handle_transactions_write_side(&peer, &mut rx).await {
+                        debug!("Error while writing transactions to {}: {}", peer, e);
+                        if !peer.get_connection().is_closed() {
+                            if let Err(e) = peer.close().await {
+                                debug!("Error while closing {} from transactions write side: {}", peer, e);
+                            }
+                        }
+                    }
+                    rx.close();
+                })
+            };
+
+            let zelf = Arc::clone(self);
+            let peer = Arc::clone(&peer);
+            tokio::spawn(async move {
+                if let Err(e) = zelf.handle_connection_read_side(&peer, write_task, TrafficClass::Transactions).await {
+                    debug!("Error while reading transactions from peer {}: {}", peer, e);
+                    if !peer.get_connection().is_closed() {
+                        if let Err(e) = peer.close().await {
+                            debug!("Error while closing {} from transactions read side: {}", peer, e);
+                        }
+                    }
+                }
+            });
+        }
+
         // task for reading from peer
         {
             let zelf = Arc::clone(&self);
             let peer = Arc::clone(&peer);
             tokio::spawn(async move {
-                if let Err(e) = zelf.handle_connection_read_side(&peer, write_task).await {
+                if let Err(e) = zelf.handle_connection_read_side(&peer, write_task, TrafficClass::Chain).await {
                     debug!("Error while running read part from peer {}: {}", peer, e);
                     if !peer.get_connection().is_closed() {
                         if let Err(e) = peer.close().await {
@@ -880,6 +1254,13 @@ impl<S: Storage> P2pServer<S> {
     }
 
     async fn handle_incoming_packet(self: &Arc<Self>, peer: &Arc<Peer>, packet: Packet<'_>) -> Result<(), P2pError> {
+        // peer must not send a packet that it didn't advertise in its handshake
+        let id = packet.get_id();
+        if !peer.has_protocol_version(get_packet_protocol_version(id)) {
+            debug!("{} sent packet {} not supported by its protocol version {}", peer, id, peer.get_protocol_version());
+            return Err(P2pError::UnsupportedPacket(id))
+        }
+
         match packet {
             Packet::Handshake(_) => {
                 error!("{} sent us handshake packet (not valid!)", peer);
@@ -909,7 +1290,9 @@ impl<S: Storage> P2pServer<S> {
                 }
 
                 // Check that the tx is not in mempool or on disk already
-                if !self.blockchain.has_tx(&hash).await? {
+                if !self.tx_relay {
+                    trace!("Transaction relay is disabled, ignoring tx {} propagated", hash);
+                } else if !self.blockchain.has_tx(&hash).await? {
                     trace!("Requesting tx {} propagated because we don't have it", hash);
                     if !self.object_tracker.request_object_from_peer(Arc::clone(peer), ObjectRequest::Transaction(hash.clone()), true).await? {
                         debug!("TX propagated {} was already requested, ignoring", hash);
@@ -981,14 +1364,19 @@ impl<S: Storage> P2pServer<S> {
                         debug!("Block {} propagated is already in processing from another peer", block_hash);
                         return Ok(())
                     }
-                    blocks_propagation_queue.put(block_hash.clone(), ());
+                    blocks_propagation_queue.put(block_hash.clone(), peer.is_priority());
                 }
 
                 let block_height = header.get_height();
                 debug!("Received block at height {} from {}", block_height, peer);
+                let blocks_processor = if peer.is_priority() {
+                    &self.priority_blocks_processor
+                } else {
+                    &self.blocks_processor
+                };
                 let peer = Arc::clone(peer);
                 // This will block the task if the bounded channel is full
-                if let Err(e) = self.blocks_processor.send((peer, header, block_hash)).await {
+                if let Err(e) = blocks_processor.send((peer, header, block_hash)).await {
                     error!("Error while sending block propagated to blocks processor task: {}", e);
                 }
             },
@@ -996,11 +1384,18 @@ impl<S: Storage> P2pServer<S> {
                 trace!("Received a chain request from {}", peer);
                 let (request, ping) = packet_wrapper.consume();
                 ping.into_owned().update_peer(peer, &self.blockchain).await?;
+                // syncing peers is the most expensive traffic, stop serving it once a cap is reached
+                if self.bandwidth.is_cap_exceeded() {
+                    debug!("Bandwidth cap reached, ignoring chain request from {}", peer);
+                    return Ok(())
+                }
+
                 let request = request.into_owned();
                 let last_request = peer.get_last_chain_sync();
                 let time = get_current_time();
                 // Node is trying to ask too fast our chain
-                if  last_request + CHAIN_SYNC_DELAY > time {
+                // trusted peers are not rate limited
+                if !peer.is_trusted() && last_request + CHAIN_SYNC_DELAY > time {
                     debug!("{} requested sync chain too fast!", peer);
                     return Err(P2pError::RequestSyncChainTooFast)
                 }
@@ -1080,6 +1475,12 @@ impl<S: Storage> P2pServer<S> {
                 match &request {
                     ObjectRequest::Block(hash) => {
                         debug!("{} asked full block {}", peer, hash);
+                        if self.bandwidth.is_cap_exceeded() {
+                            debug!("Bandwidth cap reached, not sending block {} to {}", hash, peer);
+                            peer.send_packet(Packet::ObjectResponse(ObjectResponse::NotFound(request))).await?;
+                            return Ok(())
+                        }
+
                         let block = {
                             let storage = self.blockchain.get_storage().read().await;
                             storage.get_block(hash).await
@@ -1150,6 +1551,54 @@ impl<S: Storage> P2pServer<S> {
                     return Err(P2pError::ObjectNotRequested(request))
                 }
             },
+            Packet::KeepAliveRequest(keep_alive) => {
+                trace!("Received a keep alive request ({}) from {}", keep_alive.get_sequence(), peer);
+                peer.send_packet(Packet::KeepAliveResponse(keep_alive)).await?;
+            },
+            Packet::KeepAliveResponse(keep_alive) => {
+                trace!("Received a keep alive response ({}) from {}", keep_alive.get_sequence(), peer);
+                peer.on_keep_alive_response(&keep_alive)?;
+            },
+            Packet::ObjectsRequest(request) => {
+                let (id, requests) = request.into_owned().consume();
+                debug!("{} asked {} objects (batch id {})", peer, requests.len(), id);
+                let mut responses = Vec::with_capacity(requests.len());
+                let mut size = 0;
+                for request in requests {
+                    let response = self.get_object_response(request).await;
+                    // partial response if it's too big, peer will request the missing objects again
+                    let response_size = response.size();
+                    if !responses.is_empty() && size + response_size > P2P_OBJECTS_RESPONSE_MAX_SIZE {
+                        debug!("Objects response for {} is full, sending {} objects only", peer, responses.len());
+                        break;
+                    }
+                    size += response_size;
+                    responses.push(response);
+                }
+
+                peer.send_packet(Packet::ObjectsResponse(ObjectsResponse::new(id, responses))).await?;
+            },
+            Packet::ObjectsResponse(response) => {
+                let (id, responses) = response.consume();
+                trace!("Received {} objects from {} (batch id {})", responses.len(), peer, id);
+                let responses: Vec<OwnedObjectResponse> = responses.into_iter().map(|response| response.to_owned()).collect();
+
+                // check if we requested it from this peer
+                if let Some(sender) = peer.remove_objects_batch(id).await {
+                    if sender.send(responses).is_err() {
+                        error!("Error while sending objects response to sender!");
+                    }
+                } else {
+                    // otherwise, it must have been requested by the Object Tracker
+                    for response in responses {
+                        let request = response.get_request();
+                        if !self.object_tracker.has_requested_object(request.get_hash()).await {
+                            return Err(P2pError::ObjectNotRequested(request))
+                        }
+                        self.object_tracker.handle_object_response(response).await?;
+                    }
+                }
+            },
             Packet::NotifyInventoryRequest(packet_wrapper) => {
                 trace!("Received a inventory request from {}", peer);
                 let (request, ping) = packet_wrapper.consume();
@@ -1204,15 +1653,19 @@ impl<S: Storage> P2pServer<S> {
                         }
                     }
 
+                    let mut requests = Vec::new();
                     for hash in txs.into_owned() {
                         // Verify that we don't already have it
                         if !self.blockchain.has_tx(&hash).await? {
                             trace!("Requesting TX {} from inventory response", hash);
-                            if !self.object_tracker.request_object_from_peer(Arc::clone(peer), ObjectRequest::Transaction(hash.into_owned()), false).await? {
-                                debug!("TX was already requested, ignoring");
-                            }
+                            requests.push(ObjectRequest::Transaction(hash.into_owned()));
                         }
                     }
+
+                    if !requests.is_empty() {
+                        let count = self.object_tracker.request_objects_from_peer(Arc::clone(peer), requests, false).await?;
+                        debug!("Requested {} TXs from inventory of {}", count, peer);
+                    }
                 }
 
                 // request the next page
@@ -1225,6 +1678,10 @@ impl<S: Storage> P2pServer<S> {
                 }
             },
             Packet::BootstrapChainRequest(request) => {
+                if self.bandwidth.is_cap_exceeded() {
+                    debug!("Bandwidth cap reached, ignoring bootstrap chain request from {}", peer);
+                    return Ok(())
+                }
                 self.handle_bootstrap_chain_request(peer, request.step()).await?;
             },
             Packet::BootstrapChainResponse(response) => {
@@ -1274,7 +1731,7 @@ impl<S: Storage> P2pServer<S> {
                             peer_id: peer.get_id(),
                             peer_addr: addr
                         };
-                        rpc.notify_clients_with(&NotifyEvent::PeerPeerDisconnected, value).await;
+                        rpc.notify_clients(&DaemonEvent::PeerPeerDisconnected(value)).await;
                     }
                 }
                 trace!("End locking for PeerDisconnected event");
@@ -1283,14 +1740,34 @@ impl<S: Storage> P2pServer<S> {
         Ok(())
     }
 
-    // Listen to incoming packets from a connection
+    // Listen to incoming packets from a stream of the connection
     // Packet is read from the same task always, while its handling is delegated to a unique task
-    async fn listen_connection(self: &Arc<Self>, buf: &mut [u8], peer: &Arc<Peer>) -> Result<(), P2pError> {
+    async fn listen_connection(self: &Arc<Self>, buf: &mut [u8], peer: &Arc<Peer>, class: TrafficClass) -> Result<(), P2pError> {
         // Read & parse the packet
-        let packet = peer.get_connection().read_packet(buf, MAX_BLOCK_SIZE as u32).await?;
-        // Handle the packet
-        if let Err(e) = self.handle_incoming_packet(&peer, packet).await {
-            error!("Error occured while handling incoming packet from {}: {}", peer, e);
+        let bytes = peer.get_connection().read_packet_bytes_on(class, buf, MAX_BLOCK_SIZE as u32).await?;
+        if let Some(recorder) = &self.packet_recorder {
+            recorder.record(&bytes);
+        }
+        let packet = peer.get_connection().read_packet_from_bytes(&bytes).await?;
+        peer.set_last_activity(get_current_time());
+
+        // Handle the packet and measure how long it took
+        let id = packet.get_id();
+        let name = packet.get_name();
+        let start = Instant::now();
+        let result = self.handle_incoming_packet(peer, packet).await;
+        let elapsed = start.elapsed();
+
+        self.packet_metrics.record(id, bytes.len(), elapsed, result.is_err());
+        peer.record_packet_handling(elapsed);
+        if elapsed >= Duration::from_millis(P2P_SLOW_PACKET_THRESHOLD_MS) {
+            warn!("Slow {} packet ({} bytes) from {} handled in {}ms", name, bytes.len(), peer.get_outgoing_address(), elapsed.as_millis());
+        } else {
+            trace!("{} packet ({} bytes) from {} handled in {}us", name, bytes.len(), peer.get_outgoing_address(), elapsed.as_micros());
+        }
+
+        if let Err(e) = result {
+            error!("Error occured while handling incoming {} packet from {}: {}", name, peer, e);
             peer.increment_fail_count();
         }
 
@@ -1373,6 +1850,92 @@ impl<S: Storage> P2pServer<S> {
         Ok(())
     }
 
+    // retrieve the object requested from our chain or mempool
+    async fn get_object_response(&self, request: ObjectRequest) -> ObjectResponse<'static> {
+        match &request {
+            ObjectRequest::Block(hash) => {
+                if self.bandwidth.is_cap_exceeded() {
+                    debug!("Bandwidth cap reached, block '{}' requested is not sent", hash);
+                    return ObjectResponse::NotFound(request)
+                }
+
+                let block = {
+                    let storage = self.blockchain.get_storage().read().await;
+                    storage.get_block(hash).await
+                };
+
+                match block {
+                    Ok(block) => ObjectResponse::Block(Cow::Owned(block)),
+                    Err(e) => {
+                        debug!("Block '{}' requested but not present in our chain: {}", hash, e);
+                        ObjectResponse::NotFound(request)
+                    }
+                }
+            },
+            ObjectRequest::BlockHeader(hash) => {
+                let header = {
+                    let storage = self.blockchain.get_storage().read().await;
+                    storage.get_block_header_by_hash(hash).await
+                };
+
+                match header {
+                    Ok(header) => ObjectResponse::BlockHeader(Cow::Owned(header.as_ref().clone())),
+                    Err(e) => {
+                        debug!("Block header '{}' requested but not present in our chain: {}", hash, e);
+                        ObjectResponse::NotFound(request)
+                    }
+                }
+            },
+            ObjectRequest::Transaction(hash) => {
+                match self.blockchain.get_tx(hash).await {
+                    Ok(tx) => ObjectResponse::Transaction(Cow::Owned(tx.as_ref().clone())),
+                    Err(e) => {
+                        debug!("TX '{}' requested but not present in our chain: {}", hash, e);
+                        ObjectResponse::NotFound(request)
+                    }
+                }
+            }
+        }
+    }
+
+    // retrieve all the transactions of a block
+    // check first on disk in case it was already fetch by a previous block
+    // it can happens as TXs can be integrated in multiple blocks and executed only one time
+    // otherwise, ask all the missing ones from peer in batches
+    async fn request_block_transactions(&self, peer: &Arc<Peer>, hash: &Hash, header: &BlockHeader) -> Result<Vec<Immutable<Transaction>>, BlockchainError> {
+        let mut requests = Vec::new();
+        for tx_hash in header.get_txs_hashes() {
+            if !self.blockchain.has_tx(tx_hash).await? {
+                requests.push(ObjectRequest::Transaction(tx_hash.clone()));
+            }
+        }
+
+        let mut received = HashMap::with_capacity(requests.len());
+        if !requests.is_empty() {
+            trace!("Requesting {} TXs for block {} from {}", requests.len(), hash, peer);
+            for response in peer.request_blocking_objects(requests).await? {
+                let OwnedObjectResponse::Transaction(tx, tx_hash) = response else {
+                    error!("{} sent us an invalid object response while fetching transactions of block {}", peer, hash);
+                    return Err(P2pError::ExpectedTransaction.into())
+                };
+                received.insert(tx_hash, tx);
+            }
+        }
+
+        let mut transactions = Vec::with_capacity(header.get_txs_count());
+        for tx_hash in header.get_txs_hashes() {
+            let tx = if let Some(tx) = received.remove(tx_hash) {
+                Immutable::Owned(tx)
+            } else {
+                trace!("Found the transaction {} on disk", tx_hash);
+                Immutable::Arc(self.blockchain.get_tx(tx_hash).await?)
+            };
+            transactions.push(tx);
+        }
+
+        Ok(transactions)
+    }
+
     async fn handle_chain_response(&self, peer: &Arc<Peer>, mut response: ChainResponse, requested_max_size: usize) -> Result<(), BlockchainError> {
         trace!("handle chain response from {}", peer);
         let response_size = response.size();
@@ -1439,16 +2002,19 @@ impl<S: Storage> P2pServer<S> {
             } else {
                 // request all blocks header and verify basic chain structure
                 let mut chain_validator = ChainValidator::new(self.blockchain.clone());
+                let mut requests = Vec::new();
                 for hash in blocks {
-                    trace!("Request block header for chain validator: {}", hash);
-
                     // check if we already have the block to not request it
                     if self.blockchain.has_block(&hash).await? {
                         trace!("We already have block {}, skipping", hash);
                         continue;
                     }
 
-                    let response = peer.request_blocking_object(ObjectRequest::BlockHeader(hash)).await?;
+                    trace!("Request block header for chain validator: {}", hash);
+                    requests.push(ObjectRequest::BlockHeader(hash));
+                }
+
+                for response in peer.request_blocking_objects(requests).await? {
                     if let OwnedObjectResponse::BlockHeader(header, hash) = response {
                         trace!("Received {} with hash {}", header, hash);
                         chain_validator.insert_block(hash, header).await?;
@@ -1466,51 +2032,22 @@ impl<S: Storage> P2pServer<S> {
                     let header = chain_validator.consume_block_header(&hash)?;
                     // we don't already have this block, lets retrieve its txs and add in our chain
                     if !self.blockchain.has_block(&hash).await? {
-                        let mut transactions = Vec::new(); // don't pre allocate
-                        for tx_hash in header.get_txs_hashes() {
-                            // check first on disk in case it was already fetch by a previous block
-                            // it can happens as TXs can be integrated in multiple blocks and executed only one time
-                            // check if we find it
-                            if let Some(tx) = self.blockchain.get_tx(tx_hash).await.ok() {
-                                trace!("Found the transaction {} on disk", tx_hash);
-                                transactions.push(Immutable::Arc(tx));
-                            } else { // otherwise, ask it from peer
-                                let response = peer.request_blocking_object(ObjectRequest::Transaction(tx_hash.clone())).await?;
-                                if let OwnedObjectResponse::Transaction(tx, _) = response {
-                                    trace!("Received transaction {} at block {} from {}", tx_hash, hash, peer);
-                                    transactions.push(Immutable::Owned(tx));
-                                } else {
-                                    error!("{} sent us an invalid block response", peer);
-                                    return Err(P2pError::ExpectedTransaction.into())
-                                }
-                            }
-                        }
-
+                        let transactions = self.request_block_transactions(peer, &hash, &header).await?;
                         let block = Block::new(Immutable::Arc(header), transactions);
                         self.blockchain.add_new_block(block, false, false).await?; // don't broadcast block because it's syncing
+                        peer.add_blocks_synced(1);
                     }
                 }
             }
         } else {
             // no rewind are needed, process normally
             // it will first add blocks to sync, and then all alt-tips blocks if any (top blocks)
-            let mut total_requested: usize = 0;
-            for hash in blocks { // Request all blocks now
-                if !self.blockchain.has_block(&hash).await? {
-                    trace!("Block {} is not found, asking it to {} (index = {})", hash, peer.get_outgoing_address(), total_requested);
-                    let response = peer.request_blocking_object(ObjectRequest::Block(hash)).await?;
-                    if let OwnedObjectResponse::Block(block, hash) = response {
-                        trace!("Received block {} at height {} from {}", hash, block.get_height(), peer);
-                        self.blockchain.add_new_block(block, false, false).await?;
-                    } else {
-                        error!("{} sent us an invalid block response", peer);
-                        return Err(P2pError::ExpectedBlock.into())
-                    }
-                    total_requested += 1;
-                } else {
-                    trace!("Block {} is already in chain, skipping it", hash);
-                }
-            }
+            // they are saved first so a restarted node can resume from them
+            let cursor = SyncCursor::new(BlockId::new(common_point.get_hash().clone(), common_point.get_topoheight()), blocks);
+            self.blockchain.get_storage().write().await.set_sync_cursor(Some(&cursor))?;
+            let (_, blocks) = cursor.consume();
+            let total_requested = self.request_and_add_blocks(peer, &blocks).await?;
+            self.blockchain.get_storage().write().await.set_sync_cursor(None)?;
             debug!("we've synced {} on {} blocks and {} top blocks from {}", total_requested, blocks_len, top_len, peer);
         }
 
@@ -1564,6 +2101,15 @@ impl<S: Storage> P2pServer<S> {
         self.get_peer_count().await < self.get_max_peers()
     }
 
+    // one slot is reserved for each trusted node key
+    pub async fn accept_new_connections_with_reserved(&self) -> bool {
+        self.get_peer_count().await < self.get_max_peers() + self.trusted_node_keys.len()
+    }
+
+    pub fn get_trusted_node_keys(&self) -> &HashSet<PublicKey> {
+        &self.trusted_node_keys
+    }
+
     pub async fn get_peer_count(&self) -> usize {
         let peer_list = self.peer_list.read().await;
         peer_list.size()
@@ -1596,6 +2142,94 @@ impl<S: Storage> P2pServer<S> {
         self.syncing_peer.lock().await.is_some()
     }
 
+    // start a new sync session if needed and register the peer as a source
+    async fn update_sync_progress(&self, peer: &Arc<Peer>) {
+        let mut progress = self.sync_progress.lock().await;
+        let progress = progress.get_or_insert_with(|| {
+            let topoheight = self.blockchain.get_topo_height();
+            info!("Starting chain sync at topoheight {}", topoheight);
+            SyncProgress::new(topoheight)
+        });
+        progress.add_peer(*peer.get_outgoing_address());
+    }
+
+    async fn log_sync_progress(&self) {
+        let mut progress = self.sync_progress.lock().await;
+        if let Some(progress) = progress.as_mut() {
+            if progress.should_log(Duration::from_secs(CHAIN_SYNC_PROGRESS_LOG_DELAY)) {
+                let topoheight = self.blockchain.get_topo_height();
+                let target_topoheight = self.get_best_topoheight().await.max(topoheight);
+                let eta = match progress.get_eta(topoheight, target_topoheight) {
+                    Some(eta) => format!("{}s", eta),
+                    None => "unknown".to_owned()
+                };
+                info!("Syncing chain: {}/{} ({:.2}%), {:.2} blocks/s, ETA: {}, {} peers used", topoheight, target_topoheight, get_progress(topoheight, target_topoheight), progress.get_blocks_per_second(topoheight), eta, progress.get_peers().len());
+            }
+        }
+    }
+
+    // no peer is above us anymore, the sync session is done
+    async fn end_sync_progress(&self) {
+        if let Some(progress) = self.sync_progress.lock().await.take() {
+            let topoheight = self.blockchain.get_topo_height();
+            info!("Chain synced at topoheight {} ({:.2} blocks/s using {} peers)", topoheight, progress.get_blocks_per_second(topoheight), progress.get_peers().len());
+        }
+    }
+
+    pub async fn get_metrics(&self) -> GetP2pMetricsResult<'static> {
+        let mut peers: Vec<PeerMetricsEntry> = {
+            let peer_list = self.peer_list.read().await;
+            peer_list.get_peers().values().map(|peer| PeerMetricsEntry {
+                addr: *peer.get_outgoing_address(),
+                packets: peer.get_packets_in(),
+                bytes_in: peer.get_connection().bytes_in(),
+                bytes_out: peer.get_connection().bytes_out(),
+                handling_time: peer.get_handling_time()
+            }).collect()
+        };
+        peers.sort_by_key(|peer| Reverse(peer.handling_time));
+
+        GetP2pMetricsResult {
+            packets: self.packet_metrics.get_entries(),
+            peers
+        }
+    }
+
+    pub async fn get_bandwidth(&self) -> GetBandwidthResult {
+        let mut peers: Vec<PeerBandwidthEntry> = {
+            let peer_list = self.peer_list.read().await;
+            peer_list.get_peers().values().map(|peer| PeerBandwidthEntry {
+                addr: *peer.get_outgoing_address(),
+                bytes_in: peer.get_connection().bytes_in(),
+                bytes_out: peer.get_connection().bytes_out()
+            }).collect()
+        };
+        peers.sort_by_key(|peer| Reverse(peer.bytes_in + peer.bytes_out));
+
+        GetBandwidthResult {
+            bytes_in: self.bandwidth.get_bytes_in(),
+            bytes_out: self.bandwidth.get_bytes_out(),
+            daily: self.bandwidth.get_daily(),
+            monthly: self.bandwidth.get_monthly(),
+            peers
+        }
+    }
+
+    pub async fn get_sync_status(&self) -> GetSyncStatusResult {
+        let topoheight = self.blockchain.get_topo_height();
+        let target_topoheight = self.get_best_topoheight().await.max(topoheight);
+        let progress = self.sync_progress.lock().await;
+        GetSyncStatusResult {
+            syncing: progress.is_some(),
+            topoheight,
+            target_topoheight,
+            progress: get_progress(topoheight, target_topoheight),
+            blocks_per_second: progress.as_ref().map(|p| p.get_blocks_per_second(topoheight)).unwrap_or(0f64),
+            eta: progress.as_ref().and_then(|p| p.get_eta(topoheight, target_topoheight)),
+            peers: progress.as_ref().map(|p| p.get_peers().iter().copied().collect()).unwrap_or_default()
+        }
+    }
+
     pub async fn is_connected_to(&self, peer_id: &u64) -> Result<bool, P2pError> {
         let peer_list = self.peer_list.read().await;
         Ok(self.peer_id == *peer_id || peer_list.has_peer(peer_id))
@@ -1619,6 +2253,10 @@ impl<S: Storage> P2pServer<S> {
         &self.peer_list
     }
 
+    pub fn get_crawler(&self) -> Option<&Arc<NetworkCrawler>> {
+        self.crawler.as_ref()
+    }
+
     pub async fn broadcast_tx_hash(&self, tx: Hash) {
         info!("Broadcasting tx hash {}", tx);
         let ping = self.build_generic_ping_packet().await;
@@ -1632,6 +2270,12 @@ impl<S: Storage> P2pServer<S> {
         trace!("Lock acquired for tx broadcast");
 
         for peer in peer_list.get_peers().values() {
+            // the peer asked to not receive the transactions
+            if !peer.get_services().is_relay() {
+                trace!("{} doesn't relay transactions, skipping tx hash {}", peer, tx);
+                continue
+            }
+
             // check that the peer is not too far from us
             // otherwise we may spam him for nothing
             let peer_topoheight = peer.get_topoheight();
@@ -1662,11 +2306,16 @@ impl<S: Storage> P2pServer<S> {
         let block_packet = Packet::BlockPropagation(PacketWrapper::new(Cow::Borrowed(block), Cow::Borrowed(&ping)));
         let packet_block_bytes = Bytes::from(block_packet.to_bytes());
         let packet_ping_bytes = Bytes::from(Packet::Ping(Cow::Owned(ping)).to_bytes());
+        // a block found by us or relayed from a priority node skips the packets queued for each peer
+        let priority = lock || self.blocks_propagation_queue.lock().await.peek(hash).copied().unwrap_or(false);
 
         trace!("Locking peer list for broadcasting block {}", hash);
         let peer_list = self.peer_list.read().await;
         trace!("start broadcasting block {} to all peers", hash);
-        for (_, peer) in peer_list.get_peers() {
+        // trusted peers receive the block first
+        let peers = peer_list.get_peers().values().filter(|peer| peer.is_trusted())
+            .chain(peer_list.get_peers().values().filter(|peer| !peer.is_trusted()));
+        for peer in peers {
             // if the peer can directly accept this new block, send it
             let peer_height = peer.get_height();
 
@@ -1682,8 +2331,13 @@ impl<S: Storage> P2pServer<S> {
                     // he should not send it back to us if it's a block found by us
                     blocks_propagation.put(hash.clone(), if lock { Direction::Both } else { Direction::Out });
 
-                    debug!("Broadcast {} to {} (lock: {})", hash, peer, lock);
-                    if let Err(e) = peer.send_bytes(packet_block_bytes.clone()).await {
+                    debug!("Broadcast {} to {} (lock: {}, priority: {})", hash, peer, lock, priority);
+                    let res = if priority {
+                        peer.send_bytes_priority(packet_block_bytes.clone()).await
+                    } else {
+                        peer.send_bytes(packet_block_bytes.clone()).await
+                    };
+                    if let Err(e) = res {
                         debug!("Error on broadcast block {} to {}: {}", hash, peer, e);
                     }
                 } else {
@@ -1791,11 +2445,16 @@ impl<S: Storage> P2pServer<S> {
                 for topoheight in (lower..=topoheight).rev() {
                     let hash = storage.get_hash_at_topo_height(topoheight).await?;
                     let supply = storage.get_supply_at_topo_height(topoheight).await?;
+                    let burned_supply = if peer.has_protocol_version(P2P_BURNED_SUPPLY_VERSION) {
+                        Some(storage.get_burned_supply_at_topo_height(topoheight).await?)
+                    } else {
+                        None
+                    };
                     let reward = storage.get_block_reward_at_topo_height(topoheight)?;
                     let difficulty = storage.get_difficulty_for_block_hash(&hash).await?;
                     let cumulative_difficulty = storage.get_cumulative_difficulty_for_block_hash(&hash).await?;
 
-                    blocks.push(BlockMetadata { hash, supply, reward, difficulty, cumulative_difficulty });
+                    blocks.push(BlockMetadata { hash, supply, burned_supply, reward, difficulty, cumulative_difficulty });
                 }
                 StepResponse::BlocksMetadata(blocks)
             },
@@ -1973,6 +2632,12 @@ impl<S: Storage> P2pServer<S> {
                             continue;
                         }
 
+                        // we only fast sync from peers sending it
+                        let Some(burned_supply) = metadata.burned_supply else {
+                            error!("Received block metadata {} without its burned supply", metadata.hash);
+                            return Err(P2pError::InvalidPacket.into())
+                        };
+
                         lowest_topoheight = stable_topoheight - i as u64;
                         debug!("Saving block metadata {}", metadata.hash);
                         let OwnedObjectResponse::BlockHeader(header, hash) = peer.request_blocking_object(ObjectRequest::BlockHeader(metadata.hash)).await? else {
@@ -1980,22 +2645,8 @@ impl<S: Storage> P2pServer<S> {
                             return Err(P2pError::InvalidPacket.into())
                         };
 
-                        let mut txs = Vec::with_capacity(header.get_txs_hashes().len());
                         debug!("Retrieving {} txs for block {}", header.get_txs_count(), hash);
-                        for tx_hash in header.get_txs_hashes() {
-                            trace!("Retrieving TX {} for block {}", tx_hash, hash);
-                            let tx = if self.blockchain.has_tx(tx_hash).await? {
-                                Immutable::Arc(self.blockchain.get_tx(tx_hash).await?)
-                            } else {
-                                let OwnedObjectResponse::Transaction(tx, _) = peer.request_blocking_object(ObjectRequest::Transaction(tx_hash.clone())).await? else {
-                                    error!("Received an invalid requested object while fetching block transaction {}", tx_hash);
-                                    return Err(P2pError::InvalidObjectResponseType.into())
-                                };
-                                Immutable::Owned(tx)
-                            };
-                            trace!("TX {} ok", tx_hash);
-                            txs.push(tx);
-                        }
+                        let txs = self.request_block_transactions(peer, &hash, &header).await?;
 
                         // link its TX to the block
                         let mut storage = self.blockchain.get_storage().write().await;
@@ -2005,6 +2656,7 @@ impl<S: Storage> P2pServer<S> {
 
                         // save metadata of this block
                         storage.set_supply_at_topo_height(lowest_topoheight, metadata.supply)?;
+                        storage.set_burned_supply_at_topo_height(lowest_topoheight, burned_supply)?;
                         storage.set_block_reward_at_topo_height(lowest_topoheight, metadata.reward)?;
                         storage.set_topo_height_for_block(&hash, lowest_topoheight).await?;
 
@@ -2034,6 +2686,12 @@ impl<S: Storage> P2pServer<S> {
     }
 
     async fn request_inventory_of(&self, peer: &Arc<Peer>) -> Result<(), BlockchainError> {
+        // the mempool is only synced when both sides relay the transactions
+        if !self.tx_relay || !peer.get_services().is_relay() {
+            trace!("Not requesting inventory of {}, transaction relay is disabled", peer);
+            return Ok(())
+        }
+
         debug!("Requesting inventory of {}", peer);
         let packet = Cow::Owned(NotifyInventoryRequest::new(None));
         let ping = Cow::Owned(self.build_generic_ping_packet().await);
@@ -2046,9 +2704,89 @@ impl<S: Storage> P2pServer<S> {
     // we send up to CHAIN_SYNC_REQUEST_MAX_BLOCKS blocks id (combinaison of block hash and topoheight)
     // we add at the end the genesis block to be sure to be on the same chain as others peers
     // its used to find a common point with the peer to which we ask the chain
+    // request the blocks we don't have yet by batches and add them in order
+    // returns the number of blocks requested
+    async fn request_and_add_blocks(&self, peer: &Arc<Peer>, blocks: &IndexSet<Hash>) -> Result<usize, BlockchainError> {
+        let mut total_requested: usize = 0;
+        let blocks: Vec<&Hash> = blocks.iter().collect();
+        for chunk in blocks.chunks(MAX_OBJECTS_PER_REQUEST) { // Request all blocks now by batches
+            let mut requests = Vec::with_capacity(chunk.len());
+            for hash in chunk {
+                if !self.blockchain.has_block(hash).await? {
+                    trace!("Block {} is not found, asking it to {} (index = {})", hash, peer.get_outgoing_address(), total_requested + requests.len());
+                    requests.push(ObjectRequest::Block((*hash).clone()));
+                } else {
+                    trace!("Block {} is already in chain, skipping it", hash);
+                }
+            }
+
+            if requests.is_empty() {
+                continue;
+            }
+
+            for response in peer.request_blocking_objects(requests).await? {
+                if let OwnedObjectResponse::Block(block, hash) = response {
+                    trace!("Received block {} at height {} from {}", hash, block.get_height(), peer);
+                    // it may have been propagated to us in the meantime
+                    if !self.blockchain.has_block(&hash).await? {
+                        self.blockchain.add_new_block(block, false, false).await?;
+                        peer.add_blocks_synced(1);
+                    }
+                } else {
+                    error!("{} sent us an invalid block response", peer);
+                    return Err(P2pError::ExpectedBlock.into())
+                }
+                total_requested += 1;
+            }
+        }
+        Ok(total_requested)
+    }
+
+    // request the blocks left from the last chain response, saved before a restart or a failed sync
+    // the cursor is removed once done, or if it can't be used anymore so the chain is requested again
+    async fn resume_sync_cursor(&self, peer: &Arc<Peer>) -> Result<(), BlockchainError> {
+        let cursor = {
+            let storage = self.blockchain.get_storage().read().await;
+            let Some(cursor) = storage.get_sync_cursor()? else {
+                return Ok(())
+            };
+
+            // our chain may have been rewinded below the common point since
+            let common_point = cursor.get_common_point();
+            let still_common = storage.get_hash_at_topo_height(common_point.get_topoheight()).await
+                .is_ok_and(|hash| hash == *common_point.get_hash());
+            if still_common {
+                Some(cursor)
+            } else {
+                None
+            }
+        };
+
+        let Some(cursor) = cursor else {
+            debug!("Common point of the sync cursor is not in our chain anymore, removing it");
+            self.blockchain.get_storage().write().await.set_sync_cursor(None)?;
+            return Ok(())
+        };
+
+        let (common_point, pending) = cursor.consume();
+        info!("Resuming chain sync with {} from common point {} at topoheight {} ({} blocks pending)", peer, common_point.get_hash(), common_point.get_topoheight(), pending.len());
+        match self.request_and_add_blocks(peer, &pending).await {
+            Ok(total_requested) => debug!("Resumed chain sync, {} blocks requested to {}", total_requested, peer),
+            // keep it for the next peer selected
+            Err(e @ BlockchainError::ErrorOnP2p(P2pError::AsyncTimeOut(_))) => return Err(e),
+            Err(e) => warn!("Error while resuming chain sync with {}, requesting its chain: {}", peer, e)
+        };
+        self.blockchain.get_storage().write().await.set_sync_cursor(None)?;
+
+        Ok(())
+    }
+
     pub async fn request_sync_chain_for(&self, peer: &Arc<Peer>) -> Result<(), BlockchainError> {
         trace!("Requesting chain from {}", peer);
 
+        // finish the blocks of the previous chain response first
+        self.resume_sync_cursor(peer).await?;
+
         // This can be configured by the node operator, it will be adjusted between protocol bounds
         // and based on peer configuration
         // This will allow to boost-up syncing for those who want and can be used to use low resources for low devices
@@ -24,15 +24,22 @@ pub const MAX_ITEMS_PER_PAGE: usize = 1024;
 pub struct BlockMetadata {
     pub hash: Hash,
     pub supply: u64,
+    // not sent to the peers older than P2P_BURNED_SUPPLY_VERSION
+    pub burned_supply: Option<u64>,
     pub reward: u64,
     pub difficulty: Difficulty,
     pub cumulative_difficulty: Difficulty
 }
 
-impl Serializer for BlockMetadata {
-    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+impl BlockMetadata {
+    fn read(reader: &mut Reader, with_burned_supply: bool) -> Result<Self, ReaderError> {
         let hash = reader.read_hash()?;
         let supply = reader.read_u64()?;
+        let burned_supply = if with_burned_supply {
+            Some(reader.read_u64()?)
+        } else {
+            None
+        };
         let reward = reader.read_u64()?;
         let difficulty = Difficulty::read(reader)?;
         let cumulative_difficulty = Difficulty::read(reader)?;
@@ -40,6 +47,7 @@ impl Serializer for BlockMetadata {
         Ok(Self {
             hash,
             supply,
+            burned_supply,
             reward,
             difficulty,
             cumulative_difficulty
@@ -49,12 +57,29 @@ impl Serializer for BlockMetadata {
     fn write(&self, writer: &mut Writer) {
         writer.write_hash(&self.hash);
         writer.write_u64(&self.supply);
+        if let Some(burned_supply) = &self.burned_supply {
+            writer.write_u64(burned_supply);
+        }
         writer.write_u64(&self.reward);
         self.difficulty.write(writer);
         self.cumulative_difficulty.write(writer);
     }
 }
 
+fn read_blocks_metadata(reader: &mut Reader, with_burned_supply: bool) -> Result<Vec<BlockMetadata>, ReaderError> {
+    let count = reader.read_u16()?;
+    if count as usize > MAX_ITEMS_PER_PAGE {
+        debug!("Received {} blocks metadata while maximum is set to {}", count, MAX_ITEMS_PER_PAGE);
+        return Err(ReaderError::InvalidSize)
+    }
+
+    let mut blocks = Vec::with_capacity(reader.bounded_capacity(count as usize));
+    for _ in 0..count {
+        blocks.push(BlockMetadata::read(reader, with_burned_supply)?);
+    }
+    Ok(blocks)
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd)]
 pub enum StepKind {
     ChainInfo,
@@ -291,8 +316,12 @@ impl Serializer for StepResponse {
             4 => {
                 Self::Nonces(Vec::<u64>::read(reader)?)
             },
+            // sent by the peers older than P2P_BURNED_SUPPLY_VERSION
             5 => {
-                Self::BlocksMetadata(Vec::<BlockMetadata>::read(reader)?)
+                Self::BlocksMetadata(read_blocks_metadata(reader, false)?)
+            },
+            6 => {
+                Self::BlocksMetadata(read_blocks_metadata(reader, true)?)
             },
             id => {
                 debug!("Received invalid value for StepResponse: {}", id);
@@ -329,8 +358,14 @@ impl Serializer for StepResponse {
                 nonces.write(writer);
             },
             Self::BlocksMetadata(blocks) => {
-                writer.write_u8(5);
-                blocks.write(writer);
+                // the burned supply is set on all blocks or on none of them
+                // an empty list is sent in the old format so any peer can read it
+                let with_burned_supply = !blocks.is_empty() && blocks.iter().all(|block| block.burned_supply.is_some());
+                writer.write_u8(if with_burned_supply { 6 } else { 5 });
+                writer.write_u16(blocks.len() as u16);
+                for block in blocks {
+                    block.write(writer);
+                }
             }
         };
     }
@@ -397,3 +432,43 @@ impl Serializer for BootstrapChainResponse {
         self.response.write(writer);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _create_metadata(burned_supply: Option<u64>) -> BlockMetadata {
+        BlockMetadata {
+            hash: Hash::zero(),
+            supply: 100,
+            burned_supply,
+            reward: 10,
+            difficulty: 1,
+            cumulative_difficulty: 2
+        }
+    }
+
+    #[test]
+    fn test_blocks_metadata_burned_supply() {
+        let response = StepResponse::BlocksMetadata(vec![_create_metadata(Some(5)), _create_metadata(Some(6))]);
+        let bytes = response.to_bytes();
+        assert_eq!(bytes[0], 6);
+        let StepResponse::BlocksMetadata(blocks) = StepResponse::from_bytes(&bytes).unwrap() else {
+            panic!("invalid step response")
+        };
+        assert_eq!(blocks.iter().map(|block| block.burned_supply).collect::<Vec<_>>(), vec![Some(5), Some(6)]);
+
+        // older peers receive the blocks metadata without the burned supply
+        let response = StepResponse::BlocksMetadata(vec![_create_metadata(None)]);
+        let bytes = response.to_bytes();
+        assert_eq!(bytes[0], 5);
+        let StepResponse::BlocksMetadata(blocks) = StepResponse::from_bytes(&bytes).unwrap() else {
+            panic!("invalid step response")
+        };
+        assert_eq!(blocks.len(), 1);
+        assert!(blocks[0].burned_supply.is_none());
+        assert_eq!(blocks[0].reward, 10);
+
+        assert_eq!(StepResponse::BlocksMetadata(Vec::new()).to_bytes()[0], 5);
+    }
+}
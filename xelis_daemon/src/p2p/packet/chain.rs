@@ -1,7 +1,7 @@
 use indexmap::IndexSet;
 use log::debug;
 use xelis_common::{
-    crypto::hash::Hash,
+    crypto::hash::{Hash, HASH_SIZE},
     serializer::{
         Serializer,
         Writer,
@@ -204,6 +204,7 @@ impl Serializer for ChainResponse {
             return Err(ReaderError::InvalidValue)
         }
 
+        reader.check_count(len as usize, HASH_SIZE)?;
         let mut blocks: IndexSet<Hash> = IndexSet::with_capacity(len as usize); 
         for _ in 0..len {
             let hash = reader.read_hash()?;
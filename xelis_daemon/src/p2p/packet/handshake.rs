@@ -1,7 +1,10 @@
 use log::debug;
 use xelis_common::{
+    api::daemon::NodeService,
     serializer::{Serializer, Writer, ReaderError, Reader},
-    crypto::hash::Hash, network::Network, block::Difficulty
+    crypto::{hash::{Hash, hash}, key::{KeyPair, PublicKey, Signature}},
+    network::Network,
+    block::Difficulty
 };
 
 use crate::p2p::peer_list::SharedPeerList;
@@ -10,6 +13,64 @@ use crate::p2p::peer::Peer;
 use std::collections::HashSet;
 use std::fmt::{Display, Error, Formatter};
 
+// services offered by a node to its peers, advertised in its handshake
+// unknown bits are kept so newer services can be added without breaking older nodes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NodeServices(u8);
+
+impl NodeServices {
+    // all the blocks since genesis are stored and served
+    pub const ARCHIVE: u8 = 1 << 0;
+    // every transaction and the blocks including it, orphaned ones too, can be retrieved by hash
+    pub const TX_INDEX: u8 = 1 << 1;
+    // transactions are relayed to and accepted from the peers
+    pub const RELAY: u8 = 1 << 2;
+
+    pub fn new(flags: u8) -> Self {
+        Self(flags)
+    }
+
+    pub fn has(&self, service: u8) -> bool {
+        self.0 & service == service
+    }
+
+    pub fn is_archive(&self) -> bool {
+        self.has(Self::ARCHIVE)
+    }
+
+    pub fn has_tx_index(&self) -> bool {
+        self.has(Self::TX_INDEX)
+    }
+
+    pub fn is_relay(&self) -> bool {
+        self.has(Self::RELAY)
+    }
+
+    pub fn to_list(&self) -> Vec<NodeService> {
+        let mut services = Vec::new();
+        if self.is_archive() {
+            services.push(NodeService::Archive);
+        }
+        if self.has_tx_index() {
+            services.push(NodeService::TxIndex);
+        }
+        if self.is_relay() {
+            services.push(NodeService::Relay);
+        }
+        services
+    }
+}
+
+impl Serializer for NodeServices {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(self.0);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(Self(reader.read_u8()?))
+    }
+}
+
 // this Handshake is the first data sent when connecting to the server
 // If handshake is valid, server reply with his own handshake
 // We just have to repeat this request to all peers until we reach max connection
@@ -17,6 +78,7 @@ use std::fmt::{Display, Error, Formatter};
 #[derive(Clone, Debug)]
 pub struct Handshake {
     version: String, // daemon version
+    protocol_version: u8, // P2P protocol version, packets added after it are not sent to this node
     network: Network,
     node_tag: Option<String>, // node tag
     network_id: [u8; 16],
@@ -29,19 +91,24 @@ pub struct Handshake {
     top_hash: Hash, // current block top hash
     genesis_hash: Hash, // genesis hash
     cumulative_difficulty: Difficulty,
+    node_key: PublicKey, // persistent identity of the node
+    services: NodeServices, // services offered to the peers
+    prune_depth: Option<u64>, // number of blocks kept below the top if the node prunes automatically
+    signature: Signature // signature of all fields above by the node key
 } // Server reply with his own list of peers, but we remove all already known by requester for the response.
 
 impl Handshake {
     pub const MAX_LEN: usize = 16;
 
-    pub fn new(version: String, network: Network, node_tag: Option<String>, network_id: [u8; 16], peer_id: u64, local_port: u16, utc_time: u64, topoheight: u64, height: u64, pruned_topoheight: Option<u64>, top_hash: Hash, genesis_hash: Hash, cumulative_difficulty: Difficulty) -> Self {
+    pub fn new(version: String, protocol_version: u8, network: Network, node_tag: Option<String>, network_id: [u8; 16], peer_id: u64, local_port: u16, utc_time: u64, topoheight: u64, height: u64, pruned_topoheight: Option<u64>, top_hash: Hash, genesis_hash: Hash, cumulative_difficulty: Difficulty, services: NodeServices, prune_depth: Option<u64>, keypair: &KeyPair) -> Self {
         debug_assert!(version.len() > 0 && version.len() <= Handshake::MAX_LEN); // version cannot be greater than 16 chars
         if let Some(node_tag) = &node_tag {
             debug_assert!(node_tag.len() > 0 && node_tag.len() <= Handshake::MAX_LEN); // node tag cannot be greater than 16 chars
         }
 
-        Self {
+        let mut handshake = Self {
             version,
+            protocol_version,
             network,
             node_tag,
             network_id,
@@ -53,19 +120,46 @@ impl Handshake {
             pruned_topoheight,
             top_hash,
             genesis_hash,
-            cumulative_difficulty
-        }
+            cumulative_difficulty,
+            node_key: keypair.get_public_key().clone(),
+            services,
+            prune_depth,
+            // replaced below once all the signed fields are set
+            signature: keypair.sign(&[])
+        };
+        handshake.signature = keypair.sign(handshake.get_signing_hash().as_bytes());
+        handshake
     }
 
-    pub fn create_peer(self, connection: Connection, out: bool, priority: bool, peer_list: SharedPeerList) -> Peer {
+    pub fn create_peer(self, connection: Connection, out: bool, priority: bool, trusted: bool, peer_list: SharedPeerList) -> Peer {
         let peers = HashSet::new();
-        Peer::new(connection, self.get_peer_id(), self.node_tag, self.local_port, self.version, self.top_hash, self.topoheight, self.height, self.pruned_topoheight, out, priority, self.cumulative_difficulty, peer_list, peers)
+        Peer::new(connection, self.get_peer_id(), self.node_tag, self.local_port, self.version, self.protocol_version, self.top_hash, self.topoheight, self.height, self.pruned_topoheight, out, priority, trusted, self.node_key, self.cumulative_difficulty, self.services, self.prune_depth, peer_list, peers)
+    }
+
+    // hash of all the fields signed by the node key
+    fn get_signing_hash(&self) -> Hash {
+        let mut writer = Writer::new();
+        self.write_unsigned(&mut writer);
+        hash(writer.as_bytes())
+    }
+
+    // check that the handshake has been signed by its node key
+    pub fn verify_signature(&self) -> bool {
+        self.node_key.verify_signature(&self.get_signing_hash(), &self.signature)
+    }
+
+    pub fn get_node_key(&self) -> &PublicKey {
+        &self.node_key
     }
 
     pub fn get_version(&self) -> &String {
         &self.version
     }
 
+    pub fn get_protocol_version(&self) -> u8 {
+        self.protocol_version
+    }
+
     pub fn get_network(&self) -> &Network {
         &self.network
     }
@@ -105,14 +199,24 @@ impl Handshake {
     pub fn get_pruned_topoheight(&self) -> &Option<u64> {
         &self.pruned_topoheight
     }
+
+    pub fn get_services(&self) -> NodeServices {
+        self.services
+    }
+
+    pub fn get_prune_depth(&self) -> Option<u64> {
+        self.prune_depth
+    }
 }
 
-impl Serializer for Handshake {
-    // 1 + MAX(16) + 1 + MAX(16) + 16 + 8 + 8 + 8 + 32 + 1 + 24 * 16
-    fn write(&self, writer: &mut Writer) {
+impl Handshake {
+    fn write_unsigned(&self, writer: &mut Writer) {
         // daemon version
         writer.write_string(&self.version);
 
+        // P2P protocol version
+        writer.write_u8(self.protocol_version);
+
         // network
         self.network.write(writer);
 
@@ -129,6 +233,17 @@ impl Serializer for Handshake {
         writer.write_hash(&self.top_hash); // Block Top Hash (32 bytes)
         writer.write_hash(&self.genesis_hash); // Genesis Hash
         self.cumulative_difficulty.write(writer); // Cumulative Difficulty
+        self.node_key.write(writer); // Node Key
+        self.services.write(writer); // Services
+        self.prune_depth.write(writer); // Prune Depth
+    }
+}
+
+impl Serializer for Handshake {
+    // 1 + MAX(16) + 1 + 1 + MAX(16) + 16 + 8 + 8 + 8 + 32 + 1 + 24 * 16 + 32 + 1 + 9 + 64
+    fn write(&self, writer: &mut Writer) {
+        self.write_unsigned(writer);
+        self.signature.write(writer); // Signature of all fields above
     }
 
     fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
@@ -142,6 +257,9 @@ impl Serializer for Handshake {
             return Err(ReaderError::InvalidSize)
         }
 
+        // P2P protocol version
+        let protocol_version = reader.read_u8()?;
+
         // Network
         let network = Network::read(reader)?;
 
@@ -169,8 +287,35 @@ impl Serializer for Handshake {
         let top_hash = reader.read_hash()?;
         let genesis_hash = reader.read_hash()?;
         let cumulative_difficulty = Difficulty::read(reader)?;
+        let node_key = PublicKey::read(reader)?;
+        let services = NodeServices::read(reader)?;
+        let prune_depth = Option::read(reader)?;
+        if prune_depth == Some(0) {
+            debug!("Invalid prune depth (0) in handshake packet");
+            return Err(ReaderError::InvalidValue)
+        }
+        let signature = Signature::read(reader)?;
 
-        Ok(Handshake::new(version, network, node_tag, network_id, peer_id, local_port, utc_time, topoheight, height, pruned_topoheight, top_hash, genesis_hash, cumulative_difficulty))
+        Ok(Self {
+            version,
+            protocol_version,
+            network,
+            node_tag,
+            network_id,
+            peer_id,
+            local_port,
+            utc_time,
+            topoheight,
+            height,
+            pruned_topoheight,
+            top_hash,
+            genesis_hash,
+            cumulative_difficulty,
+            node_key,
+            services,
+            prune_depth,
+            signature
+        })
     }
 }
 
@@ -2,7 +2,7 @@ use std::borrow::Cow;
 
 use indexmap::IndexSet;
 use log::debug;
-use xelis_common::{crypto::hash::Hash, serializer::{Serializer, ReaderError, Reader, Writer}};
+use xelis_common::{crypto::hash::{Hash, HASH_SIZE}, serializer::{Serializer, ReaderError, Reader, Writer}};
 
 pub const NOTIFY_MAX_LEN: usize = 16384; // 16384 * 32 bytes = 512 KiB
 
@@ -65,6 +65,7 @@ impl<'a> Serializer for NotifyInventoryResponse<'a> {
             return Err(ReaderError::InvalidSize);
         }
  
+        reader.check_count(count as usize, HASH_SIZE)?;
         let mut txs = IndexSet::with_capacity(count as usize);
         for _ in 0..count {
             if !txs.insert(Cow::Owned(reader.read_hash()?)) {
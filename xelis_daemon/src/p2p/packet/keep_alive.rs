@@ -0,0 +1,31 @@
+use xelis_common::serializer::{Serializer, Reader, ReaderError, Writer};
+
+// this packet is sent periodically to check that the connection is still alive
+// the peer must reply with the same sequence number so we can compute the round trip time
+#[derive(Debug)]
+pub struct KeepAlive {
+    sequence: u64
+}
+
+impl KeepAlive {
+    pub fn new(sequence: u64) -> Self {
+        Self {
+            sequence
+        }
+    }
+
+    pub fn get_sequence(&self) -> u64 {
+        self.sequence
+    }
+}
+
+impl Serializer for KeepAlive {
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let sequence = reader.read_u64()?;
+        Ok(Self::new(sequence))
+    }
+
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u64(&self.sequence);
+    }
+}
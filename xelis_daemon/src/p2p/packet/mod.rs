@@ -5,21 +5,32 @@ pub mod object;
 pub mod inventory;
 pub mod bootstrap_chain;
 pub mod peer_disconnected;
+pub mod keep_alive;
 
 use self::bootstrap_chain::{BootstrapChainRequest, BootstrapChainResponse};
 use self::inventory::{NotifyInventoryResponse, NotifyInventoryRequest};
-use self::object::{ObjectRequest, ObjectResponse};
+use self::object::{ObjectRequest, ObjectResponse, ObjectsRequest, ObjectsResponse};
 use self::chain::{ChainRequest, ChainResponse};
 use self::handshake::Handshake;
 use self::peer_disconnected::PacketPeerDisconnected;
+use self::keep_alive::KeepAlive;
 use self::ping::Ping;
 use std::borrow::Cow;
 use log::{trace, error};
 use xelis_common::{
     serializer::{Serializer, Reader, ReaderError, Writer},
     block::BlockHeader,
-    crypto::hash::Hash
+    crypto::hash::{Hash, HASH_SIZE}
 };
+use crate::config::{
+    CHAIN_SYNC_REQUEST_MAX_BLOCKS,
+    MAX_BLOCK_SIZE,
+    P2P_PING_PEER_LIST_LIMIT,
+    P2P_OBJECTS_BATCH_VERSION,
+    P2P_KEEP_ALIVE_VERSION
+};
+use self::object::MAX_OBJECTS_PER_REQUEST;
+use super::{error::P2pError, transport::TrafficClass};
 
 // All registered packet ids
 const HANDSHAKE_ID: u8 = 0;
@@ -35,6 +46,103 @@ const NOTIFY_INV_RESPONSE_ID: u8 = 9;
 const BOOTSTRAP_CHAIN_REQUEST_ID: u8 = 10;
 const BOOTSTRAP_CHAIN_RESPONSE_ID: u8 = 11;
 const PEER_DISCONNECTED_ID: u8 = 12;
+const OBJECTS_REQUEST_ID: u8 = 13;
+const OBJECTS_RESPONSE_ID: u8 = 14;
+const KEEP_ALIVE_REQUEST_ID: u8 = 15;
+const KEEP_ALIVE_RESPONSE_ID: u8 = 16;
+
+// names of the packets indexed by their id, used in logs and metrics
+pub const PACKET_NAMES: [&str; 17] = [
+    "handshake",
+    "transaction_propagation",
+    "block_propagation",
+    "chain_request",
+    "chain_response",
+    "ping",
+    "object_request",
+    "object_response",
+    "notify_inventory_request",
+    "notify_inventory_response",
+    "bootstrap_chain_request",
+    "bootstrap_chain_response",
+    "peer_disconnected",
+    "objects_request",
+    "objects_response",
+    "keep_alive_request",
+    "keep_alive_response"
+];
+
+// IPv6 + port
+const ADDRESS_MAX_SIZE: usize = 1 + 16 + 2;
+// top hash, topoheight, height, pruned topoheight, cumulative difficulty and the peer list
+const PING_MAX_SIZE: usize = HASH_SIZE + 8 + 8 + 9 + 8 + 1 + P2P_PING_PEER_LIST_LIMIT * ADDRESS_MAX_SIZE;
+// the handshake is also read using a buffer of this size
+pub const HANDSHAKE_MAX_SIZE: usize = 512;
+
+// maximum size of a packet including its id, none if the id is unknown
+// it is verified before decoding the packet, so a small packet type can't be used to send a big payload
+// packets containing blocks, transactions or lists of hashes are only limited by the maximum block size
+pub fn get_max_packet_size(id: u8) -> Option<usize> {
+    let size = match id {
+        HANDSHAKE_ID => HANDSHAKE_MAX_SIZE,
+        TX_PROPAGATION_ID => 1 + HASH_SIZE + PING_MAX_SIZE,
+        // id count, block ids (hash + topoheight) and the accepted response size
+        CHAIN_REQUEST_ID => 1 + 1 + CHAIN_SYNC_REQUEST_MAX_BLOCKS * (HASH_SIZE + 8) + 2 + PING_MAX_SIZE,
+        PING_ID => 1 + PING_MAX_SIZE,
+        OBJECT_REQUEST_ID => 1 + 1 + HASH_SIZE,
+        NOTIFY_INV_REQUEST_ID => 1 + 1 + PING_MAX_SIZE,
+        PEER_DISCONNECTED_ID => 1 + ADDRESS_MAX_SIZE,
+        OBJECTS_REQUEST_ID => 1 + 8 + 1 + MAX_OBJECTS_PER_REQUEST * (1 + HASH_SIZE),
+        KEEP_ALIVE_REQUEST_ID | KEEP_ALIVE_RESPONSE_ID => 1 + 8,
+        BLOCK_PROPAGATION_ID
+        | CHAIN_RESPONSE_ID
+        | OBJECT_RESPONSE_ID
+        | NOTIFY_INV_RESPONSE_ID
+        | BOOTSTRAP_CHAIN_REQUEST_ID
+        | BOOTSTRAP_CHAIN_RESPONSE_ID
+        | OBJECTS_RESPONSE_ID => MAX_BLOCK_SIZE,
+        _ => return None
+    };
+    Some(size)
+}
+
+// minimum P2P protocol version of a peer to exchange this packet with it
+// packets added after the first protocol version are only sent to the peers advertising them
+pub fn get_packet_protocol_version(id: u8) -> u8 {
+    match id {
+        OBJECTS_REQUEST_ID | OBJECTS_RESPONSE_ID => P2P_OBJECTS_BATCH_VERSION,
+        KEEP_ALIVE_REQUEST_ID | KEEP_ALIVE_RESPONSE_ID => P2P_KEEP_ALIVE_VERSION,
+        _ => 0
+    }
+}
+
+// traffic class of a packet starting with its id, to select the stream used to send it
+// the transactions propagated and their requests don't wait behind the blocks of the chain sync
+pub fn get_traffic_class(packet: &[u8]) -> TrafficClass {
+    match packet {
+        [TX_PROPAGATION_ID | NOTIFY_INV_REQUEST_ID | NOTIFY_INV_RESPONSE_ID, ..] => TrafficClass::Transactions,
+        // object type of the request or response, or of the object not found
+        [OBJECT_REQUEST_ID | OBJECT_RESPONSE_ID, 2, ..] | [OBJECT_RESPONSE_ID, 3, 2, ..] => TrafficClass::Transactions,
+        _ => TrafficClass::Chain
+    }
+}
+
+// decode a packet received, all the bytes must be used
+// this is the only entry point of the decoder for untrusted bytes, it must never panic
+pub fn parse_packet<'a>(bytes: &[u8]) -> Result<Packet<'a>, P2pError> {
+    let id = *bytes.first().ok_or(P2pError::InvalidPacketSize)?;
+    let max_size = get_max_packet_size(id).ok_or(P2pError::InvalidPacket)?;
+    if bytes.len() > max_size {
+        return Err(P2pError::InvalidPacketSize)
+    }
+
+    let mut reader = Reader::new(bytes);
+    let packet = Packet::read(&mut reader)?;
+    if reader.total_read() != bytes.len() {
+        return Err(P2pError::InvalidPacketNotFullRead)
+    }
+    Ok(packet)
+}
 
 // PacketWrapper allows us to link any Packet to a Ping
 #[derive(Debug)]
@@ -89,7 +197,41 @@ pub enum Packet<'a> {
     NotifyInventoryResponse(NotifyInventoryResponse<'a>),
     BootstrapChainRequest(BootstrapChainRequest<'a>),
     BootstrapChainResponse(BootstrapChainResponse),
-    PeerDisconnected(PacketPeerDisconnected)
+    PeerDisconnected(PacketPeerDisconnected),
+    // batched version of ObjectRequest / ObjectResponse
+    ObjectsRequest(Cow<'a, ObjectsRequest>),
+    ObjectsResponse(ObjectsResponse<'a>),
+    // application-level ping / pong to detect dead connections
+    KeepAliveRequest(KeepAlive),
+    KeepAliveResponse(KeepAlive)
+}
+
+impl Packet<'_> {
+    pub fn get_id(&self) -> u8 {
+        match self {
+            Packet::Handshake(_) => HANDSHAKE_ID,
+            Packet::TransactionPropagation(_) => TX_PROPAGATION_ID,
+            Packet::BlockPropagation(_) => BLOCK_PROPAGATION_ID,
+            Packet::ChainRequest(_) => CHAIN_REQUEST_ID,
+            Packet::ChainResponse(_) => CHAIN_RESPONSE_ID,
+            Packet::Ping(_) => PING_ID,
+            Packet::ObjectRequest(_) => OBJECT_REQUEST_ID,
+            Packet::ObjectResponse(_) => OBJECT_RESPONSE_ID,
+            Packet::NotifyInventoryRequest(_) => NOTIFY_INV_REQUEST_ID,
+            Packet::NotifyInventoryResponse(_) => NOTIFY_INV_RESPONSE_ID,
+            Packet::BootstrapChainRequest(_) => BOOTSTRAP_CHAIN_REQUEST_ID,
+            Packet::BootstrapChainResponse(_) => BOOTSTRAP_CHAIN_RESPONSE_ID,
+            Packet::PeerDisconnected(_) => PEER_DISCONNECTED_ID,
+            Packet::ObjectsRequest(_) => OBJECTS_REQUEST_ID,
+            Packet::ObjectsResponse(_) => OBJECTS_RESPONSE_ID,
+            Packet::KeepAliveRequest(_) => KEEP_ALIVE_REQUEST_ID,
+            Packet::KeepAliveResponse(_) => KEEP_ALIVE_RESPONSE_ID
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        PACKET_NAMES[self.get_id() as usize]
+    }
 }
 
 impl<'a> Serializer for Packet<'a> {
@@ -110,6 +252,10 @@ impl<'a> Serializer for Packet<'a> {
             BOOTSTRAP_CHAIN_REQUEST_ID => Packet::BootstrapChainRequest(BootstrapChainRequest::read(reader)?),
             BOOTSTRAP_CHAIN_RESPONSE_ID => Packet::BootstrapChainResponse(BootstrapChainResponse::read(reader)?),
             PEER_DISCONNECTED_ID => Packet::PeerDisconnected(PacketPeerDisconnected::read(reader)?),
+            OBJECTS_REQUEST_ID => Packet::ObjectsRequest(Cow::Owned(ObjectsRequest::read(reader)?)),
+            OBJECTS_RESPONSE_ID => Packet::ObjectsResponse(ObjectsResponse::read(reader)?),
+            KEEP_ALIVE_REQUEST_ID => Packet::KeepAliveRequest(KeepAlive::read(reader)?),
+            KEEP_ALIVE_RESPONSE_ID => Packet::KeepAliveResponse(KeepAlive::read(reader)?),
             id => {
                 error!("invalid packet id received: {}", id);
                 return Err(ReaderError::InvalidValue)
@@ -131,7 +277,11 @@ impl<'a> Serializer for Packet<'a> {
             Packet::NotifyInventoryResponse(inventory) => (NOTIFY_INV_RESPONSE_ID, inventory),
             Packet::BootstrapChainRequest(request) => (BOOTSTRAP_CHAIN_REQUEST_ID, request),
             Packet::BootstrapChainResponse(response) => (BOOTSTRAP_CHAIN_RESPONSE_ID, response),
-            Packet::PeerDisconnected(disconnected) => (PEER_DISCONNECTED_ID, disconnected)
+            Packet::PeerDisconnected(disconnected) => (PEER_DISCONNECTED_ID, disconnected),
+            Packet::ObjectsRequest(request) => (OBJECTS_REQUEST_ID, request.as_ref()),
+            Packet::ObjectsResponse(response) => (OBJECTS_RESPONSE_ID, response),
+            Packet::KeepAliveRequest(keep_alive) => (KEEP_ALIVE_REQUEST_ID, keep_alive),
+            Packet::KeepAliveResponse(keep_alive) => (KEEP_ALIVE_RESPONSE_ID, keep_alive)
         };
 
         let packet = serializer.to_bytes();
@@ -140,4 +290,28 @@ impl<'a> Serializer for Packet<'a> {
         writer.write_u8(id);
         writer.write_bytes(&packet);
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use xelis_common::{crypto::hash::Hash, serializer::Serializer};
+    use super::{get_traffic_class, keep_alive::KeepAlive, object::{ObjectRequest, ObjectResponse}, Packet, TrafficClass};
+
+    fn _class(packet: Packet) -> TrafficClass {
+        // skip the size prefix
+        get_traffic_class(&packet.to_bytes()[4..])
+    }
+
+    #[test]
+    fn test_traffic_class() {
+        let hash = Hash::zero();
+        assert_eq!(_class(Packet::ObjectRequest(Cow::Owned(ObjectRequest::Transaction(hash.clone())))), TrafficClass::Transactions);
+        assert_eq!(_class(Packet::ObjectResponse(ObjectResponse::NotFound(ObjectRequest::Transaction(hash.clone())))), TrafficClass::Transactions);
+
+        assert_eq!(_class(Packet::ObjectRequest(Cow::Owned(ObjectRequest::Block(hash.clone())))), TrafficClass::Chain);
+        assert_eq!(_class(Packet::ObjectResponse(ObjectResponse::NotFound(ObjectRequest::BlockHeader(hash)))), TrafficClass::Chain);
+        assert_eq!(_class(Packet::KeepAliveRequest(KeepAlive::new(0))), TrafficClass::Chain);
+        assert_eq!(get_traffic_class(&[]), TrafficClass::Chain);
+    }
 }
\ No newline at end of file
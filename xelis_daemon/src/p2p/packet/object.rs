@@ -12,7 +12,11 @@ use xelis_common::{
         Writer
     },
 };
-use std::{borrow::Cow, fmt::{Display, Formatter, self}};
+use std::{borrow::Cow, collections::HashSet, fmt::{Display, Formatter, self}};
+use log::debug;
+
+// Maximum objects that can be requested in one batch
+pub const MAX_OBJECTS_PER_REQUEST: usize = 64;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum ObjectRequest {
@@ -169,3 +173,115 @@ impl<'a> Serializer for ObjectResponse<'a> {
         })
     }
 }
+
+// Request multiple objects in one round trip
+// id is sent back in the response to link it to this request
+#[derive(Clone, Debug)]
+pub struct ObjectsRequest {
+    id: u64,
+    requests: Vec<ObjectRequest>
+}
+
+impl ObjectsRequest {
+    pub fn new(id: u64, requests: Vec<ObjectRequest>) -> Self {
+        debug_assert!(requests.len() <= MAX_OBJECTS_PER_REQUEST);
+        Self {
+            id,
+            requests
+        }
+    }
+
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn get_requests(&self) -> &Vec<ObjectRequest> {
+        &self.requests
+    }
+
+    pub fn consume(self) -> (u64, Vec<ObjectRequest>) {
+        (self.id, self.requests)
+    }
+}
+
+impl Serializer for ObjectsRequest {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u64(&self.id);
+        writer.write_u8(self.requests.len() as u8);
+        for request in &self.requests {
+            request.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let id = reader.read_u64()?;
+        let count = reader.read_u8()? as usize;
+        if count == 0 || count > MAX_OBJECTS_PER_REQUEST {
+            return Err(ReaderError::InvalidSize)
+        }
+
+        let mut requests = Vec::with_capacity(count);
+        let mut unique = HashSet::with_capacity(count);
+        for _ in 0..count {
+            let request = ObjectRequest::read(reader)?;
+            if !unique.insert(request.clone()) {
+                debug!("Duplicate object request {} in ObjectsRequest", request);
+                return Err(ReaderError::InvalidValue)
+            }
+            requests.push(request);
+        }
+
+        Ok(Self::new(id, requests))
+    }
+}
+
+// Response to a ObjectsRequest
+// It can be partial if all the objects requested don't fit in one packet,
+// objects not present in it should be requested again
+#[derive(Debug)]
+pub struct ObjectsResponse<'a> {
+    id: u64,
+    responses: Vec<ObjectResponse<'a>>
+}
+
+impl<'a> ObjectsResponse<'a> {
+    pub fn new(id: u64, responses: Vec<ObjectResponse<'a>>) -> Self {
+        Self {
+            id,
+            responses
+        }
+    }
+
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn consume(self) -> (u64, Vec<ObjectResponse<'a>>) {
+        (self.id, self.responses)
+    }
+}
+
+impl<'a> Serializer for ObjectsResponse<'a> {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u64(&self.id);
+        writer.write_u8(self.responses.len() as u8);
+        for response in &self.responses {
+            response.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let id = reader.read_u64()?;
+        let count = reader.read_u8()? as usize;
+        if count > MAX_OBJECTS_PER_REQUEST {
+            return Err(ReaderError::InvalidSize)
+        }
+
+        let mut responses = Vec::with_capacity(count);
+        for _ in 0..count {
+            responses.push(ObjectResponse::read(reader)?);
+        }
+
+        Ok(Self::new(id, responses))
+    }
+}
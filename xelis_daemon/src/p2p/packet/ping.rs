@@ -11,7 +11,7 @@ use xelis_common::{
         ip_from_bytes
     },
     block::Difficulty,
-    api::daemon::{NotifyEvent, PeerPeerListUpdatedEvent, Direction}
+    api::daemon::{DaemonEvent, NotifyEvent, PeerPeerListUpdatedEvent, Direction}
 };
 use crate::{
     p2p::{peer::Peer, error::P2pError},
@@ -81,7 +81,7 @@ impl<'a> Ping<'a> {
         trace!("Locking RPC Server to notify PeerStateUpdated event");
         if let Some(rpc) = blockchain.get_rpc().read().await.as_ref() {
             if rpc.is_event_tracked(&NotifyEvent::PeerStateUpdated).await {
-                rpc.notify_clients_with(&NotifyEvent::PeerStateUpdated, get_peer_entry(peer).await).await;
+                rpc.notify_clients(&DaemonEvent::PeerStateUpdated(get_peer_entry(peer).await)).await;
             }
         }
         trace!("End locking for PeerStateUpdated event");
@@ -117,7 +117,7 @@ impl<'a> Ping<'a> {
                         peer_id: peer.get_id(),
                         peerlist: self.peer_list
                     };
-                    rpc.notify_clients_with(&NotifyEvent::PeerPeerListUpdated, value).await;
+                    rpc.notify_clients(&DaemonEvent::PeerPeerListUpdated(value)).await;
                 }
             }
             trace!("End locking for PeerPeerListUpdated event");
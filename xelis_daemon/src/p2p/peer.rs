@@ -1,21 +1,26 @@
+use indexmap::IndexSet;
 use lru::LruCache;
 use xelis_common::api::daemon::Direction;
 use xelis_common::block::Difficulty;
 use crate::config::{
-    PEER_FAIL_TIME_RESET, STABLE_LIMIT, TIPS_LIMIT, PEER_TIMEOUT_BOOTSTRAP_STEP, PEER_TIMEOUT_REQUEST_OBJECT, CHAIN_SYNC_TIMEOUT_SECS
+    PEER_FAIL_TIME_RESET, PEER_FAIL_LIMIT, PEER_TRUSTED_FAIL_LIMIT, STABLE_LIMIT, TIPS_LIMIT, PEER_TIMEOUT_BOOTSTRAP_STEP, PEER_TIMEOUT_REQUEST_OBJECT, CHAIN_SYNC_TIMEOUT_SECS,
+    P2P_OBJECTS_BATCH_VERSION
 };
 use crate::p2p::packet::PacketWrapper;
-use xelis_common::utils::get_current_time;
+use xelis_common::utils::{get_current_time, get_current_timestamp};
 use xelis_common::{
-    crypto::hash::Hash,
+    crypto::{hash::Hash, key::PublicKey},
     serializer::Serializer
 };
 use super::packet::bootstrap_chain::{StepRequest, BootstrapChainRequest, StepResponse};
 use super::packet::chain::{ChainRequest, ChainResponse};
-use super::packet::object::{ObjectRequest, OwnedObjectResponse};
+use super::packet::object::{ObjectRequest, OwnedObjectResponse, ObjectsRequest, MAX_OBJECTS_PER_REQUEST};
 use super::peer_list::SharedPeerList;
 use super::connection::{Connection, ConnectionMessage};
-use super::packet::Packet;
+use super::packet::{get_traffic_class, Packet};
+use super::transport::TrafficClass;
+use super::packet::keep_alive::KeepAlive;
+use super::packet::handshake::NodeServices;
 use super::error::P2pError;
 use std::net::{SocketAddr, IpAddr};
 use std::sync::atomic::{AtomicU8, AtomicU64, AtomicBool, Ordering};
@@ -31,14 +36,21 @@ use log::{warn, trace, debug};
 
 pub type RequestedObjects = HashMap<ObjectRequest, Sender<OwnedObjectResponse>>;
 
+pub type RequestedObjectsBatches = HashMap<u64, Sender<Vec<OwnedObjectResponse>>>;
+
 pub struct Peer {
     connection: Connection, // Connection of the peer to manage read/write to TCP Stream
     id: u64, // unique ID of the peer to recognize him
     node_tag: Option<String>, // Node tag if provided
     local_port: u16, // port on which the node is listening on its side
     version: String, // daemon version
+    protocol_version: u8, // P2P protocol version advertised in its handshake
     out: bool, // True mean we are the client
     priority: bool, // if this node can be trusted (seed node or added manually by user)
+    trusted: bool, // if its node key is configured as trusted, it gets reserved slots and relaxed limits
+    services: NodeServices, // services advertised in its handshake
+    prune_depth: Option<u64>, // blocks kept below its top if it prunes automatically
+    node_key: PublicKey, // persistent identity of the node, verified in handshake
     top_hash: Mutex<Hash>, // current block top hash for this peer
     topoheight: AtomicU64, // current highest topo height for this peer
     height: AtomicU64, // current highest block height for this peer
@@ -47,10 +59,20 @@ pub struct Peer {
     fail_count: AtomicU8, // fail count: if greater than 20, we should close this connection
     peer_list: SharedPeerList, // shared pointer to the peer list in case of disconnection
     objects_requested: Mutex<RequestedObjects>, // map of requested objects from this peer
+    objects_batches: Mutex<RequestedObjectsBatches>, // map of requested objects batches from this peer
+    objects_batch_id: AtomicU64, // next id to use for a batch of objects requested
     peers: Mutex<HashMap<SocketAddr, Direction>>, // all peers sent/received
     last_peer_list: AtomicU64, // last time we received a peerlist from this peer
     last_ping: AtomicU64, // last time we got a ping packet from this peer
     last_ping_sent: AtomicU64, // last time we sent a ping packet to this peer
+    last_activity: AtomicU64, // last time we received any packet from this peer
+    keep_alive_sequence: AtomicU64, // sequence number of the last keep alive packet sent
+    keep_alive_sent_at: AtomicU64, // timestamp in millis of the last keep alive packet sent, 0 if answered
+    keep_alive_missed: AtomicU8, // keep alive packets not answered in a row
+    round_trip_time: AtomicU64, // last round trip time in millis measured with keep alive packets
+    packets_in: AtomicU64, // packets received and handled from this peer
+    handling_time: AtomicU64, // total time in micros spent handling its packets
+    blocks_synced: AtomicU64, // blocks received from this peer during chain sync and added to our chain
     cumulative_difficulty: AtomicU64, // cumulative difficulty of peer chain
     txs_cache: Mutex<LruCache<Hash, Direction>>, // All transactions propagated from/to this peer
     blocks_propagation: Mutex<LruCache<Hash, Direction>>, // last blocks propagated to/from this peer
@@ -64,7 +86,7 @@ pub struct Peer {
 }
 
 impl Peer {
-    pub fn new(connection: Connection, id: u64, node_tag: Option<String>, local_port: u16, version: String, top_hash: Hash, topoheight: u64, height: u64, pruned_topoheight: Option<u64>, out: bool, priority: bool, cumulative_difficulty: Difficulty, peer_list: SharedPeerList, peers_received: HashSet<SocketAddr>) -> Self {
+    pub fn new(connection: Connection, id: u64, node_tag: Option<String>, local_port: u16, version: String, protocol_version: u8, top_hash: Hash, topoheight: u64, height: u64, pruned_topoheight: Option<u64>, out: bool, priority: bool, trusted: bool, node_key: PublicKey, cumulative_difficulty: Difficulty, services: NodeServices, prune_depth: Option<u64>, peer_list: SharedPeerList, peers_received: HashSet<SocketAddr>) -> Self {
         let mut outgoing_address = *connection.get_address();
         outgoing_address.set_port(local_port);
 
@@ -79,20 +101,35 @@ impl Peer {
             node_tag,
             local_port,
             version,
+            protocol_version,
             top_hash: Mutex::new(top_hash),
             topoheight: AtomicU64::new(topoheight),
             height: AtomicU64::new(height),
             out,
             priority,
+            trusted,
+            node_key,
+            services,
+            prune_depth,
             last_fail_count: AtomicU64::new(0),
             fail_count: AtomicU8::new(0),
             last_chain_sync: AtomicU64::new(0),
             peer_list,
             objects_requested: Mutex::new(HashMap::new()),
+            objects_batches: Mutex::new(HashMap::new()),
+            objects_batch_id: AtomicU64::new(0),
             peers: Mutex::new(peers),
             last_peer_list: AtomicU64::new(0),
             last_ping: AtomicU64::new(0),
             last_ping_sent: AtomicU64::new(0),
+            last_activity: AtomicU64::new(get_current_time()),
+            keep_alive_sequence: AtomicU64::new(0),
+            keep_alive_sent_at: AtomicU64::new(0),
+            keep_alive_missed: AtomicU8::new(0),
+            round_trip_time: AtomicU64::new(0),
+            packets_in: AtomicU64::new(0),
+            handling_time: AtomicU64::new(0),
+            blocks_synced: AtomicU64::new(0),
             cumulative_difficulty: AtomicU64::new(cumulative_difficulty),
             txs_cache: Mutex::new(LruCache::new(128)),
             blocks_propagation: Mutex::new(LruCache::new(STABLE_LIMIT as usize * TIPS_LIMIT)),
@@ -138,6 +175,15 @@ impl Peer {
         &self.version
     }
 
+    pub fn get_protocol_version(&self) -> u8 {
+        self.protocol_version
+    }
+
+    // packets added in a protocol version are only exchanged with the peers advertising it
+    pub fn has_protocol_version(&self, version: u8) -> bool {
+        self.protocol_version >= version
+    }
+
     pub fn get_topoheight(&self) -> u64 {
         self.topoheight.load(Ordering::Acquire)
     }
@@ -199,6 +245,31 @@ impl Peer {
         self.priority
     }
 
+    pub fn is_trusted(&self) -> bool {
+        self.trusted
+    }
+
+    pub fn get_node_key(&self) -> &PublicKey {
+        &self.node_key
+    }
+
+    pub fn get_services(&self) -> NodeServices {
+        self.services
+    }
+
+    pub fn get_prune_depth(&self) -> Option<u64> {
+        self.prune_depth
+    }
+
+    // trusted peers are allowed more fails before being disconnected
+    pub fn get_fail_limit(&self) -> u8 {
+        if self.trusted {
+            PEER_TRUSTED_FAIL_LIMIT
+        } else {
+            PEER_FAIL_LIMIT
+        }
+    }
+
     pub fn get_last_fail_count(&self) -> u64 {
         self.last_fail_count.load(Ordering::Acquire)
     }
@@ -294,6 +365,100 @@ impl Peer {
         Ok(object)
     }
 
+    // generate a new id for a batch of objects requested
+    pub fn next_objects_batch_id(&self) -> u64 {
+        self.objects_batch_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    pub async fn remove_objects_batch(&self, id: u64) -> Option<Sender<Vec<OwnedObjectResponse>>> {
+        let mut batches = self.objects_batches.lock().await;
+        batches.remove(&id)
+    }
+
+    // Request multiple objects from this peer and wait on them until we receive all of them or until timeout
+    // Objects are requested in batches, and those missing in a partial response are requested again
+    // Responses are returned in the same order as requested
+    pub async fn request_blocking_objects(&self, requests: Vec<ObjectRequest>) -> Result<Vec<OwnedObjectResponse>, P2pError> {
+        // peer doesn't support the batched packets, request the objects one by one
+        if !self.has_protocol_version(P2P_OBJECTS_BATCH_VERSION) {
+            let mut objects = Vec::with_capacity(requests.len());
+            for request in requests {
+                objects.push(self.request_blocking_object(request).await?);
+            }
+            return Ok(objects)
+        }
+
+        let requests: IndexSet<ObjectRequest> = requests.into_iter().collect();
+        let mut received: HashMap<ObjectRequest, OwnedObjectResponse> = HashMap::with_capacity(requests.len());
+        loop {
+            let batch: Vec<ObjectRequest> = requests.iter()
+                .filter(|request| !received.contains_key(*request))
+                .take(MAX_OBJECTS_PER_REQUEST)
+                .cloned()
+                .collect();
+
+            if batch.is_empty() {
+                break;
+            }
+
+            for response in self.request_objects_batch(batch).await? {
+                received.insert(response.get_request(), response);
+            }
+        }
+
+        let mut objects = Vec::with_capacity(requests.len());
+        for request in requests {
+            let object = received.remove(&request).ok_or(P2pError::ObjectNotFound(request))?;
+            objects.push(object);
+        }
+
+        Ok(objects)
+    }
+
+    // Send one batch of objects requests and wait on its response
+    async fn request_objects_batch(&self, requests: Vec<ObjectRequest>) -> Result<Vec<OwnedObjectResponse>, P2pError> {
+        let id = self.next_objects_batch_id();
+        trace!("Requesting {} objects from {} (batch id {})", requests.len(), self, id);
+        let request = ObjectsRequest::new(id, requests);
+        let receiver = {
+            let mut batches = self.objects_batches.lock().await;
+            self.send_packet(Packet::ObjectsRequest(Cow::Borrowed(&request))).await?;
+            let (sender, receiver) = tokio::sync::oneshot::channel();
+            batches.insert(id, sender);
+            receiver
+        };
+
+        let responses = match timeout(Duration::from_millis(PEER_TIMEOUT_REQUEST_OBJECT), receiver).await {
+            Ok(res) => res?,
+            Err(e) => {
+                trace!("Requested objects batch {} has timed out", id);
+                self.remove_objects_batch(id).await;
+                return Err(P2pError::AsyncTimeOut(e));
+            }
+        };
+
+        // peer must send at least one object, otherwise we would request them indefinitely
+        if responses.is_empty() {
+            return Err(P2pError::EmptyObjectsResponse)
+        }
+
+        let (_, requests) = request.consume();
+        for response in &responses {
+            let response_request = response.get_request();
+            // Verify that the object is one we requested
+            if !requests.contains(&response_request) {
+                return Err(P2pError::ObjectNotRequested(response_request))
+            }
+
+            // Returns error if the object is not found
+            if let OwnedObjectResponse::NotFound(request) = response {
+                return Err(P2pError::ObjectNotFound(request.clone()))
+            }
+        }
+
+        Ok(responses)
+    }
+
     pub async fn request_boostrap_chain(&self, step: StepRequest<'_>) -> Result<StepResponse, P2pError> {
         debug!("Requesting bootstrap chain step: {:?}", step.kind());
         let step_kind = step.kind();
@@ -380,7 +545,80 @@ impl Peer {
     }
 
     pub fn set_last_ping_sent(&self, value: u64) {
-        self.last_ping.store(value, Ordering::Release)
+        self.last_ping_sent.store(value, Ordering::Release)
+    }
+
+    pub fn get_last_activity(&self) -> u64 {
+        self.last_activity.load(Ordering::Acquire)
+    }
+
+    pub fn set_last_activity(&self, value: u64) {
+        self.last_activity.store(value, Ordering::Release)
+    }
+
+    pub fn record_packet_handling(&self, duration: Duration) {
+        self.packets_in.fetch_add(1, Ordering::Relaxed);
+        self.handling_time.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn get_packets_in(&self) -> u64 {
+        self.packets_in.load(Ordering::Relaxed)
+    }
+
+    // total time in micros spent handling its packets
+    pub fn get_handling_time(&self) -> u64 {
+        self.handling_time.load(Ordering::Relaxed)
+    }
+
+    pub fn get_blocks_synced(&self) -> u64 {
+        self.blocks_synced.load(Ordering::Relaxed)
+    }
+
+    pub fn add_blocks_synced(&self, count: u64) {
+        self.blocks_synced.fetch_add(count, Ordering::Relaxed);
+    }
+
+    // round trip time in millis, 0 if not measured yet
+    pub fn get_round_trip_time(&self) -> u64 {
+        self.round_trip_time.load(Ordering::Acquire)
+    }
+
+    pub fn get_keep_alive_missed(&self) -> u8 {
+        self.keep_alive_missed.load(Ordering::Acquire)
+    }
+
+    // prepare a new keep alive packet to send
+    // if the previous one was not answered, it is counted as missed
+    pub fn next_keep_alive(&self) -> KeepAlive {
+        if self.keep_alive_sent_at.load(Ordering::Acquire) != 0 {
+            self.keep_alive_missed.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let sequence = self.keep_alive_sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        self.keep_alive_sent_at.store(get_current_timestamp() as u64, Ordering::Release);
+        KeepAlive::new(sequence)
+    }
+
+    // handle the response to our keep alive packet
+    pub fn on_keep_alive_response(&self, keep_alive: &KeepAlive) -> Result<(), P2pError> {
+        let sequence = keep_alive.get_sequence();
+        let expected = self.keep_alive_sequence.load(Ordering::Acquire);
+        if sequence > expected {
+            return Err(P2pError::InvalidKeepAliveSequence(expected, sequence))
+        }
+
+        // a late response for an old keep alive is ignored, it will be counted as missed
+        if sequence == expected {
+            let sent_at = self.keep_alive_sent_at.swap(0, Ordering::SeqCst);
+            if sent_at != 0 {
+                let rtt = (get_current_timestamp() as u64).saturating_sub(sent_at);
+                trace!("Round trip time for {} is {}ms", self, rtt);
+                self.round_trip_time.store(rtt, Ordering::Release);
+                self.keep_alive_missed.store(0, Ordering::Release);
+            }
+        }
+
+        Ok(())
     }
 
     pub fn get_last_inventory(&self) -> u64 {
@@ -416,13 +654,27 @@ impl Peer {
         self.send_bytes(Bytes::from(packet.to_bytes())).await
     }
 
+    // the transactions are queued on their own stream if the connection has one
     pub async fn send_bytes(&self, bytes: Bytes) -> Result<(), P2pError> {
         trace!("Sending {} bytes to {}", bytes.len(), self.get_outgoing_address());
-        let tx = self.connection.get_tx().lock().await;
+        let tx = match self.connection.get_transactions_tx() {
+            // skip the size prefix of the packet
+            Some(tx) if get_traffic_class(bytes.get(4..).unwrap_or_default()) == TrafficClass::Transactions => tx,
+            _ => self.connection.get_tx()
+        };
+        let tx = tx.lock().await;
         trace!("Lock acquired, Sending packet");
         tx.send(ConnectionMessage::Packet(bytes))?;
         Ok(())
     }
+
+    // bytes are sent before all the packets already waiting in the queue
+    pub async fn send_bytes_priority(&self, bytes: Bytes) -> Result<(), P2pError> {
+        trace!("Sending {} bytes in priority to {}", bytes.len(), self.get_outgoing_address());
+        let tx = self.connection.get_priority_tx().lock().await;
+        tx.send(ConnectionMessage::Packet(bytes))?;
+        Ok(())
+    }
 }
 
 impl Display for Peer {
@@ -447,7 +699,7 @@ impl Display for Peer {
             "No".to_string()
         };
 
-        write!(f, "Peer[connection: {}, id: {}, topoheight: {}, top hash: {}, height: {}, pruned: {}, priority: {}, tag: {}, version: {}, fail count: {}, out: {}, peers: {}]",
+        write!(f, "Peer[connection: {}, id: {}, topoheight: {}, top hash: {}, height: {}, pruned: {}, priority: {}, trusted: {}, tag: {}, version: {}, fail count: {}, out: {}, peers: {}]",
             self.get_connection(),
             self.get_id(),
             self.get_topoheight(),
@@ -455,6 +707,7 @@ impl Display for Peer {
             self.get_height(),
             pruned_state,
             self.is_priority(),
+            self.is_trusted(),
             self.get_node_tag().as_ref().unwrap_or(&"None".to_owned()),
             self.get_version(),
             self.get_fail_count(),
@@ -1,6 +1,9 @@
 use crate::{
     p2p::packet::peer_disconnected::PacketPeerDisconnected,
-    config::{P2P_EXTEND_PEERLIST_DELAY, PEER_FAIL_LIMIT}
+    config::{
+        P2P_EXTEND_PEERLIST_DELAY, PEER_FAIL_LIMIT, PEER_DEFAULT_REPUTATION, PEER_MAX_REPUTATION,
+        PEER_REPUTATION_MIN_SESSION, PEER_REPUTATION_REWARD, PEER_REPUTATION_PENALTY, PEER_REPUTATION_CONNECTION_PENALTY
+    }
 };
 use super::{peer::Peer, packet::Packet, error::P2pError};
 use std::{collections::HashMap, net::{SocketAddr, IpAddr}, fs, fmt::{Formatter, self, Display}, time::Duration};
@@ -43,7 +46,20 @@ pub struct StoredPeer {
     last_connection_try: u64,
     fail_count: u8,
     local_port: u16,
-    state: StoredPeerState
+    state: StoredPeerState,
+    // statistics kept across restarts, peers saved before them start from the default values
+    #[serde(default = "default_reputation")]
+    reputation: u8,
+    // total seconds connected to it
+    #[serde(default)]
+    uptime: u64,
+    // blocks received from it during chain sync
+    #[serde(default)]
+    blocks_synced: u64
+}
+
+fn default_reputation() -> u8 {
+    PEER_DEFAULT_REPUTATION
 }
 
 impl PeerList {
@@ -147,6 +163,8 @@ impl PeerList {
             }
         }
 
+        self.record_session(&peer);
+
         info!("Peer disconnected: {}", peer);
         if let Some(peer_disconnect_channel) = &self.peer_disconnect_channel {
             debug!("Notifying server that {} disconnected", peer);
@@ -181,6 +199,33 @@ impl PeerList {
         }
     }
 
+    // carry the statistics of the session over to the stored peer
+    // a session long enough or useful for the sync is rewarded, a peer disconnected for its failures is penalized
+    fn record_session(&mut self, peer: &Peer) {
+        let Some(stored_peer) = self.stored_peers.get_mut(&peer.get_outgoing_address().ip()) else {
+            return;
+        };
+
+        let current_time = get_current_time();
+        let session = current_time.saturating_sub(peer.get_connection().connected_on());
+        let blocks_synced = peer.get_blocks_synced();
+        stored_peer.uptime = stored_peer.uptime.saturating_add(session);
+        stored_peer.blocks_synced = stored_peer.blocks_synced.saturating_add(blocks_synced);
+        stored_peer.set_last_seen(current_time);
+
+        if peer.get_fail_count() >= peer.get_fail_limit() {
+            stored_peer.decrease_reputation(PEER_REPUTATION_PENALTY);
+        } else {
+            if session >= PEER_REPUTATION_MIN_SESSION {
+                stored_peer.increase_reputation(PEER_REPUTATION_REWARD);
+            }
+            if blocks_synced > 0 {
+                stored_peer.increase_reputation(PEER_REPUTATION_REWARD);
+            }
+        }
+        debug!("Session of {} recorded: {}s, {} blocks synced, reputation {}", peer, session, blocks_synced, stored_peer.get_reputation());
+    }
+
     pub fn has_peer(&self, peer_id: &u64) -> bool {
         self.peers.contains_key(peer_id)
     }
@@ -201,6 +246,12 @@ impl PeerList {
             }
         }
 
+        // peers are not removed one by one, save their current session before the peerlist
+        let peers: Vec<Arc<Peer>> = self.peers.values().cloned().collect();
+        for peer in peers {
+            self.record_session(&peer);
+        }
+
         if let Err(e) = self.save_peers_to_file() {
             error!("Error while trying to save peerlist to file: {}", e);
         }
@@ -376,16 +427,20 @@ impl PeerList {
 
     // find among stored peers a peer to connect to with the requested StoredPeerState
     // we check that we're not already connected to this peer and that we didn't tried to connect to it recently
+    // the peer with the best history is selected: highest reputation, then most blocks synced and longest uptime
     fn find_peer_to_connect_to_with_state(&mut self, current_time: u64, state: StoredPeerState) -> Option<SocketAddr> {
-        for (ip, stored_peer) in &mut self.stored_peers {
-            let addr = SocketAddr::new(*ip, stored_peer.get_local_port());
-            if *stored_peer.get_state() == state && stored_peer.get_last_connection_try() + (stored_peer.get_fail_count() as u64 * P2P_EXTEND_PEERLIST_DELAY) <= current_time && Self::internal_get_peer_by_addr(&self.peers, &addr).is_none() {
-                stored_peer.set_last_connection_try(current_time);
-                return Some(addr);
-            }
-        }
+        let peers = &self.peers;
+        let (ip, stored_peer) = self.stored_peers.iter_mut()
+            .filter(|(ip, stored_peer)| {
+                let addr = SocketAddr::new(**ip, stored_peer.get_local_port());
+                *stored_peer.get_state() == state
+                    && stored_peer.get_last_connection_try() + (stored_peer.get_fail_count() as u64 * P2P_EXTEND_PEERLIST_DELAY) <= current_time
+                    && Self::internal_get_peer_by_addr(peers, &addr).is_none()
+            })
+            .max_by_key(|(_, stored_peer)| (stored_peer.get_reputation(), stored_peer.blocks_synced, stored_peer.uptime))?;
 
-        None
+        stored_peer.set_last_connection_try(current_time);
+        Some(SocketAddr::new(*ip, stored_peer.get_local_port()))
     }
 
     // increase the fail count of a peer
@@ -397,6 +452,7 @@ impl PeerList {
                 return;
             }
             stored_peer.set_fail_count(stored_peer.get_fail_count() + 1);
+            stored_peer.decrease_reputation(PEER_REPUTATION_CONNECTION_PENALTY);
         }
     }
 
@@ -418,7 +474,10 @@ impl StoredPeer {
             last_connection_try: 0,
             fail_count: 0,
             local_port,
-            state
+            state,
+            reputation: PEER_DEFAULT_REPUTATION,
+            uptime: 0,
+            blocks_synced: 0
         }
     }
 
@@ -457,11 +516,93 @@ impl StoredPeer {
     fn get_local_port(&self) -> u16 {
         self.local_port
     }
+
+    fn get_reputation(&self) -> u8 {
+        self.reputation
+    }
+
+    fn increase_reputation(&mut self, value: u8) {
+        self.reputation = self.reputation.saturating_add(value).min(PEER_MAX_REPUTATION);
+    }
+
+    fn decrease_reputation(&mut self, value: u8) {
+        self.reputation = self.reputation.saturating_sub(value);
+    }
 }
 
 impl Display for StoredPeer {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let current_time = get_current_time();
-        write!(f, "StoredPeer[first seen: {} ago, last seen: {} ago]", format_duration(Duration::from_secs(current_time - self.first_seen)), format_duration(Duration::from_secs(current_time - self.last_seen)))
+        write!(
+            f,
+            "StoredPeer[first seen: {} ago, last seen: {} ago, reputation: {}, uptime: {}, blocks synced: {}]",
+            format_duration(Duration::from_secs(current_time - self.first_seen)),
+            format_duration(Duration::from_secs(current_time - self.last_seen)),
+            self.reputation,
+            format_duration(Duration::from_secs(self.uptime)),
+            self.blocks_synced
+        )
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_peer_list(stored_peers: HashMap<IpAddr, StoredPeer>) -> PeerList {
+        PeerList {
+            peers: HashMap::new(),
+            stored_peers,
+            filename: String::new(),
+            peer_disconnect_channel: None
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_load_stored_peers_without_statistics() {
+        let path = std::env::temp_dir().join(format!("xelis_peerlist_{}.json", std::process::id()));
+        let filename = path.to_string_lossy().to_string();
+        fs::write(&filename, r#"{"127.0.0.1":{"first_seen":1,"last_seen":2,"last_connection_try":0,"fail_count":3,"local_port":2125,"state":"Graylist"}}"#).unwrap();
+
+        let peers = PeerList::load_stored_peers(&filename).unwrap();
+        fs::remove_file(&filename).unwrap();
+
+        let stored_peer = peers.get(&"127.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(stored_peer.get_reputation(), PEER_DEFAULT_REPUTATION);
+        assert_eq!(stored_peer.uptime, 0);
+        assert_eq!(stored_peer.blocks_synced, 0);
+    }
+
+    #[test]
+    fn test_reputation_bounds() {
+        let mut stored_peer = StoredPeer::new(2125, StoredPeerState::Graylist);
+        stored_peer.increase_reputation(PEER_MAX_REPUTATION);
+        assert_eq!(stored_peer.get_reputation(), PEER_MAX_REPUTATION);
+        stored_peer.decrease_reputation(u8::MAX);
+        assert_eq!(stored_peer.get_reputation(), 0);
+    }
+
+    #[test]
+    fn test_prefer_historically_good_peers() {
+        let bad: IpAddr = "10.0.0.1".parse().unwrap();
+        let good: IpAddr = "10.0.0.2".parse().unwrap();
+        let useful: IpAddr = "10.0.0.3".parse().unwrap();
+
+        let mut bad_peer = StoredPeer::new(2125, StoredPeerState::Graylist);
+        bad_peer.decrease_reputation(PEER_REPUTATION_PENALTY);
+        let mut good_peer = StoredPeer::new(2125, StoredPeerState::Graylist);
+        good_peer.increase_reputation(PEER_REPUTATION_REWARD);
+        // same reputation, but it synced more blocks
+        let mut useful_peer = StoredPeer::new(2125, StoredPeerState::Graylist);
+        useful_peer.increase_reputation(PEER_REPUTATION_REWARD);
+        useful_peer.blocks_synced = 100;
+
+        let mut peer_list = get_peer_list(HashMap::from([(bad, bad_peer), (good, good_peer), (useful, useful_peer)]));
+        for ip in [useful, good, bad] {
+            assert_eq!(peer_list.find_peer_to_connect(), Some(SocketAddr::new(ip, 2125)));
+            // connection failed, it is not retried before some time
+            peer_list.increase_fail_count_for_saved_peer(&ip);
+        }
+        assert!(peer_list.find_peer_to_connect().is_none());
+        assert_eq!(peer_list.stored_peers[&useful].get_reputation(), PEER_DEFAULT_REPUTATION + PEER_REPUTATION_REWARD - PEER_REPUTATION_CONNECTION_PENALTY);
+    }
+}
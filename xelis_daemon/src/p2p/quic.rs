@@ -0,0 +1,349 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs,
+    io,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime}
+};
+use async_trait::async_trait;
+use log::{debug, info, trace, warn};
+use quinn::{ClientConfig, Connecting, Connection, Endpoint, RecvStream, SendStream, ServerConfig};
+use rustls::{
+    client::{Resumption, ServerCertVerified, ServerCertVerifier},
+    Certificate,
+    CertificateError,
+    PrivateKey,
+    ServerName
+};
+use tokio::{sync::{mpsc, Mutex as AsyncMutex}, time::timeout};
+use xelis_common::{crypto::hash::{hash, Hash}, serializer::Serializer};
+use crate::config::PEER_TIMEOUT_INIT_CONNECTION;
+use super::{
+    error::P2pError,
+    transport::{Transport, TransportListener, TransportStream}
+};
+
+// protocol negotiated during the TLS handshake
+const ALPN_PROTOCOL: &[u8] = b"xelis-p2p";
+// name in the self signed certificate of the node
+const CERTIFICATE_NAME: &str = "xelis";
+// identity of the node in its data directory, so its certificate stays the same across restarts
+const CERTIFICATE_FILE: &str = "quic_certificate.der";
+const KEY_FILE: &str = "quic_key.der";
+// connections accepted but not yet handled by the P2P server
+const INCOMING_QUEUE_SIZE: usize = 32;
+// session tickets kept to reconnect to the peers using 0-RTT
+const SESSIONS_CACHE_SIZE: usize = 256;
+// first byte sent on each stream by the peer connecting, to give its traffic class
+const CHAIN_STREAM: u8 = 0;
+const TRANSACTIONS_STREAM: u8 = 1;
+
+// keys of the peers we connect to, by address
+type PeerKeys = Arc<Mutex<HashMap<SocketAddr, Hash>>>;
+
+// key of a peer, it is the hash of its certificate
+fn get_certificate_key(certificate: &Certificate) -> Hash {
+    hash(&certificate.0)
+}
+
+// the map is still consistent if a thread panicked while holding the lock
+fn lock_keys(keys: &PeerKeys) -> MutexGuard<'_, HashMap<SocketAddr, Hash>> {
+    keys.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// certificates are self signed so they are verified using the key of the peer:
+// the one pinned in its address, otherwise the one of the first connection to this address
+// the TLS handshake then proves that the peer owns the private key of its certificate
+struct PeerCertificateVerifier {
+    addr: SocketAddr,
+    keys: PeerKeys
+}
+
+impl ServerCertVerifier for PeerCertificateVerifier {
+    fn verify_server_cert(&self, end_entity: &Certificate, _: &[Certificate], _: &ServerName, _: &mut dyn Iterator<Item = &[u8]>, _: &[u8], _: SystemTime) -> Result<ServerCertVerified, rustls::Error> {
+        let key = get_certificate_key(end_entity);
+        match lock_keys(&self.keys).get(&self.addr) {
+            Some(expected) if *expected != key => {
+                warn!("QUIC peer {} presented the key {} instead of {}", self.addr, key, expected);
+                Err(rustls::Error::InvalidCertificate(CertificateError::ApplicationVerificationFailure))
+            },
+            // the key of a new peer is saved once the handshake is done
+            _ => Ok(ServerCertVerified::assertion())
+        }
+    }
+}
+
+fn to_io_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+// load the certificate of the node, or create it the first time
+fn load_or_create_identity(dir: &Path) -> io::Result<(Certificate, PrivateKey)> {
+    let certificate_path = dir.join(CERTIFICATE_FILE);
+    let key_path = dir.join(KEY_FILE);
+    if certificate_path.exists() && key_path.exists() {
+        return Ok((Certificate(fs::read(certificate_path)?), PrivateKey(fs::read(key_path)?)))
+    }
+
+    let certificate = rcgen::generate_simple_self_signed(vec![CERTIFICATE_NAME.to_owned()]).map_err(to_io_error)?;
+    let key = PrivateKey(certificate.serialize_private_key_der());
+    let certificate = Certificate(certificate.serialize_der().map_err(to_io_error)?);
+
+    fs::create_dir_all(dir)?;
+    fs::write(key_path, &key.0)?;
+    fs::write(certificate_path, &certificate.0)?;
+    Ok((certificate, key))
+}
+
+fn create_server_config(certificate: Certificate, key: PrivateKey) -> io::Result<ServerConfig> {
+    let mut crypto = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate], key)
+        .map_err(to_io_error)?;
+    crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+    // accept the 0-RTT data of the peers reconnecting
+    crypto.max_early_data_size = u32::MAX;
+
+    Ok(ServerConfig::with_crypto(Arc::new(crypto)))
+}
+
+// certificate presented by the peer, only known once the handshake is done
+fn get_peer_certificate(connection: &Connection) -> Option<Certificate> {
+    let certificates = connection.peer_identity()?.downcast::<Vec<Certificate>>().ok()?;
+    certificates.first().cloned()
+}
+
+// the peer sees the stream once something is sent on it
+async fn open_stream(connection: &Connection, class: u8) -> io::Result<TransportStream> {
+    let (mut send, recv) = connection.open_bi().await?;
+    send.write_all(&[class]).await?;
+    Ok(TransportStream::new(recv, send))
+}
+
+// QUIC transport, the peer connecting opens a bidirectional stream for the chain and one for the transactions
+// so the transactions are relayed while the chain is synced
+// its endpoint is bound to the P2P address (UDP) and is used for both incoming and outgoing connections,
+// so the peers behind a NAT are reachable from the port they use to connect
+pub struct QuicTransport {
+    endpoint: Endpoint,
+    // session tickets shared by the connections to all the peers
+    resumption: Resumption,
+    keys: PeerKeys,
+    key: Hash
+}
+
+impl QuicTransport {
+    pub fn new(bind_address: &str, identity_dir: &str) -> Result<Self, P2pError> {
+        let addr: SocketAddr = bind_address.parse()?;
+        let (certificate, private_key) = load_or_create_identity(Path::new(identity_dir))?;
+        let key = get_certificate_key(&certificate);
+        info!("QUIC key of this node: {}", key);
+
+        let endpoint = Endpoint::server(create_server_config(certificate, private_key)?, addr)?;
+        Ok(Self {
+            endpoint,
+            resumption: Resumption::in_memory_sessions(SESSIONS_CACHE_SIZE),
+            keys: Arc::new(Mutex::new(HashMap::new())),
+            key
+        })
+    }
+
+    // key to pin in the address of this node: quic://<key>@<address>
+    pub fn get_key(&self) -> &Hash {
+        &self.key
+    }
+
+    // each connection verifies the key of its peer
+    fn create_client_config(&self, addr: &SocketAddr) -> ClientConfig {
+        let mut crypto = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(PeerCertificateVerifier { addr: *addr, keys: Arc::clone(&self.keys) }))
+            .with_no_client_auth();
+        crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+        crypto.resumption = self.resumption.clone();
+        // session tickets of the peers are kept to reconnect using 0-RTT
+        crypto.enable_early_data = true;
+
+        ClientConfig::new(Arc::new(crypto))
+    }
+
+    // the streams are identified by their first byte as they may be seen in any order
+    async fn accept_stream(connecting: Connecting) -> io::Result<TransportStream> {
+        let connection = connecting.await?;
+        let mut chain: Option<(SendStream, RecvStream)> = None;
+        let mut transactions: Option<(SendStream, RecvStream)> = None;
+        while chain.is_none() || transactions.is_none() {
+            let (send, mut recv) = connection.accept_bi().await?;
+            let mut class = [0u8];
+            recv.read_exact(&mut class).await.map_err(to_io_error)?;
+            match class[0] {
+                CHAIN_STREAM if chain.is_none() => chain = Some((send, recv)),
+                TRANSACTIONS_STREAM if transactions.is_none() => transactions = Some((send, recv)),
+                class => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unexpected stream with class {}", class)))
+            };
+        }
+
+        let (Some((send, recv)), Some((transactions_send, transactions_recv))) = (chain, transactions) else {
+            unreachable!()
+        };
+        Ok(TransportStream::new(recv, send).with_transactions(TransportStream::new(transactions_recv, transactions_send)))
+    }
+
+    // handle each incoming connection in its own task so a slow peer doesn't block the others
+    async fn accept_loop(endpoint: Endpoint, sender: mpsc::Sender<(TransportStream, SocketAddr)>) {
+        while let Some(connecting) = endpoint.accept().await {
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                let addr = connecting.remote_address();
+                match timeout(Duration::from_millis(PEER_TIMEOUT_INIT_CONNECTION), Self::accept_stream(connecting)).await {
+                    Ok(Ok(stream)) => {
+                        if sender.send((stream, addr)).await.is_err() {
+                            debug!("QUIC listener closed, ignoring connection from {}", addr);
+                        }
+                    },
+                    Ok(Err(e)) => debug!("Error while accepting QUIC connection from {}: {}", addr, e),
+                    Err(_) => debug!("QUIC connection from {} timed out", addr)
+                };
+            });
+        }
+        debug!("QUIC endpoint closed");
+    }
+}
+
+struct QuicTransportListener {
+    receiver: AsyncMutex<mpsc::Receiver<(TransportStream, SocketAddr)>>,
+    local_addr: SocketAddr
+}
+
+#[async_trait]
+impl TransportListener for QuicTransportListener {
+    async fn accept(&self) -> io::Result<(TransportStream, SocketAddr)> {
+        let mut receiver = self.receiver.lock().await;
+        receiver.recv().await.ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "QUIC endpoint closed"))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[async_trait]
+impl Transport for QuicTransport {
+    fn get_name(&self) -> &'static str {
+        "quic"
+    }
+
+    // the endpoint is already bound when the transport is created
+    async fn bind(&self, _: &SocketAddr) -> io::Result<Box<dyn TransportListener>> {
+        let (sender, receiver) = mpsc::channel(INCOMING_QUEUE_SIZE);
+        tokio::spawn(Self::accept_loop(self.endpoint.clone(), sender));
+        Ok(Box::new(QuicTransportListener {
+            receiver: AsyncMutex::new(receiver),
+            local_addr: self.endpoint.local_addr()?
+        }))
+    }
+
+    async fn connect(&self, addr: &SocketAddr) -> io::Result<TransportStream> {
+        // the session tickets are stored by server name, use the IP so each peer has its own
+        let connecting = self.endpoint.connect_with(self.create_client_config(addr), *addr, &addr.ip().to_string()).map_err(to_io_error)?;
+        // reconnect without waiting for the TLS handshake if we have a session ticket of this peer
+        // the first data sent is our P2P handshake, which can be replayed without side effect
+        let connection = match connecting.into_0rtt() {
+            Ok((connection, _)) => {
+                trace!("Using 0-RTT to connect to {}", addr);
+                connection
+            },
+            Err(connecting) => connecting.await?
+        };
+
+        // first connection to this peer, its key is trusted for the next ones
+        // a session ticket is only received after a verified handshake, so it is already known when using 0-RTT
+        if let Some(certificate) = get_peer_certificate(&connection) {
+            if let Entry::Vacant(entry) = lock_keys(&self.keys).entry(*addr) {
+                debug!("Trusting the QUIC key of {}", addr);
+                entry.insert(get_certificate_key(&certificate));
+            }
+        }
+
+        let chain = open_stream(&connection, CHAIN_STREAM).await?;
+        let transactions = open_stream(&connection, TRANSACTIONS_STREAM).await?;
+        Ok(chain.with_transactions(transactions))
+    }
+
+    fn pin_peer_key(&self, addr: &SocketAddr, key: &str) -> Result<(), P2pError> {
+        let key = Hash::from_hex(key.to_owned()).map_err(|_| P2pError::InvalidPeerKey(key.to_owned()))?;
+        lock_keys(&self.keys).insert(*addr, key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use super::*;
+
+    fn _create_transport(name: &str) -> QuicTransport {
+        let dir = std::env::temp_dir().join(format!("xelis_quic_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        QuicTransport::new("127.0.0.1:0", &dir.to_string_lossy()).unwrap()
+    }
+
+    async fn _exchange(from: &mut TransportStream, to: &mut TransportStream, message: &[u8]) {
+        from.writer.write_all(message).await.unwrap();
+        from.writer.flush().await.unwrap();
+        let mut buf = vec![0; message.len()];
+        to.reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, message);
+    }
+
+    #[tokio::test]
+    async fn test_streams_per_traffic_class() {
+        let server = _create_transport("streams_server");
+        let client = _create_transport("streams_client");
+        let listener = server.bind(&"127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut outgoing = client.connect(&addr).await.unwrap();
+        let (mut incoming, _) = listener.accept().await.unwrap();
+        let mut outgoing_transactions = outgoing.transactions.take().unwrap();
+        let mut incoming_transactions = incoming.transactions.take().unwrap();
+
+        // a transaction is received while the chain stream is waiting for its data
+        _exchange(&mut outgoing_transactions, &mut incoming_transactions, b"tx").await;
+        _exchange(&mut incoming, &mut outgoing, b"chain").await;
+        _exchange(&mut incoming_transactions, &mut outgoing_transactions, b"tx response").await;
+    }
+
+    #[tokio::test]
+    async fn test_peer_key() {
+        let server = _create_transport("key_server");
+        let client = _create_transport("key_client");
+        let listener = server.bind(&"127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // a peer presenting another key is rejected
+        client.pin_peer_key(&addr, &Hash::zero().to_hex()).unwrap();
+        assert!(client.connect(&addr).await.is_err());
+        assert!(matches!(client.pin_peer_key(&addr, "invalid"), Err(P2pError::InvalidPeerKey(_))));
+
+        client.pin_peer_key(&addr, &server.get_key().to_hex()).unwrap();
+        assert!(client.connect(&addr).await.is_ok());
+
+        // the key of a peer is trusted at the first connection
+        let other = _create_transport("key_other");
+        assert!(other.connect(&addr).await.is_ok());
+        assert_eq!(lock_keys(&other.keys).get(&addr), Some(server.get_key()));
+    }
+
+    #[test]
+    fn test_identity_is_kept() {
+        let dir = std::env::temp_dir().join(format!("xelis_quic_identity_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (certificate, _) = load_or_create_identity(&dir).unwrap();
+        let (loaded, _) = load_or_create_identity(&dir).unwrap();
+        assert_eq!(certificate, loaded);
+    }
+}
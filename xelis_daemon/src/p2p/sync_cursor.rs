@@ -0,0 +1,65 @@
+use indexmap::IndexSet;
+use log::debug;
+use xelis_common::{
+    crypto::hash::{Hash, HASH_SIZE},
+    serializer::{Serializer, Writer, Reader, ReaderError}
+};
+use crate::config::{CHAIN_SYNC_RESPONSE_MAX_BLOCKS, CHAIN_SYNC_TOP_BLOCKS, TIPS_LIMIT};
+use super::packet::chain::BlockId;
+
+// State of the chain sync saved in storage while the blocks of a chain response are requested
+// A node restarted during the sync requests the blocks left directly instead of a new chain
+pub struct SyncCursor {
+    // common point validated with the peer, the blocks pending are above it
+    common_point: BlockId,
+    // blocks of the chain response not added yet, in the order to add them
+    pending: IndexSet<Hash>
+}
+
+impl SyncCursor {
+    pub fn new(common_point: BlockId, pending: IndexSet<Hash>) -> Self {
+        Self {
+            common_point,
+            pending
+        }
+    }
+
+    pub fn get_common_point(&self) -> &BlockId {
+        &self.common_point
+    }
+
+    pub fn consume(self) -> (BlockId, IndexSet<Hash>) {
+        (self.common_point, self.pending)
+    }
+}
+
+impl Serializer for SyncCursor {
+    fn write(&self, writer: &mut Writer) {
+        self.common_point.write(writer);
+        writer.write_u16(self.pending.len() as u16);
+        for hash in &self.pending {
+            writer.write_hash(hash);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let common_point = BlockId::read(reader)?;
+        let len = reader.read_u16()? as usize;
+        // blocks and top blocks of a chain response
+        if len > CHAIN_SYNC_RESPONSE_MAX_BLOCKS + CHAIN_SYNC_TOP_BLOCKS * TIPS_LIMIT {
+            debug!("Invalid sync cursor length: {}", len);
+            return Err(ReaderError::InvalidValue)
+        }
+
+        reader.check_count(len, HASH_SIZE)?;
+        let mut pending = IndexSet::with_capacity(len);
+        for _ in 0..len {
+            if !pending.insert(reader.read_hash()?) {
+                debug!("Invalid sync cursor duplicate block");
+                return Err(ReaderError::InvalidValue)
+            }
+        }
+
+        Ok(Self::new(common_point, pending))
+    }
+}
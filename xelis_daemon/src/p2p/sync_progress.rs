@@ -0,0 +1,69 @@
+use std::{net::SocketAddr, time::{Duration, Instant}};
+use indexmap::IndexSet;
+
+// Progress of a chain sync session
+// A session starts with the first chain request and ends once no peer is above us anymore
+// It can be done with several peers as a new one is selected for each request
+pub struct SyncProgress {
+    started_at: Instant,
+    // our topoheight when the session started
+    start_topoheight: u64,
+    last_log: Instant,
+    // peers used as sources of blocks during the session
+    peers: IndexSet<SocketAddr>
+}
+
+impl SyncProgress {
+    pub fn new(start_topoheight: u64) -> Self {
+        let now = Instant::now();
+        Self {
+            started_at: now,
+            start_topoheight,
+            last_log: now,
+            peers: IndexSet::new()
+        }
+    }
+
+    pub fn add_peer(&mut self, addr: SocketAddr) {
+        self.peers.insert(addr);
+    }
+
+    pub fn get_peers(&self) -> &IndexSet<SocketAddr> {
+        &self.peers
+    }
+
+    // average blocks added per second since the start of the session
+    pub fn get_blocks_per_second(&self, topoheight: u64) -> f64 {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        if elapsed == 0f64 {
+            return 0f64
+        }
+        topoheight.saturating_sub(self.start_topoheight) as f64 / elapsed
+    }
+
+    // estimated seconds left to reach the target, unknown until a block has been added
+    pub fn get_eta(&self, topoheight: u64, target_topoheight: u64) -> Option<u64> {
+        let blocks_per_second = self.get_blocks_per_second(topoheight);
+        if blocks_per_second == 0f64 {
+            return None
+        }
+        Some((target_topoheight.saturating_sub(topoheight) as f64 / blocks_per_second).ceil() as u64)
+    }
+
+    // returns true at most once per delay so the progress is not logged on each request
+    pub fn should_log(&mut self, delay: Duration) -> bool {
+        if self.last_log.elapsed() < delay {
+            return false
+        }
+        self.last_log = Instant::now();
+        true
+    }
+}
+
+// percentage of the chain we have compared to the target
+pub fn get_progress(topoheight: u64, target_topoheight: u64) -> f64 {
+    if target_topoheight == 0 || topoheight >= target_topoheight {
+        return 100f64
+    }
+    topoheight as f64 * 100f64 / target_topoheight as f64
+}
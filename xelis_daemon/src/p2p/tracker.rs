@@ -4,10 +4,10 @@ use bytes::Bytes;
 use indexmap::IndexMap;
 use tokio::sync::{mpsc::{UnboundedSender, UnboundedReceiver, Sender, Receiver}, RwLock};
 use xelis_common::{crypto::hash::Hash, serializer::Serializer};
-use crate::{core::{blockchain::Blockchain, storage::Storage}, config::PEER_TIMEOUT_REQUEST_OBJECT};
+use crate::{core::{blockchain::Blockchain, storage::Storage}, config::{PEER_TIMEOUT_REQUEST_OBJECT, P2P_OBJECTS_BATCH_VERSION}};
 use log::{error, debug, trace, warn};
 
-use super::{packet::{object::{ObjectRequest, OwnedObjectResponse}, Packet}, error::P2pError, peer::Peer};
+use super::{packet::{object::{ObjectRequest, OwnedObjectResponse, ObjectsRequest, MAX_OBJECTS_PER_REQUEST}, Packet}, error::P2pError, peer::Peer};
 
 pub type SharedObjectTracker = Arc<ObjectTracker>;
 
@@ -121,6 +121,8 @@ pub struct ObjectTracker {
 
 enum Message {
     Request(Hash),
+    // all requests of a batch are for the same peer
+    RequestBatch(Vec<Hash>),
     Exit
 }
 
@@ -177,6 +179,11 @@ impl ObjectTracker {
     async fn handler_loop<S: Storage>(&self, blockchain: Arc<Blockchain<S>>, mut handler_receiver: Receiver<OwnedObjectResponse>) {
         debug!("Starting handler loop...");
         while let Some(response) = handler_receiver.recv().await {
+            // TXs are handled in order, verify their signature while the previous objects are handled
+            if let OwnedObjectResponse::Transaction(tx, hash) = &response {
+                blockchain.get_signature_verifier().submit(hash, &Arc::new(tx.clone()));
+            }
+
             let object = response.get_hash();
             let mut queue = self.queue.write().await;
             if let Some(request) = queue.get_mut(object) {
@@ -223,6 +230,9 @@ impl ObjectTracker {
                 Message::Request(object) => {
                     self.request_object_from_peer_internal(&object).await;
                 },
+                Message::RequestBatch(objects) => {
+                    self.request_objects_from_peer_internal(objects).await;
+                },
                 Message::Exit => break
             }
         }
@@ -278,6 +288,80 @@ impl ObjectTracker {
         Ok(true)
     }
 
+    // Request all objects not already requested from the peer using batches
+    // returns how many objects have been requested
+    pub async fn request_objects_from_peer(&self, peer: Arc<Peer>, requests: Vec<ObjectRequest>, broadcast: bool) -> Result<usize, P2pError> {
+        trace!("Requesting {} objects from {}", requests.len(), peer);
+        let hashes = {
+            let mut queue = self.queue.write().await;
+            let mut hashes = Vec::with_capacity(requests.len());
+            for request in requests {
+                let hash = request.get_hash().clone();
+                if queue.contains_key(&hash) {
+                    trace!("Object {} was already requested, ignoring", hash);
+                    continue;
+                }
+                queue.insert(hash.clone(), Request::new(request, Arc::clone(&peer), broadcast));
+                hashes.push(hash);
+            }
+            hashes
+        };
+
+        let count = hashes.len();
+        for chunk in hashes.chunks(MAX_OBJECTS_PER_REQUEST) {
+            trace!("Transfering {} objects requests to task", chunk.len());
+            self.request_sender.send(Message::RequestBatch(chunk.to_vec()))?;
+        }
+        Ok(count)
+    }
+
+    async fn request_objects_from_peer_internal(&self, hashes: Vec<Hash>) {
+        debug!("Requesting {} objects in batch", hashes.len());
+        let mut delete = false;
+        {
+            let mut queue = self.queue.write().await;
+            let mut peer = None;
+            let mut requests = Vec::with_capacity(hashes.len());
+            for hash in &hashes {
+                if let Some(request) = queue.get_mut(hash) {
+                    request.set_requested();
+                    requests.push(request.get_object().clone());
+                    if peer.is_none() {
+                        peer = Some(Arc::clone(request.get_peer()));
+                    }
+                }
+            }
+
+            if let Some(peer) = peer {
+                // peer doesn't support the batched packets, send one request per object
+                let packets = if peer.has_protocol_version(P2P_OBJECTS_BATCH_VERSION) {
+                    let packet = ObjectsRequest::new(peer.next_objects_batch_id(), requests);
+                    vec![Bytes::from(Packet::ObjectsRequest(Cow::Owned(packet)).to_bytes())]
+                } else {
+                    requests.iter().map(|request| Bytes::from(Packet::ObjectRequest(Cow::Borrowed(request)).to_bytes())).collect()
+                };
+
+                // send the packets to the Peer
+                for packet in packets {
+                    if let Err(e) = peer.send_bytes(packet).await {
+                        error!("Error while requesting {} objects using Object Tracker: {}", hashes.len(), e);
+                        peer.increment_fail_count();
+                        delete = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if delete {
+            trace!("Deleting {} requested objects", hashes.len());
+            let mut queue = self.queue.write().await;
+            for hash in &hashes {
+                queue.shift_remove(hash);
+            }
+        }
+    }
+
     async fn request_object_from_peer_internal(&self, request_hash: &Hash) {
         debug!("Requesting object with hash {}", request_hash);
         let mut delete = false;
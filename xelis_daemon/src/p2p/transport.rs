@@ -0,0 +1,236 @@
+use std::{collections::HashMap, io, net::SocketAddr};
+use async_trait::async_trait;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream}
+};
+use super::error::P2pError;
+
+pub type TransportReader = Box<dyn AsyncRead + Send + Unpin>;
+pub type TransportWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+// Class of the traffic exchanged with a peer
+// a transport able to multiplex streams (QUIC) carries each class in its own stream,
+// so the transactions are relayed while the chain is synced instead of waiting behind its big packets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrafficClass {
+    // blocks, chain sync and all the other packets
+    Chain,
+    // transactions propagated and requested
+    Transactions
+}
+
+// Byte stream established with a peer
+// it is split in two halves so a connection can read and write at the same time
+pub struct TransportStream {
+    pub reader: TransportReader,
+    pub writer: TransportWriter,
+    // stream of the transactions traffic, if the transport opened one
+    pub transactions: Option<Box<TransportStream>>
+}
+
+impl TransportStream {
+    pub fn new<R, W>(reader: R, writer: W) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static
+    {
+        Self {
+            reader: Box::new(reader),
+            writer: Box::new(writer),
+            transactions: None
+        }
+    }
+
+    pub fn with_transactions(mut self, stream: TransportStream) -> Self {
+        self.transactions = Some(Box::new(stream));
+        self
+    }
+}
+
+// Accept the streams opened by the peers
+#[async_trait]
+pub trait TransportListener: Send + Sync {
+    // returns the stream and the address of the peer
+    // must be cancel safe as it is used in a select loop
+    async fn accept(&self) -> io::Result<(TransportStream, SocketAddr)>;
+
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+// Carry the packets between the peers
+// the packet protocol (size prefix, handshake, packets) is the same whatever the transport,
+// a transport only has to provide an ordered and reliable byte stream (TCP, WebSocket, QUIC stream...)
+#[async_trait]
+pub trait Transport: Send + Sync {
+    // name displayed in the logs
+    fn get_name(&self) -> &'static str;
+
+    async fn bind(&self, addr: &SocketAddr) -> io::Result<Box<dyn TransportListener>>;
+
+    async fn connect(&self, addr: &SocketAddr) -> io::Result<TransportStream>;
+
+    // key that the peer at this address must present when we connect to it, as given in its address
+    // only supported by the transports authenticating the peers
+    fn pin_peer_key(&self, _: &SocketAddr, _: &str) -> Result<(), P2pError> {
+        Err(P2pError::PeerKeyNotSupported(self.get_name()))
+    }
+}
+
+// Transports used by the P2P server, all of them listen on the bind address
+// a peer is reached using the default transport, unless another one is configured for it
+pub struct Transports {
+    default: Box<dyn Transport>,
+    others: Vec<Box<dyn Transport>>,
+    // peers to reach using another transport, with its index in others
+    peers: HashMap<SocketAddr, usize>
+}
+
+impl Transports {
+    pub fn new(default: Box<dyn Transport>) -> Self {
+        Self {
+            default,
+            others: Vec::new(),
+            peers: HashMap::new()
+        }
+    }
+
+    pub fn add(&mut self, transport: Box<dyn Transport>) {
+        self.others.push(transport);
+    }
+
+    pub fn get_default(&self) -> &dyn Transport {
+        self.default.as_ref()
+    }
+
+    pub fn get_others(&self) -> &[Box<dyn Transport>] {
+        &self.others
+    }
+
+    pub fn get_for(&self, addr: &SocketAddr) -> &dyn Transport {
+        match self.peers.get(addr) {
+            Some(index) => self.others[*index].as_ref(),
+            None => self.get_default()
+        }
+    }
+
+    // parse a peer address, it can be prefixed by the transport to use such as "quic://127.0.0.1:2125"
+    // and the key of the peer can be pinned before the address such as "quic://<key>@127.0.0.1:2125"
+    pub fn parse_peer_address(&mut self, value: &str) -> Result<SocketAddr, P2pError> {
+        let Some((name, addr)) = value.split_once("://") else {
+            return Ok(value.parse()?)
+        };
+
+        let (key, addr) = match addr.split_once('@') {
+            Some((key, addr)) => (Some(key), addr),
+            None => (None, addr)
+        };
+        let addr: SocketAddr = addr.parse()?;
+        let transport = if name == self.default.get_name() {
+            self.peers.remove(&addr);
+            self.default.as_ref()
+        } else {
+            let index = self.others.iter()
+                .position(|transport| transport.get_name() == name)
+                .ok_or_else(|| P2pError::UnknownTransport(name.to_owned()))?;
+            self.peers.insert(addr, index);
+            self.others[index].as_ref()
+        };
+
+        if let Some(key) = key {
+            transport.pin_peer_key(&addr, key)?;
+        }
+        Ok(addr)
+    }
+}
+
+// Default transport
+pub struct TcpTransport;
+
+struct TcpTransportListener {
+    listener: TcpListener
+}
+
+#[async_trait]
+impl TransportListener for TcpTransportListener {
+    async fn accept(&self) -> io::Result<(TransportStream, SocketAddr)> {
+        let (stream, addr) = self.listener.accept().await?;
+        Ok((stream.into(), addr))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+#[async_trait]
+impl Transport for TcpTransport {
+    fn get_name(&self) -> &'static str {
+        "tcp"
+    }
+
+    async fn bind(&self, addr: &SocketAddr) -> io::Result<Box<dyn TransportListener>> {
+        let listener = TcpListener::bind(addr).await?;
+        Ok(Box::new(TcpTransportListener { listener }))
+    }
+
+    async fn connect(&self, addr: &SocketAddr) -> io::Result<TransportStream> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(stream.into())
+    }
+}
+
+impl From<TcpStream> for TransportStream {
+    fn from(stream: TcpStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self::new(reader, writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tcp_transport() {
+        let transport = TcpTransport;
+        let listener = transport.bind(&"127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = transport.connect(&addr).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.writer.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        server.reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[test]
+    fn test_parse_peer_address() {
+        let mut transports = Transports::new(Box::new(TcpTransport));
+        let addr: SocketAddr = "127.0.0.1:2125".parse().unwrap();
+        assert_eq!(transports.parse_peer_address("127.0.0.1:2125").unwrap(), addr);
+        assert_eq!(transports.parse_peer_address("tcp://127.0.0.1:2125").unwrap(), addr);
+        assert_eq!(transports.get_for(&addr).get_name(), "tcp");
+        assert!(matches!(transports.parse_peer_address("unknown://127.0.0.1:2125"), Err(P2pError::UnknownTransport(_))));
+        assert!(transports.parse_peer_address("tcp://127.0.0.1").is_err());
+        // TCP doesn't authenticate the peers
+        assert!(matches!(transports.parse_peer_address("tcp://key@127.0.0.1:2125"), Err(P2pError::PeerKeyNotSupported("tcp"))));
+    }
+
+    #[tokio::test]
+    async fn test_custom_stream() {
+        // any ordered byte stream can be used, such as an in memory one
+        let (a, b) = duplex(64);
+        let (a_reader, a_writer) = tokio::io::split(a);
+        let (b_reader, b_writer) = tokio::io::split(b);
+        let mut a = TransportStream::new(a_reader, a_writer);
+        let mut b = TransportStream::new(b_reader, b_writer);
+
+        a.writer.write_all(b"xelis").await.unwrap();
+        let mut buf = [0; 5];
+        b.reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"xelis");
+    }
+}
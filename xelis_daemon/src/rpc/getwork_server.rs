@@ -2,18 +2,27 @@ use std::{sync::Arc, collections::HashMap, fmt::Display, borrow::Cow};
 use actix::{Actor, AsyncContext, Handler, Message as TMessage, StreamHandler, Addr};
 use actix_web_actors::ws::{ProtocolError, Message, WebsocketContext};
 use anyhow::Context;
-use log::{debug, warn, error};
+use log::{debug, trace, warn, error};
 use lru::LruCache;
 use rand::{rngs::OsRng, RngCore};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::sync::Mutex;
 use xelis_common::{
     crypto::{key::PublicKey, hash::Hash},
-    utils::get_current_timestamp,
-    api::daemon::{GetBlockTemplateResult, SubmitBlockParams},
+    utils::{get_current_time, get_current_timestamp},
+    api::daemon::{
+        BlockRejection,
+        GetBlockTemplateResult,
+        GetMinersResult,
+        GetPowSeedResult,
+        MinerDifficultyHint,
+        MinerEntry,
+        ReportHashrateParams,
+        SubmitBlockParams
+    },
     serializer::Serializer,
-    block::{BlockHeader, BlockMiner, Difficulty},
+    block::{set_worker_tag, get_worker_tag, BlockHeader, BlockMiner, Difficulty},
     immutable::Immutable,
     rpc_server::{RpcResponseError, InternalRpcError}
 };
@@ -21,6 +30,7 @@ use crate::{
     core::{blockchain::Blockchain, storage::Storage},
     config::{DEV_PUBLIC_KEY, STABLE_LIMIT}
 };
+use super::{ApiError, get_error_code};
 
 pub type SharedGetWorkServer<S> = Arc<GetWorkServer<S>>;
 
@@ -28,7 +38,19 @@ pub type SharedGetWorkServer<S> = Arc<GetWorkServer<S>>;
 pub enum Response {
     NewJob(GetBlockTemplateResult),
     BlockAccepted,
-    BlockRejected
+    BlockRejected(BlockRejection),
+    // only sent to the miners reporting their hashrate
+    DifficultyHint(MinerDifficultyHint),
+    // sent on connection and when the seed of the next epoch is known or activated
+    NewPowSeed(GetPowSeedResult)
+}
+
+// messages sent by the miners
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Request {
+    SubmitBlock(SubmitBlockParams),
+    ReportHashrate(ReportHashrateParams)
 }
 
 impl TMessage for Response {
@@ -39,7 +61,10 @@ pub struct Miner {
     first_seen: u128, // timestamp of first connection
     key: PublicKey, // public key of account (address)
     name: String, // worker name
-    blocks_found: usize, // blocks found since he is connected
+    blocks_accepted: usize, // blocks accepted since he is connected
+    blocks_rejected: usize, // blocks rejected since he is connected
+    last_block_submitted: Option<u64>, // timestamp in seconds
+    hashrate: Option<f64> // last hashrate reported by the miner
 }
 
 impl Miner {
@@ -48,7 +73,10 @@ impl Miner {
             first_seen: get_current_timestamp(),
             key,
             name,
-            blocks_found: 0
+            blocks_accepted: 0,
+            blocks_rejected: 0,
+            last_block_submitted: None,
+            hashrate: None
         }
     }
 
@@ -64,8 +92,51 @@ impl Miner {
         &self.name
     }
 
-    pub fn get_blocks_found(&self) -> usize {
-        self.blocks_found
+    pub fn get_blocks_accepted(&self) -> usize {
+        self.blocks_accepted
+    }
+
+    pub fn get_blocks_rejected(&self) -> usize {
+        self.blocks_rejected
+    }
+
+    pub fn get_hashrate(&self) -> Option<f64> {
+        self.hashrate
+    }
+
+    fn add_block_result(&mut self, accepted: bool) {
+        if accepted {
+            self.blocks_accepted += 1;
+        } else {
+            self.blocks_rejected += 1;
+        }
+        self.last_block_submitted = Some(get_current_time());
+    }
+
+    fn to_entry(&self, mainnet: bool) -> MinerEntry {
+        MinerEntry {
+            address: self.key.to_address(mainnet),
+            worker: self.name.clone(),
+            connected_on: (self.first_seen / 1000) as u64,
+            hashrate: self.hashrate,
+            blocks_accepted: self.blocks_accepted,
+            blocks_rejected: self.blocks_rejected,
+            last_block_submitted: self.last_block_submitted
+        }
+    }
+}
+
+fn build_difficulty_hint(difficulty: Difficulty, hashrate: f64) -> MinerDifficultyHint {
+    let estimated_block_time = if hashrate > 0f64 {
+        (difficulty as f64 / hashrate) as u64
+    } else {
+        u64::MAX
+    };
+
+    MinerDifficultyHint {
+        difficulty,
+        hashrate,
+        estimated_block_time
     }
 }
 
@@ -97,8 +168,8 @@ impl<S: Storage> StreamHandler<Result<Message, ProtocolError>> for GetWorkWebSoc
             Ok(Message::Text(text)) => {
                 debug!("New message incoming from miner: {}", text);
                 let address = ctx.address();
-                let template: SubmitBlockParams = match serde_json::from_slice(text.as_bytes()) {
-                    Ok(template) => template,
+                let request: Request = match serde_json::from_slice(text.as_bytes()) {
+                    Ok(request) => request,
                     Err(e) => {
                         debug!("Error while decoding message from {:?}: {}", address, e);
                         return;
@@ -107,9 +178,14 @@ impl<S: Storage> StreamHandler<Result<Message, ProtocolError>> for GetWorkWebSoc
 
                 let server = self.server.clone();
                 let fut = async move {
-                    if let Err(e) = server.handle_block_for(address, template).await {
-                        debug!("Error while handling new job from miner: {}", e);
-                    }
+                    match request {
+                        Request::SubmitBlock(template) => {
+                            if let Err(e) = server.handle_block_for(address, template).await {
+                                debug!("Error while handling new job from miner: {}", e);
+                            }
+                        },
+                        Request::ReportHashrate(params) => server.set_miner_hashrate(address, params.hashrate).await
+                    };
                 };
                 ctx.wait(actix::fut::wrap_future(fut));
             },
@@ -153,6 +229,8 @@ pub struct GetWorkServer<S: Storage> {
     // so even a late miner have a chance to not be orphaned and be included in chain
     mining_jobs: Mutex<LruCache<Hash, (BlockHeader, Difficulty)>>,
     last_header_hash: Mutex<Option<Hash>>,
+    // PoW seed sent with the last job
+    last_pow_seed: Mutex<Option<GetPowSeedResult>>,
     // used only when a new TX is received in mempool
     last_notify: Mutex<u128>,
     notify_rate_limit_ms: u128
@@ -165,6 +243,7 @@ impl<S: Storage> GetWorkServer<S> {
             blockchain,
             mining_jobs: Mutex::new(LruCache::new(STABLE_LIMIT as usize)),
             last_header_hash: Mutex::new(None),
+            last_pow_seed: Mutex::new(None),
             last_notify: Mutex::new(0),
             notify_rate_limit_ms: 500 // maximum one time every 500ms
         }
@@ -174,9 +253,54 @@ impl<S: Storage> GetWorkServer<S> {
         self.miners.lock().await.len()
     }
 
+    pub async fn get_miners(&self) -> GetMinersResult {
+        let mainnet = self.blockchain.get_network().is_mainnet();
+        let miners = self.miners.lock().await;
+        let mut entries: Vec<MinerEntry> = miners.values().map(|miner| miner.to_entry(mainnet)).collect();
+        entries.sort_by_key(|entry| entry.connected_on);
+
+        GetMinersResult {
+            total_hashrate: miners.values().filter_map(Miner::get_hashrate).sum(),
+            miners: entries
+        }
+    }
+
+    // difficulty of the last job sent to the miners
+    async fn get_last_job_difficulty(&self) -> Option<Difficulty> {
+        let hash = self.last_header_hash.lock().await;
+        let mining_jobs = self.mining_jobs.lock().await;
+        hash.as_ref().and_then(|hash| mining_jobs.peek(hash)).map(|(_, difficulty)| *difficulty)
+    }
+
+    // save the hashrate reported by a miner, a hint is sent on its first report
+    // then each time the difficulty changes
+    async fn set_miner_hashrate(&self, addr: Addr<GetWorkWebSocketHandler<S>>, hashrate: f64) {
+        if !hashrate.is_finite() || hashrate < 0f64 {
+            debug!("Invalid hashrate reported by {:?}: {}", addr, hashrate);
+            return;
+        }
+
+        let first_report = {
+            let mut miners = self.miners.lock().await;
+            let Some(miner) = miners.get_mut(&addr) else {
+                return;
+            };
+            trace!("{} reported a hashrate of {}", miner, hashrate);
+            miner.hashrate.replace(hashrate).is_none()
+        };
+
+        if first_report {
+            if let Some(difficulty) = self.get_last_job_difficulty().await {
+                if let Err(e) = addr.send(Response::DifficultyHint(build_difficulty_hint(difficulty, hashrate))).await {
+                    debug!("Error while sending difficulty hint to {:?}: {}", addr, e);
+                }
+            }
+        }
+    }
+
     // retrieve last mining job and set random extra nonce and miner public key
     // then, send it
-    async fn send_new_job(self: Arc<Self>, addr: Addr<GetWorkWebSocketHandler<S>>, key: PublicKey) -> Result<(), InternalRpcError> {
+    async fn send_new_job(self: Arc<Self>, addr: Addr<GetWorkWebSocketHandler<S>>, key: PublicKey, worker: String) -> Result<(), InternalRpcError> {
         let (mut job, height, difficulty) = {
             let mut hash = self.last_header_hash.lock().await;
             let mut mining_jobs = self.mining_jobs.lock().await;
@@ -186,8 +310,8 @@ impl<S: Storage> GetWorkServer<S> {
                     error!("No mining job found! How is it possible ?");
                     InternalRpcError::InvalidRequest
                 })?;
-                job = BlockMiner::new(header.get_work_hash(), get_current_timestamp());
-                height = header.height;
+                job = BlockMiner::new(header.get_work_hash(), self.blockchain.get_adjusted_timestamp().max(header.get_timestamp()));
+                height = header.get_height();
                 difficulty = *diff;
             } else {
                 // generate a mining job
@@ -195,8 +319,8 @@ impl<S: Storage> GetWorkServer<S> {
                 let header = self.blockchain.get_block_template_for_storage(&storage, DEV_PUBLIC_KEY.clone()).await.context("Error while retrieving block template")?;
                 difficulty = self.blockchain.get_difficulty_at_tips(&*storage, header.get_tips()).await.context("Error while retrieving difficulty at tips")?;
 
-                job = BlockMiner::new(header.get_work_hash(), get_current_timestamp());
-                height = header.height;
+                job = BlockMiner::new(header.get_work_hash(), self.blockchain.get_adjusted_timestamp().max(header.get_timestamp()));
+                height = header.get_height();
 
                 // save the mining job, and set it as last job
                 *hash = Some(job.header_work_hash.clone());
@@ -206,19 +330,36 @@ impl<S: Storage> GetWorkServer<S> {
             (job, height, difficulty)
         };
 
-        // set miner key and random extra nonce
+        // set miner key and random extra nonce tagged with the worker name
         job.miner = Some(Cow::Owned(key));
         OsRng.fill_bytes(&mut job.extra_nonce);
+        set_worker_tag(&mut job.extra_nonce, &worker);
 
         debug!("Sending job to new miner");
-        addr.send(Response::NewJob(GetBlockTemplateResult { template: job.to_hex(), height, difficulty })).await.context("error while sending block template")??;
+        let topoheight = self.blockchain.get_topo_height();
+        addr.send(Response::NewJob(GetBlockTemplateResult { template: job.to_hex(), height, topoheight, difficulty })).await.context("error while sending block template")??;
+        Ok(())
+    }
+
+    async fn send_pow_seed(&self, addr: &Addr<GetWorkWebSocketHandler<S>>) -> Result<(), InternalRpcError> {
+        // the miners don't need any seed until a hard fork uses it
+        if !self.blockchain.is_pow_seed_used() {
+            return Ok(())
+        }
+
+        let seed = {
+            let storage = self.blockchain.get_storage().read().await;
+            self.blockchain.get_pow_seed(&*storage).await.context("Error while retrieving PoW seed")?
+        };
+
+        addr.send(Response::NewPowSeed(seed)).await.context("error while sending PoW seed")??;
         Ok(())
     }
 
     pub async fn add_miner(self: &Arc<Self>, addr: Addr<GetWorkWebSocketHandler<S>>, key: PublicKey, worker: String) {
         {
             let mut miners = self.miners.lock().await;
-            let miner = Miner::new(key.clone(), worker);
+            let miner = Miner::new(key.clone(), worker.clone());
             debug!("Adding new miner to GetWork server: {}", miner);
             miners.insert(addr.clone(), miner);
         }
@@ -226,7 +367,12 @@ impl<S: Storage> GetWorkServer<S> {
         // notify the new miner so he can work ASAP
         let zelf = Arc::clone(&self);
         tokio::spawn(async move {
-            if let Err(e) = zelf.send_new_job(addr, key).await {
+            // the seed is sent first so the miner can prepare its dataset
+            if let Err(e) = zelf.send_pow_seed(&addr).await {
+                error!("Error while sending PoW seed to miner: {}", e);
+            }
+
+            if let Err(e) = zelf.send_new_job(addr, key, worker).await {
                 error!("Error while sending new job to miner: {}", e);
             }
         });
@@ -244,34 +390,49 @@ impl<S: Storage> GetWorkServer<S> {
     // we retrieve the block header saved in cache using the mining job "header_work_hash"
     // its used to check that the job come from our server
     // when it's found, we merge the miner job inside the block header
+    // the header is pre-verified so an invalid block is rejected before searching its TXs
     async fn accept_miner_job(&self, job: BlockMiner<'_>) -> Result<Response, InternalRpcError> {
         if job.miner.is_none() {
             return Err(InternalRpcError::InvalidRequest);
         }
 
+        // only the bytes after the worker tag set by the daemon can be changed by the miner
+        if get_worker_tag(&job.extra_nonce).is_none() {
+            return Err(InternalRpcError::AnyError(ApiError::InvalidExtraNonce.into()));
+        }
+
         let mut miner_header;
         {
             let mining_jobs = self.mining_jobs.lock().await;
             if let Some((header, _)) = mining_jobs.peek(&job.header_work_hash) {
                 // job is found in cache, clone it and put miner data inside
                 miner_header = header.clone();
-                miner_header.nonce = job.nonce;
-                miner_header.extra_nonce = job.extra_nonce;
+                miner_header.set_nonce(job.nonce);
+                miner_header.set_extra_nonce(job.extra_nonce);
                 miner_header.set_miner(job.miner.ok_or(InternalRpcError::InvalidRequest)?.into_owned());
-                miner_header.timestamp = job.timestamp;
+                miner_header.set_timestamp(job.timestamp);
             } else {
                 // really old job, or miner send invalid job
                 debug!("Job {} was not found in cache", job.header_work_hash);
-                return Err(InternalRpcError::InvalidRequest)
+                return Err(InternalRpcError::AnyError(ApiError::UnknownMiningJob.into()))
             };
         }
 
+        if let Err(e) = self.blockchain.pre_verify_block_header(&miner_header).await {
+            debug!("Invalid block header submitted by miner: {}", e);
+            return Ok(Response::BlockRejected(BlockRejection { code: e.get_error_code(), message: e.to_string() }))
+        }
+
         let block = self.blockchain.build_block_from_header(Immutable::Owned(miner_header)).await.context("Error while building block from header")?;
+        let worker = get_worker_tag(block.get_extra_nonce()).unwrap_or("unknown").to_owned();
         Ok(match self.blockchain.add_new_block(block, true, true).await {
-            Ok(_) => Response::BlockAccepted,
+            Ok(_) => {
+                debug!("Block found by worker {} accepted", worker);
+                Response::BlockAccepted
+            },
             Err(e) => {
                 debug!("Error while accepting miner block: {}", e);
-                Response::BlockRejected
+                Response::BlockRejected(BlockRejection { code: e.get_error_code(), message: e.to_string() })
             }
         })
     }
@@ -285,12 +446,16 @@ impl<S: Storage> GetWorkServer<S> {
             Ok(response) => response,
             Err(e) => {
                 debug!("Error while accepting miner job: {}", e);
-                Response::BlockRejected
+                Response::BlockRejected(BlockRejection { code: get_error_code(&e), message: e.to_string() })
             }
         };
 
+        if let Some(miner) = self.miners.lock().await.get_mut(&addr) {
+            miner.add_block_result(response == Response::BlockAccepted);
+        }
+
         tokio::spawn(async move {
-            let resend_job = response == Response::BlockRejected;
+            let resend_job = matches!(response, Response::BlockRejected(_));
             debug!("Sending response to the miner");
             if let Err(e) = addr.send(response).await {
                 error!("Error while sending block rejected response: {}", e);
@@ -298,17 +463,17 @@ impl<S: Storage> GetWorkServer<S> {
 
             if resend_job {
                 debug!("Resending job to the miner");
-                let key = {
+                let miner = {
                     let miners = self.miners.lock().await;
                     if let Some(miner) = miners.get(&addr) {
-                        Some(miner.get_public_key().clone())
+                        Some((miner.get_public_key().clone(), miner.get_name().clone()))
                     } else {
                         error!("Miner not found in the list of miners! (should not happen)");
                         None
                     }
                 };
-                if let Some(key) = key {
-                    if let Err(e) = self.send_new_job(addr, key).await {
+                if let Some((key, worker)) = miner {
+                    if let Err(e) = self.send_new_job(addr, key, worker).await {
                         error!("Error while sending new job to miner: {}", e);
                     };
                 }
@@ -323,9 +488,9 @@ impl<S: Storage> GetWorkServer<S> {
     // each miner have his own task so nobody wait on other
     pub async fn notify_new_job_rate_limited(&self) -> Result<(), InternalRpcError> {
         {
-            let now = get_current_timestamp();
+            let now = self.blockchain.get_time_sync().get_clock().now();
             let mut last_notify = self.last_notify.lock().await;
-            if now - *last_notify < self.notify_rate_limit_ms {
+            if now.saturating_sub(*last_notify) < self.notify_rate_limit_ms {
                 debug!("Rate limit reached, not notifying miners");
                 return Ok(());
             }
@@ -349,15 +514,38 @@ impl<S: Storage> GetWorkServer<S> {
         }    
     
         debug!("Notify all miners for a new job");
-        let (header, difficulty) = {
+        let (header, difficulty, seed) = {
             let storage = self.blockchain.get_storage().read().await;
             let header = self.blockchain.get_block_template_for_storage(&storage, DEV_PUBLIC_KEY.clone()).await.context("Error while retrieving block template when notifying new job")?;
             let difficulty = self.blockchain.get_difficulty_at_tips(&*storage, header.get_tips()).await.context("Error while retrieving difficulty at tips when notifying new job")?;
-            (header, difficulty)
+            let seed = if self.blockchain.is_pow_seed_used() {
+                Some(self.blockchain.get_pow_seed(&*storage).await.context("Error while retrieving PoW seed when notifying new job")?)
+            } else {
+                None
+            };
+            (header, difficulty, seed)
         };
 
-        let mut job = BlockMiner::new(header.get_work_hash(), header.timestamp);
-        let height = header.height;
+        // the new seed is sent before the job using it
+        let seed = match seed {
+            Some(seed) => {
+                let mut last_pow_seed = self.last_pow_seed.lock().await;
+                if last_pow_seed.as_ref() != Some(&seed) {
+                    *last_pow_seed = Some(seed.clone());
+                    Some(seed)
+                } else {
+                    None
+                }
+            },
+            None => None
+        };
+
+        let mut job = BlockMiner::new(header.get_work_hash(), header.get_timestamp());
+        let height = header.get_height();
+        let topoheight = self.blockchain.get_topo_height();
+
+        // the miners reporting their hashrate receive a hint when the difficulty changes
+        let retarget = self.get_last_job_difficulty().await.is_some_and(|last| last != difficulty);
 
         // save the header used for job in cache
         {
@@ -377,12 +565,23 @@ impl<S: Storage> GetWorkServer<S> {
 
             job.miner = Some(Cow::Borrowed(miner.get_public_key()));
             OsRng.fill_bytes(&mut job.extra_nonce);
+            set_worker_tag(&mut job.extra_nonce, miner.get_name());
             let template = job.to_hex();
+            let hint = miner.get_hashrate()
+                .filter(|_| retarget)
+                .map(|hashrate| build_difficulty_hint(difficulty, hashrate));
+            let seed = seed.clone();
 
             // New task for each miner in case a miner is slow
             // we don't want to wait for him
             tokio::spawn(async move {
-                match addr.send(Response::NewJob(GetBlockTemplateResult { template, height, difficulty })).await {
+                if let Some(seed) = seed {
+                    if let Err(e) = addr.send(Response::NewPowSeed(seed)).await {
+                        debug!("Error while sending PoW seed to addr {:?}: {}", addr, e);
+                    }
+                }
+
+                match addr.send(Response::NewJob(GetBlockTemplateResult { template, height, topoheight, difficulty })).await {
                     Ok(request) => {
                         if let Err(e) = request {
                             warn!("Error while sending new job to addr {:?}: {}", addr, e);
@@ -392,8 +591,65 @@ impl<S: Storage> GetWorkServer<S> {
                         warn!("Error while notifying new job to addr {:?}: {}", addr, e);
                     }
                 }
+
+                if let Some(hint) = hint {
+                    if let Err(e) = addr.send(Response::DifficultyHint(hint)).await {
+                        debug!("Error while sending difficulty hint to addr {:?}: {}", addr, e);
+                    }
+                }
             });
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use xelis_common::block::{EXTRA_NONCE_SIZE, WORKER_TAG_SIZE};
+    use super::*;
+
+    #[test]
+    fn test_decode_request() {
+        let request: Request = serde_json::from_str(r#"{"block_template":"00"}"#).unwrap();
+        assert!(matches!(request, Request::SubmitBlock(params) if params.block_template == "00"));
+
+        let request: Request = serde_json::from_str(r#"{"hashrate":1500.5}"#).unwrap();
+        assert!(matches!(request, Request::ReportHashrate(params) if params.hashrate == 1500.5));
+
+        assert!(serde_json::from_str::<Request>(r#"{"unknown":1}"#).is_err());
+    }
+
+    #[test]
+    fn test_difficulty_hint() {
+        let hint = build_difficulty_hint(150_000, 1_000f64);
+        assert_eq!(hint.estimated_block_time, 150);
+        assert_eq!(build_difficulty_hint(150_000, 0f64).estimated_block_time, u64::MAX);
+    }
+
+    #[test]
+    fn test_block_rejected_response() {
+        let error = InternalRpcError::AnyError(ApiError::UnknownMiningJob.into());
+        let response = Response::BlockRejected(BlockRejection { code: get_error_code(&error), message: error.to_string() });
+        assert_eq!(json!(response), json!({"BlockRejected": {"code": "stale_block", "message": "Mining job not found, it may be too old"}}));
+
+        // internal errors are sent without code
+        let error = InternalRpcError::InvalidRequest;
+        assert_eq!(get_error_code(&error), None);
+    }
+
+    #[test]
+    fn test_worker_tag() {
+        let mut extra_nonce = [0xFFu8; EXTRA_NONCE_SIZE];
+        assert_eq!(get_worker_tag(&extra_nonce), None);
+
+        set_worker_tag(&mut extra_nonce, "rig-1");
+        assert_eq!(get_worker_tag(&extra_nonce), Some("rig-1"));
+        // the bytes after the tag are kept for the miner
+        assert_eq!(extra_nonce[WORKER_TAG_SIZE..], [0xFFu8; EXTRA_NONCE_SIZE - WORKER_TAG_SIZE]);
+
+        // long names are truncated on a char boundary
+        set_worker_tag(&mut extra_nonce, "a-very-long-worker-name");
+        assert_eq!(get_worker_tag(&extra_nonce), Some("a-very-long-wo"));
+        set_worker_tag(&mut extra_nonce, "rig-éééééééé");
+        assert_eq!(get_worker_tag(&extra_nonce), Some("rig-ééééé"));
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    sync::{atomic::{AtomicU64, Ordering}, mpsc::{self, Receiver, SyncSender, TrySendError}},
+    thread
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use log::{error, warn};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+use xelis_common::{
+    rpc_server::{RPCHook, RpcRequest, InternalRpcError},
+    utils::get_current_timestamp
+};
+use crate::config::{RPC_JOURNAL_MAX_VALUE_SIZE, RPC_JOURNAL_REDACTED_FIELDS, RPC_JOURNAL_WRITER_QUEUE_SIZE};
+
+const REDACTED_VALUE: &str = "<redacted>";
+
+// RPC request executed and its response, as saved in the journal
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    // incremented for each request journaled since the daemon started
+    pub sequence: u64,
+    // timestamp in milliseconds
+    pub timestamp: u64,
+    pub method: String,
+    pub id: Option<usize>,
+    pub params: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>
+}
+
+// Appends the journal lines to its file from its own thread, so the RPC server never waits for the disk
+// once the file reaches its maximum size, it is renamed with a `.1` suffix (replacing the previous one) and a new file is started
+struct JournalWriter {
+    path: String,
+    file: File,
+    // current size of the file in bytes
+    size: u64,
+    max_size: u64
+}
+
+impl JournalWriter {
+    fn open(path: &str) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        fs::rename(&self.path, format!("{}.1", self.path))?;
+        self.file = Self::open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    // stopped once the journal is dropped
+    fn run(mut self, receiver: Receiver<Vec<u8>>) {
+        while let Ok(line) = receiver.recv() {
+            if let Err(e) = self.write(&line) {
+                warn!("Error while writing in RPC journal file {}: {}", self.path, e);
+            }
+        }
+    }
+}
+
+// Opt-in journal of the last requests executed by the RPC server, to debug the integrations
+// entries are kept in memory and can also be appended to a file, one JSON per line
+// values of the sensitive fields are redacted and values too big are truncated before being saved
+// positional params (array) have no field names, so their values are not redacted
+pub struct RequestJournal {
+    entries: Mutex<VecDeque<JournalEntry>>,
+    // maximum entries kept in memory
+    size: usize,
+    // lines waiting to be written in the journal file
+    file: Option<SyncSender<Vec<u8>>>,
+    // lowercase names of the fields to redact
+    redacted_fields: HashSet<String>,
+    sequence: AtomicU64
+}
+
+impl RequestJournal {
+    // max_file_size is in bytes
+    pub fn new(size: usize, path: Option<String>, max_file_size: u64, redacted_fields: Vec<String>) -> Result<Self, anyhow::Error> {
+        let file = match path {
+            Some(path) => {
+                let file = JournalWriter::open(&path)
+                    .context(format!("Error while opening RPC journal file {}", path))?;
+                let writer = JournalWriter {
+                    size: file.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+                    path,
+                    file,
+                    max_size: max_file_size
+                };
+
+                let (sender, receiver) = mpsc::sync_channel(RPC_JOURNAL_WRITER_QUEUE_SIZE);
+                thread::Builder::new()
+                    .name("rpc-journal-writer".to_owned())
+                    .spawn(move || writer.run(receiver))
+                    .context("Error while starting RPC journal writer thread")?;
+                Some(sender)
+            },
+            None => None
+        };
+
+        Ok(Self {
+            entries: Mutex::new(VecDeque::with_capacity(size)),
+            size,
+            file,
+            redacted_fields: RPC_JOURNAL_REDACTED_FIELDS.iter()
+                .map(|field| field.to_string())
+                .chain(redacted_fields.into_iter().map(|field| field.to_lowercase()))
+                .collect(),
+            sequence: AtomicU64::new(0)
+        })
+    }
+
+    // last requests journaled in memory, oldest first
+    pub async fn get_recent_requests(&self, method: Option<&str>, limit: usize) -> Vec<JournalEntry> {
+        let entries = self.entries.lock().await;
+        let mut requests: Vec<JournalEntry> = entries.iter()
+            .rev()
+            .filter(|entry| method.is_none_or(|method| entry.method == method))
+            .take(limit)
+            .cloned()
+            .collect();
+        requests.reverse();
+        requests
+    }
+
+    fn sanitize(&self, mut value: Value) -> Value {
+        self.redact(&mut value);
+        let size = value.to_string().len();
+        if size > RPC_JOURNAL_MAX_VALUE_SIZE {
+            return Value::String(format!("<truncated: {} bytes>", size))
+        }
+        value
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => for (name, value) in fields.iter_mut() {
+                if self.redacted_fields.contains(&name.to_lowercase()) {
+                    *value = Value::String(REDACTED_VALUE.to_owned());
+                } else {
+                    self.redact(value);
+                }
+            },
+            Value::Array(values) => for value in values {
+                self.redact(value);
+            },
+            _ => {}
+        }
+    }
+
+    async fn add_entry(&self, entry: JournalEntry) {
+        if let Some(file) = &self.file {
+            match serde_json::to_vec(&entry) {
+                Ok(mut line) => {
+                    line.push(b'\n');
+                    match file.try_send(line) {
+                        Ok(()) => {},
+                        // the disk is too slow, don't block the RPC server
+                        Err(TrySendError::Full(_)) => warn!("RPC journal file queue is full, request {} is not written", entry.sequence),
+                        Err(TrySendError::Disconnected(_)) => error!("RPC journal writer has stopped, request {} is not written", entry.sequence)
+                    };
+                },
+                Err(e) => warn!("Error while serializing request {} for RPC journal: {}", entry.sequence, e)
+            };
+        }
+
+        // journal is only written in the file
+        if self.size == 0 {
+            return
+        }
+
+        let mut entries = self.entries.lock().await;
+        if entries.len() >= self.size {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+#[async_trait]
+impl RPCHook for RequestJournal {
+    async fn after_execution(&self, request: &RpcRequest, result: &Result<Value, InternalRpcError>) {
+        let (result, error) = match result {
+            Ok(value) => (Some(self.sanitize(value.clone())), None),
+            Err(e) => (None, Some(e.to_string()))
+        };
+
+        let entry = JournalEntry {
+            sequence: self.sequence.fetch_add(1, Ordering::Relaxed),
+            timestamp: get_current_timestamp() as u64,
+            method: request.method.clone(),
+            id: request.id,
+            params: self.sanitize(request.params.clone().unwrap_or(Value::Null)),
+            result,
+            error
+        };
+        self.add_entry(entry).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    fn request(method: &str, params: Value) -> RpcRequest {
+        RpcRequest {
+            jsonrpc: "2.0".to_owned(),
+            id: Some(1),
+            method: method.to_owned(),
+            params: Some(params)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redacted_fields() {
+        let journal = RequestJournal::new(10, None, 0, vec!["Address".to_owned()]).unwrap();
+        let params = json!({ "address": "xet1", "nested": [{ "PASSWORD": "hunter2", "amount": 5 }] });
+        journal.after_execution(&request("transfer", params), &Ok(json!({ "token": "abc" }))).await;
+
+        let entry = &journal.get_recent_requests(None, 10).await[0];
+        assert_eq!(entry.params, json!({ "address": REDACTED_VALUE, "nested": [{ "PASSWORD": REDACTED_VALUE, "amount": 5 }] }));
+        assert_eq!(entry.result, Some(json!({ "token": REDACTED_VALUE })));
+
+        // positional params can't be matched with a field name
+        journal.after_execution(&request("transfer", json!(["xet1", "hunter2"])), &Ok(Value::Null)).await;
+        let entry = &journal.get_recent_requests(None, 10).await[1];
+        assert_eq!(entry.params, json!(["xet1", "hunter2"]));
+    }
+
+    #[tokio::test]
+    async fn test_recent_requests() {
+        let journal = RequestJournal::new(3, None, 0, Vec::new()).unwrap();
+        for i in 0..5 {
+            let method = if i % 2 == 0 { "get_info" } else { "get_height" };
+            journal.after_execution(&request(method, Value::Null), &Err(InternalRpcError::InvalidRequest)).await;
+        }
+
+        // only the last 3 requests are kept, oldest first
+        let sequences: Vec<u64> = journal.get_recent_requests(None, 10).await.iter().map(|entry| entry.sequence).collect();
+        assert_eq!(sequences, vec![2, 3, 4]);
+        let sequences: Vec<u64> = journal.get_recent_requests(Some("get_info"), 1).await.iter().map(|entry| entry.sequence).collect();
+        assert_eq!(sequences, vec![4]);
+
+        let big = json!({ "data": "0".repeat(RPC_JOURNAL_MAX_VALUE_SIZE) });
+        journal.after_execution(&request("submit_block", big), &Ok(Value::Null)).await;
+        let entry = &journal.get_recent_requests(Some("submit_block"), 1).await[0];
+        assert!(entry.params.as_str().is_some_and(|value| value.starts_with("<truncated")));
+    }
+
+    #[tokio::test]
+    async fn test_file_rotation() {
+        let path = std::env::temp_dir().join(format!("xelis_rpc_journal_{}.log", std::process::id()));
+        let rotated = format!("{}.1", path.display());
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&rotated);
+
+        // each line is bigger than half of the maximum size, so the file is rotated at each line
+        let journal = RequestJournal::new(0, Some(path.display().to_string()), 150, Vec::new()).unwrap();
+        for _ in 0..3 {
+            journal.after_execution(&request("get_info", Value::Null), &Ok(Value::Null)).await;
+        }
+
+        // lines are written in background
+        let content = |path: &str| fs::read_to_string(path).unwrap_or_default();
+        while !content(&path.display().to_string()).contains("\"sequence\":2") {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(content(&path.display().to_string()).lines().count(), 1);
+        // only the previous file is kept
+        let previous = content(&rotated);
+        assert_eq!(previous.lines().count(), 1);
+        assert!(previous.contains("\"sequence\":1"));
+    }
+}
@@ -1,35 +1,41 @@
 pub mod rpc;
 pub mod getwork_server;
+pub mod journal;
 
 use crate::core::storage::Storage;
 use crate::core::{error::BlockchainError, blockchain::Blockchain};
 use crate::rpc::getwork_server::GetWorkServer;
 use actix_web::dev::ServerHandle;
 use actix_web::{
-    get, HttpServer, App, HttpResponse, Responder, HttpRequest, web::{
+    HttpServer, App, HttpResponse, HttpRequest, web::{
         self, Path, Data, Payload
     },
+    middleware::Condition,
     error::Error
 };
 use actix_web_actors::ws::WsResponseBuilder;
-use serde_json::{Value, json};
 use tokio::sync::Mutex;
-use xelis_common::api::daemon::NotifyEvent;
-use xelis_common::config;
-use xelis_common::crypto::address::Address;
+use xelis_common::api::{daemon::{DaemonEvent, NotifyEvent}, ErrorCode};
+use xelis_common::crypto::{address::Address, key::{KeyPair, PublicKey}};
 use xelis_common::rpc_server::websocket::{EventWebSocketHandler, WebSocketServerShared, WebSocketServer};
-use xelis_common::rpc_server::{InternalRpcError, RPCHandler, RPCServerHandler, json_rpc, websocket, WebSocketServerHandler};
+use xelis_common::rpc_server::{InternalRpcError, RPCHandler, RPCServerHandler, json_rpc, websocket, index, schema, WebSocketServerHandler, HttpServerConfig};
 use std::collections::HashSet;
 use std::sync::Arc;
 use log::{trace, info, error, debug, warn};
 use self::getwork_server::{GetWorkWebSocketHandler, SharedGetWorkServer};
+use self::journal::RequestJournal;
 
 pub type SharedDaemonRpcServer<S> = Arc<DaemonRpcServer<S>>;
 
 pub struct DaemonRpcServer<S: Storage> {
     handle: Mutex<Option<ServerHandle>>,
     websocket: WebSocketServerShared<EventWebSocketHandler<Arc<Blockchain<S>>, NotifyEvent>>,
-    getwork: Option<SharedGetWorkServer<S>>
+    getwork: Option<SharedGetWorkServer<S>>,
+    // sign the JSON-RPC responses with the node key
+    sign_responses: bool,
+    // last requests executed, if enabled
+    journal: Option<Arc<RequestJournal>>,
+    http_config: HttpServerConfig
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -40,12 +46,46 @@ pub enum ApiError {
     ExpectedNormalAddress,
     #[error("P2p engine is not running")]
     NoP2p,
+    #[error("Network crawler is not enabled")]
+    NoNetworkCrawler,
+    #[error("Chain split monitor is not enabled")]
+    NoSplitMonitor,
     #[error("WebSocket server is not started")]
-    NoWebSocketServer
+    NoWebSocketServer,
+    #[error("GetWork server is not enabled")]
+    NoGetWorkServer,
+    #[error("Mining job not found, it may be too old")]
+    UnknownMiningJob,
+    #[error("Extra nonce must keep the worker tag of the job")]
+    InvalidExtraNonce
+}
+
+impl ApiError {
+    pub fn get_error_code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::ClientNotRegistered => None,
+            Self::ExpectedNormalAddress => Some(ErrorCode::InvalidAddress),
+            Self::NoP2p | Self::NoNetworkCrawler | Self::NoSplitMonitor | Self::NoWebSocketServer | Self::NoGetWorkServer => Some(ErrorCode::FeatureDisabled),
+            Self::UnknownMiningJob => Some(ErrorCode::StaleBlock),
+            Self::InvalidExtraNonce => Some(ErrorCode::InvalidExtraNonce)
+        }
+    }
+}
+
+// stable code of the errors returned by the RPC methods
+// errors are wrapped in anyhow, which still allows to downcast them behind a context
+pub fn get_error_code(error: &InternalRpcError) -> Option<ErrorCode> {
+    match error {
+        InternalRpcError::AnyError(e) => match e.downcast_ref::<BlockchainError>() {
+            Some(e) => e.get_error_code(),
+            None => e.downcast_ref::<ApiError>().and_then(ApiError::get_error_code)
+        },
+        _ => None
+    }
 }
 
 impl<S: Storage> DaemonRpcServer<S> {
-    pub async fn new(bind_address: String, blockchain: Arc<Blockchain<S>>, disable_getwork_server: bool) -> Result<SharedDaemonRpcServer<S>, BlockchainError> {
+    pub async fn new(bind_address: String, blockchain: Arc<Blockchain<S>>, disable_getwork_server: bool, sign_responses: bool, journal: Option<Arc<RequestJournal>>, http_config: HttpServerConfig) -> Result<SharedDaemonRpcServer<S>, BlockchainError> {
         let getwork: Option<SharedGetWorkServer<S>> = if !disable_getwork_server {
             info!("Creating GetWork server...");
             Some(Arc::new(GetWorkServer::new(blockchain.clone())))
@@ -56,6 +96,9 @@ impl<S: Storage> DaemonRpcServer<S> {
         // create the RPC Handler which will register and contains all available methods
         let mut rpc_handler = RPCHandler::new(blockchain);
         rpc::register_methods(&mut rpc_handler);
+        if let Some(journal) = &journal {
+            rpc_handler.register_hook(Arc::clone(journal));
+        }
 
         // create the default websocket server (support event & rpc methods)
         let ws = WebSocketServer::new(EventWebSocketHandler::new(rpc_handler));
@@ -64,19 +107,30 @@ impl<S: Storage> DaemonRpcServer<S> {
             handle: Mutex::new(None),
             websocket: ws,
             getwork,
+            sign_responses,
+            journal,
+            http_config
         });
 
         {
             let clone = Arc::clone(&server);
             let http_server = HttpServer::new(move || {
                 let server = Arc::clone(&clone);
-                App::new().app_data(web::Data::from(server))
+                let config = &server.http_config;
+                let cors = Condition::new(config.is_cors_enabled(), config.build_cors());
+                let scope = web::scope(&config.get_path_prefix())
                     .route("/json_rpc", web::post().to(json_rpc::<Arc<Blockchain<S>>, DaemonRpcServer<S>>))
+                    .route("/schema", web::get().to(schema::<Arc<Blockchain<S>>, DaemonRpcServer<S>>))
                     .route("/ws", web::get().to(websocket::<EventWebSocketHandler<Arc<Blockchain<S>>, NotifyEvent>, DaemonRpcServer<S>>))
                     .route("/getwork/{address}/{worker}", web::get().to(getwork_endpoint::<S>))
-                    .service(index)
+                    .route("/", web::get().to(index));
+
+                App::new().app_data(web::Data::from(server))
+                    .wrap(cors)
+                    .service(scope)
             })
             .disable_signals()
+            .workers(server.http_config.get_threads())
             .bind(&bind_address)?
             .run();
 
@@ -99,15 +153,31 @@ impl<S: Storage> DaemonRpcServer<S> {
         self.get_websocket().get_handler().is_event_tracked(event).await
     }
 
-    pub async fn notify_clients_with<V: serde::Serialize>(&self, event: &NotifyEvent, value: V) {
-        if let Err(e) = self.notify_clients(event, json!(value)).await {
-            error!("Error while notifying event {:?}: {}", event, e);
-        }
+    // notify the clients subscribed to this event
+    pub async fn notify_clients(&self, event: &DaemonEvent<'_>) {
+        self.notify_event(event, None).await;
+    }
+
+    // notify an event involving these accounts, clients watching accounts only receive it if they watch one of them
+    pub async fn notify_clients_for_keys(&self, event: &DaemonEvent<'_>, keys: &[PublicKey]) {
+        self.notify_event(event, Some(keys)).await;
     }
 
-    pub async fn notify_clients(&self, event: &NotifyEvent, value: Value) -> Result<(), anyhow::Error> {
-        self.get_websocket().get_handler().notify(event, value).await;
-        Ok(())
+    async fn notify_event(&self, event: &DaemonEvent<'_>, keys: Option<&[PublicKey]>) {
+        let notify_event = event.get_event();
+        let value = match event.to_value() {
+            Ok(value) => value,
+            Err(e) => {
+                error!("Error while serializing event {:?}: {}", notify_event, e);
+                return;
+            }
+        };
+
+        let handler = self.get_websocket().get_handler();
+        match keys {
+            Some(keys) => handler.notify_for_keys(&notify_event, value, keys).await,
+            None => handler.notify(&notify_event, value).await
+        };
     }
 
     pub async fn stop(&self) {
@@ -124,6 +194,10 @@ impl<S: Storage> DaemonRpcServer<S> {
     pub fn getwork_server(&self) -> &Option<SharedGetWorkServer<S>> {
         &self.getwork
     }
+
+    pub fn get_journal(&self) -> Option<&Arc<RequestJournal>> {
+        self.journal.as_ref()
+    }
 }
 
 impl<S: Storage> WebSocketServerHandler<EventWebSocketHandler<Arc<Blockchain<S>>, NotifyEvent>> for DaemonRpcServer<S> {
@@ -136,14 +210,17 @@ impl<S: Storage> RPCServerHandler<Arc<Blockchain<S>>> for DaemonRpcServer<S> {
     fn get_rpc_handler(&self) -> &RPCHandler<Arc<Blockchain<S>>> {
         self.get_websocket().get_handler().get_rpc_handler()
     }
-}
-
 
-#[get("/")]
-async fn index() -> impl Responder {
-    HttpResponse::Ok().body(format!("Hello, world!\nRunning on: {}", config::VERSION))
+    fn get_response_signer(&self) -> Option<&KeyPair> {
+        if self.sign_responses {
+            Some(self.get_rpc_handler().get_data().get_node_key())
+        } else {
+            None
+        }
+    }
 }
 
+
 async fn getwork_endpoint<S: Storage>(server: Data<DaemonRpcServer<S>>, request: HttpRequest, stream: Payload, path: Path<(String, String)>) -> Result<HttpResponse, Error> {
     match &server.getwork {
         Some(getwork) => {
@@ -170,7 +247,7 @@ async fn getwork_endpoint<S: Storage>(server: Data<DaemonRpcServer<S>>, request:
 
             let key = address.to_public_key();
             let (addr, response) = WsResponseBuilder::new(GetWorkWebSocketHandler::new(getwork.clone()), &request, stream).start_with_addr()?;
-            trace!("New miner connected to GetWork WebSocket: {:?}", addr);
+            trace!("New miner connected to GetWork WebSocket: {:?} from {:?}", addr, server.http_config.get_client_address(&request));
             getwork.add_miner(addr, key, worker).await;
             Ok(response)
         },
@@ -1,4 +1,4 @@
-use crate::{core::{blockchain::{Blockchain, get_block_reward}, storage::Storage, error::BlockchainError, mempool::Mempool}, p2p::peer::Peer, config::{DEV_FEES, MAXIMUM_SUPPLY}};
+use crate::{core::{blockchain::{Blockchain, get_block_reward, verify_aux_commitment}, blockdag::find_best_tip_by_cumulative_difficulty, storage::Storage, error::BlockchainError, mempool::Mempool}, p2p::peer::Peer, config::{self, DEV_FEES, MAXIMUM_SUPPLY}};
 use super::{InternalRpcError, ApiError};
 use anyhow::Context as AnyContext;
 use human_bytes::human_bytes;
@@ -6,17 +6,34 @@ use serde_json::{json, Value};
 use xelis_common::{
     api::{daemon::{
         BlockType,
+        DaemonEvent,
         BlockResponse,
         GetBlockAtTopoHeightParams,
         GetBlockByHashParams,
         GetBlockTemplateParams,
         GetBlockTemplateResult,
+        GetPowSeedResult,
+        GetAuxCommitmentProofParams,
+        GetAuxCommitmentProofResult,
+        ReplayEventsParams,
+        ReplayEventsResult,
+        WatchAddressesParams,
+        UnwatchAddressesParams,
+        NotifyEvent,
         SubmitBlockParams,
         GetBalanceParams,
         GetNonceParams,
         SubmitTransactionParams,
+        SubmitTransactionResult,
+        TransactionAcceptanceStatus,
+        WaitForConfirmationParams,
+        WaitForConfirmationResult,
         GetTransactionParams,
         P2pStatusResult,
+        GetSyncStatusResult,
+        GetP2pMetricsResult,
+        GetBandwidthResult,
+        GetMinersResult,
         GetBlocksAtHeightParams,
         GetTopoHeightRangeParams,
         GetBalanceAtTopoHeightParams,
@@ -33,26 +50,56 @@ use xelis_common::{
         HasNonceParams,
         GetAssetParams,
         GetAccountHistoryParams,
+        GetDepositsParams,
         AccountHistoryEntry,
         AccountHistoryType,
         GetAccountAssetsParams,
         PeerEntry,
+        GetNetworkTopologyResult,
+        GetChainSplitStatusResult,
         IsTxExecutedInBlockParams,
-        SizeOnDiskResult
-    }, DataHash},
+        SizeOnDiskResult,
+        DevFeeThreshold,
+        HardFork,
+        GetContractParams,
+        GetContractResult,
+        GetContractDataParams,
+        GetContractDataResult,
+        ResolveNameParams,
+        ResolveNameResult,
+        TransactionReceiptResponse,
+        GetTransactionConfirmationsResult,
+        GetAccountPendingParams,
+        GetAccountPendingResult,
+        GetMempoolGraphResult,
+        MempoolAccountGraph,
+        MempoolGraphEntry,
+        MempoolTxState,
+        AccountPendingEntry,
+        GetTipsInfoResult,
+        EvaluateBlockParams,
+        EvaluateBlockResult,
+        EstimateFeesParams,
+        EstimateFeesResult
+    }, DataHash, DataElement, DepositEntry, DepositGroup, ValidateAddressParams, ValidateAddressResult, FeeEstimation},
+    account::VersionedBalance,
+    asset::{AssetData, AssetWithData},
+    name::{is_valid_name, NAME_SUFFIX},
     async_handler,
     serializer::Serializer,
-    transaction::{Transaction, TransactionType},
-    crypto::hash::Hash,
-    block::{BlockHeader, Block},
+    transaction::{Transaction, TransactionType, EXTRA_DATA_LIMIT_SIZE, MAX_TX_SIZE, MAX_TRANSFERS_PER_TX},
+    crypto::{address::Address, hash::{Hash, Hashable}, key::{PublicKey, Signature, SIGNATURE_LENGTH}},
+    utils::{calculate_tx_fee, is_valid_reference},
+    block::{BlockHeader, AuxCommitmentProof},
     config::{XELIS_ASSET, VERSION},
     immutable::Immutable,
-    rpc_server::{RPCHandler, parse_params},
+    rpc_server::{RPCHandler, parse_params, websocket::{EventWebSocketHandler, WebSocketSessionShared}},
     context::Context
 };
-use crate::config::BLOCK_TIME_MILLIS;
-use std::{sync::Arc, borrow::Cow};
+use crate::config::{BLOCK_TIME_MILLIS, RPC_MAX_WATCHED_ADDRESSES};
+use std::{sync::Arc, borrow::Cow, collections::{HashSet, BTreeMap}, time::Duration};
 use log::{info, debug, trace};
+use tokio::time::{Instant, timeout_at};
 
 pub async fn get_block_type_for_block<S: Storage>(blockchain: &Blockchain<S>, storage: &S, hash: &Hash) -> Result<BlockType, InternalRpcError> {
     Ok(if blockchain.is_block_orphaned_for_storage(storage, hash).await {
@@ -66,16 +113,14 @@ pub async fn get_block_type_for_block<S: Storage>(blockchain: &Blockchain<S>, st
     })
 }
 
-pub async fn get_block_response_for_hash<S: Storage>(blockchain: &Blockchain<S>, storage: &S, hash: Hash, include_txs: bool) -> Result<Value, InternalRpcError> {
-    if !storage.has_block(&hash).await.context("Error while checking if block exist")? {
-        return Err(InternalRpcError::AnyError(BlockchainError::BlockNotFound(hash).into()))
-    }
-
-    let (topoheight, supply, reward) = if storage.is_block_topological_ordered(&hash).await {
+// build the response of a block with its data (full block or header only)
+async fn build_block_response<S: Storage, T: Clone + 'static>(blockchain: &Blockchain<S>, storage: &S, hash: Hash, data: T, total_fees: Option<u64>, total_size_in_bytes: usize) -> Result<BlockResponse<'static, T>, InternalRpcError> {
+    let (topoheight, supply, burned_supply, reward) = if storage.is_block_topological_ordered(&hash).await {
         let topoheight = storage.get_topo_height_for_hash(&hash).await.context("Error while retrieving topo height")?;
         (
             Some(topoheight),
             Some(storage.get_supply_at_topo_height(topoheight).await.context("Error while retrieving supply")?),
+            Some(storage.get_burned_supply_at_topo_height(topoheight).await.context("Error while retrieving burned supply")?),
             Some(storage.get_block_reward_at_topo_height(topoheight).context("Error while retrieving block reward")?),
         )
     } else {
@@ -83,40 +128,60 @@ pub async fn get_block_response_for_hash<S: Storage>(blockchain: &Blockchain<S>,
             None,
             None,
             None,
+            None,
         )
     };
 
     let block_type = get_block_type_for_block(&blockchain, &storage, &hash).await?;
+    let retained_until_height = if block_type == BlockType::Orphaned {
+        let height = storage.get_height_for_block_hash(&hash).await.context("Error while retrieving block height")?;
+        blockchain.get_orphaned_blocks_retention().map(|retention| height + retention)
+    } else {
+        None
+    };
     let cumulative_difficulty = storage.get_cumulative_difficulty_for_block_hash(&hash).await.context("Error while retrieving cumulative difficulty")?;
     let difficulty = storage.get_difficulty_for_block_hash(&hash).await.context("Error while retrieving difficulty")?;
-    let value: Value = if include_txs {
-        let block = storage.get_block(&hash).await.context("Error while retrieving full block")?;
-
-        let total_size_in_bytes = block.size();
-        let mut total_fees = 0;
-        for (tx, tx_hash) in block.get_transactions().iter().zip(block.get_txs_hashes()) {
-            // check that the TX was correctly executed in this block
-            // retrieve all fees for valid txs
-            if storage.is_tx_executed_in_block(tx_hash, &hash).context("Error while checking if tx was executed")? {
-                    total_fees += tx.get_fee();
-            }
-        }
 
-        let data: DataHash<'_, Block> = DataHash { hash: Cow::Borrowed(&hash), data: Cow::Owned(block) };
-        json!(BlockResponse { topoheight, block_type, cumulative_difficulty, difficulty, supply, reward, total_fees: Some(total_fees), total_size_in_bytes, data })
-    } else {
-        let block = storage.get_block_header_by_hash(&hash).await.context("Error while retrieving full block")?;
+    let data = DataHash { hash: Cow::Owned(hash), data: Cow::Owned(data) };
+    Ok(BlockResponse { topoheight, block_type, cumulative_difficulty, difficulty, supply, burned_supply, reward, total_fees, total_size_in_bytes, retained_until_height, data })
+}
 
-        let mut total_size_in_bytes = block.size();
-        for tx_hash in block.get_txs_hashes() {
-            total_size_in_bytes += storage.get_transaction_size(tx_hash).await.context(format!("Error while retrieving transaction {hash} size"))?;
-        }
+// response of a block without its transactions, also notified in the NewBlock event
+pub async fn get_block_header_response_for_hash<S: Storage>(blockchain: &Blockchain<S>, storage: &S, hash: Hash) -> Result<BlockResponse<'static, Arc<BlockHeader>>, InternalRpcError> {
+    if !storage.has_block(&hash).await.context("Error while checking if block exist")? {
+        return Err(InternalRpcError::AnyError(BlockchainError::BlockNotFound(hash).into()))
+    }
 
-        let data: DataHash<'_, Arc<BlockHeader>> = DataHash { hash: Cow::Borrowed(&hash), data: Cow::Borrowed(&block) };
-        json!(BlockResponse { topoheight, block_type, cumulative_difficulty, difficulty, supply, reward, total_fees: None, total_size_in_bytes, data })
-    };
+    let block = storage.get_block_header_by_hash(&hash).await.context("Error while retrieving full block")?;
+    let mut total_size_in_bytes = block.size();
+    for tx_hash in block.get_txs_hashes() {
+        total_size_in_bytes += storage.get_transaction_size(tx_hash).await.context(format!("Error while retrieving transaction {hash} size"))?;
+    }
 
-    Ok(value)
+    build_block_response(blockchain, storage, hash, block, None, total_size_in_bytes).await
+}
+
+pub async fn get_block_response_for_hash<S: Storage>(blockchain: &Blockchain<S>, storage: &S, hash: Hash, include_txs: bool) -> Result<Value, InternalRpcError> {
+    if !include_txs {
+        return Ok(json!(get_block_header_response_for_hash(blockchain, storage, hash).await?))
+    }
+
+    if !storage.has_block(&hash).await.context("Error while checking if block exist")? {
+        return Err(InternalRpcError::AnyError(BlockchainError::BlockNotFound(hash).into()))
+    }
+
+    let block = storage.get_block(&hash).await.context("Error while retrieving full block")?;
+    let total_size_in_bytes = block.size();
+    let mut total_fees = 0;
+    for (tx, tx_hash) in block.get_transactions().iter().zip(block.get_txs_hashes()) {
+        // check that the TX was correctly executed in this block
+        // retrieve all fees for valid txs
+        if storage.is_tx_executed_in_block(tx_hash, &hash).context("Error while checking if tx was executed")? {
+                total_fees += tx.get_fee();
+        }
+    }
+
+    Ok(json!(build_block_response(blockchain, storage, hash, block, Some(total_fees), total_size_in_bytes).await?))
 }
 
 pub async fn get_transaction_response<S: Storage>(storage: &S, tx: &Arc<Transaction>, hash: &Hash, in_mempool: bool, first_seen: Option<u64>) -> Result<Value, InternalRpcError> {
@@ -157,48 +222,84 @@ pub async fn get_peer_entry(peer: &Peer) -> PeerEntry {
         peers,
         pruned_topoheight: peer.get_pruned_topoheight(),
         cumulative_difficulty: peer.get_cumulative_difficulty(),
-        connected_on: peer.get_connection().connected_on()
+        connected_on: peer.get_connection().connected_on(),
+        last_activity: peer.get_last_activity(),
+        round_trip_time: peer.get_round_trip_time(),
+        node_key: peer.get_node_key().to_hex(),
+        trusted: peer.is_trusted(),
+        services: peer.get_services().to_list(),
+        prune_depth: peer.get_prune_depth()
     }
 }
 
 pub fn register_methods<S: Storage>(handler: &mut RPCHandler<Arc<Blockchain<S>>>) {
     info!("Registering RPC methods...");
-    handler.register_method("get_version", async_handler!(version::<S>));
-    handler.register_method("get_height", async_handler!(get_height::<S>));
-    handler.register_method("get_topoheight", async_handler!(get_topoheight::<S>));
-    handler.register_method("get_stableheight", async_handler!(get_stableheight::<S>));
-    handler.register_method("get_block_template", async_handler!(get_block_template::<S>));
-    handler.register_method("get_block_at_topoheight", async_handler!(get_block_at_topoheight::<S>));
-    handler.register_method("get_blocks_at_height", async_handler!(get_blocks_at_height::<S>));
-    handler.register_method("get_block_by_hash", async_handler!(get_block_by_hash::<S>));
-    handler.register_method("get_top_block", async_handler!(get_top_block::<S>));
-    handler.register_method("submit_block", async_handler!(submit_block::<S>));
-    handler.register_method("get_last_balance", async_handler!(get_last_balance::<S>));
-    handler.register_method("get_balance_at_topoheight", async_handler!(get_balance_at_topoheight::<S>));
-    handler.register_method("get_info", async_handler!(get_info::<S>));
-    handler.register_method("get_nonce", async_handler!(get_nonce::<S>));
-    handler.register_method("has_nonce", async_handler!(has_nonce::<S>));
-    handler.register_method("get_asset", async_handler!(get_asset::<S>));
-    handler.register_method("get_assets", async_handler!(get_assets::<S>));
-    handler.register_method("count_assets", async_handler!(count_assets::<S>));
-    handler.register_method("count_accounts", async_handler!(count_accounts::<S>));
-    handler.register_method("count_transactions", async_handler!(count_transactions::<S>));
-    handler.register_method("submit_transaction", async_handler!(submit_transaction::<S>));
-    handler.register_method("get_transaction", async_handler!(get_transaction::<S>));
-    handler.register_method("p2p_status", async_handler!(p2p_status::<S>));
-    handler.register_method("get_peers", async_handler!(get_peers::<S>));
-    handler.register_method("get_mempool", async_handler!(get_mempool::<S>));
-    handler.register_method("get_tips", async_handler!(get_tips::<S>));
-    handler.register_method("get_dag_order", async_handler!(get_dag_order::<S>));
-    handler.register_method("get_blocks_range_by_topoheight", async_handler!(get_blocks_range_by_topoheight::<S>));
-    handler.register_method("get_blocks_range_by_height", async_handler!(get_blocks_range_by_height::<S>));
-    handler.register_method("get_transactions", async_handler!(get_transactions::<S>));
-    handler.register_method("get_account_history", async_handler!(get_account_history::<S>));
-    handler.register_method("get_account_assets", async_handler!(get_account_assets::<S>));
-    handler.register_method("get_accounts", async_handler!(get_accounts::<S>));
-    handler.register_method("is_tx_executed_in_block", async_handler!(is_tx_executed_in_block::<S>));
-    handler.register_method("get_dev_fee_thresholds", async_handler!(get_dev_fee_thresholds::<S>));
-    handler.register_method("get_size_on_disk", async_handler!(get_size_on_disk::<S>));
+    handler.set_schema_title("XELIS Daemon");
+    handler.register_events_schema::<DaemonEvent>();
+    handler.set_error_code_resolver(super::get_error_code);
+    handler.register_method_with_schema::<(), String>("get_version", async_handler!(version::<S>));
+    handler.register_method_with_schema::<(), u64>("get_height", async_handler!(get_height::<S>));
+    handler.register_method_with_schema::<(), u64>("get_topoheight", async_handler!(get_topoheight::<S>));
+    handler.register_method_with_schema::<(), u64>("get_stableheight", async_handler!(get_stableheight::<S>));
+    handler.register_method_with_schema::<GetBlockTemplateParams, GetBlockTemplateResult>("get_block_template", async_handler!(get_block_template::<S>));
+    handler.register_method_with_schema::<GetBlockAtTopoHeightParams, BlockResponse<BlockHeader>>("get_block_at_topoheight", async_handler!(get_block_at_topoheight::<S>));
+    handler.register_method_with_schema::<GetBlocksAtHeightParams, Vec<BlockResponse<BlockHeader>>>("get_blocks_at_height", async_handler!(get_blocks_at_height::<S>));
+    handler.register_method_with_schema::<GetBlockByHashParams, BlockResponse<BlockHeader>>("get_block_by_hash", async_handler!(get_block_by_hash::<S>));
+    handler.register_method_with_schema::<GetTopBlockParams, BlockResponse<BlockHeader>>("get_top_block", async_handler!(get_top_block::<S>));
+    handler.register_method_with_schema::<SubmitBlockParams, bool>("submit_block", async_handler!(submit_block::<S>));
+    handler.register_method_with_schema::<GetBalanceParams, GetLastBalanceResult>("get_last_balance", async_handler!(get_last_balance::<S>));
+    handler.register_method_with_schema::<GetBalanceAtTopoHeightParams, VersionedBalance>("get_balance_at_topoheight", async_handler!(get_balance_at_topoheight::<S>));
+    handler.register_method_with_schema::<(), GetInfoResult>("get_info", async_handler!(get_info::<S>));
+    handler.register_method_with_schema::<GetNonceParams, GetNonceResult>("get_nonce", async_handler!(get_nonce::<S>));
+    handler.register_method_with_schema::<HasNonceParams, HasNonceResult>("has_nonce", async_handler!(has_nonce::<S>));
+    handler.register_method_with_schema::<GetAssetParams, AssetData>("get_asset", async_handler!(get_asset::<S>));
+    handler.register_method_with_schema::<GetAssetsParams, Vec<AssetWithData>>("get_assets", async_handler!(get_assets::<S>));
+    handler.register_method_with_schema::<(), usize>("count_assets", async_handler!(count_assets::<S>));
+    handler.register_method_with_schema::<(), usize>("count_accounts", async_handler!(count_accounts::<S>));
+    handler.register_method_with_schema::<(), usize>("count_transactions", async_handler!(count_transactions::<S>));
+    handler.register_method_with_schema::<SubmitTransactionParams, SubmitTransactionResult>("submit_transaction", async_handler!(submit_transaction::<S>));
+    handler.register_method_with_schema::<EstimateFeesParams, EstimateFeesResult>("estimate_fees", async_handler!(estimate_fees::<S>));
+    handler.register_method_with_schema::<WaitForConfirmationParams, WaitForConfirmationResult>("wait_for_confirmation", async_handler!(wait_for_confirmation::<S>));
+    handler.register_method_with_schema::<GetTransactionParams, TransactionResponse<Arc<Transaction>>>("get_transaction", async_handler!(get_transaction::<S>));
+    handler.register_method_with_schema::<(), P2pStatusResult>("p2p_status", async_handler!(p2p_status::<S>));
+    handler.register_method_with_schema::<(), GetSyncStatusResult>("get_sync_status", async_handler!(get_sync_status::<S>));
+    handler.register_method_with_schema::<(), GetP2pMetricsResult>("get_p2p_metrics", async_handler!(get_p2p_metrics::<S>));
+    handler.register_method_with_schema::<(), GetBandwidthResult>("get_bandwidth", async_handler!(get_bandwidth::<S>));
+    handler.register_method_with_schema::<(), Vec<PeerEntry>>("get_peers", async_handler!(get_peers::<S>));
+    handler.register_method_with_schema::<(), GetMinersResult>("get_miners", async_handler!(get_miners::<S>));
+    handler.register_method_with_schema::<(), GetNetworkTopologyResult>("get_network_topology", async_handler!(get_network_topology::<S>));
+    handler.register_method_with_schema::<(), GetChainSplitStatusResult>("get_chain_split_status", async_handler!(get_chain_split_status::<S>));
+    handler.register_method_with_schema::<(), Vec<TransactionResponse<Arc<Transaction>>>>("get_mempool", async_handler!(get_mempool::<S>));
+    handler.register_method_with_schema::<(), GetMempoolGraphResult>("get_mempool_graph", async_handler!(get_mempool_graph::<S>));
+    handler.register_method_with_schema::<(), Vec<Hash>>("get_tips", async_handler!(get_tips::<S>));
+    handler.register_method_with_schema::<(), GetTipsInfoResult>("get_tips_info", async_handler!(get_tips_info::<S>));
+    handler.register_method_with_schema::<EvaluateBlockParams, EvaluateBlockResult>("evaluate_block", async_handler!(evaluate_block::<S>));
+    handler.register_method_with_schema::<GetTopoHeightRangeParams, Vec<Hash>>("get_dag_order", async_handler!(get_dag_order::<S>));
+    handler.register_method_with_schema::<GetTopoHeightRangeParams, Vec<BlockResponse<BlockHeader>>>("get_blocks_range_by_topoheight", async_handler!(get_blocks_range_by_topoheight::<S>));
+    handler.register_method_with_schema::<GetHeightRangeParams, Vec<BlockResponse<BlockHeader>>>("get_blocks_range_by_height", async_handler!(get_blocks_range_by_height::<S>));
+    handler.register_method_with_schema::<GetHeightRangeParams, Vec<BlockResponse<BlockHeader>>>("get_orphaned_blocks", async_handler!(get_orphaned_blocks::<S>));
+    handler.register_method_with_schema::<GetTransactionsParams, Vec<Option<TransactionResponse<Arc<Transaction>>>>>("get_transactions", async_handler!(get_transactions::<S>));
+    handler.register_method_with_schema::<GetTransactionParams, TransactionReceiptResponse>("get_transaction_receipt", async_handler!(get_transaction_receipt::<S>));
+    handler.register_method_with_schema::<GetTransactionParams, GetTransactionConfirmationsResult>("get_transaction_confirmations", async_handler!(get_transaction_confirmations::<S>));
+    handler.register_method_with_schema::<GetAccountHistoryParams, Vec<AccountHistoryEntry>>("get_account_history", async_handler!(get_account_history::<S>));
+    handler.register_method_with_schema::<ValidateAddressParams, ValidateAddressResult>("validate_address", async_handler!(validate_address::<S>));
+    handler.register_method_with_schema::<GetDepositsParams, Vec<DepositGroup>>("get_deposits", async_handler!(get_deposits::<S>));
+    handler.register_method_with_schema::<GetAccountAssetsParams, Vec<Hash>>("get_account_assets", async_handler!(get_account_assets::<S>));
+    handler.register_method_with_schema::<GetAccountPendingParams, GetAccountPendingResult>("get_account_pending", async_handler!(get_account_pending::<S>));
+    handler.register_method_with_schema::<GetAccountsParams, Vec<PublicKey>>("get_accounts", async_handler!(get_accounts::<S>));
+    handler.register_method_with_schema::<IsTxExecutedInBlockParams, bool>("is_tx_executed_in_block", async_handler!(is_tx_executed_in_block::<S>));
+    handler.register_method_with_schema::<(), Vec<DevFeeThreshold>>("get_dev_fee_thresholds", async_handler!(get_dev_fee_thresholds::<S>));
+    handler.register_method_with_schema::<(), SizeOnDiskResult>("get_size_on_disk", async_handler!(get_size_on_disk::<S>));
+    handler.register_method_with_schema::<(), Vec<HardFork>>("get_hard_forks", async_handler!(get_hard_forks::<S>));
+    handler.register_method_with_schema::<(), GetPowSeedResult>("get_pow_seed", async_handler!(get_pow_seed::<S>));
+    handler.register_method_with_schema::<GetAuxCommitmentProofParams, GetAuxCommitmentProofResult>("get_aux_commitment_proof", async_handler!(get_aux_commitment_proof::<S>));
+    handler.register_method_with_schema::<ReplayEventsParams, ReplayEventsResult>("replay_events", async_handler!(replay_events::<S>));
+    handler.register_method_with_schema::<WatchAddressesParams, usize>("watch_addresses", async_handler!(watch_addresses::<S>));
+    handler.register_method_with_schema::<UnwatchAddressesParams, usize>("unwatch_addresses", async_handler!(unwatch_addresses::<S>));
+    handler.register_method_with_schema::<(), Vec<Address>>("get_watched_addresses", async_handler!(get_watched_addresses::<S>));
+    handler.register_method_with_schema::<GetContractParams, GetContractResult>("get_contract", async_handler!(get_contract::<S>));
+    handler.register_method_with_schema::<GetContractDataParams, GetContractDataResult>("get_contract_data", async_handler!(get_contract_data::<S>));
+    handler.register_method_with_schema::<ResolveNameParams, ResolveNameResult>("resolve_name", async_handler!(resolve_name::<S>));
 }
 
 async fn version<S: Storage>(_: Context, body: Value) -> Result<Value, InternalRpcError> {
@@ -267,16 +368,22 @@ async fn get_block_template<S: Storage>(context: Context, body: Value) -> Result
     }
 
     let storage = blockchain.get_storage().read().await;
-    let block = blockchain.get_block_template_for_storage(&storage, params.address.into_owned().to_public_key()).await.context("Error while retrieving block template")?;
+    let mut block = blockchain.get_block_template_for_storage(&storage, params.address.into_owned().to_public_key()).await.context("Error while retrieving block template")?;
+    if params.aux_commitment.is_some() {
+        block.set_aux_commitment(params.aux_commitment);
+        verify_aux_commitment(&block).context("Invalid auxiliary commitment")?;
+    }
     let difficulty = blockchain.get_difficulty_at_tips(&*storage, block.get_tips()).await.context("Error while retrieving difficulty at tips")?;
-    let height = block.height;
-    Ok(json!(GetBlockTemplateResult { template: block.to_hex(), height, difficulty }))
+    let height = block.get_height();
+    let topoheight = blockchain.get_topo_height();
+    Ok(json!(GetBlockTemplateResult { template: block.to_hex(), height, topoheight, difficulty }))
 }
 
 async fn submit_block<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     let params: SubmitBlockParams = parse_params(body)?;
     let header = BlockHeader::from_hex(params.block_template)?;
     let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    blockchain.pre_verify_block_header(&header).await.context("Invalid block header")?;
     // TODO add block hashing blob on block template
     let block = blockchain.build_block_from_header(Immutable::Owned(header)).await.context("Error while building block from header")?;
     blockchain.add_new_block(block, true, true).await.context("Error while adding new block to chain")?;
@@ -306,17 +413,19 @@ async fn get_info<S: Storage>(context: Context, body: Value) -> Result<Value, In
     let height = blockchain.get_height();
     let topoheight = blockchain.get_topo_height();
     let stableheight = blockchain.get_stable_height();
-    let (top_block_hash, circulating_supply, pruned_topoheight, average_block_time) = {
+    let (top_block_hash, emitted_supply, burned_supply, pruned_topoheight, average_block_time) = {
         let storage = blockchain.get_storage().read().await;
         let top_block_hash = storage.get_hash_at_topo_height(topoheight).await.context("Error while retrieving hash at topo height")?;
         let supply = storage.get_supply_at_topo_height(topoheight).await.context("Error while retrieving supply at topo height")?;
+        let burned_supply = storage.get_burned_supply_at_topo_height(topoheight).await.context("Error while retrieving burned supply at topo height")?;
         let pruned_topoheight = storage.get_pruned_topoheight().context("Error while retrieving pruned topoheight")?;
         let average_block_time = blockchain.get_average_block_time_for_storage(&storage).await.context("Error while retrieving average block time")?;
-        (top_block_hash, supply, pruned_topoheight, average_block_time)
+        (top_block_hash, supply, burned_supply, pruned_topoheight, average_block_time)
     };
     let difficulty = blockchain.get_difficulty();
     let block_time_target = BLOCK_TIME_MILLIS;
-    let block_reward = get_block_reward(circulating_supply);
+    // emission is based on the coins emitted, burning coins doesn't change it
+    let block_reward = get_block_reward(emitted_supply);
     let mempool_size = blockchain.get_mempool_size().await;
     let version = VERSION.into();
     let network = *blockchain.get_network();
@@ -327,7 +436,9 @@ async fn get_info<S: Storage>(context: Context, body: Value) -> Result<Value, In
         stableheight,
         pruned_topoheight,
         top_block_hash,
-        circulating_supply,
+        circulating_supply: emitted_supply.saturating_sub(burned_supply),
+        emitted_supply,
+        burned_supply,
         maximum_supply: MAXIMUM_SUPPLY,
         difficulty,
         block_time_target,
@@ -392,6 +503,160 @@ async fn get_nonce<S: Storage>(context: Context, body: Value) -> Result<Value, I
     Ok(json!(GetNonceResult { topoheight, version }))
 }
 
+async fn get_account_pending<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetAccountPendingParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    if params.address.is_mainnet() != blockchain.get_network().is_mainnet() {
+        return Err(InternalRpcError::AnyError(BlockchainError::InvalidNetwork.into()))
+    }
+
+    let key = params.address.get_public_key();
+    let storage = blockchain.get_storage().read().await;
+    let nonce = get_account_nonce(&*storage, key).await?;
+    let mempool = blockchain.get_mempool().read().await;
+
+    Ok(json!(GetAccountPendingResult {
+        nonce,
+        max_pending: blockchain.get_max_pending_txs_per_account(),
+        transactions: get_account_pending_entries(&mempool, key)?
+    }))
+}
+
+// nonce expected on chain for the next transaction of this account
+async fn get_account_nonce<S: Storage>(storage: &S, key: &PublicKey) -> Result<u64, InternalRpcError> {
+    Ok(if storage.has_nonce(key).await.context("Error while checking if account has a nonce")? {
+        let (_, version) = storage.get_last_nonce(key).await.context("Error while retrieving nonce for account")?;
+        version.get_nonce()
+    } else {
+        0
+    })
+}
+
+// pending and queued transactions of an account ordered by nonce
+fn get_account_pending_entries(mempool: &Mempool, key: &PublicKey) -> Result<Vec<AccountPendingEntry<'static>>, InternalRpcError> {
+    let mut transactions = Vec::new();
+    if let Some(cache) = mempool.get_cached_nonce(key) {
+        for hash in cache.get_txs() {
+            let sorted_tx = mempool.get_sorted_tx(hash).context("Error while retrieving pending tx")?;
+            transactions.push(AccountPendingEntry {
+                hash: Cow::Owned(hash.as_ref().clone()),
+                nonce: sorted_tx.get_tx().get_nonce(),
+                fee: sorted_tx.get_fee(),
+                size: sorted_tx.get_size(),
+                first_seen: sorted_tx.get_first_seen(),
+                queued: false
+            });
+        }
+    }
+
+    if let Some(orphans) = mempool.get_orphan_txs(key) {
+        for (nonce, orphan) in orphans {
+            transactions.push(AccountPendingEntry {
+                hash: Cow::Owned(orphan.get_hash().clone()),
+                nonce: *nonce,
+                fee: orphan.get_tx().get_fee(),
+                size: orphan.get_tx().size(),
+                first_seen: orphan.get_first_seen(),
+                queued: true
+            });
+        }
+    }
+    transactions.sort_by_key(|entry| entry.nonce);
+
+    Ok(transactions)
+}
+
+// build the dependencies between the pending transactions of an account
+// each transaction depends on the one with the previous nonce, a missing nonce blocks all the next ones
+fn get_account_graph(owner: Address, nonce: u64, entries: Vec<AccountPendingEntry<'static>>) -> MempoolAccountGraph<'static> {
+    let mut by_nonce: BTreeMap<u64, Vec<AccountPendingEntry<'static>>> = BTreeMap::new();
+    for entry in entries {
+        by_nonce.entry(entry.nonce).or_default().push(entry);
+    }
+
+    let mut transactions = Vec::new();
+    let mut next_nonce = nonce;
+    let mut missing_nonce = None;
+    for (tx_nonce, group) in &by_nonce {
+        let tx_nonce = *tx_nonce;
+        if missing_nonce.is_none() && tx_nonce > next_nonce {
+            missing_nonce = Some(next_nonce);
+        }
+
+        let state = if tx_nonce < nonce || group.len() > 1 {
+            MempoolTxState::Conflict
+        } else if missing_nonce.is_some() {
+            MempoolTxState::Blocked
+        } else if tx_nonce == nonce {
+            MempoolTxState::Ready
+        } else {
+            MempoolTxState::Waiting
+        };
+
+        if tx_nonce == next_nonce {
+            next_nonce += 1;
+        }
+
+        let depends_on = tx_nonce.checked_sub(1)
+            .filter(|previous| *previous >= nonce)
+            .and_then(|previous| by_nonce.get(&previous))
+            .and_then(|previous| previous.first())
+            .map(|previous| previous.hash.clone());
+
+        for entry in group {
+            let conflicts_with = group.iter()
+                .filter(|other| other.hash != entry.hash)
+                .map(|other| other.hash.clone())
+                .collect();
+
+            transactions.push(MempoolGraphEntry {
+                entry: AccountPendingEntry {
+                    hash: entry.hash.clone(),
+                    nonce: entry.nonce,
+                    fee: entry.fee,
+                    size: entry.size,
+                    first_seen: entry.first_seen,
+                    queued: entry.queued
+                },
+                state,
+                depends_on: depends_on.clone(),
+                conflicts_with
+            });
+        }
+    }
+
+    MempoolAccountGraph {
+        owner: Cow::Owned(owner),
+        nonce,
+        missing_nonce,
+        transactions
+    }
+}
+
+async fn get_mempool_graph<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let mainnet = blockchain.get_network().is_mainnet();
+    let storage = blockchain.get_storage().read().await;
+    let mempool = blockchain.get_mempool().read().await;
+
+    let owners: HashSet<&PublicKey> = mempool.get_nonces_cache().keys()
+        .chain(mempool.get_orphans().keys())
+        .collect();
+
+    let mut accounts = Vec::with_capacity(owners.len());
+    for key in owners {
+        let nonce = get_account_nonce(&*storage, key).await?;
+        let entries = get_account_pending_entries(&mempool, key)?;
+        accounts.push(get_account_graph(key.to_address(mainnet), nonce, entries));
+    }
+    accounts.sort_by_cached_key(|account| account.owner.to_string());
+
+    Ok(json!(GetMempoolGraphResult { accounts }))
+}
+
 async fn get_asset<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     let params: GetAssetParams = parse_params(body)?;
     let blockchain: &Arc<Blockchain<S>> = context.get()?;
@@ -453,12 +718,185 @@ async fn count_transactions<S: Storage>(context: Context, body: Value) -> Result
     Ok(json!(count))
 }
 
+// a transaction submitted with a reference already used is not added, the hash of the first one is returned
 async fn submit_transaction<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     let params: SubmitTransactionParams = parse_params(body)?;
+    if params.reference.as_deref().is_some_and(|reference| !is_valid_reference(reference)) {
+        return Err(InternalRpcError::CustomStr("Invalid reference, expected an UUID"))
+    }
+
     let transaction = Transaction::from_hex(params.data)?;
     let blockchain: &Arc<Blockchain<S>> = context.get()?;
-    blockchain.add_tx_to_mempool(transaction, true).await.map_err(|e| InternalRpcError::AnyError(e.into()))?;
-    Ok(json!(true))
+    // keep it locked until the transaction is submitted, so a retry can't be added in parallel
+    let mut references = match params.reference.as_ref() {
+        Some(reference) => {
+            let mut references = blockchain.get_tx_references().lock().await;
+            if let Some(hash) = references.get(reference) {
+                debug!("Transaction with reference {} was already submitted as {}", reference, hash);
+                return Ok(json!(SubmitTransactionResult {
+                    hash: hash.clone(),
+                    status: TransactionAcceptanceStatus::AlreadyKnown,
+                    reason: None,
+                    code: None
+                }))
+            }
+            Some(references)
+        },
+        None => None
+    };
+
+    let hash = transaction.hash();
+    let (status, reason, code) = match blockchain.add_tx_to_mempool_with_hash(transaction, hash.clone(), true).await {
+        Ok(()) if blockchain.get_mempool().read().await.contains_orphan_tx(&hash) => (TransactionAcceptanceStatus::Queued, None, None),
+        Ok(()) => (TransactionAcceptanceStatus::Accepted, None, None),
+        Err(BlockchainError::TxAlreadyInMempool(_)) | Err(BlockchainError::TxAlreadyInBlockchain(_)) => (TransactionAcceptanceStatus::AlreadyKnown, None, None),
+        Err(e) => {
+            debug!("Transaction {} submitted through RPC was rejected: {}", hash, e);
+            (TransactionAcceptanceStatus::Rejected, Some(e.to_string()), e.get_error_code())
+        }
+    };
+
+    // a rejected transaction can be fixed and submitted again with the same reference
+    if status != TransactionAcceptanceStatus::Rejected {
+        if let (Some(references), Some(reference)) = (references.as_mut(), params.reference) {
+            references.put(reference, hash.clone());
+        }
+    }
+
+    Ok(json!(SubmitTransactionResult { hash, status, reason, code }))
+}
+
+// size and fee breakdown of a transaction before it is signed by its owner
+// the balances and the nonce of the owner are not checked
+async fn estimate_fees<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: EstimateFeesParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let mainnet = blockchain.get_network().is_mainnet();
+    if params.owner.is_mainnet() != mainnet || params.sponsor.as_ref().is_some_and(|sponsor| sponsor.is_mainnet() != mainnet) {
+        return Err(InternalRpcError::AnyError(BlockchainError::InvalidNetwork.into()))
+    }
+
+    if params.sponsor.as_ref().is_some_and(|sponsor| sponsor.get_public_key() == params.owner.get_public_key()) {
+        return Err(InternalRpcError::CustomStr("Sponsor cannot be the owner of the transaction"))
+    }
+
+    if let TransactionType::Transfer(transfers) = &params.tx_type {
+        if transfers.is_empty() || transfers.len() > MAX_TRANSFERS_PER_TX {
+            return Err(InternalRpcError::CustomStr("Expected between 1 and 255 transfers"))
+        }
+
+        let extra_data_size: usize = transfers.iter().filter_map(|transfer| transfer.extra_data.as_ref()).map(Vec::len).sum();
+        if extra_data_size > EXTRA_DATA_LIMIT_SIZE {
+            return Err(InternalRpcError::AnyError(BlockchainError::InvalidTransactionExtraDataTooBig(EXTRA_DATA_LIMIT_SIZE, extra_data_size).into()))
+        }
+    }
+
+    // signatures have a fixed size, a placeholder is used until the owner signs it
+    let signature = Signature::from_bytes(&[0u8; SIGNATURE_LENGTH])?;
+    let create_transaction = |fee: u64| {
+        let owner = params.owner.get_public_key().clone();
+        let tx_type = params.tx_type.clone();
+        let mut tx = match &params.sponsor {
+            Some(sponsor) => Transaction::new_sponsored(owner, tx_type, fee, 0, sponsor.get_public_key().clone(), signature.clone()),
+            None => Transaction::new(owner, tx_type, fee, 0, signature.clone())
+        };
+        if let Some(expiration) = params.expiration {
+            tx.set_expiration(expiration);
+        }
+        tx
+    };
+
+    // the fee field has a fixed size, so the minimum fee doesn't change the size
+    let tx = create_transaction(params.fee.unwrap_or(0));
+    let tx = match params.fee {
+        Some(_) => tx,
+        None => create_transaction(calculate_tx_fee(tx.get_signed_size()))
+    };
+
+    let estimation = FeeEstimation::new(&tx, mainnet);
+    if estimation.size > MAX_TX_SIZE {
+        return Err(InternalRpcError::AnyError(BlockchainError::TxTooBig(tx.hash(), MAX_TX_SIZE, estimation.size).into()))
+    }
+    let template_min_fee = blockchain.get_template_config().get_min_fee_for_size(estimation.size);
+    Ok(json!(EstimateFeesResult { estimation, template_min_fee }))
+}
+
+// default and maximum time to wait for confirmations in seconds
+const DEFAULT_CONFIRMATION_TIMEOUT: u64 = 60;
+const MAX_CONFIRMATION_TIMEOUT: u64 = 600;
+
+// Confirmations of a transaction based on the topoheight of the block in which it was executed
+async fn get_confirmations_for_tx<S: Storage>(blockchain: &Blockchain<S>, hash: &Hash) -> Result<GetTransactionConfirmationsResult<'static>, InternalRpcError> {
+    let storage = blockchain.get_storage().read().await;
+    let in_mempool = {
+        let mempool = blockchain.get_mempool().read().await;
+        mempool.contains_tx(hash) || mempool.contains_orphan_tx(hash)
+    };
+
+    let (executed_in_block, topoheight, confirmations, stable, reorg_probability) = match storage.get_block_executer_for_tx(hash) {
+        Ok(block_hash) if storage.is_block_topological_ordered(&block_hash).await => {
+            let topoheight = storage.get_topo_height_for_hash(&block_hash).await.context("Error while retrieving topoheight of executing block")?;
+            let height = storage.get_height_for_block_hash(&block_hash).await.context("Error while retrieving height of executing block")?;
+            let confirmations = blockchain.get_topo_height().saturating_sub(topoheight) + 1;
+            let stable = height <= blockchain.get_stable_height();
+            (Some(block_hash), Some(topoheight), confirmations, stable, blockchain.get_reorg_probability_at_height(height))
+        },
+        _ => {
+            // not executed yet, it must be known by the node
+            if !in_mempool && !storage.has_transaction(hash).await.context("Error while checking if transaction exists")? {
+                return Err(InternalRpcError::AnyError(BlockchainError::TxNotFound(hash.clone()).into()))
+            }
+            (None, None, 0, false, 1f64)
+        }
+    };
+
+    Ok(GetTransactionConfirmationsResult {
+        hash: Cow::Owned(hash.clone()),
+        in_mempool,
+        executed_in_block,
+        topoheight,
+        confirmations,
+        stable,
+        reorg_probability
+    })
+}
+
+async fn get_transaction_confirmations<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetTransactionParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let result = get_confirmations_for_tx(blockchain, &params.hash).await?;
+    Ok(json!(result))
+}
+
+// Wait until the transaction has the requested confirmations or the timeout is reached
+async fn wait_for_confirmation<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: WaitForConfirmationParams = parse_params(body)?;
+    if params.confirmations == 0 {
+        return Err(InternalRpcError::CustomStr("confirmations must be greater than 0"))
+    }
+
+    let timeout = params.timeout.unwrap_or(DEFAULT_CONFIRMATION_TIMEOUT);
+    if timeout > MAX_CONFIRMATION_TIMEOUT {
+        return Err(InternalRpcError::Custom(format!("timeout cannot be greater than {} seconds", MAX_CONFIRMATION_TIMEOUT)))
+    }
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    // subscribe before the first check to not miss any block
+    let mut receiver = blockchain.subscribe_topoheight();
+    loop {
+        let result = get_confirmations_for_tx(blockchain, &params.hash).await?;
+        let confirmed = result.confirmations >= params.confirmations;
+        // wait for the next block until confirmed, the timeout is reached or the chain is stopping
+        if confirmed || !matches!(timeout_at(deadline, receiver.changed()).await, Ok(Ok(()))) {
+            return Ok(json!(WaitForConfirmationResult {
+                confirmed,
+                confirmations: result.confirmations,
+                executed_in_block: result.executed_in_block,
+                topoheight: result.topoheight
+            }))
+        }
+    }
 }
 
 async fn get_transaction<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
@@ -470,6 +908,23 @@ async fn get_transaction<S: Storage>(context: Context, body: Value) -> Result<Va
     get_transaction_response_for_hash(&*storage, &mempool, &params.hash).await
 }
 
+async fn get_transaction_receipt<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetTransactionParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let storage = blockchain.get_storage().read().await;
+    if !storage.has_transaction_receipt(&params.hash).context("Error while checking if tx has a receipt")? {
+        return Err(InternalRpcError::AnyError(BlockchainError::NoTxReceipt(params.hash.into_owned()).into()))
+    }
+
+    let executed_in_block = storage.get_block_executer_for_tx(&params.hash).context("Error while retrieving block executer for tx")?;
+    let receipt = storage.get_transaction_receipt(&params.hash).context("Error while retrieving tx receipt")?;
+    Ok(json!(TransactionReceiptResponse {
+        hash: params.hash,
+        executed_in_block: Cow::Owned(executed_in_block),
+        receipt: Cow::Owned(receipt)
+    }))
+}
+
 async fn p2p_status<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     if body != Value::Null {
         return Err(InternalRpcError::UnexpectedParams)
@@ -494,13 +949,70 @@ async fn p2p_status<S: Storage>(context: Context, body: Value) -> Result<Value,
                 our_topoheight,
                 best_topoheight,
                 median_topoheight,
-                max_peers
+                max_peers,
+                node_key: blockchain.get_node_key().get_public_key().to_hex()
             }))
         },
         None => Err(InternalRpcError::AnyError(ApiError::NoP2p.into()))
     }
 }
 
+async fn get_sync_status<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let p2p = blockchain.get_p2p().read().await;
+    match p2p.as_ref() {
+        Some(p2p) => Ok(json!(p2p.get_sync_status().await)),
+        None => Err(InternalRpcError::AnyError(ApiError::NoP2p.into()))
+    }
+}
+
+async fn get_p2p_metrics<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let p2p = blockchain.get_p2p().read().await;
+    match p2p.as_ref() {
+        Some(p2p) => Ok(json!(p2p.get_metrics().await)),
+        None => Err(InternalRpcError::AnyError(ApiError::NoP2p.into()))
+    }
+}
+
+async fn get_bandwidth<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let p2p = blockchain.get_p2p().read().await;
+    match p2p.as_ref() {
+        Some(p2p) => Ok(json!(p2p.get_bandwidth().await)),
+        None => Err(InternalRpcError::AnyError(ApiError::NoP2p.into()))
+    }
+}
+
+async fn get_miners<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let getwork = match blockchain.get_rpc().read().await.as_ref() {
+        Some(rpc) => rpc.getwork_server().clone(),
+        None => None
+    };
+
+    match getwork {
+        Some(getwork) => Ok(json!(getwork.get_miners().await)),
+        None => Err(InternalRpcError::AnyError(ApiError::NoGetWorkServer.into()))
+    }
+}
+
 async fn get_peers<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     if body != Value::Null {
         return Err(InternalRpcError::UnexpectedParams)
@@ -520,6 +1032,32 @@ async fn get_peers<S: Storage>(context: Context, body: Value) -> Result<Value, I
     }
 }
 
+async fn get_network_topology<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let p2p = blockchain.get_p2p().read().await;
+    match p2p.as_ref() {
+        Some(p2p) => match p2p.get_crawler() {
+            Some(crawler) => Ok(json!(crawler.get_topology().await)),
+            None => Err(InternalRpcError::AnyError(ApiError::NoNetworkCrawler.into()))
+        },
+        None => Err(InternalRpcError::AnyError(ApiError::NoP2p.into()))
+    }
+}
+
+async fn get_chain_split_status<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    match blockchain.get_split_monitor() {
+        Some(monitor) => Ok(json!(monitor.get_status().await)),
+        None => Err(InternalRpcError::AnyError(ApiError::NoSplitMonitor.into()))
+    }
+}
+
 async fn get_mempool<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     if body != Value::Null {
         return Err(InternalRpcError::UnexpectedParams)
@@ -557,6 +1095,57 @@ async fn get_tips<S: Storage>(context: Context, body: Value) -> Result<Value, In
     Ok(json!(tips))
 }
 
+async fn get_tips_info<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let storage = blockchain.get_storage().read().await;
+    let info = blockchain.get_tips_info(&storage).await.context("Error while retrieving tips info")?;
+    Ok(json!(info))
+}
+
+// compare a block, for example the top block of another node, with the local best chain
+async fn evaluate_block<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: EvaluateBlockParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let storage = blockchain.get_storage().read().await;
+
+    let tips = storage.get_tips().await.context("Error while retrieving tips")?.into_iter().collect();
+    let best_tip = find_best_tip_by_cumulative_difficulty(&*storage, &tips).await.context("Error while retrieving best tip")?.clone();
+    let best_cumulative_difficulty = storage.get_cumulative_difficulty_for_block_hash(&best_tip).await.context("Error while retrieving best cumulative difficulty")?;
+
+    let hash = params.hash.into_owned();
+    let known = storage.has_block(&hash).await.context("Error while checking if block exist")?;
+    let (height, topoheight, block_type, cumulative_difficulty) = if known {
+        let topoheight = if storage.is_block_topological_ordered(&hash).await {
+            Some(storage.get_topo_height_for_hash(&hash).await.context("Error while retrieving topo height")?)
+        } else {
+            None
+        };
+        (
+            Some(storage.get_height_for_block_hash(&hash).await.context("Error while retrieving height")?),
+            topoheight,
+            Some(get_block_type_for_block(blockchain, &storage, &hash).await?),
+            Some(storage.get_cumulative_difficulty_for_block_hash(&hash).await.context("Error while retrieving cumulative difficulty")?)
+        )
+    } else {
+        (None, None, None, params.cumulative_difficulty)
+    };
+
+    Ok(json!(EvaluateBlockResult {
+        known,
+        height,
+        topoheight,
+        block_type,
+        cumulative_difficulty,
+        heavier: cumulative_difficulty.map(|cumulative_difficulty| cumulative_difficulty > best_cumulative_difficulty),
+        best_tip,
+        best_cumulative_difficulty,
+        stable_height: blockchain.get_stable_height()
+    }))
+}
+
 const MAX_DAG_ORDER: u64 = 64;
 // get dag order based on params
 // if no params found, get order of last 64 blocks
@@ -579,6 +1168,8 @@ async fn get_dag_order<S: Storage>(context: Context, body: Value) -> Result<Valu
 }
 
 const MAX_BLOCKS: u64 = 20;
+// only orphaned blocks are returned, so more heights can be checked at once
+const MAX_ORPHANED_BLOCKS_HEIGHTS: u64 = 500;
 
 fn get_range(start: Option<u64>, end: Option<u64>, maximum: u64, current: u64) -> Result<(u64, u64), InternalRpcError> {
     let range_start = start.unwrap_or_else(|| {
@@ -646,6 +1237,32 @@ async fn get_blocks_range_by_height<S: Storage>(context: Context, body: Value) -
     Ok(json!(blocks))
 }
 
+// get the orphaned blocks between a range of heights
+// if no params found, check the last heights
+async fn get_orphaned_blocks<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetHeightRangeParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let current_height = blockchain.get_height();
+    let (start_height, end_height) = get_range(params.start_height, params.end_height, MAX_ORPHANED_BLOCKS_HEIGHTS, current_height)?;
+
+    let storage = blockchain.get_storage().read().await;
+    let mut blocks = Vec::new();
+    for i in start_height..=end_height {
+        if !storage.has_blocks_at_height(i).await.context("Error while checking blocks at height")? {
+            continue;
+        }
+
+        let blocks_at_height = storage.get_blocks_at_height(i).await.context("Error while retrieving blocks at height")?;
+        for hash in blocks_at_height {
+            if blockchain.is_block_orphaned_for_storage(&storage, &hash).await {
+                blocks.push(get_block_response_for_hash(blockchain, &storage, hash, false).await?);
+            }
+        }
+    }
+
+    Ok(json!(blocks))
+}
+
 const MAX_TXS: usize = 20;
 // get up to 20 transactions at once
 // if a tx hash is not present, we keep the order and put json "null" value
@@ -787,6 +1404,79 @@ async fn get_account_history<S: Storage>(context: Context, body: Value) -> Resul
     Ok(json!(history))
 }
 
+// decode an address and check that it is for the network of the daemon
+async fn validate_address<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: ValidateAddressParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    Ok(json!(ValidateAddressResult::new(&params.address, *blockchain.get_network())))
+}
+
+const MAX_DEPOSITS_TOPOHEIGHT_RANGE: u64 = 100;
+// retrieve the transfers received by an address grouped by their integrated data
+// if no range is set, search in the last 100 topoheights
+async fn get_deposits<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetDepositsParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let mainnet = blockchain.get_network().is_mainnet();
+    if params.address.is_mainnet() != mainnet {
+        return Err(InternalRpcError::AnyError(BlockchainError::InvalidNetwork.into()))
+    }
+
+    let current_topoheight = blockchain.get_topo_height();
+    let (start_topoheight, end_topoheight) = get_range(params.minimum_topoheight, params.maximum_topoheight, MAX_DEPOSITS_TOPOHEIGHT_RANGE, current_topoheight)?;
+    let (filter, address) = params.address.extract_data();
+    let key = address.get_public_key();
+
+    let storage = blockchain.get_storage().read().await;
+    let pruned_topoheight = storage.get_pruned_topoheight().context("Error while retrieving pruned topoheight")?.unwrap_or(0);
+    let mut deposits = Vec::new();
+    for topoheight in start_topoheight.max(pruned_topoheight)..=end_topoheight {
+        let confirmations = current_topoheight - topoheight + 1;
+        if confirmations < params.confirmations {
+            break;
+        }
+
+        let (hash, block_header) = storage.get_block_header_at_topoheight(topoheight).await.context(format!("Error while retrieving block header at topo height {topoheight}"))?;
+        for tx_hash in block_header.get_transactions() {
+            if !storage.is_tx_executed_in_block(tx_hash, &hash).context("Error while checking if tx was executed")? {
+                continue;
+            }
+
+            let tx = storage.get_transaction(tx_hash).await.context(format!("Error while retrieving transaction {tx_hash} from block {hash}"))?;
+            let TransactionType::Transfer(transfers) = tx.get_data() else {
+                continue;
+            };
+
+            for transfer in transfers.iter().filter(|transfer| transfer.to == *key) {
+                if params.asset.as_ref().is_some_and(|asset| *asset != transfer.asset) {
+                    continue;
+                }
+
+                // extra data that isn't a valid data element is considered as no integrated data
+                let data = match &transfer.extra_data {
+                    Some(bytes) => Option::<DataElement>::from_bytes(bytes).ok().flatten(),
+                    None => None
+                };
+
+                if filter.is_some() && data != filter {
+                    continue;
+                }
+
+                deposits.push((data, DepositEntry {
+                    hash: tx_hash.clone(),
+                    topoheight,
+                    from: tx.get_owner().to_address(mainnet),
+                    asset: transfer.asset.clone(),
+                    amount: transfer.amount,
+                    confirmations
+                }));
+            }
+        }
+    }
+
+    Ok(json!(DepositGroup::group(deposits)))
+}
+
 async fn get_account_assets<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     let params: GetAccountAssetsParams = parse_params(body)?;
     let blockchain: &Arc<Blockchain<S>> = context.get()?;
@@ -874,4 +1564,254 @@ async fn get_size_on_disk<S: Storage>(context: Context, body: Value) -> Result<V
         size_bytes,
         size_formatted
     }))
-}
\ No newline at end of file
+}
+
+// Get all the configured hard forks of the network, including the upcoming ones
+async fn get_hard_forks<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    Ok(json!(config::get_hard_forks(blockchain.get_network())))
+}
+
+// PoW seeds used by the next block and by the next epoch
+async fn get_pow_seed<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    if !blockchain.is_pow_seed_used() {
+        return Err(InternalRpcError::CustomStr("No hard fork using the PoW seed is scheduled on this network"))
+    }
+
+    let storage = blockchain.get_storage().read().await;
+    let seed = blockchain.get_pow_seed(&*storage).await.context("Error while retrieving PoW seed")?;
+    Ok(json!(seed))
+}
+
+// proof that a block of an external chain is committed in a block
+async fn get_aux_commitment_proof<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetAuxCommitmentProofParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let storage = blockchain.get_storage().read().await;
+    let block_hash = params.block_hash.into_owned();
+    if !storage.has_block(&block_hash).await.context("Error while checking if block exists")? {
+        return Err(InternalRpcError::AnyError(BlockchainError::BlockNotFound(block_hash).into()))
+    }
+
+    let header = storage.get_block_header_by_hash(&block_hash).await.context("Error while retrieving block header")?;
+    let chain_id = params.chain_id.into_owned();
+    let Some((commitment, (index, entry))) = header.get_aux_commitment().and_then(|commitment| Some((commitment, commitment.get_entry(&chain_id)?))) else {
+        return Err(InternalRpcError::AnyError(BlockchainError::AuxChainNotCommitted(block_hash, chain_id).into()))
+    };
+
+    let proof = AuxCommitmentProof {
+        entry: entry.clone(),
+        index,
+        count: commitment.get_entries().len(),
+        branch: commitment.get_branch(index),
+        header_work: hex::encode(header.get_work()),
+        block_work: hex::encode(header.get_serialized_header())
+    };
+
+    let topoheight = if storage.is_block_topological_ordered(&block_hash).await {
+        Some(storage.get_topo_height_for_hash(&block_hash).await.context("Error while retrieving topoheight")?)
+    } else {
+        None
+    };
+    let difficulty = storage.get_difficulty_for_block_hash(&block_hash).await.context("Error while retrieving difficulty")?;
+
+    Ok(json!(GetAuxCommitmentProofResult {
+        block_hash,
+        height: header.get_height(),
+        topoheight,
+        difficulty,
+        proof
+    }))
+}
+
+const MAX_REPLAY_TOPOHEIGHTS: u64 = 20;
+// events which can be rebuilt from the chain
+const REPLAYABLE_EVENTS: [NotifyEvent; 3] = [NotifyEvent::BlockOrdered, NotifyEvent::TransactionExecuted, NotifyEvent::BalanceChanged];
+
+// replay the events of the blocks ordered from a topoheight, as they are notified to the subscribers
+// at most 20 topoheights are replayed per request, next_topoheight is the one to request after
+async fn replay_events<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: ReplayEventsParams = parse_params(body)?;
+    let tracked: HashSet<NotifyEvent> = match params.events {
+        Some(events) => {
+            if let Some(event) = events.iter().find(|event| !REPLAYABLE_EVENTS.contains(event)) {
+                return Err(InternalRpcError::InvalidRequest).context(format!("Event {:?} can't be replayed", event))?
+            }
+            events
+        },
+        None => REPLAYABLE_EVENTS.into_iter().collect()
+    };
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    // a websocket session watching addresses only replays the events notified to it
+    let watched: Option<HashSet<PublicKey>> = match context.get_optional::<DaemonWebSocketSession<S>>() {
+        Some(session) => {
+            let keys = session.get_server().get_handler().get_watched_keys(session).await;
+            (!keys.is_empty()).then(|| keys.into_iter().collect())
+        },
+        None => None
+    };
+
+    // hold the storage during the replay, so no block is ordered meanwhile
+    let storage = blockchain.get_storage().read().await;
+    let current_topoheight = blockchain.get_topo_height();
+    let pruned_topoheight = storage.get_pruned_topoheight().context("Error while retrieving pruned topoheight")?.unwrap_or(0);
+    if params.from_topoheight < pruned_topoheight {
+        return Err(InternalRpcError::CustomStr("From topoheight is lower than pruned topoheight"));
+    }
+
+    let to_topoheight = params.to_topoheight.unwrap_or(current_topoheight);
+    if to_topoheight < params.from_topoheight || to_topoheight > current_topoheight {
+        // already up to date with the chain
+        if params.to_topoheight.is_none() && params.from_topoheight == current_topoheight + 1 {
+            return Ok(json!(ReplayEventsResult {
+                events: Vec::new(),
+                next_topoheight: params.from_topoheight,
+                topoheight: current_topoheight
+            }))
+        }
+        return Err(InternalRpcError::InvalidRequest).context(format!("Invalid range requested, from: {}, to: {}", params.from_topoheight, to_topoheight))?
+    }
+
+    let end_topoheight = to_topoheight.min(params.from_topoheight + MAX_REPLAY_TOPOHEIGHTS - 1);
+    let mut events = Vec::new();
+    for topoheight in params.from_topoheight..=end_topoheight {
+        let values = blockchain.get_events_at_topoheight(&storage, topoheight, &tracked).await.context(format!("Error while replaying events at topoheight {}", topoheight))?;
+        events.extend(values.into_iter()
+            .filter(|(_, keys)| match (keys, &watched) {
+                (Some(keys), Some(watched)) => keys.iter().any(|key| watched.contains(key)),
+                _ => true
+            })
+            .map(|(event, _)| event));
+    }
+
+    Ok(json!(ReplayEventsResult {
+        events,
+        next_topoheight: end_topoheight + 1,
+        topoheight: current_topoheight
+    }))
+}
+
+type DaemonWebSocketSession<S> = WebSocketSessionShared<EventWebSocketHandler<Arc<Blockchain<S>>, NotifyEvent>>;
+
+// accounts of the addresses to watch, they must be on our network
+fn get_watched_keys<S: Storage>(blockchain: &Blockchain<S>, addresses: Vec<Address>) -> Result<Vec<PublicKey>, InternalRpcError> {
+    let mainnet = blockchain.get_network().is_mainnet();
+    addresses.into_iter().map(|address| {
+        if address.is_mainnet() != mainnet {
+            return Err(InternalRpcError::AnyError(BlockchainError::InvalidNetwork.into()))
+        }
+        Ok(address.to_public_key())
+    }).collect()
+}
+
+// only notify the websocket session of the events involving these addresses
+// returns the count of addresses watched by the session
+async fn watch_addresses<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: WatchAddressesParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let session: &DaemonWebSocketSession<S> = context.get_optional().ok_or(InternalRpcError::ClientNotFound)?;
+    let keys = get_watched_keys(blockchain, params.addresses)?;
+    let count = session.get_server().get_handler().watch_keys(session, keys, RPC_MAX_WATCHED_ADDRESSES).await?;
+    Ok(json!(count))
+}
+
+async fn unwatch_addresses<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: UnwatchAddressesParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let session: &DaemonWebSocketSession<S> = context.get_optional().ok_or(InternalRpcError::ClientNotFound)?;
+    let keys = match params.addresses {
+        Some(addresses) => Some(get_watched_keys(blockchain, addresses)?),
+        None => None
+    };
+    let count = session.get_server().get_handler().unwatch_keys(session, keys).await;
+    Ok(json!(count))
+}
+
+async fn get_watched_addresses<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let session: &DaemonWebSocketSession<S> = context.get_optional().ok_or(InternalRpcError::ClientNotFound)?;
+    let mainnet = blockchain.get_network().is_mainnet();
+    let addresses: Vec<Address> = session.get_server().get_handler().get_watched_keys(session).await
+        .into_iter()
+        .map(|key| key.to_address(mainnet))
+        .collect();
+    Ok(json!(addresses))
+}
+
+async fn get_contract<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetContractParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let storage = blockchain.get_storage().read().await;
+    if !storage.has_contract(&params.contract).await.context("Error while checking if contract exists")? {
+        return Err(InternalRpcError::AnyError(BlockchainError::ContractNotFound(params.contract.into_owned()).into()))
+    }
+
+    let (topoheight, code) = storage.get_contract(&params.contract).await.context("Error while retrieving contract")?;
+    Ok(json!(GetContractResult {
+        topoheight,
+        code: Cow::Owned(code)
+    }))
+}
+
+async fn get_contract_data<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetContractDataParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let current_topoheight = blockchain.get_topo_height();
+    let topoheight = params.topoheight.unwrap_or(current_topoheight);
+    if topoheight > current_topoheight {
+        return Err(InternalRpcError::UnexpectedParams).context("Topoheight cannot be greater than current chain topoheight")?
+    }
+
+    let storage = blockchain.get_storage().read().await;
+    if !storage.has_contract(&params.contract).await.context("Error while checking if contract exists")? {
+        return Err(InternalRpcError::AnyError(BlockchainError::ContractNotFound(params.contract.into_owned()).into()))
+    }
+
+    let data = storage.get_contract_data_at_maximum_topoheight(&params.contract, &params.key, topoheight).await.context("Error while retrieving contract data")?;
+    Ok(json!(GetContractDataResult {
+        topoheight: data.map(|(topoheight, _)| topoheight),
+        value: data.map(|(_, value)| value).unwrap_or(0)
+    }))
+}
+
+// an expired name is not resolved anymore, even if it was not registered again
+async fn resolve_name<S: Storage>(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: ResolveNameParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let current_topoheight = blockchain.get_topo_height();
+    let topoheight = params.topoheight.unwrap_or(current_topoheight);
+    if topoheight > current_topoheight {
+        return Err(InternalRpcError::UnexpectedParams).context("Topoheight cannot be greater than current chain topoheight")?
+    }
+
+    let name = params.name.strip_suffix(NAME_SUFFIX).unwrap_or(&params.name);
+    if !is_valid_name(name) {
+        return Err(InternalRpcError::CustomStr("Invalid name"))
+    }
+
+    let storage = blockchain.get_storage().read().await;
+    let entry = storage.get_name_at_maximum_topoheight(name, topoheight).await.context("Error while retrieving name")?
+        .map(|(_, entry)| entry)
+        .filter(|entry| entry.is_active_at(topoheight))
+        .ok_or_else(|| InternalRpcError::AnyError(BlockchainError::NameNotFound(name.to_owned()).into()))?;
+
+    Ok(json!(ResolveNameResult {
+        name: name.to_owned(),
+        owner: entry.get_owner().to_address(blockchain.get_network().is_mainnet()),
+        registered_topoheight: entry.get_registered_topoheight(),
+        expiration: entry.get_expiration()
+    }))
+}
@@ -0,0 +1,101 @@
+use std::{path::Path, sync::Arc};
+use anyhow::{Context, Result};
+use clap::Args;
+use log::{info, warn};
+use xelis_common::{
+    json_rpc::JsonRPCClient,
+    network::Network,
+    prompt::ShareablePrompt,
+    rpc_server::{RPCHandler, HttpServerConfig}
+};
+use xelis_wallet::{
+    api::AuthConfig,
+    config::get_wallet_path,
+    daemon_api::DaemonAPI,
+    wallet::Wallet
+};
+use crate::{
+    core::{blockchain::Blockchain, storage::Storage},
+    rpc::rpc::register_methods
+};
+
+// displayed as the daemon address of the embedded wallet
+const LOCAL_DAEMON_ADDRESS: &str = "local";
+
+#[derive(Args)]
+pub struct WalletConfig {
+    /// Name of the wallet to run in the same process, it is created if it doesn't exist
+    #[clap(long)]
+    pub wallet_name: Option<String>,
+    /// Password of the embedded wallet, asked in the prompt if not set
+    #[clap(long)]
+    pub wallet_password: Option<String>,
+    /// Enable the RPC server of the embedded wallet on this address
+    #[clap(long)]
+    pub wallet_rpc_bind_address: Option<String>,
+    /// Username for the RPC server authentication of the embedded wallet
+    #[clap(long)]
+    pub wallet_rpc_username: Option<String>,
+    /// Password for the RPC server authentication of the embedded wallet
+    #[clap(long)]
+    pub wallet_rpc_password: Option<String>
+}
+
+// Open the wallet and connect it to the daemon without going through HTTP:
+// its requests are executed by a RPC handler of the daemon in the same process
+pub async fn start_wallet<S: Storage>(config: WalletConfig, blockchain: Arc<Blockchain<S>>, network: Network, prompt: &ShareablePrompt) -> Result<Option<Arc<Wallet>>> {
+    let Some(name) = config.wallet_name else {
+        return Ok(None)
+    };
+
+    let password = match config.wallet_password {
+        Some(password) => password,
+        None => prompt.read_input(format!("Enter Password for '{}': ", name), true).await?
+    };
+
+    let dir = get_wallet_path(&network, name);
+    let wallet = if Path::new(&dir).is_dir() {
+        info!("Opening wallet {}", dir);
+        Wallet::open(dir, password, network)?
+    } else {
+        info!("Creating a new wallet at {}", dir);
+        Wallet::create(dir, password, None, network)?
+    };
+
+    // the handler is independent of the RPC server, so the wallet works even if it is not started
+    let mut handler = RPCHandler::new(blockchain);
+    register_methods(&mut handler);
+    let client = JsonRPCClient::with_local_handler(LOCAL_DAEMON_ADDRESS.to_owned(), Arc::new(handler));
+    wallet.set_online_mode_with_api(LOCAL_DAEMON_ADDRESS.to_owned(), DaemonAPI::with_client(client)).await
+        .context("Error while connecting the wallet to the daemon")?;
+
+    if let Some(address) = config.wallet_rpc_bind_address {
+        let auth_config = match (config.wallet_rpc_username, config.wallet_rpc_password) {
+            (Some(username), Some(password)) => Some(AuthConfig { username, password }),
+            (None, None) => None,
+            _ => {
+                warn!("Both username and password are required for the wallet RPC server authentication, ignoring it");
+                None
+            }
+        };
+
+        info!("Enabling wallet RPC Server on {} {}", address, if auth_config.is_some() { "with authentication" } else { "without authentication" });
+        wallet.enable_rpc_server(address, auth_config, HttpServerConfig::default()).await
+            .context("Error while enabling the wallet RPC Server")?;
+    }
+
+    info!("Wallet {} is running in the daemon, its address is {}", name, wallet.get_address());
+    Ok(Some(wallet))
+}
+
+pub async fn stop_wallet(wallet: &Wallet) {
+    info!("Stopping wallet...");
+    let rpc_server = wallet.get_api_server().lock().await.take();
+    if let Some(rpc_server) = rpc_server {
+        rpc_server.stop().await;
+    }
+
+    if let Err(e) = wallet.set_offline_mode().await {
+        warn!("Error while stopping the wallet network handler: {}", e);
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+use actix_web::{
+    web::{self, Data, Path, Query, Json},
+    middleware::Condition,
+    App,
+    HttpServer,
+    dev::ServerHandle
+};
+use serde::{Serialize, Deserialize};
+use xelis_common::{
+    asset::AssetWithData,
+    crypto::{hash::Hash, address::Address, key::PublicKey},
+    config::VERSION,
+    rpc_server::HttpServerConfig,
+    serializer::Serializer
+};
+use crate::{
+    config::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE},
+    entry::{BlockEntry, TransactionEntry, AccountEntry, SearchResult},
+    error::ExplorerError,
+    indexer::Indexer,
+    storage::ExplorerStorage
+};
+
+type ApiResult<T> = Result<Json<T>, ExplorerError>;
+
+pub struct ApiState {
+    storage: Arc<ExplorerStorage>,
+    indexer: Arc<Indexer>
+}
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    skip: Option<usize>,
+    limit: Option<usize>
+}
+
+impl PageQuery {
+    fn get_skip(&self) -> usize {
+        self.skip.unwrap_or(0)
+    }
+
+    fn get_limit(&self) -> Result<usize, ExplorerError> {
+        match self.limit {
+            Some(limit) if limit > MAX_PAGE_SIZE => Err(ExplorerError::InvalidQuery(format!("limit must be at most {}", MAX_PAGE_SIZE))),
+            Some(limit) => Ok(limit),
+            None => Ok(DEFAULT_PAGE_SIZE)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    query: String
+}
+
+#[derive(Serialize)]
+pub struct InfoResult {
+    version: &'static str,
+    // highest topoheight indexed
+    topoheight: Option<u64>,
+    // topoheight of the daemon at last sync
+    daemon_topoheight: u64,
+    assets: usize
+}
+
+fn parse_hash(value: &str) -> Option<Hash> {
+    if value.len() != 64 {
+        return None
+    }
+    Hash::from_hex(value.to_owned()).ok()
+}
+
+fn parse_key(value: &str) -> Result<PublicKey, ExplorerError> {
+    Address::from_string(&value.to_owned())
+        .map(Address::to_public_key)
+        .map_err(|e| ExplorerError::InvalidQuery(format!("invalid address: {}", e)))
+}
+
+async fn info(state: Data<ApiState>) -> ApiResult<InfoResult> {
+    Ok(Json(InfoResult {
+        version: VERSION,
+        topoheight: state.storage.get_top_topoheight()?,
+        daemon_topoheight: state.indexer.get_daemon_topoheight(),
+        assets: state.storage.count_assets()
+    }))
+}
+
+async fn get_blocks(state: Data<ApiState>, query: Query<PageQuery>) -> ApiResult<Vec<BlockEntry>> {
+    Ok(Json(state.storage.get_blocks(query.get_skip(), query.get_limit()?)?))
+}
+
+// block by its hash or its topoheight
+async fn get_block(state: Data<ApiState>, id: Path<String>) -> ApiResult<BlockEntry> {
+    let block = if let Ok(topoheight) = id.parse::<u64>() {
+        state.storage.get_block_at_topoheight(topoheight)?
+    } else {
+        let hash = parse_hash(&id).ok_or_else(|| ExplorerError::InvalidQuery("expected a block hash or a topoheight".to_owned()))?;
+        state.storage.get_block(&hash)?
+    };
+    Ok(Json(block.ok_or(ExplorerError::NotFound)?))
+}
+
+async fn get_transaction(state: Data<ApiState>, hash: Path<String>) -> ApiResult<TransactionEntry> {
+    let hash = parse_hash(&hash).ok_or_else(|| ExplorerError::InvalidQuery("invalid transaction hash".to_owned()))?;
+    Ok(Json(state.storage.get_transaction(&hash)?.ok_or(ExplorerError::NotFound)?))
+}
+
+async fn get_account(state: Data<ApiState>, address: Path<String>) -> ApiResult<AccountEntry> {
+    let key = parse_key(&address)?;
+    Ok(Json(state.storage.get_account(&key)?.ok_or(ExplorerError::NotFound)?))
+}
+
+async fn get_account_transactions(state: Data<ApiState>, address: Path<String>, query: Query<PageQuery>) -> ApiResult<Vec<TransactionEntry>> {
+    let key = parse_key(&address)?;
+    Ok(Json(state.storage.get_account_transactions(&key, query.get_skip(), query.get_limit()?)?))
+}
+
+async fn get_assets(state: Data<ApiState>, query: Query<PageQuery>) -> ApiResult<Vec<AssetWithData>> {
+    Ok(Json(state.storage.get_assets(query.get_skip(), query.get_limit()?)?))
+}
+
+async fn get_asset(state: Data<ApiState>, hash: Path<String>) -> ApiResult<AssetWithData> {
+    let hash = parse_hash(&hash).ok_or_else(|| ExplorerError::InvalidQuery("invalid asset hash".to_owned()))?;
+    Ok(Json(state.storage.get_asset(&hash)?.ok_or(ExplorerError::NotFound)?))
+}
+
+// search a block by topoheight, a block or a transaction by hash, or an account by address
+async fn search(state: Data<ApiState>, query: Query<SearchQuery>) -> ApiResult<SearchResult> {
+    let query = query.query.trim();
+    if let Ok(topoheight) = query.parse::<u64>() {
+        let block = state.storage.get_block_at_topoheight(topoheight)?.ok_or(ExplorerError::NotFound)?;
+        return Ok(Json(SearchResult::Block(block)))
+    }
+
+    if let Some(hash) = parse_hash(query) {
+        if let Some(block) = state.storage.get_block(&hash)? {
+            return Ok(Json(SearchResult::Block(block)))
+        }
+
+        let transaction = state.storage.get_transaction(&hash)?.ok_or(ExplorerError::NotFound)?;
+        return Ok(Json(SearchResult::Transaction(transaction)))
+    }
+
+    let key = parse_key(query)?;
+    let account = state.storage.get_account(&key)?.ok_or(ExplorerError::NotFound)?;
+    Ok(Json(SearchResult::Account(account)))
+}
+
+// start the HTTP server of the explorer API
+pub fn start_server(bind_address: &str, storage: Arc<ExplorerStorage>, indexer: Arc<Indexer>, http_config: HttpServerConfig) -> Result<ServerHandle, ExplorerError> {
+    let state = Data::new(ApiState {
+        storage,
+        indexer
+    });
+
+    let threads = http_config.get_threads();
+    let http_server = HttpServer::new(move || {
+        let cors = Condition::new(http_config.is_cors_enabled(), http_config.build_cors());
+        let scope = web::scope(&format!("{}/api", http_config.get_path_prefix()))
+            .route("/info", web::get().to(info))
+            .route("/blocks", web::get().to(get_blocks))
+            .route("/blocks/{id}", web::get().to(get_block))
+            .route("/transactions/{hash}", web::get().to(get_transaction))
+            .route("/accounts/{address}", web::get().to(get_account))
+            .route("/accounts/{address}/transactions", web::get().to(get_account_transactions))
+            .route("/assets", web::get().to(get_assets))
+            .route("/assets/{hash}", web::get().to(get_asset))
+            .route("/search", web::get().to(search));
+
+        App::new().app_data(state.clone())
+            .wrap(cors)
+            .service(scope)
+    })
+    .disable_signals()
+    .workers(threads)
+    .bind(bind_address)?
+    .run();
+
+    let handle = http_server.handle();
+    tokio::spawn(http_server);
+    Ok(handle)
+}
@@ -0,0 +1,13 @@
+// daemon address by default when no specified
+pub const DEFAULT_DAEMON_ADDRESS: &str = "http://127.0.0.1:8080";
+// explorer API server
+pub const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:8082";
+// directory of the index database
+pub const DEFAULT_DIR_PATH: &str = "explorer";
+
+// maximum items returned by a list endpoint
+pub const MAX_PAGE_SIZE: usize = 100;
+// items returned by a list endpoint if no limit is set
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+// delay before retrying to sync after an error
+pub const SYNC_RETRY_DELAY_SECS: u64 = 5;
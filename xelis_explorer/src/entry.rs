@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+use serde::{Serialize, Deserialize};
+use xelis_common::{
+    api::daemon::BlockType,
+    block::Difficulty,
+    crypto::{hash::Hash, key::PublicKey},
+    transaction::TransactionType
+};
+
+// Block as stored in the index, only ordered blocks are indexed
+#[derive(Serialize, Deserialize)]
+pub struct BlockEntry {
+    pub hash: Hash,
+    pub topoheight: u64,
+    pub height: u64,
+    pub timestamp: u128,
+    pub block_type: BlockType,
+    pub miner: PublicKey,
+    // worker name tagged in the extra nonce by the daemon
+    #[serde(default)]
+    pub worker: Option<String>,
+    pub tips: Vec<Hash>,
+    pub txs_hashes: Vec<Hash>,
+    pub difficulty: Difficulty,
+    pub cumulative_difficulty: Difficulty,
+    pub supply: Option<u64>,
+    pub reward: Option<u64>,
+    pub total_fees: Option<u64>,
+    pub total_size_in_bytes: usize
+}
+
+// Transaction executed on chain
+#[derive(Serialize, Deserialize)]
+pub struct TransactionEntry {
+    pub hash: Hash,
+    pub owner: PublicKey,
+    pub fee: u64,
+    pub nonce: u64,
+    pub data: TransactionType,
+    // block in which it was executed
+    pub executed_in_block: Hash,
+    // topoheight of the executing block
+    pub topoheight: u64,
+    pub timestamp: u128
+}
+
+impl TransactionEntry {
+    // all accounts involved in this transaction
+    pub fn get_accounts(&self) -> HashSet<&PublicKey> {
+        let mut accounts = HashSet::new();
+        accounts.insert(&self.owner);
+        if let TransactionType::Transfer(transfers) = &self.data {
+            for transfer in transfers {
+                accounts.insert(&transfer.to);
+            }
+        }
+        accounts
+    }
+}
+
+// Activity of an account seen by the explorer
+#[derive(Serialize, Deserialize)]
+pub struct AccountEntry {
+    pub key: PublicKey,
+    // first topoheight at which the account was seen
+    pub first_topoheight: u64,
+    // last topoheight at which the account had an activity
+    pub last_topoheight: u64,
+    // transactions sent or received
+    pub transactions: u64,
+    pub blocks_mined: u64
+}
+
+// Result of a search query
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "value")]
+pub enum SearchResult {
+    Block(BlockEntry),
+    Transaction(TransactionEntry),
+    Account(AccountEntry)
+}
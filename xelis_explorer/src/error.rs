@@ -0,0 +1,42 @@
+use actix_web::{ResponseError, HttpResponse, http::StatusCode};
+use serde_json::json;
+use thiserror::Error;
+use xelis_common::json_rpc::JsonRPCError;
+
+#[derive(Error, Debug)]
+pub enum ExplorerError {
+    #[error(transparent)]
+    Database(#[from] sled::Error),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Daemon(#[from] JsonRPCError),
+    #[error("Index is corrupted")]
+    CorruptedIndex,
+    #[error("Invalid topoheight {} to index, expected {}", _0, _1)]
+    InvalidTopoHeight(u64, u64),
+    #[error("Block at topoheight {} has no topoheight", _0)]
+    BlockNotOrdered(u64),
+    #[error("Invalid query: {}", _0)]
+    InvalidQuery(String),
+    #[error("Not found")]
+    NotFound
+}
+
+impl ResponseError for ExplorerError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidQuery(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(json!({
+            "error": self.to_string()
+        }))
+    }
+}
@@ -0,0 +1,204 @@
+use std::{sync::{Arc, atomic::{AtomicU64, Ordering}}, time::Duration};
+use log::{info, debug, error, warn};
+use tokio::time::sleep;
+use xelis_common::{
+    api::daemon::{DaemonEvent, NotifyEvent},
+    block::get_worker_tag,
+    json_rpc::{DaemonClient, WebSocketJsonRPCClient, EventReceiver},
+};
+use crate::{
+    config::SYNC_RETRY_DELAY_SECS,
+    entry::{BlockEntry, TransactionEntry},
+    error::ExplorerError,
+    storage::ExplorerStorage
+};
+
+// maximum assets returned by the daemon per request
+const ASSETS_PER_REQUEST: usize = 100;
+
+// Keep the index in sync with the daemon
+// Blocks are indexed in topological order, and the index is rewinded when the DAG is reordered
+pub struct Indexer {
+    client: DaemonClient,
+    // websocket address of the daemon to receive its events
+    websocket_target: String,
+    storage: Arc<ExplorerStorage>,
+    // last topoheight known of the daemon
+    daemon_topoheight: AtomicU64
+}
+
+impl Indexer {
+    pub fn new(daemon_address: &str, storage: Arc<ExplorerStorage>) -> Self {
+        let daemon_address = daemon_address.trim_end_matches('/');
+        Self {
+            client: DaemonClient::new(format!("{}/json_rpc", daemon_address)),
+            websocket_target: format!("{}/ws", daemon_address.replacen("http", "ws", 1)),
+            storage,
+            daemon_topoheight: AtomicU64::new(0)
+        }
+    }
+
+    pub fn get_client(&self) -> &DaemonClient {
+        &self.client
+    }
+
+    pub fn get_daemon_topoheight(&self) -> u64 {
+        self.daemon_topoheight.load(Ordering::SeqCst)
+    }
+
+    // run forever, restart the sync in case of error
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            if let Err(e) = self.listen().await {
+                error!("Error while syncing with daemon: {}", e);
+            }
+            sleep(Duration::from_secs(SYNC_RETRY_DELAY_SECS)).await;
+        }
+    }
+
+    // sync the index and update it on each block ordered by the daemon
+    async fn listen(&self) -> Result<(), ExplorerError> {
+        info!("Connecting to daemon events at {}", self.websocket_target);
+        let websocket = WebSocketJsonRPCClient::<NotifyEvent>::new(self.websocket_target.clone()).await?;
+        // subscribe before syncing to not miss any block
+        let mut receiver: EventReceiver<DaemonEvent<'static>> = websocket.subscribe_event(NotifyEvent::BlockOrdered).await?;
+        let res = self.handle_events(&mut receiver).await;
+        if let Err(e) = websocket.close().await {
+            debug!("Error while closing websocket: {}", e);
+        }
+        res
+    }
+
+    async fn handle_events(&self, receiver: &mut EventReceiver<DaemonEvent<'static>>) -> Result<(), ExplorerError> {
+        self.sync().await?;
+        loop {
+            let event = match receiver.next().await? {
+                DaemonEvent::BlockOrdered(event) => event,
+                event => {
+                    debug!("Ignoring unexpected event {:?}", event.get_event());
+                    continue;
+                }
+            };
+            debug!("Block {} ordered at topoheight {}", event.block_hash, event.topoheight);
+            // a block already indexed has been reordered
+            self.rewind_until(event.topoheight)?;
+            self.sync().await?;
+        }
+    }
+
+    // remove all blocks indexed at or above the topoheight
+    fn rewind_until(&self, topoheight: u64) -> Result<(), ExplorerError> {
+        let mut top = self.storage.get_top_topoheight()?;
+        while let Some(current) = top {
+            if current < topoheight {
+                break;
+            }
+            debug!("Unindexing block at topoheight {}", current);
+            top = self.storage.unindex_top_block()?;
+        }
+        Ok(())
+    }
+
+    // index all the blocks ordered by the daemon that we don't have yet
+    pub async fn sync(&self) -> Result<(), ExplorerError> {
+        let info = self.client.get_info().await?;
+        self.daemon_topoheight.store(info.topoheight, Ordering::SeqCst);
+
+        // check that our top block is still at the same topoheight on the daemon
+        let mut top = self.storage.get_top_topoheight()?;
+        while let Some(topoheight) = top {
+            if topoheight <= info.topoheight {
+                let block = self.client.get_block_at_topoheight(topoheight).await?;
+                if Some(block.data.hash.as_ref()) == self.storage.get_hash_at_topoheight(topoheight)?.as_ref() {
+                    break;
+                }
+            }
+            warn!("Block at topoheight {} has been reordered, unindexing it", topoheight);
+            top = self.storage.unindex_top_block()?;
+        }
+
+        // a pruned daemon can't give us the blocks below its pruned topoheight
+        let start = match top {
+            Some(topoheight) => topoheight + 1,
+            None => info.pruned_topoheight.unwrap_or(0)
+        };
+
+        if start <= info.topoheight {
+            info!("Indexing blocks from topoheight {} to {}", start, info.topoheight);
+        }
+
+        for topoheight in start..=info.topoheight {
+            self.index_topoheight(topoheight).await?;
+        }
+
+        self.sync_assets().await?;
+        self.storage.flush().await
+    }
+
+    async fn index_topoheight(&self, topoheight: u64) -> Result<(), ExplorerError> {
+        let response = self.client.get_block_at_topoheight(topoheight).await?;
+        let hash = response.data.hash.into_owned();
+        let header = response.data.data.into_owned();
+
+        let mut transactions = Vec::new();
+        if !header.get_txs_hashes().is_empty() {
+            let responses = self.client.get_transactions(header.get_txs_hashes().clone()).await?;
+            for response in responses.into_iter().flatten() {
+                // only the transactions executed in this block are indexed with it
+                if response.executed_in_block.as_ref() != Some(&hash) {
+                    continue;
+                }
+
+                let tx = response.data.data.into_owned();
+                transactions.push(TransactionEntry {
+                    hash: response.data.hash.into_owned(),
+                    owner: tx.get_owner().clone(),
+                    fee: tx.get_fee(),
+                    nonce: tx.get_nonce(),
+                    data: tx.get_data().clone(),
+                    executed_in_block: hash.clone(),
+                    topoheight,
+                    timestamp: header.get_timestamp()
+                });
+            }
+        }
+
+        let block = BlockEntry {
+            topoheight: response.topoheight.ok_or(ExplorerError::BlockNotOrdered(topoheight))?,
+            height: header.get_height(),
+            timestamp: header.get_timestamp(),
+            block_type: response.block_type,
+            miner: header.get_miner().clone(),
+            worker: get_worker_tag(header.get_extra_nonce()).map(str::to_owned),
+            tips: header.get_tips().clone(),
+            txs_hashes: header.get_txs_hashes().clone(),
+            difficulty: response.difficulty,
+            cumulative_difficulty: response.cumulative_difficulty,
+            supply: response.supply,
+            reward: response.reward,
+            total_fees: response.total_fees,
+            total_size_in_bytes: response.total_size_in_bytes,
+            hash
+        };
+
+        self.storage.index_block(&block, &transactions)
+    }
+
+    // assets can't be removed, only fetch the new ones
+    async fn sync_assets(&self) -> Result<(), ExplorerError> {
+        let count = self.client.count_assets().await?;
+        let mut skip = self.storage.count_assets();
+        while skip < count {
+            let assets = self.client.get_assets(Some(skip), Some(ASSETS_PER_REQUEST), None, None).await?;
+            if assets.is_empty() {
+                break;
+            }
+
+            skip += assets.len();
+            for asset in assets {
+                self.storage.add_asset(&asset)?;
+            }
+        }
+        Ok(())
+    }
+}
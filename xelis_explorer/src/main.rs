@@ -0,0 +1,86 @@
+pub mod config;
+pub mod entry;
+pub mod error;
+pub mod storage;
+pub mod indexer;
+pub mod api;
+
+use std::{sync::Arc, time::Duration};
+use clap::Parser;
+use log::{error, info};
+use anyhow::{Result, Context};
+use xelis_common::{
+    config::VERSION,
+    prompt::{Prompt, LogLevel, ShareablePrompt, command::CommandManager},
+    rpc_server::HttpServerConfig,
+    utils::set_network_to,
+    async_handler
+};
+use crate::{
+    config::{DEFAULT_DAEMON_ADDRESS, DEFAULT_BIND_ADDRESS, DEFAULT_DIR_PATH},
+    indexer::Indexer,
+    storage::ExplorerStorage
+};
+
+#[derive(Parser)]
+#[clap(version = VERSION, about = "XELIS Explorer")]
+pub struct ExplorerConfig {
+    /// Daemon address to index
+    #[clap(short = 'a', long, default_value_t = String::from(DEFAULT_DAEMON_ADDRESS))]
+    daemon_address: String,
+    /// Bind address for the explorer API
+    #[clap(short, long, default_value_t = String::from(DEFAULT_BIND_ADDRESS))]
+    bind_address: String,
+    /// Directory of the index database
+    #[clap(long, default_value_t = String::from(DEFAULT_DIR_PATH))]
+    dir_path: String,
+    /// Set log level
+    #[clap(long, arg_enum, default_value_t = LogLevel::Info)]
+    log_level: LogLevel,
+    /// Disable the log file
+    #[clap(short = 'f', long)]
+    disable_file_logging: bool,
+    /// Log filename
+    #[clap(short = 'l', long, default_value_t = String::from("xelis-explorer.log"))]
+    filename_log: String,
+    #[clap(flatten)]
+    http_config: HttpServerConfig
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let config: ExplorerConfig = ExplorerConfig::parse();
+    let prompt = Prompt::new(config.log_level, config.filename_log, config.disable_file_logging)?;
+
+    let storage = Arc::new(ExplorerStorage::new(&config.dir_path).context("Error while opening the index database")?);
+    let indexer = Arc::new(Indexer::new(&config.daemon_address, storage.clone()));
+
+    // addresses are displayed using the network of the daemon
+    let info = indexer.get_client().get_info().await.context("Error while connecting to the daemon")?;
+    info!("Connected to daemon {} on {:?} at topoheight {}", info.version, info.network, info.topoheight);
+    set_network_to(info.network);
+
+    let task = tokio::spawn(indexer.clone().run());
+    info!("Starting explorer API on {}", config.bind_address);
+    let server = api::start_server(&config.bind_address, storage.clone(), indexer, config.http_config)?;
+
+    if let Err(e) = run_prompt(prompt).await {
+        error!("Error on running prompt: {}", e);
+    }
+
+    server.stop(true).await;
+    task.abort();
+    storage.flush().await?;
+
+    Ok(())
+}
+
+async fn run_prompt(prompt: ShareablePrompt) -> Result<()> {
+    let command_manager: CommandManager<()> = CommandManager::default(prompt.clone())?;
+    let closure = |_: &_, _: &_| async {
+        Ok(String::from("XELIS Explorer >> "))
+    };
+
+    prompt.start(Duration::from_millis(100), Box::new(async_handler!(closure)), &Some(command_manager)).await?;
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use serde::{Serialize, de::DeserializeOwned};
+use sled::{Db, Tree, IVec};
+use xelis_common::{
+    asset::AssetWithData,
+    crypto::{hash::{Hash, HASH_SIZE}, key::PublicKey}
+};
+use crate::{
+    entry::{BlockEntry, TransactionEntry, AccountEntry},
+    error::ExplorerError
+};
+
+const TOP_TOPOHEIGHT: &[u8; 4] = b"TOPO";
+
+// Index of the explorer, optimized for the queries of the API
+// values are stored in JSON, keys use the binary format of the shared types
+pub struct ExplorerStorage {
+    db: Db,
+    // block hash => BlockEntry
+    blocks: Tree,
+    // topoheight => block hash
+    topoheights: Tree,
+    // tx hash => TransactionEntry
+    transactions: Tree,
+    // public key => AccountEntry
+    accounts: Tree,
+    // public key + topoheight + tx hash => nothing
+    // sorted by topoheight to list the transactions of an account
+    account_transactions: Tree,
+    // asset hash => AssetWithData
+    assets: Tree,
+    // metadata of the index
+    extra: Tree
+}
+
+fn load<T: DeserializeOwned>(tree: &Tree, key: &[u8]) -> Result<Option<T>, ExplorerError> {
+    match tree.get(key)? {
+        Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+        None => Ok(None)
+    }
+}
+
+fn store<T: Serialize>(tree: &Tree, key: &[u8], value: &T) -> Result<(), ExplorerError> {
+    tree.insert(key, serde_json::to_vec(value)?)?;
+    Ok(())
+}
+
+fn account_transaction_key(key: &PublicKey, topoheight: u64, hash: &Hash) -> Vec<u8> {
+    let mut bytes = key.as_bytes().to_vec();
+    bytes.extend_from_slice(&topoheight.to_be_bytes());
+    bytes.extend_from_slice(hash.as_bytes());
+    bytes
+}
+
+// read the tx hash and its topoheight from a key of account_transactions
+fn parse_account_transaction_key(key: &IVec) -> Result<(u64, Hash), ExplorerError> {
+    let len = key.len();
+    if len < HASH_SIZE + 8 {
+        return Err(ExplorerError::CorruptedIndex)
+    }
+    let topoheight = u64::from_be_bytes(key[len - HASH_SIZE - 8..len - HASH_SIZE].try_into().map_err(|_| ExplorerError::CorruptedIndex)?);
+    let hash = Hash::new(key[len - HASH_SIZE..].try_into().map_err(|_| ExplorerError::CorruptedIndex)?);
+    Ok((topoheight, hash))
+}
+
+impl ExplorerStorage {
+    pub fn new(dir_path: &str) -> Result<Self, ExplorerError> {
+        let db = sled::open(dir_path)?;
+        Ok(Self {
+            blocks: db.open_tree("blocks")?,
+            topoheights: db.open_tree("topoheights")?,
+            transactions: db.open_tree("transactions")?,
+            accounts: db.open_tree("accounts")?,
+            account_transactions: db.open_tree("account_transactions")?,
+            assets: db.open_tree("assets")?,
+            extra: db.open_tree("extra")?,
+            db
+        })
+    }
+
+    // highest topoheight indexed, None if nothing is indexed
+    pub fn get_top_topoheight(&self) -> Result<Option<u64>, ExplorerError> {
+        match self.extra.get(TOP_TOPOHEIGHT)? {
+            Some(value) => Ok(Some(u64::from_be_bytes(value.as_ref().try_into().map_err(|_| ExplorerError::CorruptedIndex)?))),
+            None => Ok(None)
+        }
+    }
+
+    fn set_top_topoheight(&self, topoheight: Option<u64>) -> Result<(), ExplorerError> {
+        match topoheight {
+            Some(topoheight) => self.extra.insert(TOP_TOPOHEIGHT, &topoheight.to_be_bytes())?,
+            None => self.extra.remove(TOP_TOPOHEIGHT)?
+        };
+        Ok(())
+    }
+
+    pub fn get_hash_at_topoheight(&self, topoheight: u64) -> Result<Option<Hash>, ExplorerError> {
+        match self.topoheights.get(topoheight.to_be_bytes())? {
+            Some(value) => Ok(Some(Hash::new(value.as_ref().try_into().map_err(|_| ExplorerError::CorruptedIndex)?))),
+            None => Ok(None)
+        }
+    }
+
+    pub fn get_block(&self, hash: &Hash) -> Result<Option<BlockEntry>, ExplorerError> {
+        load(&self.blocks, hash.as_bytes())
+    }
+
+    pub fn get_block_at_topoheight(&self, topoheight: u64) -> Result<Option<BlockEntry>, ExplorerError> {
+        match self.get_hash_at_topoheight(topoheight)? {
+            Some(hash) => self.get_block(&hash),
+            None => Ok(None)
+        }
+    }
+
+    // latest blocks first
+    pub fn get_blocks(&self, skip: usize, limit: usize) -> Result<Vec<BlockEntry>, ExplorerError> {
+        let mut blocks = Vec::with_capacity(limit);
+        for res in self.topoheights.iter().rev().skip(skip).take(limit) {
+            let (_, hash) = res?;
+            let block = load(&self.blocks, &hash)?.ok_or(ExplorerError::CorruptedIndex)?;
+            blocks.push(block);
+        }
+        Ok(blocks)
+    }
+
+    pub fn get_transaction(&self, hash: &Hash) -> Result<Option<TransactionEntry>, ExplorerError> {
+        load(&self.transactions, hash.as_bytes())
+    }
+
+    pub fn get_account(&self, key: &PublicKey) -> Result<Option<AccountEntry>, ExplorerError> {
+        load(&self.accounts, key.as_bytes())
+    }
+
+    // latest transactions of the account first
+    pub fn get_account_transactions(&self, key: &PublicKey, skip: usize, limit: usize) -> Result<Vec<TransactionEntry>, ExplorerError> {
+        let mut transactions = Vec::with_capacity(limit);
+        for res in self.account_transactions.scan_prefix(key.as_bytes()).rev().skip(skip).take(limit) {
+            let (key, _) = res?;
+            let (_, hash) = parse_account_transaction_key(&key)?;
+            let transaction = self.get_transaction(&hash)?.ok_or(ExplorerError::CorruptedIndex)?;
+            transactions.push(transaction);
+        }
+        Ok(transactions)
+    }
+
+    pub fn get_asset(&self, asset: &Hash) -> Result<Option<AssetWithData>, ExplorerError> {
+        load(&self.assets, asset.as_bytes())
+    }
+
+    pub fn get_assets(&self, skip: usize, limit: usize) -> Result<Vec<AssetWithData>, ExplorerError> {
+        let mut assets = Vec::with_capacity(limit);
+        for res in self.assets.iter().skip(skip).take(limit) {
+            let (_, value) = res?;
+            assets.push(serde_json::from_slice(&value)?);
+        }
+        Ok(assets)
+    }
+
+    pub fn count_assets(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn add_asset(&self, asset: &AssetWithData) -> Result<(), ExplorerError> {
+        store(&self.assets, asset.get_asset().as_bytes(), asset)
+    }
+
+    fn update_account<F: FnOnce(&mut AccountEntry)>(&self, key: &PublicKey, topoheight: u64, update: F) -> Result<(), ExplorerError> {
+        let mut account = self.get_account(key)?.unwrap_or_else(|| AccountEntry {
+            key: key.clone(),
+            first_topoheight: topoheight,
+            last_topoheight: topoheight,
+            transactions: 0,
+            blocks_mined: 0
+        });
+        account.last_topoheight = account.last_topoheight.max(topoheight);
+        update(&mut account);
+        store(&self.accounts, key.as_bytes(), &account)
+    }
+
+    // index a new block on top of the others with the transactions executed in it
+    pub fn index_block(&self, block: &BlockEntry, transactions: &[TransactionEntry]) -> Result<(), ExplorerError> {
+        if let Some(top) = self.get_top_topoheight()? {
+            if block.topoheight != top + 1 {
+                return Err(ExplorerError::InvalidTopoHeight(block.topoheight, top + 1))
+            }
+        }
+
+        for tx in transactions {
+            store(&self.transactions, tx.hash.as_bytes(), tx)?;
+            for key in tx.get_accounts() {
+                self.account_transactions.insert(account_transaction_key(key, block.topoheight, &tx.hash), &[])?;
+                self.update_account(key, block.topoheight, |account| account.transactions += 1)?;
+            }
+        }
+
+        self.update_account(&block.miner, block.topoheight, |account| account.blocks_mined += 1)?;
+        store(&self.blocks, block.hash.as_bytes(), block)?;
+        self.topoheights.insert(block.topoheight.to_be_bytes(), block.hash.as_bytes())?;
+        self.set_top_topoheight(Some(block.topoheight))
+    }
+
+    fn revert_account<F: FnOnce(&mut AccountEntry)>(&self, key: &PublicKey, topoheight: u64, update: F) -> Result<(), ExplorerError> {
+        let Some(mut account) = self.get_account(key)? else {
+            return Ok(())
+        };
+        update(&mut account);
+
+        if account.transactions == 0 && account.blocks_mined == 0 {
+            self.accounts.remove(key.as_bytes())?;
+            return Ok(())
+        }
+
+        if account.last_topoheight >= topoheight {
+            // last activity is now its latest transaction still indexed
+            account.last_topoheight = match self.account_transactions.scan_prefix(key.as_bytes()).next_back() {
+                Some(res) => parse_account_transaction_key(&res?.0)?.0,
+                None => account.first_topoheight
+            };
+        }
+        store(&self.accounts, key.as_bytes(), &account)
+    }
+
+    // remove the top block from the index, used when the DAG is reordered by the daemon
+    // returns the new top topoheight
+    pub fn unindex_top_block(&self) -> Result<Option<u64>, ExplorerError> {
+        let Some(topoheight) = self.get_top_topoheight()? else {
+            return Ok(None)
+        };
+
+        let hash = self.get_hash_at_topoheight(topoheight)?.ok_or(ExplorerError::CorruptedIndex)?;
+        let block = self.get_block(&hash)?.ok_or(ExplorerError::CorruptedIndex)?;
+        for tx_hash in &block.txs_hashes {
+            let Some(tx) = self.get_transaction(tx_hash)? else {
+                continue
+            };
+
+            // transaction was executed in another block
+            if tx.executed_in_block != hash {
+                continue
+            }
+
+            self.transactions.remove(tx_hash.as_bytes())?;
+            for key in tx.get_accounts() {
+                self.account_transactions.remove(account_transaction_key(key, topoheight, tx_hash))?;
+                self.revert_account(key, topoheight, |account| account.transactions = account.transactions.saturating_sub(1))?;
+            }
+        }
+
+        self.revert_account(&block.miner, topoheight, |account| account.blocks_mined = account.blocks_mined.saturating_sub(1))?;
+        self.blocks.remove(hash.as_bytes())?;
+        self.topoheights.remove(topoheight.to_be_bytes())?;
+
+        let top = topoheight.checked_sub(1);
+        self.set_top_topoheight(top)?;
+        Ok(top)
+    }
+
+    pub async fn flush(&self) -> Result<(), ExplorerError> {
+        self.db.flush_async().await?;
+        Ok(())
+    }
+}
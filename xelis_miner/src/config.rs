@@ -1,2 +1,2 @@
-// daemon address by default when no specified
-pub const DEFAULT_DAEMON_ADDRESS: &str = "127.0.0.1:8080";
\ No newline at end of file
+// daemon host by default when no specified, the port depends on the network
+pub const DEFAULT_DAEMON_HOST: &str = "127.0.0.1";
\ No newline at end of file
@@ -0,0 +1,295 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use anyhow::{bail, Context, Result};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    select,
+    sync::{mpsc, Mutex}
+};
+use xelis_common::{
+    api::daemon::GetPowSeedResult,
+    block::{set_worker_tag, BlockMiner, Difficulty, EXTRA_NONCE_SIZE},
+    crypto::hash::Hashable,
+    difficulty::check_difficulty,
+    serializer::Serializer
+};
+
+// byte of the extra nonce set to the worker index, the last one is used for the thread id
+// so each thread of each worker mines a disjoint nonce space
+pub const WORKER_NONCE_INDEX: usize = EXTRA_NONCE_SIZE - 2;
+// index used by the threads of the coordinator itself
+pub const COORDINATOR_INDEX: u8 = 0;
+// same limit as the daemon
+pub const MAX_WORKER_NAME_SIZE: usize = 32;
+
+// Messages exchanged between the coordinator and its workers, one JSON per line
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoordinatorMessage {
+    // sent to the workers, its extra nonce is unique per worker
+    NewJob {
+        template: String,
+        difficulty: Difficulty,
+        height: u64,
+        #[serde(default)]
+        topoheight: u64
+    },
+    // response of the daemon for a block found by the worker
+    BlockResult {
+        accepted: bool
+    },
+    // sent by the workers
+    BlockFound {
+        block: String
+    },
+    // sent by the workers once connected, they receive the jobs tagged with their name once registered
+    Register {
+        worker: String
+    },
+    // PoW seed received from the daemon, sent to the workers before the jobs using it
+    PowSeed(GetPowSeedResult)
+}
+
+impl CoordinatorMessage {
+    pub fn to_line(&self) -> Result<String> {
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        Ok(line)
+    }
+}
+
+#[derive(Clone)]
+struct Job {
+    block: BlockMiner<'static>,
+    difficulty: Difficulty,
+    height: u64,
+    topoheight: u64
+}
+
+impl Job {
+    // the worker name replaces the one of the coordinator in the tag set by the daemon
+    fn to_message(&self, index: u8, name: &str) -> CoordinatorMessage {
+        let mut block = self.block.clone();
+        set_worker_tag(&mut block.extra_nonce, name);
+        block.extra_nonce[WORKER_NONCE_INDEX] = index;
+        CoordinatorMessage::NewJob {
+            template: block.to_hex(),
+            difficulty: self.difficulty,
+            height: self.height,
+            topoheight: self.topoheight
+        }
+    }
+}
+
+struct Worker {
+    sender: mpsc::UnboundedSender<CoordinatorMessage>,
+    // set once the worker is registered
+    name: Option<String>
+}
+
+impl Worker {
+    fn send(&self, message: CoordinatorMessage) -> bool {
+        self.sender.send(message).is_ok()
+    }
+}
+
+// Distribute the jobs received from the daemon to the workers of a LAN farm
+// and submit the blocks they found through the connection of the coordinator
+pub struct Coordinator {
+    // workers connected by their index
+    workers: Mutex<HashMap<u8, Worker>>,
+    job: Mutex<Option<Job>>,
+    pow_seed: Mutex<Option<GetPowSeedResult>>,
+    // blocks found are submitted by the communication task like the ones of the local threads
+    block_sender: mpsc::Sender<BlockMiner<'static>>
+}
+
+impl Coordinator {
+    pub fn new(block_sender: mpsc::Sender<BlockMiner<'static>>) -> Arc<Self> {
+        Arc::new(Self {
+            workers: Mutex::new(HashMap::new()),
+            job: Mutex::new(None),
+            pow_seed: Mutex::new(None),
+            block_sender
+        })
+    }
+
+    pub async fn start(self: Arc<Self>, bind_address: String) {
+        let listener = match TcpListener::bind(&bind_address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Error while starting coordinator on {}: {}", bind_address, e);
+                return
+            }
+        };
+
+        info!("Coordinator is listening for workers on {}", bind_address);
+        loop {
+            match listener.accept().await {
+                Ok((stream, addr)) => {
+                    tokio::spawn(Arc::clone(&self).handle_worker(stream, addr));
+                },
+                Err(e) => error!("Error while accepting a new worker: {}", e)
+            };
+        }
+    }
+
+    // register a new worker using the first index available
+    async fn register(&self) -> Option<(u8, mpsc::UnboundedReceiver<CoordinatorMessage>)> {
+        let mut workers = self.workers.lock().await;
+        let index = (COORDINATOR_INDEX + 1..=u8::MAX).find(|index| !workers.contains_key(index))?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        workers.insert(index, Worker { sender, name: None });
+        Some((index, receiver))
+    }
+
+    async fn handle_worker(self: Arc<Self>, mut stream: TcpStream, addr: SocketAddr) {
+        let Some((index, mut receiver)) = self.register().await else {
+            warn!("Maximum workers reached, rejecting {}", addr);
+            let _ = stream.shutdown().await;
+            return
+        };
+
+        info!("Worker #{} connected from {}", index, addr);
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        loop {
+            select! {
+                Some(message) = receiver.recv() => {
+                    let res = match message.to_line() {
+                        Ok(line) => write.write_all(line.as_bytes()).await.context("Error while sending message"),
+                        Err(e) => Err(e)
+                    };
+
+                    if let Err(e) = res {
+                        debug!("Error with worker #{}: {:#}", index, e);
+                        break;
+                    }
+                },
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => {
+                        if let Err(e) = self.handle_worker_message(index, &line).await {
+                            warn!("Invalid message from worker #{}: {:#}", index, e);
+                            break;
+                        }
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        debug!("Error while reading from worker #{}: {}", index, e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.workers.lock().await.remove(&index);
+        info!("Worker #{} ({}) disconnected", index, addr);
+    }
+
+    async fn handle_worker_message(&self, index: u8, line: &str) -> Result<()> {
+        let block = match serde_json::from_str(line)? {
+            CoordinatorMessage::BlockFound { block } => block,
+            CoordinatorMessage::Register { worker } => return self.register_name(index, worker).await,
+            _ => bail!("unexpected message")
+        };
+
+        let block = BlockMiner::from_hex(block).context("Error while decoding block")?;
+        if block.extra_nonce[WORKER_NONCE_INDEX] != index {
+            bail!("block found outside of its nonce space")
+        }
+
+        // verify the POW of the current job, blocks of an old job are verified by the daemon
+        if let Some(job) = self.job.lock().await.as_ref() {
+            if job.block.header_work_hash == block.header_work_hash && !check_difficulty(&block.get_pow_hash(), job.difficulty)? {
+                bail!("block found doesn't match the job difficulty")
+            }
+        }
+
+        info!("Worker #{} ({}) found block {}", index, self.get_name(index).await, block.hash());
+        self.block_sender.send(block).await.context("Error while submitting block")?;
+        Ok(())
+    }
+
+    // name of the worker, or its index if not registered
+    async fn get_name(&self, index: u8) -> String {
+        let workers = self.workers.lock().await;
+        workers.get(&index)
+            .and_then(|worker| worker.name.clone())
+            .unwrap_or_else(|| format!("#{}", index))
+    }
+
+    // save the worker name and send it the current job
+    async fn register_name(&self, index: u8, name: String) -> Result<()> {
+        if name.len() > MAX_WORKER_NAME_SIZE {
+            bail!("worker name is too long")
+        }
+
+        let mut workers = self.workers.lock().await;
+        let Some(worker) = workers.get_mut(&index) else {
+            return Ok(())
+        };
+
+        if worker.name.is_some() {
+            bail!("worker already registered")
+        }
+
+        info!("Worker #{} registered as {}", index, name);
+        if let Some(seed) = self.pow_seed.lock().await.as_ref() {
+            worker.send(CoordinatorMessage::PowSeed(seed.clone()));
+        }
+        if let Some(job) = self.job.lock().await.as_ref() {
+            worker.send(job.to_message(index, &name));
+        }
+        worker.name = Some(name);
+        Ok(())
+    }
+
+    // the local threads also mine the job, using the coordinator index
+    pub async fn set_job(&self, block: &BlockMiner<'static>, difficulty: Difficulty, height: u64, topoheight: u64) {
+        let job = Job {
+            block: block.clone(),
+            difficulty,
+            height,
+            topoheight
+        };
+
+        let workers = self.workers.lock().await;
+        for (index, worker) in workers.iter() {
+            // wait for the worker to register first
+            let Some(name) = worker.name.as_deref() else {
+                continue;
+            };
+
+            if !worker.send(job.to_message(*index, name)) {
+                debug!("Worker #{} is disconnected, job not sent", index);
+            }
+        }
+        *self.job.lock().await = Some(job);
+    }
+
+    pub async fn set_pow_seed(&self, seed: GetPowSeedResult) {
+        let workers = self.workers.lock().await;
+        for (index, worker) in workers.iter().filter(|(_, worker)| worker.name.is_some()) {
+            if !worker.send(CoordinatorMessage::PowSeed(seed.clone())) {
+                debug!("Worker #{} is disconnected, PoW seed not sent", index);
+            }
+        }
+        *self.pow_seed.lock().await = Some(seed);
+    }
+
+    // the daemon answers the blocks in the order they are submitted
+    pub async fn notify_block_result(&self, index: u8, accepted: bool) {
+        if index == COORDINATOR_INDEX {
+            return
+        }
+
+        let workers = self.workers.lock().await;
+        if let Some(worker) = workers.get(&index) {
+            if !worker.send(CoordinatorMessage::BlockResult { accepted }) {
+                debug!("Worker #{} is disconnected, block result not sent", index);
+            }
+        }
+    }
+}
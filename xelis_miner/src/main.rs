@@ -1,11 +1,23 @@
 pub mod config;
-
-use std::{time::Duration, sync::atomic::{AtomicU64, Ordering, AtomicUsize, AtomicBool}, thread};
-use crate::config::DEFAULT_DAEMON_ADDRESS;
+pub mod stats;
+pub mod coordinator;
+
+use std::{time::Duration, sync::{atomic::{AtomicU64, Ordering, AtomicUsize, AtomicBool}, Arc}, thread, collections::VecDeque};
+use crate::{
+    config::DEFAULT_DAEMON_HOST,
+    stats::{ReportFormat, SessionStats},
+    coordinator::{Coordinator, CoordinatorMessage, COORDINATOR_INDEX, WORKER_NONCE_INDEX}
+};
 use fern::colors::Color;
 use futures_util::{StreamExt, SinkExt};
 use serde::{Serialize, Deserialize};
-use tokio::{sync::{broadcast, mpsc, Mutex}, select, time::Instant};
+use tokio::{
+    sync::{broadcast, mpsc, Mutex},
+    select,
+    time::{interval, Instant},
+    net::TcpStream,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader}
+};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use xelis_common::{
     block::{BlockMiner, BLOCK_WORK_SIZE, Difficulty},
@@ -13,12 +25,14 @@ use xelis_common::{
     difficulty::check_difficulty,
     config::VERSION,
     utils::{get_current_timestamp, format_hashrate, format_difficulty},
-    crypto::{hash::{Hashable, Hash, hash}, address::Address},
-    api::daemon::{GetBlockTemplateResult, SubmitBlockParams}, prompt::{Prompt, command::CommandManager, LogLevel, ShareablePrompt, self}, async_handler,
+    crypto::{hash::{Hash, Hashable, hash, hash_x4, get_hash_x4_implementation, HASH_LANES}, address::Address},
+    network::Network,
+    api::daemon::{BlockRejection, GetBlockTemplateResult, GetPowSeedResult, MinerDifficultyHint, ReportHashrateParams, SubmitBlockParams}, prompt::{Prompt, command::CommandManager, LogLevel, ShareablePrompt, self}, async_handler,
+    pow::{DatasetConfig, DatasetManager, DatasetMode, DATASET_PARAMS}
 };
 use clap::Parser;
 use log::{error, info, debug, warn};
-use anyhow::{Result, Error, Context};
+use anyhow::{Result, Error, Context, bail};
 use lazy_static::lazy_static;
 
 #[derive(Parser)]
@@ -27,9 +41,12 @@ pub struct MinerConfig {
     /// Wallet address to mine and receive block rewards on
     #[clap(short, long)]
     miner_address: String,
-    /// Daemon address to connect to for mining
-    #[clap(short = 'a', long, default_value_t = String::from(DEFAULT_DAEMON_ADDRESS))]
-    daemon_address: String,
+    /// Daemon address to connect to for mining, default is the local daemon of the network
+    #[clap(short = 'a', long)]
+    daemon_address: Option<String>,
+    /// Network of the daemon, default is detected from the miner address
+    #[clap(long, arg_enum)]
+    network: Option<Network>,
     /// Set log level
     #[clap(long, arg_enum, default_value_t = LogLevel::Info)]
     log_level: LogLevel,
@@ -50,21 +67,120 @@ pub struct MinerConfig {
     num_threads: u8,
     /// Worker name to be displayed on daemon side
     #[clap(short, long, default_value_t = String::from("default"))]
-    worker: String
+    worker: String,
+    /// Append the statistics of the mining session to this file when the miner exits
+    #[clap(long)]
+    report_file: Option<String>,
+    /// Format of the session report
+    #[clap(long, arg_enum, default_value_t = ReportFormat::Csv)]
+    report_format: ReportFormat,
+    /// Distribute the jobs of the daemon to the workers connecting to this address (ip:port)
+    #[clap(long)]
+    coordinator_bind_address: Option<String>,
+    /// Mine the jobs distributed by this coordinator (ip:port) instead of connecting to the daemon
+    #[clap(long, conflicts_with = "coordinator-bind-address")]
+    coordinator_address: Option<String>,
+    /// Percentage of the time spent mining (such as 50%), the threads sleep the rest of the time to reduce the load
+    #[clap(long, default_value = "100%", parse(try_from_str = parse_duty))]
+    duty: u8,
+    #[clap(flatten)]
+    pow_dataset: DatasetConfig
+}
+
+fn parse_duty(value: &str) -> Result<u8, String> {
+    let duty: u8 = value.trim_end_matches('%').parse().map_err(|e| format!("invalid percentage: {}", e))?;
+    if duty == 0 || duty > 100 {
+        return Err("duty must be between 1% and 100%".to_owned())
+    }
+    Ok(duty)
+}
+
+// Alternate the mining bursts and the sleep periods of a thread
+struct DutyCycle {
+    burst: Duration,
+    pause: Duration,
+    burst_start: Instant
+}
+
+impl DutyCycle {
+    // no duty cycle at 100%
+    fn new(duty: u8) -> Option<Self> {
+        if duty >= 100 {
+            return None
+        }
+
+        let burst = DUTY_CYCLE_PERIOD * duty as u32 / 100;
+        Some(Self {
+            burst,
+            pause: DUTY_CYCLE_PERIOD - burst,
+            burst_start: Instant::now()
+        })
+    }
+
+    // sleep once the burst is over, the job is checked right after
+    // so a job received during the pause is mined as soon as it ends
+    fn pause_if_needed(&mut self) {
+        if self.burst_start.elapsed() >= self.burst {
+            thread::sleep(self.pause);
+            self.burst_start = Instant::now();
+        }
+    }
 }
 
 #[derive(Clone)]
 enum ThreadNotification<'a> {
-    NewJob(BlockMiner<'a>, Difficulty, u64), // block work, difficulty, height
+    NewJob(BlockMiner<'a>, Difficulty, u64, u64), // block work, difficulty, height, job id
     WebSocketClosed, // WebSocket connection has been closed
     Exit // all threads must stop
 }
 
+// Last job mined, to ignore the jobs already received or outdated and to detect the stale blocks
+#[derive(Default)]
+struct JobTracker {
+    header_work_hash: Option<Hash>,
+    topoheight: u64
+}
+
+impl JobTracker {
+    // returns false if the job must be ignored
+    // the daemon sends the jobs to each miner in a separate task so they may be received out of order
+    fn update(&mut self, block: &BlockMiner, topoheight: u64) -> bool {
+        if self.header_work_hash.as_ref() == Some(&block.header_work_hash) {
+            debug!("Job {} already received, ignoring it", block.header_work_hash);
+            return false
+        }
+
+        if topoheight < self.topoheight {
+            warn!("Job {} is outdated (topoheight {} while current is {}), ignoring it", block.header_work_hash, topoheight, self.topoheight);
+            return false
+        }
+
+        self.header_work_hash = Some(block.header_work_hash.clone());
+        self.topoheight = topoheight;
+        true
+    }
+
+    // a block is stale if its job has been replaced, its template may be already orphaned
+    fn is_stale(&self, block: &BlockMiner) -> bool {
+        self.header_work_hash.as_ref() != Some(&block.header_work_hash)
+    }
+}
+
+// Block submitted and waiting for a response, the daemon answers in order
+struct PendingBlock {
+    submitted: Instant,
+    // index of the worker which found it when using a coordinator
+    worker: u8,
+    stale: bool
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum SocketMessage {
     NewJob(GetBlockTemplateResult),
     BlockAccepted,
-    BlockRejected
+    BlockRejected(BlockRejection),
+    DifficultyHint(MinerDifficultyHint),
+    NewPowSeed(GetPowSeedResult)
 }
 
 static WEBSOCKET_CONNECTED: AtomicBool = AtomicBool::new(false);
@@ -75,10 +191,15 @@ static HASHRATE_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 lazy_static! {
     static ref HASHRATE_LAST_TIME: Mutex<Instant> = Mutex::new(Instant::now());
+    static ref SESSION_STATS: SessionStats = SessionStats::default();
 }
 
 // After how many iterations we update the timestamp of the block to avoid too much CPU usage 
 const UPDATE_EVERY_NONCE: u64 = 1_000;
+// interval in seconds to report our hashrate to the daemon
+const REPORT_HASHRATE_INTERVAL: u64 = 30;
+// a mining burst followed by a pause when a duty is set
+const DUTY_CYCLE_PERIOD: Duration = Duration::from_secs(1);
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
@@ -91,6 +212,18 @@ async fn main() -> Result<()> {
             return Ok(())
         }
     };
+
+    // a testnet address is also used on the dev network, so it can only be detected between mainnet and testnet
+    let network = match config.network {
+        Some(network) if network.is_mainnet() != address.is_mainnet() => {
+            error!("Miner address {} can't be used on network {}", address, network);
+            return Ok(())
+        },
+        Some(network) => network,
+        None if address.is_mainnet() => Network::Mainnet,
+        None => Network::Testnet
+    };
+    let daemon_address = config.daemon_address.unwrap_or_else(|| format!("{}:{}", DEFAULT_DAEMON_HOST, network.get_default_rpc_port()));
     let threads_count = num_cpus::get();
     let mut threads = config.num_threads;
     if threads_count > u8::MAX as usize {
@@ -110,7 +243,20 @@ async fn main() -> Result<()> {
         return Ok(())
     }
 
+    // mining with the light mode is much slower, the full dataset is used unless the memory limit is too low
+    let pow_dataset = match DatasetManager::new(config.pow_dataset, DATASET_PARAMS, DatasetMode::Full) {
+        Ok(manager) => Arc::new(manager),
+        Err(e) => {
+            error!("Invalid PoW dataset configuration: {}", e);
+            return Ok(())
+        }
+    };
+    debug!("PoW dataset will be used in {:?} mode", pow_dataset.get_mode());
+
     info!("Miner address: {}", address);    
+    if config.duty < 100 {
+        info!("Duty cycle enabled, threads will mine {}% of the time", config.duty);
+    }
     if config.num_threads != 0 && threads as usize != threads_count {
         warn!("Attention, the number of threads used may not be optimal, recommended is: {}", threads_count);
     }
@@ -121,13 +267,22 @@ async fn main() -> Result<()> {
     let (block_sender, block_receiver) = mpsc::channel::<BlockMiner>(threads as usize);
     for id in 0..threads {
         debug!("Starting thread #{}", id);
-        if let Err(e) = start_thread(id, sender.subscribe(), block_sender.clone()) {
+        if let Err(e) = start_thread(id, sender.subscribe(), block_sender.clone(), config.duty) {
             error!("Error while creating Mining Thread #{}: {}", id, e);
         }
     }
 
     // start communication task
-    let task = tokio::spawn(communication_task(config.daemon_address, sender.clone(), block_receiver, address, config.worker));
+    let task = if let Some(coordinator_address) = config.coordinator_address {
+        tokio::spawn(worker_task(coordinator_address, sender.clone(), block_receiver, config.worker.clone(), pow_dataset))
+    } else {
+        let coordinator = config.coordinator_bind_address.map(|bind_address| {
+            let coordinator = Coordinator::new(block_sender.clone());
+            tokio::spawn(Arc::clone(&coordinator).start(bind_address));
+            coordinator
+        });
+        tokio::spawn(communication_task(daemon_address, sender.clone(), block_receiver, address, config.worker.clone(), coordinator, pow_dataset))
+    };
 
     if let Err(e) = run_prompt(prompt).await {
         error!("Error on running prompt: {}", e);
@@ -141,11 +296,40 @@ async fn main() -> Result<()> {
     // stop the communication task
     task.abort();
 
+    let report = SESSION_STATS.get_report(&config.worker, threads);
+    info!("Session: {} jobs, {} hashes, {} blocks found ({} accepted, {} rejected, {} stale with {} rejected), average latency: {}ms", report.jobs_received, report.hashes, report.blocks_found, report.blocks_accepted, report.blocks_rejected, report.stale_blocks, report.stale_rejected, report.average_latency);
+    if let Some(path) = config.report_file {
+        match report.write_to(&path, config.report_format) {
+            Ok(()) => info!("Session report written to {}", path),
+            Err(e) => error!("Error while writing session report to {}: {}", path, e)
+        };
+    }
+
     Ok(())
 }
 
+// verify that the vectorized hashing used to mine gives the same hashes as the scalar implementation
+fn validate_hash_x4(iterations: usize) -> bool {
+    let mut values = [[0u8; BLOCK_WORK_SIZE]; HASH_LANES];
+    for _ in 0..iterations {
+        values.iter_mut().flatten().for_each(|v| *v = rand::random::<u8>());
+        let hashes = hash_x4([&values[0], &values[1], &values[2], &values[3]]);
+        if values.iter().zip(hashes.iter()).any(|(value, result)| hash(value) != *result) {
+            return false
+        }
+    }
+    true
+}
+
 fn benchmark(threads: usize, iterations: usize) {
-    println!("{0: <10} | {1: <10} | {2: <16} | {3: <13} | {4: <13}", "Threads", "Total Time", "Total Iterations", "Time/PoW (ms)", "Hashrate");
+    let implementation = get_hash_x4_implementation();
+    if !validate_hash_x4(iterations) {
+        error!("Hashes computed using {} don't match the scalar implementation", implementation);
+        return
+    }
+    info!("Vectorized hashing ({}) validated against the scalar implementation", implementation);
+
+    println!("{0: <10} | {1: <10} | {2: <16} | {3: <13} | {4: <13} | {5: <13}", "Threads", "Total Time", "Total Iterations", "Time/PoW (ms)", "Hashrate", "Hashrate x4");
 
     for bench in 1..=threads {
         let start = Instant::now();
@@ -166,7 +350,27 @@ fn benchmark(threads: usize, iterations: usize) {
         }
         let duration = start.elapsed().as_millis();
         let hashrate = format_hashrate(1000f64 / (duration as f64 / (bench*iterations) as f64));
-        println!("{0: <10} | {1: <10} | {2: <16} | {3: <13} | {4: <13}", bench, duration, bench*iterations, duration/(bench*iterations) as u128, hashrate);
+
+        // same iterations using the vectorized hashing of the mining threads
+        let start = Instant::now();
+        let mut handles = vec![];
+        for _ in 0..bench {
+            let handle = thread::spawn(move || {
+                let mut values = [[0u8; BLOCK_WORK_SIZE]; HASH_LANES];
+                for _ in 0..iterations.div_ceil(HASH_LANES) {
+                    values.iter_mut().flatten().for_each(|v| *v = rand::random::<u8>());
+                    let _ = hash_x4([&values[0], &values[1], &values[2], &values[3]]);
+                }
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let duration_x4 = start.elapsed().as_millis();
+        let hashrate_x4 = format_hashrate(1000f64 / (duration_x4 as f64 / (bench*iterations.div_ceil(HASH_LANES)*HASH_LANES) as f64));
+        println!("{0: <10} | {1: <10} | {2: <16} | {3: <13} | {4: <13} | {5: <13}", bench, duration, bench*iterations, duration/(bench*iterations) as u128, hashrate, hashrate_x4);
     }
 }
 
@@ -174,7 +378,7 @@ fn benchmark(threads: usize, iterations: usize) {
 // It maintains a WebSocket connection with the daemon and notify all threads when it receive a new job.
 // Its also the task who have the job to send directly the new block found by one of the threads.
 // This allow mining threads to only focus on mining and receiving jobs through memory channels.
-async fn communication_task(mut daemon_address: String, job_sender: broadcast::Sender<ThreadNotification<'_>>, mut block_receiver: mpsc::Receiver<BlockMiner<'_>>, address: Address, worker: String) {
+async fn communication_task(mut daemon_address: String, job_sender: broadcast::Sender<ThreadNotification<'static>>, mut block_receiver: mpsc::Receiver<BlockMiner<'static>>, address: Address, worker: String, coordinator: Option<Arc<Coordinator>>, pow_dataset: Arc<DatasetManager>) {
     info!("Starting communication task");
     'main: loop {
         if !daemon_address.starts_with("ws://") && !daemon_address.starts_with("wss://") {
@@ -213,10 +417,26 @@ async fn communication_task(mut daemon_address: String, job_sender: broadcast::S
         WEBSOCKET_CONNECTED.store(true, Ordering::SeqCst);
         info!("Connected successfully to {}", daemon_address);
         let (mut write, mut read) = client.split();
+        let mut pending_blocks: VecDeque<PendingBlock> = VecDeque::new();
+        let mut tracker = JobTracker::default();
+        let mut report_interval = interval(Duration::from_secs(REPORT_HASHRATE_INTERVAL));
+        // skip the first tick as nothing was mined yet
+        report_interval.tick().await;
+        let mut last_hashes = SESSION_STATS.get_hashes();
         loop {
             select! {
+                _ = report_interval.tick() => { // report the hashrate since the last report, it includes the local threads only
+                    let hashes = SESSION_STATS.get_hashes();
+                    let hashrate = hashes.saturating_sub(last_hashes) as f64 / REPORT_HASHRATE_INTERVAL as f64;
+                    last_hashes = hashes;
+                    let report = serde_json::json!(ReportHashrateParams { hashrate }).to_string();
+                    if let Err(e) = write.send(Message::Text(report)).await {
+                        error!("Error while reporting the hashrate to the daemon: {}", e);
+                        break;
+                    }
+                },
                 Some(message) = read.next() => { // read all messages from daemon
-                    match handle_websocket_message(message, &job_sender).await {
+                    match handle_websocket_message(message, &job_sender, &mut pending_blocks, &mut tracker, coordinator.as_ref(), &pow_dataset).await {
                         Ok(exit) => {
                             if exit {
                                 break;
@@ -230,11 +450,22 @@ async fn communication_task(mut daemon_address: String, job_sender: broadcast::S
                 },
                 Some(block) = block_receiver.recv() => { // send all valid blocks found to the daemon
                     debug!("Block header work hash found: {}", block.header_work_hash);
+                    let stale = tracker.is_stale(&block);
+                    if stale {
+                        warn!("Block {} was found for an old job", block.hash());
+                    }
+                    SESSION_STATS.add_block_found(stale);
+
                     let submit = serde_json::json!(SubmitBlockParams { block_template: block.to_hex() }).to_string();
                     if let Err(e) = write.send(Message::Text(submit)).await {
                         error!("Error while sending the block found to the daemon: {}", e);
                         break;
                     }
+                    pending_blocks.push_back(PendingBlock {
+                        submitted: Instant::now(),
+                        worker: block.extra_nonce[WORKER_NONCE_INDEX],
+                        stale
+                    });
                 }
             }
         }
@@ -249,27 +480,82 @@ async fn communication_task(mut daemon_address: String, job_sender: broadcast::S
     }
 }
 
-async fn handle_websocket_message(message: Result<Message, tokio_tungstenite::tungstenite::Error>, job_sender: &broadcast::Sender<ThreadNotification<'_>>) -> Result<bool, Error> {
+// handle the response of the daemon for the oldest block submitted
+// a stale block rejected is expected as its template may be orphaned, so it is not reported as an error
+async fn handle_block_result(accepted: bool, reason: Option<String>, pending_blocks: &mut VecDeque<PendingBlock>, coordinator: Option<&Arc<Coordinator>>) {
+    let pending = pending_blocks.pop_front();
+    let latency = pending.as_ref().map(|block| block.submitted.elapsed()).unwrap_or_default();
+    let stale = pending.as_ref().is_some_and(|block| block.stale);
+    SESSION_STATS.add_block_result(accepted, stale, pending.as_ref().map(|_| latency));
+    if let (Some(coordinator), Some(block)) = (coordinator, pending.as_ref()) {
+        coordinator.notify_block_result(block.worker, accepted).await;
+    }
+
+    if accepted {
+        BLOCKS_FOUND.fetch_add(1, Ordering::SeqCst);
+        info!("Block submitted has been accepted by network ! (latency: {}ms)", latency.as_millis());
+    } else {
+        // the coordinator only forwards the result to its workers
+        let reason = reason.map(|reason| format!(": {}", reason)).unwrap_or_default();
+        if stale {
+            warn!("Stale block submitted has been rejected by network{} (latency: {}ms)", reason, latency.as_millis());
+        } else {
+            BLOCKS_REJECTED.fetch_add(1, Ordering::SeqCst);
+            error!("Block submitted has been rejected by network{} (latency: {}ms)", reason, latency.as_millis());
+        }
+    }
+}
+
+// prepare the datasets of the current and next epochs in background
+// the threads keep mining the jobs received meanwhile
+fn handle_pow_seed(seed: &GetPowSeedResult, pow_dataset: &Arc<DatasetManager>) {
+    debug!("PoW seed is {} (seed topoheight {})", seed.seed, seed.seed_topoheight);
+    if seed.pow_algorithm.requires_dataset() {
+        pow_dataset.prepare(seed.seed_topoheight, seed.seed.clone());
+    }
+
+    if let Some(next_seed) = &seed.next_seed {
+        info!("Next PoW seed is {}, it will be used from topoheight {}", next_seed, seed.next_seed_activation_topoheight);
+        if seed.next_pow_algorithm.requires_dataset() {
+            pow_dataset.prepare(seed.next_seed_topoheight, next_seed.clone());
+        }
+    }
+}
+
+async fn handle_websocket_message(message: Result<Message, tokio_tungstenite::tungstenite::Error>, job_sender: &broadcast::Sender<ThreadNotification<'static>>, pending_blocks: &mut VecDeque<PendingBlock>, tracker: &mut JobTracker, coordinator: Option<&Arc<Coordinator>>, pow_dataset: &Arc<DatasetManager>) -> Result<bool, Error> {
     match message? {
         Message::Text(text) => {
             debug!("new message from daemon: {}", text);
             match serde_json::from_slice::<SocketMessage>(text.as_bytes())? {
                 SocketMessage::NewJob(job) => {
+                    let mut block = BlockMiner::from_hex(job.template).context("Error while decoding new job received from daemon")?;
+                    if !tracker.update(&block, job.topoheight) {
+                        SESSION_STATS.add_ignored_job();
+                        return Ok(false)
+                    }
+
                     info!("New job received from daemon: difficulty = {} and height = {}", job.difficulty, job.height);
-                    let block = BlockMiner::from_hex(job.template).context("Error while decoding new job received from daemon")?;
                     CURRENT_HEIGHT.store(job.height, Ordering::SeqCst);
+                    SESSION_STATS.add_job();
+                    if let Some(coordinator) = coordinator {
+                        block.extra_nonce[WORKER_NONCE_INDEX] = COORDINATOR_INDEX;
+                        coordinator.set_job(&block, job.difficulty, job.height, job.topoheight).await;
+                    }
 
-                    if let Err(e) = job_sender.send(ThreadNotification::NewJob(block, job.difficulty, job.height)) {
+                    if let Err(e) = job_sender.send(ThreadNotification::NewJob(block, job.difficulty, job.height, SESSION_STATS.get_current_job())) {
                         error!("Error while sending new job to threads: {}", e);
                     }
                 },
-                SocketMessage::BlockAccepted => {
-                    BLOCKS_FOUND.fetch_add(1, Ordering::SeqCst);
-                    info!("Block submitted has been accepted by network !");
+                SocketMessage::BlockAccepted => handle_block_result(true, None, pending_blocks, coordinator).await,
+                SocketMessage::BlockRejected(rejection) => handle_block_result(false, Some(rejection.message), pending_blocks, coordinator).await,
+                SocketMessage::DifficultyHint(hint) => {
+                    info!("Network difficulty is now {}, estimated time to find a block at {}: {}s", format_difficulty(hint.difficulty), format_hashrate(hint.hashrate), hint.estimated_block_time);
                 },
-                SocketMessage::BlockRejected => {
-                    BLOCKS_REJECTED.fetch_add(1, Ordering::SeqCst);
-                    error!("Block submitted has been rejected by network !");
+                SocketMessage::NewPowSeed(seed) => {
+                    handle_pow_seed(&seed, pow_dataset);
+                    if let Some(coordinator) = coordinator {
+                        coordinator.set_pow_seed(seed).await;
+                    }
                 }
             }
         },
@@ -291,11 +577,96 @@ async fn handle_websocket_message(message: Result<Message, tokio_tungstenite::tu
     Ok(false)
 }
 
-fn start_thread(id: u8, mut job_receiver: broadcast::Receiver<ThreadNotification<'static>>, block_sender: mpsc::Sender<BlockMiner<'static>>) -> Result<(), Error> {
+// Worker mode: same as the communication task but the jobs are received from a coordinator
+// which submits the blocks found to the daemon
+async fn worker_task(coordinator_address: String, job_sender: broadcast::Sender<ThreadNotification<'static>>, mut block_receiver: mpsc::Receiver<BlockMiner<'static>>, worker: String, pow_dataset: Arc<DatasetManager>) {
+    info!("Starting worker task");
+    loop {
+        info!("Trying to connect to coordinator {}", coordinator_address);
+        match TcpStream::connect(&coordinator_address).await {
+            Ok(stream) => {
+                WEBSOCKET_CONNECTED.store(true, Ordering::SeqCst);
+                info!("Connected successfully to coordinator {}", coordinator_address);
+                if let Err(e) = handle_coordinator_connection(stream, &job_sender, &mut block_receiver, &worker, &pow_dataset).await {
+                    error!("Error with coordinator {}: {:#}", coordinator_address, e);
+                }
+
+                WEBSOCKET_CONNECTED.store(false, Ordering::SeqCst);
+                if job_sender.send(ThreadNotification::WebSocketClosed).is_err() {
+                    error!("Error while sending WebSocketClosed message to threads");
+                }
+            },
+            Err(e) => error!("Error while connecting to coordinator {}: {}", coordinator_address, e)
+        };
+
+        warn!("Trying to connect to coordinator again in 10 seconds...");
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    }
+}
+
+async fn handle_coordinator_connection(stream: TcpStream, job_sender: &broadcast::Sender<ThreadNotification<'static>>, block_receiver: &mut mpsc::Receiver<BlockMiner<'static>>, worker: &str, pow_dataset: &Arc<DatasetManager>) -> Result<()> {
+    let (read, mut write) = stream.into_split();
+    // register our name so the blocks we find are attributed to us
+    let line = CoordinatorMessage::Register { worker: worker.to_owned() }.to_line()?;
+    write.write_all(line.as_bytes()).await.context("Error while registering to the coordinator")?;
+
+    let mut lines = BufReader::new(read).lines();
+    let mut pending_blocks: VecDeque<PendingBlock> = VecDeque::new();
+    let mut tracker = JobTracker::default();
+    loop {
+        select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    warn!("Coordinator has closed the connection");
+                    return Ok(())
+                };
+
+                match serde_json::from_str::<CoordinatorMessage>(&line)? {
+                    CoordinatorMessage::NewJob { template, difficulty, height, topoheight } => {
+                        let block = BlockMiner::from_hex(template).context("Error while decoding new job received from coordinator")?;
+                        if !tracker.update(&block, topoheight) {
+                            SESSION_STATS.add_ignored_job();
+                            continue;
+                        }
+
+                        info!("New job received from coordinator: difficulty = {} and height = {}", difficulty, height);
+                        CURRENT_HEIGHT.store(height, Ordering::SeqCst);
+                        SESSION_STATS.add_job();
+
+                        if let Err(e) = job_sender.send(ThreadNotification::NewJob(block, difficulty, height, SESSION_STATS.get_current_job())) {
+                            error!("Error while sending new job to threads: {}", e);
+                        }
+                    },
+                    CoordinatorMessage::BlockResult { accepted } => handle_block_result(accepted, None, &mut pending_blocks, None).await,
+                    CoordinatorMessage::PowSeed(seed) => handle_pow_seed(&seed, pow_dataset),
+                    CoordinatorMessage::BlockFound { .. } | CoordinatorMessage::Register { .. } => bail!("Unexpected message from coordinator")
+                };
+            },
+            Some(block) = block_receiver.recv() => {
+                debug!("Block header work hash found: {}", block.header_work_hash);
+                let stale = tracker.is_stale(&block);
+                if stale {
+                    warn!("Block {} was found for an old job", block.hash());
+                }
+                SESSION_STATS.add_block_found(stale);
+
+                let line = CoordinatorMessage::BlockFound { block: block.to_hex() }.to_line()?;
+                write.write_all(line.as_bytes()).await.context("Error while sending the block found to the coordinator")?;
+                pending_blocks.push_back(PendingBlock {
+                    submitted: Instant::now(),
+                    worker: block.extra_nonce[WORKER_NONCE_INDEX],
+                    stale
+                });
+            }
+        }
+    }
+}
+
+fn start_thread(id: u8, mut job_receiver: broadcast::Receiver<ThreadNotification<'static>>, block_sender: mpsc::Sender<BlockMiner<'static>>, duty: u8) -> Result<(), Error> {
     let builder = thread::Builder::new().name(format!("Mining Thread #{}", id));
     builder.spawn(move || {
         let mut job: BlockMiner;
-        let mut hash: Hash;
+        let mut duty_cycle = DutyCycle::new(duty);
 
         info!("Mining Thread #{}: started", id);
         'main: loop {
@@ -319,34 +690,52 @@ fn start_thread(id: u8, mut job_receiver: broadcast::Receiver<ThreadNotification
                     info!("Exiting Mining Thread #{}...", id);
                     break 'main;
                 },
-                ThreadNotification::NewJob(new_job, expected_difficulty, height) => {
+                ThreadNotification::NewJob(new_job, expected_difficulty, height, job_id) => {
                     debug!("Mining Thread #{} received a new job", id);
                     job = new_job;
                     // set thread id in extra nonce for more work spread between threads
                     // because it's a u8, it support up to 255 threads
                     job.extra_nonce[job.extra_nonce.len() - 1] = id;
 
-                    // Solve block
-                    hash = job.get_pow_hash();
-                    while !match check_difficulty(&hash, expected_difficulty) {
-                        Ok(value) => value,
-                        Err(e) => {
-                            error!("Mining Thread #{}: error on difficulty check: {}", id, e);
+                    // Solve block, several nonces are tried at once
+                    'solve: loop {
+                        // abandon the job as soon as a new one is received, its template may be orphaned
+                        if job_id != SESSION_STATS.get_current_job() {
+                            debug!("Mining Thread #{}: job replaced, abandoning it", id);
                             continue 'main;
                         }
-                    } {
+
+                        let hashes = job.get_pow_hashes();
+                        HASHRATE_COUNTER.fetch_add(HASH_LANES, Ordering::Relaxed);
+                        SESSION_STATS.add_hashes(HASH_LANES);
+                        for (i, hash) in hashes.iter().enumerate() {
+                            match check_difficulty(hash, expected_difficulty) {
+                                Ok(true) => {
+                                    job.nonce += i as u64;
+                                    break 'solve;
+                                },
+                                Ok(false) => {},
+                                Err(e) => {
+                                    error!("Mining Thread #{}: error on difficulty check: {}", id, e);
+                                    continue 'main;
+                                }
+                            }
+                        }
+
+                        job.nonce += HASH_LANES as u64;
                         // check if we have a new job pending
                         // Only update every 1 000 iterations to avoid too much CPU usage
-                        if job.nonce % UPDATE_EVERY_NONCE == 0 {
+                        if job.nonce % UPDATE_EVERY_NONCE < HASH_LANES as u64 {
                             if !job_receiver.is_empty() {
                                 continue 'main;
                             }
-                        }
 
-                        job.nonce += 1;
-                        hash = job.get_pow_hash();
-                        job.timestamp = get_current_timestamp();
-                        HASHRATE_COUNTER.fetch_add(1, Ordering::Relaxed);
+                            if let Some(duty_cycle) = duty_cycle.as_mut() {
+                                duty_cycle.pause_if_needed();
+                            }
+                        }
+                        // never below the job timestamp, in case the clock went backward
+                        job.timestamp = get_current_timestamp().max(job.timestamp);
                     }
 
                     // compute the reference hash for easier finding of the block
@@ -0,0 +1,196 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration
+};
+use anyhow::Result;
+use serde::Serialize;
+use tokio::time::Instant;
+use xelis_common::utils::get_current_time;
+
+#[derive(Clone, Copy, clap::ArgEnum)]
+pub enum ReportFormat {
+    Csv,
+    Json
+}
+
+impl Display for ReportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let str = match &self {
+            Self::Csv => "csv",
+            Self::Json => "json"
+        };
+        write!(f, "{}", str)
+    }
+}
+
+// Counters of the current mining session
+pub struct SessionStats {
+    started_at: u64,
+    start: Instant,
+    jobs_received: AtomicU64,
+    // jobs ignored because they were already received or outdated
+    jobs_ignored: AtomicU64,
+    hashes: AtomicU64,
+    blocks_found: AtomicU64,
+    blocks_accepted: AtomicU64,
+    blocks_rejected: AtomicU64,
+    // blocks found for a job already replaced by a new one
+    stale_blocks: AtomicU64,
+    // stale blocks rejected by the daemon, they are not counted in the rejected blocks
+    stale_rejected: AtomicU64,
+    // time between the submission of a block and the response of the daemon
+    total_latency: AtomicU64,
+    latency_count: AtomicU64
+}
+
+// Report written when the miner exits, durations are in milliseconds except the session one in seconds
+#[derive(Serialize)]
+pub struct SessionReport<'a> {
+    pub worker: &'a str,
+    pub threads: u8,
+    pub started_at: u64,
+    pub duration: u64,
+    pub jobs_received: u64,
+    pub jobs_ignored: u64,
+    pub hashes: u64,
+    pub hashrate: f64,
+    pub blocks_found: u64,
+    pub blocks_accepted: u64,
+    pub blocks_rejected: u64,
+    pub stale_blocks: u64,
+    pub stale_rejected: u64,
+    pub average_latency: u64
+}
+
+const CSV_HEADER: &str = "worker,threads,started_at,duration,jobs_received,jobs_ignored,hashes,hashrate,blocks_found,blocks_accepted,blocks_rejected,stale_blocks,stale_rejected,average_latency";
+
+impl SessionReport<'_> {
+    fn to_csv_row(&self) -> String {
+        format!(
+            "\"{}\",{},{},{},{},{},{},{:.2},{},{},{},{},{},{}",
+            self.worker.replace('"', "\"\""),
+            self.threads,
+            self.started_at,
+            self.duration,
+            self.jobs_received,
+            self.jobs_ignored,
+            self.hashes,
+            self.hashrate,
+            self.blocks_found,
+            self.blocks_accepted,
+            self.blocks_rejected,
+            self.stale_blocks,
+            self.stale_rejected,
+            self.average_latency
+        )
+    }
+
+    // the report is appended to the file to compare the sessions over time
+    // one row per session in CSV (header is written if the file is new), one JSON object per line otherwise
+    pub fn write_to(&self, path: &str, format: ReportFormat) -> Result<()> {
+        let is_new = !Path::new(path).exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        match format {
+            ReportFormat::Csv => {
+                if is_new {
+                    writeln!(file, "{}", CSV_HEADER)?;
+                }
+                writeln!(file, "{}", self.to_csv_row())?;
+            },
+            ReportFormat::Json => {
+                writeln!(file, "{}", serde_json::to_string(self)?)?;
+            }
+        };
+        Ok(())
+    }
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self {
+            started_at: get_current_time(),
+            start: Instant::now(),
+            jobs_received: AtomicU64::new(0),
+            jobs_ignored: AtomicU64::new(0),
+            hashes: AtomicU64::new(0),
+            blocks_found: AtomicU64::new(0),
+            blocks_accepted: AtomicU64::new(0),
+            blocks_rejected: AtomicU64::new(0),
+            stale_blocks: AtomicU64::new(0),
+            stale_rejected: AtomicU64::new(0),
+            total_latency: AtomicU64::new(0),
+            latency_count: AtomicU64::new(0)
+        }
+    }
+}
+
+impl SessionStats {
+    pub fn add_job(&self) {
+        self.jobs_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_ignored_job(&self) {
+        self.jobs_ignored.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // jobs received are counted, so it is also the id of the latest one
+    pub fn get_current_job(&self) -> u64 {
+        self.jobs_received.load(Ordering::Relaxed)
+    }
+
+    pub fn get_hashes(&self) -> u64 {
+        self.hashes.load(Ordering::Relaxed)
+    }
+
+    pub fn add_hashes(&self, count: usize) {
+        self.hashes.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_block_found(&self, stale: bool) {
+        self.blocks_found.fetch_add(1, Ordering::Relaxed);
+        if stale {
+            self.stale_blocks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn add_block_result(&self, accepted: bool, stale: bool, latency: Option<Duration>) {
+        if accepted {
+            self.blocks_accepted.fetch_add(1, Ordering::Relaxed);
+        } else if stale {
+            self.stale_rejected.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.blocks_rejected.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if let Some(latency) = latency {
+            self.total_latency.fetch_add(latency.as_millis() as u64, Ordering::Relaxed);
+            self.latency_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get_report<'a>(&self, worker: &'a str, threads: u8) -> SessionReport<'a> {
+        let elapsed = self.start.elapsed();
+        let hashes = self.hashes.load(Ordering::Relaxed);
+        let latency_count = self.latency_count.load(Ordering::Relaxed);
+        SessionReport {
+            worker,
+            threads,
+            started_at: self.started_at,
+            duration: elapsed.as_secs(),
+            jobs_received: self.jobs_received.load(Ordering::Relaxed),
+            jobs_ignored: self.jobs_ignored.load(Ordering::Relaxed),
+            hashes,
+            hashrate: if elapsed.is_zero() { 0f64 } else { hashes as f64 / elapsed.as_secs_f64() },
+            blocks_found: self.blocks_found.load(Ordering::Relaxed),
+            blocks_accepted: self.blocks_accepted.load(Ordering::Relaxed),
+            blocks_rejected: self.blocks_rejected.load(Ordering::Relaxed),
+            stale_blocks: self.stale_blocks.load(Ordering::Relaxed),
+            stale_rejected: self.stale_rejected.load(Ordering::Relaxed),
+            average_latency: self.total_latency.load(Ordering::Relaxed).checked_div(latency_count).unwrap_or(0)
+        }
+    }
+}
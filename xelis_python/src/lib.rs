@@ -0,0 +1,2 @@
+// the module is defined in xelis_common, this crate only builds it as a Python extension
+pub use xelis_common::python::*;
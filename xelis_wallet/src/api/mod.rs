@@ -2,8 +2,8 @@ mod rpc;
 mod rpc_server;
 mod xswd;
 
-use serde::ser::Serialize;
-use xelis_common::{api::wallet::NotifyEvent, rpc_server::WebSocketServerHandler};
+use log::error;
+use xelis_common::{api::wallet::WalletEvent, rpc_server::WebSocketServerHandler};
 
 pub use self::{
     rpc_server::{WalletRpcServer, WalletRpcServerShared, AuthConfig},
@@ -30,14 +30,22 @@ impl<W> APIServer<W>
 where
     W: Clone + Send + Sync + XSWDPermissionHandler + XSWDNodeMethodHandler + 'static
 {
-    pub async fn notify_event<V: Serialize>(&self, event: &NotifyEvent, value: &V) {
-        let json = serde_json::to_value(value).unwrap();
+    pub async fn notify_event(&self, event: &WalletEvent<'_>) {
+        let notify_event = event.get_event();
+        let json = match event.to_value() {
+            Ok(json) => json,
+            Err(e) => {
+                error!("Error while serializing event {:?}: {}", notify_event, e);
+                return;
+            }
+        };
+
         match self {
             APIServer::RPCServer(server) => {
-                server.get_websocket().get_handler().notify(event, json).await;
+                server.get_websocket().get_handler().notify(&notify_event, json).await;
             },
             APIServer::XSWD(xswd) => {
-                xswd.get_handler().notify(event, json).await;
+                xswd.get_handler().notify(&notify_event, json).await;
             }
         }
     }
@@ -1,7 +1,7 @@
 use std::{sync::Arc, borrow::Cow};
 
 use anyhow::Context as AnyContext;
-use log::info;
+use log::{info, debug};
 use xelis_common::{
     rpc_server::{
         RPCHandler, InternalRpcError, parse_params, websocket::WebSocketSessionShared
@@ -12,43 +12,76 @@ use xelis_common::{
         wallet::{
             BuildTransactionParams, FeeBuilder, TransactionResponse, ListTransactionsParams, GetAddressParams,
             GetBalanceParams, GetTransactionParams, SplitAddressParams, SplitAddressResult, GetCustomDataParams,
-            SetCustomDataParams, GetCustomTreeKeysParams, GetAssetPrecisionParams, RescanParams, QueryDBParams
+            SetCustomDataParams, GetCustomTreeKeysParams, GetAssetPrecisionParams, RescanParams, QueryDBParams,
+            GetDepositsParams, SponsorTransactionParams, ReplaceTransactionParams, CreateInvoiceParams, GetInvoiceParams, ListInvoicesParams,
+            InvoiceEntry, AddWebhookParams, RemoveWebhookParams, GetWebhookDeliveriesParams, Webhook, WebhookDelivery,
+            GetQRCodeParams, GetQRCodeResult, QRCodeFormat, CosignerInfo, CosignerCommitment, CosignTransactionParams,
+            CosignTransactionResult, EstimateFeesParams, AuditEntry, GetAuditLogParams, VerifyAuditLogResult, TransactionEntry, EntryData,
+            WalletEvent
         },
-        DataHash
+        daemon::GetSyncStatusResult,
+        DataHash, DataElement, DataValue, QueryResult, FeeEstimation, DepositEntry, DepositGroup, ValidateAddressParams, ValidateAddressResult
     },
-    crypto::hash::Hashable,
+    crypto::{hash::{Hash, Hashable}, address::Address},
+    network::Network,
+    transaction::Transaction,
+    utils::is_valid_reference,
     serializer::Serializer, context::Context
 };
 use serde_json::{Value, json};
-use crate::{wallet::{Wallet, WalletError}, entry::TransactionEntry};
+use crate::{wallet::{Wallet, WalletError}, config::{MAX_TX_REFERENCES, DEFAULT_INVOICE_EXPIRATION, DEFAULT_WEBHOOK_CONFIRMATIONS, DEFAULT_QR_CODE_SCALE, MAX_QR_CODE_SCALE, QR_CODE_BORDER, MAX_AUDIT_ENTRIES}, qrcode::{QRCode, build_payment_uri}, cosigner::Cosigner, privacy::PrivacyReport};
+use base64::{engine::general_purpose::STANDARD, Engine};
 
 use super::xswd::XSWDWebSocketHandler;
 
 pub fn register_methods(handler: &mut RPCHandler<Arc<Wallet>>) {
     info!("Registering RPC methods...");
-    handler.register_method("get_version", async_handler!(get_version));
-    handler.register_method("get_network", async_handler!(get_network));
-    handler.register_method("get_nonce", async_handler!(get_nonce));
-    handler.register_method("get_topoheight", async_handler!(get_topoheight));
-    handler.register_method("get_address", async_handler!(get_address));
-    handler.register_method("split_address", async_handler!(split_address));
-    handler.register_method("rescan", async_handler!(rescan));
-    handler.register_method("get_balance", async_handler!(get_balance));
-    handler.register_method("get_tracked_assets", async_handler!(get_tracked_assets));
-    handler.register_method("get_asset_precision", async_handler!(get_asset_precision));
-    handler.register_method("get_transaction", async_handler!(get_transaction));
-    handler.register_method("build_transaction", async_handler!(build_transaction));
-    handler.register_method("list_transactions", async_handler!(list_transactions));
-    handler.register_method("is_online", async_handler!(is_online));
+    handler.set_schema_title("XELIS Wallet");
+    handler.register_events_schema::<WalletEvent>();
+    handler.register_method_with_schema::<(), String>("get_version", async_handler!(get_version));
+    handler.register_method_with_schema::<(), Network>("get_network", async_handler!(get_network));
+    handler.register_method_with_schema::<(), u64>("get_nonce", async_handler!(get_nonce));
+    handler.register_method_with_schema::<(), u64>("get_topoheight", async_handler!(get_topoheight));
+    handler.register_method_with_schema::<GetAddressParams, Address>("get_address", async_handler!(get_address));
+    handler.register_method_with_schema::<SplitAddressParams, SplitAddressResult>("split_address", async_handler!(split_address));
+    handler.register_method_with_schema::<ValidateAddressParams, ValidateAddressResult>("validate_address", async_handler!(validate_address));
+    handler.register_method_with_schema::<RescanParams, bool>("rescan", async_handler!(rescan));
+    handler.register_method_with_schema::<GetBalanceParams, u64>("get_balance", async_handler!(get_balance));
+    handler.register_method_with_schema::<(), Vec<Hash>>("get_tracked_assets", async_handler!(get_tracked_assets));
+    handler.register_method_with_schema::<GetAssetPrecisionParams, u8>("get_asset_precision", async_handler!(get_asset_precision));
+    handler.register_method_with_schema::<GetTransactionParams, DataHash<TransactionEntry>>("get_transaction", async_handler!(get_transaction));
+    handler.register_method_with_schema::<BuildTransactionParams, TransactionResponse>("build_transaction", async_handler!(build_transaction));
+    handler.register_method_with_schema::<EstimateFeesParams, FeeEstimation>("estimate_fees", async_handler!(estimate_fees));
+    handler.register_method_with_schema::<SponsorTransactionParams, TransactionResponse>("sponsor_transaction", async_handler!(sponsor_transaction));
+    handler.register_method_with_schema::<ReplaceTransactionParams, TransactionResponse>("bump_fee", async_handler!(bump_fee));
+    handler.register_method_with_schema::<ReplaceTransactionParams, TransactionResponse>("cancel", async_handler!(cancel));
+    handler.register_method_with_schema::<ListTransactionsParams, Vec<TransactionEntry>>("list_transactions", async_handler!(list_transactions));
+    handler.register_method_with_schema::<GetDepositsParams, Vec<DepositGroup>>("get_deposits", async_handler!(get_deposits));
+    handler.register_method_with_schema::<CreateInvoiceParams, InvoiceEntry>("create_invoice", async_handler!(create_invoice));
+    handler.register_method_with_schema::<GetInvoiceParams, InvoiceEntry>("get_invoice", async_handler!(get_invoice));
+    handler.register_method_with_schema::<ListInvoicesParams, Vec<InvoiceEntry>>("list_invoices", async_handler!(list_invoices));
+    handler.register_method_with_schema::<AddWebhookParams, Webhook>("add_webhook", async_handler!(add_webhook));
+    handler.register_method_with_schema::<RemoveWebhookParams, bool>("remove_webhook", async_handler!(remove_webhook));
+    handler.register_method_with_schema::<(), Vec<Webhook>>("list_webhooks", async_handler!(list_webhooks));
+    handler.register_method_with_schema::<GetWebhookDeliveriesParams, Vec<WebhookDelivery>>("get_webhook_deliveries", async_handler!(get_webhook_deliveries));
+    handler.register_method_with_schema::<GetQRCodeParams, GetQRCodeResult>("get_qr_code", async_handler!(get_qr_code));
+    handler.register_method_with_schema::<(), PrivacyReport>("get_privacy_report", async_handler!(get_privacy_report));
+    handler.register_method_with_schema::<(), CosignerInfo>("get_cosigner_info", async_handler!(get_cosigner_info));
+    handler.register_method_with_schema::<(), CosignerCommitment>("get_cosigner_commitment", async_handler!(get_cosigner_commitment));
+    handler.register_method_with_schema::<CosignTransactionParams, CosignTransactionResult>("cosign_transaction", async_handler!(cosign_transaction));
+    handler.register_method_with_schema::<GetAuditLogParams, Vec<AuditEntry>>("get_audit_log", async_handler!(get_audit_log));
+    handler.register_method_with_schema::<(), VerifyAuditLogResult>("verify_audit_log", async_handler!(verify_audit_log));
+    handler.register_method_with_schema::<(), bool>("is_online", async_handler!(is_online));
+    handler.register_method_with_schema::<(), Option<GetSyncStatusResult>>("get_daemon_sync_status", async_handler!(get_daemon_sync_status));
 
     // These functions allow to have an encrypted DB directly in the wallet storage
     // You can retrieve keys, values, have differents trees, and store values
     // It is restricted in XSWD context, and open to everything in RPC
     // Keys and values can be anything
-    handler.register_method("get_keys_from_db", async_handler!(get_keys_from_db));
-    handler.register_method("get_value_from_db", async_handler!(get_value_from_db));
-    handler.register_method("set_value_in_db", async_handler!(set_value_in_db));
-    handler.register_method("query_db", async_handler!(query_db));
+    handler.register_method_with_schema::<GetCustomTreeKeysParams, Vec<DataValue>>("get_keys_from_db", async_handler!(get_keys_from_db));
+    handler.register_method_with_schema::<GetCustomDataParams, DataElement>("get_value_from_db", async_handler!(get_value_from_db));
+    handler.register_method_with_schema::<SetCustomDataParams, bool>("set_value_in_db", async_handler!(set_value_in_db));
+    handler.register_method_with_schema::<QueryDBParams, QueryResult>("query_db", async_handler!(query_db));
 }
 
 async fn get_version(_: Context, body: Value) -> Result<Value, InternalRpcError> {
@@ -116,6 +149,13 @@ async fn split_address(_: Context, body: Value) -> Result<Value, InternalRpcErro
     }))
 }
 
+// decode an address and check that it is for the network of the wallet
+async fn validate_address(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: ValidateAddressParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    Ok(json!(ValidateAddressResult::new(&params.address, *wallet.get_network())))
+}
+
 async fn rescan(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     let params: RescanParams = parse_params(body)?;
     let wallet: &Arc<Wallet> = context.get()?;
@@ -165,6 +205,20 @@ async fn get_transaction(context: Context, body: Value) -> Result<Value, Interna
     Ok(json!(data))
 }
 
+async fn estimate_fees(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: EstimateFeesParams = parse_params(body)?;
+    if let Some(addr) = &params.sponsor {
+        if !addr.is_normal() {
+            return Err(InternalRpcError::CustomStr("Sponsor address should be in normal format (not integrated address)"))
+        }
+    }
+
+    let wallet: &Arc<Wallet> = context.get()?;
+    let sponsor = params.sponsor.map(|addr| addr.to_public_key());
+    let estimation = wallet.estimate_fees(params.tx_type, params.fee.unwrap_or(FeeBuilder::Multiplier(1f64)), sponsor, params.expiration)?;
+    Ok(json!(estimation))
+}
+
 async fn build_transaction(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     let params: BuildTransactionParams = parse_params(body)?;
     let wallet: &Arc<Wallet> = context.get()?;
@@ -177,18 +231,114 @@ async fn build_transaction(context: Context, body: Value) -> Result<Value, Inter
         return Err(InternalRpcError::CustomStr("Invalid params, should either be broadcasted, or returned in hex format"))
     }
 
-    // create the TX
+    // a sponsored TX must be signed by its sponsor before being broadcasted
+    if params.sponsor.is_some() && (params.broadcast || !params.tx_as_hex) {
+        return Err(InternalRpcError::CustomStr("Invalid params, a sponsored TX cannot be broadcasted and must be returned in hex format"))
+    }
+
+    if let Some(addr) = &params.sponsor {
+        if !addr.is_normal() {
+            return Err(InternalRpcError::CustomStr("Sponsor address should be in normal format (not integrated address)"))
+        }
+    }
+
+    if params.reference.as_deref().is_some_and(|reference| !is_valid_reference(reference)) {
+        return Err(InternalRpcError::CustomStr("Invalid reference, expected an UUID"))
+    }
+
+    // keep it locked until the TX is built, so a retry can't build another one in parallel
+    let mut references = match params.reference.as_ref() {
+        Some(_) => Some(wallet.get_tx_references().lock().await),
+        None => None
+    };
+
+    // a retry with the same reference returns the TX built first
+    let previous = references.as_ref().zip(params.reference.as_ref())
+        .and_then(|(references, reference)| references.get(reference).cloned());
+    let (tx, broadcasted) = match previous {
+        Some((tx, broadcasted)) => {
+            debug!("Transaction {} was already built with reference", tx.hash());
+            (tx, broadcasted)
+        },
+        None => {
+            // create the TX
+            let nonce = wallet.get_next_nonce().await;
+            let storage = wallet.get_storage().read().await;
+            let sponsor = params.sponsor.map(|addr| addr.to_public_key());
+            let tx = wallet.create_transaction(&storage, params.tx_type, params.fee.unwrap_or(FeeBuilder::Multiplier(1f64)), sponsor, nonce, params.expiration)?;
+            (tx, false)
+        }
+    };
+
+    // if requested, broadcast the TX ourself
+    if params.broadcast && !broadcasted {
+        wallet.submit_transaction_with_reference(&tx, params.reference.as_deref()).await.context("Couldn't broadcast transaction")?;
+    }
+
+    if let (Some(references), Some(reference)) = (references.as_mut(), params.reference) {
+        references.insert(reference, (tx.clone(), broadcasted || params.broadcast));
+        if references.len() > MAX_TX_REFERENCES {
+            references.shift_remove_index(0);
+        }
+    }
+
+    // returns the created TX and its hash
+    Ok(json!(TransactionResponse {
+        tx_as_hex: if params.tx_as_hex {
+            Some(hex::encode(tx.to_bytes()))
+        } else {
+            None
+        },
+        inner: DataHash {
+            hash: Cow::Owned(tx.hash()),
+            data: Cow::Owned(tx)
+        }
+    }))
+}
+
+// Sign a TX built by another wallet to pay its fees
+async fn sponsor_transaction(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: SponsorTransactionParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    if !wallet.is_online().await && params.broadcast {
+        return Err(WalletError::NotOnlineMode).context("Cannot broadcast TX")?
+    }
+
+    let tx = Transaction::from_hex(params.tx_as_hex).context("Invalid transaction")?;
     let tx = {
         let storage = wallet.get_storage().read().await;
-        wallet.create_transaction(&storage, params.tx_type, params.fee.unwrap_or(FeeBuilder::Multiplier(1f64)))?
+        wallet.sponsor_transaction(&storage, tx)?
     };
 
-    // if requested, broadcast the TX ourself
     if params.broadcast {
         wallet.submit_transaction(&tx).await.context("Couldn't broadcast transaction")?;
     }
 
-    // returns the created TX and its hash
+    Ok(json!(TransactionResponse {
+        tx_as_hex: Some(hex::encode(tx.to_bytes())),
+        inner: DataHash {
+            hash: Cow::Owned(tx.hash()),
+            data: Cow::Owned(tx)
+        }
+    }))
+}
+
+// Replace a TX of the wallet still in mempool by the same TX with a higher fee
+async fn bump_fee(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: ReplaceTransactionParams = parse_params(body)?;
+    replace_transaction(context, params, false).await
+}
+
+// Replace a TX of the wallet still in mempool by a minimal burn with a higher fee
+async fn cancel(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: ReplaceTransactionParams = parse_params(body)?;
+    replace_transaction(context, params, true).await
+}
+
+async fn replace_transaction(context: Context, params: ReplaceTransactionParams, cancel: bool) -> Result<Value, InternalRpcError> {
+    let wallet: &Arc<Wallet> = context.get()?;
+    let tx = wallet.replace_transaction(&params.hash, cancel, params.fee).await?;
+
     Ok(json!(TransactionResponse {
         tx_as_hex: if params.tx_as_hex {
             Some(hex::encode(tx.to_bytes()))
@@ -217,6 +367,278 @@ async fn list_transactions(context: Context, body: Value) -> Result<Value, Inter
     Ok(json!(txs))
 }
 
+// Incoming transfers grouped by their integrated data
+async fn get_deposits(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetDepositsParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let mainnet = wallet.get_network().is_mainnet();
+    let storage = wallet.get_storage().read().await;
+    let topoheight = storage.get_daemon_topoheight()?;
+    let txs = storage.get_filtered_transactions(None, params.min_topoheight, params.max_topoheight, true, false, false, false, None)?;
+
+    let mut deposits = Vec::new();
+    for tx in txs {
+        let EntryData::Incoming(sender, transfers) = tx.get_entry() else {
+            continue;
+        };
+
+        let confirmations = (topoheight + 1).saturating_sub(tx.get_topoheight());
+        if confirmations < params.confirmations {
+            continue;
+        }
+
+        for transfer in transfers {
+            if params.asset.as_ref().is_some_and(|asset| asset != transfer.get_asset()) {
+                continue;
+            }
+
+            if params.data.is_some() && *transfer.get_extra_data() != params.data {
+                continue;
+            }
+
+            deposits.push((transfer.get_extra_data().clone(), DepositEntry {
+                hash: tx.get_hash().clone(),
+                topoheight: tx.get_topoheight(),
+                from: sender.to_address(mainnet),
+                asset: transfer.get_asset().clone(),
+                amount: transfer.get_amount(),
+                confirmations
+            }));
+        }
+    }
+
+    // transactions are not stored in topological order
+    deposits.sort_by_key(|(_, deposit)| deposit.topoheight);
+    Ok(json!(DepositGroup::group(deposits)))
+}
+
+async fn create_invoice(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: CreateInvoiceParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let asset = params.asset.unwrap_or(XELIS_ASSET);
+    let invoice = wallet.create_invoice(asset, params.amount, params.expires_in.unwrap_or(DEFAULT_INVOICE_EXPIRATION), params.memo).await?;
+    info!("Invoice {} created for {} of asset {}", invoice.id, invoice.amount, invoice.asset);
+
+    Ok(json!(InvoiceEntry {
+        address: wallet.get_invoice_address(&invoice),
+        invoice: Cow::Owned(invoice)
+    }))
+}
+
+async fn get_invoice(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetInvoiceParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let storage = wallet.get_storage().read().await;
+    let invoice = storage.get_invoice(&params.id).context("Invoice not found")?;
+
+    Ok(json!(InvoiceEntry {
+        address: wallet.get_invoice_address(&invoice),
+        invoice: Cow::Owned(invoice)
+    }))
+}
+
+async fn list_invoices(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: ListInvoicesParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let storage = wallet.get_storage().read().await;
+    let mut invoices = storage.get_invoices()?;
+    if let Some(state) = params.state {
+        invoices.retain(|invoice| invoice.state == state);
+    }
+    invoices.sort_by_key(|invoice| invoice.created_at);
+
+    let entries: Vec<InvoiceEntry> = invoices.into_iter()
+        .map(|invoice| InvoiceEntry {
+            address: wallet.get_invoice_address(&invoice),
+            invoice: Cow::Owned(invoice)
+        })
+        .collect();
+    Ok(json!(entries))
+}
+
+async fn add_webhook(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: AddWebhookParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let webhook = wallet.add_webhook(params.url, params.confirmations.unwrap_or(DEFAULT_WEBHOOK_CONFIRMATIONS)).await?;
+    info!("Webhook {} added for {}", webhook.id, webhook.url);
+    Ok(json!(webhook))
+}
+
+async fn remove_webhook(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: RemoveWebhookParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let removed = wallet.remove_webhook(&params.id).await?;
+    if removed {
+        info!("Webhook {} removed", params.id);
+    }
+    Ok(json!(removed))
+}
+
+async fn list_webhooks(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let wallet: &Arc<Wallet> = context.get()?;
+    let storage = wallet.get_storage().read().await;
+    let webhooks = storage.get_webhooks()?;
+    Ok(json!(webhooks))
+}
+
+async fn get_webhook_deliveries(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetWebhookDeliveriesParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let deliveries = wallet.get_webhook_manager().get_deliveries(params.id.as_ref()).await;
+    Ok(json!(deliveries))
+}
+
+// QR Code of the wallet address, built by the wallet so the integrated data is always encoded correctly
+async fn get_qr_code(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetQRCodeParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let (address, amount, asset) = if let Some(id) = params.invoice {
+        if params.integrated_data.is_some() || params.amount.is_some() || params.asset.is_some() {
+            return Err(InternalRpcError::CustomStr("Invoice cannot be used with integrated data, amount or asset"))
+        }
+
+        let storage = wallet.get_storage().read().await;
+        let invoice = storage.get_invoice(&id).context("Invoice not found")?;
+        (wallet.get_invoice_address(&invoice), Some(invoice.amount), invoice.asset)
+    } else {
+        let address = match params.integrated_data {
+            Some(data) => wallet.get_address_with(data),
+            None => wallet.get_address()
+        };
+        (address, params.amount, params.asset.unwrap_or(XELIS_ASSET))
+    };
+
+    let scale = params.scale.unwrap_or(DEFAULT_QR_CODE_SCALE);
+    if scale == 0 || scale > MAX_QR_CODE_SCALE {
+        return Err(InternalRpcError::Custom(format!("Invalid scale, expected a value between 1 and {}", MAX_QR_CODE_SCALE)))
+    }
+
+    let content = build_payment_uri(&address, amount, &asset);
+    let qr_code = QRCode::encode(content.as_bytes()).context("Error while encoding QR Code")?;
+    let format = params.format.unwrap_or(QRCodeFormat::Png);
+    let image = match format {
+        QRCodeFormat::Png => qr_code.to_png(scale as usize, QR_CODE_BORDER).context("Error while generating QR Code image")?,
+        QRCodeFormat::Svg => qr_code.to_svg(QR_CODE_BORDER).into_bytes()
+    };
+
+    Ok(json!(GetQRCodeResult {
+        content,
+        format,
+        image: STANDARD.encode(image)
+    }))
+}
+
+async fn get_privacy_report(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let wallet: &Arc<Wallet> = context.get()?;
+    let report = wallet.get_privacy_report().await?;
+    Ok(json!(report))
+}
+
+// co-signer mode can only be enabled at startup with its policy
+fn get_cosigner(cosigner: &Option<Cosigner>) -> Result<&Cosigner, InternalRpcError> {
+    cosigner.as_ref().ok_or_else(|| InternalRpcError::AnyError(WalletError::CosignerNotEnabled.into()))
+}
+
+async fn get_cosigner_info(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let wallet: &Arc<Wallet> = context.get()?;
+    let cosigner = wallet.get_cosigner().read().await;
+    let share = get_cosigner(&cosigner)?.get_share();
+    Ok(json!(CosignerInfo {
+        address: share.get_group_key().to_address(wallet.get_network().is_mainnet()),
+        index: share.get_index()
+    }))
+}
+
+// first round of the threshold signature, the commitment must be sent back with the TX to co-sign
+async fn get_cosigner_commitment(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let wallet: &Arc<Wallet> = context.get()?;
+    let cosigner = wallet.get_cosigner().read().await;
+    let cosigner = get_cosigner(&cosigner)?;
+    let (id, commitment) = cosigner.create_commitment().await;
+    Ok(json!(CosignerCommitment {
+        id,
+        commitment
+    }))
+}
+
+// second round, the signature share is only returned if the TX is accepted by the policy
+async fn cosign_transaction(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: CosignTransactionParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let tx = Transaction::from_hex(params.tx_as_hex).context("Invalid transaction")?;
+
+    let cosigner = wallet.get_cosigner().read().await;
+    let cosigner = get_cosigner(&cosigner)?;
+    let share = {
+        let mut storage = wallet.get_storage().write().await;
+        cosigner.cosign(&mut storage, &tx, &params.commitment_id, &params.commitments).await?
+    };
+
+    info!("Transaction {} of the shared account has been co-signed", tx.get_signing_hash());
+    Ok(json!(CosignTransactionResult {
+        share
+    }))
+}
+
+async fn get_audit_log(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetAuditLogParams = parse_params(body)?;
+    let wallet: &Arc<Wallet> = context.get()?;
+    let storage = wallet.get_storage().read().await;
+    let count = storage.get_audit_entries_count()?;
+    if count == 0 {
+        return Ok(json!(Vec::<AuditEntry>::new()))
+    }
+
+    let min_index = params.min_index.unwrap_or(0);
+    let max_index = match params.max_index {
+        Some(max_index) => max_index.min(count - 1),
+        None => (count - 1).min(min_index.saturating_add(MAX_AUDIT_ENTRIES - 1))
+    };
+    if min_index > max_index {
+        return Err(InternalRpcError::CustomStr("Invalid range, min index is above max index"))
+    }
+
+    if max_index - min_index >= MAX_AUDIT_ENTRIES {
+        return Err(InternalRpcError::Custom(format!("Invalid range, maximum {} entries can be requested", MAX_AUDIT_ENTRIES)))
+    }
+
+    let mut entries = Vec::new();
+    for index in min_index..=max_index {
+        let entry = storage.get_audit_entry(index)?;
+        if params.event.is_none_or(|event| event == entry.event) {
+            entries.push(entry);
+        }
+    }
+    Ok(json!(entries))
+}
+
+async fn verify_audit_log(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let wallet: &Arc<Wallet> = context.get()?;
+    let storage = wallet.get_storage().read().await;
+    let result = storage.verify_audit_log()?;
+    Ok(json!(result))
+}
+
 async fn is_online(context: Context, body: Value) -> Result<Value, InternalRpcError> {
     if body != Value::Null {
         return Err(InternalRpcError::UnexpectedParams)
@@ -227,6 +649,15 @@ async fn is_online(context: Context, body: Value) -> Result<Value, InternalRpcEr
     Ok(json!(is_connected))
 }
 
+async fn get_daemon_sync_status(context: Context, body: Value) -> Result<Value, InternalRpcError> {
+    if body != Value::Null {
+        return Err(InternalRpcError::UnexpectedParams)
+    }
+
+    let wallet: &Arc<Wallet> = context.get()?;
+    Ok(json!(wallet.get_daemon_sync_status().await))
+}
+
 // In EncryptedStorage, custom trees are already prefixed
 async fn get_tree_name(context: &Context, tree: String) -> Result<String, InternalRpcError> {
     // If the API is not used through XSWD, we don't need to prefix the tree name with the app id
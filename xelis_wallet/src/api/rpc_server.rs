@@ -4,8 +4,8 @@ use actix_web_httpauth::{middleware::HttpAuthentication, extractors::basic::Basi
 use anyhow::Result;
 use log::{info, warn};
 use tokio::sync::Mutex;
-use xelis_common::{config, rpc_server::{RPCHandler, RPCServerHandler, json_rpc, websocket, websocket::{EventWebSocketHandler, WebSocketServerShared, WebSocketServer}, WebSocketServerHandler}, api::wallet::NotifyEvent};
-use actix_web::{get, HttpResponse, Responder, HttpServer, web::{Data, self}, App, dev::{ServerHandle, ServiceRequest}, Error, error::{ErrorUnauthorized, ErrorBadGateway, ErrorBadRequest}};
+use xelis_common::{rpc_server::{RPCHandler, RPCServerHandler, json_rpc, websocket, index, schema, websocket::{EventWebSocketHandler, WebSocketServerShared, WebSocketServer}, WebSocketServerHandler, HttpServerConfig}, api::wallet::NotifyEvent};
+use actix_web::{HttpServer, web::{Data, self}, App, dev::{ServerHandle, ServiceRequest}, Error, error::{ErrorUnauthorized, ErrorBadGateway, ErrorBadRequest}, middleware::Condition};
 
 pub type WalletRpcServerShared<W> = Arc<WalletRpcServer<W>>;
 
@@ -20,18 +20,20 @@ where
 {
     handle: Mutex<Option<ServerHandle>>,
     websocket: WebSocketServerShared<EventWebSocketHandler<W, NotifyEvent>>,
-    auth_config: Option<AuthConfig>
+    auth_config: Option<AuthConfig>,
+    http_config: HttpServerConfig
 }
 
 impl<W> WalletRpcServer<W>
 where
     W: Clone + Send + Sync + 'static
 {
-    pub async fn new(bind_address: String, rpc_handler: RPCHandler<W>, auth_config: Option<AuthConfig>) -> Result<WalletRpcServerShared<W>> {
+    pub async fn new(bind_address: String, rpc_handler: RPCHandler<W>, auth_config: Option<AuthConfig>, http_config: HttpServerConfig) -> Result<WalletRpcServerShared<W>> {
         let server = Arc::new(Self {
             handle: Mutex::new(None),
             websocket: WebSocketServer::new(EventWebSocketHandler::new(rpc_handler)),
-            auth_config
+            auth_config,
+            http_config
         });
 
         {
@@ -39,14 +41,23 @@ where
             let http_server = HttpServer::new(move || {
                 let server = Arc::clone(&clone);
                 let auth = HttpAuthentication::basic(auth::<W>);
+                let config = &server.http_config;
+                // CORS is wrapped last to answer the preflight requests before the authentication
+                let cors = Condition::new(config.is_cors_enabled(), config.build_cors());
+                let scope = web::scope(&config.get_path_prefix())
+                    .route("/ws", web::get().to(websocket::<EventWebSocketHandler<W, NotifyEvent>, Self>))
+                    .route("/json_rpc", web::post().to(json_rpc::<W, WalletRpcServer<W>>))
+                    .route("/schema", web::get().to(schema::<W, WalletRpcServer<W>>))
+                    .route("/", web::get().to(index));
+
                 App::new()
                     .app_data(Data::from(server))
                     .wrap(auth)
-                    .route("/ws", web::get().to(websocket::<EventWebSocketHandler<W, NotifyEvent>, Self>))
-                    .route("/json_rpc", web::post().to(json_rpc::<W, WalletRpcServer<W>>))
-                    .service(index)
+                    .wrap(cors)
+                    .service(scope)
             })
             .disable_signals()
+            .workers(server.http_config.get_threads())
             .bind(&bind_address)?
             .run();
 
@@ -113,13 +124,11 @@ where
     match data {
         Some(server) => match server.authenticate(credentials).await {
             Ok(_) => Ok(request),
-            Err(e) => Err((e, request))
+            Err(e) => {
+                warn!("Invalid RPC authentication from {:?}: {}", server.http_config.get_client_address(request.request()), e);
+                Err((e, request))
+            }
         },
         None => Err((ErrorBadGateway("RPC Server was not found"), request))
     }
-}
-
-#[get("/")]
-async fn index() -> impl Responder {
-    HttpResponse::Ok().body(format!("Hello, world!\nRunning on: {}", config::VERSION))
 }
\ No newline at end of file
@@ -0,0 +1,619 @@
+// Backups of the wallet storage uploaded to a remote provider
+// a backup is the snapshot of the storage as stored on disk: every value is encrypted
+// using the master key, which can only be decrypted using the password of the wallet
+
+use std::{cmp::Reverse, fs, io::{Read, Write}, time::Duration};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use hmac::{Hmac, Mac};
+use log::debug;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use xelis_common::{
+    crypto::hash::{hash, Hash, HASH_SIZE},
+    utils::get_current_time
+};
+use crate::config::{BACKUP_TIMEOUT, DEFAULT_BACKUP_INTERVAL, DEFAULT_BACKUP_VERSIONS, MAX_BACKUP_SIZE, MIN_BACKUP_INTERVAL};
+
+// magic bytes at the start of a backup file
+const BACKUP_MAGIC: &[u8; 4] = b"XWBK";
+// version of the backup file format
+const BACKUP_FORMAT_VERSION: u8 = 1;
+// name of a backup file, followed by its creation timestamp and the extension
+const BACKUP_NAME_PREFIX: &str = "wallet-";
+const BACKUP_EXTENSION: &str = ".backup";
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("Invalid backup file")]
+    InvalidFormat,
+    #[error("Unsupported backup format version {}", _0)]
+    UnsupportedVersion(u8),
+    #[error("Backup file is corrupted, checksum mismatch")]
+    ChecksumMismatch,
+    #[error("Decompressed backup is bigger than {} bytes", MAX_BACKUP_SIZE)]
+    TooLarge,
+    #[error("No backup found")]
+    NoBackupFound,
+    #[error("Backup version {} not found", _0)]
+    VersionNotFound(u64),
+    #[error("Unexpected HTTP status {} from the backup provider: {}", _0, _1)]
+    UnexpectedStatus(StatusCode, String),
+    #[error("Backup interval must be at least {} seconds", MIN_BACKUP_INTERVAL)]
+    IntervalTooLow,
+    #[error("At least one backup version must be kept")]
+    NoVersionKept
+}
+
+// Remote storage of the backup files, identified by their name
+#[async_trait]
+pub trait BackupProvider: Send + Sync {
+    // upload a file, replacing it if it already exists
+    async fn upload(&self, name: &str, data: Vec<u8>) -> Result<()>;
+
+    async fn download(&self, name: &str) -> Result<Vec<u8>>;
+
+    // names of all the files stored
+    async fn list(&self) -> Result<Vec<String>>;
+
+    async fn delete(&self, name: &str) -> Result<()>;
+}
+
+// returns the response if its status is a success
+async fn check_response(response: Response) -> Result<Response> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(BackupError::UnexpectedStatus(status, body).into())
+    }
+    Ok(response)
+}
+
+// values of all the elements with this tag in an XML document
+// responses of the providers are simple enough to not require a full XML parser
+fn find_xml_values<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('>') else {
+            break
+        };
+
+        // the tag may be prefixed by a namespace and followed by attributes
+        let element = &rest[..end];
+        rest = &rest[end + 1..];
+        // closing and empty elements have no value
+        if element.starts_with('/') || element.ends_with('/') {
+            continue
+        }
+        let name = element.split_whitespace().next().unwrap_or(element);
+        let local_name = name.rsplit(':').next().unwrap_or(name);
+        if local_name != tag {
+            continue
+        }
+
+        if let Some(close) = rest.find("</") {
+            values.push(rest[..close].trim());
+            rest = &rest[close..];
+        }
+    }
+    values
+}
+
+// URI encoding as expected by the S3 signature
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte))
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// S3-compatible object storage (AWS, MinIO...), requests are signed using AWS Signature Version 4
+// objects are accessed using the path-style URL: {endpoint}/{bucket}/{prefix}{name}
+pub struct S3Provider {
+    client: Client,
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    prefix: String
+}
+
+impl S3Provider {
+    pub fn new(client: Client, endpoint: String, bucket: String, region: String, access_key: String, secret_key: String, prefix: String) -> Self {
+        Self {
+            client,
+            endpoint: endpoint.trim_end_matches('/').to_owned(),
+            bucket,
+            region,
+            access_key,
+            secret_key,
+            prefix
+        }
+    }
+
+    // query parameters must be sorted by name
+    fn request(&self, method: Method, key: Option<&str>, query: &[(&str, &str)], payload: &[u8]) -> Result<RequestBuilder> {
+        let path = match key {
+            Some(key) => format!("/{}/{}", uri_encode(&self.bucket, true), uri_encode(&format!("{}{}", self.prefix, key), false)),
+            None => format!("/{}", uri_encode(&self.bucket, true))
+        };
+        let query = query.iter()
+            .map(|(name, value)| format!("{}={}", uri_encode(name, true), uri_encode(value, true)))
+            .collect::<Vec<String>>()
+            .join("&");
+
+        let url = reqwest::Url::parse(&format!("{}{}{}{}", self.endpoint, path, if query.is_empty() { "" } else { "?" }, query))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().context("No host in S3 endpoint")?, port),
+            None => url.host_str().context("No host in S3 endpoint")?.to_owned()
+        };
+
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex::encode(Sha256::digest(payload));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, query, host, payload_hash, timestamp, signed_headers, payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", timestamp, scope, hex::encode(Sha256::digest(canonical_request.as_bytes())));
+
+        let key = hmac_sha256(format!("AWS4{}", self.secret_key).as_bytes(), date.as_bytes());
+        let key = hmac_sha256(&key, self.region.as_bytes());
+        let key = hmac_sha256(&key, b"s3");
+        let key = hmac_sha256(&key, b"aws4_request");
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, signed_headers, signature
+        );
+
+        Ok(self.client.request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", timestamp)
+            .header("authorization", authorization))
+    }
+}
+
+#[async_trait]
+impl BackupProvider for S3Provider {
+    async fn upload(&self, name: &str, data: Vec<u8>) -> Result<()> {
+        let request = self.request(Method::PUT, Some(name), &[], &data)?;
+        check_response(request.body(data).send().await?).await?;
+        Ok(())
+    }
+
+    async fn download(&self, name: &str) -> Result<Vec<u8>> {
+        let request = self.request(Method::GET, Some(name), &[], &[])?;
+        let response = check_response(request.send().await?).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = Vec::with_capacity(3);
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            query.push(("list-type", "2"));
+            query.push(("prefix", self.prefix.as_str()));
+
+            let request = self.request(Method::GET, None, &query, &[])?;
+            let xml = check_response(request.send().await?).await?.text().await?;
+            names.extend(find_xml_values(&xml, "Key").into_iter().filter_map(|key| key.strip_prefix(self.prefix.as_str())).map(str::to_owned));
+
+            token = find_xml_values(&xml, "NextContinuationToken").first().map(|token| token.to_string());
+            if token.is_none() {
+                break
+            }
+        }
+        Ok(names)
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        let request = self.request(Method::DELETE, Some(name), &[], &[])?;
+        check_response(request.send().await?).await?;
+        Ok(())
+    }
+}
+
+// WebDAV server (Nextcloud, ownCloud...), files are stored in an existing collection
+pub struct WebDAVProvider {
+    client: Client,
+    url: String,
+    username: Option<String>,
+    password: Option<String>
+}
+
+impl WebDAVProvider {
+    pub fn new(client: Client, url: String, username: Option<String>, password: Option<String>) -> Self {
+        let url = if url.ends_with('/') { url } else { format!("{}/", url) };
+        Self {
+            client,
+            url,
+            username,
+            password
+        }
+    }
+
+    fn request(&self, method: Method, name: &str) -> RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.url, uri_encode(name, true)));
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_ref()),
+            None => request
+        }
+    }
+}
+
+#[async_trait]
+impl BackupProvider for WebDAVProvider {
+    async fn upload(&self, name: &str, data: Vec<u8>) -> Result<()> {
+        check_response(self.request(Method::PUT, name).body(data).send().await?).await?;
+        Ok(())
+    }
+
+    async fn download(&self, name: &str) -> Result<Vec<u8>> {
+        let response = check_response(self.request(Method::GET, name).send().await?).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let request = self.request(Method::from_bytes(b"PROPFIND")?, "")
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml")
+            .body(r#"<?xml version="1.0" encoding="utf-8"?><propfind xmlns="DAV:"><prop><resourcetype/></prop></propfind>"#);
+
+        let xml = check_response(request.send().await?).await?.text().await?;
+        // the collection itself is also listed, its href ends with a slash
+        Ok(find_xml_values(&xml, "href").into_iter()
+            .filter(|href| !href.ends_with('/'))
+            .filter_map(|href| href.rsplit('/').next())
+            .map(str::to_owned)
+            .collect())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        check_response(self.request(Method::DELETE, name).send().await?).await?;
+        Ok(())
+    }
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_BACKUP_INTERVAL
+}
+
+fn default_max_versions() -> usize {
+    DEFAULT_BACKUP_VERSIONS
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BackupProviderConfig {
+    S3 {
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+        // folder of the backups in the bucket, e.g. "xelis/"
+        #[serde(default)]
+        prefix: String
+    },
+    WebDAV {
+        // URL of the collection in which the backups are stored
+        url: String,
+        username: Option<String>,
+        password: Option<String>
+    }
+}
+
+// Backups configuration, loaded from a JSON file as it contains the credentials of the provider
+#[derive(Deserialize)]
+pub struct BackupConfig {
+    provider: BackupProviderConfig,
+    // seconds between two backups, a backup is only uploaded if the storage changed
+    #[serde(default = "default_interval")]
+    interval: u64,
+    // backups kept on the provider, the oldest ones are deleted
+    #[serde(default = "default_max_versions")]
+    max_versions: usize
+}
+
+impl BackupConfig {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).context("Error while reading backup config file")?;
+        let config: Self = serde_json::from_str(&content).context("Error while parsing backup config file")?;
+        if config.interval < MIN_BACKUP_INTERVAL {
+            return Err(BackupError::IntervalTooLow.into())
+        }
+
+        if config.max_versions == 0 {
+            return Err(BackupError::NoVersionKept.into())
+        }
+
+        Ok(config)
+    }
+}
+
+// Backup stored on the provider, identified by its creation timestamp
+#[derive(Clone, Debug)]
+pub struct BackupVersion {
+    pub name: String,
+    pub timestamp: u64
+}
+
+impl BackupVersion {
+    fn new(timestamp: u64) -> Self {
+        Self {
+            name: format!("{}{}{}", BACKUP_NAME_PREFIX, timestamp, BACKUP_EXTENSION),
+            timestamp
+        }
+    }
+
+    // files which are not backups are ignored
+    fn from_name(name: &str) -> Option<Self> {
+        let timestamp = name.strip_prefix(BACKUP_NAME_PREFIX)?.strip_suffix(BACKUP_EXTENSION)?.parse().ok()?;
+        Some(Self::new(timestamp))
+    }
+}
+
+// backup file: magic, format version, hash of the snapshot then the compressed snapshot
+fn encode_backup(snapshot: &[u8], checksum: &Hash) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    data.extend_from_slice(BACKUP_MAGIC);
+    data.push(BACKUP_FORMAT_VERSION);
+    data.extend_from_slice(checksum.as_bytes());
+
+    let mut encoder = ZlibEncoder::new(data, Compression::default());
+    encoder.write_all(snapshot)?;
+    Ok(encoder.finish()?)
+}
+
+fn decode_backup(data: &[u8]) -> Result<Vec<u8>> {
+    let header_size = BACKUP_MAGIC.len() + 1 + HASH_SIZE;
+    if data.len() < header_size || !data.starts_with(BACKUP_MAGIC) {
+        return Err(BackupError::InvalidFormat.into())
+    }
+
+    let version = data[BACKUP_MAGIC.len()];
+    if version != BACKUP_FORMAT_VERSION {
+        return Err(BackupError::UnsupportedVersion(version).into())
+    }
+
+    // a small file may be decompressed in a huge snapshot, stop reading once above the limit
+    let mut snapshot = Vec::new();
+    ZlibDecoder::new(&data[header_size..]).take(MAX_BACKUP_SIZE + 1).read_to_end(&mut snapshot).context("Error while decompressing backup")?;
+    if snapshot.len() as u64 > MAX_BACKUP_SIZE {
+        return Err(BackupError::TooLarge.into())
+    }
+    if hash(&snapshot).as_bytes()[..] != data[BACKUP_MAGIC.len() + 1..header_size] {
+        return Err(BackupError::ChecksumMismatch.into())
+    }
+
+    Ok(snapshot)
+}
+
+pub struct BackupManager {
+    provider: Box<dyn BackupProvider>,
+    interval: u64,
+    max_versions: usize,
+    // hash of the last snapshot uploaded, to not upload the same storage twice
+    last_checksum: Mutex<Option<Hash>>
+}
+
+impl BackupManager {
+    pub fn new(config: BackupConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(BACKUP_TIMEOUT))
+            .build()?;
+
+        let provider: Box<dyn BackupProvider> = match config.provider {
+            BackupProviderConfig::S3 { endpoint, bucket, region, access_key, secret_key, prefix } => Box::new(S3Provider::new(client, endpoint, bucket, region, access_key, secret_key, prefix)),
+            BackupProviderConfig::WebDAV { url, username, password } => Box::new(WebDAVProvider::new(client, url, username, password))
+        };
+
+        Ok(Self {
+            provider,
+            interval: config.interval,
+            max_versions: config.max_versions,
+            last_checksum: Mutex::new(None)
+        })
+    }
+
+    pub fn get_interval(&self) -> u64 {
+        self.interval
+    }
+
+    // latest backups first
+    pub async fn list_versions(&self) -> Result<Vec<BackupVersion>> {
+        let mut versions: Vec<BackupVersion> = self.provider.list().await
+            .context("Error while listing backups")?
+            .iter()
+            .filter_map(|name| BackupVersion::from_name(name))
+            .collect();
+
+        versions.sort_by_key(|version| Reverse(version.timestamp));
+        Ok(versions)
+    }
+
+    // upload a snapshot of the storage, unless it is the same as the last one uploaded and it is not forced
+    // the oldest versions are then deleted
+    pub async fn upload(&self, snapshot: Vec<u8>, force: bool) -> Result<Option<BackupVersion>> {
+        let checksum = hash(&snapshot);
+        let mut last_checksum = self.last_checksum.lock().await;
+        if !force && last_checksum.as_ref() == Some(&checksum) {
+            debug!("Storage didn't change since the last backup, skipping it");
+            return Ok(None)
+        }
+
+        let version = BackupVersion::new(get_current_time());
+        let data = encode_backup(&snapshot, &checksum)?;
+        self.provider.upload(&version.name, data).await.context("Error while uploading backup")?;
+        *last_checksum = Some(checksum);
+
+        for old in self.list_versions().await?.iter().skip(self.max_versions) {
+            debug!("Deleting old backup {}", old.name);
+            self.provider.delete(&old.name).await.with_context(|| format!("Error while deleting old backup {}", old.name))?;
+        }
+
+        Ok(Some(version))
+    }
+
+    // download a backup, the latest one if no version is set, and returns its storage snapshot
+    pub async fn download(&self, version: Option<u64>) -> Result<(BackupVersion, Vec<u8>)> {
+        let versions = self.list_versions().await?;
+        let version = match version {
+            Some(timestamp) => versions.into_iter().find(|v| v.timestamp == timestamp).ok_or(BackupError::VersionNotFound(timestamp))?,
+            None => versions.into_iter().next().ok_or(BackupError::NoBackupFound)?
+        };
+
+        let data = self.provider.download(&version.name).await.context("Error while downloading backup")?;
+        let snapshot = decode_backup(&data)?;
+        Ok((version, snapshot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _encode(snapshot: &[u8]) -> Vec<u8> {
+        encode_backup(snapshot, &hash(snapshot)).unwrap()
+    }
+
+    fn _backup_error(data: &[u8]) -> BackupError {
+        decode_backup(data).unwrap_err().downcast::<BackupError>().unwrap()
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let snapshot = b"wallet storage snapshot".repeat(100);
+        let data = _encode(&snapshot);
+        assert!(data.starts_with(BACKUP_MAGIC));
+        assert_eq!(decode_backup(&data).unwrap(), snapshot);
+
+        // an empty storage is also a valid backup
+        assert_eq!(decode_backup(&_encode(&[])).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_backup_checksum_mismatch() {
+        let snapshot = b"wallet storage snapshot";
+        let data = encode_backup(snapshot, &hash(b"another snapshot")).unwrap();
+        assert!(matches!(_backup_error(&data), BackupError::ChecksumMismatch));
+
+        // a corrupted checksum in the header
+        let mut data = _encode(snapshot);
+        data[BACKUP_MAGIC.len() + 1] ^= 0xFF;
+        assert!(matches!(_backup_error(&data), BackupError::ChecksumMismatch));
+    }
+
+    #[test]
+    fn test_backup_invalid_header() {
+        let mut data = _encode(b"wallet storage snapshot");
+
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'Y';
+        assert!(matches!(_backup_error(&bad_magic), BackupError::InvalidFormat));
+
+        // too short to contain the header
+        assert!(matches!(_backup_error(&data[..BACKUP_MAGIC.len() + 1]), BackupError::InvalidFormat));
+
+        data[BACKUP_MAGIC.len()] = BACKUP_FORMAT_VERSION + 1;
+        assert!(matches!(_backup_error(&data), BackupError::UnsupportedVersion(version) if version == BACKUP_FORMAT_VERSION + 1));
+    }
+
+    #[test]
+    fn test_find_xml_values_s3() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+    <Name>bucket</Name>
+    <Prefix>xelis/</Prefix>
+    <KeyCount>2</KeyCount>
+    <IsTruncated>true</IsTruncated>
+    <NextContinuationToken>1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=</NextContinuationToken>
+    <Contents>
+        <Key>xelis/wallet-1700000000.backup</Key>
+        <Size>1024</Size>
+    </Contents>
+    <Contents>
+        <Key>xelis/wallet-1700003600.backup</Key>
+        <Size>2048</Size>
+    </Contents>
+</ListBucketResult>"#;
+
+        assert_eq!(find_xml_values(xml, "Key"), vec!["xelis/wallet-1700000000.backup", "xelis/wallet-1700003600.backup"]);
+        assert_eq!(find_xml_values(xml, "NextContinuationToken"), vec!["1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM="]);
+        // KeyCount is not the Key tag
+        assert_eq!(find_xml_values(xml, "KeyCount"), vec!["2"]);
+        assert!(find_xml_values(xml, "ContinuationToken").is_empty());
+    }
+
+    #[test]
+    fn test_find_xml_values_webdav() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+    <d:response>
+        <d:href>/remote.php/dav/files/user/backups/</d:href>
+        <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+    </d:response>
+    <d:response>
+        <d:href>/remote.php/dav/files/user/backups/wallet-1700000000.backup</d:href>
+        <d:propstat><d:prop><d:resourcetype/></d:prop></d:propstat>
+    </d:response>
+</d:multistatus>"#;
+
+        assert_eq!(find_xml_values(xml, "href"), vec!["/remote.php/dav/files/user/backups/", "/remote.php/dav/files/user/backups/wallet-1700000000.backup"]);
+        // empty elements have no value
+        assert!(find_xml_values(xml, "collection").is_empty());
+    }
+
+    #[test]
+    fn test_uri_encode() {
+        assert_eq!(uri_encode("wallet-1700000000.backup", true), "wallet-1700000000.backup");
+        assert_eq!(uri_encode("a-Z_0.~", true), "a-Z_0.~");
+        assert_eq!(uri_encode("xelis/wallet 1", false), "xelis/wallet%201");
+        assert_eq!(uri_encode("xelis/wallet 1", true), "xelis%2Fwallet%201");
+        assert_eq!(uri_encode("1ueG+c=", true), "1ueG%2Bc%3D");
+        assert_eq!(uri_encode("é", true), "%C3%A9");
+    }
+
+    #[test]
+    fn test_backup_version_from_name() {
+        let version = BackupVersion::from_name("wallet-1700000000.backup").unwrap();
+        assert_eq!(version.timestamp, 1700000000);
+        assert_eq!(version.name, "wallet-1700000000.backup");
+        assert_eq!(BackupVersion::new(1700000000).name, version.name);
+
+        assert!(BackupVersion::from_name("wallet-.backup").is_none());
+        assert!(BackupVersion::from_name("wallet-abc.backup").is_none());
+        assert!(BackupVersion::from_name("wallet-1700000000.txt").is_none());
+        assert!(BackupVersion::from_name("other-1700000000.backup").is_none());
+    }
+}
@@ -1,5 +1,7 @@
+use std::path::Path;
 use argon2::{Params, Argon2, Algorithm, Version};
 use lazy_static::lazy_static;
+use xelis_common::network::Network;
 
 pub const DIR_PATH: &str = "wallets/";
 pub const XSWD_BIND_ADDRESS: &str = "0.0.0.0:44325";
@@ -7,8 +9,59 @@ pub const PASSWORD_HASH_SIZE: usize = 32;
 pub const SALT_SIZE: usize = 32;
 pub const KEY_SIZE: usize = 32;
 
-// daemon address by default when no specified
-pub const DEFAULT_DAEMON_ADDRESS: &str = "http://127.0.0.1:8080";
+// daemon host by default when no specified, the port depends on the network
+pub const DEFAULT_DAEMON_HOST: &str = "http://127.0.0.1";
+
+// seconds before an invoice expires when not specified
+pub const DEFAULT_INVOICE_EXPIRATION: u64 = 60 * 60;
+
+// confirmations before an incoming transaction is notified to a webhook when not specified
+pub const DEFAULT_WEBHOOK_CONFIRMATIONS: u64 = 8;
+// a callback is retried until it is delivered or this number of attempts is reached
+pub const MAX_WEBHOOK_ATTEMPTS: u8 = 5;
+// delay in seconds before the first retry, doubled after each attempt
+pub const WEBHOOK_RETRY_DELAY: u64 = 5;
+// timeout in seconds of a callback request
+pub const WEBHOOK_TIMEOUT: u64 = 10;
+// deliveries kept in memory for the delivery logs
+pub const MAX_WEBHOOK_DELIVERIES: usize = 100;
+
+// pixels per module of the QR Code PNG images when not specified
+pub const DEFAULT_QR_CODE_SCALE: u8 = 8;
+pub const MAX_QR_CODE_SCALE: u8 = 32;
+// quiet zone around the QR Codes, in modules
+pub const QR_CODE_BORDER: usize = 4;
+
+// transactions built with a client reference kept in memory to return them on a retry
+pub const MAX_TX_REFERENCES: usize = 1024;
+
+// fee increase in percent of a replacement transaction when its fee is not specified
+pub const DEFAULT_FEE_BUMP_PERCENT: u64 = 25;
+
+// audit entries returned at most by a request
+pub const MAX_AUDIT_ENTRIES: u64 = 1024;
+
+// commitments of the co-signer waiting for a signing request
+pub const MAX_PENDING_COMMITMENTS: usize = 100;
+// period in seconds of the daily limits of the co-signer policy
+pub const COSIGNER_LIMIT_PERIOD: u64 = 24 * 60 * 60;
+
+// seconds between two backups of the storage when not specified
+pub const DEFAULT_BACKUP_INTERVAL: u64 = 60 * 60;
+pub const MIN_BACKUP_INTERVAL: u64 = 60;
+// backups kept on the provider when not specified
+pub const DEFAULT_BACKUP_VERSIONS: usize = 10;
+// timeout in seconds of a request to the backup provider
+pub const BACKUP_TIMEOUT: u64 = 60;
+// maximum size in bytes of a decompressed backup, a bigger one is rejected
+pub const MAX_BACKUP_SIZE: u64 = 1024 * 1024 * 1024;
+
+// blocks between receiving and spending funds under which both transactions are correlated by timing
+pub const PRIVACY_QUICK_SPEND_DELAY: u64 = 20;
+// payments to the same recipient required to detect a recurring interval
+pub const PRIVACY_MIN_RECURRING_PAYMENTS: usize = 3;
+// maximum deviation in percent of an interval from the average one to be considered regular
+pub const PRIVACY_RECURRING_TOLERANCE: u64 = 10;
 
 lazy_static! {
     pub static ref PASSWORD_ALGORITHM: Argon2<'static> = {
@@ -16,4 +69,26 @@ lazy_static! {
         let params = Params::new(15 * 1000, 16, 1,  Some(PASSWORD_HASH_SIZE)).unwrap();
         Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
     };
-}
\ No newline at end of file
+}
+
+// daemon address by default for the selected network
+pub fn get_default_daemon_address(network: &Network) -> String {
+    format!("{}:{}", DEFAULT_DAEMON_HOST, network.get_default_rpc_port())
+}
+
+// Path of a wallet for the selected network
+// mainnet wallets stay in the root directory, others networks have their own sub directory
+// a wallet created before the split is still used when only it exists
+pub fn get_wallet_path(network: &Network, name: &str) -> String {
+    let legacy = format!("{}{}", DIR_PATH, name);
+    if network.is_mainnet() {
+        return legacy
+    }
+
+    let path = format!("{}{}/{}", DIR_PATH, network.get_default_dir_name(), name);
+    if !Path::new(&path).exists() && Path::new(&legacy).is_dir() {
+        return legacy
+    }
+
+    path
+}
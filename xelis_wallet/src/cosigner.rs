@@ -0,0 +1,205 @@
+use std::{collections::{HashMap, HashSet}, fs};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use xelis_common::{
+    api::wallet::AuditEvent,
+    crypto::{
+        address::Address,
+        hash::{hash, Hash, Hashable},
+        key::PublicKey,
+        threshold::{KeyShare, SignatureShare, SigningCommitment, SigningNonces}
+    },
+    serializer::{Reader, ReaderError, Serializer, Writer},
+    transaction::{Transaction, TransactionType},
+    utils::{format_xelis, get_current_time}
+};
+use crate::{config::{COSIGNER_LIMIT_PERIOD, MAX_PENDING_COMMITMENTS}, storage::EncryptedStorage};
+
+#[derive(Error, Debug)]
+pub enum CosignerError {
+    #[error("Transaction is not owned by the shared account {}", _0)]
+    InvalidOwner(PublicKey),
+    #[error("Only transfers can be co-signed")]
+    UnsupportedTransactionType,
+    #[error("Transaction fee {} is above the maximum allowed {}", format_xelis(*_0), format_xelis(*_1))]
+    FeeTooHigh(u64, u64),
+    #[error("Destination {} is not allowed by the policy", _0)]
+    DestinationNotAllowed(PublicKey),
+    #[error("Asset {} is not allowed by the policy", _0)]
+    AssetNotAllowed(Hash),
+    #[error("Amount {} of asset {} is above the maximum of {} per transaction", _1, _0, _2)]
+    AmountTooHigh(Hash, u64, u64),
+    #[error("Daily limit of {} for asset {} would be exceeded, {} already co-signed", _3, _0, _2)]
+    DailyLimitExceeded(Hash, u64, u64, u64),
+    #[error("Commitment not found, it may have already been used")]
+    CommitmentNotFound,
+    #[error("Invalid allowed address {}, expected a normal address", _0)]
+    InvalidAllowedAddress(String)
+}
+
+// Rules checked before co-signing a transaction of the shared account
+// it is only loaded from a file on the co-signer host so it can't be changed through the API
+// amounts are in atomic units
+#[derive(Deserialize)]
+pub struct CosignerPolicy {
+    // maximum amount sent per transaction for each asset, assets not listed can't be sent
+    max_amounts: HashMap<Hash, u64>,
+    // maximum amount co-signed for each asset over the last 24 hours
+    #[serde(default)]
+    daily_limits: HashMap<Hash, u64>,
+    // destinations allowed, any destination is allowed if empty
+    #[serde(default)]
+    allowed_addresses: Vec<Address>,
+    // maximum fee in XELIS paid by the transaction
+    max_fee: Option<u64>
+}
+
+impl CosignerPolicy {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).context("Error while reading co-signer policy file")?;
+        let policy: Self = serde_json::from_str(&content).context("Error while parsing co-signer policy file")?;
+        if let Some(address) = policy.allowed_addresses.iter().find(|address| !address.is_normal()) {
+            return Err(CosignerError::InvalidAllowedAddress(address.to_string()).into())
+        }
+        Ok(policy)
+    }
+}
+
+// Amounts of a co-signed transaction, kept to enforce the daily limits
+pub struct CosignedTransaction {
+    // hash signed by the shared account
+    pub hash: Hash,
+    pub timestamp: u64,
+    pub amounts: Vec<(Hash, u64)>
+}
+
+impl Serializer for CosignedTransaction {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_hash(&self.hash);
+        writer.write_u64(&self.timestamp);
+        writer.write_u8(self.amounts.len() as u8);
+        for (asset, amount) in &self.amounts {
+            writer.write_hash(asset);
+            writer.write_u64(amount);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let hash = reader.read_hash()?;
+        let timestamp = reader.read_u64()?;
+        let count = reader.read_u8()? as usize;
+        let mut amounts = Vec::with_capacity(count);
+        for _ in 0..count {
+            amounts.push((reader.read_hash()?, reader.read_u64()?));
+        }
+        Ok(Self { hash, timestamp, amounts })
+    }
+}
+
+// Server side of a 2-of-3 custody: the wallet holds one key share of the shared account
+// and provides its signature share for the transactions accepted by its policy
+pub struct Cosigner {
+    share: KeyShare,
+    policy: CosignerPolicy,
+    allowed_keys: HashSet<PublicKey>,
+    // nonces of the commitments not used yet, by commitment id
+    nonces: Mutex<IndexMap<Hash, SigningNonces>>
+}
+
+impl Cosigner {
+    pub fn new(share: KeyShare, policy: CosignerPolicy) -> Self {
+        let allowed_keys = policy.allowed_addresses.iter().map(|address| address.get_public_key().clone()).collect();
+        Self {
+            share,
+            policy,
+            allowed_keys,
+            nonces: Mutex::new(IndexMap::new())
+        }
+    }
+
+    pub fn get_share(&self) -> &KeyShare {
+        &self.share
+    }
+
+    // nonces are only kept in memory, the oldest ones are dropped when the limit is reached
+    pub async fn create_commitment(&self) -> (Hash, SigningCommitment) {
+        let (nonces, commitment) = self.share.commit();
+        let id = hash(&commitment.to_bytes());
+        let mut pending = self.nonces.lock().await;
+        if pending.len() >= MAX_PENDING_COMMITMENTS {
+            pending.shift_remove_index(0);
+        }
+        pending.insert(id.clone(), nonces);
+        (id, commitment)
+    }
+
+    // returns the amounts sent per asset if the transaction is accepted by the policy
+    fn check_policy(&self, storage: &EncryptedStorage, transaction: &Transaction, signing_hash: &Hash) -> Result<Vec<(Hash, u64)>> {
+        if transaction.get_owner() != self.share.get_group_key() {
+            return Err(CosignerError::InvalidOwner(self.share.get_group_key().clone()).into())
+        }
+
+        let TransactionType::Transfer(transfers) = transaction.get_data() else {
+            return Err(CosignerError::UnsupportedTransactionType.into())
+        };
+
+        if let Some(max_fee) = self.policy.max_fee {
+            if transaction.get_fee() > max_fee {
+                return Err(CosignerError::FeeTooHigh(transaction.get_fee(), max_fee).into())
+            }
+        }
+
+        let mut amounts: IndexMap<Hash, u64> = IndexMap::new();
+        for transfer in transfers {
+            if !self.allowed_keys.is_empty() && !self.allowed_keys.contains(&transfer.to) {
+                return Err(CosignerError::DestinationNotAllowed(transfer.to.clone()).into())
+            }
+
+            let amount = amounts.entry(transfer.asset.clone()).or_insert(0);
+            *amount = amount.checked_add(transfer.amount).context("Overflow in transfer amounts")?;
+        }
+
+        let since = get_current_time().saturating_sub(COSIGNER_LIMIT_PERIOD);
+        let cosigned = storage.get_cosigned_transactions()?;
+        for (asset, amount) in &amounts {
+            let max_amount = *self.policy.max_amounts.get(asset).ok_or_else(|| CosignerError::AssetNotAllowed(asset.clone()))?;
+            if *amount > max_amount {
+                return Err(CosignerError::AmountTooHigh(asset.clone(), *amount, max_amount).into())
+            }
+
+            if let Some(limit) = self.policy.daily_limits.get(asset) {
+                // a transaction signed again, e.g. after a failed aggregation, is only counted once
+                let used: u64 = cosigned.iter()
+                    .filter(|tx| tx.timestamp > since && tx.hash != *signing_hash)
+                    .flat_map(|tx| tx.amounts.iter())
+                    .filter(|(a, _)| a == asset)
+                    .map(|(_, amount)| *amount)
+                    .sum();
+
+                if used.saturating_add(*amount) > *limit {
+                    return Err(CosignerError::DailyLimitExceeded(asset.clone(), *amount, used, *limit).into())
+                }
+            }
+        }
+
+        Ok(amounts.into_iter().collect())
+    }
+
+    // the commitment is consumed even if the transaction is rejected
+    pub async fn cosign(&self, storage: &mut EncryptedStorage, transaction: &Transaction, commitment_id: &Hash, commitments: &[SigningCommitment]) -> Result<SignatureShare> {
+        let nonces = self.nonces.lock().await.shift_remove(commitment_id).ok_or(CosignerError::CommitmentNotFound)?;
+        let signing_hash = transaction.get_signing_hash();
+        let amounts = self.check_policy(storage, transaction, &signing_hash)?;
+        let share = self.share.sign(nonces, &signing_hash, commitments)?;
+        storage.add_audit_entry(AuditEvent::Sign, format!("signature share of transaction {}", transaction.hash()))?;
+        storage.save_cosigned_transaction(&CosignedTransaction {
+            hash: signing_hash,
+            timestamp: get_current_time(),
+            amounts
+        })?;
+        Ok(share)
+    }
+}
@@ -1,108 +1,358 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap, fmt::{self, Display, Formatter}, fs, sync::Arc};
 
-use anyhow::{Context, Result};
-use xelis_common::{json_rpc::JsonRPCClient, api::daemon::{GetLastBalanceResult, GetBalanceAtTopoHeightParams, GetBalanceParams, GetInfoResult, SubmitTransactionParams, BlockResponse, GetBlockAtTopoHeightParams, GetTransactionParams, GetNonceParams, GetNonceResult, GetAssetsParams, IsTxExecutedInBlockParams}, account::VersionedBalance, crypto::{address::Address, hash::Hash}, transaction::Transaction, serializer::Serializer, block::{BlockHeader, Block}, asset::AssetWithData};
+use anyhow::{Context, Error, Result, bail};
+use log::warn;
+use tokio::sync::Mutex;
+use xelis_common::{json_rpc::{JsonRPCClient, JsonRPCError, DaemonClient, BlockHeaderResponse, BlockWithTxsResponse, BatchRequest, RetryPolicy, Authentication}, config::MAX_BATCH_REQUESTS, api::daemon::{GetLastBalanceResult, GetInfoResult, GetSyncStatusResult, GetNonceResult, GetAccountPendingResult, IsTxExecutedInBlockParams, SubmitTransactionResult, ResolveNameResult}, account::VersionedBalance, crypto::{address::Address, hash::Hash, key::PublicKey}, transaction::Transaction, asset::AssetWithData};
+
+// trust score of a daemon without any anomaly detected
+pub const MAX_TRUST_SCORE: u8 = 100;
+// below this score, the daemon is suspicious
+pub const SUSPICIOUS_TRUST_SCORE: u8 = 80;
+// below this score, the daemon is not trusted anymore and the wallet switches to a fallback daemon if any
+pub const UNTRUSTED_TRUST_SCORE: u8 = 50;
+
+// Evidence of a daemon misbehaving
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Anomaly {
+    // a response contradicts the request or a previous response
+    InconsistentResponse,
+    // a block at the stable height has been replaced
+    DeepReorg,
+    // a block referenced by the daemon can't be retrieved from it
+    MissingBlock
+}
+
+impl Anomaly {
+    fn get_penalty(&self) -> u8 {
+        match self {
+            Self::InconsistentResponse => 20,
+            Self::DeepReorg => 50,
+            Self::MissingBlock => 10
+        }
+    }
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InconsistentResponse => write!(f, "inconsistent response"),
+            Self::DeepReorg => write!(f, "reorg beyond stable height"),
+            Self::MissingBlock => write!(f, "missing block")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrustLevel {
+    Trusted,
+    Suspicious,
+    Untrusted
+}
+
+impl Display for TrustLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trusted => write!(f, "trusted"),
+            Self::Suspicious => write!(f, "suspicious"),
+            Self::Untrusted => write!(f, "untrusted")
+        }
+    }
+}
+
+// Trust in the daemon, lowered by each anomaly detected and slowly restored while it behaves correctly
+#[derive(Clone)]
+pub struct NodeTrust {
+    score: u8,
+    anomalies: HashMap<Anomaly, usize>
+}
+
+impl NodeTrust {
+    pub fn new() -> Self {
+        Self {
+            score: MAX_TRUST_SCORE,
+            anomalies: HashMap::new()
+        }
+    }
+
+    pub fn get_score(&self) -> u8 {
+        self.score
+    }
+
+    pub fn get_level(&self) -> TrustLevel {
+        if self.score < UNTRUSTED_TRUST_SCORE {
+            TrustLevel::Untrusted
+        } else if self.score < SUSPICIOUS_TRUST_SCORE {
+            TrustLevel::Suspicious
+        } else {
+            TrustLevel::Trusted
+        }
+    }
+
+    // count of each anomaly detected
+    pub fn get_anomalies(&self) -> &HashMap<Anomaly, usize> {
+        &self.anomalies
+    }
+
+    fn report(&mut self, anomaly: Anomaly) {
+        *self.anomalies.entry(anomaly).or_insert(0) += 1;
+        self.score = self.score.saturating_sub(anomaly.get_penalty());
+    }
+
+    fn restore(&mut self) {
+        self.score = (self.score + 1).min(MAX_TRUST_SCORE);
+    }
+}
+
+impl Default for NodeTrust {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the daemon answered with an error, unlike a connection error it can't be caused by the network
+pub fn is_daemon_error(e: &Error) -> bool {
+    matches!(e.downcast_ref::<JsonRPCError>(), Some(JsonRPCError::ServerError { .. }))
+}
+
+// options of the HTTP connection to the daemon and the fallback daemons
+// useful to reach a daemon behind a reverse proxy using HTTPS and/or authentication
+#[derive(Clone, Default)]
+pub struct DaemonConnectionConfig {
+    // paths of the PEM encoded certificates added to the trusted roots
+    pub root_certificates: Vec<String>,
+    // only trust the root certificates configured
+    pub only_custom_roots: bool,
+    // path of the PEM encoded client certificate followed by its private key
+    pub client_certificate: Option<String>,
+    // headers sent in each request
+    pub headers: Vec<(String, String)>,
+    pub authentication: Option<Authentication>
+}
+
+impl DaemonConnectionConfig {
+    // JSON-RPC client configured with these options
+    pub fn build_client(&self, target: String) -> Result<JsonRPCClient> {
+        let mut builder = JsonRPCClient::builder(target)
+            .with_built_in_roots(!self.only_custom_roots);
+
+        for path in &self.root_certificates {
+            let pem = fs::read(path).with_context(|| format!("Error while reading root certificate {}", path))?;
+            builder = builder.with_root_certificate(pem);
+        }
+
+        if let Some(path) = &self.client_certificate {
+            let pem = fs::read(path).with_context(|| format!("Error while reading client certificate {}", path))?;
+            builder = builder.with_identity(pem);
+        }
+
+        for (key, value) in &self.headers {
+            builder = builder.with_header(key, value);
+        }
+
+        builder = match &self.authentication {
+            Some(Authentication::Basic { username, password }) => builder.with_basic_auth(username, password.clone()),
+            Some(Authentication::Bearer(token)) => builder.with_bearer_auth(token),
+            None => builder
+        };
+
+        builder.build().context("Error while building the daemon client")
+    }
+}
 
 pub struct DaemonAPI {
-    client: JsonRPCClient,
+    client: DaemonClient,
+    trust: Mutex<NodeTrust>
 }
 
 impl DaemonAPI {
-    pub fn new(daemon_address: String) -> Self {
+    pub fn new(daemon_address: String, node_key: Option<PublicKey>) -> Self {
+        let mut client = JsonRPCClient::new(daemon_address);
+        // a transaction must not be submitted twice if the daemon already received it
+        client.set_retry_policy(RetryPolicy::default().with_non_idempotent_method("submit_transaction"));
+        // responses not signed by this key are rejected
+        client.set_node_key(node_key);
+        Self::with_client(client)
+    }
+
+    // client built using the connection options (TLS, authentication...)
+    pub fn with_config(daemon_address: String, node_key: Option<PublicKey>, config: &DaemonConnectionConfig) -> Result<Self> {
+        let mut client = config.build_client(daemon_address)?;
+        // a transaction must not be submitted twice if the daemon already received it
+        client.set_retry_policy(RetryPolicy::default().with_non_idempotent_method("submit_transaction"));
+        // responses not signed by this key are rejected
+        client.set_node_key(node_key);
+        Ok(Self::with_client(client))
+    }
+
+    // use a client configured by the caller (TLS, authentication...)
+    pub fn with_client(client: JsonRPCClient) -> Self {
         Self {
-            client: JsonRPCClient::new(daemon_address)
+            client: DaemonClient::with_client(client),
+            trust: Mutex::new(NodeTrust::new())
         }
     }
 
+    pub async fn get_trust(&self) -> NodeTrust {
+        self.trust.lock().await.clone()
+    }
+
+    // returns the new trust score of the daemon
+    pub async fn report_anomaly(&self, anomaly: Anomaly) -> u8 {
+        let mut trust = self.trust.lock().await;
+        trust.report(anomaly);
+        warn!("Daemon anomaly detected: {}, its trust score is now {}", anomaly, trust.get_score());
+        trust.get_score()
+    }
+
+    // called after each synchronization without anomaly
+    pub async fn restore_trust(&self) {
+        self.trust.lock().await.restore();
+    }
+
     pub fn get_client(&self) -> &JsonRPCClient {
-        &self.client
+        self.client.get_client()
     }
 
     pub async fn get_version(&self) -> Result<String> {
-        let version = self.client.call("get_version").await.context("Error while retrieving version from daemon")?;
+        let version = self.client.get_version().await.context("Error while retrieving version from daemon")?;
         Ok(version)
     }
 
     pub async fn get_info(&self) -> Result<GetInfoResult> {
-        let info = self.client.call("get_info").await.context("Error while retrieving info from chain")?;
+        let info = self.client.get_info().await.context("Error while retrieving info from chain")?;
         Ok(info)
     }
 
+    pub async fn get_sync_status(&self) -> Result<GetSyncStatusResult> {
+        let status = self.client.get_sync_status().await.context("Error while retrieving sync status from daemon")?;
+        Ok(status)
+    }
+
     pub async fn count_assets(&self) -> Result<usize> {
-        let count = self.client.call("count_assets").await?;
+        let count = self.client.count_assets().await?;
         Ok(count)
     }
 
     pub async fn get_assets(&self, skip: Option<usize>, maximum: Option<usize>, minimum_topoheight: Option<u64>, maximum_topoheight: Option<u64>) -> Result<Vec<AssetWithData>> {
-        let assets = self.client.call_with("get_assets", &GetAssetsParams {
-            maximum,
-            skip,
-            minimum_topoheight,
-            maximum_topoheight
-        }).await?;
+        let assets = self.client.get_assets(skip, maximum, minimum_topoheight, maximum_topoheight).await?;
         Ok(assets)
     }
 
     pub async fn get_last_balance(&self, address: &Address, asset: &Hash) -> Result<GetLastBalanceResult> {
-        let balance = self.client.call_with("get_last_balance", &GetBalanceParams {
-            address: Cow::Borrowed(address),
-            asset: Cow::Borrowed(asset),
-        }).await.context("Error while retrieving last balance")?;
+        let balance = self.client.get_last_balance(address, asset).await.context("Error while retrieving last balance")?;
         Ok(balance)
     }
 
     pub async fn get_balance_at_topoheight(&self, address: &Address, asset: &Hash, topoheight: u64) -> Result<VersionedBalance> {
-        let balance = self.client.call_with("get_balance_at_topoheight", &GetBalanceAtTopoHeightParams {
-            topoheight,
-            asset: Cow::Borrowed(asset),
-            address: Cow::Borrowed(address)
-        }).await.context("Error while retrieving balance at topoheight")?;
+        let balance = self.client.get_balance_at_topoheight(address, asset, topoheight).await.context("Error while retrieving balance at topoheight")?;
         Ok(balance)
     }
 
-    pub async fn get_block_at_topoheight(&self, topoheight: u64) -> Result<BlockResponse<'_, BlockHeader>> {
-        let block = self.client.call_with("get_block_at_topoheight", &GetBlockAtTopoHeightParams {
-            topoheight,
-            include_txs: false
-        }).await.context(format!("Error while fetching block at topoheight {}", topoheight))?;
+    // last version of the balance at or below this topoheight, following the previous versions from the last one
+    // returns None if the balance didn't exist yet at this topoheight
+    pub async fn get_balance_at_maximum_topoheight(&self, address: &Address, asset: &Hash, maximum_topoheight: u64) -> Result<Option<(u64, VersionedBalance)>> {
+        let last = self.get_last_balance(address, asset).await?;
+        let (mut topoheight, mut version) = (last.topoheight, last.balance);
+        while topoheight > maximum_topoheight {
+            match version.get_previous_topoheight() {
+                Some(previous) if previous >= topoheight => {
+                    self.report_anomaly(Anomaly::InconsistentResponse).await;
+                    bail!("Daemon returned a previous balance version at topoheight {} for the version at topoheight {}", previous, topoheight)
+                },
+                Some(previous) => {
+                    version = self.get_balance_at_topoheight(address, asset, previous).await?;
+                    topoheight = previous;
+                },
+                None => return Ok(None)
+            }
+        }
+        Ok(Some((topoheight, version)))
+    }
+
+    pub async fn get_block_at_topoheight(&self, topoheight: u64) -> Result<BlockHeaderResponse> {
+        let block = self.client.get_block_at_topoheight(topoheight).await.context(format!("Error while fetching block at topoheight {}", topoheight))?;
+        self.check_block_topoheight(block.topoheight, topoheight).await?;
         Ok(block)
     }
 
-    pub async fn get_block_with_txs_at_topoheight(&self, topoheight: u64) -> Result<BlockResponse<'_, Block>> {
-        let block = self.client.call_with("get_block_at_topoheight", &GetBlockAtTopoHeightParams {
-            topoheight,
-            include_txs: true
-        }).await.context(format!("Error while fetching block at topoheight {}", topoheight))?;
+    pub async fn get_block_with_txs_at_topoheight(&self, topoheight: u64) -> Result<BlockWithTxsResponse> {
+        let block = self.client.get_block_with_txs_at_topoheight(topoheight).await.context(format!("Error while fetching block at topoheight {}", topoheight))?;
+        self.check_block_topoheight(block.topoheight, topoheight).await?;
         Ok(block)
     }
 
-    pub async fn get_transaction(&self, hash: &Hash) -> Result<Transaction> {
-        let tx = self.client.call_with("get_transaction", &GetTransactionParams {
-            hash: Cow::Borrowed(hash)
-        }).await.context(format!("Error while fetching transaction {}", hash))?;
-        Ok(tx)
+    pub async fn get_blocks_at_height(&self, height: u64) -> Result<Vec<BlockHeaderResponse>> {
+        let blocks = self.client.get_blocks_at_height(height).await.context(format!("Error while fetching blocks at height {}", height))?;
+        Ok(blocks)
     }
 
-    pub async fn submit_transaction(&self, transaction: &Transaction) -> Result<()> {
-        let _: bool = self.client.call_with("submit_transaction", &SubmitTransactionParams {
-            data: transaction.to_hex()
-        }).await?;
+    async fn check_block_topoheight(&self, block_topoheight: Option<u64>, topoheight: u64) -> Result<()> {
+        if block_topoheight != Some(topoheight) {
+            self.report_anomaly(Anomaly::InconsistentResponse).await;
+            bail!("Daemon returned a block at topoheight {:?} instead of {}", block_topoheight, topoheight)
+        }
         Ok(())
     }
 
+    pub async fn get_transaction(&self, hash: &Hash) -> Result<Transaction> {
+        let response = self.client.get_transaction(hash).await.context(format!("Error while fetching transaction {}", hash))?;
+        let tx = response.data.data.into_owned();
+        Ok(Arc::try_unwrap(tx).unwrap_or_else(|tx| tx.as_ref().clone()))
+    }
+
+    // returns the transaction only if it is waiting in the mempool
+    pub async fn get_mempool_transaction(&self, hash: &Hash) -> Result<Option<Transaction>> {
+        let response = self.client.get_transaction(hash).await.context(format!("Error while fetching transaction {}", hash))?;
+        if !response.in_mempool {
+            return Ok(None)
+        }
+
+        let tx = response.data.data.into_owned();
+        Ok(Some(Arc::try_unwrap(tx).unwrap_or_else(|tx| tx.as_ref().clone())))
+    }
+
+    pub async fn submit_transaction(&self, transaction: &Transaction, reference: Option<&str>) -> Result<SubmitTransactionResult> {
+        let result = self.client.submit_transaction_with_reference(transaction, reference).await?;
+        Ok(result)
+    }
+
+    pub async fn resolve_name(&self, name: &str) -> Result<ResolveNameResult> {
+        let result = self.client.resolve_name(name, None).await.context(format!("Error while resolving name {}", name))?;
+        Ok(result)
+    }
+
     pub async fn get_last_nonce(&self, address: &Address) -> Result<GetNonceResult> {
-        let nonce = self.client.call_with("get_nonce", &GetNonceParams {
-            address: Cow::Borrowed(address),
-            topoheight: None
-        }).await.context(format!("Error while fetching nonce from address {}", address))?;
+        let nonce = self.client.get_nonce(address, None).await.context(format!("Error while fetching nonce from address {}", address))?;
         Ok(nonce)
     }
 
+    pub async fn get_account_pending(&self, address: &Address) -> Result<GetAccountPendingResult<'static>> {
+        let pending = self.client.get_account_pending(address).await.context(format!("Error while fetching pending transactions of address {}", address))?;
+        Ok(pending)
+    }
+
     pub async fn is_tx_executed_in_block(&self, tx_hash: &Hash, block_hash: &Hash) -> Result<bool> {
-        let is_executed = self.client.call_with("is_tx_executed_in_block", &IsTxExecutedInBlockParams {
-            tx_hash: Cow::Borrowed(tx_hash),
-            block_hash: Cow::Borrowed(block_hash)
-        }).await.context(format!("Error while checking if tx {} is executed in block {}", tx_hash, block_hash))?;
+        let is_executed = self.client.is_tx_executed_in_block(tx_hash, block_hash).await.context(format!("Error while checking if tx {} is executed in block {}", tx_hash, block_hash))?;
         Ok(is_executed)
     }
+
+    // same as is_tx_executed_in_block but for several transactions using batch requests
+    pub async fn are_txs_executed_in_block(&self, txs_hashes: &[Hash], block_hash: &Hash) -> Result<Vec<bool>> {
+        let mut results = Vec::with_capacity(txs_hashes.len());
+        for chunk in txs_hashes.chunks(MAX_BATCH_REQUESTS) {
+            let mut batch = BatchRequest::new();
+            let ids: Vec<usize> = chunk.iter().map(|tx_hash| batch.add_with("is_tx_executed_in_block", &IsTxExecutedInBlockParams {
+                tx_hash: Cow::Borrowed(tx_hash),
+                block_hash: Cow::Borrowed(block_hash)
+            })).collect();
+
+            let mut response = self.get_client().call_batch(batch).await.context(format!("Error while checking if txs are executed in block {}", block_hash))?;
+            for (id, tx_hash) in ids.into_iter().zip(chunk) {
+                let is_executed = response.get(id).context(format!("Error while checking if tx {} is executed in block {}", tx_hash, block_hash))?;
+                results.push(is_executed);
+            }
+        }
+        Ok(results)
+    }
 }
\ No newline at end of file
@@ -5,8 +5,12 @@ pub mod config;
 pub mod cipher;
 pub mod daemon_api;
 pub mod network_handler;
-pub mod entry;
 pub mod mnemonics;
+pub mod webhook;
+pub mod qrcode;
+pub mod cosigner;
+pub mod backup;
+pub mod privacy;
 
 #[cfg(feature = "api_server")]
 pub mod api;
\ No newline at end of file
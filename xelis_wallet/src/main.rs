@@ -1,20 +1,27 @@
-use std::{sync::Arc, time::Duration, path::Path};
+use std::{sync::Arc, time::Duration, path::Path, fs};
 
 use anyhow::{Result, Context};
-use xelis_wallet::config::DIR_PATH;
+use xelis_wallet::config::{get_wallet_path, get_default_daemon_address};
 use fern::colors::Color;
 use log::{error, info};
 use clap::Parser;
+use chrono::{TimeZone, Utc};
 use xelis_common::{config::{
     VERSION, XELIS_ASSET, COIN_DECIMALS
-}, prompt::{Prompt, command::{CommandManager, Command, CommandHandler, CommandError}, argument::{Arg, ArgType, ArgumentManager}, LogLevel, self, ShareablePrompt, PromptError}, async_handler, crypto::{address::{Address, AddressType}, hash::Hashable}, transaction::{TransactionType, Transaction}, utils::{format_xelis, set_network_to, get_network, format_coin}, serializer::Serializer, network::Network, api::wallet::FeeBuilder};
+}, prompt::{Prompt, command::{CommandManager, Command, CommandHandler, CommandError}, argument::{Arg, ArgType, ArgumentManager}, LogLevel, self, ShareablePrompt, PromptError}, async_handler, crypto::{address::AddressType, hash::Hashable, key::PublicKey, threshold::{KeyShare, THRESHOLD}}, transaction::{TransactionType, Transaction}, utils::{format_xelis, set_network_to, get_network, format_coin}, serializer::Serializer, network::Network, api::wallet::FeeBuilder, json_rpc::Authentication, name::{is_valid_name, get_name_registration_cost, NAME_SUFFIX, NAME_MIN_LENGTH, NAME_MAX_LENGTH, NAME_MIN_DURATION, NAME_MAX_DURATION}};
 use xelis_wallet::{
     wallet::Wallet,
-    config::DEFAULT_DAEMON_ADDRESS
+    config::{DEFAULT_QR_CODE_SCALE, QR_CODE_BORDER},
+    daemon_api::{TrustLevel, DaemonConnectionConfig, MAX_TRUST_SCORE},
+    qrcode::{QRCode, build_payment_uri},
+    backup::{BackupConfig, BackupManager},
+    storage::Storage
 };
 
 #[cfg(feature = "api_server")]
-use xelis_wallet::api::AuthConfig;
+use xelis_wallet::{api::AuthConfig, cosigner::CosignerPolicy};
+#[cfg(feature = "api_server")]
+use xelis_common::rpc_server::HttpServerConfig;
 
 // This struct is used to configure the RPC Server
 // In case we want to enable it instead of starting
@@ -30,15 +37,44 @@ pub struct RPCConfig {
     rpc_username: Option<String>,
     /// password for RPC authentication
     #[clap(long)]
-    rpc_password: Option<String>
+    rpc_password: Option<String>,
+    #[clap(flatten)]
+    rpc_http_config: HttpServerConfig
 }
 
 #[derive(Parser)]
 #[clap(version = VERSION, about = "XELIS Wallet")]
 pub struct Config {
-    /// Daemon address to use
-    #[clap(short = 'a', long, default_value_t = String::from(DEFAULT_DAEMON_ADDRESS))]
-    daemon_address: String,
+    /// Daemon address to use, default is the local daemon of the selected network
+    #[clap(short = 'a', long)]
+    daemon_address: Option<String>,
+    /// Public key of the daemon in hex, its responses must be signed by it
+    #[clap(long)]
+    daemon_node_key: Option<String>,
+    /// Daemon address to switch to when the current one is not trusted anymore, can be set several times
+    #[clap(long)]
+    fallback_daemon_address: Vec<String>,
+    /// PEM file of a certificate trusted to connect to the daemon using HTTPS, can be set several times
+    #[clap(long)]
+    daemon_root_certificate: Vec<String>,
+    /// Only trust the certificates set using --daemon-root-certificate
+    #[clap(long)]
+    daemon_only_custom_roots: bool,
+    /// PEM file of the client certificate followed by its private key, sent to the daemon
+    #[clap(long)]
+    daemon_client_certificate: Option<String>,
+    /// Header sent in each request to the daemon in 'name: value' format, can be set several times
+    #[clap(long)]
+    daemon_header: Vec<String>,
+    /// Username of the basic authentication sent to the daemon
+    #[clap(long)]
+    daemon_username: Option<String>,
+    /// Password of the basic authentication sent to the daemon
+    #[clap(long, requires = "daemon-username")]
+    daemon_password: Option<String>,
+    /// Bearer token sent to the daemon
+    #[clap(long, conflicts_with = "daemon-username")]
+    daemon_bearer_token: Option<String>,
     /// Disable online mode
     #[clap(short, long)]
     offline_mode: bool,
@@ -63,6 +99,13 @@ pub struct Config {
     /// Network selected for chain
     #[clap(long, arg_enum, default_value_t = Network::Mainnet)]
     network: Network,
+    /// Upload backups of the encrypted wallet storage using the provider set in this config file
+    #[clap(long)]
+    backup_config: Option<String>,
+    /// Restore the wallet from a backup before opening it: 'latest' or the timestamp of the backup
+    /// Wallet name and backup config are required, the wallet must not exist
+    #[clap(long)]
+    restore_backup: Option<String>,
     /// RPC Server configuration
     #[cfg(feature = "api_server")]
     #[structopt(flatten)]
@@ -70,7 +113,21 @@ pub struct Config {
     /// XSWD Server configuration
     #[cfg(feature = "api_server")]
     #[clap(long)]
-    enable_xswd: bool
+    enable_xswd: bool,
+    /// Run without the interactive prompt, only the RPC Server is available
+    /// Wallet name, password and RPC Server bind address are required
+    #[cfg(feature = "api_server")]
+    #[clap(long)]
+    daemon: bool,
+    /// PID file written in daemon mode
+    #[cfg(feature = "api_server")]
+    #[clap(long, default_value_t = String::from("xelis-wallet.pid"))]
+    pid_file: String,
+    /// Co-sign the transactions of the shared account through the API, using the rules of this policy file
+    /// A key share must be imported in the wallet first
+    #[cfg(feature = "api_server")]
+    #[clap(long)]
+    cosigner_policy: Option<String>
 }
 
 #[tokio::main]
@@ -78,8 +135,32 @@ async fn main() -> Result<()> {
     let config: Config = Config::parse();
     set_network_to(config.network);
 
+    #[cfg(feature = "api_server")]
+    let prompt = if config.daemon {
+        Prompt::new_headless(config.log_level, config.filename_log, config.disable_file_logging)?
+    } else {
+        Prompt::new(config.log_level, config.filename_log, config.disable_file_logging)?
+    };
+    #[cfg(not(feature = "api_server"))]
     let prompt = Prompt::new(config.log_level, config.filename_log, config.disable_file_logging)?;
 
+    // storage of the wallet restored from a backup
+    let mut restored = None;
+    if let Some(version) = &config.restore_backup {
+        let (Some(name), Some(path)) = (&config.name, &config.backup_config) else {
+            error!("Invalid parameters configuration: wallet name and backup config must be provided to restore a backup");
+            return Ok(())
+        };
+
+        match restore_backup(name, path, version).await {
+            Ok(storage) => restored = Some(storage),
+            Err(e) => {
+                error!("Error while restoring backup: {:#}", e);
+                return Ok(())
+            }
+        };
+    }
+
     #[cfg(feature = "api_server")]
     {
         // Sanity check
@@ -100,12 +181,30 @@ async fn main() -> Result<()> {
             error!("Invalid parameters configuration: usernamd AND password must be provided");
             return Ok(())
         }
+
+        if config.daemon {
+            // XSWD asks the permissions in the prompt
+            if config.enable_xswd || config.rpc.rpc_bind_address.is_none() {
+                error!("Invalid parameters configuration: RPC Server must be enabled in daemon mode");
+                return Ok(())
+            }
+
+            if config.disable_file_logging {
+                error!("Invalid parameters configuration: file logging cannot be disabled in daemon mode");
+                return Ok(())
+            }
+
+            let (Some(name), Some(password)) = (config.name, config.password) else {
+                error!("Invalid parameters configuration: wallet name and password must be provided in daemon mode");
+                return Ok(())
+            };
+
+            return run_daemon(name, password, config.seed, config.network, config.pid_file, restored).await
+        }
     }
 
     let command_manager = CommandManager::default(prompt.clone())?;
     if let Some(name) = config.name {
-        let dir = format!("{}{}", DIR_PATH, name);
-
         // read password from option or ask him
         let password = if let Some(password) = config.password {
             password
@@ -113,14 +212,7 @@ async fn main() -> Result<()> {
             prompt.read_input(format!("Enter Password for '{}': ", name), true).await?
         };
 
-        let wallet = if Path::new(&dir).is_dir() {
-            info!("Opening wallet {}", dir);
-            Wallet::open(dir, password, config.network)?
-        } else {
-            info!("Creating a new wallet at {}", dir);
-            Wallet::create(dir, password, config.seed, config.network)?
-        };
-
+        let wallet = open_or_create_wallet(&name, password, config.seed, config.network, restored)?;
         apply_config(&wallet).await;
         setup_wallet_command_manager(wallet, &command_manager, &prompt).await?;
     } else {
@@ -139,20 +231,138 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+fn open_or_create_wallet(name: &str, password: String, seed: Option<String>, network: Network, restored: Option<Storage>) -> Result<Arc<Wallet>> {
+    let dir = get_wallet_path(&network, name);
+    if let Some(storage) = restored {
+        info!("Opening restored wallet {}", dir);
+        Wallet::open_storage(storage, password, network)
+    } else if Path::new(&dir).is_dir() {
+        info!("Opening wallet {}", dir);
+        Wallet::open(dir, password, network)
+    } else {
+        info!("Creating a new wallet at {}", dir);
+        Wallet::create(dir, password, seed, network)
+    }
+}
+
+// Download a backup from the provider and write it as the storage of a new wallet
+async fn restore_backup(name: &str, config_path: &str, version: &str) -> Result<Storage> {
+    let dir = get_wallet_path(&get_network(), name);
+    if Path::new(&dir).exists() {
+        anyhow::bail!("Wallet {} already exists, it must be moved before restoring a backup", dir)
+    }
+
+    let version = match version {
+        "latest" => None,
+        value => Some(value.parse::<u64>().context("Invalid backup version, expected 'latest' or a timestamp")?)
+    };
+
+    let manager = BackupManager::new(BackupConfig::from_file(config_path)?)?;
+    let (version, snapshot) = manager.download(version).await?;
+    let storage = Storage::import(dir.clone(), &snapshot)?;
+    info!("Backup {} restored in {}, it is opened using the password of the wallet when it was backed up", version.name, dir);
+    Ok(storage)
+}
+
+// Run the wallet without prompt until it is stopped by a signal, for a service manager such as systemd
+#[cfg(feature = "api_server")]
+async fn run_daemon(name: String, password: String, seed: Option<String>, network: Network, pid_file: String, restored: Option<Storage>) -> Result<()> {
+    let wallet = open_or_create_wallet(&name, password, seed, network, restored)?;
+    apply_config(&wallet).await;
+    if wallet.get_api_server().lock().await.is_none() {
+        anyhow::bail!("RPC Server is not running, stopping the wallet")
+    }
+
+    fs::write(&pid_file, std::process::id().to_string()).context("Error while writing PID file")?;
+    info!("Wallet is running in daemon mode (PID file: {})", pid_file);
+
+    let res = wait_for_shutdown_signal().await;
+    info!("Stopping wallet...");
+    if let Err(e) = wallet.stop_api_server().await {
+        error!("Error while stopping RPC Server: {}", e);
+    }
+
+    if wallet.is_online().await {
+        if let Err(e) = wallet.set_offline_mode().await {
+            error!("Error while stopping the network handler: {}", e);
+        }
+    }
+
+    if let Err(e) = fs::remove_file(&pid_file) {
+        error!("Error while deleting PID file: {}", e);
+    }
+    res
+}
+
+#[cfg(feature = "api_server")]
+async fn wait_for_shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            res = tokio::signal::ctrl_c() => res?,
+            _ = terminate.recv() => {}
+        };
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
+
 // Apply the config passed in params
 async fn apply_config(wallet: &Arc<Wallet>) {
     let config: Config = Config::parse();
 
+    wallet.set_fallback_daemons(config.fallback_daemon_address).await;
+    let mut headers = Vec::with_capacity(config.daemon_header.len());
+    for header in &config.daemon_header {
+        match header.split_once(':') {
+            Some((key, value)) => headers.push((key.trim().to_owned(), value.trim().to_owned())),
+            None => error!("Invalid daemon header '{}', expected 'name: value'", header)
+        };
+    }
+
+    let authentication = match (config.daemon_username, config.daemon_bearer_token) {
+        (Some(username), _) => Some(Authentication::Basic { username, password: config.daemon_password }),
+        (None, Some(token)) => Some(Authentication::Bearer(token)),
+        (None, None) => None
+    };
+
+    wallet.set_daemon_connection_config(DaemonConnectionConfig {
+        root_certificates: config.daemon_root_certificate,
+        only_custom_roots: config.daemon_only_custom_roots,
+        client_certificate: config.daemon_client_certificate,
+        headers,
+        authentication
+    }).await;
+
     if !config.offline_mode {
-        info!("Trying to connect to daemon at '{}'", config.daemon_address);
-        if let Err(e) = wallet.set_online_mode(&config.daemon_address).await {
-            error!("Couldn't connect to daemon: {}", e);
-            info!("You can activate online mode using 'online_mode [daemon_address]'");
-        } else {
-            info!("Online mode enabled");
+        match config.daemon_node_key.map(PublicKey::from_hex).transpose() {
+            Ok(node_key) => {
+                let daemon_address = config.daemon_address.unwrap_or_else(|| get_default_daemon_address(wallet.get_network()));
+                info!("Trying to connect to daemon at '{}'", daemon_address);
+                if let Err(e) = wallet.set_online_mode(&daemon_address, node_key).await {
+                    error!("Couldn't connect to daemon: {}", e);
+                    info!("You can activate online mode using 'online_mode [daemon_address] [node_key]'");
+                } else {
+                    info!("Online mode enabled");
+                }
+            },
+            Err(e) => error!("Invalid daemon node key: {}", e)
         }
     }
 
+    if let Some(path) = config.backup_config {
+        match BackupConfig::from_file(&path).and_then(BackupManager::new) {
+            Ok(manager) => match wallet.enable_backups(manager).await {
+                Ok(()) => info!("Backups enabled with config {}", path),
+                Err(e) => error!("Error while enabling backups: {}", e)
+            },
+            Err(e) => error!("Error while loading backup config: {:#}", e)
+        };
+    }
+
     #[cfg(feature = "api_server")]
     {
         if config.enable_xswd && config.rpc.rpc_bind_address.is_some() {
@@ -160,6 +370,17 @@ async fn apply_config(wallet: &Arc<Wallet>) {
             return;
         }
 
+        // enabled before the API so no request is received without it
+        if let Some(path) = config.cosigner_policy {
+            match CosignerPolicy::from_file(&path) {
+                Ok(policy) => match wallet.enable_cosigner(policy).await {
+                    Ok(()) => info!("Co-signer mode enabled with policy {}", path),
+                    Err(e) => error!("Error while enabling co-signer mode: {}", e)
+                },
+                Err(e) => error!("Error while loading co-signer policy: {:#}", e)
+            };
+        }
+
         if let Some(address) = config.rpc.rpc_bind_address {
             let auth_config = if let (Some(username), Some(password)) = (config.rpc.rpc_username, config.rpc.rpc_password) {
                 Some(AuthConfig {
@@ -171,7 +392,7 @@ async fn apply_config(wallet: &Arc<Wallet>) {
             };
 
             info!("Enabling RPC Server on {} {}", address, if auth_config.is_some() { "with authentication" } else { "without authentication" });
-            if let Err(e) = wallet.enable_rpc_server(address, auth_config).await {
+            if let Err(e) = wallet.enable_rpc_server(address, auth_config, config.rpc.rpc_http_config).await {
                 error!("Error while enabling RPC Server: {}", e);
             }
         } else if config.enable_xswd {
@@ -193,14 +414,23 @@ async fn setup_wallet_command_manager(wallet: Arc<Wallet>, command_manager: &Com
     command_manager.add_command(Command::new("change_password", "Set a new password to open your wallet", CommandHandler::Async(async_handler!(change_password))))?;
     command_manager.add_command(Command::with_optional_arguments("transfer", "Send asset to a specified address", vec![Arg::new("asset", ArgType::Hash)], CommandHandler::Async(async_handler!(transfer))))?;
     command_manager.add_command(Command::with_required_arguments("burn", "Burn amount of asset", vec![Arg::new("asset", ArgType::Hash), Arg::new("amount", ArgType::Number)], CommandHandler::Async(async_handler!(burn))))?;
+    command_manager.add_command(Command::with_arguments("register_name", "Register a name pointing to your address, or renew it, for a duration in topoheights", vec![Arg::new("name", ArgType::String)], vec![Arg::new("duration", ArgType::Number)], CommandHandler::Async(async_handler!(register_name))))?;
     command_manager.add_command(Command::new("display_address", "Show your wallet address", CommandHandler::Async(async_handler!(display_address))))?;
+    command_manager.add_command(Command::with_optional_arguments("qr_code", "Show a QR Code of your address requesting an amount (atomic units) or save it as a .png or .svg file", vec![Arg::new("amount", ArgType::Number), Arg::new("asset", ArgType::Hash), Arg::new("filename", ArgType::String)], CommandHandler::Async(async_handler!(qr_code))))?;
     command_manager.add_command(Command::with_optional_arguments("balance", "List all non-zero balances or show the selected one", vec![Arg::new("asset", ArgType::Hash)], CommandHandler::Async(async_handler!(balance))))?;
     command_manager.add_command(Command::with_optional_arguments("history", "Show all your transactions", vec![Arg::new("page", ArgType::Number)], CommandHandler::Async(async_handler!(history))))?;
-    command_manager.add_command(Command::with_optional_arguments("online_mode", "Set your wallet in online mode", vec![Arg::new("daemon_address", ArgType::String)], CommandHandler::Async(async_handler!(online_mode))))?;
+    command_manager.add_command(Command::with_optional_arguments("online_mode", "Set your wallet in online mode", vec![Arg::new("daemon_address", ArgType::String), Arg::new("node_key", ArgType::String)], CommandHandler::Async(async_handler!(online_mode))))?;
+    command_manager.add_command(Command::new("node_trust", "Show the trust in the daemon and the anomalies detected", CommandHandler::Async(async_handler!(node_trust))))?;
     command_manager.add_command(Command::new("offline_mode", "Set your wallet in offline mode", CommandHandler::Async(async_handler!(offline_mode))))?;
     command_manager.add_command(Command::with_optional_arguments("rescan", "Rescan balance and transactions", vec![Arg::new("topoheight", ArgType::Number)], CommandHandler::Async(async_handler!(rescan))))?;
     command_manager.add_command(Command::with_optional_arguments("seed", "Show seed of selected language", vec![Arg::new("language", ArgType::Number)], CommandHandler::Async(async_handler!(seed))))?;
     command_manager.add_command(Command::new("nonce", "Show current nonce", CommandHandler::Async(async_handler!(nonce))))?;
+    command_manager.add_command(Command::new("generate_key_shares", "Generate the key shares of a new 2-of-3 shared account in files", CommandHandler::Async(async_handler!(generate_key_shares))))?;
+    command_manager.add_command(Command::with_required_arguments("import_key_share", "Import a key share file to co-sign the transactions of its shared account", vec![Arg::new("filename", ArgType::String)], CommandHandler::Async(async_handler!(import_key_share))))?;
+    command_manager.add_command(Command::new("backup", "Upload a backup of the wallet now", CommandHandler::Async(async_handler!(backup))))?;
+    command_manager.add_command(Command::new("list_backups", "List the backups stored by the backup provider", CommandHandler::Async(async_handler!(list_backups))))?;
+    command_manager.add_command(Command::new("privacy_report", "Analyze the history for address reuse, linkable extra data and timing patterns", CommandHandler::Async(async_handler!(privacy_report))))?;
+    command_manager.add_command(Command::with_optional_arguments("export_balance_proof", "Export a signed proof of your balances at a topoheight", vec![Arg::new("topoheight", ArgType::Number), Arg::new("filename", ArgType::String)], CommandHandler::Async(async_handler!(export_balance_proof))))?;
 
     #[cfg(feature = "api_server")]
     {
@@ -247,7 +477,13 @@ async fn prompt_message_builder(_: &Prompt, command_manager: &Option<CommandMana
                 prompt::colorize_string(Color::Green, &format_xelis(storage.get_balance_for(&XELIS_ASSET).unwrap_or(0))),
             );
             let status = if wallet.is_online().await {
-                prompt::colorize_str(Color::Green, "Online")
+                let trust_level = wallet.get_daemon_trust().await.map(|(_, trust)| trust.get_level());
+                match (wallet.get_daemon_sync_status().await, trust_level) {
+                    (_, Some(TrustLevel::Untrusted)) => prompt::colorize_str(Color::Red, "Untrusted node"),
+                    (_, Some(TrustLevel::Suspicious)) => prompt::colorize_str(Color::Yellow, "Suspicious node"),
+                    (Some(sync_status), _) if sync_status.syncing => prompt::colorize_string(Color::Yellow, &format!("Syncing {:.0}%", sync_status.progress)),
+                    _ => prompt::colorize_str(Color::Green, "Online")
+                }
             } else {
                 prompt::colorize_str(Color::Red, "Offline")
             };
@@ -293,7 +529,7 @@ async fn open_wallet(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager)
         return Ok(())
     }
 
-    let dir = format!("{}{}", DIR_PATH, name);
+    let dir = get_wallet_path(&get_network(), &name);
     if !Path::new(&dir).is_dir() {
         manager.message("No wallet found with this name");
         return Ok(())
@@ -323,7 +559,7 @@ async fn create_wallet(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager
         return Ok(())
     }
 
-    let dir = format!("{}{}", DIR_PATH, name);
+    let dir = get_wallet_path(&get_network(), &name);
     // check if it doesn't exists yet
     if Path::new(&dir).is_dir() {
         manager.message("Wallet already exist with this name!");
@@ -347,7 +583,7 @@ async fn create_wallet(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager
 
     // Display the seed in prompt
     {
-        let seed = wallet.get_seed(0)?; // TODO language index
+        let seed = wallet.get_seed(0).await?; // TODO language index
         prompt.read_input(format!("Seed: {}\r\nPress ENTER to continue", seed), false)
             .await.context("Error while displaying seed")?;
     }
@@ -372,7 +608,7 @@ async fn recover_wallet(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManage
         return Ok(())
     }
 
-    let dir = format!("{}{}", DIR_PATH, name);
+    let dir = get_wallet_path(&get_network(), &name);
     // check if it doesn't exists yet
     if Path::new(&dir).is_dir() {
         manager.message("Wallet already exist with this name!");
@@ -428,10 +664,10 @@ async fn transfer(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) ->
 
     // read address
     let str_address = prompt.read_input(
-        prompt::colorize_str(Color::Green, "Address: "),
+        prompt::colorize_str(Color::Green, "Address or name.xel: "),
         false
     ).await.context("Error while reading address")?;
-    let address = Address::from_string(&str_address).context("Invalid address")?;
+    let address = wallet.resolve_recipient(&str_address).await.context("Invalid address or name")?;
 
     let asset = prompt.read_hash(
         prompt::colorize_str(Color::Green, "Asset (default XELIS): ")
@@ -467,27 +703,60 @@ async fn transfer(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) ->
         AddressType::Data(data) => Some(data)
     };
 
+    let nonce = wallet.get_next_nonce().await;
     let tx = {
         let storage = wallet.get_storage().read().await;
         let transfer = wallet.create_transfer(&storage, asset, key, extra_data, amount)?;
-        wallet.create_transaction(&storage, TransactionType::Transfer(vec![transfer]), FeeBuilder::Multiplier(1f64))?
+        wallet.create_transaction(&storage, TransactionType::Transfer(vec![transfer]), FeeBuilder::Multiplier(1f64), None, nonce, None)?
     };
 
     broadcast_tx(wallet, manager, tx).await;
     Ok(())
 }
 
+// the registration cost is burned, renewing an active name extends its expiration
+async fn register_name(manager: &CommandManager<Arc<Wallet>>, mut arguments: ArgumentManager) -> Result<(), CommandError> {
+    let name = arguments.get_value("name")?.to_string_value()?;
+    let name = name.strip_suffix(NAME_SUFFIX).unwrap_or(&name).to_owned();
+    if !is_valid_name(&name) {
+        manager.error(format!("Invalid name, expected between {} and {} lowercase letters, digits or dashes", NAME_MIN_LENGTH, NAME_MAX_LENGTH));
+        return Ok(())
+    }
+
+    let duration = if arguments.has_argument("duration") {
+        arguments.get_value("duration")?.to_number()?
+    } else {
+        NAME_MIN_DURATION
+    };
+    if !(NAME_MIN_DURATION..=NAME_MAX_DURATION).contains(&duration) {
+        manager.error(format!("Invalid duration, expected between {} and {} topoheights", NAME_MIN_DURATION, NAME_MAX_DURATION));
+        return Ok(())
+    }
+
+    let wallet = manager.get_data().lock()?.clone().ok_or(CommandError::NoData)?;
+    let nonce = wallet.get_next_nonce().await;
+    let tx = {
+        let storage = wallet.get_storage().read().await;
+        manager.message(format!("Registering {}{} for {} topoheights, {} will be burned", name, NAME_SUFFIX, duration, format_xelis(get_name_registration_cost(duration))));
+        wallet.create_transaction(&storage, TransactionType::RegisterName { name, duration }, FeeBuilder::Multiplier(1f64), None, nonce, None)?
+    };
+
+    broadcast_tx(&wallet, manager, tx).await;
+    Ok(())
+}
+
 async fn burn(manager: &CommandManager<Arc<Wallet>>, mut arguments: ArgumentManager) -> Result<(), CommandError> {
     let amount = arguments.get_value("amount")?.to_number()?;
     let asset = arguments.get_value("asset")?.to_hash()?;
     let lock = manager.get_data().lock()?;
     let wallet = lock.as_ref().ok_or(CommandError::NoData)?;
+    let nonce = wallet.get_next_nonce().await;
     let tx = {
         let storage = wallet.get_storage().read().await;
         let decimals = storage.get_asset_decimals(&asset).unwrap_or(COIN_DECIMALS);
 
         manager.message(format!("Burning {} of {}", format_coin(amount, decimals), asset));
-        wallet.create_transaction(&storage, TransactionType::Burn { asset, amount }, FeeBuilder::Multiplier(1f64))?
+        wallet.create_transaction(&storage, TransactionType::Burn { asset, amount }, FeeBuilder::Multiplier(1f64), None, nonce, None)?
     };
 
     broadcast_tx(wallet, manager, tx).await;
@@ -502,6 +771,39 @@ async fn display_address(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManag
     Ok(())
 }
 
+// Show the QR Code in the terminal or save it as an image
+async fn qr_code(manager: &CommandManager<Arc<Wallet>>, mut arguments: ArgumentManager) -> Result<(), CommandError> {
+    let lock = manager.get_data().lock()?;
+    let wallet = lock.as_ref().ok_or(CommandError::NoData)?;
+    let amount = if arguments.has_argument("amount") {
+        Some(arguments.get_value("amount")?.to_number()?)
+    } else {
+        None
+    };
+
+    let asset = if arguments.has_argument("asset") {
+        arguments.get_value("asset")?.to_hash()?
+    } else {
+        XELIS_ASSET
+    };
+
+    let content = build_payment_uri(&wallet.get_address(), amount, &asset);
+    let qr_code = QRCode::encode(content.as_bytes()).context("Error while encoding QR Code")?;
+    if arguments.has_argument("filename") {
+        let filename = arguments.get_value("filename")?.to_string_value()?;
+        let image = match Path::new(&filename).extension().and_then(|extension| extension.to_str()) {
+            Some("png") => qr_code.to_png(DEFAULT_QR_CODE_SCALE as usize, QR_CODE_BORDER).context("Error while generating QR Code image")?,
+            Some("svg") => qr_code.to_svg(QR_CODE_BORDER).into_bytes(),
+            _ => return Err(CommandError::InvalidArgument("Filename must end with .png or .svg".to_owned()))
+        };
+        fs::write(&filename, image).context("Error while writing QR Code")?;
+        manager.message(format!("QR Code of {} exported to {}", content, filename));
+    } else {
+        manager.message(format!("{}\n{}", qr_code.to_terminal(QR_CODE_BORDER), content));
+    }
+    Ok(())
+}
+
 // Show current balance for specified asset or list all non-zero balances
 async fn balance(manager: &CommandManager<Arc<Wallet>>, mut arguments: ArgumentManager) -> Result<(), CommandError> {
     let lock = manager.get_data().lock()?;
@@ -578,15 +880,33 @@ async fn online_mode(manager: &CommandManager<Arc<Wallet>>, mut arguments: Argum
         let daemon_address = if arguments.has_argument("daemon_address") {
             arguments.get_value("daemon_address")?.to_string_value()?
         } else {
-            DEFAULT_DAEMON_ADDRESS.to_string()
+            get_default_daemon_address(wallet.get_network())
+        };
+
+        let node_key = if arguments.has_argument("node_key") {
+            Some(PublicKey::from_hex(arguments.get_value("node_key")?.to_string_value()?).context("Invalid node key")?)
+        } else {
+            None
         };
 
-        wallet.set_online_mode(&daemon_address).await.context("Couldn't enable online mode")?;
+        wallet.set_online_mode(&daemon_address, node_key).await.context("Couldn't enable online mode")?;
         manager.message("Wallet is now online");
     }
     Ok(())
 }
 
+// Show the trust score of the daemon we are connected to
+async fn node_trust(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) -> Result<(), CommandError> {
+    let lock = manager.get_data().lock()?;
+    let wallet = lock.as_ref().ok_or(CommandError::NoData)?;
+    let (daemon_address, trust) = wallet.get_daemon_trust().await.context("Wallet is not in online mode")?;
+    manager.message(format!("Daemon {} is {} (score {}/{})", daemon_address, trust.get_level(), trust.get_score(), MAX_TRUST_SCORE));
+    for (anomaly, count) in trust.get_anomalies() {
+        manager.message(format!("- {}: {}", anomaly, count));
+    }
+    Ok(())
+}
+
 // Set your wallet in offline mode
 async fn offline_mode(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) -> Result<(), CommandError> {
     let lock = manager.get_data().lock()?;
@@ -631,7 +951,7 @@ async fn seed(manager: &CommandManager<Arc<Wallet>>, mut arguments: ArgumentMana
         0
     };
 
-    let seed = wallet.get_seed(language as usize)?;
+    let seed = wallet.get_seed(language as usize).await?;
     prompt.read_input(
         prompt::colorize_string(Color::Green, &format!("Seed: {}\r\nPress ENTER to continue", seed)),
         false
@@ -647,6 +967,144 @@ async fn nonce(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) -> Res
     Ok(())
 }
 
+// Export the balances signed by the wallet at a topoheight in a JSON file
+async fn export_balance_proof(manager: &CommandManager<Arc<Wallet>>, mut arguments: ArgumentManager) -> Result<(), CommandError> {
+    let lock = manager.get_data().lock()?;
+    let wallet = lock.as_ref().ok_or(CommandError::NoData)?;
+    let topoheight = if arguments.has_argument("topoheight") {
+        Some(arguments.get_value("topoheight")?.to_number()?)
+    } else {
+        None
+    };
+
+    let proof = wallet.create_balance_proof(topoheight).await.context("Error while creating balance proof")?;
+    let filename = if arguments.has_argument("filename") {
+        arguments.get_value("filename")?.to_string_value()?
+    } else {
+        format!("balance_proof_{}.json", proof.data.topoheight)
+    };
+
+    let content = serde_json::to_string_pretty(&proof).context("Error while serializing balance proof")?;
+    fs::write(&filename, content).context("Error while writing balance proof")?;
+
+    manager.message(format!("Balance proof at topoheight {} (block {}):", proof.data.topoheight, proof.data.block_hash));
+    {
+        let storage = wallet.get_storage().read().await;
+        for balance in &proof.data.balances {
+            let decimals = storage.get_asset_decimals(&balance.asset).unwrap_or(COIN_DECIMALS);
+            manager.message(format!("- {}: {}", balance.asset, format_coin(balance.balance, decimals)));
+        }
+    }
+    manager.message(format!("Exported to {}", filename));
+    Ok(())
+}
+
+// Upload a backup even if the wallet didn't change since the last one
+async fn backup(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) -> Result<(), CommandError> {
+    let wallet = manager.get_data().lock()?.clone().ok_or(CommandError::NoData)?;
+    if let Some(version) = wallet.backup(true).await.context("Error while uploading backup")? {
+        manager.message(format!("Backup {} uploaded", version.name));
+    }
+    Ok(())
+}
+
+async fn list_backups(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) -> Result<(), CommandError> {
+    let wallet = manager.get_data().lock()?.clone().ok_or(CommandError::NoData)?;
+    let backup_manager = wallet.get_backup_manager().await.context("Error while listing backups")?;
+    let versions = backup_manager.list_versions().await.context("Error while listing backups")?;
+    if versions.is_empty() {
+        manager.message("No backup found");
+        return Ok(())
+    }
+
+    manager.message(format!("{} backups (latest first):", versions.len()));
+    for version in versions {
+        let date = Utc.timestamp_opt(version.timestamp as i64, 0).single().map(|date| date.to_rfc2822()).unwrap_or_default();
+        manager.message(format!("- {} ({})", version.timestamp, date));
+    }
+    manager.message("Restore one using --restore-backup <timestamp> with a new wallet name");
+    Ok(())
+}
+
+async fn privacy_report(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) -> Result<(), CommandError> {
+    let wallet = manager.get_data().lock()?.clone().ok_or(CommandError::NoData)?;
+    let report = wallet.get_privacy_report().await.context("Error while analyzing history")?;
+    manager.message(format!("Analyzed {} incoming, {} outgoing and {} coinbase transactions", report.incoming, report.outgoing, report.coinbase));
+    manager.message(format!("Distinct senders: {}, distinct recipients: {}, transfers with extra data: {}", report.distinct_senders, report.distinct_recipients, report.transfers_with_extra_data));
+
+    if !report.reused_counterparties.is_empty() {
+        manager.message("Reused counterparties:");
+        for counterparty in &report.reused_counterparties {
+            manager.message(format!("- {}: {} received, {} sent between topoheight {} and {}", counterparty.address, counterparty.incoming, counterparty.outgoing, counterparty.first_topoheight, counterparty.last_topoheight));
+        }
+    }
+
+    if !report.extra_data_links.is_empty() {
+        manager.message("Transactions linked by the same extra data:");
+        for link in &report.extra_data_links {
+            let data = serde_json::to_string(&link.data).context("Error while serializing extra data")?;
+            manager.message(format!("- {} in {} transactions", data, link.transactions.len()));
+        }
+    }
+
+    if !report.quick_spends.is_empty() {
+        manager.message("Funds spent shortly after being received:");
+        for spend in &report.quick_spends {
+            manager.message(format!("- {} sent {} blocks after receiving {}", spend.outgoing, spend.delay, spend.incoming));
+        }
+    }
+
+    if !report.recurring_payments.is_empty() {
+        manager.message("Recurring payments:");
+        for payment in &report.recurring_payments {
+            manager.message(format!("- {} payments to {} every ~{} blocks", payment.payments, payment.recipient, payment.interval));
+        }
+    }
+
+    if report.suggestions.is_empty() {
+        manager.message("No privacy issue found in the history");
+    } else {
+        manager.message("Suggestions:");
+        for suggestion in &report.suggestions {
+            manager.message(format!("- {}", suggestion));
+        }
+    }
+
+    Ok(())
+}
+
+// Generate the shares of a new shared account, each file must be given to a different participant
+async fn generate_key_shares(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) -> Result<(), CommandError> {
+    let shares = KeyShare::generate();
+    let filenames: Vec<String> = shares.iter().map(|share| format!("key_share_{}.hex", share.get_index())).collect();
+    if let Some(filename) = filenames.iter().find(|filename| Path::new(filename).exists()) {
+        manager.error(format!("File {} already exists, move it before generating new key shares", filename));
+        return Ok(())
+    }
+
+    for (share, filename) in shares.iter().zip(filenames.iter()) {
+        fs::write(filename, share.to_hex()).context("Error while writing key share")?;
+    }
+
+    manager.message(format!("Shared account address: {}", shares[0].get_group_key().to_address(get_network().is_mainnet())));
+    manager.message(format!("Key shares exported to {}", filenames.join(", ")));
+    manager.warn(format!("Any {} of them can sign, keep each one in a different place and delete these files once imported", THRESHOLD));
+    Ok(())
+}
+
+async fn import_key_share(manager: &CommandManager<Arc<Wallet>>, mut arguments: ArgumentManager) -> Result<(), CommandError> {
+    let lock = manager.get_data().lock()?;
+    let wallet = lock.as_ref().ok_or(CommandError::NoData)?;
+    let filename = arguments.get_value("filename")?.to_string_value()?;
+    let content = fs::read_to_string(&filename).context("Error while reading key share")?;
+    let share = KeyShare::from_hex(content.trim().to_owned()).context("Invalid key share")?;
+
+    wallet.import_key_share(&share).await.context("Error while importing key share")?;
+    manager.message(format!("Key share #{} of {} imported", share.get_index(), share.get_group_key().to_address(get_network().is_mainnet())));
+    manager.warn(format!("You can now delete {} and restart the wallet with the co-signer policy", filename));
+    Ok(())
+}
+
 #[cfg(feature = "api_server")]
 async fn stop_api_server(manager: &CommandManager<Arc<Wallet>>, _: ArgumentManager) -> Result<(), CommandError> {
     let lock = manager.get_data().lock()?;
@@ -669,7 +1127,7 @@ async fn start_rpc_server(manager: &CommandManager<Arc<Wallet>>, mut arguments:
         password
     });
 
-    wallet.enable_rpc_server(bind_address, auth_config).await.context("Error while enabling RPC Server")?;
+    wallet.enable_rpc_server(bind_address, auth_config, HttpServerConfig::default()).await.context("Error while enabling RPC Server")?;
     manager.message("RPC Server has been enabled");
     Ok(())
 }
@@ -1,17 +1,14 @@
-use std::{sync::{Arc, atomic::{AtomicBool, Ordering}}, time::Duration};
+use std::{borrow::Cow, sync::{Arc, atomic::{AtomicBool, Ordering}}, time::Duration};
 use thiserror::Error;
 use anyhow::Error;
 use log::{debug, error, info, warn};
 use tokio::{task::JoinHandle, sync::Mutex, time::interval};
-use xelis_common::{crypto::{hash::Hash, address::Address}, block::Block, transaction::TransactionType, account::VersionedBalance, asset::AssetWithData, serializer::Serializer};
+use xelis_common::{crypto::{hash::Hash, address::Address, key::PublicKey}, api::{DataElement, daemon::{GetSyncStatusResult, GetInfoResult}, wallet::{Invoice, InvoiceEntry, InvoiceState, WebhookEvent, TransactionEntry, EntryData, TransferEntry}}, block::Block, transaction::TransactionType, account::VersionedBalance, asset::AssetWithData, serializer::Serializer, utils::get_current_time};
 
-use crate::{daemon_api::DaemonAPI, wallet::Wallet, entry::{EntryData, Transfer, TransactionEntry}};
+use crate::{daemon_api::{DaemonAPI, DaemonConnectionConfig, Anomaly, UNTRUSTED_TRUST_SCORE, is_daemon_error}, wallet::Wallet};
 
 #[cfg(feature = "api_server")]
-use {
-    std::borrow::Cow,
-    xelis_common::api::wallet::{NotifyEvent, BalanceChanged}
-};
+use xelis_common::api::wallet::{WalletEvent, BalanceChanged};
 
 // NetworkHandler must be behind a Arc to be accessed from Wallet (to stop it) or from tokio task
 pub type SharedNetworkHandler = Arc<NetworkHandler>;
@@ -35,16 +32,25 @@ pub struct NetworkHandler {
     wallet: Arc<Wallet>,
     // api to communicate with daemon
     api: DaemonAPI,
+    daemon_address: String,
     // used in case the daemon is not responding but we're already connected
-    is_paused: AtomicBool
+    is_paused: AtomicBool,
+    // last sync status of the daemon, unknown if the daemon doesn't support it
+    daemon_sync_status: Mutex<Option<GetSyncStatusResult>>
 }
 
 // how many assets we get by request
 const MAX_ASSETS: usize = 64;
 
 impl NetworkHandler {
-    pub async fn new<S: ToString>(wallet: Arc<Wallet>, daemon_address: S) -> Result<SharedNetworkHandler, Error> {
-        let api = DaemonAPI::new(format!("{}/json_rpc", daemon_address.to_string()));
+    pub async fn new<S: ToString>(wallet: Arc<Wallet>, daemon_address: S, node_key: Option<PublicKey>, config: &DaemonConnectionConfig) -> Result<SharedNetworkHandler, Error> {
+        let daemon_address = daemon_address.to_string();
+        let api = DaemonAPI::with_config(format!("{}/json_rpc", daemon_address), node_key, config)?;
+        Self::with_api(wallet, daemon_address, api).await
+    }
+
+    // use an already configured API, such as one executing the requests in the same process
+    pub async fn with_api(wallet: Arc<Wallet>, daemon_address: String, api: DaemonAPI) -> Result<SharedNetworkHandler, Error> {
         // check that we can correctly get version from daemon
         let version = api.get_version().await?;
         debug!("Connected to daemon running version {}", version);
@@ -53,7 +59,9 @@ impl NetworkHandler {
             task: Mutex::new(None),
             wallet,
             api,
-            is_paused: AtomicBool::new(false)
+            daemon_address,
+            is_paused: AtomicBool::new(false),
+            daemon_sync_status: Mutex::new(None)
         }))
     }
 
@@ -85,6 +93,10 @@ impl NetworkHandler {
         &self.api
     }
 
+    pub fn get_daemon_address(&self) -> &String {
+        &self.daemon_address
+    }
+
     // check if the network handler is running (that we have a task and its not finished)
     pub async fn is_running(&self) -> bool {
         let task = self.task.lock().await;
@@ -99,6 +111,10 @@ impl NetworkHandler {
         self.is_paused.load(Ordering::SeqCst)
     }
 
+    pub async fn get_daemon_sync_status(&self) -> Option<GetSyncStatusResult> {
+        self.daemon_sync_status.lock().await.clone()
+    }
+
     async fn get_versioned_balance_and_topoheight(&self, address: &Address, asset: &Hash, current_topoheight: Option<u64>) -> Result<Option<(u64, VersionedBalance)>, Error> {
         let (topoheight, balance) = match &current_topoheight {
             Some(topoheight) => (*topoheight, self.api.get_balance_at_topoheight(address, asset, *topoheight).await?),
@@ -116,10 +132,10 @@ impl NetworkHandler {
                 #[cfg(feature = "api_server")]
                 {
                     if let Some(api_server) = self.wallet.get_api_server().lock().await.as_ref() {
-                        api_server.notify_event(&NotifyEvent::BalanceChanged, &BalanceChanged {
+                        api_server.notify_event(&WalletEvent::BalanceChanged(BalanceChanged {
                             asset: Cow::Borrowed(&asset),
                             balance: balance.get_balance()
-                        }).await;
+                        })).await;
                     }
                 }
 
@@ -141,7 +157,16 @@ impl NetworkHandler {
                 return Ok(())
             }
 
-            let response = self.api.get_block_with_txs_at_topoheight(topoheight).await?;
+            let response = match self.api.get_block_with_txs_at_topoheight(topoheight).await {
+                Ok(response) => response,
+                Err(e) => {
+                    // the block of a balance version must exist
+                    if is_daemon_error(&e) {
+                        self.api.report_anomaly(Anomaly::MissingBlock).await;
+                    }
+                    return Err(e)
+                }
+            };
             let block: Block = response.data.data.into_owned();
             let block_hash = response.data.hash.into_owned();
 
@@ -155,7 +180,7 @@ impl NetworkHandler {
                     #[cfg(feature = "api_server")]
                     {
                         if let Some(api_server) = self.wallet.get_api_server().lock().await.as_ref() {
-                            api_server.notify_event(&NotifyEvent::NewTransaction, &entry).await;
+                            api_server.notify_event(&WalletEvent::NewTransaction(Cow::Borrowed(&entry))).await;
                         }
                     }
 
@@ -166,11 +191,14 @@ impl NetworkHandler {
                 }
             }
 
+            // transactions that may be linked to us, executions are checked at once below
+            let mut candidates: Vec<(Hash, Option<u64>, Option<u64>, EntryData)> = Vec::new();
             let (block, txs) = block.split();
             for (tx_hash, tx) in block.into_owned().take_txs_hashes().into_iter().zip(txs) {
                 let tx = tx.into_owned();
                 let is_owner = *tx.get_owner() == *address.get_public_key();
-                let fee = if is_owner { Some(tx.get_fee()) } else { None };
+                // fees of a sponsored tx are not paid by us
+                let fee = if is_owner && tx.get_sponsor().is_none() { Some(tx.get_fee()) } else { None };
                 let nonce = if is_owner { Some(tx.get_nonce()) } else { None };
                 let (owner, data) = tx.consume();
                 let entry: Option<EntryData> = match data {
@@ -182,15 +210,12 @@ impl NetworkHandler {
                         }
                     },
                     TransactionType::Transfer(txs) => {
-                        let mut transfers: Vec<Transfer> = Vec::new();
+                        let mut transfers: Vec<TransferEntry> = Vec::new();
                         for tx in txs {
                             if is_owner || tx.to == *address.get_public_key() {
-                                let extra_data = if let Some(bytes) = tx.extra_data {
-                                    Option::from_bytes(&bytes)?
-                                } else {
-                                    None
-                                };
-                                let transfer = Transfer::new(tx.to, tx.asset, tx.amount, extra_data);
+                                // extra data is written as a DataElement by the sender, ignore it if it can't be decoded
+                                let extra_data = tx.extra_data.and_then(|bytes| DataElement::from_bytes(&bytes).ok());
+                                let transfer = TransferEntry::new(tx.to, tx.asset, tx.amount, extra_data);
                                 transfers.push(transfer);
                             }
                         }
@@ -210,21 +235,33 @@ impl NetworkHandler {
                 };
 
                 if let Some(entry) = entry {
-                    // New transaction entry that may be linked to us, check if TX was executed
-                    if !self.api.is_tx_executed_in_block(&tx_hash, &block_hash).await? {
+                    candidates.push((tx_hash, fee, nonce, entry));
+                }
+            }
+
+            if !candidates.is_empty() {
+                // New transaction entries that may be linked to us, check if TXs were executed
+                let txs_hashes: Vec<Hash> = candidates.iter().map(|(tx_hash, ..)| tx_hash.clone()).collect();
+                let executed = self.api.are_txs_executed_in_block(&txs_hashes, &block_hash).await?;
+                for ((tx_hash, fee, nonce, entry), is_executed) in candidates.into_iter().zip(executed) {
+                    if !is_executed {
                         debug!("Transaction {} was a good candidate but was not executed in block {}, skipping", tx_hash, block_hash);
                         continue;
                     }
 
-                    let entry = TransactionEntry::new(tx_hash, topoheight, fee, nonce, entry);
+                    let mut entry = TransactionEntry::new(tx_hash, topoheight, fee, nonce, entry);
                     let mut storage = self.wallet.get_storage().write().await;
+                    // our transaction may have replaced others waiting in mempool
+                    if nonce.is_some() {
+                        entry.set_replaced(storage.get_replaced_transactions(entry.get_hash())?);
+                    }
 
                     if !storage.has_transaction(entry.get_hash())? {
                         // notify listeners of new transaction
                         #[cfg(feature = "api_server")]
                         {
                             if let Some(api_server) = self.wallet.get_api_server().lock().await.as_ref() {
-                                api_server.notify_event(&NotifyEvent::NewTransaction, &entry).await;
+                                api_server.notify_event(&WalletEvent::NewTransaction(Cow::Borrowed(&entry))).await;
                             }
                         }
     
@@ -241,6 +278,65 @@ impl NetworkHandler {
         Ok(())
     }
 
+    // check the daemon responses against the ones received previously
+    // checkpoint is a block at the last stable height seen, it must never be replaced
+    async fn detect_anomaly(&self, info: &GetInfoResult, stableheight: u64, checkpoint: Option<&(u64, Hash)>) -> Option<Anomaly> {
+        if info.stableheight < stableheight {
+            debug!("Daemon stable height went back from {} to {}", stableheight, info.stableheight);
+            return Some(Anomaly::InconsistentResponse)
+        }
+
+        let (topoheight, hash) = checkpoint?;
+        if info.topoheight < *topoheight {
+            debug!("Daemon topoheight {} is below the stable topoheight {}", info.topoheight, topoheight);
+            return Some(Anomaly::InconsistentResponse)
+        }
+
+        match self.api.get_block_at_topoheight(*topoheight).await {
+            Ok(block) if *block.data.hash != *hash => {
+                debug!("Stable block {} at topoheight {} has been replaced by {}", hash, topoheight, block.data.hash);
+                Some(Anomaly::DeepReorg)
+            },
+            Err(e) if is_daemon_error(&e) => {
+                debug!("Stable block {} at topoheight {} is missing: {}", hash, topoheight, e);
+                Some(Anomaly::MissingBlock)
+            },
+            _ => None
+        }
+    }
+
+    // first block ordered at this stable height
+    async fn get_stable_checkpoint(&self, stableheight: u64) -> Option<(u64, Hash)> {
+        match self.api.get_blocks_at_height(stableheight).await {
+            Ok(blocks) => blocks.into_iter()
+                .filter_map(|block| block.topoheight.map(|topoheight| (topoheight, block.data.hash.into_owned())))
+                .min_by_key(|(topoheight, _)| *topoheight),
+            Err(e) => {
+                debug!("Error while retrieving blocks at stable height {}: {}", stableheight, e);
+                None
+            }
+        }
+    }
+
+    // switch to a fallback daemon once this one is not trusted anymore
+    // returns true if the syncing must be stopped
+    async fn handle_anomaly(&self, anomaly: Anomaly) -> bool {
+        let score = self.api.report_anomaly(anomaly).await;
+        if score >= UNTRUSTED_TRUST_SCORE || !self.wallet.has_fallback_daemon().await {
+            return false
+        }
+
+        error!("Daemon {} is not trusted anymore, switching to a fallback daemon", self.daemon_address);
+        let wallet = Arc::clone(&self.wallet);
+        // this task is stopped during the switch, so it must be done from another one
+        tokio::spawn(async move {
+            if let Err(e) = wallet.switch_to_fallback_daemon().await {
+                error!("Error while switching to a fallback daemon: {:#}", e);
+            }
+        });
+        true
+    }
+
     // start syncing the wallet with data from daemon API
     // we get all assets registered on chain and check their balance
     // we also check if there is a balance change at a previous topoheight
@@ -253,6 +349,8 @@ impl NetworkHandler {
             let storage = self.wallet.get_storage().read().await;
             (storage.get_daemon_topoheight().unwrap_or(0), storage.get_top_block_hash().unwrap_or(Hash::zero()))
         };
+        let mut stableheight = 0;
+        let mut checkpoint: Option<(u64, Hash)> = None;
         let mut interval = interval(Duration::from_secs(5));
         loop {
             interval.tick().await;
@@ -283,6 +381,31 @@ impl NetworkHandler {
                 self.is_paused.store(false, Ordering::SeqCst);
             }
 
+            if let Some(anomaly) = self.detect_anomaly(&info, stableheight, checkpoint.as_ref()).await {
+                if self.handle_anomaly(anomaly).await {
+                    return Ok(())
+                }
+                // the checkpoint is taken again from the current chain of the daemon
+                checkpoint = None;
+            }
+
+            if checkpoint.is_none() || info.stableheight != stableheight {
+                if let Some(new_checkpoint) = self.get_stable_checkpoint(info.stableheight).await {
+                    checkpoint = Some(new_checkpoint);
+                }
+            }
+            stableheight = stableheight.max(info.stableheight);
+
+            // while the daemon is syncing, its topoheight is not the one of the network
+            let sync_status = match self.api.get_sync_status().await {
+                Ok(status) => Some(status),
+                Err(e) => {
+                    debug!("Error while retrieving daemon sync status: {}", e);
+                    None
+                }
+            };
+            *self.daemon_sync_status.lock().await = sync_status;
+
             debug!("current topoheight: {}, info topoheight: {}", info.topoheight, current_topoheight);
             if info.topoheight == current_topoheight {
                 if current_topoheight != 0 && info.top_block_hash != top_block_hash {
@@ -290,6 +413,8 @@ impl NetworkHandler {
                     let mut storage = self.wallet.get_storage().write().await;
                     storage.delete_transactions_above_topoheight(current_topoheight - 1)?;
                 } else {
+                    // no new block, only the expiration of the invoices can change
+                    self.update_invoices().await;
                     continue;
                 }
             }
@@ -299,13 +424,15 @@ impl NetworkHandler {
             #[cfg(feature = "api_server")]
             {
                 if let Some(api_server) = self.wallet.get_api_server().lock().await.as_ref() {
-                    api_server.notify_event(&NotifyEvent::NewChainInfo, &info).await;
+                    api_server.notify_event(&WalletEvent::NewChainInfo(Cow::Borrowed(&info))).await;
                 }
             }
             top_block_hash = info.top_block_hash;
 
             if let Err(e) = self.sync_new_blocks(&address, current_topoheight, info.topoheight).await {
                 error!("Error while syncing new blocks: {}", e);
+            } else {
+                self.api.restore_trust().await;
             }
 
             // save current topoheight in daemon
@@ -316,9 +443,127 @@ impl NetworkHandler {
                 storage.set_top_block_hash(&top_block_hash)?;
             }
             current_topoheight = info.topoheight;
+            self.update_invoices().await;
+            self.update_webhooks(current_topoheight).await;
         }
     }
 
+    async fn update_invoices(&self) {
+        if let Err(e) = self.try_update_invoices().await {
+            error!("Error while updating invoices: {}", e);
+        }
+    }
+
+    // the pending invoices are paid once the transfers received with their payment data reach their amount,
+    // otherwise they expire once their expiration is reached
+    async fn try_update_invoices(&self) -> Result<(), Error> {
+        let mut changed: Vec<Invoice> = Vec::new();
+        let webhooks = {
+            let mut storage = self.wallet.get_storage().write().await;
+            let pending: Vec<Invoice> = storage.get_invoices()?
+                .into_iter()
+                .filter(|invoice| invoice.state == InvoiceState::Pending)
+                .collect();
+            if pending.is_empty() {
+                return Ok(())
+            }
+
+            let txs = storage.get_filtered_transactions(None, None, None, true, false, false, false, None)?;
+            let now = get_current_time();
+            for mut invoice in pending {
+                let data = invoice.get_payment_data();
+                let mut received = 0u64;
+                let mut transactions = Vec::new();
+                for tx in &txs {
+                    let EntryData::Incoming(_, transfers) = tx.get_entry() else {
+                        continue;
+                    };
+
+                    let amount = transfers.iter()
+                        .filter(|transfer| *transfer.get_asset() == invoice.asset && transfer.get_extra_data().as_ref() == Some(&data))
+                        .fold(0u64, |total, transfer| total.saturating_add(transfer.get_amount()));
+                    if amount > 0 {
+                        received = received.saturating_add(amount);
+                        transactions.push(tx.get_hash().clone());
+                    }
+                }
+
+                let state = if received >= invoice.amount {
+                    InvoiceState::Paid
+                } else if now >= invoice.expiration {
+                    InvoiceState::Expired
+                } else {
+                    InvoiceState::Pending
+                };
+
+                if state == InvoiceState::Pending && received == invoice.received {
+                    continue;
+                }
+
+                invoice.received = received;
+                invoice.transactions = transactions;
+                invoice.state = state;
+                storage.save_invoice(&invoice)?;
+                if state != InvoiceState::Pending {
+                    changed.push(invoice);
+                }
+            }
+
+            storage.get_webhooks()?
+        };
+
+        for invoice in changed {
+            info!("Invoice {} is now {:?} ({} received of {})", invoice.id, invoice.state, invoice.received, invoice.amount);
+            let entry = InvoiceEntry {
+                address: self.wallet.get_invoice_address(&invoice),
+                invoice: Cow::Borrowed(&invoice)
+            };
+
+            if invoice.state == InvoiceState::Paid {
+                for webhook in &webhooks {
+                    self.wallet.send_webhook(webhook, WebhookEvent::InvoicePaid, &entry)?;
+                }
+            }
+
+            #[cfg(feature = "api_server")]
+            {
+                if let Some(api_server) = self.wallet.get_api_server().lock().await.as_ref() {
+                    api_server.notify_event(&WalletEvent::InvoiceChanged(entry)).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn update_webhooks(&self, topoheight: u64) {
+        if let Err(e) = self.try_update_webhooks(topoheight).await {
+            error!("Error while updating webhooks: {}", e);
+        }
+    }
+
+    // notify each webhook of the incoming transactions that reached its confirmations
+    // a transaction has one confirmation at the topoheight including it
+    async fn try_update_webhooks(&self, topoheight: u64) -> Result<(), Error> {
+        let mut storage = self.wallet.get_storage().write().await;
+        for mut webhook in storage.get_webhooks()? {
+            let max_topoheight = match (topoheight + 1).checked_sub(webhook.confirmations) {
+                Some(max_topoheight) if max_topoheight > webhook.topoheight => max_topoheight,
+                _ => continue
+            };
+
+            let txs = storage.get_filtered_transactions(None, Some(webhook.topoheight + 1), Some(max_topoheight), true, false, false, false, None)?;
+            for tx in &txs {
+                self.wallet.send_webhook(&webhook, WebhookEvent::TransactionConfirmed, tx)?;
+            }
+
+            webhook.topoheight = max_topoheight;
+            storage.save_webhook(&webhook)?;
+        }
+
+        Ok(())
+    }
+
     async fn sync_new_blocks(&self, address: &Address, current_topoheight: u64, network_topoheight: u64) -> Result<(), Error> {
         let mut assets = {
             let storage = self.wallet.get_storage().read().await;
@@ -343,7 +588,7 @@ impl NetworkHandler {
                         #[cfg(feature = "api_server")]
                         {
                             if let Some(api_server) = self.wallet.get_api_server().lock().await.as_ref() {
-                                api_server.notify_event(&NotifyEvent::NewAsset, asset_data).await;
+                                api_server.notify_event(&WalletEvent::NewAsset(Cow::Borrowed(asset_data))).await;
                             }
                         }
 
@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::Serialize;
+use xelis_common::{
+    api::{DataElement, wallet::{EntryData, TransactionEntry}},
+    crypto::{address::Address, hash::Hash, key::PublicKey}
+};
+use crate::config::{PRIVACY_MIN_RECURRING_PAYMENTS, PRIVACY_QUICK_SPEND_DELAY, PRIVACY_RECURRING_TOLERANCE};
+
+// Counterparty found in several transactions of the history
+#[derive(Serialize, JsonSchema)]
+pub struct ReusedCounterparty {
+    pub address: Address,
+    // transactions received from it
+    pub incoming: usize,
+    // transactions sent to it
+    pub outgoing: usize,
+    pub first_topoheight: u64,
+    pub last_topoheight: u64
+}
+
+// Same extra data found in several transactions, extra data is public so anyone can link them
+#[derive(Serialize, JsonSchema)]
+pub struct ExtraDataLink {
+    pub data: DataElement,
+    pub transactions: Vec<Hash>
+}
+
+// Transaction sent shortly after receiving funds
+#[derive(Serialize, JsonSchema)]
+pub struct QuickSpend {
+    pub incoming: Hash,
+    pub outgoing: Hash,
+    // in blocks
+    pub delay: u64
+}
+
+// Payments sent to the same recipient at a regular interval
+#[derive(Serialize, JsonSchema)]
+pub struct RecurringPayment {
+    pub recipient: Address,
+    pub payments: usize,
+    // average interval in blocks
+    pub interval: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PrivacyReport {
+    pub incoming: usize,
+    pub outgoing: usize,
+    pub coinbase: usize,
+    pub distinct_senders: usize,
+    pub distinct_recipients: usize,
+    // transfers sent or received with extra data
+    pub transfers_with_extra_data: usize,
+    pub reused_counterparties: Vec<ReusedCounterparty>,
+    pub extra_data_links: Vec<ExtraDataLink>,
+    pub quick_spends: Vec<QuickSpend>,
+    pub recurring_payments: Vec<RecurringPayment>,
+    pub suggestions: Vec<String>
+}
+
+#[derive(Default)]
+struct Usage {
+    incoming: usize,
+    outgoing: usize,
+    first_topoheight: u64,
+    last_topoheight: u64
+}
+
+impl PrivacyReport {
+    // analyze the wallet history, everything is computed from the public data of the transactions
+    pub fn analyze(transactions: &[TransactionEntry], mainnet: bool) -> Self {
+        let mut transactions: Vec<&TransactionEntry> = transactions.iter().collect();
+        transactions.sort_by_key(|tx| tx.get_topoheight());
+
+        let mut incoming = 0;
+        let mut outgoing = 0;
+        let mut coinbase = 0;
+        let mut senders = HashSet::new();
+        let mut recipients = HashSet::new();
+        let mut transfers_with_extra_data = 0;
+        let mut usages: IndexMap<&PublicKey, Usage> = IndexMap::new();
+        // extra data can't be hashed, fields are not ordered so their serialized form can differ
+        let mut links: Vec<ExtraDataLink> = Vec::new();
+        let mut payments: IndexMap<&PublicKey, Vec<u64>> = IndexMap::new();
+        let mut quick_spends = Vec::new();
+        // last transaction which brought funds to the wallet
+        let mut last_received: Option<&TransactionEntry> = None;
+
+        for tx in &transactions {
+            let topoheight = tx.get_topoheight();
+            let (keys, transfers): (Vec<&PublicKey>, _) = match tx.get_entry() {
+                EntryData::Coinbase(_) => {
+                    coinbase += 1;
+                    last_received = Some(tx);
+                    continue;
+                },
+                EntryData::Burn { .. } => continue,
+                EntryData::Incoming(sender, transfers) => {
+                    incoming += 1;
+                    senders.insert(sender);
+                    last_received = Some(tx);
+                    (vec![sender], transfers)
+                },
+                EntryData::Outgoing(transfers) => {
+                    outgoing += 1;
+                    if let Some(received) = last_received {
+                        let delay = topoheight - received.get_topoheight();
+                        if delay <= PRIVACY_QUICK_SPEND_DELAY {
+                            quick_spends.push(QuickSpend {
+                                incoming: received.get_hash().clone(),
+                                outgoing: tx.get_hash().clone(),
+                                delay
+                            });
+                        }
+                    }
+
+                    let mut keys: Vec<&PublicKey> = Vec::new();
+                    for transfer in transfers {
+                        recipients.insert(transfer.get_key());
+                        if !keys.contains(&transfer.get_key()) {
+                            keys.push(transfer.get_key());
+                            payments.entry(transfer.get_key()).or_default().push(topoheight);
+                        }
+                    }
+                    (keys, transfers)
+                }
+            };
+
+            let is_incoming = matches!(tx.get_entry(), EntryData::Incoming(..));
+            for key in keys {
+                let usage = usages.entry(key).or_insert_with(|| Usage { first_topoheight: topoheight, ..Default::default() });
+                if is_incoming {
+                    usage.incoming += 1;
+                } else {
+                    usage.outgoing += 1;
+                }
+                usage.last_topoheight = topoheight;
+            }
+
+            for transfer in transfers {
+                if let Some(data) = transfer.get_extra_data() {
+                    transfers_with_extra_data += 1;
+                    match links.iter_mut().find(|link| link.data == *data) {
+                        Some(link) => {
+                            // a transaction is only listed once even if several of its transfers use the same data
+                            if link.transactions.last() != Some(tx.get_hash()) {
+                                link.transactions.push(tx.get_hash().clone());
+                            }
+                        },
+                        None => links.push(ExtraDataLink {
+                            data: data.clone(),
+                            transactions: vec![tx.get_hash().clone()]
+                        })
+                    }
+                }
+            }
+        }
+
+        let mut reused_counterparties: Vec<ReusedCounterparty> = usages.into_iter()
+            .filter(|(_, usage)| usage.incoming + usage.outgoing > 1)
+            .map(|(key, usage)| ReusedCounterparty {
+                address: key.to_address(mainnet),
+                incoming: usage.incoming,
+                outgoing: usage.outgoing,
+                first_topoheight: usage.first_topoheight,
+                last_topoheight: usage.last_topoheight
+            })
+            .collect();
+        reused_counterparties.sort_by_key(|c| std::cmp::Reverse(c.incoming + c.outgoing));
+
+        let extra_data_links: Vec<ExtraDataLink> = links.into_iter()
+            .filter(|link| link.transactions.len() > 1)
+            .collect();
+
+        let recurring_payments: Vec<RecurringPayment> = payments.into_iter()
+            .filter_map(|(key, topoheights)| Self::find_interval(&topoheights).map(|interval| RecurringPayment {
+                recipient: key.to_address(mainnet),
+                payments: topoheights.len(),
+                interval
+            }))
+            .collect();
+
+        let mut report = Self {
+            incoming,
+            outgoing,
+            coinbase,
+            distinct_senders: senders.len(),
+            distinct_recipients: recipients.len(),
+            transfers_with_extra_data,
+            reused_counterparties,
+            extra_data_links,
+            quick_spends,
+            recurring_payments,
+            suggestions: Vec::new()
+        };
+        report.suggestions = report.build_suggestions();
+        report
+    }
+
+    // average interval between the payments if they are all close enough to it
+    fn find_interval(topoheights: &[u64]) -> Option<u64> {
+        if topoheights.len() < PRIVACY_MIN_RECURRING_PAYMENTS {
+            return None
+        }
+
+        let intervals: Vec<u64> = topoheights.windows(2).map(|w| w[1] - w[0]).collect();
+        let average = intervals.iter().sum::<u64>() / intervals.len() as u64;
+        if average == 0 {
+            return None
+        }
+
+        let tolerance = average * PRIVACY_RECURRING_TOLERANCE / 100;
+        if intervals.iter().all(|interval| interval.abs_diff(average) <= tolerance) {
+            Some(average)
+        } else {
+            None
+        }
+    }
+
+    fn build_suggestions(&self) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        if self.incoming + self.coinbase > 1 {
+            suggestions.push(format!(
+                "All {} incoming transactions were received on the same address and can be listed by anyone, integrated addresses share the same key and don't prevent it: use a new wallet for payments that must not be linked to this one",
+                self.incoming + self.coinbase
+            ));
+        }
+
+        if !self.reused_counterparties.is_empty() {
+            suggestions.push(format!(
+                "{} counterparties appear in several transactions: each one can see your other payments with them, use a dedicated wallet for recurring counterparties",
+                self.reused_counterparties.len()
+            ));
+        }
+
+        if !self.extra_data_links.is_empty() {
+            suggestions.push(format!(
+                "{} extra data values were used in several transactions and link them publicly: use a unique integrated data per payment, for example by creating an invoice",
+                self.extra_data_links.len()
+            ));
+        } else if self.transfers_with_extra_data > 0 {
+            suggestions.push(format!(
+                "{} transfers contain extra data which is stored in plain text on chain: avoid putting personal information in it",
+                self.transfers_with_extra_data
+            ));
+        }
+
+        if !self.quick_spends.is_empty() {
+            suggestions.push(format!(
+                "{} transactions were sent less than {} blocks after receiving funds: wait a random delay before spending or moving received funds, including when consolidating them to a new wallet",
+                self.quick_spends.len(), PRIVACY_QUICK_SPEND_DELAY
+            ));
+        }
+
+        if !self.recurring_payments.is_empty() {
+            suggestions.push(format!(
+                "Payments to {} recipients follow a regular interval: vary the time at which recurring payments are sent",
+                self.recurring_payments.len()
+            ));
+        }
+
+        suggestions
+    }
+}
+
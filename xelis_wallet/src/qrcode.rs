@@ -0,0 +1,609 @@
+// QR Code encoder used to share the wallet addresses and payment URIs
+// only the byte mode with a medium error correction level is supported,
+// which is enough for a bech32 address (lowercase, so the alphanumeric mode can't be used)
+
+use std::io::Write;
+use flate2::{write::ZlibEncoder, Compression};
+use thiserror::Error;
+use xelis_common::{
+    config::XELIS_ASSET,
+    crypto::{address::Address, hash::Hash}
+};
+
+// scheme of the payment URIs, followed by the address and the optional query parameters
+pub const PAYMENT_URI_SCHEME: &str = "xelis";
+
+const MIN_VERSION: usize = 1;
+const MAX_VERSION: usize = 40;
+
+// error correction codewords per block for the medium level, indexed by version
+const ECC_CODEWORDS_PER_BLOCK: [usize; MAX_VERSION + 1] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26,
+    26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28
+];
+
+// error correction blocks for the medium level, indexed by version
+const NUM_ERROR_CORRECTION_BLOCKS: [usize; MAX_VERSION + 1] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14,
+    16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49
+];
+
+// format bits of the medium error correction level
+const ECC_FORMAT_BITS: u32 = 0;
+
+// weights of the mask penalty rules
+const PENALTY_N1: i32 = 3;
+const PENALTY_N2: i32 = 3;
+const PENALTY_N3: i32 = 40;
+const PENALTY_N4: i32 = 10;
+
+#[derive(Error, Debug)]
+pub enum QRCodeError {
+    #[error("Data is too long to be encoded in a QR Code: {} bytes", _0)]
+    DataTooLong(usize),
+    #[error("Error while encoding the PNG image: {}", _0)]
+    Png(#[from] std::io::Error)
+}
+
+pub struct QRCode {
+    version: usize,
+    size: usize,
+    // true for a dark module, row by row
+    modules: Vec<bool>,
+    // modules of the function patterns, they are not masked
+    is_function: Vec<bool>
+}
+
+impl QRCode {
+    // encode the data using the smallest version possible
+    pub fn encode(data: &[u8]) -> Result<Self, QRCodeError> {
+        let version = (MIN_VERSION..=MAX_VERSION)
+            .find(|version| Self::get_data_bits(*version, data.len()) <= get_num_data_codewords(*version) * 8)
+            .ok_or(QRCodeError::DataTooLong(data.len()))?;
+
+        let capacity = get_num_data_codewords(version) * 8;
+        let mut bits = BitBuffer::default();
+        // byte mode
+        bits.append(0b0100, 4);
+        bits.append(data.len() as u32, Self::get_char_count_bits(version));
+        for byte in data {
+            bits.append(*byte as u32, 8);
+        }
+
+        // terminator and padding to the capacity
+        bits.append(0, (capacity - bits.len()).min(4));
+        bits.append(0, (8 - bits.len() % 8) % 8);
+        for pad in [0xEC, 0x11].iter().cycle() {
+            if bits.len() >= capacity {
+                break;
+            }
+            bits.append(*pad, 8);
+        }
+
+        let size = version * 4 + 17;
+        let mut qr_code = Self {
+            version,
+            size,
+            modules: vec![false; size * size],
+            is_function: vec![false; size * size]
+        };
+        qr_code.draw_function_patterns();
+        let codewords = qr_code.add_ecc_and_interleave(&bits.to_bytes());
+        qr_code.draw_codewords(&codewords);
+
+        // keep the mask with the lowest penalty
+        let mut best_mask = 0;
+        let mut min_penalty = i32::MAX;
+        for mask in 0..8 {
+            qr_code.apply_mask(mask);
+            qr_code.draw_format_bits(mask);
+            let penalty = qr_code.get_penalty_score();
+            if penalty < min_penalty {
+                best_mask = mask;
+                min_penalty = penalty;
+            }
+            // masks are applied using a XOR, so it is reverted by applying it again
+            qr_code.apply_mask(mask);
+        }
+        qr_code.apply_mask(best_mask);
+        qr_code.draw_format_bits(best_mask);
+
+        Ok(qr_code)
+    }
+
+    fn get_char_count_bits(version: usize) -> usize {
+        if version <= 9 { 8 } else { 16 }
+    }
+
+    fn get_data_bits(version: usize, len: usize) -> usize {
+        4 + Self::get_char_count_bits(version) + len * 8
+    }
+
+    pub fn get_version(&self) -> usize {
+        self.version
+    }
+
+    // modules per side, without the quiet zone
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    // modules outside of the code are light
+    pub fn is_dark(&self, x: i32, y: i32) -> bool {
+        let size = self.size as i32;
+        (0..size).contains(&x) && (0..size).contains(&y) && self.modules[(y * size + x) as usize]
+    }
+
+    fn set_function_module(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.is_function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        // timing patterns
+        for i in 0..self.size {
+            self.set_function_module(6, i, i % 2 == 0);
+            self.set_function_module(i, 6, i % 2 == 0);
+        }
+
+        // finder patterns in three corners
+        self.draw_finder_pattern(3, 3);
+        self.draw_finder_pattern(self.size as i32 - 4, 3);
+        self.draw_finder_pattern(3, self.size as i32 - 4);
+
+        // alignment patterns, except where they overlap the finder patterns
+        let positions = self.get_alignment_pattern_positions();
+        let count = positions.len();
+        for i in 0..count {
+            for j in 0..count {
+                let overlaps_finder = (i == 0 && (j == 0 || j == count - 1)) || (i == count - 1 && j == 0);
+                if !overlaps_finder {
+                    self.draw_alignment_pattern(positions[i], positions[j]);
+                }
+            }
+        }
+
+        // reserve the format modules, they are drawn once the mask is selected
+        self.draw_format_bits(0);
+        self.draw_version();
+    }
+
+    fn draw_finder_pattern(&mut self, x: i32, y: i32) {
+        for dy in -4..=4 {
+            for dx in -4..=4 {
+                let (xx, yy) = (x + dx, y + dy);
+                if (0..self.size as i32).contains(&xx) && (0..self.size as i32).contains(&yy) {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function_module(xx as usize, yy as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment_pattern(&mut self, x: usize, y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function_module((x as i32 + dx) as usize, (y as i32 + dy) as usize, dark);
+            }
+        }
+    }
+
+    // centers of the alignment patterns, on both axes
+    fn get_alignment_pattern_positions(&self) -> Vec<usize> {
+        if self.version == 1 {
+            return Vec::new()
+        }
+
+        let count = self.version / 7 + 2;
+        let step = if self.version == 32 {
+            26
+        } else {
+            (self.version * 4 + count * 2 + 1) / (count * 2 - 2) * 2
+        };
+
+        // evenly spaced from the last one, the first one is always next to the finder pattern
+        let mut positions: Vec<usize> = (0..count - 1).map(|i| self.size - 7 - i * step).collect();
+        positions.push(6);
+        positions.reverse();
+        positions
+    }
+
+    fn draw_format_bits(&mut self, mask: u32) {
+        let data = ECC_FORMAT_BITS << 3 | mask;
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+
+        // around the top left finder pattern
+        for i in 0..=5 {
+            self.set_function_module(8, i, get_bit(bits, i));
+        }
+        self.set_function_module(8, 7, get_bit(bits, 6));
+        self.set_function_module(8, 8, get_bit(bits, 7));
+        self.set_function_module(7, 8, get_bit(bits, 8));
+        for i in 9..15 {
+            self.set_function_module(14 - i, 8, get_bit(bits, i));
+        }
+
+        // copy split between the two other finder patterns
+        for i in 0..8 {
+            self.set_function_module(self.size - 1 - i, 8, get_bit(bits, i));
+        }
+        for i in 8..15 {
+            self.set_function_module(8, self.size - 15 + i, get_bit(bits, i));
+        }
+        // always dark
+        self.set_function_module(8, self.size - 8, true);
+    }
+
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return
+        }
+
+        let mut remainder = self.version as u32;
+        for _ in 0..12 {
+            remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+        }
+        let bits = (self.version as u32) << 12 | remainder;
+
+        // two copies near the top right and bottom left finder patterns
+        for i in 0..18 {
+            let dark = get_bit(bits, i);
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function_module(a, b, dark);
+            self.set_function_module(b, a, dark);
+        }
+    }
+
+    // split the data in blocks, compute their error correction and interleave them
+    fn add_ecc_and_interleave(&self, data: &[u8]) -> Vec<u8> {
+        let num_blocks = NUM_ERROR_CORRECTION_BLOCKS[self.version];
+        let block_ecc_len = ECC_CODEWORDS_PER_BLOCK[self.version];
+        let raw_codewords = get_num_raw_data_modules(self.version) / 8;
+        let num_short_blocks = num_blocks - raw_codewords % num_blocks;
+        let short_block_len = raw_codewords / num_blocks;
+
+        let divisor = reed_solomon_compute_divisor(block_ecc_len);
+        let mut blocks: Vec<Vec<u8>> = Vec::with_capacity(num_blocks);
+        let mut offset = 0;
+        for i in 0..num_blocks {
+            let len = short_block_len - block_ecc_len + if i < num_short_blocks { 0 } else { 1 };
+            let mut block = data[offset..offset + len].to_vec();
+            offset += len;
+            let ecc = reed_solomon_compute_remainder(&block, &divisor);
+            // short blocks are padded so all blocks have the same length, the padding is skipped below
+            if i < num_short_blocks {
+                block.push(0);
+            }
+            block.extend(ecc);
+            blocks.push(block);
+        }
+
+        let mut result = Vec::with_capacity(raw_codewords);
+        for i in 0..blocks[0].len() {
+            for (j, block) in blocks.iter().enumerate() {
+                if i != short_block_len - block_ecc_len || j >= num_short_blocks {
+                    result.push(block[i]);
+                }
+            }
+        }
+        result
+    }
+
+    // codewords are placed in a zigzag from the bottom right corner, two columns at a time
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let mut i = 0;
+        let mut right = self.size as i32 - 1;
+        while right >= 1 {
+            // skip the vertical timing pattern
+            if right == 6 {
+                right = 5;
+            }
+
+            for vertical in 0..self.size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { self.size - 1 - vertical } else { vertical };
+                    let index = y * self.size + x;
+                    if !self.is_function[index] && i < data.len() * 8 {
+                        self.modules[index] = get_bit(data[i >> 3] as u32, 7 - (i & 7));
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0
+                };
+                let index = y * self.size + x;
+                if invert && !self.is_function[index] {
+                    self.modules[index] = !self.modules[index];
+                }
+            }
+        }
+    }
+
+    fn get_penalty_score(&self) -> i32 {
+        let size = self.size as i32;
+        let mut result = 0;
+
+        // runs of the same color and finder-like patterns in rows and columns
+        for horizontal in [true, false] {
+            for a in 0..size {
+                let mut run_color = false;
+                let mut run_len = 0;
+                let mut history = [0i32; 7];
+                for b in 0..size {
+                    let color = if horizontal { self.is_dark(b, a) } else { self.is_dark(a, b) };
+                    if color == run_color {
+                        run_len += 1;
+                        if run_len == 5 {
+                            result += PENALTY_N1;
+                        } else if run_len > 5 {
+                            result += 1;
+                        }
+                    } else {
+                        self.finder_penalty_add_history(run_len, &mut history);
+                        if !run_color {
+                            result += self.finder_penalty_count_patterns(&history) * PENALTY_N3;
+                        }
+                        run_color = color;
+                        run_len = 1;
+                    }
+                }
+                result += self.finder_penalty_terminate_and_count(run_color, run_len, &mut history) * PENALTY_N3;
+            }
+        }
+
+        // 2x2 blocks of the same color
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.is_dark(x, y);
+                if color == self.is_dark(x + 1, y) && color == self.is_dark(x, y + 1) && color == self.is_dark(x + 1, y + 1) {
+                    result += PENALTY_N2;
+                }
+            }
+        }
+
+        // balance of dark and light modules
+        let dark = self.modules.iter().filter(|dark| **dark).count() as i32;
+        let total = size * size;
+        let k = ((dark * 20 - total * 10).abs() + total - 1) / total - 1;
+        result + k * PENALTY_N4
+    }
+
+    fn finder_penalty_count_patterns(&self, history: &[i32; 7]) -> i32 {
+        let n = history[1];
+        let core = n > 0 && history[2] == n && history[3] == n * 3 && history[4] == n && history[5] == n;
+        (core && history[0] >= n * 4 && history[6] >= n) as i32 + (core && history[6] >= n * 4 && history[0] >= n) as i32
+    }
+
+    fn finder_penalty_terminate_and_count(&self, run_color: bool, mut run_len: i32, history: &mut [i32; 7]) -> i32 {
+        if run_color {
+            self.finder_penalty_add_history(run_len, history);
+            run_len = 0;
+        }
+        // the quiet zone is light
+        run_len += self.size as i32;
+        self.finder_penalty_add_history(run_len, history);
+        self.finder_penalty_count_patterns(history)
+    }
+
+    fn finder_penalty_add_history(&self, mut run_len: i32, history: &mut [i32; 7]) {
+        // the first run includes the quiet zone
+        if history[0] == 0 {
+            run_len += self.size as i32;
+        }
+        history.copy_within(0..6, 1);
+        history[0] = run_len;
+    }
+
+    // one unit per module
+    pub fn to_svg(&self, border: usize) -> String {
+        let dimension = self.size + border * 2;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.is_dark(x as i32, y as i32) {
+                    if !path.is_empty() {
+                        path.push(' ');
+                    }
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + border, y + border));
+                }
+            }
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" viewBox=\"0 0 {0} {0}\" stroke=\"none\">\
+            <rect width=\"100%\" height=\"100%\" fill=\"#FFFFFF\"/>\
+            <path d=\"{1}\" fill=\"#000000\"/>\
+            </svg>",
+            dimension,
+            path
+        )
+    }
+
+    // grayscale image with `scale` pixels per module
+    pub fn to_png(&self, scale: usize, border: usize) -> Result<Vec<u8>, QRCodeError> {
+        let dimension = (self.size + border * 2) * scale;
+        let mut pixels = Vec::with_capacity((dimension + 1) * dimension);
+        for py in 0..dimension {
+            // no filter
+            pixels.push(0);
+            let y = (py / scale) as i32 - border as i32;
+            for px in 0..dimension {
+                let x = (px / scale) as i32 - border as i32;
+                pixels.push(if self.is_dark(x, y) { 0x00 } else { 0xFF });
+            }
+        }
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&pixels)?;
+        let compressed = encoder.finish()?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend((dimension as u32).to_be_bytes());
+        header.extend((dimension as u32).to_be_bytes());
+        // bit depth 8, grayscale, default compression, filter and no interlace
+        header.extend([8, 0, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+        write_png_chunk(&mut png, b"IHDR", &header);
+        write_png_chunk(&mut png, b"IDAT", &compressed);
+        write_png_chunk(&mut png, b"IEND", &[]);
+        Ok(png)
+    }
+
+    // two modules per character using half blocks, light modules are drawn
+    // so it can be scanned on a terminal with a dark background
+    pub fn to_terminal(&self, border: usize) -> String {
+        let min = -(border as i32);
+        let max = (self.size + border) as i32;
+        let mut output = String::new();
+        let mut y = min;
+        while y < max {
+            for x in min..max {
+                let top = !self.is_dark(x, y);
+                let bottom = y + 1 < max && !self.is_dark(x, y + 1);
+                output.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' '
+                });
+            }
+            output.push('\n');
+            y += 2;
+        }
+        output
+    }
+}
+
+// content to encode in a QR Code: the address alone, or a payment URI if an amount or another asset is requested
+// amount is written in atomic units
+pub fn build_payment_uri(address: &Address, amount: Option<u64>, asset: &Hash) -> String {
+    if amount.is_none() && *asset == XELIS_ASSET {
+        return address.to_string()
+    }
+
+    let mut params = Vec::new();
+    if let Some(amount) = amount {
+        params.push(format!("amount={}", amount));
+    }
+    if *asset != XELIS_ASSET {
+        params.push(format!("asset={}", asset));
+    }
+    format!("{}:{}?{}", PAYMENT_URI_SCHEME, address, params.join("&"))
+}
+
+#[derive(Default)]
+struct BitBuffer {
+    bits: Vec<bool>
+}
+
+impl BitBuffer {
+    // append the lowest `len` bits of the value, most significant first
+    fn append(&mut self, value: u32, len: usize) {
+        for i in (0..len).rev() {
+            self.bits.push((value >> i) & 1 != 0);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.bits.chunks(8)
+            .map(|chunk| chunk.iter().fold(0u8, |byte, bit| byte << 1 | *bit as u8))
+            .collect()
+    }
+}
+
+fn get_bit(value: u32, index: usize) -> bool {
+    (value >> index) & 1 != 0
+}
+
+// modules available for the data and error correction codewords
+fn get_num_raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let count = version / 7 + 2;
+        result -= (25 * count - 10) * count - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn get_num_data_codewords(version: usize) -> usize {
+    get_num_raw_data_modules(version) / 8 - ECC_CODEWORDS_PER_BLOCK[version] * NUM_ERROR_CORRECTION_BLOCKS[version]
+}
+
+// generator polynomial of the given degree, without its leading term
+fn reed_solomon_compute_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = reed_solomon_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = reed_solomon_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_compute_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (value, coefficient) in result.iter_mut().zip(divisor) {
+            *value ^= reed_solomon_multiply(*coefficient, factor);
+        }
+    }
+    result
+}
+
+// product in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn reed_solomon_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
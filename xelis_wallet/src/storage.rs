@@ -1,14 +1,16 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::Mutex};
 use indexmap::IndexMap;
 use sled::{Tree, Db};
 use xelis_common::{
-    crypto::{hash::Hash, key::{KeyPair, PublicKey}},
-    serializer::{Reader, Serializer},
+    crypto::{hash::Hash, key::{KeyPair, PublicKey}, threshold::KeyShare},
+    serializer::{Reader, Serializer, Writer},
     network::Network,
-    api::{DataValue, DataElement, QueryResult, Query},
+    api::{DataValue, DataElement, QueryResult, Query, wallet::{Invoice, Webhook, AuditEntry, AuditEvent, VerifyAuditLogResult, TransactionEntry, EntryData, MAX_AUDIT_DETAILS_SIZE, MAX_REPLACED_TRANSACTIONS}},
+    utils::get_current_time,
 };
 use anyhow::{Context, Result, anyhow};
-use crate::{config::SALT_SIZE, cipher::Cipher, wallet::WalletError, entry::{TransactionEntry, EntryData}};
+use log::info;
+use crate::{config::SALT_SIZE, cipher::Cipher, wallet::WalletError, cosigner::CosignedTransaction};
 
 // keys used to retrieve from storage
 const NONCE_KEY: &[u8] = b"NONCE";
@@ -18,6 +20,8 @@ const PASSWORD_SALT_KEY: &[u8] = b"PSALT";
 // Master key to encrypt/decrypt while interacting with the storage 
 const MASTER_KEY: &[u8] = b"MKEY";
 const KEY_PAIR: &[u8] = b"KPAIR";
+// share of the shared account key used in co-signer mode
+const KEY_SHARE: &[u8] = b"KSHARE";
 
 // const used for online mode
 // represent the daemon topoheight
@@ -25,6 +29,46 @@ const TOPOHEIGHT_KEY: &[u8] = b"TOPH";
 // represent the daemon top block hash
 const TOP_BLOCK_HASH_KEY: &[u8] = b"TOPBH";
 const NETWORK: &[u8] = b"NET";
+// number of entries in the audit log
+const AUDIT_COUNT_KEY: &[u8] = b"AUDITC";
+// Version of the storage format, saved in plaintext to be checked before decrypting anything
+const VERSION_KEY: &[u8] = b"VERSION";
+
+// Current version of the storage format
+// Wallets created before the format was versioned don't have the version record and are in version 1
+pub const STORAGE_VERSION: u8 = 3;
+
+// Migration of the storage from a version to the next one
+// A migration can be interrupted and must be safe to apply again
+type Migration = fn(&mut EncryptedStorage) -> Result<()>;
+
+// migrations ordered by version, the first one is from version 1 to 2
+const MIGRATIONS: [Migration; STORAGE_VERSION as usize - 1] = [
+    migrate_v1_to_v2,
+    migrate_v2_to_v3
+];
+
+// version 2 only introduces the version record, data is unchanged
+fn migrate_v1_to_v2(_: &mut EncryptedStorage) -> Result<()> {
+    Ok(())
+}
+
+// version 3 adds the transactions replaced at the end of the transaction entries
+// an entry already migrated can be read entirely and is skipped
+fn migrate_v2_to_v3(storage: &mut EncryptedStorage) -> Result<()> {
+    for el in storage.transactions.iter() {
+        let (key, value) = el?;
+        let mut bytes = storage.cipher.decrypt_value(&value)?;
+        if TransactionEntry::from_bytes(&bytes).is_ok() {
+            continue;
+        }
+
+        // no transaction replaced
+        bytes.push(0);
+        storage.transactions.insert(key, storage.cipher.encrypt_value(&bytes)?)?;
+    }
+    Ok(())
+}
 
 // Use this struct to get access to non-encrypted keys (such as salt for KDF and encrypted master key)
 pub struct Storage {
@@ -38,6 +82,15 @@ pub struct EncryptedStorage {
     balances: Tree,
     extra: Tree,
     assets: Tree,
+    invoices: Tree,
+    webhooks: Tree,
+    cosigned: Tree,
+    // replacement TX hash => hash of the TX it replaced
+    replacements: Tree,
+    // append-only log of the sensitive operations, keyed by index
+    audit: Tree,
+    // an entry depends on the previous one, appends must not be concurrent
+    audit_lock: Mutex<()>,
     inner: Storage
 }
 
@@ -49,10 +102,18 @@ impl EncryptedStorage {
             balances: inner.db.open_tree(&cipher.hash_key("balances"))?,
             extra: inner.db.open_tree(&cipher.hash_key("extra"))?,
             assets: inner.db.open_tree(&cipher.hash_key("assets"))?,
+            invoices: inner.db.open_tree(cipher.hash_key("invoices"))?,
+            webhooks: inner.db.open_tree(cipher.hash_key("webhooks"))?,
+            cosigned: inner.db.open_tree(cipher.hash_key("cosigned"))?,
+            replacements: inner.db.open_tree(cipher.hash_key("replacements"))?,
+            audit: inner.db.open_tree(cipher.hash_key("audit"))?,
+            audit_lock: Mutex::new(()),
             cipher,
             inner
         };
 
+        storage.migrate()?;
+
         if storage.has_network()? {
             let storage_network = storage.get_network()?;
             if storage_network != network {
@@ -65,6 +126,28 @@ impl EncryptedStorage {
         Ok(storage)
     }
 
+    // apply the migrations in order until the storage is in the current version
+    // the version is saved after each migration so an interrupted upgrade resumes from the last one applied
+    fn migrate(&mut self) -> Result<()> {
+        let mut version = self.inner.get_version()?;
+        if version == STORAGE_VERSION {
+            return Ok(())
+        }
+
+        while version < STORAGE_VERSION {
+            info!("Migrating wallet storage from version {} to {}", version, version + 1);
+            let migration = MIGRATIONS[version as usize - 1];
+            migration(self).with_context(|| format!("Error while migrating wallet storage from version {} to {}", version, version + 1))?;
+
+            version += 1;
+            self.inner.set_version(version)?;
+            self.inner.db.flush()?;
+        }
+        info!("Wallet storage is now in version {}", version);
+
+        Ok(())
+    }
+
     // Key must be hashed or encrypted before calling this function
     fn internal_load<V: Serializer>(&self, tree: &Tree, key: &[u8]) -> Result<V> {
         let data = tree.get(key)?.context(format!("load from disk: tree = {:?}, key = {}", tree.name(), String::from_utf8_lossy(key)))?;
@@ -396,6 +479,126 @@ impl EncryptedStorage {
         self.contains_data(&self.transactions, hash.as_bytes())
     }
 
+    pub fn set_replaced_transaction(&mut self, replacement: &Hash, replaced: &Hash) -> Result<()> {
+        self.save_to_disk(&self.replacements, replacement.as_bytes(), replaced.as_bytes())
+    }
+
+    // transactions replaced one after the other until this one, the last replaced first
+    pub fn get_replaced_transactions(&self, hash: &Hash) -> Result<Vec<Hash>> {
+        let mut replaced = Vec::new();
+        let mut current = hash.clone();
+        while replaced.len() < MAX_REPLACED_TRANSACTIONS && self.contains_data(&self.replacements, current.as_bytes())? {
+            current = self.load_from_disk(&self.replacements, current.as_bytes())?;
+            replaced.push(current.clone());
+        }
+        Ok(replaced)
+    }
+
+    pub fn save_invoice(&mut self, invoice: &Invoice) -> Result<()> {
+        self.save_to_disk(&self.invoices, invoice.id.as_bytes(), &invoice.to_bytes())
+    }
+
+    pub fn get_invoice(&self, id: &Hash) -> Result<Invoice> {
+        self.load_from_disk(&self.invoices, id.as_bytes())
+    }
+
+    pub fn has_invoice(&self, id: &Hash) -> Result<bool> {
+        self.contains_data(&self.invoices, id.as_bytes())
+    }
+
+    // invoices are not ordered on disk
+    pub fn get_invoices(&self) -> Result<Vec<Invoice>> {
+        let mut invoices = Vec::new();
+        for el in self.invoices.iter().values() {
+            let value = el?;
+            invoices.push(Invoice::from_bytes(&self.cipher.decrypt_value(&value)?)?);
+        }
+        Ok(invoices)
+    }
+
+    pub fn save_webhook(&mut self, webhook: &Webhook) -> Result<()> {
+        self.save_to_disk(&self.webhooks, webhook.id.as_bytes(), &webhook.to_bytes())
+    }
+
+    pub fn has_webhook(&self, id: &Hash) -> Result<bool> {
+        self.contains_data(&self.webhooks, id.as_bytes())
+    }
+
+    pub fn delete_webhook(&mut self, id: &Hash) -> Result<()> {
+        self.delete_from_disk(&self.webhooks, id.as_bytes())
+    }
+
+    pub fn get_webhooks(&self) -> Result<Vec<Webhook>> {
+        let mut webhooks = Vec::new();
+        for el in self.webhooks.iter().values() {
+            let value = el?;
+            webhooks.push(Webhook::from_bytes(&self.cipher.decrypt_value(&value)?)?);
+        }
+        Ok(webhooks)
+    }
+
+    pub fn save_cosigned_transaction(&mut self, transaction: &CosignedTransaction) -> Result<()> {
+        self.save_to_disk(&self.cosigned, transaction.hash.as_bytes(), &transaction.to_bytes())
+    }
+
+    pub fn get_cosigned_transactions(&self) -> Result<Vec<CosignedTransaction>> {
+        let mut transactions = Vec::new();
+        for el in self.cosigned.iter().values() {
+            let value = el?;
+            transactions.push(CosignedTransaction::from_bytes(&self.cipher.decrypt_value(&value)?)?);
+        }
+        Ok(transactions)
+    }
+
+    pub fn get_audit_entries_count(&self) -> Result<u64> {
+        if !self.contains_data(&self.extra, AUDIT_COUNT_KEY)? {
+            return Ok(0)
+        }
+        self.load_from_disk(&self.extra, AUDIT_COUNT_KEY)
+    }
+
+    pub fn get_audit_entry(&self, index: u64) -> Result<AuditEntry> {
+        self.load_from_disk(&self.audit, &index.to_be_bytes())
+    }
+
+    // chain a new entry to the last one, entries are never modified or deleted
+    // it only needs a read access to the storage so it can be recorded while signing
+    pub fn add_audit_entry(&self, event: AuditEvent, mut details: String) -> Result<AuditEntry> {
+        if details.len() > MAX_AUDIT_DETAILS_SIZE {
+            let mut size = MAX_AUDIT_DETAILS_SIZE;
+            while !details.is_char_boundary(size) {
+                size -= 1;
+            }
+            details.truncate(size);
+        }
+
+        let _lock = self.audit_lock.lock().map_err(|_| anyhow!("Audit log lock is poisoned"))?;
+        let index = self.get_audit_entries_count()?;
+        let previous = if index > 0 {
+            self.get_audit_entry(index - 1)?.hash
+        } else {
+            Hash::zero()
+        };
+
+        let entry = AuditEntry::new(index, get_current_time(), event, details, previous);
+        self.save_to_disk(&self.audit, &index.to_be_bytes(), &entry.to_bytes())?;
+        self.save_to_disk(&self.extra, AUDIT_COUNT_KEY, &(index + 1).to_be_bytes())?;
+        Ok(entry)
+    }
+
+    // check each entry against the previous one, an entry that can't be decrypted also breaks the chain
+    pub fn verify_audit_log(&self) -> Result<VerifyAuditLogResult> {
+        let count = self.get_audit_entries_count()?;
+        let mut head = Hash::zero();
+        for index in 0..count {
+            match self.get_audit_entry(index) {
+                Ok(entry) if entry.is_valid_after(index, &head) => head = entry.hash,
+                _ => return Ok(VerifyAuditLogResult { count, head, invalid_index: Some(index) })
+            };
+        }
+        Ok(VerifyAuditLogResult { count, head, invalid_index: None })
+    }
+
     pub fn get_nonce(&self) -> Result<u64> {
         self.load_from_disk(&self.extra, NONCE_KEY)
     }
@@ -412,6 +615,18 @@ impl EncryptedStorage {
         self.load_from_disk(&self.extra, KEY_PAIR)
     }
 
+    pub fn set_key_share(&mut self, share: &KeyShare) -> Result<()> {
+        self.save_to_disk(&self.extra, KEY_SHARE, &share.to_bytes())
+    }
+
+    pub fn get_key_share(&self) -> Result<KeyShare> {
+        self.load_from_disk(&self.extra, KEY_SHARE)
+    }
+
+    pub fn has_key_share(&self) -> Result<bool> {
+        self.contains_data(&self.extra, KEY_SHARE)
+    }
+
     pub fn set_daemon_topoheight(&mut self, topoheight: u64) -> Result<()> {
         self.save_to_disk(&self.extra, TOPOHEIGHT_KEY, &topoheight.to_be_bytes())
     }
@@ -458,12 +673,36 @@ impl EncryptedStorage {
 }
 
 impl Storage {
+    // a storage written by a more recent version of the wallet is refused
+    // as it could be corrupted by a format that we don't know
     pub fn new(name: String) -> Result<Self> {
-        let db = sled::open(name)?;
+        Self::from_db(sled::open(name)?)
+    }
 
-        Ok(Self {
+    fn from_db(db: Db) -> Result<Self> {
+        let storage = Self {
             db
-        })
+        };
+
+        let version = storage.get_version()?;
+        if version == 0 || version > STORAGE_VERSION {
+            return Err(WalletError::UnsupportedStorageVersion(version, STORAGE_VERSION).into())
+        }
+
+        Ok(storage)
+    }
+
+    // version of the storage format, 1 if it was created before being versioned
+    pub fn get_version(&self) -> Result<u8> {
+        match self.db.get(VERSION_KEY)? {
+            Some(value) => Ok(u8::from_bytes(&value)?),
+            None => Ok(1)
+        }
+    }
+
+    pub fn set_version(&mut self, version: u8) -> Result<()> {
+        self.db.insert(VERSION_KEY, &[version])?;
+        Ok(())
     }
 
     // save the encrypted form of the master key
@@ -519,4 +758,53 @@ impl Storage {
         self.db.insert(SALT_KEY, salt)?;
         Ok(())
     }
+
+    // snapshot of every tree as stored on disk, values are still encrypted
+    // so it can only be opened with the password of the wallet
+    pub fn export(&self) -> Result<Vec<u8>> {
+        self.db.flush()?;
+        let mut writer = Writer::new();
+        let names = self.db.tree_names();
+        writer.write_u16(names.len() as u16);
+        for name in names {
+            let tree = self.db.open_tree(&name)?;
+            writer.write_u16(name.len() as u16);
+            writer.write_bytes(&name);
+            writer.write_u32(&(tree.len() as u32));
+            for entry in tree.iter() {
+                let (key, value) = entry?;
+                writer.write_u32(&(key.len() as u32));
+                writer.write_bytes(&key);
+                writer.write_u32(&(value.len() as u32));
+                writer.write_bytes(&value);
+            }
+        }
+        Ok(writer.bytes())
+    }
+
+    // write a snapshot created by `export` in a new storage
+    // the storage returned must be used to open the wallet, as its files stay locked for a short time once closed
+    pub fn import(name: String, snapshot: &[u8]) -> Result<Self> {
+        let db = sled::open(name)?;
+        let mut reader = Reader::new(snapshot);
+        let trees = reader.read_u16()?;
+        for _ in 0..trees {
+            let size = reader.read_u16()? as usize;
+            let tree = db.open_tree(reader.read_bytes_ref(size)?)?;
+            let entries = reader.read_u32()?;
+            for _ in 0..entries {
+                let size = reader.read_u32()? as usize;
+                let key = reader.read_bytes_ref(size)?.to_vec();
+                let size = reader.read_u32()? as usize;
+                tree.insert(key, reader.read_bytes_ref(size)?)?;
+            }
+        }
+
+        if reader.size() != 0 {
+            return Err(anyhow!("Invalid storage snapshot: {} bytes left", reader.size()))
+        }
+
+        db.flush()?;
+        Self::from_db(db)
+    }
 }
\ No newline at end of file
@@ -1,39 +1,57 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, mem};
 
 use xelis_common::{
-    transaction::{Transaction, TransactionType, EXTRA_DATA_LIMIT_SIZE},
+    transaction::{Transaction, TransactionType, Transfer, EXTRA_DATA_LIMIT_SIZE, MAX_TX_SIZE, MAX_TRANSFERS_PER_TX},
     utils::calculate_tx_fee,
-    serializer::{Writer, Serializer},
-    crypto::{key::{SIGNATURE_LENGTH, PublicKey, KeyPair}, hash::{Hash, hash}}, api::wallet::FeeBuilder
+    config::XELIS_ASSET,
+    name::get_name_registration_cost,
+    contract::MAX_CONTRACT_CODE_SIZE,
+    serializer::Serializer,
+    crypto::{key::{SIGNATURE_LENGTH, PublicKey, KeyPair, Signature}, hash::Hash}, api::{wallet::FeeBuilder, daemon::GetAccountPendingResult, FeeEstimation}
 };
 
 use crate::wallet::WalletError;
 
+// next nonce accepted by the daemon: the nonce on chain followed by the pending transactions of the account
+// queued transactions after a missing nonce are not counted so the gap is filled first
+pub fn get_next_safe_nonce(pending: &GetAccountPendingResult) -> u64 {
+    pending.transactions.iter().fold(pending.nonce, |next, entry| if entry.nonce == next { next + 1 } else { next })
+}
+
+fn create_transaction(owner: PublicKey, data: TransactionType, fee: u64, nonce: u64, sponsor: Option<PublicKey>, expiration: Option<u64>, signature: Signature) -> Transaction {
+    let mut tx = match sponsor {
+        Some(sponsor) => Transaction::new_sponsored(owner, data, fee, nonce, sponsor, signature),
+        None => Transaction::new(owner, data, fee, nonce, signature)
+    };
+    if let Some(expiration) = expiration {
+        tx.set_expiration(expiration);
+    }
+    tx
+}
+
 pub struct TransactionBuilder {
     owner: PublicKey,
     data: TransactionType,
     nonce: u64,
     fee_builder: FeeBuilder,
+    // account which will pay the fee
+    sponsor: Option<PublicKey>,
+    // topoheight above which the transaction can't be executed
+    expiration: Option<u64>
 }
 
 impl TransactionBuilder {
-    pub fn new(owner: PublicKey, data: TransactionType, nonce: u64, fee_builder: FeeBuilder) -> Self {
+    pub fn new(owner: PublicKey, data: TransactionType, nonce: u64, fee_builder: FeeBuilder, sponsor: Option<PublicKey>, expiration: Option<u64>) -> Self {
         Self {
             owner,
             data,
             nonce,
-            fee_builder
+            fee_builder,
+            sponsor,
+            expiration
         }
     }
 
-    fn serialize(&self) -> Writer {
-        let mut writer = Writer::new();
-        writer.write_u8(0);
-        self.owner.write(&mut writer);
-        self.data.write(&mut writer);
-        writer
-    }
-
     fn verify_fees_internal(&self, calculated_fees: u64) -> Result<u64, WalletError> {
         let provided_fees = match &self.fee_builder {
             FeeBuilder::Multiplier(multiplier) => (calculated_fees as f64  * multiplier) as u64,
@@ -64,7 +82,10 @@ impl TransactionBuilder {
                     *current += tx.amount; 
                 }
             },
-            TransactionType::DeployContract(_) => {}
+            TransactionType::DeployContract(_) => {},
+            TransactionType::RegisterName { duration, .. } => {
+                total_spent.insert(&XELIS_ASSET, get_name_registration_cost(*duration));
+            }
         }
 
         total_spent
@@ -82,29 +103,29 @@ impl TransactionBuilder {
         total_size
     }
 
-    fn estimate_fees_internal(&self, writer: &Writer) -> u64 {
-        // 8 represent the field 'fee' in bytes size
-        let total_bytes = SIGNATURE_LENGTH + 8 + writer.total_write();
-        let calculated_fees = calculate_tx_fee(total_bytes);
-        calculated_fees
+    // transaction with a placeholder signature, its size is the same as the signed one
+    fn create_unsigned(&self, fee: u64) -> Transaction {
+        let signature = Signature::from_bytes(&[0u8; SIGNATURE_LENGTH]).expect("valid placeholder signature");
+        create_transaction(self.owner.clone(), self.data.clone(), fee, self.nonce, self.sponsor.clone(), self.expiration, signature)
     }
 
+    // minimum fees based on the exact size of the signed transaction
     pub fn estimate_fees(&self) -> u64 {
-        let writer = self.serialize();
-        self.estimate_fees_internal(&writer)
+        calculate_tx_fee(self.create_unsigned(0).get_signed_size())
     }
 
-    pub fn build(self, keypair: &KeyPair) -> Result<Transaction, WalletError> {
-        if *keypair.get_public_key() != self.owner {
-            return Err(WalletError::InvalidKeyPair)
-        }
+    // fees which will be set in the transaction using the fee builder
+    pub fn get_fees(&self) -> Result<u64, WalletError> {
+        self.verify_fees_internal(self.estimate_fees())
+    }
 
+    pub fn verify(&self) -> Result<(), WalletError> {
         if let TransactionType::Transfer(txs) = &self.data {
             if txs.len() == 0 {
                 return Err(WalletError::ExpectedOneTx)
             }
 
-            if txs.len() > u8::MAX as usize {
+            if txs.len() > MAX_TRANSFERS_PER_TX {
                 return Err(WalletError::TooManyTx)
             }
 
@@ -120,15 +141,101 @@ impl TransactionBuilder {
             return Err(WalletError::ExtraDataTooBig(EXTRA_DATA_LIMIT_SIZE, extra_data_size))
         }
 
-        let mut writer = self.serialize();
-        let fee = self.verify_fees_internal(self.estimate_fees_internal(&writer))?;
-        writer.write_u64(&fee);
-        writer.write_u64(&self.nonce);
+        // its size is serialized on 2 bytes
+        if let TransactionType::DeployContract(code) = &self.data {
+            if code.len() > MAX_CONTRACT_CODE_SIZE {
+                return Err(WalletError::ContractCodeTooBig(MAX_CONTRACT_CODE_SIZE, code.len()))
+            }
+        }
+
+        let size = self.create_unsigned(0).get_signed_size();
+        if size > MAX_TX_SIZE {
+            return Err(WalletError::TransactionTooBig(MAX_TX_SIZE, size))
+        }
+
+        Ok(())
+    }
+
+    // split the transfers in as many transactions as needed to respect the consensus limits
+    // each transaction uses the next nonce and is built with the same fee builder, sponsor and expiration
+    // other transaction types are only verified
+    pub fn split(self) -> Result<Vec<Self>, WalletError> {
+        let Self { owner, data, nonce, fee_builder, sponsor, expiration } = self;
+        let transfers = match data {
+            TransactionType::Transfer(transfers) => transfers,
+            data => {
+                let builder = Self::new(owner, data, nonce, fee_builder, sponsor, expiration);
+                builder.verify()?;
+                return Ok(vec![builder])
+            }
+        };
+
+        if transfers.is_empty() {
+            return Err(WalletError::ExpectedOneTx)
+        }
+
+        // size of the signed transaction without any transfer
+        let base_size = Self::new(owner.clone(), TransactionType::Transfer(Vec::new()), nonce, fee_builder.clone(), sponsor.clone(), expiration)
+            .create_unsigned(0)
+            .get_signed_size();
+
+        let mut groups: Vec<Vec<Transfer>> = Vec::new();
+        let mut current: Vec<Transfer> = Vec::new();
+        let mut size = base_size;
+        let mut extra_data_size = 0;
+        for transfer in transfers {
+            let transfer_extra_data_size = transfer.extra_data.as_ref().map_or(0, Vec::len);
+            if transfer_extra_data_size > EXTRA_DATA_LIMIT_SIZE {
+                return Err(WalletError::ExtraDataTooBig(EXTRA_DATA_LIMIT_SIZE, transfer_extra_data_size))
+            }
+
+            let full = current.len() == MAX_TRANSFERS_PER_TX
+                || extra_data_size + transfer_extra_data_size > EXTRA_DATA_LIMIT_SIZE
+                || size + transfer.size() > MAX_TX_SIZE;
+            if full && !current.is_empty() {
+                groups.push(mem::take(&mut current));
+                size = base_size;
+                extra_data_size = 0;
+            }
+
+            size += transfer.size();
+            extra_data_size += transfer_extra_data_size;
+            current.push(transfer);
+        }
+        groups.push(current);
+
+        groups.into_iter().enumerate().map(|(i, transfers)| {
+            let builder = Self::new(owner.clone(), TransactionType::Transfer(transfers), nonce + i as u64, fee_builder.clone(), sponsor.clone(), expiration);
+            builder.verify()?;
+            Ok(builder)
+        }).collect()
+    }
+
+    // size and fee breakdown of the transaction without signing it
+    pub fn estimate(&self, mainnet: bool) -> Result<FeeEstimation, WalletError> {
+        self.verify()?;
+        let fee = self.get_fees()?;
+        Ok(FeeEstimation::new(&self.create_unsigned(fee), mainnet))
+    }
+
+    pub fn build(self, keypair: &KeyPair) -> Result<Transaction, WalletError> {
+        if *keypair.get_public_key() != self.owner {
+            return Err(WalletError::InvalidKeyPair)
+        }
+
+        self.verify()?;
+        let fee = self.get_fees()?;
+        let Self { owner, data, nonce, sponsor, expiration, .. } = self;
+        let sponsored = sponsor.is_some();
 
-        let signature = keypair.sign(hash(writer.as_bytes()).as_bytes());
-        let tx = Transaction::new(self.owner, self.data, fee, self.nonce, signature);
+        // the signature is not part of the signing hash, so any value can be used to compute it
+        let unsigned = create_transaction(owner, data, fee, nonce, sponsor.clone(), expiration, keypair.sign(&[]));
+        let signature = keypair.sign(unsigned.get_signing_hash().as_bytes());
+        let (owner, data) = unsigned.consume();
+        let tx = create_transaction(owner, data, fee, nonce, sponsor, expiration, signature);
 
-        if !tx.verify_signature() {
+        // the sponsor signature can only be verified once the sponsor signed it
+        if !sponsored && !tx.verify_signature() {
             return Err(WalletError::InvalidSignature)
         }
 
@@ -1,31 +1,44 @@
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Error, Context};
+use indexmap::IndexMap;
+use serde::Serialize;
 use serde_json::{Value, json};
 use tokio::sync::{Mutex, RwLock};
-use xelis_common::api::DataElement;
-use xelis_common::api::wallet::FeeBuilder;
+use xelis_common::api::daemon::{TransactionAcceptanceStatus, GetSyncStatusResult};
+use xelis_common::api::{DataElement, FeeEstimation};
+use xelis_common::api::wallet::{AuditEvent, FeeBuilder, BalanceProof, BalanceProofData, ProvenBalance, Invoice, InvoiceState, MAX_INVOICE_MEMO_SIZE, Webhook, WebhookDelivery, WebhookEvent, WebhookPayload, MAX_WEBHOOK_URL_SIZE, get_webhook_signing_hash};
 use xelis_common::config::{XELIS_ASSET, COIN_DECIMALS};
 use xelis_common::crypto::address::Address;
-use xelis_common::crypto::hash::Hash;
+use xelis_common::name::parse_name_recipient;
+use xelis_common::crypto::hash::{Hash, Hashable, HASH_SIZE};
 use xelis_common::crypto::key::{KeyPair, PublicKey};
+use xelis_common::crypto::threshold::KeyShare;
 use xelis_common::rpc_server::{RpcRequest, InternalRpcError, RpcResponseError};
-use xelis_common::utils::{format_xelis, format_coin};
+use xelis_common::utils::{format_xelis, format_coin, get_current_time};
 use xelis_common::network::Network;
 use xelis_common::serializer::{Serializer, Writer};
 use xelis_common::transaction::{TransactionType, Transfer, Transaction, EXTRA_DATA_LIMIT_SIZE};
 use crate::api::XSWDNodeMethodHandler;
 use crate::cipher::Cipher;
-use crate::config::{PASSWORD_ALGORITHM, PASSWORD_HASH_SIZE, SALT_SIZE};
+use crate::config::{PASSWORD_ALGORITHM, PASSWORD_HASH_SIZE, SALT_SIZE, DEFAULT_FEE_BUMP_PERCENT};
 use crate::mnemonics;
 use crate::network_handler::{NetworkHandler, SharedNetworkHandler, NetworkError};
-use crate::storage::{EncryptedStorage, Storage};
-use crate::transaction_builder::TransactionBuilder;
+use crate::daemon_api::{DaemonAPI, DaemonConnectionConfig, NodeTrust};
+use crate::storage::{EncryptedStorage, Storage, STORAGE_VERSION};
+use crate::transaction_builder::{TransactionBuilder, get_next_safe_nonce};
+use crate::webhook::WebhookManager;
+use crate::cosigner::{Cosigner, CosignerPolicy};
+use crate::backup::{BackupManager, BackupVersion};
+use crate::privacy::PrivacyReport;
 use chacha20poly1305::{aead::OsRng, Error as CryptoError};
 use rand::RngCore;
 use thiserror::Error;
-use log::{error, debug};
+use log::{error, debug, info, warn};
 
 #[cfg(feature = "api_server")]
 use {
@@ -47,7 +60,7 @@ use {
         colorize_string,
         colorize_str
     },
-    xelis_common::rpc_server::RPCHandler
+    xelis_common::rpc_server::{RPCHandler, HttpServerConfig}
 };
 
 #[derive(Error, Debug)]
@@ -60,6 +73,8 @@ pub enum WalletError {
     ExpectedOneTx,
     #[error("Too many txs included max is {}", u8::MAX)]
     TooManyTx,
+    #[error("Transaction is too big, expected maximum {} bytes but got {} bytes", _0, _1)]
+    TransactionTooBig(usize, usize),
     #[error("Transaction owner is the receiver")]
     TxOwnerIsReceiver,
     #[error("Error from crypto: {}", _0)]
@@ -84,12 +99,28 @@ pub enum WalletError {
     NotEnoughFundsForFee(u64, u64),
     #[error("Invalid address params")]
     InvalidAddressParams,
+    #[error("Name {} resolves to an address of another network", _0)]
+    InvalidNameNetwork(String),
     #[error("Invalid extra data in this transaction, expected maximum {} bytes but got {} bytes", _0, _1)]
     ExtraDataTooBig(usize, usize),
+    #[error("Contract code is too big, expected maximum {} bytes but got {} bytes", _0, _1)]
+    ContractCodeTooBig(usize, usize),
     #[error("Wallet is not in online mode")]
     NotOnlineMode,
+    #[error("Transaction was rejected by daemon: {}", _0)]
+    TransactionRejected(String),
+    #[error("Reference {} was already used for transaction {}", _0, _1)]
+    TransactionReferenceUsed(String, Hash),
+    #[error("Transaction {} is not in the mempool of the daemon", _0)]
+    TransactionNotInMempool(Hash),
+    #[error("Transaction {} was not built by this wallet", _0)]
+    NotTransactionOwner(Hash),
+    #[error("Transaction {} is sponsored and can't be replaced", _0)]
+    SponsoredTransactionReplacement(Hash),
     #[error("Wallet is already in online mode")]
     AlreadyOnlineMode,
+    #[error("No fallback daemon is reachable")]
+    NoFallbackDaemonReachable,
     #[error("Asset is already present on disk")]
     AssetAlreadyRegistered,
     #[error("Topoheight is too high to rescan")]
@@ -104,8 +135,34 @@ pub enum WalletError {
     RPCServerAlreadyRunning,
     #[error("Invalid fees provided, minimum fees calculated: {}, provided: {}", format_xelis(*_0), format_xelis(*_1))]
     InvalidFeeProvided(u64, u64),
+    #[error("This wallet is not the sponsor of the transaction")]
+    InvalidSponsor,
     #[error("Wallet name cannot be empty")]
     EmptyName,
+    #[error("Invoice amount cannot be zero")]
+    InvalidInvoiceAmount,
+    #[error("Invoice memo is too long, expected maximum {} bytes but got {} bytes", _0, _1)]
+    InvoiceMemoTooLong(usize, usize),
+    #[error("Asset {} is not tracked by this wallet", _0)]
+    AssetNotTracked(Hash),
+    #[error("Invalid webhook URL, expected an http or https URL")]
+    InvalidWebhookUrl,
+    #[error("Webhook URL is too long, expected maximum {} bytes but got {} bytes", _0, _1)]
+    WebhookUrlTooLong(usize, usize),
+    #[error("Webhook confirmations cannot be zero")]
+    InvalidWebhookConfirmations,
+    #[error("A key share is already saved in this wallet")]
+    KeyShareAlreadyImported,
+    #[error("No key share saved in this wallet")]
+    NoKeyShare,
+    #[error("Co-signer mode is not enabled")]
+    CosignerNotEnabled,
+    #[error("Backups are not enabled")]
+    BackupsNotEnabled,
+    #[error("Backups are already enabled")]
+    BackupsAlreadyEnabled,
+    #[error("Wallet storage is in version {} but only versions up to {} are supported, please update your wallet", _0, _1)]
+    UnsupportedStorageVersion(u8, u8),
     #[cfg(feature = "api_server")]
     #[error("No handler available for this request")]
     NoHandlerAvailable,
@@ -120,8 +177,20 @@ pub struct Wallet {
     keypair: KeyPair,
     // network handler for online mode to keep wallet synced
     network_handler: Mutex<Option<SharedNetworkHandler>>,
+    // daemons used in order when the current one is not trusted anymore
+    fallback_daemons: Mutex<Vec<String>>,
+    // TLS and authentication options used to connect to the daemons
+    daemon_connection: Mutex<DaemonConnectionConfig>,
     // network on which we are connected
     network: Network,
+    // callbacks sent to the webhooks
+    webhooks: WebhookManager,
+    // set when the wallet co-signs the transactions of a shared account
+    cosigner: RwLock<Option<Cosigner>>,
+    // uploads the encrypted storage to a backup provider
+    backups: RwLock<Option<Arc<BackupManager>>>,
+    // last transactions built with a client reference, and if they were broadcasted
+    tx_references: Mutex<IndexMap<String, (Transaction, bool)>>,
     // RPC Server
     #[cfg(feature = "api_server")]
     api_server: Mutex<Option<APIServer<Arc<Self>>>>,
@@ -143,7 +212,13 @@ impl Wallet {
             storage: RwLock::new(storage),
             keypair,
             network_handler: Mutex::new(None),
+            fallback_daemons: Mutex::new(Vec::new()),
+            daemon_connection: Mutex::new(DaemonConnectionConfig::default()),
             network,
+            webhooks: WebhookManager::default(),
+            cosigner: RwLock::new(None),
+            backups: RwLock::new(None),
+            tx_references: Mutex::new(IndexMap::new()),
             #[cfg(feature = "api_server")]
             api_server: Mutex::new(None),
             prompt: RwLock::new(None)
@@ -158,6 +233,7 @@ impl Wallet {
         }
 
         // generate random keypair or recover it from seed
+        let recovered = seed.is_some();
         let keypair = if let Some(seed) = seed {
         debug!("Retrieving keypair from seed...");
         let words: Vec<String> = seed.split_whitespace().map(str::to_string).collect();
@@ -178,6 +254,8 @@ impl Wallet {
 
         debug!("Creating storage for {}", name);
         let mut inner = Storage::new(name)?;
+        // a new storage is already in the current format
+        inner.set_version(STORAGE_VERSION)?;
 
         // generate the Cipher
         let cipher = Cipher::new(&hashed_password, None)?;
@@ -203,6 +281,7 @@ impl Wallet {
         let mut storage = EncryptedStorage::new(inner, &master_key, storage_salt, network)?;
 
         storage.set_keypair(&keypair)?;
+        storage.add_audit_entry(AuditEvent::Unlock, if recovered { "wallet recovered from seed" } else { "wallet created" }.to_owned())?;
 
         Ok(Self::new(storage, keypair, network))
     }
@@ -214,7 +293,11 @@ impl Wallet {
 
         debug!("Creating storage for {}", name);
         let storage = Storage::new(name)?;
-        
+        Self::open_storage(storage, password, network)
+    }
+
+    // open a wallet from its public storage, e.g. just restored from a backup
+    pub fn open_storage(storage: Storage, password: String, network: Network) -> Result<Arc<Self>, Error> {
         // get password salt for KDF
         debug!("Retrieving password salt from public storage");
         let salt = storage.get_password_salt()?;
@@ -244,6 +327,7 @@ impl Wallet {
         let storage = EncryptedStorage::new(storage, &master_key, salt, network)?;
         debug!("Retrieving keypair from encrypted storage");
         let keypair =  storage.get_keypair()?;
+        storage.add_audit_entry(AuditEvent::Unlock, "wallet opened".to_owned())?;
 
         Ok(Self::new(storage, keypair, network))
     }
@@ -257,7 +341,7 @@ impl Wallet {
     }
 
     #[cfg(feature = "api_server")]
-    pub async fn enable_rpc_server(self: &Arc<Self>, bind_address: String, config: Option<AuthConfig>) -> Result<(), Error> {
+    pub async fn enable_rpc_server(self: &Arc<Self>, bind_address: String, config: Option<AuthConfig>, http_config: HttpServerConfig) -> Result<(), Error> {
         let mut lock = self.api_server.lock().await;
         if lock.is_some() {
             return Err(WalletError::RPCServerAlreadyRunning.into())
@@ -265,7 +349,7 @@ impl Wallet {
         let mut rpc_handler = RPCHandler::new(self.clone());
         register_rpc_methods(&mut rpc_handler);
 
-        let rpc_server = WalletRpcServer::new(bind_address, rpc_handler, config).await?;
+        let rpc_server = WalletRpcServer::new(bind_address, rpc_handler, config, http_config).await?;
         *lock = Some(APIServer::RPCServer(rpc_server));
         Ok(())
     }
@@ -388,12 +472,20 @@ impl Wallet {
         Ok(transfer)
     }
 
-    // create the final transaction with calculated fees and signature
-    // also check that we have enough funds for the transaction
-    pub fn create_transaction(&self, storage: &EncryptedStorage, transaction_type: TransactionType, fee: FeeBuilder) -> Result<Transaction, Error> {
-        let nonce = storage.get_nonce().unwrap_or(0);
-        let builder = TransactionBuilder::new(self.keypair.get_public_key().clone(), transaction_type, nonce, fee);
-        let assets_spent: HashMap<&Hash, u64> = builder.total_spent();
+    // check that we have enough funds for every assets spent and the fees of all the transactions
+    // fees are not counted if they are paid by a sponsor
+    fn verify_funds(&self, storage: &EncryptedStorage, builders: &[TransactionBuilder], sponsored: bool) -> Result<(), Error> {
+        let mut assets_spent: HashMap<&Hash, u64> = HashMap::new();
+        let mut fees = 0;
+        for builder in builders {
+            for (asset, amount) in builder.total_spent() {
+                *assets_spent.entry(asset).or_insert(0) += amount;
+            }
+
+            if !sponsored {
+                fees += builder.get_fees()?;
+            }
+        }
 
         // check that we have enough balance for every assets spent
         for (asset, amount) in &assets_spent {
@@ -406,23 +498,168 @@ impl Wallet {
         }
 
         // now we have to check that we have enough funds for spent + fees
-        let total_native_spent = assets_spent.get(&XELIS_ASSET).unwrap_or(&0) +  builder.estimate_fees();
+        let total_native_spent = assets_spent.get(&XELIS_ASSET).unwrap_or(&0) + fees;
         let native_balance = storage.get_balance_for(&XELIS_ASSET).unwrap_or(0);
         if total_native_spent > native_balance {
             return Err(WalletError::NotEnoughFundsForFee(native_balance, total_native_spent).into())
         }
 
-        Ok(builder.build(&self.keypair)?)
+        Ok(())
+    }
+
+    // create the final transaction with calculated fees and signature
+    // also check that we have enough funds for the transaction
+    // if a sponsor is set, the fees will be paid by it once it signed the transaction
+    // nonce should be retrieved using `get_next_nonce` before locking the storage
+    // if an expiration topoheight is set, the transaction can't be executed above it
+    pub fn create_transaction(&self, storage: &EncryptedStorage, transaction_type: TransactionType, fee: FeeBuilder, sponsor: Option<PublicKey>, nonce: u64, expiration: Option<u64>) -> Result<Transaction, Error> {
+        if sponsor.as_ref() == Some(self.keypair.get_public_key()) {
+            return Err(WalletError::InvalidSponsor.into())
+        }
+
+        let sponsored = sponsor.is_some();
+        let builder = TransactionBuilder::new(self.keypair.get_public_key().clone(), transaction_type, nonce, fee, sponsor, expiration);
+        self.verify_funds(storage, std::slice::from_ref(&builder), sponsored)?;
+
+        let transaction = builder.build(&self.keypair)?;
+        storage.add_audit_entry(AuditEvent::Sign, format!("transaction {}", transaction.hash()))?;
+        Ok(transaction)
+    }
+
+    // same as `create_transaction` but the transfers are split in several transactions if they don't fit in one
+    // they use consecutive nonces starting at the one provided and must be submitted in this order
+    pub fn create_transactions(&self, storage: &EncryptedStorage, transaction_type: TransactionType, fee: FeeBuilder, sponsor: Option<PublicKey>, nonce: u64, expiration: Option<u64>) -> Result<Vec<Transaction>, Error> {
+        if sponsor.as_ref() == Some(self.keypair.get_public_key()) {
+            return Err(WalletError::InvalidSponsor.into())
+        }
+
+        let sponsored = sponsor.is_some();
+        let builders = TransactionBuilder::new(self.keypair.get_public_key().clone(), transaction_type, nonce, fee, sponsor, expiration).split()?;
+        self.verify_funds(storage, &builders, sponsored)?;
+
+        let mut transactions = Vec::with_capacity(builders.len());
+        for builder in builders {
+            let transaction = builder.build(&self.keypair)?;
+            storage.add_audit_entry(AuditEvent::Sign, format!("transaction {}", transaction.hash()))?;
+            transactions.push(transaction);
+        }
+        Ok(transactions)
+    }
+
+    // size and fee breakdown of a transaction before building it, nothing is signed
+    // balances are not checked so the result can be used to compute the maximum amount to send
+    pub fn estimate_fees(&self, transaction_type: TransactionType, fee: FeeBuilder, sponsor: Option<PublicKey>, expiration: Option<u64>) -> Result<FeeEstimation, Error> {
+        if sponsor.as_ref() == Some(self.keypair.get_public_key()) {
+            return Err(WalletError::InvalidSponsor.into())
+        }
+
+        // the nonce has a fixed size, it doesn't change the estimation
+        let builder = TransactionBuilder::new(self.keypair.get_public_key().clone(), transaction_type, 0, fee, sponsor, expiration);
+        Ok(builder.estimate(self.network.is_mainnet())?)
+    }
+
+    // sign a transaction built by another wallet to pay its fees
+    // we must have enough funds to pay them
+    pub fn sponsor_transaction(&self, storage: &EncryptedStorage, mut transaction: Transaction) -> Result<Transaction, Error> {
+        if !transaction.sign_as_sponsor(&self.keypair) {
+            return Err(WalletError::InvalidSponsor.into())
+        }
+
+        if !transaction.verify_signature() {
+            return Err(WalletError::InvalidSignature.into())
+        }
+
+        let native_balance = storage.get_balance_for(&XELIS_ASSET).unwrap_or(0);
+        if transaction.get_fee() > native_balance {
+            return Err(WalletError::NotEnoughFundsForFee(native_balance, transaction.get_fee()).into())
+        }
+
+        storage.add_audit_entry(AuditEvent::Sign, format!("sponsored transaction {}", transaction.hash()))?;
+        Ok(transaction)
+    }
+
+    // replace one of our transactions waiting in the daemon mempool using the same nonce and a higher fee
+    // a cancelled transaction is replaced by a burn of the smallest amount possible, transfers to ourself are rejected
+    // by default, the fee is increased by DEFAULT_FEE_BUMP_PERCENT
+    // the replacement is broadcasted and saved so the history entry of the transaction executed lists the ones it replaced
+    pub async fn replace_transaction(&self, hash: &Hash, cancel: bool, fee: Option<u64>) -> Result<Transaction, Error> {
+        let transaction = {
+            let network_handler = self.network_handler.lock().await;
+            let api = network_handler.as_ref().ok_or(WalletError::NotOnlineMode)?.get_api();
+            api.get_mempool_transaction(hash).await?.ok_or_else(|| WalletError::TransactionNotInMempool(hash.clone()))?
+        };
+
+        if transaction.get_owner() != self.keypair.get_public_key() {
+            return Err(WalletError::NotTransactionOwner(hash.clone()).into())
+        }
+
+        // the replacement would have to be signed again by the sponsor
+        if transaction.get_sponsor().is_some() {
+            return Err(WalletError::SponsoredTransactionReplacement(hash.clone()).into())
+        }
+
+        // the daemon only replaces a transaction by one with a higher fee
+        let min_fee = transaction.get_fee() + 1;
+        let fee = match fee {
+            Some(fee) if fee < min_fee => return Err(WalletError::InvalidFeeProvided(min_fee, fee).into()),
+            Some(fee) => fee,
+            None => min_fee.max(transaction.get_fee() + transaction.get_fee() * DEFAULT_FEE_BUMP_PERCENT / 100)
+        };
+
+        let data = if cancel {
+            TransactionType::Burn { asset: XELIS_ASSET, amount: 1 }
+        } else {
+            transaction.get_data().clone()
+        };
+
+        let replacement = {
+            let storage = self.storage.read().await;
+            self.create_transaction(&storage, data, FeeBuilder::Value(fee), None, transaction.get_nonce(), transaction.get_expiration())?
+        };
+        self.submit_transaction(&replacement).await?;
+
+        let replacement_hash = replacement.hash();
+        let mut storage = self.storage.write().await;
+        storage.set_replaced_transaction(&replacement_hash, hash)?;
+        info!("Transaction {} replaced by {}", hash, replacement_hash);
+
+        Ok(replacement)
     }
 
     // submit a transaction to the network through the connection to daemon
     // returns error if the wallet is in offline mode
+    // client reference -> transaction built with it and if it was broadcasted
+    pub fn get_tx_references(&self) -> &Mutex<IndexMap<String, (Transaction, bool)>> {
+        &self.tx_references
+    }
+
     pub async fn submit_transaction(&self, transaction: &Transaction) -> Result<(), WalletError> {
+        self.submit_transaction_with_reference(transaction, None).await
+    }
+
+    // the daemon doesn't add a transaction submitted with a reference already used
+    pub async fn submit_transaction_with_reference(&self, transaction: &Transaction, reference: Option<&str>) -> Result<(), WalletError> {
         let network_handler = self.network_handler.lock().await;
         if let Some(network_handler) = network_handler.as_ref() {
-            network_handler.get_api().submit_transaction(transaction).await?;
+            let result = network_handler.get_api().submit_transaction(transaction, reference).await?;
+            if result.status == TransactionAcceptanceStatus::Rejected {
+                return Err(WalletError::TransactionRejected(result.reason.unwrap_or_default()))
+            }
+
+            if let Some(reference) = reference {
+                if result.hash != transaction.hash() {
+                    return Err(WalletError::TransactionReferenceUsed(reference.to_owned(), result.hash))
+                }
+            }
+
             let mut storage = self.storage.write().await;
-            storage.set_nonce(transaction.get_nonce() + 1)?;
+            // a sponsored transaction may be broadcasted by its sponsor
+            // a replacement reuses the nonce of a pending transaction
+            if transaction.get_owner() == self.keypair.get_public_key() {
+                let nonce = storage.get_nonce().unwrap_or(0).max(transaction.get_nonce() + 1);
+                storage.set_nonce(nonce)?;
+            }
+            storage.add_audit_entry(AuditEvent::Send, format!("transaction {}", result.hash))?;
             Ok(())
         } else {
             Err(WalletError::NotOnlineMode)
@@ -430,14 +667,24 @@ impl Wallet {
     }
 
     // set wallet in online mode: start a communication task which will keep the wallet synced
-    pub async fn set_online_mode(self: &Arc<Self>, daemon_address: &String) -> Result<(), Error> {
+    // if a node key is set, every response of the daemon must be signed by it
+    pub async fn set_online_mode(self: &Arc<Self>, daemon_address: &String, node_key: Option<PublicKey>) -> Result<(), Error> {
+        let api = {
+            let config = self.daemon_connection.lock().await;
+            DaemonAPI::with_config(format!("{}/json_rpc", daemon_address), node_key, &config)?
+        };
+        self.set_online_mode_with_api(daemon_address.clone(), api).await
+    }
+
+    // connect using this API, the daemon address is only used to display it
+    pub async fn set_online_mode_with_api(self: &Arc<Self>, daemon_address: String, api: DaemonAPI) -> Result<(), Error> {
         if self.is_online().await {
             // user have to set in offline mode himself first
             return Err(WalletError::AlreadyOnlineMode.into())
         }
 
         // create the network handler
-        let network_handler = NetworkHandler::new(Arc::clone(&self), daemon_address).await?;
+        let network_handler = NetworkHandler::with_api(Arc::clone(self), daemon_address, api).await?;
         // start the task
         network_handler.start().await?;
         *self.network_handler.lock().await = Some(network_handler);
@@ -445,6 +692,55 @@ impl Wallet {
         Ok(())
     }
 
+    // options used for the next connections to the daemon and the fallback daemons
+    pub async fn set_daemon_connection_config(&self, config: DaemonConnectionConfig) {
+        *self.daemon_connection.lock().await = config;
+    }
+
+    pub async fn set_fallback_daemons(&self, daemon_addresses: Vec<String>) {
+        *self.fallback_daemons.lock().await = daemon_addresses;
+    }
+
+    pub async fn has_fallback_daemon(&self) -> bool {
+        !self.fallback_daemons.lock().await.is_empty()
+    }
+
+    // replace the current daemon by the first fallback daemon reachable
+    // the current one is moved at the end of the fallback daemons
+    // fallback daemons responses are not required to be signed as their node keys are unknown
+    // the future is boxed as it is spawned from the syncing task of the network handler, which is started here
+    pub fn switch_to_fallback_daemon(self: Arc<Self>) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        Box::pin(async move {
+            let mut handler = self.network_handler.lock().await;
+            let previous = handler.take().ok_or(WalletError::NotOnlineMode)?;
+            previous.stop().await?;
+
+            let config = self.daemon_connection.lock().await.clone();
+            let mut fallback_daemons = self.fallback_daemons.lock().await;
+            for _ in 0..fallback_daemons.len() {
+                let daemon_address = fallback_daemons.remove(0);
+                match NetworkHandler::new(Arc::clone(&self), &daemon_address, None, &config).await {
+                    Ok(network_handler) => {
+                        network_handler.start().await?;
+                        *handler = Some(network_handler);
+                        fallback_daemons.push(previous.get_daemon_address().clone());
+                        warn!("Switched from daemon {} to fallback daemon {}", previous.get_daemon_address(), daemon_address);
+                        return Ok(())
+                    },
+                    Err(e) => {
+                        warn!("Fallback daemon {} is not reachable: {:#}", daemon_address, e);
+                        fallback_daemons.push(daemon_address);
+                    }
+                }
+            }
+
+            // no fallback daemon reachable, keep the current one
+            previous.start().await?;
+            *handler = Some(previous);
+            Err(WalletError::NoFallbackDaemonReachable.into())
+        })
+    }
+
     // set wallet in offline mode: stop communication task if exists
     pub async fn set_offline_mode(&self) -> Result<(), WalletError> {
         let mut handler = self.network_handler.lock().await;
@@ -504,6 +800,38 @@ impl Wallet {
         }
     }
 
+    // address and trust of the daemon we are connected to
+    pub async fn get_daemon_trust(&self) -> Option<(String, NodeTrust)> {
+        let network_handler = self.network_handler.lock().await;
+        let network_handler = network_handler.as_ref()?;
+        Some((network_handler.get_daemon_address().clone(), network_handler.get_api().get_trust().await))
+    }
+
+    // sync status of the daemon we are connected to, if it supports it
+    pub async fn get_daemon_sync_status(&self) -> Option<GetSyncStatusResult> {
+        if let Some(network_handler) = self.network_handler.lock().await.as_ref() {
+            network_handler.get_daemon_sync_status().await
+        } else {
+            None
+        }
+    }
+
+    // a recipient in the `name.xel` format is resolved by the daemon, any other recipient must be an address
+    pub async fn resolve_recipient(&self, recipient: &str) -> Result<Address, WalletError> {
+        let Some(name) = parse_name_recipient(recipient) else {
+            return Ok(Address::from_string(&recipient.to_owned())?)
+        };
+
+        let network_handler = self.network_handler.lock().await;
+        let network_handler = network_handler.as_ref().ok_or(WalletError::NotOnlineMode)?;
+        let result = network_handler.get_api().resolve_name(name).await?;
+        if result.owner.is_mainnet() != self.network.is_mainnet() {
+            return Err(WalletError::InvalidNameNetwork(name.to_owned()))
+        }
+
+        Ok(result.owner)
+    }
+
     // this function allow to user to get the network handler in case in want to stay in online mode
     // but want to pause / resume the syncing task through start/stop functions from it
     pub async fn get_network_handler(&self) -> &Mutex<Option<Arc<NetworkHandler>>> {
@@ -522,8 +850,226 @@ impl Wallet {
         self.keypair.get_public_key().to_address_with(self.get_network().is_mainnet(), data)
     }
 
-    pub fn get_seed(&self, language_index: usize) -> Result<String, Error> {
+    // analyze the whole history for address reuse, linkable extra data and timing patterns
+    pub async fn get_privacy_report(&self) -> Result<PrivacyReport, Error> {
+        let storage = self.storage.read().await;
+        let transactions = storage.get_transactions()?;
+        Ok(PrivacyReport::analyze(&transactions, self.get_network().is_mainnet()))
+    }
+
+    // create a payment request, its random ID is integrated in the address to pay it
+    pub async fn create_invoice(&self, asset: Hash, amount: u64, expires_in: u64, memo: Option<String>) -> Result<Invoice, Error> {
+        if amount == 0 {
+            return Err(WalletError::InvalidInvoiceAmount.into())
+        }
+
+        if let Some(memo) = &memo {
+            if memo.len() > MAX_INVOICE_MEMO_SIZE {
+                return Err(WalletError::InvoiceMemoTooLong(MAX_INVOICE_MEMO_SIZE, memo.len()).into())
+            }
+        }
+
+        let mut storage = self.storage.write().await;
+        if !storage.contains_asset(&asset)? {
+            return Err(WalletError::AssetNotTracked(asset).into())
+        }
+
+        let id = loop {
+            let mut bytes = [0u8; HASH_SIZE];
+            OsRng.fill_bytes(&mut bytes);
+            let id = Hash::new(bytes);
+            if !storage.has_invoice(&id)? {
+                break id
+            }
+        };
+
+        let created_at = get_current_time();
+        let invoice = Invoice {
+            id,
+            asset,
+            amount,
+            received: 0,
+            created_at,
+            expiration: created_at.saturating_add(expires_in),
+            memo,
+            state: InvoiceState::Pending,
+            transactions: Vec::new()
+        };
+        storage.save_invoice(&invoice)?;
+
+        Ok(invoice)
+    }
+
+    // integrated address to use to pay this invoice
+    pub fn get_invoice_address(&self, invoice: &Invoice) -> Address {
+        self.get_address_with(invoice.get_payment_data())
+    }
+
+    // only the incoming transactions after the current topoheight are notified to the new webhook
+    pub async fn add_webhook(&self, url: String, confirmations: u64) -> Result<Webhook, Error> {
+        if url.len() > MAX_WEBHOOK_URL_SIZE {
+            return Err(WalletError::WebhookUrlTooLong(MAX_WEBHOOK_URL_SIZE, url.len()).into())
+        }
+
+        match reqwest::Url::parse(&url) {
+            Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {},
+            _ => return Err(WalletError::InvalidWebhookUrl.into())
+        };
+
+        if confirmations == 0 {
+            return Err(WalletError::InvalidWebhookConfirmations.into())
+        }
+
+        let mut storage = self.storage.write().await;
+        let id = loop {
+            let mut bytes = [0u8; HASH_SIZE];
+            OsRng.fill_bytes(&mut bytes);
+            let id = Hash::new(bytes);
+            if !storage.has_webhook(&id)? {
+                break id
+            }
+        };
+
+        let webhook = Webhook {
+            id,
+            url,
+            confirmations,
+            topoheight: storage.get_daemon_topoheight().unwrap_or(0)
+        };
+        storage.save_webhook(&webhook)?;
+
+        Ok(webhook)
+    }
+
+    // returns false if the webhook doesn't exist
+    pub async fn remove_webhook(&self, id: &Hash) -> Result<bool, Error> {
+        let mut storage = self.storage.write().await;
+        if !storage.has_webhook(id)? {
+            return Ok(false)
+        }
+
+        storage.delete_webhook(id)?;
+        Ok(true)
+    }
+
+    // sign the payload with the wallet key and send it in background
+    pub fn send_webhook<T: Serialize>(&self, webhook: &Webhook, event: WebhookEvent, data: T) -> Result<(), Error> {
+        let mut bytes = [0u8; HASH_SIZE];
+        OsRng.fill_bytes(&mut bytes);
+        let payload = WebhookPayload {
+            id: Hash::new(bytes),
+            webhook: webhook.id.clone(),
+            event,
+            timestamp: get_current_time(),
+            data
+        };
+
+        let body = serde_json::to_vec(&payload)?;
+        let signature = self.keypair.sign(get_webhook_signing_hash(&body).as_bytes());
+        let delivery = WebhookDelivery {
+            id: payload.id,
+            webhook: payload.webhook,
+            url: webhook.url.clone(),
+            event,
+            created_at: payload.timestamp,
+            attempts: 0,
+            delivered: false,
+            status: None,
+            error: None
+        };
+        self.webhooks.send(delivery, body, signature);
+
+        Ok(())
+    }
+
+    pub fn get_webhook_manager(&self) -> &WebhookManager {
+        &self.webhooks
+    }
+
+    // save the share of a shared account key, it can't be replaced once saved
+    pub async fn import_key_share(&self, share: &KeyShare) -> Result<(), Error> {
+        let mut storage = self.storage.write().await;
+        if storage.has_key_share()? {
+            return Err(WalletError::KeyShareAlreadyImported.into())
+        }
+
+        storage.set_key_share(share)
+    }
+
+    // co-sign the transactions of the shared account using the key share saved
+    pub async fn enable_cosigner(&self, policy: CosignerPolicy) -> Result<(), Error> {
+        let share = {
+            let storage = self.storage.read().await;
+            if !storage.has_key_share()? {
+                return Err(WalletError::NoKeyShare.into())
+            }
+            storage.get_key_share()?
+        };
+
+        *self.cosigner.write().await = Some(Cosigner::new(share, policy));
+        Ok(())
+    }
+
+    pub fn get_cosigner(&self) -> &RwLock<Option<Cosigner>> {
+        &self.cosigner
+    }
+
+    // upload a backup of the storage periodically, starting now
+    // the task stops once the wallet is dropped
+    pub async fn enable_backups(self: &Arc<Self>, manager: BackupManager) -> Result<(), Error> {
+        let mut backups = self.backups.write().await;
+        if backups.is_some() {
+            return Err(WalletError::BackupsAlreadyEnabled.into())
+        }
+
+        let manager = Arc::new(manager);
+        let interval = Duration::from_secs(manager.get_interval());
+        *backups = Some(manager);
+
+        let wallet = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let Some(wallet) = wallet.upgrade() else {
+                    break
+                };
+
+                match wallet.backup(false).await {
+                    Ok(Some(version)) => info!("Backup {} uploaded", version.name),
+                    Ok(None) => {},
+                    Err(e) => error!("Error while uploading backup: {:#}", e)
+                };
+            }
+        });
+
+        Ok(())
+    }
+
+    // upload a backup of the storage, skipped if it didn't change since the last one unless forced
+    pub async fn backup(&self, force: bool) -> Result<Option<BackupVersion>, Error> {
+        let manager = self.get_backup_manager().await?;
+        // the storage can't be modified while its snapshot is created
+        let snapshot = {
+            let storage = self.storage.read().await;
+            storage.get_public_storage().export()?
+        };
+        let version = manager.upload(snapshot, force).await?;
+        if let Some(version) = &version {
+            let storage = self.storage.read().await;
+            storage.add_audit_entry(AuditEvent::Export, format!("backup {}", version.name))?;
+        }
+        Ok(version)
+    }
+
+    pub async fn get_backup_manager(&self) -> Result<Arc<BackupManager>, WalletError> {
+        self.backups.read().await.clone().ok_or(WalletError::BackupsNotEnabled)
+    }
+
+    pub async fn get_seed(&self, language_index: usize) -> Result<String, Error> {
         let words = mnemonics::key_to_words(self.keypair.get_private_key(), language_index)?;
+        let storage = self.storage.read().await;
+        storage.add_audit_entry(AuditEvent::Export, "seed".to_owned())?;
         Ok(words.join(" "))
     }
 
@@ -532,6 +1078,76 @@ impl Wallet {
         storage.get_nonce().unwrap_or(0)
     }
 
+    // nonce to use for the next transaction
+    // in online mode, it is checked against the nonce on chain and the pending transactions in the daemon mempool
+    // so a wallet restored or out of sync doesn't build a transaction rejected for an invalid nonce
+    pub async fn get_next_nonce(&self) -> u64 {
+        let local_nonce = self.get_nonce().await;
+        let network_handler = self.network_handler.lock().await;
+        let network_handler = match network_handler.as_ref() {
+            Some(network_handler) => network_handler,
+            None => return local_nonce
+        };
+
+        match network_handler.get_api().get_account_pending(&self.get_address()).await {
+            Ok(pending) => {
+                let nonce = get_next_safe_nonce(&pending);
+                if nonce != local_nonce {
+                    warn!("Local nonce {} differs from the next nonce {} expected by the daemon, using it", local_nonce, nonce);
+                }
+                nonce
+            },
+            Err(e) => {
+                warn!("Couldn't check nonce against the daemon, using local nonce {}: {:#}", local_nonce, e);
+                local_nonce
+            }
+        }
+    }
+
+    // sign the balances of all the wallet assets at this topoheight, the synced topoheight by default
+    // balances are retrieved from the daemon so they can be verified against any node
+    pub async fn create_balance_proof(&self, topoheight: Option<u64>) -> Result<BalanceProof, Error> {
+        let (assets, topoheight) = {
+            let storage = self.storage.read().await;
+            let topoheight = match topoheight {
+                Some(topoheight) => topoheight,
+                None => storage.get_daemon_topoheight()?
+            };
+            (storage.get_assets()?, topoheight)
+        };
+
+        let network_handler = self.network_handler.lock().await;
+        let api = network_handler.as_ref().ok_or(WalletError::NotOnlineMode)?.get_api();
+        let block = api.get_block_at_topoheight(topoheight).await?;
+        let address = self.get_address();
+
+        let mut balances = Vec::new();
+        for asset in assets {
+            if let Some((version_topoheight, version)) = api.get_balance_at_maximum_topoheight(&address, &asset, topoheight).await? {
+                if version.get_balance() > 0 {
+                    balances.push(ProvenBalance {
+                        asset,
+                        balance: version.get_balance(),
+                        topoheight: version_topoheight
+                    });
+                }
+            }
+        }
+        balances.sort_by(|a, b| a.asset.cmp(&b.asset));
+
+        let data = BalanceProofData {
+            address,
+            topoheight,
+            block_hash: block.data.hash.into_owned(),
+            block_timestamp: block.data.data.get_timestamp(),
+            balances
+        };
+        let signature = self.keypair.sign(data.get_signing_hash().as_bytes());
+        let storage = self.storage.read().await;
+        storage.add_audit_entry(AuditEvent::Sign, format!("balance proof at topoheight {}", topoheight))?;
+        Ok(BalanceProof { data, signature })
+    }
+
     pub fn get_storage(&self) -> &RwLock<EncryptedStorage> {
         &self.storage
     }
@@ -545,47 +1161,57 @@ impl Wallet {
 #[async_trait]
 impl XSWDPermissionHandler for Arc<Wallet> {
     async fn request_permission(&self, app_state: &AppStateShared, request: PermissionRequest<'_>) -> Result<PermissionResult, Error> {
-        if let Some(prompt) = self.prompt.read().await.as_ref() {
-            match request {
-                PermissionRequest::Application(signed) => {
-                    let mut message = format!("XSWD: Allow application {} ({}) to access your wallet\r\n(Y/N): ", app_state.get_name(), app_state.get_id());
-                    if signed {
-                        message = colorize_str(Color::BrightYellow, "NOTE: Application authorizaion was already approved previously.\r\n") + &message;
-                    }
-                    let accepted = prompt.read_valid_str_value(colorize_string(Color::Blue, &message), vec!["y", "n"]).await? == "y";
-                    if accepted {
-                        Ok(PermissionResult::Allow)
-                    } else {
-                        Ok(PermissionResult::Deny)
-                    }
-                },
-                PermissionRequest::Request(request) => {
-                    let params = if let Some(params) = &request.params {
-                        params.to_string()
-                    } else {
-                        "".to_string()
-                    };
-
-                    let message = format!(
-                        "XSWD: Request from {}: {}\r\nParams: {}\r\nDo you want to allow this request ?\r\n([A]llow / [D]eny / [AA] Always Allow / [AD] Always Deny): ",
-                        app_state.get_name(),
-                        request.method,
-                        params
-                    );
-
-                    let answer = prompt.read_valid_str_value(colorize_string(Color::Blue, &message), vec!["a", "d", "aa", "ad"]).await?;
-                    Ok(match answer.as_str() {
-                        "a" => PermissionResult::Allow,
-                        "d" => PermissionResult::Deny,
-                        "aa" => PermissionResult::AlwaysAllow,
-                        "ad" => PermissionResult::AlwaysDeny,
-                        _ => unreachable!()
-                    })
+        let prompt = self.prompt.read().await;
+        let Some(prompt) = prompt.as_ref() else {
+            return Err(WalletError::NoHandlerAvailable.into())
+        };
+
+        let (result, details) = match request {
+            PermissionRequest::Application(signed) => {
+                let mut message = format!("XSWD: Allow application {} ({}) to access your wallet\r\n(Y/N): ", app_state.get_name(), app_state.get_id());
+                if signed {
+                    message = colorize_str(Color::BrightYellow, "NOTE: Application authorizaion was already approved previously.\r\n") + &message;
                 }
+                let accepted = prompt.read_valid_str_value(colorize_string(Color::Blue, &message), vec!["y", "n"]).await? == "y";
+                let result = if accepted {
+                    PermissionResult::Allow
+                } else {
+                    PermissionResult::Deny
+                };
+                (result, format!("application {} ({})", app_state.get_name(), app_state.get_id()))
+            },
+            PermissionRequest::Request(request) => {
+                let params = if let Some(params) = &request.params {
+                    params.to_string()
+                } else {
+                    "".to_string()
+                };
+
+                let message = format!(
+                    "XSWD: Request from {}: {}\r\nParams: {}\r\nDo you want to allow this request ?\r\n([A]llow / [D]eny / [AA] Always Allow / [AD] Always Deny): ",
+                    app_state.get_name(),
+                    request.method,
+                    params
+                );
+
+                let answer = prompt.read_valid_str_value(colorize_string(Color::Blue, &message), vec!["a", "d", "aa", "ad"]).await?;
+                let result = match answer.as_str() {
+                    "a" => PermissionResult::Allow,
+                    "d" => PermissionResult::Deny,
+                    "aa" => PermissionResult::AlwaysAllow,
+                    "ad" => PermissionResult::AlwaysDeny,
+                    _ => unreachable!()
+                };
+                let always = if let PermissionResult::AlwaysAllow = result { "always " } else { "" };
+                (result, format!("{}{} for application {} ({})", always, request.method, app_state.get_name(), app_state.get_id()))
             }
-        } else {
-            Err(WalletError::NoHandlerAvailable.into())
+        };
+
+        if result.is_positive() {
+            let storage = self.storage.read().await;
+            storage.add_audit_entry(AuditEvent::PermissionGrant, details)?;
         }
+        Ok(result)
     }
 
     // there is a lock to acquire so it make it "single threaded"
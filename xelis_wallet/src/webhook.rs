@@ -0,0 +1,98 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+use log::{debug, info, warn};
+use reqwest::{Client, header::CONTENT_TYPE};
+use tokio::{sync::Mutex, time::sleep};
+use xelis_common::{
+    api::wallet::WebhookDelivery,
+    crypto::{hash::Hash, key::Signature}
+};
+use crate::config::{MAX_WEBHOOK_ATTEMPTS, MAX_WEBHOOK_DELIVERIES, WEBHOOK_RETRY_DELAY, WEBHOOK_TIMEOUT};
+
+// header containing the signature of the body by the wallet key
+pub const SIGNATURE_HEADER: &str = "X-Xelis-Signature";
+
+// Send the callbacks of the webhooks in background and keep the latest deliveries
+pub struct WebhookManager {
+    client: Client,
+    deliveries: Arc<Mutex<VecDeque<WebhookDelivery>>>
+}
+
+impl Default for WebhookManager {
+    fn default() -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(WEBHOOK_TIMEOUT))
+                .build()
+                .unwrap_or_default(),
+            deliveries: Arc::new(Mutex::new(VecDeque::new()))
+        }
+    }
+}
+
+impl WebhookManager {
+    // POST the body to the webhook url, retried with an exponential backoff until it is accepted
+    pub fn send(&self, delivery: WebhookDelivery, body: Vec<u8>, signature: Signature) {
+        let client = self.client.clone();
+        let deliveries = Arc::clone(&self.deliveries);
+        tokio::spawn(async move {
+            let id = delivery.id.clone();
+            let url = delivery.url.clone();
+            {
+                let mut deliveries = deliveries.lock().await;
+                if deliveries.len() >= MAX_WEBHOOK_DELIVERIES {
+                    deliveries.pop_front();
+                }
+                deliveries.push_back(delivery);
+            }
+
+            let mut delay = WEBHOOK_RETRY_DELAY;
+            for attempt in 1..=MAX_WEBHOOK_ATTEMPTS {
+                let res = client.post(&url)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(SIGNATURE_HEADER, signature.to_hex())
+                    .body(body.clone())
+                    .send().await;
+
+                let (status, error) = match res {
+                    Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+                    Ok(response) => (Some(response.status().as_u16()), Some(format!("HTTP status {}", response.status()))),
+                    Err(e) => (None, Some(e.to_string()))
+                };
+
+                let delivered = error.is_none();
+                match &error {
+                    None => info!("Webhook callback {} delivered to {}", id, url),
+                    Some(e) => warn!("Webhook callback {} to {} failed (attempt {}/{}): {}", id, url, attempt, MAX_WEBHOOK_ATTEMPTS, e)
+                };
+
+                // the delivery may have been removed from the logs by newer ones
+                if let Some(delivery) = deliveries.lock().await.iter_mut().find(|delivery| delivery.id == id) {
+                    delivery.attempts = attempt;
+                    delivery.delivered = delivered;
+                    delivery.status = status;
+                    delivery.error = error;
+                }
+
+                if delivered {
+                    return
+                }
+
+                if attempt < MAX_WEBHOOK_ATTEMPTS {
+                    debug!("Retrying webhook callback {} in {}s", id, delay);
+                    sleep(Duration::from_secs(delay)).await;
+                    delay *= 2;
+                }
+            }
+        });
+    }
+
+    // latest deliveries first
+    pub async fn get_deliveries(&self, webhook: Option<&Hash>) -> Vec<WebhookDelivery> {
+        let deliveries = self.deliveries.lock().await;
+        deliveries.iter()
+            .rev()
+            .filter(|delivery| webhook.is_none_or(|id| delivery.webhook == *id))
+            .cloned()
+            .collect()
+    }
+}
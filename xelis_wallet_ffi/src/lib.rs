@@ -0,0 +1,400 @@
+// C ABI over the wallet core so mobile apps (iOS, Android) can reuse it without re-implementing the protocol
+// The header can be generated using cbindgen with the `cbindgen.toml` of this crate
+//
+// Rules for the callers:
+// - strings are UTF-8 and NUL terminated, only the parameters documented as nullable can be NULL
+// - each string returned must be released using `xelis_string_free`, and each wallet using `xelis_wallet_free`
+// - on error, a function returns NULL or `XELIS_ERROR` and `xelis_last_error` returns the reason
+// - a wallet can be used from any thread, but must not be released while another call is using it
+// - a panic never unwinds into the caller, it is reported as an error
+
+// every function is unsafe because of the raw pointers given by the caller, the rules above apply to all of them
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    ffi::{CStr, CString},
+    fmt::Display,
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr::null_mut,
+    sync::Arc
+};
+use anyhow::{Context, Error, Result, bail};
+use tokio::runtime::Runtime;
+use xelis_common::{
+    api::{wallet::{BuildTransactionParams, FeeBuilder, TransactionResponse}, DataHash},
+    config::XELIS_ASSET,
+    crypto::hash::{Hash, Hashable},
+    network::Network,
+    serializer::Serializer,
+    transaction::Transaction,
+    utils::set_network_to
+};
+use xelis_wallet::wallet::Wallet;
+
+pub const XELIS_OK: i32 = 0;
+pub const XELIS_ERROR: i32 = -1;
+
+// Opaque handle given to the caller
+// the runtime runs the wallet tasks, such as the synchronization in online mode
+pub struct XelisWallet {
+    wallet: Arc<Wallet>,
+    runtime: Runtime
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error<E: Display>(e: E) {
+    let message = CString::new(format!("{:#}", e)).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+fn into_c_string(value: String) -> *mut c_char {
+    match CString::new(value) {
+        Ok(value) => value.into_raw(),
+        Err(e) => {
+            set_last_error(e);
+            null_mut()
+        }
+    }
+}
+
+// returns the status code expected by the caller
+fn into_code(result: Result<()>) -> i32 {
+    match result {
+        Ok(()) => XELIS_OK,
+        Err(e) => {
+            set_last_error(e);
+            XELIS_ERROR
+        }
+    }
+}
+
+fn into_ptr<T>(result: Result<T>, f: impl FnOnce(T) -> *mut c_char) -> *mut c_char {
+    match result {
+        Ok(value) => f(value),
+        Err(e) => {
+            set_last_error(e);
+            null_mut()
+        }
+    }
+}
+
+unsafe fn read_str<'a>(value: *const c_char) -> Result<&'a str> {
+    if value.is_null() {
+        bail!("Unexpected NULL string")
+    }
+    CStr::from_ptr(value).to_str().context("Invalid UTF-8 string")
+}
+
+unsafe fn read_optional_str<'a>(value: *const c_char) -> Result<Option<&'a str>> {
+    if value.is_null() {
+        return Ok(None)
+    }
+    read_str(value).map(Some)
+}
+
+unsafe fn read_wallet<'a>(wallet: *const XelisWallet) -> Result<&'a XelisWallet> {
+    wallet.as_ref().context("Unexpected NULL wallet")
+}
+
+fn create_handle(network: Network, f: impl FnOnce() -> Result<Arc<Wallet>, Error>) -> *mut XelisWallet {
+    let result = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Error while creating the runtime")
+        .and_then(|runtime| {
+            set_network_to(network);
+            // the wallet must be created inside the runtime as it may spawn tasks
+            let wallet = runtime.block_on(async { f() })?;
+            Ok(XelisWallet { wallet, runtime })
+        });
+
+    match result {
+        Ok(handle) => Box::into_raw(Box::new(handle)),
+        Err(e) => {
+            set_last_error(e);
+            null_mut()
+        }
+    }
+}
+
+// unwinding across the C ABI is undefined behavior, so each exported function runs in this guard
+// a panic is caught and reported as an error, and the default value is returned instead
+fn guard<T>(default: T, f: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(e) => {
+            let reason = e.downcast_ref::<&str>().copied()
+                .or_else(|| e.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown reason");
+            set_last_error(format!("Unexpected panic: {}", reason));
+            default
+        }
+    }
+}
+
+fn parse_network(network: &str) -> Result<Network> {
+    network.parse().map_err(Error::msg)
+}
+
+// returns the reason of the last error on this thread, NULL if none
+#[no_mangle]
+pub extern "C" fn xelis_last_error() -> *mut c_char {
+    guard(null_mut(), || {
+        LAST_ERROR.with(|error| match error.borrow().as_ref() {
+            Some(message) => message.clone().into_raw(),
+            None => null_mut()
+        })
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xelis_string_free(value: *mut c_char) {
+    guard((), || {
+        if !value.is_null() {
+            drop(CString::from_raw(value));
+        }
+    })
+}
+
+// create a new wallet, network is "mainnet", "testnet" or "dev"
+// seed is nullable: a new random key is generated if not set, otherwise the wallet is recovered from it
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_create(name: *const c_char, password: *const c_char, network: *const c_char, seed: *const c_char) -> *mut XelisWallet {
+    guard(null_mut(), || {
+        let params = (|| -> Result<_> {
+            Ok((read_str(name)?, read_str(password)?, parse_network(read_str(network)?)?, read_optional_str(seed)?))
+        })();
+
+        match params {
+            Ok((name, password, network, seed)) => create_handle(network, || Wallet::create(name.to_owned(), password.to_owned(), seed.map(str::to_owned), network)),
+            Err(e) => {
+                set_last_error(e);
+                null_mut()
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_open(name: *const c_char, password: *const c_char, network: *const c_char) -> *mut XelisWallet {
+    guard(null_mut(), || {
+        let params = (|| -> Result<_> {
+            Ok((read_str(name)?, read_str(password)?, parse_network(read_str(network)?)?))
+        })();
+
+        match params {
+            Ok((name, password, network)) => create_handle(network, || Wallet::open(name.to_owned(), password.to_owned(), network)),
+            Err(e) => {
+                set_last_error(e);
+                null_mut()
+            }
+        }
+    })
+}
+
+// stop the synchronization if online and close the wallet
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_free(wallet: *mut XelisWallet) {
+    guard((), || {
+        if wallet.is_null() {
+            return
+        }
+
+        let handle = Box::from_raw(wallet);
+        handle.runtime.block_on(async {
+            if handle.wallet.is_online().await {
+                if let Err(e) = handle.wallet.set_offline_mode().await {
+                    set_last_error(e);
+                }
+            }
+        });
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_get_address(wallet: *const XelisWallet) -> *mut c_char {
+    guard(null_mut(), || {
+        into_ptr(read_wallet(wallet), |handle| into_c_string(handle.wallet.get_address().to_string()))
+    })
+}
+
+// seed of the wallet in the language at this index, 0 for english
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_get_seed(wallet: *const XelisWallet, language_index: usize) -> *mut c_char {
+    guard(null_mut(), || {
+        let result = read_wallet(wallet).and_then(|handle| handle.runtime.block_on(handle.wallet.get_seed(language_index)));
+        into_ptr(result, into_c_string)
+    })
+}
+
+// connect to the daemon to synchronize the wallet in background
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_set_online_mode(wallet: *const XelisWallet, daemon_address: *const c_char) -> i32 {
+    guard(XELIS_ERROR, || {
+        into_code((|| {
+            let handle = read_wallet(wallet)?;
+            let daemon_address = read_str(daemon_address)?.to_owned();
+            handle.runtime.block_on(handle.wallet.set_online_mode(&daemon_address, None))
+        })())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_set_offline_mode(wallet: *const XelisWallet) -> i32 {
+    guard(XELIS_ERROR, || {
+        into_code((|| {
+            let handle = read_wallet(wallet)?;
+            handle.runtime.block_on(handle.wallet.set_offline_mode())?;
+            Ok(())
+        })())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_is_online(wallet: *const XelisWallet) -> bool {
+    guard(false, || {
+        match read_wallet(wallet) {
+            Ok(handle) => handle.runtime.block_on(handle.wallet.is_online()),
+            Err(e) => {
+                set_last_error(e);
+                false
+            }
+        }
+    })
+}
+
+// asset is nullable and is XELIS if not set, the balance is written in atomic units
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_get_balance(wallet: *const XelisWallet, asset: *const c_char, balance: *mut u64) -> i32 {
+    guard(XELIS_ERROR, || {
+        into_code((|| {
+            let handle = read_wallet(wallet)?;
+            let asset = match read_optional_str(asset)? {
+                Some(asset) => Hash::from_hex(asset.to_owned()).context("Invalid asset")?,
+                None => XELIS_ASSET
+            };
+            let balance = balance.as_mut().context("Unexpected NULL balance")?;
+
+            let storage = handle.runtime.block_on(handle.wallet.get_storage().read());
+            *balance = storage.get_balance_for(&asset).unwrap_or(0);
+            Ok(())
+        })())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_get_nonce(wallet: *const XelisWallet, nonce: *mut u64) -> i32 {
+    guard(XELIS_ERROR, || {
+        into_code((|| {
+            let handle = read_wallet(wallet)?;
+            let nonce = nonce.as_mut().context("Unexpected NULL nonce")?;
+            *nonce = handle.runtime.block_on(handle.wallet.get_nonce());
+            Ok(())
+        })())
+    })
+}
+
+// build and sign a transaction, params are in JSON using the same format as the `build_transaction` RPC method
+// returns the transaction in JSON with its hash and its hex format
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_build_transaction(wallet: *const XelisWallet, params: *const c_char) -> *mut c_char {
+    guard(null_mut(), || {
+        let result = (|| {
+            let handle = read_wallet(wallet)?;
+            let params: BuildTransactionParams = serde_json::from_str(read_str(params)?).context("Invalid params")?;
+            if params.sponsor.is_some() && params.broadcast {
+                bail!("A sponsored transaction cannot be broadcasted before being signed by its sponsor")
+            }
+
+            handle.runtime.block_on(async {
+                let nonce = handle.wallet.get_next_nonce().await;
+                let tx = {
+                    let storage = handle.wallet.get_storage().read().await;
+                    let sponsor = params.sponsor.map(|addr| addr.to_public_key());
+                    handle.wallet.create_transaction(&storage, params.tx_type, params.fee.unwrap_or(FeeBuilder::Multiplier(1f64)), sponsor, nonce, params.expiration)?
+                };
+
+                if params.broadcast {
+                    handle.wallet.submit_transaction(&tx).await.context("Couldn't broadcast transaction")?;
+                }
+                to_response(tx)
+            })
+        })();
+
+        into_ptr(result, into_c_string)
+    })
+}
+
+// sign a transaction built by another wallet to pay its fees
+// returns the transaction in JSON with its hash and its hex format
+#[no_mangle]
+pub unsafe extern "C" fn xelis_wallet_sponsor_transaction(wallet: *const XelisWallet, tx_as_hex: *const c_char, broadcast: bool) -> *mut c_char {
+    guard(null_mut(), || {
+        let result = (|| {
+            let handle = read_wallet(wallet)?;
+            let tx = Transaction::from_hex(read_str(tx_as_hex)?.to_owned()).context("Invalid transaction")?;
+
+            handle.runtime.block_on(async {
+                let tx = {
+                    let storage = handle.wallet.get_storage().read().await;
+                    handle.wallet.sponsor_transaction(&storage, tx)?
+                };
+
+                if broadcast {
+                    handle.wallet.submit_transaction(&tx).await.context("Couldn't broadcast transaction")?;
+                }
+                to_response(tx)
+            })
+        })();
+
+        into_ptr(result, into_c_string)
+    })
+}
+
+fn to_response(tx: Transaction) -> Result<String> {
+    let response = TransactionResponse {
+        tx_as_hex: Some(tx.to_hex()),
+        inner: DataHash {
+            hash: Cow::Owned(tx.hash()),
+            data: Cow::Owned(tx)
+        }
+    };
+    Ok(serde_json::to_string(&response)?)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _last_error() -> Option<String> {
+        LAST_ERROR.with(|error| error.borrow().as_ref().map(|message| message.to_string_lossy().into_owned()))
+    }
+
+    #[test]
+    fn test_guard_catches_panic() {
+        assert_eq!(guard(XELIS_OK, || XELIS_OK), XELIS_OK);
+
+        assert_eq!(guard(XELIS_ERROR, || -> i32 { panic!("invalid state") }), XELIS_ERROR);
+        assert_eq!(_last_error().as_deref(), Some("Unexpected panic: invalid state"));
+
+        let ptr = guard(null_mut(), || -> *mut c_char { panic!("{} is invalid", "state") });
+        assert!(ptr.is_null());
+        assert_eq!(_last_error().as_deref(), Some("Unexpected panic: state is invalid"));
+    }
+
+    #[test]
+    fn test_null_parameters() {
+        unsafe {
+            assert!(xelis_wallet_get_address(std::ptr::null()).is_null());
+            assert_eq!(xelis_wallet_get_nonce(std::ptr::null(), null_mut()), XELIS_ERROR);
+        }
+        assert_eq!(_last_error().as_deref(), Some("Unexpected NULL wallet"));
+    }
+}
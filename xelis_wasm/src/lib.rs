@@ -0,0 +1,2 @@
+// the bindings are defined in xelis_common, this crate only builds them as a WASM package
+pub use xelis_common::wasm::*;